@@ -1,8 +1,10 @@
+use super::{AwsPartition, CLOUD_PARTITION};
 use opentelemetry::{Array, KeyValue, StringValue, Value};
 use opentelemetry_sdk::resource::ResourceDetector;
 use opentelemetry_sdk::Resource;
 use opentelemetry_semantic_conventions as semconv;
 use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 // For a complete list of reserved environment variables in Lambda, see:
 // https://docs.aws.amazon.com/lambda/latest/dg/configuration-envvars.html
@@ -12,6 +14,13 @@ const AWS_LAMBDA_FUNCTION_VERSION_ENV_VAR: &str = "AWS_LAMBDA_FUNCTION_VERSION";
 const AWS_LAMBDA_LOG_STREAM_NAME_ENV_VAR: &str = "AWS_LAMBDA_LOG_STREAM_NAME";
 const AWS_LAMBDA_MEMORY_LIMIT_ENV_VAR: &str = "AWS_LAMBDA_FUNCTION_MEMORY_SIZE";
 const AWS_LAMBDA_LOG_GROUP_NAME_ENV_VAR: &str = "AWS_LAMBDA_LOG_GROUP_NAME";
+const AWS_LAMBDA_INITIALIZATION_TYPE_ENV_VAR: &str = "AWS_LAMBDA_INITIALIZATION_TYPE";
+
+/// Resource attribute holding how the Lambda execution environment was initialized, one of
+/// `on-demand`, `provisioned-concurrency` or `snap-start`.
+pub const AWS_LAMBDA_INITIALIZATION_TYPE: &str = "aws.lambda.initialization_type";
+
+const INITIALIZATION_TYPE_PROVISIONED_CONCURRENCY: &str = "provisioned-concurrency";
 
 /// Resource detector that collects resource information from AWS Lambda environment.
 pub struct LambdaResourceDetector;
@@ -36,9 +45,12 @@ impl ResourceDetector for LambdaResourceDetector {
         let instance = env::var(AWS_LAMBDA_LOG_STREAM_NAME_ENV_VAR).unwrap_or_default();
         let log_group_name = env::var(AWS_LAMBDA_LOG_GROUP_NAME_ENV_VAR).unwrap_or_default();
 
-        let attributes = [
+        let partition = AwsPartition::from_region(&aws_region);
+
+        let mut attributes = vec![
             KeyValue::new(semconv::resource::CLOUD_PROVIDER, "aws"),
             KeyValue::new(semconv::resource::CLOUD_REGION, aws_region),
+            KeyValue::new(CLOUD_PARTITION, partition.as_str()),
             KeyValue::new(semconv::resource::FAAS_INSTANCE, instance),
             KeyValue::new(semconv::resource::FAAS_NAME, lambda_name),
             KeyValue::new(semconv::resource::FAAS_VERSION, function_version),
@@ -48,6 +60,12 @@ impl ResourceDetector for LambdaResourceDetector {
                 Value::Array(Array::from(vec![StringValue::from(log_group_name)])),
             ),
         ];
+        if let Ok(initialization_type) = env::var(AWS_LAMBDA_INITIALIZATION_TYPE_ENV_VAR) {
+            attributes.push(KeyValue::new(
+                AWS_LAMBDA_INITIALIZATION_TYPE,
+                initialization_type,
+            ));
+        }
 
         Resource::builder_empty()
             .with_attributes(attributes)
@@ -55,6 +73,68 @@ impl ResourceDetector for LambdaResourceDetector {
     }
 }
 
+/// Tracks whether the next Lambda invocation is a cold start, for the `faas.coldstart` span
+/// attribute.
+///
+/// The first invocation of an on-demand execution environment is a cold start. Environments
+/// initialized by provisioned concurrency are ready before their first invocation, so it is not.
+/// Environments restored from a SnapStart snapshot skip the initialization too, but the first
+/// invocation after a restore waits for it and is reported as a cold start, call
+/// [`LambdaColdStart::restored`] from the restore hook.
+///
+/// ```
+/// use opentelemetry_aws::detector::LambdaColdStart;
+///
+/// static COLD_START: std::sync::OnceLock<LambdaColdStart> = std::sync::OnceLock::new();
+///
+/// fn handler() {
+///     let cold_start = COLD_START.get_or_init(LambdaColdStart::new).attribute();
+///     // add `cold_start` to the invocation span
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LambdaColdStart {
+    cold: AtomicBool,
+}
+
+impl LambdaColdStart {
+    /// Create a tracker for the current execution environment, reading its initialization type
+    /// from the `AWS_LAMBDA_INITIALIZATION_TYPE` environment variable.
+    pub fn new() -> Self {
+        let initialization_type =
+            env::var(AWS_LAMBDA_INITIALIZATION_TYPE_ENV_VAR).unwrap_or_default();
+        LambdaColdStart {
+            cold: AtomicBool::new(
+                initialization_type != INITIALIZATION_TYPE_PROVISIONED_CONCURRENCY,
+            ),
+        }
+    }
+
+    /// Mark the next invocation as a cold start, after the execution environment was restored
+    /// from a SnapStart snapshot.
+    pub fn restored(&self) {
+        self.cold.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the invocation starting is a cold start, only the first call after the
+    /// initialization or a restore returns `true`.
+    pub fn is_cold_start(&self) -> bool {
+        self.cold.swap(false, Ordering::Relaxed)
+    }
+
+    /// The `faas.coldstart` attribute of the invocation starting, see
+    /// [`LambdaColdStart::is_cold_start`].
+    pub fn attribute(&self) -> KeyValue {
+        KeyValue::new(semconv::attribute::FAAS_COLDSTART, self.is_cold_start())
+    }
+}
+
+impl Default for LambdaColdStart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -82,6 +162,7 @@ mod tests {
                     .with_attributes([
                         KeyValue::new(semconv::resource::CLOUD_PROVIDER, "aws"),
                         KeyValue::new(semconv::resource::CLOUD_REGION, "eu-west-3"),
+                        KeyValue::new(CLOUD_PARTITION, "aws"),
                         KeyValue::new(
                             semconv::resource::FAAS_INSTANCE,
                             "2023/01/01/[$LATEST]5d1edb9e525d486696cf01a3503487bc",
@@ -106,6 +187,90 @@ mod tests {
         );
     }
 
+    #[sealed_test]
+    fn test_aws_lambda_detector_partition() {
+        for (region, partition) in [
+            ("us-gov-west-1", "aws-us-gov"),
+            ("cn-north-1", "aws-cn"),
+            ("ap-southeast-2", "aws"),
+        ] {
+            temp_env::with_vars(
+                [
+                    (AWS_LAMBDA_FUNCTION_NAME_ENV_VAR, Some("my-lambda-function")),
+                    (AWS_REGION_ENV_VAR, Some(region)),
+                ],
+                || {
+                    let got = LambdaResourceDetector {}.detect();
+                    assert_eq!(
+                        got.get(&CLOUD_PARTITION.into()),
+                        Some(Value::from(partition))
+                    );
+                },
+            );
+        }
+    }
+
+    #[sealed_test]
+    fn test_aws_lambda_detector_initialization_type() {
+        temp_env::with_vars(
+            [
+                (AWS_LAMBDA_FUNCTION_NAME_ENV_VAR, Some("my-lambda-function")),
+                (AWS_LAMBDA_INITIALIZATION_TYPE_ENV_VAR, Some("snap-start")),
+            ],
+            || {
+                let got = LambdaResourceDetector {}.detect();
+                assert_eq!(
+                    got.get(&AWS_LAMBDA_INITIALIZATION_TYPE.into()),
+                    Some(Value::from("snap-start"))
+                );
+            },
+        );
+    }
+
+    #[sealed_test]
+    fn test_cold_start_on_demand() {
+        temp_env::with_var(
+            AWS_LAMBDA_INITIALIZATION_TYPE_ENV_VAR,
+            Some("on-demand"),
+            || {
+                let cold_start = LambdaColdStart::new();
+                assert_eq!(
+                    cold_start.attribute(),
+                    KeyValue::new(semconv::attribute::FAAS_COLDSTART, true)
+                );
+                assert!(!cold_start.is_cold_start());
+            },
+        );
+    }
+
+    #[sealed_test]
+    fn test_cold_start_provisioned_concurrency() {
+        temp_env::with_var(
+            AWS_LAMBDA_INITIALIZATION_TYPE_ENV_VAR,
+            Some("provisioned-concurrency"),
+            || {
+                let cold_start = LambdaColdStart::new();
+                assert!(!cold_start.is_cold_start());
+            },
+        );
+    }
+
+    #[sealed_test]
+    fn test_cold_start_snap_start_restore() {
+        temp_env::with_var(
+            AWS_LAMBDA_INITIALIZATION_TYPE_ENV_VAR,
+            Some("snap-start"),
+            || {
+                let cold_start = LambdaColdStart::new();
+                assert!(cold_start.is_cold_start());
+                assert!(!cold_start.is_cold_start());
+                cold_start.restored();
+                assert!(cold_start.is_cold_start());
+                assert!(!cold_start.is_cold_start());
+            },
+        );
+    }
+
     #[sealed_test]
     fn test_aws_lambda_detector_returns_empty_if_no_lambda_environment() {
         let detector = LambdaResourceDetector {};
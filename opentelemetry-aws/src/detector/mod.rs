@@ -1,4 +1,6 @@
 #[cfg(feature = "detector-aws-lambda")]
 mod lambda;
+mod partition;
 #[cfg(feature = "detector-aws-lambda")]
-pub use lambda::LambdaResourceDetector;
+pub use lambda::{LambdaColdStart, LambdaResourceDetector, AWS_LAMBDA_INITIALIZATION_TYPE};
+pub use partition::{AwsPartition, CLOUD_PARTITION};
@@ -0,0 +1,143 @@
+use std::fmt;
+
+/// Resource attribute holding the AWS partition of the region, one of `aws`, `aws-cn` or
+/// `aws-us-gov`.
+pub const CLOUD_PARTITION: &str = "cloud.partition";
+
+/// An AWS partition, the group of regions sharing the endpoint domain and ARN prefix.
+///
+/// The regions of China and GovCloud are in their own partitions, so their endpoints and ARNs
+/// differ from those of the standard regions:
+///
+/// ```
+/// use opentelemetry_aws::detector::AwsPartition;
+///
+/// let partition = AwsPartition::from_region("cn-north-1");
+/// assert_eq!(partition, AwsPartition::AwsCn);
+/// assert_eq!(
+///     partition.endpoint("xray", "cn-north-1"),
+///     "https://xray.cn-north-1.amazonaws.com.cn"
+/// );
+/// assert_eq!(
+///     partition.arn("lambda", "cn-north-1", "123456789012", "function:my-function"),
+///     "arn:aws-cn:lambda:cn-north-1:123456789012:function:my-function"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AwsPartition {
+    /// The standard regions.
+    #[default]
+    Aws,
+    /// The China regions, `cn-*`.
+    AwsCn,
+    /// The AWS GovCloud (US) regions, `us-gov-*`.
+    AwsUsGov,
+}
+
+impl AwsPartition {
+    /// The partition of `region`, the standard one for unknown regions.
+    pub fn from_region(region: &str) -> Self {
+        if region.starts_with("cn-") {
+            AwsPartition::AwsCn
+        } else if region.starts_with("us-gov-") {
+            AwsPartition::AwsUsGov
+        } else {
+            AwsPartition::Aws
+        }
+    }
+
+    /// The partition of `arn`, `None` when it isn't an ARN of a known partition.
+    pub fn from_arn(arn: &str) -> Option<Self> {
+        let mut parts = arn.splitn(3, ':');
+        if parts.next() != Some("arn") {
+            return None;
+        }
+        match parts.next()? {
+            "aws" => Some(AwsPartition::Aws),
+            "aws-cn" => Some(AwsPartition::AwsCn),
+            "aws-us-gov" => Some(AwsPartition::AwsUsGov),
+            _ => None,
+        }
+    }
+
+    /// The name of the partition, the prefix of its ARNs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AwsPartition::Aws => "aws",
+            AwsPartition::AwsCn => "aws-cn",
+            AwsPartition::AwsUsGov => "aws-us-gov",
+        }
+    }
+
+    /// The domain of the service endpoints of the partition.
+    pub fn dns_suffix(&self) -> &'static str {
+        match self {
+            AwsPartition::Aws | AwsPartition::AwsUsGov => "amazonaws.com",
+            AwsPartition::AwsCn => "amazonaws.com.cn",
+        }
+    }
+
+    /// The HTTPS endpoint of `service` in `region`.
+    pub fn endpoint(&self, service: &str, region: &str) -> String {
+        format!("https://{service}.{region}.{}", self.dns_suffix())
+    }
+
+    /// The ARN of `resource` of `service`, in `region` and the account `account_id`.
+    pub fn arn(&self, service: &str, region: &str, account_id: &str, resource: &str) -> String {
+        format!("arn:{self}:{service}:{region}:{account_id}:{resource}")
+    }
+}
+
+impl fmt::Display for AwsPartition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_region() {
+        assert_eq!(AwsPartition::from_region("eu-west-3"), AwsPartition::Aws);
+        assert_eq!(
+            AwsPartition::from_region("cn-northwest-1"),
+            AwsPartition::AwsCn
+        );
+        assert_eq!(
+            AwsPartition::from_region("us-gov-west-1"),
+            AwsPartition::AwsUsGov
+        );
+        assert_eq!(AwsPartition::from_region(""), AwsPartition::Aws);
+    }
+
+    #[test]
+    fn test_from_arn() {
+        assert_eq!(
+            AwsPartition::from_arn(
+                "arn:aws-us-gov:states:us-gov-east-1:123456789012:stateMachine:orders"
+            ),
+            Some(AwsPartition::AwsUsGov)
+        );
+        assert_eq!(
+            AwsPartition::from_arn("arn:aws:sqs:eu-west-3:123456789012:orders"),
+            Some(AwsPartition::Aws)
+        );
+        assert_eq!(AwsPartition::from_arn("arn:aws-iso:s3:::bucket"), None);
+        assert_eq!(AwsPartition::from_arn("aws:sqs"), None);
+    }
+
+    #[test]
+    fn test_endpoint_and_arn() {
+        let partition = AwsPartition::from_region("us-gov-west-1");
+        assert_eq!(
+            partition.endpoint("xray", "us-gov-west-1"),
+            "https://xray.us-gov-west-1.amazonaws.com"
+        );
+        let arn = partition.arn("sqs", "us-gov-west-1", "123456789012", "orders");
+        assert_eq!(arn, "arn:aws-us-gov:sqs:us-gov-west-1:123456789012:orders");
+        assert_eq!(AwsPartition::from_arn(&arn), Some(partition));
+    }
+}
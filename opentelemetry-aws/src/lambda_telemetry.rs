@@ -0,0 +1,413 @@
+//! # AWS Lambda Telemetry API
+//!
+//! Receive the telemetry of a Lambda function in an extension, through the [Telemetry API], and
+//! emit it as OpenTelemetry log records.
+//!
+//! [`TelemetryListener`] is the HTTP destination of a Telemetry API subscription. Every event it
+//! receives is converted with [`emit`]:
+//!
+//! * `function` and `extension` events become log records with the logged line as body, or the
+//!   fields of JSON formatted logs as a map body, with the severity of their `level` field.
+//! * `platform.*` events become log records with the event record as map body, correlated with
+//!   the X-Ray trace of the invocation when the record carries its `tracing` context.
+//!
+//! Every log record has the event type as `aws.lambda.telemetry.type` attribute, and the
+//! request id as `faas.invocation_id` attribute when it is known.
+//!
+//! ```no_run
+//! use opentelemetry::logs::LoggerProvider;
+//! use opentelemetry_aws::lambda_telemetry::TelemetryListener;
+//! use opentelemetry_sdk::logs::SdkLoggerProvider;
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let provider = SdkLoggerProvider::builder().build();
+//! let listener = TelemetryListener::bind(("0.0.0.0", 4243))?;
+//! // Send `listener.subscription()` to the Telemetry API:
+//! // PUT http://${AWS_LAMBDA_RUNTIME_API}/2022-07-01/telemetry
+//! let subscription = listener.subscription(&["platform", "function"])?;
+//! listener.serve(provider.logger("lambda-telemetry"));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [Telemetry API]: https://docs.aws.amazon.com/lambda/latest/dg/telemetry-api.html
+use crate::trace::xray_propagator::span_context_from_str;
+use opentelemetry::logs::{AnyValue, LogRecord, Logger, Severity};
+use opentelemetry::{otel_warn, Key};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Attribute holding the type of the telemetry event, e.g. `platform.report` or `function`.
+pub const AWS_LAMBDA_TELEMETRY_TYPE: &str = "aws.lambda.telemetry.type";
+
+const FAAS_INVOCATION_ID: &str = "faas.invocation_id";
+const SCHEMA_VERSION: &str = "2022-12-13";
+/// Host name of the extension in the Lambda sandbox.
+const SANDBOX_HOST: &str = "sandbox.localdomain";
+/// Telemetry batches are at most 1 MB, with some margin.
+const MAX_BODY_SIZE: usize = 4 * 1024 * 1024;
+
+/// An event of the Telemetry API.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TelemetryEvent {
+    /// Time of the event, as an RFC 3339 timestamp.
+    pub time: String,
+    /// Type of the event, e.g. `platform.start` or `function`.
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// Record of the event, a log line for `function` and `extension` events, an object
+    /// otherwise.
+    pub record: Value,
+}
+
+/// Emit `event` as a log record with `logger`.
+pub fn emit<L: Logger>(logger: &L, event: &TelemetryEvent) {
+    let mut log_record = logger.create_log_record();
+    log_record.set_observed_timestamp(SystemTime::now());
+    if let Some(timestamp) = parse_time(&event.time) {
+        log_record.set_timestamp(timestamp);
+    }
+    log_record.add_attribute(AWS_LAMBDA_TELEMETRY_TYPE, event.event_type.clone());
+
+    let record = match &event.record {
+        Value::Object(record) => record,
+        Value::Null => {
+            logger.emit(log_record);
+            return;
+        }
+        line => {
+            log_record.set_body(to_any_value(line).unwrap_or_else(|| AnyValue::from("")));
+            logger.emit(log_record);
+            return;
+        }
+    };
+
+    if let Some(request_id) = record
+        .get("requestId")
+        .and_then(Value::as_str)
+        .filter(|request_id| !request_id.is_empty())
+    {
+        log_record.add_attribute(FAAS_INVOCATION_ID, request_id.to_string());
+    }
+    if event.event_type.starts_with("platform.") {
+        log_record.set_severity_number(Severity::Info);
+        log_record.set_severity_text("INFO");
+        let span_context = record
+            .get("tracing")
+            .and_then(|tracing| tracing.get("value"))
+            .and_then(Value::as_str)
+            .and_then(span_context_from_str);
+        if let Some(span_context) = span_context {
+            log_record.set_trace_context(
+                span_context.trace_id(),
+                span_context.span_id(),
+                Some(span_context.trace_flags()),
+            );
+        }
+    } else if let Some((severity, severity_text)) = record
+        .get("level")
+        .and_then(Value::as_str)
+        .and_then(severity)
+    {
+        log_record.set_severity_number(severity);
+        log_record.set_severity_text(severity_text);
+    }
+    if let Some(body) = to_any_value(&event.record) {
+        log_record.set_body(body);
+    }
+    logger.emit(log_record);
+}
+
+/// HTTP listener receiving the events of a Telemetry API subscription.
+#[derive(Debug)]
+pub struct TelemetryListener {
+    listener: TcpListener,
+}
+
+impl TelemetryListener {
+    /// Listen on `addr`, usually `0.0.0.0` with a port of the extension's choice.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(TelemetryListener {
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    /// The address the listener is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// The body of the Telemetry API subscription request, subscribing to the event `types`
+    /// among `platform`, `function` and `extension`.
+    pub fn subscription(&self, types: &[&str]) -> io::Result<String> {
+        let port = self.local_addr()?.port();
+        Ok(serde_json::json!({
+            "schemaVersion": SCHEMA_VERSION,
+            "types": types,
+            "destination": {
+                "protocol": "HTTP",
+                "URI": format!("http://{SANDBOX_HOST}:{port}"),
+            },
+        })
+        .to_string())
+    }
+
+    /// Emit the events received by the listener with `logger`, on a background thread.
+    pub fn serve<L: Logger + Send + Sync + 'static>(self, logger: L) -> JoinHandle<()> {
+        let logger = Arc::new(logger);
+        thread::spawn(move || {
+            for stream in self.listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let logger = logger.clone();
+                thread::spawn(move || {
+                    if let Err(err) = handle_connection(stream, &*logger) {
+                        otel_warn!(
+                            name: "LambdaTelemetry.ConnectionFailed",
+                            error = err.to_string()
+                        );
+                    }
+                });
+            }
+        })
+    }
+}
+
+/// Handle the requests of a keep-alive connection until it is closed.
+fn handle_connection<L: Logger>(mut stream: TcpStream, logger: &L) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let mut content_length = 0;
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value
+                        .trim()
+                        .parse()
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                }
+            }
+        }
+        if content_length > MAX_BODY_SIZE {
+            stream.write_all(b"HTTP/1.1 413 Payload Too Large\r\nContent-Length: 0\r\n\r\n")?;
+            return Ok(());
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+
+        match serde_json::from_slice::<Vec<TelemetryEvent>>(&body) {
+            Ok(events) => events.iter().for_each(|event| emit(logger, event)),
+            Err(err) => otel_warn!(
+                name: "LambdaTelemetry.InvalidEvents",
+                error = err.to_string()
+            ),
+        }
+        stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")?;
+    }
+}
+
+fn severity(level: &str) -> Option<(Severity, &'static str)> {
+    Some(match level.to_ascii_uppercase().as_str() {
+        "TRACE" => (Severity::Trace, "TRACE"),
+        "DEBUG" => (Severity::Debug, "DEBUG"),
+        "INFO" => (Severity::Info, "INFO"),
+        "WARN" | "WARNING" => (Severity::Warn, "WARN"),
+        "ERROR" => (Severity::Error, "ERROR"),
+        "FATAL" => (Severity::Fatal, "FATAL"),
+        _ => return None,
+    })
+}
+
+/// Convert a JSON value, `None` for `null`.
+fn to_any_value(value: &Value) -> Option<AnyValue> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => AnyValue::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => AnyValue::Int(i),
+            None => AnyValue::Double(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => AnyValue::from(s.clone()),
+        Value::Array(values) => {
+            AnyValue::ListAny(Box::new(values.iter().filter_map(to_any_value).collect()))
+        }
+        Value::Object(fields) => AnyValue::Map(Box::new(
+            fields
+                .iter()
+                .filter_map(|(key, value)| Some((Key::new(key.clone()), to_any_value(value)?)))
+                .collect::<HashMap<_, _>>(),
+        )),
+    })
+}
+
+/// Parse an RFC 3339 UTC timestamp, e.g. `2022-10-12T00:03:50.123Z`.
+fn parse_time(time: &str) -> Option<SystemTime> {
+    let time = time.strip_suffix('Z')?;
+    let (date, time) = time.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut time = time.splitn(3, ':').map(str::parse::<u64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    let nanos = if fraction.is_empty() {
+        0
+    } else if !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    } else {
+        let digits = &fraction[..fraction.len().min(9)];
+        digits.parse::<u32>().ok()? * 10u32.pow(9 - digits.len() as u32)
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    // Days since the epoch of a proleptic Gregorian date.
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = u64::try_from(era * 146_097 + day_of_era - 719_468).ok()?;
+
+    let seconds = days * 86_400 + hour * 3_600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::new(seconds, nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::logs::LoggerProvider;
+    use opentelemetry::trace::TraceId;
+    use opentelemetry_sdk::logs::{InMemoryLogExporter, SdkLogRecord, SdkLoggerProvider};
+
+    fn emitted(events: &str) -> Vec<SdkLogRecord> {
+        let exporter = InMemoryLogExporter::default();
+        let provider = SdkLoggerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let logger = provider.logger("test");
+        for event in serde_json::from_str::<Vec<TelemetryEvent>>(events).unwrap() {
+            emit(&logger, &event);
+        }
+        exporter
+            .get_emitted_logs()
+            .unwrap()
+            .into_iter()
+            .map(|log| log.record)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(
+            parse_time("2022-10-12T00:03:50.000Z"),
+            Some(UNIX_EPOCH + Duration::from_secs(1_665_533_030))
+        );
+        assert_eq!(
+            parse_time("1970-01-01T00:00:01.5Z"),
+            Some(UNIX_EPOCH + Duration::from_millis(1_500))
+        );
+        assert_eq!(parse_time("2022-10-12T00:03:50+02:00"), None);
+        assert_eq!(parse_time("2022-13-12T00:03:50Z"), None);
+        assert_eq!(parse_time("2022-10-12T00:03:50.12345678\u{e9}9Z"), None);
+    }
+
+    #[test]
+    fn test_emit_platform_event() {
+        let logs = emitted(
+            r#"[{
+                "time": "2022-10-12T00:03:50.000Z",
+                "type": "platform.start",
+                "record": {
+                    "requestId": "6d68ca91-49c9-448d-89b8-7ca3e6dc66aa",
+                    "version": "$LATEST",
+                    "tracing": {
+                        "spanId": "54565fb41ac79632",
+                        "type": "X-Amzn-Trace-Id",
+                        "value": "Root=1-62e900b2-710d76f009d6e7785905449a;Parent=0efbd19962d95b05;Sampled=1"
+                    }
+                }
+            }]"#,
+        );
+        assert_eq!(logs.len(), 1);
+        let record = &logs[0];
+        assert_eq!(record.severity_number(), Some(Severity::Info));
+        assert_eq!(
+            record.trace_context().map(|cx| cx.trace_id),
+            Some(TraceId::from_hex("62e900b2710d76f009d6e7785905449a").unwrap())
+        );
+        assert!(record
+            .attributes_iter()
+            .any(|(key, value)| key.as_str() == FAAS_INVOCATION_ID
+                && *value == AnyValue::from("6d68ca91-49c9-448d-89b8-7ca3e6dc66aa")));
+        assert!(matches!(record.body(), Some(AnyValue::Map(_))));
+    }
+
+    #[test]
+    fn test_emit_function_events() {
+        let logs = emitted(
+            r#"[
+                {"time": "2022-10-12T00:03:50.000Z", "type": "function", "record": "hello"},
+                {"time": "2022-10-12T00:03:51.000Z", "type": "function",
+                 "record": {"level": "ERROR", "message": "failed", "requestId": "abc"}}
+            ]"#,
+        );
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].body(), Some(&AnyValue::from("hello")));
+        assert_eq!(logs[0].severity_number(), None);
+        assert_eq!(logs[1].severity_number(), Some(Severity::Error));
+        assert!(logs[1].trace_context().is_none());
+    }
+
+    #[test]
+    fn test_listener() {
+        let exporter = InMemoryLogExporter::default();
+        let provider = SdkLoggerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let listener = TelemetryListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert!(listener
+            .subscription(&["platform"])
+            .unwrap()
+            .contains(&format!("http://sandbox.localdomain:{}", addr.port())));
+        listener.serve(provider.logger("test"));
+
+        let body = r#"[{"time": "2022-10-12T00:03:50.000Z", "type": "function", "record": "hi"}]"#;
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: sandbox.localdomain\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut status = String::new();
+        BufReader::new(stream).read_line(&mut status).unwrap();
+        assert_eq!(status.trim_end(), "HTTP/1.1 200 OK");
+        assert_eq!(exporter.get_emitted_logs().unwrap().len(), 1);
+    }
+}
@@ -1,2 +1,4 @@
 pub mod detector;
+#[cfg(feature = "lambda-telemetry")]
+pub mod lambda_telemetry;
 pub mod trace;
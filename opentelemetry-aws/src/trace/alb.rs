@@ -0,0 +1,78 @@
+//! Correlation of Application Load Balancer access logs with the spans of the targets.
+//!
+//! The load balancer sends an `X-Amzn-Trace-Id` header to its targets, adding a `Root` field
+//! when the request has none and a `Self` field otherwise, and writes the header in the
+//! `trace_id` field of its access log entry. Spans of a target extracting the header with the
+//! [`XrayPropagator`](super::XrayPropagator) belong to the trace of its `Root` field, so
+//! [`parse_access_log_trace_id`] gives the trace id to join the access log entries on, and
+//! [`access_log_trace_id`] the exact field of the entry of a request, e.g. to log it.
+//!
+//! ```
+//! use opentelemetry_aws::trace::alb::{access_log_trace_id, parse_access_log_trace_id};
+//!
+//! let header = "Self=1-67891234-12456789abcdef012345678;Root=1-67891233-abcdef012345678912345678";
+//! assert_eq!(access_log_trace_id(header), format!("\"{header}\""));
+//!
+//! let trace_id = parse_access_log_trace_id(&access_log_trace_id(header)).unwrap();
+//! assert_eq!(trace_id.to_string(), "67891233abcdef012345678912345678");
+//! ```
+use crate::trace::xray_propagator::trace_id_from_xray;
+use opentelemetry::trace::TraceId;
+
+const ROOT_KEY: &str = "Root";
+
+/// The `trace_id` field of the access log entry of a request received with the
+/// `X-Amzn-Trace-Id` header `header`: the header in double quotes.
+pub fn access_log_trace_id(header: &str) -> String {
+    format!("\"{header}\"")
+}
+
+/// The trace id of the `Root` field of the `trace_id` field of an access log entry, quoted or
+/// not, or `None` for entries without trace header, logged as `-`.
+pub fn parse_access_log_trace_id(field: &str) -> Option<TraceId> {
+    let header = field.trim();
+    let header = header
+        .strip_prefix('"')
+        .and_then(|header| header.strip_suffix('"'))
+        .unwrap_or(header);
+    header
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case(ROOT_KEY))
+        .and_then(|(_, value)| trace_id_from_xray(value.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_access_log_trace_id() {
+        let root = TraceId::from_hex("5833726236d228ad5d99923122bbe354").unwrap();
+        for field in [
+            "\"Root=1-58337262-36d228ad5d99923122bbe354\"",
+            "Root=1-58337262-36d228ad5d99923122bbe354",
+            "\"Self=1-58337263-12456789abcdef0123456789;Root=1-58337262-36d228ad5d99923122bbe354\"",
+            "\"Root=1-58337262-36d228ad5d99923122bbe354;Parent=53995c3f42cd8ad8;Sampled=1\"",
+        ] {
+            assert_eq!(parse_access_log_trace_id(field), Some(root), "{field}");
+        }
+        for field in [
+            "-",
+            "\"\"",
+            "\"Self=1-58337263-12456789abcdef0123456789\"",
+            "\"Root=1-x\"",
+        ] {
+            assert_eq!(parse_access_log_trace_id(field), None, "{field}");
+        }
+    }
+
+    #[test]
+    fn test_access_log_trace_id() {
+        let header = "Root=1-58337262-36d228ad5d99923122bbe354";
+        assert_eq!(
+            access_log_trace_id(header),
+            "\"Root=1-58337262-36d228ad5d99923122bbe354\""
+        );
+    }
+}
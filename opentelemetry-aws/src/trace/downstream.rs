@@ -0,0 +1,107 @@
+//! Propagation of the X-Ray trace header to the services called by a Lambda function.
+//!
+//! AWS services continuing an X-Ray trace take the header value in different places: an HTTP
+//! header for API Gateway and function URL responses, the `AWSTraceHeader` message system
+//! attribute for SQS `SendMessage`, and the `traceHeader` parameter of Step Functions
+//! `StartExecution`. [`trace_header`] gives the value for the span of a context, and the
+//! `http` feature adds [`inject_response`] for `lambda_http::Response`, which is an
+//! `http::Response`.
+//!
+//! ```ignore
+//! use aws_sdk_sqs::types::{MessageSystemAttributeNameForSends, MessageSystemAttributeValue};
+//! use opentelemetry::Context;
+//! use opentelemetry_aws::trace::downstream::{trace_header, AWS_TRACE_HEADER_ATTRIBUTE};
+//!
+//! let cx = Context::current();
+//! let mut send_message = sqs.send_message().queue_url(queue_url).message_body(body);
+//! if let Some(header) = trace_header(&cx) {
+//!     send_message = send_message.message_system_attributes(
+//!         MessageSystemAttributeNameForSends::from(AWS_TRACE_HEADER_ATTRIBUTE),
+//!         MessageSystemAttributeValue::builder()
+//!             .data_type("String")
+//!             .string_value(header)
+//!             .build()?,
+//!     );
+//! }
+//! send_message.send().await?;
+//!
+//! sfn.start_execution()
+//!     .state_machine_arn(state_machine_arn)
+//!     .input(input)
+//!     .set_trace_header(trace_header(&cx))
+//!     .send()
+//!     .await?;
+//! ```
+use crate::trace::xray_propagator::span_context_to_string;
+#[cfg(feature = "http")]
+use crate::trace::xray_propagator::AWS_XRAY_TRACE_HEADER;
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::Context;
+
+/// Name of the SQS message system attribute carrying the X-Ray trace header.
+pub const AWS_TRACE_HEADER_ATTRIBUTE: &str = "AWSTraceHeader";
+
+/// The X-Ray trace header value of the span of `cx`, if its span context is valid.
+///
+/// Unlike injecting with a [`XrayPropagator`], this does not depend on the global propagator,
+/// as AWS services only understand the X-Ray format.
+///
+/// [`XrayPropagator`]: crate::trace::XrayPropagator
+pub fn trace_header(cx: &Context) -> Option<String> {
+    span_context_to_string(cx.span().span_context())
+}
+
+/// Set the `x-amzn-trace-id` header of `headers` to the X-Ray trace header of the span of `cx`.
+#[cfg(feature = "http")]
+pub fn inject_headers(cx: &Context, headers: &mut http::HeaderMap) {
+    let Some(value) = trace_header(cx) else {
+        return;
+    };
+    if let Ok(value) = http::HeaderValue::from_str(&value) {
+        headers.insert(http::HeaderName::from_static(AWS_XRAY_TRACE_HEADER), value);
+    }
+}
+
+/// Set the `x-amzn-trace-id` header of `response`, e.g. a `lambda_http::Response`, to the
+/// X-Ray trace header of the span of `cx`.
+#[cfg(feature = "http")]
+pub fn inject_response<B>(cx: &Context, response: &mut http::Response<B>) {
+    inject_headers(cx, response.headers_mut());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{SpanContext, SpanId, TraceFlags, TraceId, TraceState};
+
+    fn context() -> Context {
+        Context::new().with_remote_span_context(SpanContext::new(
+            TraceId::from_hex("58406520a006649127e371903a2de979").unwrap(),
+            SpanId::from_hex("4c721bf33e3caf8f").unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        ))
+    }
+
+    const HEADER: &str =
+        "Root=1-58406520-a006649127e371903a2de979;Parent=4c721bf33e3caf8f;Sampled=1";
+
+    #[test]
+    fn test_trace_header() {
+        assert_eq!(trace_header(&context()).as_deref(), Some(HEADER));
+        assert_eq!(trace_header(&Context::new()), None);
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_inject_response() {
+        let mut response = http::Response::new(());
+        inject_response(&context(), &mut response);
+        assert_eq!(response.headers()["x-amzn-trace-id"], HEADER);
+
+        let mut response = http::Response::new(());
+        inject_response(&Context::new(), &mut response);
+        assert!(response.headers().is_empty());
+    }
+}
@@ -1,10 +1,20 @@
 #[cfg(feature = "trace")]
+pub mod alb;
+#[cfg(feature = "trace")]
+pub mod downstream;
+#[cfg(feature = "trace")]
 pub mod id_generator;
 #[cfg(feature = "trace")]
+pub mod redaction;
+#[cfg(feature = "trace")]
+pub mod spans;
+#[cfg(feature = "step-functions")]
+pub mod step_functions;
+#[cfg(feature = "trace")]
 pub mod xray_propagator;
 
 #[cfg(feature = "trace")]
-pub use xray_propagator::XrayPropagator;
+pub use xray_propagator::{XrayPropagator, XraySampled, XrayTraceHeader};
 
 #[cfg(feature = "trace")]
 pub use id_generator::XrayIdGenerator;
@@ -0,0 +1,345 @@
+//! Builders of the spans of SQS and Lambda calls, with the attributes of the messaging and FaaS
+//! semantic conventions.
+//!
+//! Each builder sets the span kind, name and required attributes of the conventions from the
+//! identifiers of the call, so the spans of all services are named and attributed alike.
+//! [`SqsReceiveSpanBuilder`] links the receive span to the traces of the senders of the
+//! messages, from their `AWSTraceHeader` message system attribute, rather than parenting it to
+//! any of them. `build` returns a [`SpanBuilder`] to add other attributes to and start.
+//!
+//! ```
+//! use opentelemetry::global;
+//! use opentelemetry_aws::trace::spans::{LambdaInvokeSpanBuilder, SqsReceiveSpanBuilder};
+//!
+//! let tracer = global::tracer("orders");
+//! let queue_url = "https://sqs.eu-west-3.amazonaws.com/123456789012/orders";
+//!
+//! // With the id and `AWSTraceHeader` system attribute of each received message
+//! let span = SqsReceiveSpanBuilder::new(queue_url)
+//!     .with_message(
+//!         "5fea7756-0ea4-451a-a703-a558b933e274",
+//!         Some("Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1"),
+//!     )
+//!     .build()
+//!     .start(&tracer);
+//!
+//! let function = "arn:aws:lambda:eu-west-3:123456789012:function:billing";
+//! let span = LambdaInvokeSpanBuilder::new(function)
+//!     .build()
+//!     .start(&tracer);
+//! ```
+use crate::trace::xray_propagator::span_context_from_str;
+use opentelemetry::trace::{Link, SpanBuilder, SpanContext, SpanKind};
+use opentelemetry::KeyValue;
+
+const MESSAGING_SYSTEM: &str = "aws_sqs";
+const RPC_SYSTEM: &str = "aws-api";
+
+/// Builder of the producer span sending messages to an SQS queue.
+#[derive(Clone, Debug)]
+pub struct SqsSendSpanBuilder {
+    queue_url: String,
+    batch_size: usize,
+}
+
+impl SqsSendSpanBuilder {
+    /// Send a message to the queue of `queue_url`.
+    pub fn new(queue_url: impl Into<String>) -> Self {
+        SqsSendSpanBuilder {
+            queue_url: queue_url.into(),
+            batch_size: 1,
+        }
+    }
+
+    /// Send `batch_size` messages with `SendMessageBatch`.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// The builder of the span.
+    pub fn build(self) -> SpanBuilder {
+        let method = if self.batch_size > 1 {
+            "SendMessageBatch"
+        } else {
+            "SendMessage"
+        };
+        let mut attributes = sqs_attributes(&self.queue_url, "send", method);
+        if self.batch_size > 1 {
+            attributes.push(KeyValue::new(
+                "messaging.batch.message_count",
+                self.batch_size as i64,
+            ));
+        }
+        SpanBuilder::from_name(format!("send {}", queue_name(&self.queue_url)))
+            .with_kind(SpanKind::Producer)
+            .with_attributes(attributes)
+    }
+}
+
+/// Builder of the consumer span receiving messages from an SQS queue, linked to the spans which
+/// sent them.
+#[derive(Clone, Debug)]
+pub struct SqsReceiveSpanBuilder {
+    queue_url: String,
+    message_ids: Vec<String>,
+    links: Vec<SpanContext>,
+}
+
+impl SqsReceiveSpanBuilder {
+    /// Receive messages from the queue of `queue_url`.
+    pub fn new(queue_url: impl Into<String>) -> Self {
+        SqsReceiveSpanBuilder {
+            queue_url: queue_url.into(),
+            message_ids: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    /// Add a received message, with its `AWSTraceHeader` message system attribute if any.
+    ///
+    /// The span is linked to the span context of the trace header, once for all the messages
+    /// sent by the same span.
+    pub fn with_message(
+        mut self,
+        message_id: impl Into<String>,
+        trace_header: Option<&str>,
+    ) -> Self {
+        self.message_ids.push(message_id.into());
+        let span_context = trace_header
+            .and_then(span_context_from_str)
+            .filter(SpanContext::is_valid);
+        if let Some(span_context) = span_context {
+            if !self.links.contains(&span_context) {
+                self.links.push(span_context);
+            }
+        }
+        self
+    }
+
+    /// The builder of the span.
+    pub fn build(self) -> SpanBuilder {
+        let mut attributes = sqs_attributes(&self.queue_url, "receive", "ReceiveMessage");
+        attributes.push(KeyValue::new(
+            "messaging.batch.message_count",
+            self.message_ids.len() as i64,
+        ));
+        if let [message_id] = self.message_ids.as_slice() {
+            attributes.push(KeyValue::new("messaging.message.id", message_id.clone()));
+        }
+        SpanBuilder::from_name(format!("receive {}", queue_name(&self.queue_url)))
+            .with_kind(SpanKind::Consumer)
+            .with_attributes(attributes)
+            .with_links(self.links.into_iter().map(Link::with_context).collect())
+    }
+}
+
+/// Builder of the client span invoking a Lambda function.
+#[derive(Clone, Debug)]
+pub struct LambdaInvokeSpanBuilder {
+    function: String,
+    region: Option<String>,
+}
+
+impl LambdaInvokeSpanBuilder {
+    /// Invoke `function`, its name, with an optional `:qualifier`, or its full or partial ARN.
+    ///
+    /// The region of the function is taken from its ARN.
+    pub fn new(function: impl Into<String>) -> Self {
+        let function = function.into();
+        let region = function
+            .strip_prefix("arn:")
+            .and_then(|arn| arn.split(':').nth(2))
+            .filter(|region| !region.is_empty())
+            .map(str::to_string);
+        LambdaInvokeSpanBuilder { function, region }
+    }
+
+    /// Invoke the function in `region`, when it isn't given by an ARN.
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    /// The builder of the span.
+    pub fn build(self) -> SpanBuilder {
+        let mut attributes = vec![
+            KeyValue::new(
+                "faas.invoked_name",
+                function_name(&self.function).to_string(),
+            ),
+            KeyValue::new("faas.invoked_provider", "aws"),
+            KeyValue::new("rpc.system", RPC_SYSTEM),
+            KeyValue::new("rpc.service", "Lambda"),
+            KeyValue::new("rpc.method", "Invoke"),
+        ];
+        attributes.extend(
+            self.region
+                .map(|region| KeyValue::new("faas.invoked_region", region)),
+        );
+        if self.function.starts_with("arn:") {
+            attributes.push(KeyValue::new("aws.lambda.invoked_arn", self.function));
+        }
+        SpanBuilder::from_name("Lambda.Invoke")
+            .with_kind(SpanKind::Client)
+            .with_attributes(attributes)
+    }
+}
+
+fn sqs_attributes(queue_url: &str, operation: &'static str, method: &'static str) -> Vec<KeyValue> {
+    let mut attributes = vec![
+        KeyValue::new("messaging.system", MESSAGING_SYSTEM),
+        KeyValue::new(
+            "messaging.destination.name",
+            queue_name(queue_url).to_string(),
+        ),
+        KeyValue::new("messaging.operation.name", operation),
+        KeyValue::new("messaging.operation.type", operation),
+        KeyValue::new("aws.sqs.queue.url", queue_url.to_string()),
+        KeyValue::new("rpc.system", RPC_SYSTEM),
+        KeyValue::new("rpc.service", "SQS"),
+        KeyValue::new("rpc.method", method),
+    ];
+    attributes.extend(
+        queue_region(queue_url).map(|region| KeyValue::new("cloud.region", region.to_string())),
+    );
+    attributes
+}
+
+/// The name of the queue of `queue_url`, its last path segment.
+fn queue_name(queue_url: &str) -> &str {
+    let queue_url = queue_url.trim_end_matches('/');
+    queue_url.rsplit('/').next().unwrap_or(queue_url)
+}
+
+/// The region of `queue_url`, from its `sqs.{region}.` or legacy `{region}.queue.` host.
+fn queue_region(queue_url: &str) -> Option<&str> {
+    let host = queue_url.split_once("://")?.1.split('/').next()?;
+    let mut labels = host.split('.');
+    match (labels.next()?, labels.next()?) {
+        ("sqs", region) => Some(region),
+        (region, "queue") => Some(region),
+        _ => None,
+    }
+}
+
+/// The name of `function`, without the ARN prefix and qualifier.
+fn function_name(function: &str) -> &str {
+    let mut parts = function.split(':');
+    match parts.position(|part| part == "function") {
+        Some(_) => parts.next().unwrap_or(function),
+        None => function.split(':').next().unwrap_or(function),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::SpanId;
+
+    const QUEUE_URL: &str = "https://sqs.eu-west-3.amazonaws.com/123456789012/orders";
+
+    fn header(parent: &str) -> String {
+        format!("Root=1-5759e988-bd862e3fe1be46a994272793;Parent={parent};Sampled=1")
+    }
+
+    #[test]
+    fn test_sqs_send() {
+        let builder = SqsSendSpanBuilder::new(QUEUE_URL).build();
+        assert_eq!(builder.name, "send orders");
+        assert_eq!(builder.span_kind, Some(SpanKind::Producer));
+        let attributes = builder.attributes.unwrap();
+        assert!(attributes.contains(&KeyValue::new("messaging.system", "aws_sqs")));
+        assert!(attributes.contains(&KeyValue::new("messaging.destination.name", "orders")));
+        assert!(attributes.contains(&KeyValue::new("cloud.region", "eu-west-3")));
+        assert!(attributes.contains(&KeyValue::new("rpc.method", "SendMessage")));
+
+        let builder = SqsSendSpanBuilder::new(QUEUE_URL)
+            .with_batch_size(3)
+            .build();
+        let attributes = builder.attributes.unwrap();
+        assert!(attributes.contains(&KeyValue::new("rpc.method", "SendMessageBatch")));
+        assert!(attributes.contains(&KeyValue::new("messaging.batch.message_count", 3)));
+    }
+
+    #[test]
+    fn test_sqs_receive() {
+        let builder = SqsReceiveSpanBuilder::new(QUEUE_URL)
+            .with_message("1", Some(&header("53995c3f42cd8ad8")))
+            .with_message("2", Some(&header("53995c3f42cd8ad8")))
+            .with_message("3", Some(&header("4c721bf33e3caf8f")))
+            .with_message("4", Some("Root=1-x"))
+            .with_message("5", None)
+            .build();
+        assert_eq!(builder.name, "receive orders");
+        assert_eq!(builder.span_kind, Some(SpanKind::Consumer));
+        let attributes = builder.attributes.unwrap();
+        assert!(attributes.contains(&KeyValue::new("messaging.operation.type", "receive")));
+        assert!(attributes.contains(&KeyValue::new("messaging.batch.message_count", 5)));
+        assert!(!attributes
+            .iter()
+            .any(|kv| kv.key.as_str() == "messaging.message.id"));
+        let links: Vec<SpanId> = builder
+            .links
+            .unwrap()
+            .iter()
+            .map(|link| link.span_context.span_id())
+            .collect();
+        assert_eq!(
+            links,
+            [
+                SpanId::from_hex("53995c3f42cd8ad8").unwrap(),
+                SpanId::from_hex("4c721bf33e3caf8f").unwrap()
+            ]
+        );
+
+        let builder = SqsReceiveSpanBuilder::new(
+            "https://eu-west-3.queue.amazonaws.com/123456789012/orders/",
+        )
+        .with_message("1", None)
+        .build();
+        assert_eq!(builder.name, "receive orders");
+        let attributes = builder.attributes.unwrap();
+        assert!(attributes.contains(&KeyValue::new("messaging.message.id", "1")));
+        assert!(attributes.contains(&KeyValue::new("cloud.region", "eu-west-3")));
+        assert!(builder.links.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_lambda_invoke() {
+        let builder = LambdaInvokeSpanBuilder::new(
+            "arn:aws:lambda:eu-west-3:123456789012:function:billing:prod",
+        )
+        .build();
+        assert_eq!(builder.name, "Lambda.Invoke");
+        assert_eq!(builder.span_kind, Some(SpanKind::Client));
+        let attributes = builder.attributes.unwrap();
+        assert!(attributes.contains(&KeyValue::new("faas.invoked_name", "billing")));
+        assert!(attributes.contains(&KeyValue::new("faas.invoked_region", "eu-west-3")));
+        assert!(attributes.contains(&KeyValue::new(
+            "aws.lambda.invoked_arn",
+            "arn:aws:lambda:eu-west-3:123456789012:function:billing:prod"
+        )));
+
+        for function in ["billing", "billing:prod", "123456789012:function:billing"] {
+            let attributes = LambdaInvokeSpanBuilder::new(function)
+                .build()
+                .attributes
+                .unwrap();
+            assert!(
+                attributes.contains(&KeyValue::new("faas.invoked_name", "billing")),
+                "{function}"
+            );
+            assert!(!attributes
+                .iter()
+                .any(|kv| kv.key.as_str() == "faas.invoked_region"));
+        }
+
+        let attributes = LambdaInvokeSpanBuilder::new("billing")
+            .with_region("us-gov-west-1")
+            .build()
+            .attributes
+            .unwrap();
+        assert!(attributes.contains(&KeyValue::new("faas.invoked_region", "us-gov-west-1")));
+    }
+}
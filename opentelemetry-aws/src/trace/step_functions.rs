@@ -0,0 +1,137 @@
+//! Propagation of the X-Ray trace header through the JSON payloads of Step Functions states.
+//!
+//! Step Functions passes the trace header of an execution to the Lambda tasks it invokes, but
+//! not to activity workers, `.waitForTaskToken` callbacks or the items of `Map` and the
+//! branches of `Parallel` states processed outside of the execution trace. [`inject`] writes the
+//! trace header to the `AWSTraceHeader` field of a task input or output, [`extract`] reads it
+//! back in the worker, and [`links`] links a span to the traces of all the items or branch
+//! outputs it receives.
+//!
+//! ```ignore
+//! use opentelemetry::trace::{TraceContextExt, Tracer};
+//! use opentelemetry::Context;
+//! use opentelemetry_aws::trace::step_functions;
+//!
+//! // Activity worker
+//! let task = sfn.get_activity_task().activity_arn(arn).send().await?;
+//! let input: serde_json::Value = serde_json::from_str(task.input().unwrap_or("{}"))?;
+//! let cx = step_functions::extract_with_context(&Context::current(), &input);
+//! let span = tracer.start_with_context("process", &cx);
+//! let mut output = process(input)?;
+//! step_functions::inject(&cx.with_span(span), &mut output);
+//!
+//! // State after a Map state, receiving the outputs of its iterations
+//! let span = tracer
+//!     .span_builder("aggregate")
+//!     .with_links(step_functions::links(outputs.as_array().into_iter().flatten()))
+//!     .start(&tracer);
+//! ```
+use crate::trace::xray_propagator::{span_context_from_str, span_context_to_string};
+use opentelemetry::trace::{Link, SpanContext, TraceContextExt};
+use opentelemetry::Context;
+use serde_json::Value;
+
+/// Field of the state payloads holding the X-Ray trace header.
+pub const TRACE_HEADER_FIELD: &str = "AWSTraceHeader";
+
+/// Set the `AWSTraceHeader` field of `payload` to the X-Ray trace header of the span of `cx`.
+///
+/// Payloads which are not JSON objects, and contexts without a valid span context, are left
+/// unchanged.
+pub fn inject(cx: &Context, payload: &mut Value) {
+    let Some(object) = payload.as_object_mut() else {
+        return;
+    };
+    if let Some(header) = span_context_to_string(cx.span().span_context()) {
+        object.insert(TRACE_HEADER_FIELD.to_string(), Value::String(header));
+    }
+}
+
+/// The span context of the `AWSTraceHeader` field of `payload`, if it holds a valid one.
+pub fn extract(payload: &Value) -> Option<SpanContext> {
+    payload
+        .get(TRACE_HEADER_FIELD)
+        .and_then(Value::as_str)
+        .and_then(span_context_from_str)
+        .filter(SpanContext::is_valid)
+}
+
+/// `cx` with the span context of the `AWSTraceHeader` field of `payload` as remote parent, or
+/// `cx` itself if the field holds no valid span context.
+pub fn extract_with_context(cx: &Context, payload: &Value) -> Context {
+    match extract(payload) {
+        Some(span_context) => cx.with_remote_span_context(span_context),
+        None => cx.clone(),
+    }
+}
+
+/// Links to the distinct span contexts of the `AWSTraceHeader` field of `payloads`, e.g. the
+/// items of a `Map` state batch or the outputs of the branches of a `Parallel` state.
+pub fn links<'a>(payloads: impl IntoIterator<Item = &'a Value>) -> Vec<Link> {
+    let mut span_contexts: Vec<SpanContext> = Vec::new();
+    for span_context in payloads.into_iter().filter_map(extract) {
+        if !span_contexts.contains(&span_context) {
+            span_contexts.push(span_context);
+        }
+    }
+    span_contexts.into_iter().map(Link::with_context).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{SpanId, TraceFlags, TraceId, TraceState};
+    use serde_json::json;
+
+    const HEADER: &str =
+        "Root=1-58406520-a006649127e371903a2de979;Parent=4c721bf33e3caf8f;Sampled=1";
+
+    fn span_context(span_id: &str) -> SpanContext {
+        SpanContext::new(
+            TraceId::from_hex("58406520a006649127e371903a2de979").unwrap(),
+            SpanId::from_hex(span_id).unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        )
+    }
+
+    #[test]
+    fn test_inject_extract() {
+        let cx = Context::new().with_remote_span_context(span_context("4c721bf33e3caf8f"));
+        let mut payload = json!({ "order": 42 });
+        inject(&cx, &mut payload);
+        assert_eq!(payload, json!({ "order": 42, "AWSTraceHeader": HEADER }));
+        assert_eq!(extract(&payload), Some(span_context("4c721bf33e3caf8f")));
+
+        let mut payload = json!([1, 2]);
+        inject(&cx, &mut payload);
+        assert_eq!(payload, json!([1, 2]));
+        assert_eq!(extract(&payload), None);
+
+        let cx = extract_with_context(&Context::new(), &json!({ "AWSTraceHeader": "invalid" }));
+        assert!(!cx.span().span_context().is_valid());
+    }
+
+    #[test]
+    fn test_links() {
+        let other = "Root=1-58406520-a006649127e371903a2de979;Parent=53995c3f42cd8ad8;Sampled=1";
+        let items = json!([
+            { "AWSTraceHeader": HEADER },
+            { "AWSTraceHeader": other },
+            { "AWSTraceHeader": HEADER },
+            { "id": 3 },
+        ]);
+        let links = links(items.as_array().unwrap());
+        assert_eq!(
+            links
+                .iter()
+                .map(|link| link.span_context.clone())
+                .collect::<Vec<_>>(),
+            [
+                span_context("4c721bf33e3caf8f"),
+                span_context("53995c3f42cd8ad8")
+            ]
+        );
+    }
+}
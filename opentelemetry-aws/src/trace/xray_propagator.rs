@@ -44,11 +44,15 @@ use opentelemetry::{
     trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
     Context,
 };
+use opentelemetry_sdk::trace::IdGenerator;
 use std::borrow::Cow;
 use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
 use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-const AWS_XRAY_TRACE_HEADER: &str = "x-amzn-trace-id";
+pub(crate) const AWS_XRAY_TRACE_HEADER: &str = "x-amzn-trace-id";
 const AWS_XRAY_VERSION_KEY: &str = "1";
 const HEADER_PARENT_KEY: &str = "Parent";
 const HEADER_ROOT_KEY: &str = "Root";
@@ -60,6 +64,11 @@ const REQUESTED_SAMPLE_DECISION: &str = "?";
 
 const TRACE_FLAG_DEFERRED: TraceFlags = TraceFlags::new(0x02);
 
+/// X-Ray rejects trace ids whose epoch is older than 30 days.
+const XRAY_MAX_TRACE_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Tolerated clock skew for trace ids whose epoch is in the future.
+const XRAY_MAX_CLOCK_SKEW: Duration = Duration::from_secs(5 * 60);
+
 // TODO Replace this with LazyLock when MSRV is 1.80+
 static TRACE_CONTEXT_HEADER_FIELDS: OnceLock<[String; 1]> = OnceLock::new();
 
@@ -83,71 +92,69 @@ fn trace_context_header_fields() -> &'static [String; 1] {
 /// global::set_text_map_propagator(XrayPropagator::default());
 /// ```
 ///
+/// ## Root generation
+///
+/// By default, extracting from a request without a valid header returns the context unchanged,
+/// so the first span of the trace gets its trace id from the installed id generator. Like the
+/// X-Ray SDKs, the propagator can instead start a new X-Ray trace root, so the first service of a
+/// chain produces X-Ray exportable trace ids even when [`XrayIdGenerator`] isn't installed:
+///
+/// ```
+/// use opentelemetry_aws::trace::XrayPropagator;
+///
+/// let propagator = XrayPropagator::new().with_root_generation(true);
+/// ```
+///
+/// The generated root only carries a new trace id, like a header with only a `Root` field: it has
+/// no parent span id and its sampled flag is unset, leaving the sampling decision of the trace to
+/// the sampler of the tracer provider.
+///
 /// [otel-spec]: https://github.com/open-telemetry/opentelemetry-specification/blob/master/specification/trace/api.md#SpanContext
 /// [xray-trace-id]: https://docs.aws.amazon.com/xray/latest/devguide/xray-api-sendingdata.html#xray-api-traceids
 /// [xray-header]: https://docs.aws.amazon.com/xray/latest/devguide/xray-concepts.html#xray-concepts-tracingheader
+/// [`XrayIdGenerator`]: crate::trace::XrayIdGenerator
 #[derive(Clone, Debug, Default)]
 pub struct XrayPropagator {
-    _private: (),
+    generate_root: bool,
 }
 
 /// Extract `SpanContext` from AWS X-Ray format string
 ///
 /// Extract OpenTelemetry [SpanContext][otel-spec] from [X-Ray Trace format][xray-trace-id] string.
 ///
+/// Field keys are matched case-insensitively and whitespace around keys and values is ignored,
+/// as some AWS services forward headers such as `root=...; sampled=1`.
+///
+/// The epoch of the trace id is not validated, so traces started by services generating their
+/// own ids, such as CloudFront, are continued. See [`is_xray_export_compatible`].
+///
 /// [otel-spec]: https://github.com/open-telemetry/opentelemetry-specification/blob/master/specification/trace/api.md#SpanContext
 /// [xray-trace-id]: https://docs.aws.amazon.com/xray/latest/devguide/xray-api-sendingdata.html#xray-api-traceids
 pub fn span_context_from_str(value: &str) -> Option<SpanContext> {
-    let parts: Vec<(&str, &str)> = value
-        .split_terminator(';')
-        .filter_map(from_key_value_pair)
-        .collect();
-
-    let mut trace_id = TraceId::INVALID;
-    let mut parent_segment_id = SpanId::INVALID;
-    let mut sampling_decision = TRACE_FLAG_DEFERRED;
-    let mut kv_vec = Vec::with_capacity(parts.len());
-
-    for (key, value) in parts {
-        match key {
-            HEADER_ROOT_KEY => match TraceId::try_from(XrayTraceId(Cow::from(value))) {
-                Err(_) => return None,
-                Ok(parsed) => trace_id = parsed,
-            },
-            HEADER_PARENT_KEY => {
-                parent_segment_id = SpanId::from_hex(value).unwrap_or(SpanId::INVALID)
-            }
-            HEADER_SAMPLED_KEY => {
-                sampling_decision = match value {
-                    NOT_SAMPLED => TraceFlags::default(),
-                    SAMPLED => TraceFlags::SAMPLED,
-                    REQUESTED_SAMPLE_DECISION => TRACE_FLAG_DEFERRED,
-                    _ => TRACE_FLAG_DEFERRED,
-                }
-            }
-            _ => kv_vec.push((key.to_ascii_lowercase(), value.to_string())),
-        }
-    }
+    value.parse::<XrayTraceHeader>().ok()?.to_span_context()
+}
 
-    match TraceState::from_key_value(kv_vec) {
-        Ok(trace_state) => {
-            if trace_id == TraceId::INVALID {
-                return None;
-            }
+/// Whether X-Ray accepts segments with this trace id.
+///
+/// X-Ray rejects trace ids whose first 8 hexadecimal digits are not the epoch of a time within
+/// the last 30 days, which may be the case of ids extracted from headers of other services, e.g.
+/// CloudFront viewer requests. Exporters can use this to regenerate such ids and link the
+/// original trace instead of having the segments dropped.
+pub fn is_xray_export_compatible(trace_id: TraceId) -> bool {
+    is_xray_export_compatible_at(trace_id, opentelemetry::time::now())
+}
 
-            Some(SpanContext::new(
-                trace_id,
-                parent_segment_id,
-                sampling_decision,
-                true,
-                trace_state,
-            ))
-        }
-        Err(trace_state_err) => {
-            otel_error!(name: "SpanContextFromStr", error = format!("{:?}", trace_state_err));
-            None //todo: assign an error type instead of using None
-        }
+fn is_xray_export_compatible_at(trace_id: TraceId, now: SystemTime) -> bool {
+    if trace_id == TraceId::INVALID {
+        return false;
     }
+    let bytes = trace_id.to_bytes();
+    let epoch = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let Ok(now) = now.duration_since(UNIX_EPOCH) else {
+        return false;
+    };
+    let epoch = Duration::from_secs(epoch.into());
+    now.saturating_sub(XRAY_MAX_TRACE_AGE) <= epoch && epoch <= now + XRAY_MAX_CLOCK_SKEW
 }
 
 /// Generate AWS X-Ray format string from `SpanContext`
@@ -157,55 +164,261 @@ pub fn span_context_from_str(value: &str) -> Option<SpanContext> {
 /// [xray-trace-id]: https://docs.aws.amazon.com/xray/latest/devguide/xray-api-sendingdata.html#xray-api-traceids
 /// [otel-spec]: https://github.com/open-telemetry/opentelemetry-specification/blob/master/specification/trace/api.md#SpanContext
 pub fn span_context_to_string(span_context: &SpanContext) -> Option<String> {
-    if !span_context.is_valid() {
-        return None;
+    XrayTraceHeader::from_span_context(span_context).map(|header| header.to_string())
+}
+
+/// The fields of an `x-amzn-trace-id` header.
+///
+/// Proxies and middlewares can parse a header, change some of its fields, e.g. set the `Parent`
+/// to the id of their own segment, and format it back, without going through a span context:
+///
+/// ```
+/// use opentelemetry::trace::SpanId;
+/// use opentelemetry_aws::trace::XrayTraceHeader;
+///
+/// let mut header: XrayTraceHeader =
+///     "Root=1-58406520-a006649127e371903a2de979;Parent=4c721bf33e3caf8f;Sampled=1;Lineage=a87bd80c:1"
+///         .parse()
+///         .unwrap();
+/// header.parent = Some(SpanId::from_hex("53995c3f42cd8ad8").unwrap());
+/// assert_eq!(
+///     header.to_string(),
+///     "Root=1-58406520-a006649127e371903a2de979;Parent=53995c3f42cd8ad8;Sampled=1;Lineage=a87bd80c:1"
+/// );
+/// ```
+///
+/// Headers are parsed as leniently as the [`XrayPropagator`] extracts them: keys are matched
+/// case-insensitively, whitespace around keys and values is ignored, and invalid `Parent` and
+/// `Sampled` fields are dropped. Only a missing or invalid `Root` fails parsing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XrayTraceHeader {
+    /// The trace id of the `Root` field.
+    pub root: TraceId,
+    /// The segment id of the `Parent` field.
+    pub parent: Option<SpanId>,
+    /// The `Sampled` field.
+    pub sampled: Option<XraySampled>,
+    /// The other fields, e.g. `Self` or `Lineage`, in order, with their keys as written.
+    pub extra_fields: Vec<(String, String)>,
+}
+
+/// The value of the `Sampled` field of an `x-amzn-trace-id` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XraySampled {
+    /// `Sampled=1`
+    Sampled,
+    /// `Sampled=0`
+    NotSampled,
+    /// `Sampled=?`, the sampling decision is left to the receiver.
+    Requested,
+}
+
+impl XraySampled {
+    fn as_str(self) -> &'static str {
+        match self {
+            XraySampled::Sampled => SAMPLED,
+            XraySampled::NotSampled => NOT_SAMPLED,
+            XraySampled::Requested => REQUESTED_SAMPLE_DECISION,
+        }
     }
+}
 
-    let xray_trace_id = XrayTraceId::from(span_context.trace_id());
+impl XrayTraceHeader {
+    /// A header of the trace `root`, without other fields.
+    pub fn new(root: TraceId) -> Self {
+        XrayTraceHeader {
+            root,
+            parent: None,
+            sampled: None,
+            extra_fields: Vec::new(),
+        }
+    }
+
+    /// Set the `Parent` field.
+    pub fn with_parent(mut self, parent: SpanId) -> Self {
+        self.parent = Some(parent);
+        self
+    }
 
-    let sampling_decision =
-        if span_context.trace_flags() & TRACE_FLAG_DEFERRED == TRACE_FLAG_DEFERRED {
-            REQUESTED_SAMPLE_DECISION
+    /// Set the `Sampled` field.
+    pub fn with_sampled(mut self, sampled: XraySampled) -> Self {
+        self.sampled = Some(sampled);
+        self
+    }
+
+    /// Append the field `key`, written after the `Root`, `Parent` and `Sampled` fields.
+    pub fn with_extra_field(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_fields.push((key.into(), value.into()));
+        self
+    }
+
+    /// The header of `span_context`, with its trace state as extra fields, or `None` if the
+    /// span context is invalid.
+    pub fn from_span_context(span_context: &SpanContext) -> Option<Self> {
+        if !span_context.is_valid() {
+            return None;
+        }
+
+        let sampled = if span_context.trace_flags() & TRACE_FLAG_DEFERRED == TRACE_FLAG_DEFERRED {
+            XraySampled::Requested
         } else if span_context.is_sampled() {
-            SAMPLED
+            XraySampled::Sampled
         } else {
-            NOT_SAMPLED
+            XraySampled::NotSampled
         };
+        let extra_fields = span_context
+            .trace_state()
+            .header_delimited("=", ";")
+            .split_terminator(';')
+            .filter_map(from_key_value_pair)
+            .map(|(key, value)| (title_case(key), value.to_string()))
+            .collect();
+
+        Some(XrayTraceHeader {
+            root: span_context.trace_id(),
+            parent: Some(span_context.span_id()),
+            sampled: Some(sampled),
+            extra_fields,
+        })
+    }
 
-    let trace_state_header = span_context
-        .trace_state()
-        .header_delimited("=", ";")
-        .split_terminator(';')
-        .map(title_case)
-        .collect::<Vec<_>>()
-        .join(";");
-    let trace_state_prefix = if trace_state_header.is_empty() {
-        ""
-    } else {
-        ";"
-    };
+    /// The remote span context of the header, with the extra fields as trace state, or `None`
+    /// if they are not valid trace state entries.
+    pub fn to_span_context(&self) -> Option<SpanContext> {
+        let trace_flags = match self.sampled {
+            Some(XraySampled::Sampled) => TraceFlags::SAMPLED,
+            Some(XraySampled::NotSampled) => TraceFlags::default(),
+            Some(XraySampled::Requested) | None => TRACE_FLAG_DEFERRED,
+        };
+        let entries = self
+            .extra_fields
+            .iter()
+            .map(|(key, value)| (key.to_ascii_lowercase(), value.clone()));
+
+        match TraceState::from_key_value(entries) {
+            Ok(trace_state) => Some(SpanContext::new(
+                self.root,
+                self.parent.unwrap_or(SpanId::INVALID),
+                trace_flags,
+                true,
+                trace_state,
+            )),
+            Err(trace_state_err) => {
+                otel_error!(name: "SpanContextFromStr", error = format!("{:?}", trace_state_err));
+                None //todo: assign an error type instead of using None
+            }
+        }
+    }
+}
+
+impl FromStr for XrayTraceHeader {
+    type Err = ParseXrayTraceHeaderError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut root = None;
+        let mut parent = None;
+        let mut sampled = None;
+        let mut extra_fields = Vec::new();
 
-    Some(format!(
-        "{}={};{}={:016x};{}={}{}{}",
-        HEADER_ROOT_KEY,
-        xray_trace_id.0,
-        HEADER_PARENT_KEY,
-        span_context.span_id(),
-        HEADER_SAMPLED_KEY,
-        sampling_decision,
-        trace_state_prefix,
-        trace_state_header
-    ))
+        for (key, value) in value.split_terminator(';').filter_map(from_key_value_pair) {
+            match key {
+                _ if key.eq_ignore_ascii_case(HEADER_ROOT_KEY) => {
+                    root = Some(trace_id_from_xray(value).ok_or(ParseXrayTraceHeaderError(()))?)
+                }
+                _ if key.eq_ignore_ascii_case(HEADER_PARENT_KEY) => {
+                    parent = SpanId::from_hex(value)
+                        .ok()
+                        .filter(|id| *id != SpanId::INVALID)
+                }
+                _ if key.eq_ignore_ascii_case(HEADER_SAMPLED_KEY) => {
+                    sampled = match value {
+                        NOT_SAMPLED => Some(XraySampled::NotSampled),
+                        SAMPLED => Some(XraySampled::Sampled),
+                        REQUESTED_SAMPLE_DECISION => Some(XraySampled::Requested),
+                        _ => None,
+                    }
+                }
+                _ => extra_fields.push((key.to_string(), value.to_string())),
+            }
+        }
+
+        Ok(XrayTraceHeader {
+            root: root.ok_or(ParseXrayTraceHeaderError(()))?,
+            parent,
+            sampled,
+            extra_fields,
+        })
+    }
 }
 
+impl fmt::Display for XrayTraceHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let root = XrayTraceId::from(self.root);
+        write!(f, "{HEADER_ROOT_KEY}={}", root.0)?;
+        if let Some(parent) = self.parent {
+            write!(f, ";{HEADER_PARENT_KEY}={parent:016x}")?;
+        }
+        if let Some(sampled) = self.sampled {
+            write!(f, ";{HEADER_SAMPLED_KEY}={}", sampled.as_str())?;
+        }
+        for (key, value) in &self.extra_fields {
+            write!(f, ";{key}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Error parsing an [`XrayTraceHeader`] without a valid `Root` field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseXrayTraceHeaderError(());
+
+impl fmt::Display for ParseXrayTraceHeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("missing or invalid Root field in X-Ray trace header")
+    }
+}
+
+impl std::error::Error for ParseXrayTraceHeaderError {}
+
 impl XrayPropagator {
     /// Creates a new `XrayTraceContextPropagator`.
     pub fn new() -> Self {
         XrayPropagator::default()
     }
 
+    /// Start a new X-Ray trace root when no valid header is found, instead of returning the
+    /// context unchanged.
+    pub fn with_root_generation(mut self, generate_root: bool) -> Self {
+        self.generate_root = generate_root;
+        self
+    }
+
+    /// A new X-Ray trace root, without a parent span and with a deferred sampling decision.
+    fn new_root(&self) -> SpanContext {
+        SpanContext::new(
+            crate::trace::XrayIdGenerator::default().new_trace_id(),
+            SpanId::INVALID,
+            TRACE_FLAG_DEFERRED,
+            true,
+            TraceState::default(),
+        )
+    }
+
+    /// Extracts the span context from the first valid header value.
+    ///
+    /// Load balancers may append their own `x-amzn-trace-id` header, so repeated headers, and
+    /// values joined by a comma, are tried in order.
     fn extract_span_context(&self, extractor: &dyn Extractor) -> Option<SpanContext> {
-        span_context_from_str(extractor.get(AWS_XRAY_TRACE_HEADER)?.trim())
+        let values = extractor
+            .get_all(AWS_XRAY_TRACE_HEADER)
+            .unwrap_or_else(|| extractor.get(AWS_XRAY_TRACE_HEADER).into_iter().collect());
+
+        values
+            .into_iter()
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .find_map(span_context_from_str)
     }
 }
 
@@ -219,9 +432,11 @@ impl TextMapPropagator for XrayPropagator {
     }
 
     fn extract_with_context(&self, cx: &Context, extractor: &dyn Extractor) -> Context {
-        self.extract_span_context(extractor)
-            .map(|sc| cx.with_remote_span_context(sc))
-            .unwrap_or_else(|| cx.clone())
+        match self.extract_span_context(extractor) {
+            Some(sc) => cx.with_remote_span_context(sc),
+            None if self.generate_root => cx.with_remote_span_context(self.new_root()),
+            None => cx.clone(),
+        }
     }
 
     fn fields(&self) -> FieldIter<'_> {
@@ -277,12 +492,17 @@ impl From<TraceId> for XrayTraceId<'static> {
     }
 }
 
+/// The trace id of an X-Ray formatted trace id, e.g. `1-58406520-a006649127e371903a2de979`.
+pub(crate) fn trace_id_from_xray(value: &str) -> Option<TraceId> {
+    TraceId::try_from(XrayTraceId(Cow::from(value))).ok()
+}
+
 fn from_key_value_pair(pair: &str) -> Option<(&str, &str)> {
     let mut key_value_pair: Option<(&str, &str)> = None;
 
     if let Some(index) = pair.find('=') {
         let (key, value) = pair.split_at(index);
-        key_value_pair = Some((key, value.trim_start_matches('=')));
+        key_value_pair = Some((key.trim(), value.trim_start_matches('=').trim()));
     }
     key_value_pair
 }
@@ -355,6 +575,91 @@ mod tests {
         }
     }
 
+    #[rustfmt::skip]
+    fn extract_lenient_test_data() -> Vec<(Vec<&'static str>, SpanContext)> {
+        let expected = SpanContext::new(TraceId::from_hex("58406520a006649127e371903a2de979").unwrap(), SpanId::from_hex("4c721bf33e3caf8f").unwrap(), TraceFlags::SAMPLED, true, TraceState::default());
+        vec![
+            (vec!["root=1-58406520-a006649127e371903a2de979;parent=4c721bf33e3caf8f;sampled=1"], expected.clone()),
+            (vec!["ROOT=1-58406520-a006649127e371903a2de979;PARENT=4c721bf33e3caf8f;SAMPLED=1"], expected.clone()),
+            (vec![" Root = 1-58406520-a006649127e371903a2de979 ; Parent= 4c721bf33e3caf8f ;Sampled =1 "], expected.clone()),
+            (vec!["Root=1-bogus-bad", "Root=1-58406520-a006649127e371903a2de979;Parent=4c721bf33e3caf8f;Sampled=1"], expected.clone()),
+            (vec!["Root=1-58406520-a006649127e371903a2de979;Parent=4c721bf33e3caf8f;Sampled=1", "Root=1-5759e988-bd862e3fe1be46a994272793;Sampled=0"], expected.clone()),
+            (vec!["Self=1-67891234-12456789abcdef012345678;Root=1-58406520-a006649127e371903a2de979;Parent=4c721bf33e3caf8f;Sampled=1"], SpanContext::new(TraceId::from_hex("58406520a006649127e371903a2de979").unwrap(), SpanId::from_hex("4c721bf33e3caf8f").unwrap(), TraceFlags::SAMPLED, true, TraceState::from_str("self=1-67891234-12456789abcdef012345678").unwrap())),
+            (vec!["Sampled=1, Root=1-58406520-a006649127e371903a2de979;Parent=4c721bf33e3caf8f;Sampled=1"], expected.clone()),
+            (vec!["Root=1-bogus-bad", ""], SpanContext::empty_context()),
+        ]
+    }
+
+    /// Extractor returning every value of a repeated header, like `HeaderExtractor`.
+    struct MultiValueExtractor(Vec<&'static str>);
+
+    impl Extractor for MultiValueExtractor {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.get_all(key)?.first().copied()
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            vec![AWS_XRAY_TRACE_HEADER]
+        }
+
+        fn get_all(&self, key: &str) -> Option<Vec<&str>> {
+            (key == AWS_XRAY_TRACE_HEADER && !self.0.is_empty()).then(|| self.0.clone())
+        }
+    }
+
+    #[test]
+    fn test_extract_lenient() {
+        let propagator = XrayPropagator::default();
+        for (headers, expected) in extract_lenient_test_data() {
+            let context = propagator.extract(&MultiValueExtractor(headers.clone()));
+            assert_eq!(context.span().span_context(), &expected, "{headers:?}");
+        }
+    }
+
+    #[rustfmt::skip]
+    fn extract_viewer_test_data() -> Vec<(&'static str, SpanContext)> {
+        vec![
+            // CloudFront viewer request ids, with an epoch X-Ray does not accept.
+            ("Root=1-00000000-4c3d0bb8c9a5ec4e8e9a36f2", SpanContext::new(TraceId::from_hex("000000004c3d0bb8c9a5ec4e8e9a36f2").unwrap(), SpanId::INVALID, TRACE_FLAG_DEFERRED, true, TraceState::default())),
+            ("Root=1-ffffffff-4c3d0bb8c9a5ec4e8e9a36f2;Sampled=1", SpanContext::new(TraceId::from_hex("ffffffff4c3d0bb8c9a5ec4e8e9a36f2").unwrap(), SpanId::INVALID, TraceFlags::SAMPLED, true, TraceState::default())),
+            // WAF forwards the viewer header, with the id of its own segment.
+            ("Root=1-5759e988-bd862e3fe1be46a994272793;Self=1-67891233-abcdef012345678912345678;Sampled=1", SpanContext::new(TraceId::from_hex("5759e988bd862e3fe1be46a994272793").unwrap(), SpanId::INVALID, TraceFlags::SAMPLED, true, TraceState::from_str("self=1-67891233-abcdef012345678912345678").unwrap())),
+        ]
+    }
+
+    #[test]
+    fn test_extract_viewer() {
+        let propagator = XrayPropagator::default();
+        for (header, expected) in extract_viewer_test_data() {
+            let map: HashMap<String, String> =
+                HashMap::from([(AWS_XRAY_TRACE_HEADER.to_string(), header.to_string())]);
+            let context = propagator.extract(&map);
+            assert_eq!(context.span().span_context(), &expected, "{header}");
+        }
+    }
+
+    #[test]
+    fn test_is_xray_export_compatible() {
+        // 2016-12-01T18:00:00Z
+        let now = UNIX_EPOCH + Duration::from_secs(0x58406520);
+        let compatible =
+            |hex: &str| is_xray_export_compatible_at(TraceId::from_hex(hex).unwrap(), now);
+
+        assert!(compatible("58406520a006649127e371903a2de979"));
+        // 29 days old, and 1 minute in the future
+        assert!(compatible("581a29a0a006649127e371903a2de979"));
+        assert!(compatible("5840655ca006649127e371903a2de979"));
+        // 31 days old, and 1 hour in the future
+        assert!(!compatible("581786a0a006649127e371903a2de979"));
+        assert!(!compatible("58407330a006649127e371903a2de979"));
+        assert!(!compatible("000000004c3d0bb8c9a5ec4e8e9a36f2"));
+        assert!(!is_xray_export_compatible_at(TraceId::INVALID, now));
+
+        assert!(is_xray_export_compatible(
+            crate::trace::XrayIdGenerator::default().new_trace_id()
+        ));
+    }
+
     #[test]
     fn test_extract_empty() {
         let map: HashMap<String, String> = HashMap::new();
@@ -363,6 +668,91 @@ mod tests {
         assert_eq!(context.span().span_context(), &SpanContext::empty_context())
     }
 
+    #[test]
+    fn test_extract_root_generation() {
+        let propagator = XrayPropagator::new().with_root_generation(true);
+        for header in [None, Some("Root=1-bogus-bad")] {
+            let map: HashMap<String, String> = header
+                .map(|header| (AWS_XRAY_TRACE_HEADER.to_string(), header.to_string()))
+                .into_iter()
+                .collect();
+            let context = propagator.extract(&map);
+            let span_context = context.span().span_context().clone();
+
+            assert_ne!(span_context.trace_id(), TraceId::INVALID, "{header:?}");
+            assert_eq!(span_context.span_id(), SpanId::INVALID);
+            assert!(span_context.is_remote());
+            assert!(!span_context.is_sampled());
+            assert!(is_xray_export_compatible(span_context.trace_id()));
+        }
+
+        // Valid headers are extracted as is.
+        let map: HashMap<String, String> = HashMap::from([(
+            AWS_XRAY_TRACE_HEADER.to_string(),
+            "Root=1-58406520-a006649127e371903a2de979;Parent=4c721bf33e3caf8f;Sampled=0"
+                .to_string(),
+        )]);
+        let context = propagator.extract(&map);
+        assert_eq!(
+            context.span().span_context().trace_id(),
+            TraceId::from_hex("58406520a006649127e371903a2de979").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_header_round_trip() {
+        for value in [
+            "Root=1-58406520-a006649127e371903a2de979",
+            "Root=1-58406520-a006649127e371903a2de979;Parent=4c721bf33e3caf8f;Sampled=?",
+            "Root=1-58406520-a006649127e371903a2de979;Sampled=0;Self=1-58406520-bf42676c05e20ba4a90e448e;Lineage=a87bd80c:1",
+        ] {
+            let header = XrayTraceHeader::from_str(value).unwrap();
+            assert_eq!(header.to_string(), value);
+        }
+
+        let header = XrayTraceHeader::from_str(
+            " root = 1-58406520-a006649127e371903a2de979 ;parent=garbage;sampled=2;self=x",
+        )
+        .unwrap();
+        assert_eq!(
+            header,
+            XrayTraceHeader::new(TraceId::from_hex("58406520a006649127e371903a2de979").unwrap())
+                .with_extra_field("self", "x")
+        );
+
+        for value in ["", "Parent=4c721bf33e3caf8f;Sampled=1", "Root=1-bogus-bad"] {
+            assert!(XrayTraceHeader::from_str(value).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn test_header_builder() {
+        let header =
+            XrayTraceHeader::new(TraceId::from_hex("58406520a006649127e371903a2de979").unwrap())
+                .with_parent(SpanId::from_hex("4c721bf33e3caf8f").unwrap())
+                .with_sampled(XraySampled::Sampled)
+                .with_extra_field("Self", "1-58406520-bf42676c05e20ba4a90e448e");
+        assert_eq!(
+            header.to_string(),
+            "Root=1-58406520-a006649127e371903a2de979;Parent=4c721bf33e3caf8f;Sampled=1;Self=1-58406520-bf42676c05e20ba4a90e448e"
+        );
+
+        let span_context = header.to_span_context().unwrap();
+        assert!(span_context.is_sampled());
+        assert_eq!(
+            span_context.trace_state().get("self"),
+            Some("1-58406520-bf42676c05e20ba4a90e448e")
+        );
+        assert_eq!(
+            XrayTraceHeader::from_span_context(&span_context),
+            Some(header)
+        );
+        assert_eq!(
+            XrayTraceHeader::from_span_context(&SpanContext::empty_context()),
+            None
+        );
+    }
+
     #[test]
     fn test_inject() {
         let propagator = XrayPropagator::default();
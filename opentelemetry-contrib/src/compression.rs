@@ -0,0 +1,320 @@
+//! # HTTP body compression
+//!
+//! Shared compression of request bodies for HTTP based exporters.
+//!
+//! [`Compression`] selects the algorithm, `gzip` needs the `compression_gzip` feature and `zstd`
+//! the `compression_zstd` feature. [`Compressor`] applies it to request bodies, leaving bodies
+//! smaller than a threshold uncompressed as compressing them costs more than it saves, and
+//! downgrades to a compression the server accepts when it answers with
+//! `415 Unsupported Media Type`.
+//!
+//! ```
+//! use opentelemetry_contrib::compression::{Compression, Compressor};
+//!
+//! let compressor = Compressor::new(Compression::None).with_min_size(512);
+//! let request = http::Request::post("http://localhost:8126")
+//!     .body(vec![0u8; 1024])
+//!     .unwrap();
+//! let request = compressor.compress_request(request).unwrap();
+//! assert!(request.headers().get(http::header::CONTENT_ENCODING).is_none());
+//! ```
+use std::fmt::{self, Display};
+use std::io;
+use std::str::FromStr;
+
+/// Bodies smaller than this many bytes are not compressed by default.
+pub const DEFAULT_MIN_SIZE: usize = 1024;
+
+/// Compression algorithm of request bodies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// No compression.
+    #[default]
+    None,
+    /// Gzip compression, requires the `compression_gzip` feature.
+    Gzip,
+    /// Zstandard compression, requires the `compression_zstd` feature.
+    Zstd,
+}
+
+impl Compression {
+    /// The `Content-Encoding` of bodies compressed with this algorithm.
+    pub const fn content_encoding(self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gzip"),
+            Compression::Zstd => Some("zstd"),
+        }
+    }
+
+    /// Whether the feature needed by this algorithm is enabled.
+    pub const fn is_supported(self) -> bool {
+        match self {
+            Compression::None => true,
+            Compression::Gzip => cfg!(feature = "compression_gzip"),
+            Compression::Zstd => cfg!(feature = "compression_zstd"),
+        }
+    }
+
+    /// Whether an `Accept-Encoding` header value accepts this algorithm.
+    ///
+    /// Encodings with a `q=0` weight are refused, `*` accepts any encoding not listed.
+    pub fn is_accepted_by(self, accept_encoding: &str) -> bool {
+        let Some(content_encoding) = self.content_encoding() else {
+            return true;
+        };
+        let mut wildcard = false;
+        for item in accept_encoding.split(',') {
+            let mut parts = item.split(';').map(str::trim);
+            let coding = parts.next().unwrap_or_default();
+            let refused = parts.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            if coding.eq_ignore_ascii_case(content_encoding) {
+                return !refused;
+            }
+            if coding == "*" {
+                wildcard = !refused;
+            }
+        }
+        wildcard
+    }
+
+    /// Compress `data`.
+    pub fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            #[cfg(feature = "compression_gzip")]
+            Compression::Gzip => {
+                use flate2::write::GzEncoder;
+                use std::io::Write;
+
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "compression_zstd")]
+            Compression::Zstd => zstd::bulk::compress(data, zstd::DEFAULT_COMPRESSION_LEVEL),
+            #[allow(unreachable_patterns)]
+            unsupported => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{unsupported} compression requires the compression_{unsupported} feature"),
+            )),
+        }
+    }
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        })
+    }
+}
+
+impl FromStr for Compression {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "identity" | "" => Ok(Compression::None),
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown compression: {other}"),
+            )),
+        }
+    }
+}
+
+/// Compresses request bodies with a [`Compression`], bypassing small bodies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Compressor {
+    compression: Compression,
+    min_size: usize,
+}
+
+impl Default for Compressor {
+    fn default() -> Self {
+        Self::new(Compression::None)
+    }
+}
+
+impl Compressor {
+    /// Create a compressor using `compression` for bodies of at least [`DEFAULT_MIN_SIZE`] bytes.
+    pub fn new(compression: Compression) -> Self {
+        Compressor {
+            compression,
+            min_size: DEFAULT_MIN_SIZE,
+        }
+    }
+
+    /// Only compress bodies of at least `min_size` bytes.
+    pub fn with_min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// The configured algorithm.
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Compress `body`, returning it with its `Content-Encoding`, or unchanged with `None` if it
+    /// is smaller than the threshold.
+    pub fn compress(&self, body: Vec<u8>) -> io::Result<(Vec<u8>, Option<&'static str>)> {
+        match self.compression.content_encoding() {
+            Some(content_encoding) if body.len() >= self.min_size => {
+                Ok((self.compression.compress(&body)?, Some(content_encoding)))
+            }
+            _ => Ok((body, None)),
+        }
+    }
+
+    /// Compress the body of `request` and set its `Content-Encoding` header.
+    pub fn compress_request(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> io::Result<http::Request<Vec<u8>>> {
+        let (mut parts, body) = request.into_parts();
+        let (body, content_encoding) = self.compress(body)?;
+        if let Some(content_encoding) = content_encoding {
+            parts.headers.insert(
+                http::header::CONTENT_ENCODING,
+                http::HeaderValue::from_static(content_encoding),
+            );
+            if parts.headers.contains_key(http::header::CONTENT_LENGTH) {
+                parts
+                    .headers
+                    .insert(http::header::CONTENT_LENGTH, body.len().into());
+            }
+        }
+        Ok(http::Request::from_parts(parts, body))
+    }
+
+    /// The compressor to use after the server answered with the `accept_encoding` value of its
+    /// `Accept-Encoding` header, typically along with a `415 Unsupported Media Type` status.
+    ///
+    /// Keeps the configured algorithm if the server accepts it, otherwise falls back to another
+    /// supported algorithm the server accepts, preferring `zstd` over `gzip`, or to no
+    /// compression.
+    pub fn negotiate(&self, accept_encoding: Option<&str>) -> Compressor {
+        let compression = accept_encoding
+            .and_then(|accept_encoding| {
+                [self.compression, Compression::Zstd, Compression::Gzip]
+                    .into_iter()
+                    .find(|compression| {
+                        compression.is_supported() && compression.is_accepted_by(accept_encoding)
+                    })
+            })
+            .unwrap_or(Compression::None);
+        Compressor {
+            compression,
+            min_size: self.min_size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("gzip".parse::<Compression>().unwrap(), Compression::Gzip);
+        assert_eq!(" ZSTD ".parse::<Compression>().unwrap(), Compression::Zstd);
+        assert_eq!("none".parse::<Compression>().unwrap(), Compression::None);
+        assert!("brotli".parse::<Compression>().is_err());
+    }
+
+    #[test]
+    fn test_is_accepted_by() {
+        assert!(Compression::Gzip.is_accepted_by("gzip, deflate"));
+        assert!(Compression::Gzip.is_accepted_by("deflate, GZIP;q=0.5"));
+        assert!(!Compression::Gzip.is_accepted_by("gzip;q=0, zstd"));
+        assert!(Compression::Zstd.is_accepted_by("*"));
+        assert!(!Compression::Zstd.is_accepted_by("*, zstd;q=0"));
+        assert!(!Compression::Zstd.is_accepted_by("gzip"));
+        assert!(Compression::None.is_accepted_by(""));
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let compressor = Compressor::new(Compression::Zstd).with_min_size(10);
+        assert_eq!(compressor.negotiate(Some("zstd")), compressor);
+        let downgraded = compressor.negotiate(Some("gzip"));
+        let expected = if Compression::Gzip.is_supported() {
+            Compression::Gzip
+        } else {
+            Compression::None
+        };
+        assert_eq!(downgraded.compression(), expected);
+        assert_eq!(downgraded.min_size, 10);
+        assert_eq!(
+            compressor.negotiate(Some("br")).compression(),
+            Compression::None
+        );
+        assert_eq!(compressor.negotiate(None).compression(), Compression::None);
+    }
+
+    #[test]
+    fn test_compress_bypass() {
+        let compressor = Compressor::new(Compression::Gzip).with_min_size(16);
+        let (body, content_encoding) = compressor.compress(b"small".to_vec()).unwrap();
+        assert_eq!(body, b"small");
+        assert_eq!(content_encoding, None);
+    }
+
+    #[cfg(feature = "compression_gzip")]
+    #[test]
+    fn test_compress_request_gzip() {
+        use std::io::Read;
+
+        let body = b"a".repeat(4096);
+        let request = http::Request::post("http://localhost")
+            .header(http::header::CONTENT_LENGTH, body.len())
+            .body(body.clone())
+            .unwrap();
+        let request = Compressor::new(Compression::Gzip)
+            .compress_request(request)
+            .unwrap();
+
+        assert_eq!(request.headers()[http::header::CONTENT_ENCODING], "gzip");
+        assert_eq!(
+            request.headers()[http::header::CONTENT_LENGTH],
+            request.body().len().to_string().as_str()
+        );
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(request.body().as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, body);
+    }
+
+    #[cfg(feature = "compression_zstd")]
+    #[test]
+    fn test_compress_zstd() {
+        let body = b"a".repeat(4096);
+        let (compressed, content_encoding) = Compressor::new(Compression::Zstd)
+            .compress(body.clone())
+            .unwrap();
+        assert_eq!(content_encoding, Some("zstd"));
+        assert_eq!(
+            zstd::bulk::decompress(&compressed, body.len()).unwrap(),
+            body
+        );
+    }
+
+    #[cfg(not(feature = "compression_zstd"))]
+    #[test]
+    fn test_compress_unsupported() {
+        assert!(!Compression::Zstd.is_supported());
+        assert!(Compression::Zstd.compress(b"data").is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! # Exporter self-observability
+//!
+//! Standard metrics exporters record about themselves, so telemetry loss can be monitored
+//! the same way whatever the exporter:
+//!
+//! * `otel.exporter.exported`: items successfully exported.
+//! * `otel.exporter.failed`: items the exporter failed to export, by `error.type`.
+//! * `otel.exporter.dropped`: items dropped before an export was attempted, by `error.type`,
+//!   e.g. because a queue was full.
+//! * `otel.exporter.duration`: duration of the export operations, in seconds.
+//! * `otel.exporter.queue.utilization`: fraction of the exporter queue in use, for exporters
+//!   with an internal queue.
+//! * `otel.exporter.throttle.duration`: time exports waited on a rate limit, in seconds, for
+//!   exporters limiting their request rate to respect the backend quotas.
+//!
+//! All metrics carry the `otel.exporter.name` and `otel.signal` attributes.
+//!
+//! The instruments are created from the global meter provider. Exporters of the metrics signal
+//! should be careful not to export their own metrics through themselves, which would produce
+//! telemetry for every export.
+use opentelemetry::global;
+use opentelemetry::metrics::{Counter, Gauge, Histogram, Meter};
+use opentelemetry::KeyValue;
+use std::sync::Arc;
+use std::time::Duration;
+
+const INSTRUMENTATION_NAME: &str = "opentelemetry-contrib";
+
+/// Attribute holding the name of the exporter, e.g. `datadog`.
+pub const EXPORTER_NAME: &str = "otel.exporter.name";
+/// Attribute holding the [`Signal`] of the exported items.
+pub const SIGNAL: &str = "otel.signal";
+/// Attribute holding the reason of a failure or drop.
+pub const ERROR_TYPE: &str = "error.type";
+
+/// The signal of the exported items.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    /// Spans.
+    Traces,
+    /// Log records.
+    Logs,
+    /// Metric data points.
+    Metrics,
+}
+
+impl Signal {
+    /// The value of the `otel.signal` attribute.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Signal::Traces => "traces",
+            Signal::Logs => "logs",
+            Signal::Metrics => "metrics",
+        }
+    }
+}
+
+/// The self-metrics of an exporter.
+///
+/// Cloning is cheap, clones record to the same instruments.
+#[derive(Clone, Debug)]
+pub struct ExporterMetrics {
+    exported: Counter<u64>,
+    failed: Counter<u64>,
+    dropped: Counter<u64>,
+    duration: Histogram<f64>,
+    queue_utilization: Gauge<f64>,
+    throttle: Histogram<f64>,
+    attributes: Arc<[KeyValue]>,
+}
+
+impl ExporterMetrics {
+    /// Create the metrics of the exporter `exporter_name`, using the global meter provider.
+    pub fn new(exporter_name: &'static str, signal: Signal) -> Self {
+        Self::with_meter(&global::meter(INSTRUMENTATION_NAME), exporter_name, signal)
+    }
+
+    /// Create the metrics of the exporter `exporter_name` with `meter`.
+    pub fn with_meter(meter: &Meter, exporter_name: &'static str, signal: Signal) -> Self {
+        ExporterMetrics {
+            exported: meter
+                .u64_counter("otel.exporter.exported")
+                .with_description("Number of items successfully exported.")
+                .with_unit("{item}")
+                .build(),
+            failed: meter
+                .u64_counter("otel.exporter.failed")
+                .with_description("Number of items the exporter failed to export.")
+                .with_unit("{item}")
+                .build(),
+            dropped: meter
+                .u64_counter("otel.exporter.dropped")
+                .with_description("Number of items dropped before being exported.")
+                .with_unit("{item}")
+                .build(),
+            duration: meter
+                .f64_histogram("otel.exporter.duration")
+                .with_description("Duration of the export operations.")
+                .with_unit("s")
+                .build(),
+            queue_utilization: meter
+                .f64_gauge("otel.exporter.queue.utilization")
+                .with_description("Fraction of the exporter queue in use.")
+                .with_unit("1")
+                .build(),
+            throttle: meter
+                .f64_histogram("otel.exporter.throttle.duration")
+                .with_description("Time exports waited on a rate limit.")
+                .with_unit("s")
+                .build(),
+            attributes: Arc::new([
+                KeyValue::new(EXPORTER_NAME, exporter_name),
+                KeyValue::new(SIGNAL, signal.as_str()),
+            ]),
+        }
+    }
+
+    /// Record the successful export of `count` items, which took `duration`.
+    pub fn record_success(&self, count: u64, duration: Duration) {
+        self.exported.add(count, &self.attributes);
+        self.duration
+            .record(duration.as_secs_f64(), &self.attributes);
+    }
+
+    /// Record the failed export of `count` items, which took `duration`.
+    ///
+    /// `error_type` should have a low cardinality, e.g. `timeout` or an HTTP status code.
+    pub fn record_failure(&self, count: u64, duration: Duration, error_type: &'static str) {
+        let attributes = self.attributes_with_error(error_type);
+        self.failed.add(count, &attributes);
+        self.duration.record(duration.as_secs_f64(), &attributes);
+    }
+
+    /// Record `count` items dropped before an export was attempted.
+    pub fn record_dropped(&self, count: u64, error_type: &'static str) {
+        self.dropped
+            .add(count, &self.attributes_with_error(error_type));
+    }
+
+    /// Record that `used` slots of a queue of `capacity` slots are in use.
+    pub fn record_queue_utilization(&self, used: usize, capacity: usize) {
+        if capacity > 0 {
+            self.queue_utilization
+                .record(used as f64 / capacity as f64, &self.attributes);
+        }
+    }
+
+    /// Record that an export waited `duration` on a rate limit before being sent.
+    pub fn record_throttle(&self, duration: Duration) {
+        self.throttle
+            .record(duration.as_secs_f64(), &self.attributes);
+    }
+
+    fn attributes_with_error(&self, error_type: &'static str) -> Vec<KeyValue> {
+        let mut attributes = self.attributes.to_vec();
+        attributes.push(KeyValue::new(ERROR_TYPE, error_type));
+        attributes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData, ResourceMetrics};
+    use opentelemetry_sdk::metrics::{InMemoryMetricExporter, PeriodicReader, SdkMeterProvider};
+
+    fn sum(metrics: &[ResourceMetrics], name: &str, error_type: Option<&str>) -> u64 {
+        metrics
+            .iter()
+            .flat_map(|rm| rm.scope_metrics())
+            .flat_map(|sm| sm.metrics())
+            .filter(|metric| metric.name() == name)
+            .map(|metric| match metric.data() {
+                AggregatedMetrics::U64(MetricData::Sum(sum)) => sum
+                    .data_points()
+                    .filter(|dp| {
+                        dp.attributes()
+                            .find(|kv| kv.key.as_str() == ERROR_TYPE)
+                            .map(|kv| kv.value.to_string())
+                            .as_deref()
+                            == error_type
+                    })
+                    .map(|dp| dp.value())
+                    .sum(),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_record() {
+        let exporter = InMemoryMetricExporter::default();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter.clone()).build())
+            .build();
+        let metrics = ExporterMetrics::with_meter(&provider.meter("test"), "test", Signal::Logs);
+
+        metrics.record_success(3, Duration::from_millis(10));
+        metrics.record_failure(2, Duration::from_millis(10), "timeout");
+        metrics.record_dropped(1, "queue_full");
+        metrics.record_queue_utilization(16, 64);
+        provider.force_flush().unwrap();
+
+        let exported = exporter.get_finished_metrics().unwrap();
+        assert_eq!(sum(&exported, "otel.exporter.exported", None), 3);
+        assert_eq!(sum(&exported, "otel.exporter.failed", Some("timeout")), 2);
+        assert_eq!(
+            sum(&exported, "otel.exporter.dropped", Some("queue_full")),
+            1
+        );
+    }
+}
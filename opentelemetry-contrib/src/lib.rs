@@ -11,6 +11,36 @@
 //!
 //! * `binary-propagator`: Adds Experimental binary propagator to propagate trace context using binary format.
 //! * `base64-format`: Enables base64 format support for binary propagators.
+//! * `adaptive_sampler`: Adds a sampler adjusting its probability to sample a target number of
+//!   spans per second.
+//! * `attribute_limit_processor`: Adds a span processor enforcing the attribute limits of
+//!   backends on the exported spans.
+//! * `baggage_span_processor`: Adds a span processor copying selected baggage entries to the
+//!   attributes of spans.
+//! * `compression`: Adds the request body compression shared by HTTP exporters, with the
+//!   `compression_gzip` and `compression_zstd` features enabling the algorithms.
+//! * `event_to_log_processor`: Adds a span processor emitting the events of spans, such as
+//!   exceptions, as log records correlated with their span.
+//! * `exporter_metrics`: Adds the standard exporter self-metrics used by the exporters of this repository.
+//! * `log_sampling_processor`: Adds a log processor suppressing the identical log records of a
+//!   log storm within a window, exporting a summary of the suppressed records.
+//! * `metrics_temporality`: Adds the conversion of metric streams between the delta and
+//!   cumulative temporalities, for exporters of backends expecting one of them.
+//! * `opencensus_propagator`: Adds a propagator bridging the `grpc-trace-bin` and
+//!   `X-Cloud-Trace-Context` span context encodings of OpenCensus.
+//! * `runtime_config`: Adds a config adjusting the sampling probability, forcing the sampling
+//!   and setting the minimum log severity at runtime, with the `runtime_config_http` feature
+//!   serving it over HTTP.
+//! * `thread`: Adds helpers propagating the current context to spawned threads, with the `rayon`
+//!   feature adding a rayon thread pool propagating it to its tasks.
+//!
+//! ## WebAssembly
+//!
+//! The propagators and the `api`, `base64_format`, `binary_propagator`, `jaeger_json_exporter`
+//! and `zipkin_json_exporter` features compile for `wasm32-wasip1`, as checked by CI. They don't
+//! open sockets: the Zipkin exporter posts spans with the given `HttpClient`, e.g. over the
+//! fetch bindings of the host, and the Jaeger exporter writes files and reads the time with the
+//! given `JaegerJsonRuntime`.
 #![warn(
     future_incompatible,
     missing_debug_implementations,
@@ -30,4 +60,16 @@
 )]
 #![cfg_attr(test, deny(warnings))]
 
+#[cfg(feature = "compression")]
+pub mod compression;
+#[cfg(feature = "exporter_metrics")]
+pub mod exporter_metrics;
+#[cfg(feature = "log_sampling_processor")]
+pub mod logs;
+#[cfg(feature = "metrics_temporality")]
+pub mod metrics;
+#[cfg(feature = "runtime_config")]
+pub mod runtime_config;
+#[cfg(feature = "thread")]
+pub mod thread;
 pub mod trace;
@@ -0,0 +1,11 @@
+//! # Opentelemetry logs contrib
+//!
+//! This module provides log processors preparing log records for export, wrapping the processor
+//! exporting them.
+//!
+//! Currently, the following processors are supported:
+//!
+//! * `sampling`, which suppresses the identical log records of a log storm, exporting a summary
+
+#[cfg(feature = "log_sampling_processor")]
+pub mod sampling;
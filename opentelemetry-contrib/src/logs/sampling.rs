@@ -0,0 +1,354 @@
+//! # Log Sampling Processor
+//!
+//! Rate limits identical log records before they reach the processor exporting them, so a log
+//! storm, e.g. the same error logged for every request while a dependency is down, doesn't
+//! overwhelm the backend.
+//!
+//! Records are identical when they have the same severity, body and attributes. Within a
+//! window, only the first records of a kind are exported and the next ones are suppressed.
+//! When the window of a kind ends, the first suppressed record is exported with the
+//! `log.suppressed_count` attribute holding the number of records suppressed, so the storm is
+//! still visible with its size.
+//!
+//! ```no_run
+//! use opentelemetry_contrib::logs::sampling::LogSamplingProcessor;
+//! use opentelemetry_sdk::logs::{SdkLoggerProvider, SimpleLogProcessor};
+//! use std::time::Duration;
+//! # use opentelemetry_sdk::logs::InMemoryLogExporter;
+//! # let exporter = InMemoryLogExporter::default();
+//!
+//! let provider = SdkLoggerProvider::builder()
+//!     .with_log_processor(
+//!         LogSamplingProcessor::new(SimpleLogProcessor::new(exporter))
+//!             .with_window(Duration::from_secs(60))
+//!             .with_max_per_window(5),
+//!     )
+//!     .build();
+//! ```
+use opentelemetry::logs::{AnyValue, LogRecord as _, Severity};
+use opentelemetry::InstrumentationScope;
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::logs::{LogProcessor, SdkLogRecord};
+use opentelemetry_sdk::Resource;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Attribute holding the number of records suppressed in a window, on the summary record.
+pub const SUPPRESSED_COUNT: &str = "log.suppressed_count";
+
+const DEFAULT_WINDOW: Duration = Duration::from_secs(10);
+const DEFAULT_MAX_PER_WINDOW: u64 = 1;
+const DEFAULT_MAX_KINDS: usize = 1024;
+
+/// Suppresses the identical log records exported by the wrapped processor within a window,
+/// exporting a summary of the suppressed records when the window ends.
+#[derive(Debug)]
+pub struct LogSamplingProcessor<P> {
+    inner: P,
+    window: Duration,
+    max_per_window: u64,
+    max_kinds: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    /// The kinds of records seen in their current window, by fingerprint.
+    kinds: HashMap<u64, Kind>,
+    last_sweep: Instant,
+}
+
+#[derive(Debug)]
+struct Kind {
+    window_start: Instant,
+    count: u64,
+    /// The first suppressed record of the window, and the number of records suppressed.
+    suppressed: Option<(SdkLogRecord, InstrumentationScope, u64)>,
+}
+
+impl Kind {
+    fn new(now: Instant) -> Self {
+        Kind {
+            window_start: now,
+            count: 0,
+            suppressed: None,
+        }
+    }
+
+    /// The summary of the suppressed records of the window.
+    fn summary(self) -> Option<(SdkLogRecord, InstrumentationScope)> {
+        self.suppressed.map(|(mut record, scope, count)| {
+            record.add_attribute(SUPPRESSED_COUNT, count as i64);
+            (record, scope)
+        })
+    }
+}
+
+impl<P: LogProcessor> LogSamplingProcessor<P> {
+    /// Export through `inner` the first of the identical records of each 10 second window.
+    pub fn new(inner: P) -> Self {
+        LogSamplingProcessor {
+            inner,
+            window: DEFAULT_WINDOW,
+            max_per_window: DEFAULT_MAX_PER_WINDOW,
+            max_kinds: DEFAULT_MAX_KINDS,
+            state: Mutex::new(State {
+                kinds: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+
+    /// Count the identical records in windows of `window`, 10 seconds by default.
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Export the first `max_per_window` identical records of a window, 1 by default.
+    pub fn with_max_per_window(mut self, max_per_window: u64) -> Self {
+        self.max_per_window = max_per_window.max(1);
+        self
+    }
+
+    /// Track at most `max_kinds` kinds of records at once, 1024 by default. The records of
+    /// other kinds are exported without being counted until the windows of tracked kinds end.
+    pub fn with_max_kinds(mut self, max_kinds: usize) -> Self {
+        self.max_kinds = max_kinds;
+        self
+    }
+
+    /// Whether to export `record` at `now`, along with the summaries of the windows ended.
+    fn sample(
+        &self,
+        record: &SdkLogRecord,
+        scope: &InstrumentationScope,
+        now: Instant,
+    ) -> (bool, Vec<(SdkLogRecord, InstrumentationScope)>) {
+        let mut summaries = Vec::new();
+        let Ok(mut state) = self.state.lock() else {
+            return (true, summaries);
+        };
+
+        // Forget the kinds whose window ended, so they don't fill the map.
+        if now.saturating_duration_since(state.last_sweep) >= self.window {
+            state.last_sweep = now;
+            let window = self.window;
+            let ended: Vec<u64> = state
+                .kinds
+                .iter()
+                .filter(|(_, kind)| now.saturating_duration_since(kind.window_start) >= window)
+                .map(|(fingerprint, _)| *fingerprint)
+                .collect();
+            for fingerprint in ended {
+                summaries.extend(state.kinds.remove(&fingerprint).and_then(Kind::summary));
+            }
+        }
+
+        let fingerprint = fingerprint(record);
+        if !state.kinds.contains_key(&fingerprint) && state.kinds.len() >= self.max_kinds {
+            return (true, summaries);
+        }
+        let kind = state
+            .kinds
+            .entry(fingerprint)
+            .or_insert_with(|| Kind::new(now));
+        if now.saturating_duration_since(kind.window_start) >= self.window {
+            summaries.extend(std::mem::replace(kind, Kind::new(now)).summary());
+        }
+
+        kind.count += 1;
+        if kind.count <= self.max_per_window {
+            return (true, summaries);
+        }
+        match &mut kind.suppressed {
+            Some((_, _, count)) => *count += 1,
+            None => kind.suppressed = Some((record.clone(), scope.clone(), 1)),
+        }
+        (false, summaries)
+    }
+
+    /// Export the summaries of all the windows, ended or not.
+    fn flush_summaries(&self) {
+        let kinds = match self.state.lock() {
+            Ok(mut state) => std::mem::take(&mut state.kinds),
+            Err(_) => return,
+        };
+        for (mut record, scope) in kinds.into_values().filter_map(Kind::summary) {
+            self.inner.emit(&mut record, &scope);
+        }
+    }
+}
+
+impl<P: LogProcessor> LogProcessor for LogSamplingProcessor<P> {
+    fn emit(&self, record: &mut SdkLogRecord, scope: &InstrumentationScope) {
+        let (export, summaries) = self.sample(record, scope, Instant::now());
+        for (mut summary, scope) in summaries {
+            self.inner.emit(&mut summary, &scope);
+        }
+        if export {
+            self.inner.emit(record, scope);
+        }
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.flush_summaries();
+        self.inner.force_flush()
+    }
+
+    fn shutdown(&self) -> OTelSdkResult {
+        self.flush_summaries();
+        self.inner.shutdown()
+    }
+
+    fn event_enabled(&self, level: Severity, target: &str, name: Option<&str>) -> bool {
+        self.inner.event_enabled(level, target, name)
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+/// The fingerprint of the severity, body and attributes of `record`.
+fn fingerprint(record: &SdkLogRecord) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    record
+        .severity_number()
+        .map(|severity| severity as i32)
+        .hash(&mut hasher);
+    record.body().is_some().hash(&mut hasher);
+    if let Some(body) = record.body() {
+        hash_value(body, &mut hasher);
+    }
+    for (key, value) in record.attributes_iter() {
+        key.hash(&mut hasher);
+        hash_value(value, &mut hasher);
+    }
+    hasher.finish()
+}
+
+fn hash_value(value: &AnyValue, hasher: &mut impl Hasher) {
+    match value {
+        AnyValue::Int(i) => (0u8, i).hash(hasher),
+        AnyValue::Double(f) => (1u8, f.to_bits()).hash(hasher),
+        AnyValue::String(s) => (2u8, s.as_str()).hash(hasher),
+        AnyValue::Boolean(b) => (3u8, b).hash(hasher),
+        AnyValue::Bytes(bytes) => (4u8, bytes.as_slice()).hash(hasher),
+        AnyValue::ListAny(values) => {
+            (5u8, values.len()).hash(hasher);
+            for value in values.iter() {
+                hash_value(value, hasher);
+            }
+        }
+        AnyValue::Map(map) => {
+            // The entries of a map are in no particular order, so their hashes are summed.
+            let sum = map.iter().fold(0u64, |sum, (key, value)| {
+                let mut entry_hasher = DefaultHasher::new();
+                key.hash(&mut entry_hasher);
+                hash_value(value, &mut entry_hasher);
+                sum.wrapping_add(entry_hasher.finish())
+            });
+            (6u8, sum).hash(hasher);
+        }
+        _ => 7u8.hash(hasher),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::logs::{Logger, LoggerProvider};
+    use opentelemetry_sdk::logs::{InMemoryLogExporter, SdkLoggerProvider, SimpleLogProcessor};
+
+    fn record(body: &str, attribute: i64) -> SdkLogRecord {
+        let mut record = SdkLoggerProvider::builder()
+            .build()
+            .logger("test")
+            .create_log_record();
+        record.set_severity_number(Severity::Error);
+        record.set_body(body.to_string().into());
+        record.add_attribute("attempt", attribute);
+        record
+    }
+
+    fn suppressed_count(record: &SdkLogRecord) -> Option<AnyValue> {
+        record
+            .attributes_iter()
+            .find(|(key, _)| key.as_str() == SUPPRESSED_COUNT)
+            .map(|(_, value)| value.clone())
+    }
+
+    #[test]
+    fn test_suppress_duplicates() {
+        let exporter = InMemoryLogExporter::default();
+        let processor = LogSamplingProcessor::new(SimpleLogProcessor::new(exporter.clone()))
+            .with_max_per_window(2);
+        let scope = InstrumentationScope::default();
+        let start = Instant::now();
+
+        let mut exported = 0;
+        for i in 0..5 {
+            let (export, summaries) = processor.sample(
+                &record("timeout", 1),
+                &scope,
+                start + Duration::from_millis(i),
+            );
+            assert!(summaries.is_empty());
+            exported += u32::from(export);
+        }
+        assert_eq!(exported, 2);
+
+        // Other records are counted apart.
+        let (export, _) = processor.sample(&record("timeout", 2), &scope, start);
+        assert!(export);
+        let (export, _) = processor.sample(&record("refused", 1), &scope, start);
+        assert!(export);
+
+        // The next window starts with the summary of the previous one.
+        let (export, summaries) = processor.sample(
+            &record("timeout", 1),
+            &scope,
+            start + DEFAULT_WINDOW + Duration::from_millis(1),
+        );
+        assert!(export);
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(suppressed_count(&summaries[0].0), Some(AnyValue::Int(3)));
+        assert_eq!(exporter.get_emitted_logs().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_flush_summaries() {
+        let exporter = InMemoryLogExporter::default();
+        let processor = LogSamplingProcessor::new(SimpleLogProcessor::new(exporter.clone()));
+        let scope = InstrumentationScope::default();
+        for _ in 0..3 {
+            processor.emit(&mut record("timeout", 1), &scope);
+        }
+        processor.emit(&mut record("refused", 1), &scope);
+        processor.force_flush().unwrap();
+
+        let logs = exporter.get_emitted_logs().unwrap();
+        let counts: Vec<_> = logs
+            .iter()
+            .map(|log| suppressed_count(&log.record))
+            .collect();
+        assert_eq!(counts, [None, None, Some(AnyValue::Int(2))]);
+    }
+
+    #[test]
+    fn test_max_kinds() {
+        let exporter = InMemoryLogExporter::default();
+        let processor =
+            LogSamplingProcessor::new(SimpleLogProcessor::new(exporter)).with_max_kinds(1);
+        let scope = InstrumentationScope::default();
+        let now = Instant::now();
+        assert!(processor.sample(&record("timeout", 1), &scope, now).0);
+        assert!(!processor.sample(&record("timeout", 1), &scope, now).0);
+        assert!(processor.sample(&record("refused", 1), &scope, now).0);
+        assert!(processor.sample(&record("refused", 1), &scope, now).0);
+    }
+}
@@ -0,0 +1,11 @@
+//! # Opentelemetry metrics contrib
+//!
+//! This module provides helpers for metrics exporters converting the metrics produced by the SDK
+//! to the data model of their backend.
+//!
+//! Currently, the following helpers are supported:
+//!
+//! * `temporality`, which converts metric streams between the delta and cumulative temporalities
+
+#[cfg(feature = "metrics_temporality")]
+pub mod temporality;
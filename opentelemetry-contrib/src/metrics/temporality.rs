@@ -0,0 +1,495 @@
+//! # Temporality conversion
+//!
+//! Backends expect sums and histograms in one temporality, e.g. delta for DogStatsD and
+//! cumulative for Prometheus, while a meter provider exports all its metrics with the
+//! temporality of its reader. [`DeltaToCumulative`] and [`CumulativeToDelta`] convert the data
+//! points of metric streams in an exporter, keeping the state of every stream they see.
+//!
+//! The state is bounded: streams not seen for the configured idle time are evicted, and the
+//! points of new streams are dropped while the maximum number of streams is reached.
+//!
+//! ```
+//! use opentelemetry::KeyValue;
+//! use opentelemetry_contrib::metrics::temporality::{DataPoint, DeltaToCumulative, StreamKey};
+//! use std::time::{Duration, SystemTime};
+//!
+//! let mut processor = DeltaToCumulative::new(10_000, Duration::from_secs(300));
+//! let key = StreamKey::new("http.requests", &[KeyValue::new("http.route", "/users")]);
+//!
+//! let start = SystemTime::now();
+//! let first = start + Duration::from_secs(10);
+//! let second = first + Duration::from_secs(10);
+//! processor.process(key.clone(), DataPoint::new(start, first, 3_u64));
+//! let cumulative = processor
+//!     .process(key, DataPoint::new(first, second, 2_u64))
+//!     .unwrap();
+//! assert_eq!((cumulative.start_time, cumulative.value), (start, 5));
+//! ```
+use opentelemetry::KeyValue;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, SystemTime};
+
+/// A data point of a metric stream.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataPoint<V> {
+    /// Start of the aggregation period.
+    pub start_time: SystemTime,
+    /// End of the aggregation period.
+    pub time: SystemTime,
+    /// Aggregated value.
+    pub value: V,
+}
+
+impl<V> DataPoint<V> {
+    /// Create a data point aggregating `value` from `start_time` to `time`.
+    pub fn new(start_time: SystemTime, time: SystemTime, value: V) -> Self {
+        DataPoint {
+            start_time,
+            time,
+            value,
+        }
+    }
+}
+
+/// Identity of a metric stream, from the metric name and the attributes of its data points.
+///
+/// Exporters with more identifying fields, like the instrumentation scope or the unit, may use
+/// their own key type instead.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StreamKey {
+    name: String,
+    attributes: Vec<(String, String)>,
+}
+
+impl StreamKey {
+    /// Create the key of the stream of `name` with `attributes`, in any order.
+    pub fn new(name: impl Into<String>, attributes: &[KeyValue]) -> Self {
+        let mut attributes: Vec<(String, String)> = attributes
+            .iter()
+            // The debug representation keeps values of different types apart.
+            .map(|kv| (kv.key.to_string(), format!("{:?}", kv.value)))
+            .collect();
+        attributes.sort();
+        StreamKey {
+            name: name.into(),
+            attributes,
+        }
+    }
+}
+
+/// A value aggregated over time, which deltas are added to and subtracted from.
+pub trait TemporalValue: Clone {
+    /// The value aggregated over both periods, or `None` if the values cannot be combined, e.g.
+    /// histograms with different bucket boundaries.
+    fn add(&self, delta: &Self) -> Option<Self>;
+
+    /// The value aggregated since `previous`, or `None` if the stream was reset in between,
+    /// e.g. a monotonic value decreased.
+    fn delta_since(&self, previous: &Self, monotonic: bool) -> Option<Self>;
+}
+
+impl TemporalValue for u64 {
+    fn add(&self, delta: &Self) -> Option<Self> {
+        Some(self.saturating_add(*delta))
+    }
+
+    fn delta_since(&self, previous: &Self, _monotonic: bool) -> Option<Self> {
+        self.checked_sub(*previous)
+    }
+}
+
+impl TemporalValue for i64 {
+    fn add(&self, delta: &Self) -> Option<Self> {
+        Some(self.saturating_add(*delta))
+    }
+
+    fn delta_since(&self, previous: &Self, monotonic: bool) -> Option<Self> {
+        if monotonic && self < previous {
+            return None;
+        }
+        Some(self.saturating_sub(*previous))
+    }
+}
+
+impl TemporalValue for f64 {
+    fn add(&self, delta: &Self) -> Option<Self> {
+        Some(self + delta)
+    }
+
+    fn delta_since(&self, previous: &Self, monotonic: bool) -> Option<Self> {
+        if monotonic && self < previous {
+            return None;
+        }
+        Some(self - previous)
+    }
+}
+
+/// The value of a histogram data point.
+///
+/// The minimum and maximum are left out, as they cannot be derived for the converted period.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistogramValue {
+    /// Upper bounds of the buckets, the last bucket having no upper bound.
+    pub bounds: Vec<f64>,
+    /// Number of values in each bucket, one more than `bounds`.
+    pub bucket_counts: Vec<u64>,
+    /// Number of values.
+    pub count: u64,
+    /// Sum of the values.
+    pub sum: f64,
+}
+
+impl TemporalValue for HistogramValue {
+    fn add(&self, delta: &Self) -> Option<Self> {
+        if self.bounds != delta.bounds || self.bucket_counts.len() != delta.bucket_counts.len() {
+            return None;
+        }
+        Some(HistogramValue {
+            bounds: self.bounds.clone(),
+            bucket_counts: self
+                .bucket_counts
+                .iter()
+                .zip(&delta.bucket_counts)
+                .map(|(count, delta)| count.saturating_add(*delta))
+                .collect(),
+            count: self.count.saturating_add(delta.count),
+            sum: self.sum + delta.sum,
+        })
+    }
+
+    fn delta_since(&self, previous: &Self, _monotonic: bool) -> Option<Self> {
+        if self.bounds != previous.bounds
+            || self.bucket_counts.len() != previous.bucket_counts.len()
+        {
+            return None;
+        }
+        let bucket_counts = self
+            .bucket_counts
+            .iter()
+            .zip(&previous.bucket_counts)
+            .map(|(count, previous)| count.checked_sub(*previous))
+            .collect::<Option<Vec<_>>>()?;
+        Some(HistogramValue {
+            bounds: self.bounds.clone(),
+            bucket_counts,
+            count: self.count.checked_sub(previous.count)?,
+            sum: self.sum - previous.sum,
+        })
+    }
+}
+
+/// The state of the streams of a processor.
+#[derive(Debug)]
+struct Streams<K, V> {
+    points: HashMap<K, DataPoint<V>>,
+    max_streams: usize,
+    max_idle: Duration,
+}
+
+impl<K: Hash + Eq, V> Streams<K, V> {
+    fn new(max_streams: usize, max_idle: Duration) -> Self {
+        Streams {
+            points: HashMap::new(),
+            max_streams,
+            max_idle,
+        }
+    }
+
+    /// Whether a point of a new stream at `time` can be tracked, evicting the stale streams if
+    /// the maximum is reached.
+    fn has_room(&mut self, time: SystemTime) -> bool {
+        if self.points.len() >= self.max_streams {
+            self.evict_stale(time);
+        }
+        self.points.len() < self.max_streams
+    }
+
+    fn evict_stale(&mut self, now: SystemTime) -> usize {
+        let before = self.points.len();
+        let max_idle = self.max_idle;
+        self.points
+            .retain(|_, point| match now.duration_since(point.time) {
+                Ok(idle) => idle <= max_idle,
+                Err(_) => true,
+            });
+        before - self.points.len()
+    }
+}
+
+/// Converts the delta data points of metric streams to cumulative data points.
+///
+/// The cumulative value of a stream starts at the start time of its first point, or of the
+/// first point after a reset, e.g. when histogram bucket boundaries change.
+#[derive(Debug)]
+pub struct DeltaToCumulative<K, V> {
+    streams: Streams<K, V>,
+}
+
+impl<K: Hash + Eq, V: TemporalValue> DeltaToCumulative<K, V> {
+    /// Create a processor tracking at most `max_streams` streams, evicting those without a
+    /// point for `max_idle`.
+    pub fn new(max_streams: usize, max_idle: Duration) -> Self {
+        DeltaToCumulative {
+            streams: Streams::new(max_streams, max_idle),
+        }
+    }
+
+    /// The cumulative point of the stream `key` including the delta `point`.
+    ///
+    /// Returns `None` for points not after the last point of the stream, or starting before it
+    /// as their delta would be counted twice, and points of new streams while the maximum number
+    /// of streams is reached.
+    pub fn process(&mut self, key: K, point: DataPoint<V>) -> Option<DataPoint<V>> {
+        match self.streams.points.get_mut(&key) {
+            Some(cumulative) => {
+                if point.time <= cumulative.time || point.start_time < cumulative.time {
+                    return None;
+                }
+                match cumulative.value.add(&point.value) {
+                    Some(value) => {
+                        cumulative.value = value;
+                        cumulative.time = point.time;
+                    }
+                    None => *cumulative = point,
+                }
+                Some(cumulative.clone())
+            }
+            None => {
+                if !self.streams.has_room(point.time) {
+                    return None;
+                }
+                self.streams.points.insert(key, point.clone());
+                Some(point)
+            }
+        }
+    }
+
+    /// Forget the streams without a point in the idle time before `now`, returning their count.
+    pub fn evict_stale(&mut self, now: SystemTime) -> usize {
+        self.streams.evict_stale(now)
+    }
+
+    /// Number of streams tracked.
+    pub fn len(&self) -> usize {
+        self.streams.points.len()
+    }
+
+    /// Whether no stream is tracked.
+    pub fn is_empty(&self) -> bool {
+        self.streams.points.is_empty()
+    }
+}
+
+/// Converts the cumulative data points of metric streams to delta data points.
+///
+/// The first point of a stream, and the first point after a reset, are kept as is: the
+/// cumulative value is the delta since the start time of the stream. A reset is detected from a
+/// new start time, or a decreasing value for monotonic streams.
+#[derive(Debug)]
+pub struct CumulativeToDelta<K, V> {
+    streams: Streams<K, V>,
+}
+
+impl<K: Hash + Eq, V: TemporalValue> CumulativeToDelta<K, V> {
+    /// Create a processor tracking at most `max_streams` streams, evicting those without a
+    /// point for `max_idle`.
+    pub fn new(max_streams: usize, max_idle: Duration) -> Self {
+        CumulativeToDelta {
+            streams: Streams::new(max_streams, max_idle),
+        }
+    }
+
+    /// The delta point of the stream `key` since its previous cumulative point.
+    ///
+    /// `monotonic` tells whether the values of the stream only increase, like the sums of
+    /// counters. Returns `None` for points not after the last point of the stream, and points of
+    /// new streams while the maximum number of streams is reached.
+    pub fn process(
+        &mut self,
+        key: K,
+        point: DataPoint<V>,
+        monotonic: bool,
+    ) -> Option<DataPoint<V>> {
+        match self.streams.points.get_mut(&key) {
+            Some(previous) => {
+                if point.time <= previous.time {
+                    return None;
+                }
+                let delta = (point.start_time == previous.start_time)
+                    .then(|| point.value.delta_since(&previous.value, monotonic))
+                    .flatten()
+                    .map(|value| DataPoint::new(previous.time, point.time, value));
+                *previous = point.clone();
+                Some(delta.unwrap_or(point))
+            }
+            None => {
+                if !self.streams.has_room(point.time) {
+                    return None;
+                }
+                self.streams.points.insert(key, point.clone());
+                Some(point)
+            }
+        }
+    }
+
+    /// Forget the streams without a point in the idle time before `now`, returning their count.
+    pub fn evict_stale(&mut self, now: SystemTime) -> usize {
+        self.streams.evict_stale(now)
+    }
+
+    /// Number of streams tracked.
+    pub fn len(&self) -> usize {
+        self.streams.points.len()
+    }
+
+    /// Whether no stream is tracked.
+    pub fn is_empty(&self) -> bool {
+        self.streams.points.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn key(route: &str) -> StreamKey {
+        StreamKey::new("requests", &[KeyValue::new("route", route.to_string())])
+    }
+
+    #[test]
+    fn test_stream_key() {
+        let a = KeyValue::new("a", 1_i64);
+        let b = KeyValue::new("b", "1");
+        assert_eq!(
+            StreamKey::new("m", &[a.clone(), b.clone()]),
+            StreamKey::new("m", &[b, a])
+        );
+        assert_ne!(
+            StreamKey::new("m", &[KeyValue::new("a", 1_i64)]),
+            StreamKey::new("m", &[KeyValue::new("a", "1")])
+        );
+    }
+
+    #[test]
+    fn test_delta_to_cumulative() {
+        let mut processor = DeltaToCumulative::new(10, Duration::from_secs(60));
+        assert_eq!(
+            processor.process(key("/"), DataPoint::new(at(0), at(10), 3_u64)),
+            Some(DataPoint::new(at(0), at(10), 3))
+        );
+        assert_eq!(
+            processor.process(key("/"), DataPoint::new(at(10), at(20), 2)),
+            Some(DataPoint::new(at(0), at(20), 5))
+        );
+        // Out of order.
+        assert_eq!(
+            processor.process(key("/"), DataPoint::new(at(5), at(15), 1)),
+            None
+        );
+        // Overlapping the last point.
+        assert_eq!(
+            processor.process(key("/"), DataPoint::new(at(15), at(25), 1)),
+            None
+        );
+        assert_eq!(
+            processor.process(key("/"), DataPoint::new(at(20), at(30), 2)),
+            Some(DataPoint::new(at(0), at(30), 7))
+        );
+        assert_eq!(
+            processor.process(key("/users"), DataPoint::new(at(10), at(20), 1)),
+            Some(DataPoint::new(at(10), at(20), 1))
+        );
+        assert_eq!(processor.len(), 2);
+    }
+
+    #[test]
+    fn test_cumulative_to_delta() {
+        let mut processor = CumulativeToDelta::new(10, Duration::from_secs(60));
+        assert_eq!(
+            processor.process(key("/"), DataPoint::new(at(0), at(10), 3.0), true),
+            Some(DataPoint::new(at(0), at(10), 3.0))
+        );
+        assert_eq!(
+            processor.process(key("/"), DataPoint::new(at(0), at(20), 5.0), true),
+            Some(DataPoint::new(at(10), at(20), 2.0))
+        );
+        // The counter restarted.
+        assert_eq!(
+            processor.process(key("/"), DataPoint::new(at(25), at(30), 1.0), true),
+            Some(DataPoint::new(at(25), at(30), 1.0))
+        );
+        // A decrease is a reset of monotonic streams only.
+        assert_eq!(
+            processor.process(key("/"), DataPoint::new(at(25), at(40), 0.5), true),
+            Some(DataPoint::new(at(25), at(40), 0.5))
+        );
+        assert_eq!(
+            processor.process(key("/"), DataPoint::new(at(25), at(50), 0.25), false),
+            Some(DataPoint::new(at(40), at(50), -0.25))
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        let histogram = |bucket_counts: Vec<u64>| HistogramValue {
+            bounds: vec![10.0],
+            count: bucket_counts.iter().sum(),
+            sum: bucket_counts.iter().sum::<u64>() as f64,
+            bucket_counts,
+        };
+        let mut processor = CumulativeToDelta::new(10, Duration::from_secs(60));
+        processor.process(
+            key("/"),
+            DataPoint::new(at(0), at(10), histogram(vec![1, 2])),
+            true,
+        );
+        assert_eq!(
+            processor
+                .process(
+                    key("/"),
+                    DataPoint::new(at(0), at(20), histogram(vec![4, 2])),
+                    true
+                )
+                .map(|point| point.value),
+            Some(histogram(vec![3, 0]))
+        );
+
+        let mut processor = DeltaToCumulative::new(10, Duration::from_secs(60));
+        processor.process(
+            key("/"),
+            DataPoint::new(at(0), at(10), histogram(vec![1, 2])),
+        );
+        assert_eq!(
+            processor
+                .process(
+                    key("/"),
+                    DataPoint::new(at(10), at(20), histogram(vec![4, 2]))
+                )
+                .map(|point| point.value),
+            Some(histogram(vec![5, 4]))
+        );
+    }
+
+    #[test]
+    fn test_bounded_state() {
+        let mut processor = DeltaToCumulative::new(2, Duration::from_secs(60));
+        processor.process(key("/a"), DataPoint::new(at(0), at(10), 1_i64));
+        processor.process(key("/b"), DataPoint::new(at(0), at(50), 1));
+        assert_eq!(
+            processor.process(key("/c"), DataPoint::new(at(0), at(60), 1)),
+            None
+        );
+        // `/a` is stale 60 seconds after its last point.
+        assert!(processor
+            .process(key("/c"), DataPoint::new(at(0), at(71), 1))
+            .is_some());
+        assert_eq!(processor.len(), 2);
+        assert_eq!(processor.evict_stale(at(200)), 2);
+        assert!(processor.is_empty());
+    }
+}
@@ -0,0 +1,495 @@
+//! # Runtime Configuration
+//!
+//! Adjusts the sampling and the logging of a running service, e.g. to sample every trace while
+//! investigating an incident, without restarting it.
+//!
+//! A [`RuntimeConfig`] holds the settings read by the components it creates: the sampling
+//! probability and the forced sampling of [`RuntimeSampler`], and the minimum severity of
+//! [`RuntimeSeverityProcessor`]. Clones share the settings, so the config can be kept by an
+//! admin endpoint while the sampler and the processor are installed on the providers. Every
+//! change is logged with its previous and new values by the internal logs of OpenTelemetry,
+//! as an audit trail.
+//!
+//! The `runtime_config_http` feature adds [`RuntimeConfig::handle_request`], serving the
+//! settings over HTTP from any server.
+//!
+//! ```no_run
+//! use opentelemetry::logs::Severity;
+//! use opentelemetry_contrib::runtime_config::RuntimeConfig;
+//! use opentelemetry_sdk::logs::{SdkLoggerProvider, SimpleLogProcessor};
+//! use opentelemetry_sdk::trace::{Sampler, SdkTracerProvider};
+//! # use opentelemetry_sdk::logs::InMemoryLogExporter;
+//! # let exporter = InMemoryLogExporter::default();
+//!
+//! let config = RuntimeConfig::new()
+//!     .with_sampling_probability(0.01)
+//!     .with_min_severity(Some(Severity::Warn));
+//! let tracer_provider = SdkTracerProvider::builder()
+//!     .with_sampler(Sampler::ParentBased(Box::new(config.sampler())))
+//!     .build();
+//! let logger_provider = SdkLoggerProvider::builder()
+//!     .with_log_processor(config.log_processor(SimpleLogProcessor::new(exporter)))
+//!     .build();
+//!
+//! // During an incident
+//! config.set_force_sampling(true);
+//! config.set_min_severity(Some(Severity::Debug));
+//! ```
+use crate::trace::sampler::ratio_sampled;
+use opentelemetry::logs::Severity;
+use opentelemetry::trace::{
+    Link, SamplingDecision, SamplingResult, SpanKind, TraceContextExt, TraceId,
+};
+use opentelemetry::{otel_info, Context, InstrumentationScope, KeyValue};
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::logs::{LogProcessor, SdkLogRecord};
+use opentelemetry_sdk::trace::ShouldSample;
+use opentelemetry_sdk::Resource;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
+use std::sync::Arc;
+
+const SEVERITIES: [Severity; 24] = [
+    Severity::Trace,
+    Severity::Trace2,
+    Severity::Trace3,
+    Severity::Trace4,
+    Severity::Debug,
+    Severity::Debug2,
+    Severity::Debug3,
+    Severity::Debug4,
+    Severity::Info,
+    Severity::Info2,
+    Severity::Info3,
+    Severity::Info4,
+    Severity::Warn,
+    Severity::Warn2,
+    Severity::Warn3,
+    Severity::Warn4,
+    Severity::Error,
+    Severity::Error2,
+    Severity::Error3,
+    Severity::Error4,
+    Severity::Fatal,
+    Severity::Fatal2,
+    Severity::Fatal3,
+    Severity::Fatal4,
+];
+
+/// Settings of the sampling and the logging adjustable at runtime.
+///
+/// Clones share their settings.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    state: Arc<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    /// Bits of the sampling probability.
+    sampling_probability: AtomicU64,
+    force_sampling: AtomicBool,
+    /// The minimum severity as a number, 0 when all the records are exported.
+    min_severity: AtomicI32,
+}
+
+impl RuntimeConfig {
+    /// Create a config sampling every trace and exporting every log record.
+    pub fn new() -> Self {
+        RuntimeConfig {
+            state: Arc::new(State {
+                sampling_probability: AtomicU64::new(1.0_f64.to_bits()),
+                force_sampling: AtomicBool::new(false),
+                min_severity: AtomicI32::new(0),
+            }),
+        }
+    }
+
+    /// Start with the sampling `probability`, 1 by default.
+    pub fn with_sampling_probability(self, probability: f64) -> Self {
+        self.store_sampling_probability(probability);
+        self
+    }
+
+    /// Start with the minimum severity of the exported records, `None` by default.
+    pub fn with_min_severity(self, severity: Option<Severity>) -> Self {
+        self.store_min_severity(severity);
+        self
+    }
+
+    /// A sampler sampling with the probability of this config, or every trace while forced.
+    ///
+    /// Use it as the root sampler of the `ParentBased` sampler of the SDK, so the children of
+    /// a span follow its decision.
+    pub fn sampler(&self) -> RuntimeSampler {
+        RuntimeSampler {
+            config: self.clone(),
+        }
+    }
+
+    /// A processor dropping the records below the minimum severity of this config before
+    /// `inner`.
+    pub fn log_processor<P: LogProcessor>(&self, inner: P) -> RuntimeSeverityProcessor<P> {
+        RuntimeSeverityProcessor {
+            config: self.clone(),
+            inner,
+        }
+    }
+
+    /// The probability of sampling a trace, between 0 and 1.
+    pub fn sampling_probability(&self) -> f64 {
+        f64::from_bits(self.state.sampling_probability.load(Ordering::Relaxed))
+    }
+
+    /// Set the probability of sampling a trace, clamped between 0 and 1.
+    pub fn set_sampling_probability(&self, probability: f64) {
+        let old = self.sampling_probability();
+        let new = self.store_sampling_probability(probability);
+        otel_info!(
+            name: "RuntimeConfig.SamplingProbabilityChanged",
+            old = old,
+            new = new
+        );
+    }
+
+    /// Whether every trace is sampled, whatever the sampling probability.
+    pub fn force_sampling(&self) -> bool {
+        self.state.force_sampling.load(Ordering::Relaxed)
+    }
+
+    /// Sample every trace while `force_sampling` is `true`, e.g. during an investigation.
+    pub fn set_force_sampling(&self, force_sampling: bool) {
+        let old = self
+            .state
+            .force_sampling
+            .swap(force_sampling, Ordering::Relaxed);
+        otel_info!(
+            name: "RuntimeConfig.ForceSamplingChanged",
+            old = old,
+            new = force_sampling
+        );
+    }
+
+    /// The minimum severity of the exported records, `None` when all of them are exported.
+    pub fn min_severity(&self) -> Option<Severity> {
+        let min_severity = self.state.min_severity.load(Ordering::Relaxed);
+        SEVERITIES
+            .into_iter()
+            .find(|severity| *severity as i32 == min_severity)
+    }
+
+    /// Set the minimum severity of the exported records, `None` to export all of them.
+    pub fn set_min_severity(&self, severity: Option<Severity>) {
+        let old = self.min_severity();
+        self.store_min_severity(severity);
+        otel_info!(
+            name: "RuntimeConfig.MinSeverityChanged",
+            old = old.map_or("none", |severity| severity.name()),
+            new = severity.map_or("none", |severity| severity.name())
+        );
+    }
+
+    fn store_sampling_probability(&self, probability: f64) -> f64 {
+        let probability = if probability.is_nan() {
+            0.0
+        } else {
+            probability.clamp(0.0, 1.0)
+        };
+        self.state
+            .sampling_probability
+            .store(probability.to_bits(), Ordering::Relaxed);
+        probability
+    }
+
+    fn store_min_severity(&self, severity: Option<Severity>) {
+        self.state.min_severity.store(
+            severity.map_or(0, |severity| severity as i32),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Whether records of `severity` pass the minimum severity.
+    #[inline]
+    fn severity_enabled(&self, severity: Severity) -> bool {
+        severity as i32 >= self.state.min_severity.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Samples traces with the probability of a [`RuntimeConfig`], by trace id like the
+/// `TraceIdRatioBased` sampler of the SDK, or all of them while its sampling is forced.
+#[derive(Clone, Debug)]
+pub struct RuntimeSampler {
+    config: RuntimeConfig,
+}
+
+impl ShouldSample for RuntimeSampler {
+    fn should_sample(
+        &self,
+        parent_context: Option<&Context>,
+        trace_id: TraceId,
+        _name: &str,
+        _span_kind: &SpanKind,
+        _attributes: &[KeyValue],
+        _links: &[Link],
+    ) -> SamplingResult {
+        let sampled = self.config.force_sampling()
+            || ratio_sampled(trace_id, self.config.sampling_probability());
+        SamplingResult {
+            decision: if sampled {
+                SamplingDecision::RecordAndSample
+            } else {
+                SamplingDecision::Drop
+            },
+            attributes: Vec::new(),
+            trace_state: parent_context
+                .map(|cx| cx.span().span_context().trace_state().clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Drops the log records below the minimum severity of a [`RuntimeConfig`] before the wrapped
+/// processor. Records without a severity are kept.
+#[derive(Debug)]
+pub struct RuntimeSeverityProcessor<P> {
+    config: RuntimeConfig,
+    inner: P,
+}
+
+impl<P: LogProcessor> LogProcessor for RuntimeSeverityProcessor<P> {
+    fn emit(&self, record: &mut SdkLogRecord, scope: &InstrumentationScope) {
+        if record
+            .severity_number()
+            .map_or(true, |severity| self.config.severity_enabled(severity))
+        {
+            self.inner.emit(record, scope);
+        }
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn shutdown(&self) -> OTelSdkResult {
+        self.inner.shutdown()
+    }
+
+    fn event_enabled(&self, level: Severity, target: &str, name: Option<&str>) -> bool {
+        self.config.severity_enabled(level) && self.inner.event_enabled(level, target, name)
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+#[cfg(feature = "runtime_config_http")]
+impl RuntimeConfig {
+    /// Serve the settings over HTTP.
+    ///
+    /// A `GET` request returns the settings as JSON:
+    ///
+    /// ```json
+    /// {"sampling_probability":0.01,"force_sampling":false,"min_severity":"WARN"}
+    /// ```
+    ///
+    /// A `POST` or `PUT` request changes the settings given in its form encoded body, e.g.
+    /// `force_sampling=true&min_severity=debug`, and returns the new settings. The minimum
+    /// severity is one of `trace`, `debug`, `info`, `warn`, `error`, `fatal` or `none`. When a
+    /// setting is invalid, none is changed and the response is `400 Bad Request`.
+    ///
+    /// Mount it on an admin endpoint of the server of the service, only reachable by its
+    /// operators.
+    pub fn handle_request(
+        &self,
+        request: http::Request<bytes::Bytes>,
+    ) -> http::Response<bytes::Bytes> {
+        match *request.method() {
+            http::Method::GET => {}
+            http::Method::POST | http::Method::PUT => {
+                if let Err(error) = self.update(request.body()) {
+                    return response(http::StatusCode::BAD_REQUEST, "text/plain", error);
+                }
+            }
+            _ => {
+                return response(
+                    http::StatusCode::METHOD_NOT_ALLOWED,
+                    "text/plain",
+                    "method not allowed".to_string(),
+                )
+            }
+        }
+        let body = format!(
+            r#"{{"sampling_probability":{},"force_sampling":{},"min_severity":{}}}"#,
+            self.sampling_probability(),
+            self.force_sampling(),
+            self.min_severity()
+                .map_or("null".to_string(), |severity| format!(
+                    r#""{}""#,
+                    severity.name()
+                )),
+        );
+        response(http::StatusCode::OK, "application/json", body)
+    }
+
+    /// Apply the settings of the form encoded `body`, all of them or none.
+    fn update(&self, body: &[u8]) -> Result<(), String> {
+        let body = std::str::from_utf8(body).map_err(|_| "body is not UTF-8".to_string())?;
+        let (mut sampling_probability, mut force_sampling, mut min_severity) = (None, None, None);
+        for pair in body.trim().split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "sampling_probability" => {
+                    sampling_probability = Some(
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|probability| (0.0..=1.0).contains(probability))
+                            .ok_or_else(|| format!("invalid sampling_probability: {value}"))?,
+                    );
+                }
+                "force_sampling" => {
+                    force_sampling = Some(
+                        value
+                            .parse::<bool>()
+                            .map_err(|_| format!("invalid force_sampling: {value}"))?,
+                    );
+                }
+                "min_severity" => {
+                    min_severity = Some(match value.to_ascii_lowercase().as_str() {
+                        "none" => None,
+                        "trace" => Some(Severity::Trace),
+                        "debug" => Some(Severity::Debug),
+                        "info" => Some(Severity::Info),
+                        "warn" => Some(Severity::Warn),
+                        "error" => Some(Severity::Error),
+                        "fatal" => Some(Severity::Fatal),
+                        _ => return Err(format!("invalid min_severity: {value}")),
+                    });
+                }
+                _ => return Err(format!("unknown setting: {key}")),
+            }
+        }
+        if let Some(probability) = sampling_probability {
+            self.set_sampling_probability(probability);
+        }
+        if let Some(force_sampling) = force_sampling {
+            self.set_force_sampling(force_sampling);
+        }
+        if let Some(severity) = min_severity {
+            self.set_min_severity(severity);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "runtime_config_http")]
+fn response(
+    status: http::StatusCode,
+    content_type: &'static str,
+    body: String,
+) -> http::Response<bytes::Bytes> {
+    let mut response = http::Response::new(bytes::Bytes::from(body));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static(content_type),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::logs::{LogRecord as _, Logger, LoggerProvider};
+    use opentelemetry_sdk::logs::{InMemoryLogExporter, SdkLoggerProvider, SimpleLogProcessor};
+
+    #[test]
+    fn test_sampler() {
+        let config = RuntimeConfig::new().with_sampling_probability(0.0);
+        let sampler = config.sampler();
+        let sample = || {
+            sampler
+                .should_sample(None, TraceId::from(42), "span", &SpanKind::Server, &[], &[])
+                .decision
+        };
+        assert_eq!(sample(), SamplingDecision::Drop);
+
+        config.set_force_sampling(true);
+        assert_eq!(sample(), SamplingDecision::RecordAndSample);
+
+        config.set_force_sampling(false);
+        config.set_sampling_probability(2.0);
+        assert_eq!(config.sampling_probability(), 1.0);
+        assert_eq!(sample(), SamplingDecision::RecordAndSample);
+    }
+
+    #[test]
+    fn test_log_processor() {
+        let exporter = InMemoryLogExporter::default();
+        let config = RuntimeConfig::new().with_min_severity(Some(Severity::Warn));
+        let provider = SdkLoggerProvider::builder()
+            .with_log_processor(config.log_processor(SimpleLogProcessor::new(exporter.clone())))
+            .build();
+        let logger = provider.logger("test");
+        let emit = |severity: Severity| {
+            let mut record = logger.create_log_record();
+            record.set_severity_number(severity);
+            logger.emit(record);
+        };
+
+        emit(Severity::Info);
+        emit(Severity::Error);
+        assert_eq!(exporter.get_emitted_logs().unwrap().len(), 1);
+
+        config.set_min_severity(Some(Severity::Debug));
+        assert_eq!(config.min_severity(), Some(Severity::Debug));
+        emit(Severity::Info);
+        assert_eq!(exporter.get_emitted_logs().unwrap().len(), 2);
+
+        config.set_min_severity(None);
+        assert_eq!(config.min_severity(), None);
+        emit(Severity::Trace);
+        assert_eq!(exporter.get_emitted_logs().unwrap().len(), 3);
+    }
+
+    #[cfg(feature = "runtime_config_http")]
+    #[test]
+    fn test_handle_request() {
+        let config = RuntimeConfig::new();
+        let request = |method: http::Method, body: &'static str| {
+            let mut request = http::Request::new(bytes::Bytes::from_static(body.as_bytes()));
+            *request.method_mut() = method;
+            config.handle_request(request)
+        };
+
+        let response = request(http::Method::GET, "");
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(
+            response.body(),
+            r#"{"sampling_probability":1,"force_sampling":false,"min_severity":null}"#
+        );
+
+        let response = request(
+            http::Method::POST,
+            "sampling_probability=0.25&force_sampling=true&min_severity=warn",
+        );
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(
+            response.body(),
+            r#"{"sampling_probability":0.25,"force_sampling":true,"min_severity":"WARN"}"#
+        );
+
+        let response = request(http::Method::PUT, "force_sampling=false&min_severity=loud");
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        assert!(config.force_sampling());
+
+        let response = request(http::Method::DELETE, "");
+        assert_eq!(response.status(), http::StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
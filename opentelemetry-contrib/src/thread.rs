@@ -0,0 +1,266 @@
+//! # Context propagation across threads
+//!
+//! The current [`Context`] is thread-local, so work moved to another thread loses it and the
+//! spans created there have no parent. The helpers of this module capture the current context
+//! and attach it on the thread running the work.
+//!
+//! ```
+//! use opentelemetry::Context;
+//! use opentelemetry_contrib::thread::spawn_with_context;
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct RequestId(u64);
+//!
+//! let _guard = Context::current_with_value(RequestId(7)).attach();
+//! let handle = spawn_with_context(|| Context::current().get::<RequestId>().map(|id| id.0));
+//! assert_eq!(handle.join().unwrap(), Some(7));
+//! ```
+//!
+//! With the `rayon` feature, [`ContextThreadPool`] does the same for the tasks of a rayon
+//! thread pool, and [`propagate`] for the closures of parallel iterators.
+use opentelemetry::Context;
+use std::thread::{self, JoinHandle, Scope, ScopedJoinHandle};
+
+/// Spawn a thread running `f` with the current context attached.
+///
+/// See [`std::thread::spawn`].
+pub fn spawn_with_context<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let cx = Context::current();
+    thread::spawn(move || {
+        let _guard = cx.attach();
+        f()
+    })
+}
+
+/// Spawn a scoped thread running `f` with the current context attached.
+///
+/// See [`Scope::spawn`].
+pub fn spawn_scoped_with_context<'scope, 'env, F, T>(
+    scope: &'scope Scope<'scope, 'env>,
+    f: F,
+) -> ScopedJoinHandle<'scope, T>
+where
+    F: FnOnce() -> T + Send + 'scope,
+    T: Send + 'scope,
+{
+    let cx = Context::current();
+    scope.spawn(move || {
+        let _guard = cx.attach();
+        f()
+    })
+}
+
+/// Wrap `f` so it runs with the current context attached, whatever the thread calling it.
+///
+/// This is useful for the closures of parallel iterators, which run on the worker threads:
+///
+/// ```
+/// # #[cfg(feature = "rayon")]
+/// # {
+/// use opentelemetry::Context;
+/// use opentelemetry_contrib::thread::propagate;
+/// use rayon::prelude::*;
+///
+/// #[derive(Debug)]
+/// struct RequestId(u64);
+///
+/// let _guard = Context::current_with_value(RequestId(7)).attach();
+/// let ids: Vec<_> = (0..4)
+///     .into_par_iter()
+///     .map(propagate(|_| Context::current().get::<RequestId>().map(|id| id.0)))
+///     .collect();
+/// assert!(ids.iter().all(|id| *id == Some(7)));
+/// # }
+/// ```
+pub fn propagate<A, R, F>(f: F) -> impl Fn(A) -> R + Send + Sync
+where
+    F: Fn(A) -> R + Send + Sync,
+{
+    let cx = Context::current();
+    move |arg| {
+        let _guard = cx.clone().attach();
+        f(arg)
+    }
+}
+
+#[cfg(feature = "rayon")]
+pub use self::pool::{ContextThreadPool, ContextThreadPoolBuilder};
+
+#[cfg(feature = "rayon")]
+mod pool {
+    use opentelemetry::Context;
+
+    /// Builder of a [`ContextThreadPool`], wrapping a [`rayon::ThreadPoolBuilder`].
+    #[derive(Debug, Default)]
+    pub struct ContextThreadPoolBuilder {
+        builder: rayon::ThreadPoolBuilder,
+    }
+
+    impl ContextThreadPoolBuilder {
+        /// Create a builder with the default rayon configuration.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Set the number of threads of the pool, see [`rayon::ThreadPoolBuilder::num_threads`].
+        pub fn num_threads(mut self, num_threads: usize) -> Self {
+            self.builder = self.builder.num_threads(num_threads);
+            self
+        }
+
+        /// Set the name of the threads of the pool, see
+        /// [`rayon::ThreadPoolBuilder::thread_name`].
+        pub fn thread_name<F>(mut self, closure: F) -> Self
+        where
+            F: FnMut(usize) -> String + 'static,
+        {
+            self.builder = self.builder.thread_name(closure);
+            self
+        }
+
+        /// Build the thread pool.
+        pub fn build(self) -> Result<ContextThreadPool, rayon::ThreadPoolBuildError> {
+            Ok(ContextThreadPool {
+                pool: self.builder.build()?,
+            })
+        }
+    }
+
+    impl From<rayon::ThreadPoolBuilder> for ContextThreadPoolBuilder {
+        fn from(builder: rayon::ThreadPoolBuilder) -> Self {
+            ContextThreadPoolBuilder { builder }
+        }
+    }
+
+    /// A rayon thread pool running its tasks with the context of the caller attached.
+    ///
+    /// Only the operations submitted through this type carry the context. The closures of
+    /// parallel iterators run inside [`install`](Self::install) still need
+    /// [`propagate`](super::propagate), as rayon may run them on other worker threads.
+    #[derive(Debug)]
+    pub struct ContextThreadPool {
+        pool: rayon::ThreadPool,
+    }
+
+    impl ContextThreadPool {
+        /// Run `op` in the pool with the current context attached, see
+        /// [`rayon::ThreadPool::install`].
+        pub fn install<OP, R>(&self, op: OP) -> R
+        where
+            OP: FnOnce() -> R + Send,
+            R: Send,
+        {
+            let cx = Context::current();
+            self.pool.install(move || {
+                let _guard = cx.attach();
+                op()
+            })
+        }
+
+        /// Spawn `op` in the pool with the current context attached, see
+        /// [`rayon::ThreadPool::spawn`].
+        pub fn spawn<OP>(&self, op: OP)
+        where
+            OP: FnOnce() + Send + 'static,
+        {
+            let cx = Context::current();
+            self.pool.spawn(move || {
+                let _guard = cx.attach();
+                op()
+            })
+        }
+
+        /// Run `oper_a` and `oper_b` in the pool, possibly in parallel, with the current context
+        /// attached, see [`rayon::ThreadPool::join`].
+        pub fn join<A, B, RA, RB>(&self, oper_a: A, oper_b: B) -> (RA, RB)
+        where
+            A: FnOnce() -> RA + Send,
+            B: FnOnce() -> RB + Send,
+            RA: Send,
+            RB: Send,
+        {
+            let cx = Context::current();
+            let cx_b = cx.clone();
+            self.pool.join(
+                move || {
+                    let _guard = cx.attach();
+                    oper_a()
+                },
+                move || {
+                    let _guard = cx_b.attach();
+                    oper_b()
+                },
+            )
+        }
+
+        /// The wrapped rayon thread pool.
+        pub fn inner(&self) -> &rayon::ThreadPool {
+            &self.pool
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Value(u64);
+
+    fn current_value() -> Option<u64> {
+        Context::current().get::<Value>().map(|value| value.0)
+    }
+
+    #[test]
+    fn test_spawn_with_context() {
+        let _guard = Context::current_with_value(Value(1)).attach();
+        assert_eq!(spawn_with_context(current_value).join().unwrap(), Some(1));
+        assert_eq!(thread::spawn(current_value).join().unwrap(), None);
+    }
+
+    #[test]
+    fn test_spawn_scoped_with_context() {
+        let _guard = Context::current_with_value(Value(2)).attach();
+        thread::scope(|scope| {
+            let handle = spawn_scoped_with_context(scope, current_value);
+            assert_eq!(handle.join().unwrap(), Some(2));
+        });
+    }
+
+    #[test]
+    fn test_propagate() {
+        let f = {
+            let _guard = Context::current_with_value(Value(3)).attach();
+            propagate(|offset: u64| current_value().map(|value| value + offset))
+        };
+        assert_eq!(current_value(), None);
+        assert_eq!(f(1), Some(4));
+        assert_eq!(
+            thread::scope(|scope| scope.spawn(|| f(2)).join().unwrap()),
+            Some(5)
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_context_thread_pool() {
+        let pool = ContextThreadPoolBuilder::new()
+            .num_threads(2)
+            .thread_name(|index| format!("worker-{index}"))
+            .build()
+            .unwrap();
+        let _guard = Context::current_with_value(Value(4)).attach();
+
+        assert_eq!(pool.install(current_value), Some(4));
+        assert_eq!(pool.join(current_value, current_value), (Some(4), Some(4)));
+        assert_eq!(pool.inner().install(current_value), None);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        pool.spawn(move || tx.send(current_value()).unwrap());
+        assert_eq!(rx.recv().unwrap(), Some(4));
+    }
+}
@@ -87,7 +87,8 @@ impl<R: JaegerJsonRuntime> SpanExporter for JaegerJsonExporter<R> {
         let file_name = out_path.join(format!(
             "{}-{}.json",
             file_prefix,
-            SystemTime::now()
+            runtime
+                .now()
                 .duration_since(SystemTime::UNIX_EPOCH)
                 .expect("This does not fail")
                 .as_secs()
@@ -207,6 +208,13 @@ pub trait JaegerJsonRuntime: RuntimeChannel + std::fmt::Debug {
         path: &Path,
         content: &[u8],
     ) -> impl std::future::Future<Output = OTelSdkResult> + Send;
+    /// The current time, naming the written files
+    ///
+    /// Defaults to [`opentelemetry::time::now`], runtimes without a system clock, e.g. in
+    /// WebAssembly hosts, return the time of their host instead.
+    fn now(&self) -> SystemTime {
+        opentelemetry::time::now()
+    }
 }
 
 #[cfg(feature = "rt-tokio")]
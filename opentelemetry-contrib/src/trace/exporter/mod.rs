@@ -6,8 +6,12 @@
 //! Currently, the following exporters are supported:
 //!
 //! * `jaeger_json`, which allows to export traces into files using jaegers json format
+//! * `zipkin_json`, which allows to export traces into files or to a Zipkin collector using the
+//!   Zipkin v2 json format
 //!
 //! This module also provides relative types for those exporters.
 
 #[cfg(feature = "jaeger_json_exporter")]
 pub mod jaeger_json;
+#[cfg(feature = "zipkin_json_exporter")]
+pub mod zipkin_json;
@@ -0,0 +1,499 @@
+//! # Zipkin JSON v2 Exporter
+//!
+//! Exports spans in the [Zipkin v2 JSON format](https://zipkin.io/zipkin-api/#/default/post_spans),
+//! either appended to a file, one JSON list per export and per line, or posted to a Zipkin
+//! compatible collector such as `http://localhost:9411/api/v2/spans`.
+//!
+//! The span mapping follows the [OpenTelemetry Zipkin specification](https://opentelemetry.io/docs/specs/otel/trace/sdk_exporters/zipkin/):
+//!
+//! * `localEndpoint` is derived from the `service.name` and `host.ip` resource attributes,
+//!   unless set on the builder.
+//! * `remoteEndpoint` is derived from the peer attributes (`peer.service`, `server.address`,
+//!   `network.peer.address`, ...) of `CLIENT` and `PRODUCER` spans.
+//! * `INTERNAL` spans have no `kind`, and `SERVER` spans reusing the span ID of their remote
+//!   parent, as created by Zipkin instrumentation joining spans, are marked `shared`.
+//! * Tags set with [`ZipkinJsonExporterBuilder::with_local_tag`] are added to every span,
+//!   span attributes with the same key take precedence.
+use bytes::Bytes;
+use opentelemetry::trace::{SpanId, SpanKind, Status};
+use opentelemetry::{Key, KeyValue, Value};
+use opentelemetry_http::HttpClient;
+use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
+use opentelemetry_sdk::trace::{SpanData, SpanExporter};
+use opentelemetry_sdk::Resource;
+use serde_json::{json, Map};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Attributes naming the remote service, by decreasing priority.
+const REMOTE_SERVICE_KEYS: [&str; 9] = [
+    "peer.service",
+    "server.address",
+    "net.peer.name",
+    "network.peer.address",
+    "net.sock.peer.name",
+    "net.sock.peer.addr",
+    "peer.hostname",
+    "peer.address",
+    "db.name",
+];
+
+/// Attributes holding the remote IP address, by decreasing priority.
+const REMOTE_IP_KEYS: [&str; 3] = ["network.peer.address", "net.sock.peer.addr", "net.peer.ip"];
+
+/// Attributes holding the remote port, by decreasing priority.
+const REMOTE_PORT_KEYS: [&str; 4] = [
+    "server.port",
+    "network.peer.port",
+    "net.peer.port",
+    "net.sock.peer.port",
+];
+
+/// Where the [`ZipkinJsonExporter`] writes spans.
+#[derive(Debug, Clone)]
+pub enum ZipkinJsonSink {
+    /// Append every export as a JSON list on its own line to this file, created if missing.
+    File(PathBuf),
+    /// Post every export to a Zipkin collector, e.g. `http://localhost:9411/api/v2/spans`.
+    Http {
+        /// The collector endpoint.
+        endpoint: http::Uri,
+        /// The client sending the requests.
+        client: Arc<dyn HttpClient>,
+    },
+}
+
+/// Builder for [`ZipkinJsonExporter`].
+#[derive(Debug)]
+pub struct ZipkinJsonExporterBuilder {
+    sink: ZipkinJsonSink,
+    service_name: Option<String>,
+    local_address: Option<SocketAddr>,
+    local_tags: Vec<KeyValue>,
+}
+
+impl ZipkinJsonExporterBuilder {
+    /// Set the `localEndpoint` service name, defaults to the `service.name` resource attribute.
+    pub fn with_service_name(mut self, service_name: impl Into<String>) -> Self {
+        self.service_name = Some(service_name.into());
+        self
+    }
+
+    /// Set the `localEndpoint` address and port, defaults to the first address of the
+    /// `host.ip` resource attribute.
+    pub fn with_local_address(mut self, local_address: SocketAddr) -> Self {
+        self.local_address = Some(local_address);
+        self
+    }
+
+    /// Add a tag to every exported span.
+    pub fn with_local_tag(mut self, tag: KeyValue) -> Self {
+        self.local_tags.push(tag);
+        self
+    }
+
+    /// Build the exporter.
+    pub fn build(self) -> ZipkinJsonExporter {
+        ZipkinJsonExporter {
+            sink: self.sink,
+            service_name: self.service_name,
+            local_ip: self.local_address.map(|address| address.ip()),
+            local_port: self.local_address.map(|address| address.port()),
+            local_tags: self.local_tags,
+        }
+    }
+}
+
+/// An exporter writing spans in the Zipkin v2 JSON format.
+#[derive(Debug)]
+pub struct ZipkinJsonExporter {
+    sink: ZipkinJsonSink,
+    service_name: Option<String>,
+    local_ip: Option<IpAddr>,
+    local_port: Option<u16>,
+    local_tags: Vec<KeyValue>,
+}
+
+impl ZipkinJsonExporter {
+    /// Create a builder for an exporter writing to `sink`.
+    pub fn builder(sink: ZipkinJsonSink) -> ZipkinJsonExporterBuilder {
+        ZipkinJsonExporterBuilder {
+            sink,
+            service_name: None,
+            local_address: None,
+            local_tags: Vec::new(),
+        }
+    }
+
+    fn local_endpoint(&self) -> serde_json::Value {
+        let mut endpoint = Map::new();
+        if let Some(service_name) = &self.service_name {
+            endpoint.insert("serviceName".into(), json!(service_name));
+        }
+        if let Some(ip) = self.local_ip {
+            insert_ip(&mut endpoint, ip);
+        }
+        if let Some(port) = self.local_port {
+            endpoint.insert("port".into(), json!(port));
+        }
+        endpoint.into()
+    }
+
+    fn span_to_json(
+        &self,
+        span: &SpanData,
+        local_endpoint: &serde_json::Value,
+    ) -> serde_json::Value {
+        let mut zipkin = Map::new();
+        zipkin.insert(
+            "traceId".into(),
+            json!(span.span_context.trace_id().to_string()),
+        );
+        zipkin.insert("id".into(), json!(span.span_context.span_id().to_string()));
+
+        let shared = span.span_kind == SpanKind::Server
+            && span.parent_span_is_remote
+            && span.parent_span_id == span.span_context.span_id();
+        if span.parent_span_id != SpanId::INVALID && !shared {
+            zipkin.insert("parentId".into(), json!(span.parent_span_id.to_string()));
+        }
+        zipkin.insert("name".into(), json!(span.name));
+        if let Some(kind) = kind(&span.span_kind) {
+            zipkin.insert("kind".into(), json!(kind));
+        }
+        zipkin.insert("timestamp".into(), json!(micros(span.start_time)));
+        let duration = span
+            .end_time
+            .duration_since(span.start_time)
+            .map_or(0, |duration| duration.as_micros() as u64);
+        zipkin.insert("duration".into(), json!(duration.max(1)));
+        zipkin.insert("localEndpoint".into(), local_endpoint.clone());
+        if matches!(span.span_kind, SpanKind::Client | SpanKind::Producer) {
+            if let Some(remote_endpoint) = remote_endpoint(&span.attributes) {
+                zipkin.insert("remoteEndpoint".into(), remote_endpoint);
+            }
+        }
+        if !span.events.is_empty() {
+            let annotations = span
+                .events
+                .iter()
+                .map(|event| {
+                    let value = if event.attributes.is_empty() {
+                        event.name.to_string()
+                    } else {
+                        let attributes: Map<String, serde_json::Value> = event
+                            .attributes
+                            .iter()
+                            .map(|kv| (kv.key.to_string(), json!(kv.value.to_string())))
+                            .collect();
+                        format!("\"{}\":{}", event.name, serde_json::Value::from(attributes))
+                    };
+                    json!({ "timestamp": micros(event.timestamp), "value": value })
+                })
+                .collect::<Vec<_>>();
+            zipkin.insert("annotations".into(), annotations.into());
+        }
+        if shared {
+            zipkin.insert("shared".into(), json!(true));
+        }
+
+        let mut tags = Map::new();
+        for kv in self.local_tags.iter().chain(span.attributes.iter()) {
+            tags.insert(kv.key.to_string(), json!(kv.value.to_string()));
+        }
+        let scope = &span.instrumentation_scope;
+        if !scope.name().is_empty() {
+            tags.insert("otel.scope.name".into(), json!(scope.name()));
+        }
+        if let Some(version) = scope.version() {
+            tags.insert("otel.scope.version".into(), json!(version));
+        }
+        match &span.status {
+            Status::Unset => {}
+            Status::Ok => {
+                tags.insert("otel.status_code".into(), json!("OK"));
+            }
+            Status::Error { description } => {
+                tags.insert("otel.status_code".into(), json!("ERROR"));
+                tags.insert("error".into(), json!(description.to_string()));
+            }
+        }
+        if !tags.is_empty() {
+            zipkin.insert("tags".into(), tags.into());
+        }
+
+        zipkin.into()
+    }
+
+    fn write_to_file(path: &Path, mut body: Vec<u8>) -> OTelSdkResult {
+        body.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(&body))
+            .map_err(|e| OTelSdkError::InternalFailure(format!("{e:?}")))
+    }
+
+    async fn post(endpoint: &http::Uri, client: &dyn HttpClient, body: Vec<u8>) -> OTelSdkResult {
+        let request = http::Request::post(endpoint)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(Bytes::from(body))
+            .map_err(|e| OTelSdkError::InternalFailure(format!("{e:?}")))?;
+        let response = client
+            .send_bytes(request)
+            .await
+            .map_err(|e| OTelSdkError::InternalFailure(format!("HTTP request failed: {e}")))?;
+        if !response.status().is_success() {
+            return Err(OTelSdkError::InternalFailure(format!(
+                "HTTP response error: {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl SpanExporter for ZipkinJsonExporter {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        let local_endpoint = self.local_endpoint();
+        let spans = batch
+            .iter()
+            .map(|span| self.span_to_json(span, &local_endpoint))
+            .collect::<Vec<_>>();
+        let body = serde_json::to_vec(&spans).expect("This is a valid json value");
+
+        match &self.sink {
+            ZipkinJsonSink::File(path) => Self::write_to_file(path, body),
+            ZipkinJsonSink::Http { endpoint, client } => {
+                Self::post(endpoint, client.as_ref(), body).await
+            }
+        }
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        if self.service_name.is_none() {
+            self.service_name = resource
+                .get(&Key::from_static_str("service.name"))
+                .map(|name| name.to_string());
+        }
+        if self.local_ip.is_none() {
+            self.local_ip = resource
+                .get(&Key::from_static_str("host.ip"))
+                .and_then(|value| first_ip(&value));
+        }
+    }
+}
+
+fn kind(span_kind: &SpanKind) -> Option<&'static str> {
+    match span_kind {
+        SpanKind::Client => Some("CLIENT"),
+        SpanKind::Server => Some("SERVER"),
+        SpanKind::Producer => Some("PRODUCER"),
+        SpanKind::Consumer => Some("CONSUMER"),
+        SpanKind::Internal => None,
+    }
+}
+
+fn micros(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_micros() as u64)
+}
+
+fn insert_ip(endpoint: &mut Map<String, serde_json::Value>, ip: IpAddr) {
+    match ip {
+        IpAddr::V4(ip) => endpoint.insert("ipv4".into(), json!(ip.to_string())),
+        IpAddr::V6(ip) => endpoint.insert("ipv6".into(), json!(ip.to_string())),
+    };
+}
+
+/// The first IP address of a string or string array value.
+fn first_ip(value: &Value) -> Option<IpAddr> {
+    match value {
+        Value::String(s) => s.as_str().parse().ok(),
+        Value::Array(opentelemetry::Array::String(values)) => {
+            values.iter().find_map(|s| s.as_str().parse().ok())
+        }
+        _ => None,
+    }
+}
+
+fn find_attribute<'a>(attributes: &'a [KeyValue], keys: &[&str]) -> Option<&'a Value> {
+    keys.iter().find_map(|key| {
+        attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == *key)
+            .map(|kv| &kv.value)
+    })
+}
+
+fn remote_endpoint(attributes: &[KeyValue]) -> Option<serde_json::Value> {
+    let mut endpoint = Map::new();
+    if let Some(service_name) = find_attribute(attributes, &REMOTE_SERVICE_KEYS) {
+        endpoint.insert("serviceName".into(), json!(service_name.to_string()));
+    }
+    if let Some(ip) = find_attribute(attributes, &REMOTE_IP_KEYS).and_then(first_ip) {
+        insert_ip(&mut endpoint, ip);
+    }
+    let port = find_attribute(attributes, &REMOTE_PORT_KEYS).and_then(|port| match port {
+        Value::I64(port) => u16::try_from(*port).ok(),
+        Value::String(port) => port.as_str().parse().ok(),
+        _ => None,
+    });
+    if let Some(port) = port {
+        endpoint.insert("port".into(), json!(port));
+    }
+    (!endpoint.is_empty()).then(|| endpoint.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{SpanContext, TraceFlags, TraceId, TraceState};
+    use opentelemetry::InstrumentationScope;
+    use opentelemetry_sdk::trace::{SpanEvents, SpanLinks};
+    use std::time::Duration;
+
+    fn span(span_kind: SpanKind, attributes: Vec<KeyValue>) -> SpanData {
+        let start_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+        SpanData {
+            span_context: SpanContext::new(
+                TraceId::from(1),
+                SpanId::from(2),
+                TraceFlags::SAMPLED,
+                false,
+                TraceState::default(),
+            ),
+            parent_span_id: SpanId::from(1),
+            parent_span_is_remote: false,
+            span_kind,
+            name: "request".into(),
+            start_time,
+            end_time: start_time + Duration::from_millis(5),
+            attributes,
+            dropped_attributes_count: 0,
+            events: SpanEvents::default(),
+            links: SpanLinks::default(),
+            status: Status::Unset,
+            instrumentation_scope: InstrumentationScope::builder("component").build(),
+        }
+    }
+
+    fn exporter(sink: ZipkinJsonSink) -> ZipkinJsonExporter {
+        let mut exporter = ZipkinJsonExporter::builder(sink)
+            .with_local_tag(KeyValue::new("deployment", "blue"))
+            .build();
+        exporter.set_resource(
+            &Resource::builder_empty()
+                .with_service_name("my-service")
+                .with_attribute(KeyValue::new("host.ip", "10.0.0.1"))
+                .build(),
+        );
+        exporter
+    }
+
+    #[test]
+    fn test_client_span() {
+        let exporter = exporter(ZipkinJsonSink::File(PathBuf::new()));
+        let span = span(
+            SpanKind::Client,
+            vec![
+                KeyValue::new("server.address", "db.example.com"),
+                KeyValue::new("network.peer.address", "10.0.0.2"),
+                KeyValue::new("server.port", 5432),
+                KeyValue::new("deployment", "green"),
+            ],
+        );
+
+        let json = exporter.span_to_json(&span, &exporter.local_endpoint());
+        assert_eq!(
+            json,
+            json!({
+                "traceId": "00000000000000000000000000000001",
+                "id": "0000000000000002",
+                "parentId": "0000000000000001",
+                "name": "request",
+                "kind": "CLIENT",
+                "timestamp": 1_000_000,
+                "duration": 5_000,
+                "localEndpoint": { "serviceName": "my-service", "ipv4": "10.0.0.1" },
+                "remoteEndpoint": {
+                    "serviceName": "db.example.com",
+                    "ipv4": "10.0.0.2",
+                    "port": 5432,
+                },
+                "tags": {
+                    "server.address": "db.example.com",
+                    "network.peer.address": "10.0.0.2",
+                    "server.port": "5432",
+                    "deployment": "green",
+                    "otel.scope.name": "component",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_server_span() {
+        let exporter = exporter(ZipkinJsonSink::File(PathBuf::new()));
+        let mut span = span(
+            SpanKind::Server,
+            vec![KeyValue::new("server.address", "api.example.com")],
+        );
+        span.status = Status::error("boom");
+
+        let json = exporter.span_to_json(&span, &exporter.local_endpoint());
+        assert_eq!(json["kind"], "SERVER");
+        assert!(json.get("remoteEndpoint").is_none());
+        assert!(json.get("shared").is_none());
+        assert_eq!(json["tags"]["deployment"], "blue");
+        assert_eq!(json["tags"]["otel.status_code"], "ERROR");
+        assert_eq!(json["tags"]["error"], "boom");
+
+        span.parent_span_id = span.span_context.span_id();
+        span.parent_span_is_remote = true;
+        let json = exporter.span_to_json(&span, &exporter.local_endpoint());
+        assert_eq!(json["shared"], true);
+        assert!(json.get("parentId").is_none());
+    }
+
+    #[test]
+    fn test_internal_span() {
+        let exporter = ZipkinJsonExporter::builder(ZipkinJsonSink::File(PathBuf::new()))
+            .with_service_name("configured")
+            .with_local_address("[::1]:8080".parse().unwrap())
+            .build();
+        let span = span(SpanKind::Internal, vec![]);
+
+        let json = exporter.span_to_json(&span, &exporter.local_endpoint());
+        assert!(json.get("kind").is_none());
+        assert_eq!(
+            json["localEndpoint"],
+            json!({ "serviceName": "configured", "ipv6": "::1", "port": 8080 })
+        );
+    }
+
+    #[test]
+    fn test_export_to_file() {
+        let path = std::env::temp_dir().join(format!("zipkin-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let exporter = exporter(ZipkinJsonSink::File(path.clone()));
+
+        futures_executor::block_on(exporter.export(vec![span(SpanKind::Client, vec![])])).unwrap();
+        futures_executor::block_on(exporter.export(vec![span(SpanKind::Server, vec![])])).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0][0]["kind"], "CLIENT");
+        assert_eq!(lines[1][0]["kind"], "SERVER");
+    }
+}
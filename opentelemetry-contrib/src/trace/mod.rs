@@ -7,7 +7,9 @@ mod context;
 pub use context::{new_span_if_parent_sampled, new_span_if_recording, Contextualized};
 
 pub mod exporter;
+pub mod processor;
 pub mod propagator;
+pub mod sampler;
 
 #[cfg(feature = "api")]
 mod tracer_source;
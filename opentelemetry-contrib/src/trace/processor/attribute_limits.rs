@@ -0,0 +1,311 @@
+//! # Attribute Limit Processor
+//!
+//! Enforces limits on the number and length of the attributes of spans before they are
+//! exported, for backends rejecting or silently cutting larger values, e.g. the 25,000
+//! characters of Datadog tag values or the annotation limits of AWS X-Ray.
+//!
+//! Unlike the [`SpanLimits`] of the SDK, which apply to every processor as attributes are
+//! recorded, these limits only apply to the spans exported by the wrapped processor, and mark
+//! what they changed:
+//!
+//! * String values longer than the limit are truncated and end with the truncation marker,
+//!   `...` by default, which is counted in the limit.
+//! * Attributes over the limit are dropped, counted in the `dropped_attributes_count` of the
+//!   span, event or link, and spans carry the number of attributes dropped by the processor
+//!   as the `otel.dropped_attributes_count` attribute, which is counted in the limit. With a
+//!   limit of 0, spans carry no attributes at all, not even this one.
+//!
+//! ```no_run
+//! use opentelemetry_contrib::trace::processor::attribute_limits::{
+//!     AttributeLimitProcessor, AttributeLimits,
+//! };
+//! use opentelemetry_sdk::trace::{SdkTracerProvider, SimpleSpanProcessor};
+//! # use opentelemetry_sdk::trace::InMemorySpanExporter;
+//! # let exporter = InMemorySpanExporter::default();
+//!
+//! let limits = AttributeLimits::default()
+//!     .with_max_attribute_count(64)
+//!     .with_max_value_length(25_000);
+//! let provider = SdkTracerProvider::builder()
+//!     .with_span_processor(AttributeLimitProcessor::new(
+//!         SimpleSpanProcessor::new(exporter),
+//!         limits,
+//!     ))
+//!     .build();
+//! ```
+//!
+//! [`SpanLimits`]: opentelemetry_sdk::trace::SpanLimits
+use opentelemetry::{Array, Context, KeyValue, StringValue, Value};
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::trace::{Span, SpanData, SpanProcessor};
+use opentelemetry_sdk::Resource;
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// Attribute holding the number of span attributes dropped by the processor.
+pub const DROPPED_ATTRIBUTES_COUNT: &str = "otel.dropped_attributes_count";
+
+const DEFAULT_TRUNCATION_MARKER: &str = "...";
+
+/// Limits on the attributes of spans, and of their events and links.
+#[derive(Clone, Debug)]
+pub struct AttributeLimits {
+    max_attribute_count: Option<usize>,
+    max_value_length: Option<usize>,
+    truncation_marker: Cow<'static, str>,
+}
+
+impl Default for AttributeLimits {
+    fn default() -> Self {
+        AttributeLimits {
+            max_attribute_count: None,
+            max_value_length: None,
+            truncation_marker: Cow::Borrowed(DEFAULT_TRUNCATION_MARKER),
+        }
+    }
+}
+
+impl AttributeLimits {
+    /// Keep at most `max_attribute_count` attributes, unlimited by default.
+    pub fn with_max_attribute_count(mut self, max_attribute_count: usize) -> Self {
+        self.max_attribute_count = Some(max_attribute_count);
+        self
+    }
+
+    /// Truncate string values, and the strings of arrays, to at most `max_value_length`
+    /// characters, unlimited by default.
+    pub fn with_max_value_length(mut self, max_value_length: usize) -> Self {
+        self.max_value_length = Some(max_value_length);
+        self
+    }
+
+    /// End truncated values with `truncation_marker`, `...` by default.
+    pub fn with_truncation_marker(
+        mut self,
+        truncation_marker: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.truncation_marker = truncation_marker.into();
+        self
+    }
+
+    /// Apply the limits to the attributes of `span`, and of its events and links.
+    pub fn apply(&self, span: &mut SpanData) {
+        let dropped = self.limit(&mut span.attributes, true);
+        span.dropped_attributes_count += dropped;
+        if dropped > 0 && self.max_attribute_count != Some(0) {
+            span.attributes
+                .push(KeyValue::new(DROPPED_ATTRIBUTES_COUNT, i64::from(dropped)));
+        }
+        for event in span.events.events.iter_mut() {
+            event.dropped_attributes_count += self.limit(&mut event.attributes, false);
+        }
+        for link in span.links.links.iter_mut() {
+            link.dropped_attributes_count += self.limit(&mut link.attributes, false);
+        }
+    }
+
+    /// Truncate the values of `attributes` and drop the attributes over the limit, keeping a
+    /// slot for the dropped count if `reserve_marker`. Returns the number of dropped attributes.
+    fn limit(&self, attributes: &mut Vec<KeyValue>, reserve_marker: bool) -> u32 {
+        let mut dropped = 0;
+        if let Some(max) = self.max_attribute_count {
+            if attributes.len() > max {
+                let keep = if reserve_marker {
+                    max.saturating_sub(1)
+                } else {
+                    max
+                };
+                dropped = (attributes.len() - keep) as u32;
+                attributes.truncate(keep);
+            }
+        }
+        if let Some(max_length) = self.max_value_length {
+            for kv in attributes.iter_mut() {
+                self.truncate_value(&mut kv.value, max_length);
+            }
+        }
+        dropped
+    }
+
+    fn truncate_value(&self, value: &mut Value, max_length: usize) {
+        match value {
+            Value::String(s) => {
+                if let Some(truncated) = self.truncate(s.as_str(), max_length) {
+                    *s = truncated.into();
+                }
+            }
+            Value::Array(Array::String(values)) => {
+                for s in values.iter_mut() {
+                    if let Some(truncated) = self.truncate(s.as_str(), max_length) {
+                        *s = StringValue::from(truncated);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// `s` cut to `max_length` characters including the marker, if it is longer.
+    fn truncate(&self, s: &str, max_length: usize) -> Option<String> {
+        let (cut, _) = s.char_indices().nth(max_length)?;
+        let marker_length = self.truncation_marker.chars().count();
+        if marker_length >= max_length {
+            // No room for the marker.
+            return Some(s[..cut].to_string());
+        }
+        let (cut, _) = s.char_indices().nth(max_length - marker_length)?;
+        Some(format!("{}{}", &s[..cut], self.truncation_marker))
+    }
+}
+
+/// Applies [`AttributeLimits`] to the spans before passing them to the wrapped processor.
+#[derive(Debug)]
+pub struct AttributeLimitProcessor<P> {
+    inner: P,
+    limits: AttributeLimits,
+}
+
+impl<P: SpanProcessor> AttributeLimitProcessor<P> {
+    /// Apply `limits` to the spans ended, before `inner` processes them.
+    pub fn new(inner: P, limits: AttributeLimits) -> Self {
+        AttributeLimitProcessor { inner, limits }
+    }
+}
+
+impl<P: SpanProcessor> SpanProcessor for AttributeLimitProcessor<P> {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        self.inner.on_start(span, cx);
+    }
+
+    fn on_end(&self, mut span: SpanData) {
+        self.limits.apply(&mut span);
+        self.inner.on_end(span);
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn shutdown(&self) -> OTelSdkResult {
+        self.inner.shutdown()
+    }
+
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        self.inner.shutdown_with_timeout(timeout)
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{Event, SpanContext, SpanId, SpanKind, Status};
+    use opentelemetry::InstrumentationScope;
+    use opentelemetry_sdk::trace::{SpanEvents, SpanLinks};
+    use std::time::SystemTime;
+
+    fn span(attributes: Vec<KeyValue>) -> SpanData {
+        SpanData {
+            span_context: SpanContext::empty_context(),
+            parent_span_id: SpanId::INVALID,
+            parent_span_is_remote: false,
+            span_kind: SpanKind::Internal,
+            name: "span".into(),
+            start_time: SystemTime::UNIX_EPOCH,
+            end_time: SystemTime::UNIX_EPOCH,
+            attributes,
+            dropped_attributes_count: 0,
+            events: SpanEvents::default(),
+            links: SpanLinks::default(),
+            status: Status::Unset,
+            instrumentation_scope: InstrumentationScope::builder("component").build(),
+        }
+    }
+
+    #[test]
+    fn test_truncate_values() {
+        let limits = AttributeLimits::default().with_max_value_length(6);
+        let mut span = span(vec![
+            KeyValue::new("short", "abcdef"),
+            KeyValue::new("long", "abcdefgh"),
+            KeyValue::new("unicode", "éééééééé"),
+            KeyValue::new(
+                "array",
+                Value::Array(Array::String(vec!["a".into(), "abcdefgh".into()])),
+            ),
+            KeyValue::new("int", 123_456_789i64),
+        ]);
+        limits.apply(&mut span);
+
+        assert_eq!(span.attributes[0].value, Value::from("abcdef"));
+        assert_eq!(span.attributes[1].value, Value::from("abc..."));
+        assert_eq!(span.attributes[2].value, Value::from("ééé..."));
+        assert_eq!(
+            span.attributes[3].value,
+            Value::Array(Array::String(vec!["a".into(), "abc...".into()]))
+        );
+        assert_eq!(span.attributes[4].value, Value::I64(123_456_789));
+        assert_eq!(span.dropped_attributes_count, 0);
+    }
+
+    #[test]
+    fn test_truncation_marker_longer_than_limit() {
+        let limits = AttributeLimits::default()
+            .with_max_value_length(2)
+            .with_truncation_marker("[truncated]");
+        let mut span = span(vec![KeyValue::new("long", "abcdef")]);
+        limits.apply(&mut span);
+        assert_eq!(span.attributes[0].value, Value::from("ab"));
+    }
+
+    #[test]
+    fn test_drop_attributes() {
+        let limits = AttributeLimits::default().with_max_attribute_count(3);
+        let mut span = span(
+            (0..5i64)
+                .map(|i| KeyValue::new(format!("key{i}"), i))
+                .collect(),
+        );
+        span.dropped_attributes_count = 1;
+        let mut event = Event::with_name("event");
+        event.attributes = (0..4i64)
+            .map(|i| KeyValue::new(format!("key{i}"), i))
+            .collect();
+        span.events.events.push(event);
+        limits.apply(&mut span);
+
+        assert_eq!(span.attributes.len(), 3);
+        assert_eq!(span.attributes[1].key.as_str(), "key1");
+        assert_eq!(
+            span.attributes[2],
+            KeyValue::new(DROPPED_ATTRIBUTES_COUNT, 3i64)
+        );
+        assert_eq!(span.dropped_attributes_count, 4);
+        assert_eq!(span.events.events[0].attributes.len(), 3);
+        assert_eq!(span.events.events[0].dropped_attributes_count, 1);
+    }
+
+    #[test]
+    fn test_drop_all_attributes() {
+        let limits = AttributeLimits::default().with_max_attribute_count(0);
+        let mut span = span(vec![KeyValue::new("a", "value"), KeyValue::new("b", true)]);
+        limits.apply(&mut span);
+        assert!(span.attributes.is_empty());
+        assert_eq!(span.dropped_attributes_count, 2);
+    }
+
+    #[test]
+    fn test_within_limits() {
+        let limits = AttributeLimits::default()
+            .with_max_attribute_count(2)
+            .with_max_value_length(10);
+        let attributes = vec![KeyValue::new("a", "value"), KeyValue::new("b", true)];
+        let mut span = span(attributes.clone());
+        limits.apply(&mut span);
+        assert_eq!(span.attributes, attributes);
+        assert_eq!(span.dropped_attributes_count, 0);
+    }
+}
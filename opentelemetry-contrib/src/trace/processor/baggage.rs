@@ -0,0 +1,97 @@
+//! # Baggage Span Processor
+//!
+//! Copies selected baggage entries of the parent context to the attributes of spans as they
+//! start, like the baggage span processors of the Java and JavaScript contrib repositories, so
+//! markers such as a tenant id or a debug flag set once in the baggage are found on every span
+//! without changing each instrumentation.
+//!
+//! Only the allowed keys are copied, as baggage is received from upstream services and may
+//! carry sensitive or unbounded values.
+//!
+//! ```no_run
+//! use opentelemetry_contrib::trace::processor::baggage::BaggageSpanProcessor;
+//! use opentelemetry_sdk::trace::SdkTracerProvider;
+//! # use opentelemetry_sdk::trace::InMemorySpanExporter;
+//! # let exporter = InMemorySpanExporter::default();
+//!
+//! let provider = SdkTracerProvider::builder()
+//!     .with_span_processor(BaggageSpanProcessor::new(["tenant.id", "debug"]))
+//!     .with_simple_exporter(exporter)
+//!     .build();
+//! ```
+use opentelemetry::baggage::BaggageExt;
+use opentelemetry::trace::Span as _;
+use opentelemetry::{Context, Key, KeyValue};
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::trace::{Span, SpanData, SpanProcessor};
+use std::time::Duration;
+
+/// Sets the allowed baggage entries of the parent context as attributes of the spans started.
+///
+/// The attributes are set when the span starts, so the sampler, and processors added before this
+/// one, do not see them in `on_start`.
+#[derive(Clone, Debug)]
+pub struct BaggageSpanProcessor {
+    keys: Vec<Key>,
+}
+
+impl BaggageSpanProcessor {
+    /// Copy the baggage entries with one of `keys` to the span attributes with the same key.
+    pub fn new<K: Into<Key>>(keys: impl IntoIterator<Item = K>) -> Self {
+        BaggageSpanProcessor {
+            keys: keys.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl SpanProcessor for BaggageSpanProcessor {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        let baggage = cx.baggage();
+        for key in &self.keys {
+            if let Some(value) = baggage.get(key.as_str()) {
+                span.set_attribute(KeyValue::new(key.clone(), value.clone()));
+            }
+        }
+    }
+
+    fn on_end(&self, _span: SpanData) {}
+
+    fn force_flush(&self) -> OTelSdkResult {
+        Ok(())
+    }
+
+    fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{Tracer, TracerProvider};
+    use opentelemetry::Value;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+
+    #[test]
+    fn test_copy_allowed_baggage() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_span_processor(BaggageSpanProcessor::new(["tenant.id", "debug"]))
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let tracer = provider.tracer("test");
+
+        let cx = Context::new().with_baggage([
+            KeyValue::new("tenant.id", "acme"),
+            KeyValue::new("user.email", "someone@example.com"),
+        ]);
+        tracer.start_with_context("span", &cx).end();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(
+            spans[0].attributes,
+            vec![KeyValue::new("tenant.id", Value::from("acme"))]
+        );
+    }
+}
@@ -0,0 +1,17 @@
+//! # Opentelemetry span processor contrib
+//!
+//! This module provides span processors preparing spans for export, wrapping the processor
+//! exporting them or added before it.
+//!
+//! Currently, the following processors are supported:
+//!
+//! * `attribute_limits`, which enforces the attribute limits of backends on the exported spans
+//! * `baggage`, which copies selected baggage entries to the attributes of the started spans
+//! * `span_events`, which emits the events of the ended spans as log records
+
+#[cfg(feature = "attribute_limit_processor")]
+pub mod attribute_limits;
+#[cfg(feature = "baggage_span_processor")]
+pub mod baggage;
+#[cfg(feature = "event_to_log_processor")]
+pub mod span_events;
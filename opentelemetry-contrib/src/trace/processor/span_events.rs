@@ -0,0 +1,223 @@
+//! # Event To Log Processor
+//!
+//! Emits the events of the ended spans, such as the `exception` events recorded by
+//! `Span::record_error`, as log records correlated with their span, so backends storing logs
+//! and traces separately have the exception details in the log store too.
+//!
+//! ```no_run
+//! use opentelemetry::logs::LoggerProvider;
+//! use opentelemetry_contrib::trace::processor::span_events::EventToLogProcessor;
+//! use opentelemetry_sdk::logs::SdkLoggerProvider;
+//! use opentelemetry_sdk::trace::SdkTracerProvider;
+//!
+//! let logger_provider = SdkLoggerProvider::builder().build();
+//! let tracer_provider = SdkTracerProvider::builder()
+//!     .with_span_processor(
+//!         EventToLogProcessor::new(logger_provider.logger("span-events"))
+//!             .with_event_names(["exception"]),
+//!     )
+//!     .build();
+//! ```
+use opentelemetry::logs::{AnyValue, LogRecord, Logger, Severity};
+use opentelemetry::{Array, Context, Value};
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::trace::{Span, SpanData, SpanProcessor};
+use std::borrow::Cow;
+use std::fmt;
+use std::time::Duration;
+
+const EXCEPTION_EVENT_NAME: &str = "exception";
+const EXCEPTION_MESSAGE: &str = "exception.message";
+
+/// Emits the events of the ended spans as log records with the trace context of their span.
+///
+/// The log records have the timestamp, name and attributes of the event. `exception` events
+/// are [`Severity::Error`] records whose body is their `exception.message` attribute, other
+/// events are [`Severity::Info`] records whose body is their name.
+///
+/// The records are emitted when the span ends, in the order of the events, and only for the
+/// spans which are recorded.
+pub struct EventToLogProcessor<L> {
+    logger: L,
+    event_names: Option<Vec<Cow<'static, str>>>,
+}
+
+impl<L: Logger> EventToLogProcessor<L> {
+    /// Emit the events of every span with `logger`.
+    pub fn new(logger: L) -> Self {
+        EventToLogProcessor {
+            logger,
+            event_names: None,
+        }
+    }
+
+    /// Only emit the events named one of `names`, e.g. `exception`.
+    pub fn with_event_names<N>(mut self, names: impl IntoIterator<Item = N>) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        self.event_names = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    fn emit(&self, span: &SpanData) {
+        let span_context = &span.span_context;
+        for event in span.events.iter() {
+            if let Some(names) = &self.event_names {
+                if !names.contains(&event.name) {
+                    continue;
+                }
+            }
+
+            let mut record = self.logger.create_log_record();
+            record.set_timestamp(event.timestamp);
+            record.set_observed_timestamp(event.timestamp);
+            record.set_trace_context(
+                span_context.trace_id(),
+                span_context.span_id(),
+                Some(span_context.trace_flags()),
+            );
+            if let Cow::Borrowed(name) = event.name {
+                record.set_event_name(name);
+            }
+
+            let body = if event.name == EXCEPTION_EVENT_NAME {
+                record.set_severity_number(Severity::Error);
+                record.set_severity_text("ERROR");
+                event
+                    .attributes
+                    .iter()
+                    .find(|kv| kv.key.as_str() == EXCEPTION_MESSAGE)
+                    .map(|kv| kv.value.to_string())
+            } else {
+                record.set_severity_number(Severity::Info);
+                record.set_severity_text("INFO");
+                None
+            };
+            record.set_body(AnyValue::from(
+                body.unwrap_or_else(|| event.name.to_string()),
+            ));
+            record.add_attributes(
+                event
+                    .attributes
+                    .iter()
+                    .map(|kv| (kv.key.clone(), any_value(&kv.value))),
+            );
+            self.logger.emit(record);
+        }
+    }
+}
+
+/// The log attribute value of a span attribute value.
+fn any_value(value: &Value) -> AnyValue {
+    fn list<T: Clone + Into<AnyValue>>(values: &[T]) -> AnyValue {
+        AnyValue::ListAny(Box::new(values.iter().cloned().map(Into::into).collect()))
+    }
+
+    match value {
+        Value::Bool(value) => AnyValue::Boolean(*value),
+        Value::I64(value) => AnyValue::Int(*value),
+        Value::F64(value) => AnyValue::Double(*value),
+        Value::String(value) => AnyValue::String(value.clone()),
+        Value::Array(Array::Bool(values)) => list(values),
+        Value::Array(Array::I64(values)) => list(values),
+        Value::Array(Array::F64(values)) => list(values),
+        Value::Array(Array::String(values)) => list(values),
+        _ => AnyValue::String(value.to_string().into()),
+    }
+}
+
+impl<L> fmt::Debug for EventToLogProcessor<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventToLogProcessor")
+            .field("event_names", &self.event_names)
+            .finish()
+    }
+}
+
+impl<L: Logger + Send + Sync> SpanProcessor for EventToLogProcessor<L> {
+    fn on_start(&self, _span: &mut Span, _cx: &Context) {}
+
+    fn on_end(&self, span: SpanData) {
+        self.emit(&span);
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        Ok(())
+    }
+
+    fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::logs::LoggerProvider;
+    use opentelemetry::trace::{Span as _, TraceContextExt, Tracer, TracerProvider};
+    use opentelemetry::KeyValue;
+    use opentelemetry_sdk::logs::{InMemoryLogExporter, SdkLoggerProvider, SimpleLogProcessor};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+
+    #[test]
+    fn test_events_to_logs() {
+        let exporter = InMemoryLogExporter::default();
+        let logger_provider = SdkLoggerProvider::builder()
+            .with_log_processor(SimpleLogProcessor::new(exporter.clone()))
+            .build();
+        let tracer_provider = SdkTracerProvider::builder()
+            .with_span_processor(
+                EventToLogProcessor::new(logger_provider.logger("span-events"))
+                    .with_event_names(["exception", "retry"]),
+            )
+            .build();
+        let tracer = tracer_provider.tracer("test");
+
+        let mut span = tracer.start("operation");
+        let span_context = span.span_context().clone();
+        span.add_event("cache.miss", vec![]);
+        span.add_event(
+            "exception",
+            vec![
+                KeyValue::new("exception.type", "io::Error"),
+                KeyValue::new("exception.message", "connection reset"),
+            ],
+        );
+        span.add_event("retry", vec![KeyValue::new("attempt", 2)]);
+        span.end();
+
+        let logs = exporter.get_emitted_logs().unwrap();
+        assert_eq!(logs.len(), 2);
+
+        let exception = &logs[0].record;
+        assert_eq!(exception.event_name(), Some("exception"));
+        assert_eq!(exception.severity_number(), Some(Severity::Error));
+        assert_eq!(
+            exception.body(),
+            Some(&AnyValue::from("connection reset".to_string()))
+        );
+        let trace_context = exception.trace_context().unwrap();
+        assert_eq!(trace_context.trace_id, span_context.trace_id());
+        assert_eq!(trace_context.span_id, span_context.span_id());
+        assert_eq!(exception.attributes_iter().count(), 2);
+
+        let retry = &logs[1].record;
+        assert_eq!(retry.severity_number(), Some(Severity::Info));
+        assert_eq!(retry.body(), Some(&AnyValue::from("retry".to_string())));
+        assert!(retry
+            .attributes_iter()
+            .any(|(key, value)| key.as_str() == "attempt" && *value == AnyValue::Int(2)));
+
+        // Spans started in the context of a span keep its trace.
+        let cx = Context::current_with_span(tracer.start("parent"));
+        let mut child = tracer.start_with_context("child", &cx);
+        child.add_event("retry", vec![]);
+        child.end();
+        let logs = exporter.get_emitted_logs().unwrap();
+        assert_eq!(
+            logs[2].record.trace_context().unwrap().trace_id,
+            cx.span().span_context().trace_id()
+        );
+    }
+}
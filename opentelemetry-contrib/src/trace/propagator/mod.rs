@@ -6,7 +6,11 @@
 //! Currently, the following propagators are supported:
 //!
 //! * `binary_propagator`, propagating trace context in the binary format.
+//! * `opencensus`, propagating trace context in the `grpc-trace-bin` and `X-Cloud-Trace-Context`
+//!   headers of OpenCensus.
 //!
 //! This module also provides relative types for those propagators.
 pub mod binary;
+#[cfg(feature = "opencensus_propagator")]
+pub mod opencensus;
 pub mod trace_context_response;
@@ -0,0 +1,287 @@
+//! # OpenCensus Propagator
+//!
+//! Conversions between the span context encodings of OpenCensus and [`SpanContext`], and a
+//! propagator bridging them, so services instrumented with OpenTelemetry continue the traces of
+//! services and agents still running OpenCensus, and the other way around.
+//!
+//! OpenCensus propagates the span context in two forms:
+//!
+//! - the binary format, sent by gRPC in the `grpc-trace-bin` metadata, base64 encoded on the
+//!   wire, see [`from_binary`] and [`to_binary`],
+//! - the `X-Cloud-Trace-Context` HTTP header, `TRACE_ID/SPAN_ID;o=OPTIONS` with a decimal span
+//!   id, see [`from_cloud_trace_context`] and [`to_cloud_trace_context`].
+//!
+//! [`OpenCensusPropagator`] extracts either header, preferring `grpc-trace-bin`, and injects
+//! both by default. Compose it with the W3C propagator during the migration:
+//!
+//! ```
+//! use opentelemetry::global;
+//! use opentelemetry::propagation::TextMapCompositePropagator;
+//! use opentelemetry_contrib::trace::propagator::opencensus::OpenCensusPropagator;
+//! use opentelemetry_sdk::propagation::TraceContextPropagator;
+//!
+//! global::set_text_map_propagator(TextMapCompositePropagator::new(vec![
+//!     Box::new(TraceContextPropagator::new()),
+//!     Box::new(OpenCensusPropagator::new().with_cloud_trace_context(false)),
+//! ]));
+//! ```
+use crate::trace::propagator::binary::{BinaryFormat, BinaryPropagator};
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use base64::Engine;
+use opentelemetry::propagation::text_map_propagator::FieldIter;
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState};
+use opentelemetry::Context;
+
+/// The gRPC metadata key of the binary span context.
+pub const GRPC_TRACE_BIN_HEADER: &str = "grpc-trace-bin";
+/// The HTTP header of the Google Cloud Trace span context.
+pub const CLOUD_TRACE_CONTEXT_HEADER: &str = "x-cloud-trace-context";
+
+/// The span context of the OpenCensus binary format, if valid.
+pub fn from_binary(bytes: &[u8]) -> Option<SpanContext> {
+    Some(BinaryPropagator::new().deserialize_from_bytes(bytes)).filter(SpanContext::is_valid)
+}
+
+/// The OpenCensus binary format of `span_context`, if valid.
+pub fn to_binary(span_context: &SpanContext) -> Option<[u8; 29]> {
+    if !span_context.is_valid() {
+        return None;
+    }
+    Some(BinaryPropagator::new().serialize_into_bytes(span_context))
+}
+
+/// The span context of an `X-Cloud-Trace-Context` header value, if valid.
+///
+/// The span is sampled when the options are missing or their first bit is set, as Google Cloud
+/// services do.
+pub fn from_cloud_trace_context(value: &str) -> Option<SpanContext> {
+    let (trace_id, rest) = value.trim().split_once('/')?;
+    if trace_id.len() != 32 {
+        return None;
+    }
+    let (span_id, options) = match rest.split_once(";o=") {
+        Some((span_id, options)) => (span_id, options.parse::<u8>().ok()?),
+        None => (rest, 1),
+    };
+    let trace_flags = if options & 1 == 1 {
+        TraceFlags::SAMPLED
+    } else {
+        TraceFlags::default()
+    };
+    let span_context = SpanContext::new(
+        TraceId::from_hex(trace_id).ok()?,
+        SpanId::from(span_id.parse::<u64>().ok()?),
+        trace_flags,
+        true,
+        TraceState::NONE,
+    );
+    Some(span_context).filter(SpanContext::is_valid)
+}
+
+/// The `X-Cloud-Trace-Context` header value of `span_context`, if valid.
+pub fn to_cloud_trace_context(span_context: &SpanContext) -> Option<String> {
+    if !span_context.is_valid() {
+        return None;
+    }
+    Some(format!(
+        "{}/{};o={}",
+        span_context.trace_id(),
+        u64::from_be_bytes(span_context.span_id().to_bytes()),
+        u8::from(span_context.is_sampled())
+    ))
+}
+
+/// Propagates the span context in the `grpc-trace-bin` and `X-Cloud-Trace-Context` headers of
+/// OpenCensus.
+///
+/// `grpc-trace-bin` values are base64 encoded, padded or not, as in HTTP/2 headers; values are
+/// injected without padding, as gRPC recommends.
+#[derive(Clone, Debug)]
+pub struct OpenCensusPropagator {
+    binary: bool,
+    cloud_trace_context: bool,
+    fields: Vec<String>,
+}
+
+impl Default for OpenCensusPropagator {
+    fn default() -> Self {
+        OpenCensusPropagator::new()
+    }
+}
+
+impl OpenCensusPropagator {
+    /// Extract and inject both headers.
+    pub fn new() -> Self {
+        OpenCensusPropagator {
+            binary: true,
+            cloud_trace_context: true,
+            fields: vec![
+                GRPC_TRACE_BIN_HEADER.to_string(),
+                CLOUD_TRACE_CONTEXT_HEADER.to_string(),
+            ],
+        }
+    }
+
+    /// Whether to inject the `grpc-trace-bin` header, true by default.
+    pub fn with_binary(mut self, enabled: bool) -> Self {
+        self.binary = enabled;
+        self.update_fields();
+        self
+    }
+
+    /// Whether to inject the `X-Cloud-Trace-Context` header, true by default.
+    pub fn with_cloud_trace_context(mut self, enabled: bool) -> Self {
+        self.cloud_trace_context = enabled;
+        self.update_fields();
+        self
+    }
+
+    fn update_fields(&mut self) {
+        self.fields.clear();
+        if self.binary {
+            self.fields.push(GRPC_TRACE_BIN_HEADER.to_string());
+        }
+        if self.cloud_trace_context {
+            self.fields.push(CLOUD_TRACE_CONTEXT_HEADER.to_string());
+        }
+    }
+
+    fn extract_span_context(&self, extractor: &dyn Extractor) -> Option<SpanContext> {
+        let binary = extractor.get(GRPC_TRACE_BIN_HEADER).and_then(|value| {
+            let value = value.trim();
+            let bytes = STANDARD_NO_PAD
+                .decode(value)
+                .or_else(|_| STANDARD.decode(value))
+                .ok()?;
+            from_binary(&bytes)
+        });
+        binary.or_else(|| {
+            extractor
+                .get(CLOUD_TRACE_CONTEXT_HEADER)
+                .and_then(from_cloud_trace_context)
+        })
+    }
+}
+
+impl TextMapPropagator for OpenCensusPropagator {
+    fn inject_context(&self, cx: &Context, injector: &mut dyn Injector) {
+        let span = cx.span();
+        let span_context = span.span_context();
+        if self.binary {
+            if let Some(bytes) = to_binary(span_context) {
+                injector.set(GRPC_TRACE_BIN_HEADER, STANDARD_NO_PAD.encode(bytes));
+            }
+        }
+        if self.cloud_trace_context {
+            if let Some(value) = to_cloud_trace_context(span_context) {
+                injector.set(CLOUD_TRACE_CONTEXT_HEADER, value);
+            }
+        }
+    }
+
+    fn extract_with_context(&self, cx: &Context, extractor: &dyn Extractor) -> Context {
+        self.extract_span_context(extractor)
+            .map(|span_context| cx.with_remote_span_context(span_context))
+            .unwrap_or_else(|| cx.clone())
+    }
+
+    fn fields(&self) -> FieldIter<'_> {
+        FieldIter::new(&self.fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::testing::trace::TestSpan;
+    use std::collections::HashMap;
+
+    fn span_context(trace_flags: TraceFlags) -> SpanContext {
+        SpanContext::new(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+            trace_flags,
+            true,
+            TraceState::NONE,
+        )
+    }
+
+    #[test]
+    fn test_cloud_trace_context() {
+        let sampled = span_context(TraceFlags::SAMPLED);
+        let value = to_cloud_trace_context(&sampled).unwrap();
+        assert_eq!(
+            value,
+            "4bf92f3577b34da6a3ce929d0e0e4736/67667974448284343;o=1"
+        );
+        assert_eq!(from_cloud_trace_context(&value), Some(sampled.clone()));
+        assert_eq!(
+            from_cloud_trace_context("4bf92f3577b34da6a3ce929d0e0e4736/67667974448284343"),
+            Some(sampled)
+        );
+        assert_eq!(
+            from_cloud_trace_context("4bf92f3577b34da6a3ce929d0e0e4736/67667974448284343;o=0"),
+            Some(span_context(TraceFlags::default()))
+        );
+        for value in [
+            "",
+            "4bf92f3577b34da6a3ce929d0e0e4736",
+            "4bf92f3577b34da6a3ce929d0e0e4736/0;o=1",
+            "4bf92f3577b34da6/1;o=1",
+            "4bf92f3577b34da6a3ce929d0e0e4736/x;o=1",
+        ] {
+            assert_eq!(from_cloud_trace_context(value), None, "{value}");
+        }
+        assert_eq!(to_cloud_trace_context(&SpanContext::empty_context()), None);
+    }
+
+    #[test]
+    fn test_binary() {
+        let sampled = span_context(TraceFlags::SAMPLED);
+        let bytes = to_binary(&sampled).unwrap();
+        assert_eq!(from_binary(&bytes), Some(sampled));
+        assert_eq!(from_binary(&[0; 29]), None);
+        assert_eq!(to_binary(&SpanContext::empty_context()), None);
+    }
+
+    #[test]
+    fn test_propagator() {
+        let propagator = OpenCensusPropagator::new();
+        let cx = Context::new().with_span(TestSpan(span_context(TraceFlags::SAMPLED)));
+        let mut headers = HashMap::new();
+        propagator.inject_context(&cx, &mut headers);
+        assert_eq!(
+            headers[CLOUD_TRACE_CONTEXT_HEADER],
+            "4bf92f3577b34da6a3ce929d0e0e4736/67667974448284343;o=1"
+        );
+        assert!(!headers[GRPC_TRACE_BIN_HEADER].ends_with('='));
+
+        let cx = propagator.extract(&headers);
+        assert_eq!(cx.span().span_context(), &span_context(TraceFlags::SAMPLED));
+
+        // grpc-trace-bin is preferred, padded or not.
+        let not_sampled = span_context(TraceFlags::default());
+        let mut extracted = headers.clone();
+        extracted.insert(
+            GRPC_TRACE_BIN_HEADER.to_string(),
+            STANDARD.encode(to_binary(&not_sampled).unwrap()),
+        );
+        let cx = propagator.extract(&extracted);
+        assert_eq!(cx.span().span_context(), &not_sampled);
+
+        headers.insert(GRPC_TRACE_BIN_HEADER.to_string(), "invalid".to_string());
+        let cx = propagator.extract(&headers);
+        assert_eq!(cx.span().span_context(), &span_context(TraceFlags::SAMPLED));
+        assert!(!propagator.extract(&HashMap::new()).has_active_span());
+
+        let propagator = OpenCensusPropagator::new().with_cloud_trace_context(false);
+        assert_eq!(
+            propagator.fields().collect::<Vec<_>>(),
+            [GRPC_TRACE_BIN_HEADER]
+        );
+        let mut headers = HashMap::new();
+        propagator.inject_context(&cx, &mut headers);
+        assert_eq!(headers.len(), 1);
+    }
+}
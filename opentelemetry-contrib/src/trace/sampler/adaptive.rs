@@ -0,0 +1,268 @@
+//! # Adaptive Sampler
+//!
+//! Samples a target number of spans per second whatever the traffic, keeping the cost of
+//! tracing constant through traffic spikes without retuning a fixed ratio.
+//!
+//! The sampler counts the spans it is asked about and, every adjustment interval, sets its
+//! sampling probability to the target divided by an exponentially weighted moving average of
+//! their rate. Within an interval, spans are sampled by trace id like the `TraceIdRatioBased`
+//! sampler of the SDK.
+//!
+//! As the root sampler of the `ParentBased` sampler, only the root spans are counted and
+//! sampled by this sampler, their children following the decision of their parent, so the
+//! target is a number of sampled traces per second.
+//!
+//! ```no_run
+//! use opentelemetry_contrib::trace::sampler::adaptive::AdaptiveSampler;
+//! use opentelemetry_sdk::trace::{Sampler, SdkTracerProvider};
+//!
+//! let provider = SdkTracerProvider::builder()
+//!     .with_sampler(Sampler::ParentBased(Box::new(AdaptiveSampler::new(100.0))))
+//!     .build();
+//! ```
+use crate::trace::sampler::ratio_sampled;
+use opentelemetry::trace::{
+    Link, SamplingDecision, SamplingResult, SpanKind, TraceContextExt, TraceId,
+};
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_sdk::trace::ShouldSample;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const DEFAULT_ADJUSTMENT_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_SMOOTHING: f64 = 0.5;
+
+/// Samples a target number of spans per second, adjusting its probability to the rate of the
+/// spans it is asked about.
+///
+/// Clones share their probability and span count.
+#[derive(Clone, Debug)]
+pub struct AdaptiveSampler {
+    target: f64,
+    adjustment_interval: Duration,
+    smoothing: f64,
+    min_probability: f64,
+    state: Arc<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    /// Bits of the current sampling probability.
+    probability: AtomicU64,
+    /// Spans asked about since the start of the adjustment interval.
+    count: AtomicU64,
+    controller: Mutex<Controller>,
+}
+
+#[derive(Debug)]
+struct Controller {
+    interval_start: Instant,
+    /// Moving average of the spans per second, unknown until the first adjustment.
+    rate: Option<f64>,
+}
+
+impl AdaptiveSampler {
+    /// Sample `target` spans per second, starting with a probability of 1.
+    pub fn new(target: f64) -> Self {
+        AdaptiveSampler {
+            target: target.max(0.0),
+            adjustment_interval: DEFAULT_ADJUSTMENT_INTERVAL,
+            smoothing: DEFAULT_SMOOTHING,
+            min_probability: 0.0,
+            state: Arc::new(State {
+                probability: AtomicU64::new(1.0_f64.to_bits()),
+                count: AtomicU64::new(0),
+                controller: Mutex::new(Controller {
+                    interval_start: Instant::now(),
+                    rate: None,
+                }),
+            }),
+        }
+    }
+
+    /// Adjust the probability every `interval`, one second by default.
+    pub fn with_adjustment_interval(mut self, interval: Duration) -> Self {
+        self.adjustment_interval = interval;
+        self
+    }
+
+    /// Weight of the rate of the last interval in the moving average of the span rate, between
+    /// 0 and 1, `0.5` by default. Higher weights react faster to changes of traffic, lower ones
+    /// smooth out short bursts.
+    pub fn with_smoothing(mut self, smoothing: f64) -> Self {
+        self.smoothing = smoothing.clamp(f64::MIN_POSITIVE, 1.0);
+        self
+    }
+
+    /// Never sample with a probability lower than `min_probability`, 0 by default, to keep
+    /// some traces of rare operations through large spikes.
+    pub fn with_min_probability(mut self, min_probability: f64) -> Self {
+        self.min_probability = min_probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sample with `probability` until the first adjustment, 1 by default.
+    pub fn with_initial_probability(self, probability: f64) -> Self {
+        self.set_probability(probability.clamp(0.0, 1.0));
+        self
+    }
+
+    /// The current sampling probability.
+    pub fn probability(&self) -> f64 {
+        f64::from_bits(self.state.probability.load(Ordering::Relaxed))
+    }
+
+    fn set_probability(&self, probability: f64) {
+        self.state
+            .probability
+            .store(probability.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Count a span at `now`, adjusting the probability at the end of an interval, and whether
+    /// to sample the span of `trace_id`.
+    fn sample(&self, trace_id: TraceId, now: Instant) -> bool {
+        self.state.count.fetch_add(1, Ordering::Relaxed);
+        self.adjust(now);
+        ratio_sampled(trace_id, self.probability())
+    }
+
+    fn adjust(&self, now: Instant) {
+        // Another thread is adjusting the probability.
+        let Ok(mut controller) = self.state.controller.try_lock() else {
+            return;
+        };
+        let elapsed = now.saturating_duration_since(controller.interval_start);
+        if elapsed < self.adjustment_interval || elapsed.is_zero() {
+            return;
+        }
+        let count = self.state.count.swap(0, Ordering::Relaxed);
+        controller.interval_start = now;
+
+        let rate = count as f64 / elapsed.as_secs_f64();
+        let rate = match controller.rate {
+            Some(average) => self.smoothing * rate + (1.0 - self.smoothing) * average,
+            None => rate,
+        };
+        controller.rate = Some(rate);
+
+        let probability = if rate > self.target {
+            (self.target / rate).max(self.min_probability)
+        } else {
+            1.0
+        };
+        self.set_probability(probability);
+    }
+}
+
+impl ShouldSample for AdaptiveSampler {
+    fn should_sample(
+        &self,
+        parent_context: Option<&Context>,
+        trace_id: TraceId,
+        _name: &str,
+        _span_kind: &SpanKind,
+        _attributes: &[KeyValue],
+        _links: &[Link],
+    ) -> SamplingResult {
+        let decision = if self.sample(trace_id, Instant::now()) {
+            SamplingDecision::RecordAndSample
+        } else {
+            SamplingDecision::Drop
+        };
+        SamplingResult {
+            decision,
+            attributes: Vec::new(),
+            trace_state: parent_context
+                .map(|cx| cx.span().span_context().trace_state().clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{SpanContext, SpanId, TraceFlags, TraceState};
+    use opentelemetry_sdk::trace::Sampler;
+
+    /// A trace id with random-looking low bits, from the SplitMix64 generator.
+    fn trace_id(i: u64) -> TraceId {
+        let mut z = i.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        TraceId::from(u128::from(z ^ (z >> 31)))
+    }
+
+    /// Ask `sampler` about `rate` spans per second for `duration`, returning the number of
+    /// spans sampled in the last second.
+    fn run(sampler: &AdaptiveSampler, start: Instant, rate: u64, duration: Duration) -> u64 {
+        let spans = rate * duration.as_secs();
+        let step = Duration::from_secs(1) / rate as u32;
+        let mut sampled = 0;
+        for i in 0..spans {
+            if sampler.sample(trace_id(i), start + step * i as u32) && spans - i <= rate {
+                sampled += 1;
+            }
+        }
+        sampled
+    }
+
+    #[test]
+    fn test_converge_to_target() {
+        let sampler = AdaptiveSampler::new(100.0);
+        let start = Instant::now();
+        assert_eq!(sampler.probability(), 1.0);
+
+        let sampled = run(&sampler, start, 1_000, Duration::from_secs(5));
+        assert!((0.09..0.11).contains(&sampler.probability()));
+        assert!((70..130).contains(&sampled), "{sampled}");
+
+        // A spike of traffic lowers the probability to keep the sampled spans on target.
+        let sampled = run(
+            &sampler,
+            start + Duration::from_secs(5),
+            10_000,
+            Duration::from_secs(8),
+        );
+        assert!((0.009..0.012).contains(&sampler.probability()));
+        assert!((70..130).contains(&sampled), "{sampled}");
+    }
+
+    #[test]
+    fn test_below_target() {
+        let sampler = AdaptiveSampler::new(100.0).with_initial_probability(0.5);
+        assert_eq!(sampler.probability(), 0.5);
+        run(&sampler, Instant::now(), 50, Duration::from_secs(3));
+        assert_eq!(sampler.probability(), 1.0);
+    }
+
+    #[test]
+    fn test_min_probability() {
+        let sampler = AdaptiveSampler::new(1.0).with_min_probability(0.05);
+        run(&sampler, Instant::now(), 1_000, Duration::from_secs(3));
+        assert_eq!(sampler.probability(), 0.05);
+    }
+
+    #[test]
+    fn test_parent_based() {
+        let sampler = Sampler::ParentBased(Box::new(
+            AdaptiveSampler::new(1.0).with_initial_probability(0.0),
+        ));
+        let sample = |parent: Option<&Context>| {
+            sampler
+                .should_sample(parent, trace_id(1), "span", &SpanKind::Server, &[], &[])
+                .decision
+        };
+        assert_eq!(sample(None), SamplingDecision::Drop);
+
+        let parent = Context::new().with_remote_span_context(SpanContext::new(
+            trace_id(1),
+            SpanId::from(1),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        ));
+        assert_eq!(sample(Some(&parent)), SamplingDecision::RecordAndSample);
+    }
+}
@@ -0,0 +1,23 @@
+//! # Opentelemetry sampler contrib
+//!
+//! This module provides samplers to configure on the tracer provider, alone or as the root
+//! sampler of the `ParentBased` sampler of the SDK.
+//!
+//! Currently, the following samplers are supported:
+//!
+//! * `adaptive`, which adjusts its sampling probability to sample a target number of spans per
+//!   second
+
+#[cfg(feature = "adaptive_sampler")]
+pub mod adaptive;
+
+/// Whether to sample `trace_id` with `probability`, as the `TraceIdRatioBased` sampler does.
+#[cfg(any(feature = "runtime_config", feature = "adaptive_sampler"))]
+pub(crate) fn ratio_sampled(trace_id: opentelemetry::trace::TraceId, probability: f64) -> bool {
+    if probability >= 1.0 {
+        return true;
+    }
+    let upper_bound = (probability.max(0.0) * (1u64 << 63) as f64) as u64;
+    let random = (u128::from_be_bytes(trace_id.to_bytes()) as u64) >> 1;
+    random < upper_bound
+}
@@ -0,0 +1,226 @@
+//! Discovery of the Datadog agent endpoint from the environment.
+use crate::exporter::Error;
+use http::Uri;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use url::Url;
+
+/// Environment variable holding the full URL of the agent.
+const DD_TRACE_AGENT_URL: &str = "DD_TRACE_AGENT_URL";
+/// Environment variable holding the host of the agent.
+const DD_AGENT_HOST: &str = "DD_AGENT_HOST";
+/// Environment variable holding the trace port of the agent.
+const DD_TRACE_AGENT_PORT: &str = "DD_TRACE_AGENT_PORT";
+
+const DEFAULT_AGENT_HOST: &str = "127.0.0.1";
+const DEFAULT_AGENT_PORT: &str = "8126";
+/// Socket the agent listens on when it is mounted into the container, e.g. on ECS or Kubernetes.
+const DEFAULT_AGENT_SOCKET: &str = "/var/run/datadog/apm.socket";
+
+/// Consecutive connection failures after which the agent endpoint is resolved again.
+const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+
+/// Where the agent is reached.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum AgentEndpoint {
+    /// An HTTP agent URL.
+    Url(String),
+    /// A Unix domain socket, given as a `unix://` URL.
+    Socket(PathBuf),
+}
+
+impl AgentEndpoint {
+    pub(crate) fn parse(endpoint: &str) -> Self {
+        match endpoint.strip_prefix("unix://") {
+            Some(path) => AgentEndpoint::Socket(PathBuf::from(path)),
+            None => AgentEndpoint::Url(endpoint.to_string()),
+        }
+    }
+
+    /// Resolve the endpoint with the precedence of the Datadog tracing libraries:
+    /// `DD_TRACE_AGENT_URL`, then `DD_AGENT_HOST` and `DD_TRACE_AGENT_PORT`, then the agent
+    /// socket if it exists and `socket_supported`, then `http://127.0.0.1:8126`.
+    pub(crate) fn discover(socket_supported: bool) -> Self {
+        Self::discover_with(
+            |name| std::env::var(name).ok().filter(|value| !value.is_empty()),
+            |path| socket_supported && path.exists(),
+        )
+    }
+
+    fn discover_with(
+        var: impl Fn(&str) -> Option<String>,
+        socket_exists: impl Fn(&Path) -> bool,
+    ) -> Self {
+        if let Some(url) = var(DD_TRACE_AGENT_URL) {
+            return Self::parse(&url);
+        }
+        let host = var(DD_AGENT_HOST);
+        let port = var(DD_TRACE_AGENT_PORT);
+        if host.is_none() && port.is_none() && socket_exists(Path::new(DEFAULT_AGENT_SOCKET)) {
+            return AgentEndpoint::Socket(PathBuf::from(DEFAULT_AGENT_SOCKET));
+        }
+        let host = host.unwrap_or_else(|| DEFAULT_AGENT_HOST.to_string());
+        let port = port.unwrap_or_else(|| DEFAULT_AGENT_PORT.to_string());
+        if host.contains(':') && !host.starts_with('[') {
+            AgentEndpoint::Url(format!("http://[{host}]:{port}"))
+        } else {
+            AgentEndpoint::Url(format!("http://{host}:{port}"))
+        }
+    }
+
+    /// The URL of the `path` API of the agent, keeping the query of the endpoint.
+    ///
+    /// Socket endpoints use the URI format of the `hyperlocal` crate, a hex encoded socket path
+    /// as host.
+    pub(crate) fn request_url(&self, path: &str) -> Result<Uri, Error> {
+        match self {
+            AgentEndpoint::Url(endpoint) => {
+                let mut endpoint = endpoint.parse::<Url>().map_err::<Error, _>(Into::into)?;
+                let mut paths = endpoint
+                    .path_segments()
+                    .map(|c| c.filter(|s| !s.is_empty()).collect::<Vec<_>>())
+                    .unwrap_or_default();
+                paths.push(path);
+
+                let path_str = paths.join("/");
+                endpoint.set_path(path_str.as_str());
+
+                endpoint.as_str().parse().map_err::<Error, _>(Into::into)
+            }
+            AgentEndpoint::Socket(socket) => {
+                let mut host = String::new();
+                for byte in socket.as_os_str().as_encoded_bytes() {
+                    let _ = write!(host, "{byte:02x}");
+                }
+                format!("unix://{host}:0/{}", path.trim_start_matches('/'))
+                    .parse()
+                    .map_err::<Error, _>(Into::into)
+            }
+        }
+    }
+}
+
+/// Re-resolves a discovered agent endpoint after repeated connection failures, so an agent
+/// moving, e.g. to a new node, is followed without restarting the application.
+#[derive(Debug)]
+pub(crate) struct AgentDiscovery {
+    socket_supported: bool,
+    failures: AtomicU32,
+}
+
+impl AgentDiscovery {
+    pub(crate) fn new(socket_supported: bool) -> Self {
+        AgentDiscovery {
+            socket_supported,
+            failures: AtomicU32::new(0),
+        }
+    }
+
+    pub(crate) fn discover(&self) -> AgentEndpoint {
+        AgentEndpoint::discover(self.socket_supported)
+    }
+
+    pub(crate) fn record_success(&self) {
+        self.failures.store(0, Ordering::Relaxed);
+    }
+
+    /// Record a connection failure, returning whether the endpoint should be resolved again.
+    pub(crate) fn record_failure(&self) -> bool {
+        let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= MAX_CONSECUTIVE_FAILURES {
+            self.failures.store(0, Ordering::Relaxed);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn discover(vars: &[(&str, &str)], socket_exists: bool) -> AgentEndpoint {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        AgentEndpoint::discover_with(
+            |name| vars.get(name).map(|value| value.to_string()),
+            |_| socket_exists,
+        )
+    }
+
+    #[test]
+    fn test_discover_precedence() {
+        assert_eq!(
+            discover(
+                &[
+                    (DD_TRACE_AGENT_URL, "http://agent:1234"),
+                    (DD_AGENT_HOST, "host")
+                ],
+                true
+            ),
+            AgentEndpoint::Url("http://agent:1234".to_string())
+        );
+        assert_eq!(
+            discover(&[(DD_TRACE_AGENT_URL, "unix:///tmp/apm.socket")], false),
+            AgentEndpoint::Socket(PathBuf::from("/tmp/apm.socket"))
+        );
+        assert_eq!(
+            discover(&[(DD_AGENT_HOST, "10.0.0.1")], true),
+            AgentEndpoint::Url("http://10.0.0.1:8126".to_string())
+        );
+        assert_eq!(
+            discover(
+                &[(DD_AGENT_HOST, "fe80::1"), (DD_TRACE_AGENT_PORT, "9000")],
+                true
+            ),
+            AgentEndpoint::Url("http://[fe80::1]:9000".to_string())
+        );
+        assert_eq!(
+            discover(&[], true),
+            AgentEndpoint::Socket(PathBuf::from(DEFAULT_AGENT_SOCKET))
+        );
+        assert_eq!(
+            discover(&[], false),
+            AgentEndpoint::Url("http://127.0.0.1:8126".to_string())
+        );
+    }
+
+    #[test]
+    fn test_discover_from_env() {
+        temp_env::with_vars(
+            [
+                (DD_TRACE_AGENT_URL, None),
+                (DD_AGENT_HOST, Some("dd-agent")),
+                (DD_TRACE_AGENT_PORT, Some("")),
+            ],
+            || {
+                assert_eq!(
+                    AgentEndpoint::discover(false),
+                    AgentEndpoint::Url("http://dd-agent:8126".to_string())
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_socket_request_url() {
+        let url = AgentEndpoint::Socket(PathBuf::from("/a.sock"))
+            .request_url("/v0.5/traces")
+            .unwrap();
+        assert_eq!(url.to_string(), "unix://2f612e736f636b:0/v0.5/traces");
+    }
+
+    #[test]
+    fn test_record_failure() {
+        let discovery = AgentDiscovery::new(false);
+        assert!(!discovery.record_failure());
+        assert!(!discovery.record_failure());
+        discovery.record_success();
+        assert!(!discovery.record_failure());
+        assert!(!discovery.record_failure());
+        assert!(discovery.record_failure());
+        assert!(!discovery.record_failure());
+    }
+}
@@ -0,0 +1,245 @@
+//! Sampling with the rates the agent returns in its responses to the exporter.
+use crate::exporter::sampling;
+use crate::DatadogTraceStateBuilder;
+use opentelemetry::trace::{Link, SpanKind, TraceContextExt, TraceId};
+use opentelemetry::{otel_debug, Context, KeyValue};
+use opentelemetry_sdk::trace::{SamplingDecision, SamplingResult, ShouldSample};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Key of the rate applying to the services without their own rate.
+const DEFAULT_RATE_KEY: &str = "service:,env:";
+
+/// The `rate_by_service` of the last agent response, shared by the exporter and the samplers.
+#[derive(Debug, Default)]
+pub(crate) struct AgentRates {
+    /// Key of the rate of the exported service, `service:{service},env:{env}`.
+    key: RwLock<String>,
+    rates: RwLock<HashMap<String, f64>>,
+}
+
+impl AgentRates {
+    /// Apply the rates of `service` in `env`.
+    pub(crate) fn set_service(&self, service: &str, env: Option<&str>) {
+        if let Ok(mut key) = self.key.write() {
+            *key = format!("service:{service},env:{}", env.unwrap_or_default());
+        }
+    }
+
+    /// Replace the rates by the `rate_by_service` of the response `body` of the agent.
+    ///
+    /// Bodies without rates, e.g. of agents without sampling, leave the rates unchanged.
+    pub(crate) fn update(&self, body: &[u8]) {
+        let rates = serde_json::from_slice::<serde_json::Value>(body)
+            .ok()
+            .and_then(|body| {
+                let rates = body.get("rate_by_service")?.as_object()?;
+                Some(
+                    rates
+                        .iter()
+                        .filter_map(|(key, rate)| {
+                            Some((key.clone(), rate.as_f64()?.clamp(0.0, 1.0)))
+                        })
+                        .collect::<HashMap<_, _>>(),
+                )
+            });
+        match rates {
+            Some(rates) => {
+                if let Ok(mut current) = self.rates.write() {
+                    *current = rates;
+                }
+            }
+            None => otel_debug!(name: "DatadogExporter.NoAgentRates"),
+        }
+    }
+
+    /// The rate of the exported service, or the default rate of the agent, `1` until the agent
+    /// responded.
+    fn rate(&self) -> f64 {
+        let (Ok(key), Ok(rates)) = (self.key.read(), self.rates.read()) else {
+            return 1.0;
+        };
+        rates
+            .get(key.as_str())
+            .or_else(|| rates.get(DEFAULT_RATE_KEY))
+            .copied()
+            .unwrap_or(1.0)
+    }
+}
+
+/// A sampler applying the sampling rates the Datadog agent computes for every service and env,
+/// to keep the number of traces it receives close to its target.
+///
+/// The agent returns the rates in its responses to the exporter, which must be built with
+/// [`DatadogPipelineBuilder::with_agent_sampler`]. Until the first response, every trace is
+/// sampled.
+///
+/// Like the Datadog tracing libraries, the sampler records every trace and sets the sampling
+/// decision as the priority of the root span, so the agent computes its stats from all the
+/// traces and drops the rejected ones. The priority is only sent by
+/// [`ApiVersion::Version05`] and [`ApiVersion::Version07`]. Spans with a parent follow its
+/// sampling decision and priority.
+///
+/// ```no_run
+/// use opentelemetry_datadog::{new_pipeline, DatadogAgentSampler};
+/// use opentelemetry_sdk::trace::SdkTracerProvider;
+///
+/// # fn main() -> Result<(), opentelemetry_datadog::Error> {
+/// let sampler = DatadogAgentSampler::new();
+/// let exporter = new_pipeline()
+///     .with_service_name("checkout")
+///     .with_agent_sampler(&sampler)
+///     .build_exporter()?;
+/// let provider = SdkTracerProvider::builder()
+///     .with_sampler(sampler)
+///     .with_batch_exporter(exporter)
+///     .build();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`DatadogPipelineBuilder::with_agent_sampler`]: crate::DatadogPipelineBuilder::with_agent_sampler
+/// [`ApiVersion::Version05`]: crate::ApiVersion::Version05
+/// [`ApiVersion::Version07`]: crate::ApiVersion::Version07
+#[derive(Clone, Debug, Default)]
+pub struct DatadogAgentSampler {
+    rates: Arc<AgentRates>,
+}
+
+impl DatadogAgentSampler {
+    /// Create a sampler sampling every trace until the agent returns its rates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current rate of the service of the exporter.
+    pub fn rate(&self) -> f64 {
+        self.rates.rate()
+    }
+
+    pub(crate) fn rates(&self) -> Arc<AgentRates> {
+        self.rates.clone()
+    }
+}
+
+impl ShouldSample for DatadogAgentSampler {
+    fn should_sample(
+        &self,
+        parent_context: Option<&Context>,
+        trace_id: TraceId,
+        _name: &str,
+        _span_kind: &SpanKind,
+        _attributes: &[KeyValue],
+        _links: &[Link],
+    ) -> SamplingResult {
+        if let Some(parent) = parent_context.filter(|cx| cx.has_active_span()) {
+            let span = parent.span();
+            let parent = span.span_context();
+            if parent.is_valid() {
+                return SamplingResult {
+                    decision: if parent.is_sampled() {
+                        SamplingDecision::RecordAndSample
+                    } else {
+                        SamplingDecision::Drop
+                    },
+                    attributes: Vec::new(),
+                    trace_state: parent.trace_state().clone(),
+                };
+            }
+        }
+
+        let rate = self.rate();
+        let trace_id = u128::from_be_bytes(trace_id.to_bytes()) as u64;
+        let keep = sampling::keep(trace_id, rate);
+        SamplingResult {
+            decision: SamplingDecision::RecordAndSample,
+            attributes: Vec::new(),
+            trace_state: DatadogTraceStateBuilder::default()
+                .with_priority_sampling(keep)
+                .build(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DatadogTraceState;
+    use opentelemetry::trace::{SpanContext, SpanId, TraceFlags, TraceState};
+
+    fn sample(
+        sampler: &DatadogAgentSampler,
+        parent: Option<&Context>,
+        trace_id: u128,
+    ) -> SamplingResult {
+        sampler.should_sample(
+            parent,
+            TraceId::from(trace_id),
+            "span",
+            &SpanKind::Internal,
+            &[],
+            &[],
+        )
+    }
+
+    #[test]
+    fn test_agent_rates() {
+        let sampler = DatadogAgentSampler::new();
+        let rates = sampler.rates();
+        rates.set_service("checkout", Some("prod"));
+        assert_eq!(sampler.rate(), 1.0);
+
+        rates.update(br#"{"rate_by_service":{"service:,env:":0.5,"service:checkout,env:prod":0.25,"service:cart,env:":2}}"#);
+        assert_eq!(sampler.rate(), 0.25);
+        rates.set_service("cart", None);
+        assert_eq!(sampler.rate(), 1.0);
+        rates.set_service("billing", None);
+        assert_eq!(sampler.rate(), 0.5);
+
+        // Responses without rates keep the last ones.
+        rates.update(b"OK");
+        assert_eq!(sampler.rate(), 0.5);
+    }
+
+    #[test]
+    fn test_should_sample() {
+        let sampler = DatadogAgentSampler::new();
+        let result = sample(&sampler, None, 42);
+        assert_eq!(result.decision, SamplingDecision::RecordAndSample);
+        assert!(result.trace_state.priority_sampling_enabled());
+
+        sampler
+            .rates()
+            .update(br#"{"rate_by_service":{"service:,env:":0}}"#);
+        let result = sample(&sampler, None, 42);
+        assert_eq!(result.decision, SamplingDecision::RecordAndSample);
+        assert!(!result.trace_state.priority_sampling_enabled());
+
+        // Spans with a parent follow its decision.
+        let trace_state = DatadogTraceStateBuilder::default()
+            .with_priority_sampling(true)
+            .build();
+        let parent = Context::new().with_remote_span_context(SpanContext::new(
+            TraceId::from(42),
+            SpanId::from(1),
+            TraceFlags::SAMPLED,
+            true,
+            trace_state,
+        ));
+        let result = sample(&sampler, Some(&parent), 42);
+        assert_eq!(result.decision, SamplingDecision::RecordAndSample);
+        assert!(result.trace_state.priority_sampling_enabled());
+
+        let parent = Context::new().with_remote_span_context(SpanContext::new(
+            TraceId::from(42),
+            SpanId::from(1),
+            TraceFlags::default(),
+            true,
+            TraceState::default(),
+        ));
+        assert_eq!(
+            sample(&sampler, Some(&parent), 42).decision,
+            SamplingDecision::Drop
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Guard against tags with a runaway number of distinct values.
+use opentelemetry::{otel_warn, Key, Value};
+use opentelemetry_sdk::trace::SpanData;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The value replacing the values of a tag over the cardinality limit.
+pub const OVERFLOW_VALUE: &str = "__overflow__";
+
+const DEFAULT_WINDOW: Duration = Duration::from_secs(3600);
+
+/// The tags mapped to the fields of Datadog spans, or to their error tracking, excluded by
+/// default.
+const MODEL_KEYS: [&str; 7] = [
+    "service.name",
+    "operation.name",
+    "resource.name",
+    "span.type",
+    "error.type",
+    "error.msg",
+    "error.stack",
+];
+
+/// The prefix of the tags reserved by Datadog, which are never limited.
+const RESERVED_PREFIX: &str = "_dd.";
+
+/// Limits the number of distinct values of each tag within a window, replacing the values of a
+/// tag over the limit by `__overflow__`, so a bad attribute, e.g. a user id recorded as a tag,
+/// doesn't multiply the tag combinations Datadog indexes and bills.
+///
+/// The values of a tag are counted from the start of the window, 1 hour by default. Once a
+/// tag has `max_values` distinct values, the spans with other values are exported with
+/// `__overflow__` instead, aggregating them under a single value, and a warning is logged once
+/// per tag and window. The values already seen keep being exported as they are.
+///
+/// Only the string attributes of the spans kept by the sampling rules, exported as tags, are
+/// counted. The attributes mapped to the fields of Datadog spans, `service.name`,
+/// `operation.name`, `resource.name` and `span.type`, the `error.type`, `error.msg` and
+/// `error.stack` attributes, and the `_dd.` attributes reserved by Datadog are excluded. Other
+/// attributes expected to have many values, e.g. `http.url`, can be excluded with
+/// [`with_excluded_key`](Self::with_excluded_key).
+///
+/// ```no_run
+/// use opentelemetry_datadog::{new_pipeline, TagCardinalityGuard};
+///
+/// # fn main() -> Result<(), opentelemetry_datadog::Error> {
+/// let exporter = new_pipeline()
+///     .with_tag_cardinality_guard(
+///         TagCardinalityGuard::new(1000).with_excluded_key("http.url"),
+///     )
+///     .build_exporter()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TagCardinalityGuard {
+    max_values: usize,
+    window: Duration,
+    excluded_keys: HashSet<Key>,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    window_start: Instant,
+    /// Hashes of the values of each tag seen in the window.
+    values: HashMap<Key, HashSet<u64>>,
+    /// The tags over the limit in the window, logged once.
+    overflowed: HashSet<Key>,
+}
+
+impl TagCardinalityGuard {
+    /// Keep at most `max_values` distinct values of each tag per window.
+    pub fn new(max_values: usize) -> Self {
+        TagCardinalityGuard {
+            max_values,
+            window: DEFAULT_WINDOW,
+            excluded_keys: MODEL_KEYS.into_iter().map(Key::from_static_str).collect(),
+            state: Mutex::new(State {
+                window_start: Instant::now(),
+                values: HashMap::new(),
+                overflowed: HashSet::new(),
+            }),
+        }
+    }
+
+    /// Count the values of the tags in windows of `window`, 1 hour by default.
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Don't limit the values of the tag `key`.
+    pub fn with_excluded_key(mut self, key: impl Into<Key>) -> Self {
+        self.excluded_keys.insert(key.into());
+        self
+    }
+
+    /// Replace the values of the tags of `spans` over the limit.
+    pub(crate) fn apply(&self, spans: &mut [SpanData]) {
+        self.apply_at(spans, Instant::now());
+    }
+
+    fn is_excluded(&self, key: &Key) -> bool {
+        self.excluded_keys.contains(key) || key.as_str().starts_with(RESERVED_PREFIX)
+    }
+
+    fn apply_at(&self, spans: &mut [SpanData], now: Instant) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if now.saturating_duration_since(state.window_start) >= self.window {
+            state.window_start = now;
+            state.values.clear();
+            state.overflowed.clear();
+        }
+
+        let state = &mut *state;
+        for kv in spans.iter_mut().flat_map(|span| span.attributes.iter_mut()) {
+            let Value::String(value) = &kv.value else {
+                continue;
+            };
+            if self.is_excluded(&kv.key) {
+                continue;
+            }
+            let mut hasher = DefaultHasher::new();
+            value.as_str().hash(&mut hasher);
+            let hash = hasher.finish();
+
+            let values = state.values.entry(kv.key.clone()).or_default();
+            if values.contains(&hash) {
+                continue;
+            }
+            if values.len() < self.max_values {
+                values.insert(hash);
+                continue;
+            }
+            if state.overflowed.insert(kv.key.clone()) {
+                otel_warn!(
+                    name: "DatadogExporter.TagCardinalityExceeded",
+                    key = kv.key.to_string(),
+                    max_values = self.max_values as u64
+                );
+            }
+            kv.value = Value::from(OVERFLOW_VALUE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{SpanContext, SpanId, SpanKind, Status};
+    use opentelemetry::{InstrumentationScope, KeyValue};
+    use opentelemetry_sdk::trace::{SpanEvents, SpanLinks};
+    use std::time::SystemTime;
+
+    fn span(attributes: Vec<KeyValue>) -> SpanData {
+        SpanData {
+            span_context: SpanContext::empty_context(),
+            parent_span_id: SpanId::INVALID,
+            parent_span_is_remote: false,
+            span_kind: SpanKind::Server,
+            name: "span".into(),
+            start_time: SystemTime::UNIX_EPOCH,
+            end_time: SystemTime::UNIX_EPOCH,
+            attributes,
+            dropped_attributes_count: 0,
+            events: SpanEvents::default(),
+            links: SpanLinks::default(),
+            status: Status::Unset,
+            instrumentation_scope: InstrumentationScope::builder("component").build(),
+        }
+    }
+
+    #[test]
+    fn test_overflow() {
+        let guard = TagCardinalityGuard::new(2)
+            .with_window(Duration::from_secs(60))
+            .with_excluded_key("http.url");
+        let start = Instant::now();
+        let mut spans: Vec<SpanData> = ["a", "b", "c", "a", "d"]
+            .into_iter()
+            .map(|user| {
+                span(vec![
+                    KeyValue::new("user.id", user),
+                    KeyValue::new("http.url", format!("/users/{user}")),
+                    KeyValue::new("retries", 3),
+                    KeyValue::new("error.msg", format!("user {user} not found")),
+                    KeyValue::new("_dd.origin", format!("origin-{user}")),
+                ])
+            })
+            .collect();
+        guard.apply_at(&mut spans, start);
+
+        let users: Vec<String> = spans
+            .iter()
+            .map(|span| span.attributes[0].value.to_string())
+            .collect();
+        assert_eq!(users, ["a", "b", OVERFLOW_VALUE, "a", OVERFLOW_VALUE]);
+        assert_eq!(spans[4].attributes[1].value, Value::from("/users/d"));
+        assert_eq!(spans[4].attributes[2].value, Value::I64(3));
+        assert_eq!(
+            spans[4].attributes[3].value,
+            Value::from("user d not found")
+        );
+        assert_eq!(spans[4].attributes[4].value, Value::from("origin-d"));
+
+        // The values are counted again in the next window.
+        let mut spans = vec![span(vec![KeyValue::new("user.id", "c")])];
+        guard.apply_at(&mut spans, start + Duration::from_secs(30));
+        assert_eq!(spans[0].attributes[0].value, Value::from(OVERFLOW_VALUE));
+        let mut spans = vec![span(vec![KeyValue::new("user.id", "c")])];
+        guard.apply_at(&mut spans, start + Duration::from_secs(60));
+        assert_eq!(spans[0].attributes[0].value, Value::from("c"));
+    }
+}
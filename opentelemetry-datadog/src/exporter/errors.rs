@@ -0,0 +1,198 @@
+//! Mapping of the OpenTelemetry exceptions and error statuses to Datadog error spans.
+use opentelemetry::trace::{SpanId, Status};
+use opentelemetry::{KeyValue, Value};
+use opentelemetry_sdk::trace::SpanData;
+use opentelemetry_semantic_conventions as semcov;
+
+/// Name of the span events recording an exception.
+const EXCEPTION_EVENT_NAME: &str = "exception";
+
+// https://github.com/DataDog/datadog-agent/blob/7.62.0/pkg/trace/api/otlp.go
+const ERROR_TYPE_KEY: &str = "error.type";
+const ERROR_MSG_KEY: &str = "error.msg";
+const ERROR_STACK_KEY: &str = "error.stack";
+
+/// The Datadog error tags and the attributes of the `exception` events they are read from.
+const ERROR_TAGS: [(&str, &str); 3] = [
+    (ERROR_TYPE_KEY, semcov::trace::EXCEPTION_TYPE),
+    (ERROR_MSG_KEY, semcov::trace::EXCEPTION_MESSAGE),
+    (ERROR_STACK_KEY, semcov::trace::EXCEPTION_STACKTRACE),
+];
+
+/// Tag `span` with the `error.type`, `error.msg` and `error.stack` of its last `exception`
+/// event, as the Datadog agent does for OTLP spans, unless already set.
+pub(crate) fn tag_exception(span: &mut SpanData) {
+    let Some(event) = span
+        .events
+        .events
+        .iter()
+        .rev()
+        .find(|event| event.name == EXCEPTION_EVENT_NAME)
+    else {
+        return;
+    };
+    let tags: Vec<KeyValue> = ERROR_TAGS
+        .iter()
+        .filter(|(tag, _)| !has_attribute(span, tag))
+        .filter_map(|(tag, attribute)| {
+            event
+                .attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == *attribute)
+                .map(|kv| KeyValue::new(*tag, kv.value.clone()))
+        })
+        .collect();
+    span.attributes.extend(tags);
+}
+
+/// Roll up the errors of each trace of `spans` to its local root span, see [`roll_up_errors`].
+///
+/// The spans are sorted by trace id.
+pub(crate) fn roll_up_batch_errors(spans: &mut [SpanData]) {
+    spans.sort_unstable_by_key(|span| span.span_context.trace_id().to_bytes());
+    let mut start = 0;
+    while start < spans.len() {
+        let trace_id = spans[start].span_context.trace_id();
+        let end = spans[start..]
+            .iter()
+            .position(|span| span.span_context.trace_id() != trace_id)
+            .map_or(spans.len(), |len| start + len);
+        roll_up_errors(&mut spans[start..end]);
+        start = end;
+    }
+}
+
+/// Mark the local root span of `trace` as an error when one of its other spans is, as Datadog
+/// measures the errors of a service on its entry spans, copying the error tags of the first
+/// such span.
+///
+/// Only a span without parent or with a remote parent is a local root, so that the first span
+/// exported of a trace whose root ends in a later batch isn't marked.
+pub(crate) fn roll_up_errors(trace: &mut [SpanData]) {
+    let Some(root) = trace
+        .iter()
+        .position(|span| span.parent_span_id == SpanId::INVALID || span.parent_span_is_remote)
+    else {
+        return;
+    };
+    if matches!(trace[root].status, Status::Error { .. }) {
+        return;
+    }
+    let Some(error) = trace
+        .iter()
+        .find(|span| matches!(span.status, Status::Error { .. }))
+    else {
+        return;
+    };
+    let status = error.status.clone();
+    let tags: Vec<(&str, Value)> = ERROR_TAGS
+        .iter()
+        .filter_map(|(tag, _)| Some((*tag, attribute(error, tag)?.clone())))
+        .collect();
+
+    let root = &mut trace[root];
+    root.status = status;
+    for (tag, value) in tags {
+        if !has_attribute(root, tag) {
+            root.attributes.push(KeyValue::new(tag, value));
+        }
+    }
+}
+
+fn attribute<'a>(span: &'a SpanData, key: &str) -> Option<&'a Value> {
+    span.attributes
+        .iter()
+        .find(|kv| kv.key.as_str() == key)
+        .map(|kv| &kv.value)
+}
+
+fn has_attribute(span: &SpanData, key: &str) -> bool {
+    attribute(span, key).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter::model::tests::get_span;
+    use opentelemetry::trace::Event;
+    use std::time::SystemTime;
+
+    fn exception(message: &str) -> Event {
+        Event::new(
+            EXCEPTION_EVENT_NAME,
+            SystemTime::UNIX_EPOCH,
+            vec![
+                KeyValue::new(semcov::trace::EXCEPTION_TYPE, "io::Error"),
+                KeyValue::new(semcov::trace::EXCEPTION_MESSAGE, message.to_string()),
+            ],
+            0,
+        )
+    }
+
+    #[test]
+    fn test_tag_exception() {
+        let mut span = get_span(7, 1, 99);
+        span.events.events.push(exception("first"));
+        span.events.events.push(exception("connection reset"));
+        span.attributes
+            .push(KeyValue::new(ERROR_TYPE_KEY, "ConnectionReset"));
+        tag_exception(&mut span);
+
+        assert_eq!(
+            attribute(&span, ERROR_TYPE_KEY),
+            Some(&Value::from("ConnectionReset"))
+        );
+        assert_eq!(
+            attribute(&span, ERROR_MSG_KEY),
+            Some(&Value::from("connection reset"))
+        );
+        assert_eq!(attribute(&span, ERROR_STACK_KEY), None);
+    }
+
+    #[test]
+    fn test_roll_up_errors() {
+        let mut root = get_span(7, 0, 1);
+        root.attributes
+            .push(KeyValue::new(ERROR_TYPE_KEY, "Handler"));
+        let mut child = get_span(7, 1, 2);
+        child.status = Status::error("connection reset");
+        child.events.events.push(exception("connection reset"));
+        tag_exception(&mut child);
+        let mut trace = [child, root];
+        roll_up_errors(&mut trace);
+
+        assert_eq!(trace[1].status, Status::error("connection reset"));
+        assert_eq!(
+            attribute(&trace[1], ERROR_TYPE_KEY),
+            Some(&Value::from("Handler"))
+        );
+        assert_eq!(
+            attribute(&trace[1], ERROR_MSG_KEY),
+            Some(&Value::from("connection reset"))
+        );
+
+        // Without the local root in the batch, no span is marked.
+        let mut child = get_span(7, 1, 2);
+        child.status = Status::error("connection reset");
+        let mut trace = [get_span(7, 1, 3), child];
+        roll_up_errors(&mut trace);
+        assert_eq!(trace[0].status, Status::Ok);
+    }
+
+    #[test]
+    fn test_roll_up_batch_errors() {
+        let mut child = get_span(8, 1, 2);
+        child.status = Status::error("timeout");
+        let mut batch = [get_span(8, 0, 1), get_span(7, 0, 3), child];
+        roll_up_batch_errors(&mut batch);
+
+        let status = |span_id: u64| {
+            batch
+                .iter()
+                .find(|span| span.span_context.span_id() == SpanId::from(span_id))
+                .map(|span| span.status.clone())
+        };
+        assert_eq!(status(1), Some(Status::error("timeout")));
+        assert_eq!(status(3), Some(Status::Ok));
+    }
+}
@@ -0,0 +1,178 @@
+//! Resolution of the hostname the traces are attributed to.
+//!
+//! The hostname is resolved like the Datadog tracing libraries do, from the first of:
+//!
+//! * the `DD_HOSTNAME` environment variable,
+//! * the hostname set with [`DatadogPipelineBuilder::with_hostname`],
+//! * the hostname of the operating system,
+//! * the EC2 instance id, when running on EC2 with the default `ip-*` hostname, or no hostname.
+//!
+//! [`DatadogPipelineBuilder::with_hostname`]: crate::DatadogPipelineBuilder::with_hostname
+
+/// Environment variable holding the hostname of the traces.
+const DD_HOSTNAME: &str = "DD_HOSTNAME";
+/// Environment variable enabling the reporting of the hostname as a tag.
+const DD_TRACE_REPORT_HOSTNAME: &str = "DD_TRACE_REPORT_HOSTNAME";
+
+/// Tag holding the hostname of the traces.
+pub(crate) const HOSTNAME_TAG: &str = "_dd.hostname";
+/// Header holding the hostname of the traces in the payload.
+pub(crate) const DATADOG_HOSTNAME_HEADER: &str = "Datadog-Hostname";
+
+/// Files holding the hostname of the operating system.
+const HOSTNAME_FILES: [&str; 2] = ["/proc/sys/kernel/hostname", "/etc/hostname"];
+/// File holding the instance id on EC2 Nitro instances, readable without querying the instance
+/// metadata service.
+const EC2_INSTANCE_ID_FILE: &str = "/sys/devices/virtual/dmi/id/board_asset_tag";
+/// Prefixes of the hostnames assigned by default to EC2 instances.
+const EC2_DEFAULT_HOSTNAME_PREFIXES: [&str; 3] = ["ip-", "domu", "ec2amaz-"];
+
+/// How the hostname of the traces is reported to the agent.
+///
+/// Without it, the agent attributes the traces to its own host, which is wrong when the agent
+/// is not running on the same host as the application, e.g. a cluster agent or a Fargate task.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HostnameReporting {
+    /// The hostname is not reported.
+    ///
+    /// This is the default, unless the `DD_TRACE_REPORT_HOSTNAME` environment variable is
+    /// `true`.
+    #[default]
+    Disabled,
+    /// The hostname is the `_dd.hostname` tag of every span.
+    Tag,
+    /// The hostname is the `Datadog-Hostname` header of the payloads.
+    Header,
+}
+
+impl HostnameReporting {
+    pub(crate) fn from_env() -> Self {
+        match std::env::var(DD_TRACE_REPORT_HOSTNAME) {
+            Ok(value) if value.eq_ignore_ascii_case("true") || value == "1" => {
+                HostnameReporting::Tag
+            }
+            _ => HostnameReporting::Disabled,
+        }
+    }
+}
+
+/// Resolve the hostname of the traces, see the [module documentation](self).
+pub(crate) fn resolve(configured: Option<&str>) -> Option<String> {
+    resolve_with(
+        configured,
+        |name| std::env::var(name).ok(),
+        |path| std::fs::read_to_string(path).ok(),
+    )
+}
+
+fn resolve_with(
+    configured: Option<&str>,
+    var: impl Fn(&str) -> Option<String>,
+    read_file: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    if let Some(hostname) = var(DD_HOSTNAME).and_then(non_empty) {
+        return Some(hostname);
+    }
+    if let Some(hostname) = configured.map(str::to_string).and_then(non_empty) {
+        return Some(hostname);
+    }
+    let os_hostname = HOSTNAME_FILES
+        .iter()
+        .find_map(|path| read_file(path).and_then(non_empty))
+        .or_else(|| var("HOSTNAME").and_then(non_empty))
+        .or_else(|| var("COMPUTERNAME").and_then(non_empty));
+    match os_hostname {
+        Some(hostname) if !is_default_ec2_hostname(&hostname) => Some(hostname),
+        os_hostname => read_file(EC2_INSTANCE_ID_FILE)
+            .and_then(non_empty)
+            .filter(|instance_id| instance_id.starts_with("i-"))
+            .or(os_hostname),
+    }
+}
+
+fn non_empty(value: String) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn is_default_ec2_hostname(hostname: &str) -> bool {
+    let hostname = hostname.to_ascii_lowercase();
+    EC2_DEFAULT_HOSTNAME_PREFIXES
+        .iter()
+        .any(|prefix| hostname.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn resolve_in(
+        configured: Option<&str>,
+        vars: &[(&str, &str)],
+        files: &[(&str, &str)],
+    ) -> Option<String> {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        let files: HashMap<_, _> = files.iter().copied().collect();
+        resolve_with(
+            configured,
+            |name| vars.get(name).map(|value| value.to_string()),
+            |path| files.get(path).map(|value| value.to_string()),
+        )
+    }
+
+    #[test]
+    fn test_resolve_precedence() {
+        let os = [("/proc/sys/kernel/hostname", "web-1\n")];
+        assert_eq!(
+            resolve_in(Some("configured"), &[("DD_HOSTNAME", "node-1")], &os).as_deref(),
+            Some("node-1")
+        );
+        assert_eq!(
+            resolve_in(Some("configured"), &[("DD_HOSTNAME", "")], &os).as_deref(),
+            Some("configured")
+        );
+        assert_eq!(resolve_in(None, &[], &os).as_deref(), Some("web-1"));
+        assert_eq!(
+            resolve_in(None, &[("HOSTNAME", "container")], &[]).as_deref(),
+            Some("container")
+        );
+        assert_eq!(resolve_in(None, &[], &[]), None);
+    }
+
+    #[test]
+    fn test_resolve_ec2_instance_id() {
+        let instance_id = (EC2_INSTANCE_ID_FILE, "i-0123456789abcdef0\n");
+        assert_eq!(
+            resolve_in(
+                None,
+                &[],
+                &[("/etc/hostname", "ip-10-0-0-1.ec2.internal"), instance_id]
+            )
+            .as_deref(),
+            Some("i-0123456789abcdef0")
+        );
+        assert_eq!(
+            resolve_in(None, &[], &[instance_id]).as_deref(),
+            Some("i-0123456789abcdef0")
+        );
+        assert_eq!(
+            resolve_in(None, &[], &[("/etc/hostname", "web-1"), instance_id]).as_deref(),
+            Some("web-1")
+        );
+        // Not on Nitro, or not on EC2.
+        assert_eq!(
+            resolve_in(
+                None,
+                &[],
+                &[
+                    ("/etc/hostname", "ip-10-0-0-1"),
+                    (EC2_INSTANCE_ID_FILE, "Default string")
+                ]
+            )
+            .as_deref(),
+            Some("ip-10-0-0-1")
+        );
+    }
+}
@@ -1,32 +1,46 @@
+mod agent;
+#[cfg(feature = "agent-sampling")]
+mod agent_sampler;
+mod cardinality;
+mod errors;
+mod hostname;
 mod intern;
 mod model;
+#[cfg(any(feature = "sampling-rules", feature = "agent-sampling"))]
+mod sampling;
 
+#[cfg(feature = "agent-sampling")]
+pub use agent_sampler::DatadogAgentSampler;
+pub use cardinality::TagCardinalityGuard;
+pub use hostname::HostnameReporting;
+pub use model::meta_struct::meta_struct_attribute;
 pub use model::ApiVersion;
 pub use model::Error;
 pub use model::FieldMappingFn;
+#[cfg(feature = "sampling-rules")]
+pub use sampling::SamplingRules;
 
+use crate::exporter::agent::{AgentDiscovery, AgentEndpoint};
+use crate::exporter::hostname::{DATADOG_HOSTNAME_HEADER, HOSTNAME_TAG};
 use crate::exporter::model::FieldMapping;
 use http::{Method, Request, Uri};
-use opentelemetry::{Key, KeyValue};
+use opentelemetry::{trace::TraceId, Key, KeyValue};
 use opentelemetry_http::{HttpClient, ResponseExt};
 use opentelemetry_sdk::{
     error::{OTelSdkError, OTelSdkResult},
     resource::{ResourceDetector, SdkProvidedResourceDetector},
-    trace::{Config, SdkTracerProvider},
+    trace::{BatchConfig, BatchSpanProcessor, Config, SdkTracerProvider},
     trace::{SpanData, SpanExporter},
     Resource,
 };
 use opentelemetry_semantic_conventions as semcov;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
-use std::sync::Arc;
-use url::Url;
+use std::sync::{Arc, RwLock};
 
 use self::model::unified_tags::UnifiedTags;
 
-/// Default Datadog collector endpoint
-const DEFAULT_AGENT_ENDPOINT: &str = "http://127.0.0.1:8126";
-
 /// Header name used to inform the Datadog agent of the number of traces in the payload
 const DATADOG_TRACE_COUNT_HEADER: &str = "X-Datadog-Trace-Count";
 
@@ -61,15 +75,43 @@ impl Mapping {
 /// Datadog span exporter
 pub struct DatadogExporter {
     client: Arc<dyn HttpClient>,
-    request_url: Uri,
+    request_url: RwLock<Uri>,
+    discovery: Option<AgentDiscovery>,
     model_config: ModelConfig,
     api_version: ApiVersion,
     mapping: Mapping,
     unified_tags: UnifiedTags,
     resource: Option<Resource>,
+    /// Hostname of the traces, when reported, set on `ApiVersion::Version07` payloads.
+    hostname: Option<String>,
+    /// Hostname sent in the `Datadog-Hostname` header.
+    hostname_header: Option<String>,
+    #[cfg(feature = "self-metrics")]
+    metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics,
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    compressor: std::sync::RwLock<opentelemetry_contrib::compression::Compressor>,
+    #[cfg(feature = "sampling-rules")]
+    sampling_rules: Option<SamplingRules>,
+    /// Rates of the agent sampler updated from the agent responses.
+    #[cfg(feature = "agent-sampling")]
+    agent_rates: Option<Arc<agent_sampler::AgentRates>>,
+    cardinality_guard: Option<TagCardinalityGuard>,
+    error_roll_up: bool,
+    /// Whether the service name is read from the resource of the tracer provider, when it
+    /// wasn't set on the builder.
+    service_name_from_resource: bool,
 }
 
 impl DatadogExporter {
+    /// Create a builder for the exporter, the same as [`new_pipeline`].
+    ///
+    /// Use [`DatadogPipelineBuilder::build_exporter`] or
+    /// [`DatadogPipelineBuilder::build_batch_processor`] to add the exporter to a tracer provider
+    /// alongside other exporters.
+    pub fn builder() -> DatadogPipelineBuilder {
+        new_pipeline()
+    }
+
     fn new(
         model_config: ModelConfig,
         request_url: Uri,
@@ -77,23 +119,82 @@ impl DatadogExporter {
         client: Arc<dyn HttpClient>,
         mapping: Mapping,
         unified_tags: UnifiedTags,
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        compressor: opentelemetry_contrib::compression::Compressor,
     ) -> Self {
         DatadogExporter {
             client,
-            request_url,
+            request_url: RwLock::new(request_url),
+            discovery: None,
             model_config,
             api_version,
             mapping,
             unified_tags,
             resource: None,
+            hostname: None,
+            hostname_header: None,
+            #[cfg(feature = "self-metrics")]
+            metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics::new(
+                "datadog",
+                opentelemetry_contrib::exporter_metrics::Signal::Traces,
+            ),
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            compressor: std::sync::RwLock::new(compressor),
+            #[cfg(feature = "sampling-rules")]
+            sampling_rules: None,
+            #[cfg(feature = "agent-sampling")]
+            agent_rates: None,
+            cardinality_guard: None,
+            error_roll_up: false,
+            service_name_from_resource: false,
         }
     }
 
-    fn build_request(
-        &self,
-        mut batch: Vec<SpanData>,
-    ) -> Result<http::Request<Vec<u8>>, OTelSdkError> {
-        let traces: Vec<&[SpanData]> = group_into_traces(&mut batch);
+    /// Drop the traces rejected by the sampling rules, pairing the others with the rate of the
+    /// rule that kept them.
+    fn sample<'a>(&self, traces: Vec<&'a [SpanData]>) -> Vec<(&'a [SpanData], Option<f64>)> {
+        #[cfg(feature = "sampling-rules")]
+        if let Some(sampling_rules) = &self.sampling_rules {
+            return traces
+                .into_iter()
+                .filter_map(|trace| {
+                    let (keep, rule_rate) =
+                        sampling_rules.sample(trace, &self.mapping, &self.model_config);
+                    keep.then_some((trace, rule_rate))
+                })
+                .collect();
+        }
+        traces.into_iter().map(|trace| (trace, None)).collect()
+    }
+
+    fn build_request(&self, batch: Vec<SpanData>) -> Result<http::Request<Vec<u8>>, OTelSdkError> {
+        let (data, trace_count) = self.encode(batch)?;
+        self.payload_request(data, trace_count)
+    }
+
+    /// Encode `batch`, returning the payload with the number of traces it holds.
+    fn encode(&self, mut batch: Vec<SpanData>) -> Result<(Vec<u8>, usize), OTelSdkError> {
+        if self.error_roll_up {
+            batch.iter_mut().for_each(errors::tag_exception);
+            errors::roll_up_batch_errors(&mut batch);
+        }
+        let traces = match &self.cardinality_guard {
+            // Only the tags of the traces kept by the sampling rules are counted.
+            Some(cardinality_guard) => {
+                let rule_rates: HashMap<TraceId, Option<f64>> = self
+                    .sample(group_into_traces(&mut batch))
+                    .into_iter()
+                    .map(|(trace, rule_rate)| (trace[0].span_context.trace_id(), rule_rate))
+                    .collect();
+                batch.retain(|span| rule_rates.contains_key(&span.span_context.trace_id()));
+                cardinality_guard.apply(&mut batch);
+                group_into_traces(&mut batch)
+                    .into_iter()
+                    .map(|trace| (trace, rule_rates[&trace[0].span_context.trace_id()]))
+                    .collect()
+            }
+            None => self.sample(group_into_traces(&mut batch)),
+        };
         let trace_count = traces.len();
         let data = self
             .api_version
@@ -103,22 +204,117 @@ impl DatadogExporter {
                 &self.mapping,
                 &self.unified_tags,
                 self.resource.as_ref(),
+                self.hostname.as_deref(),
             )
             .map_err(|e| OTelSdkError::InternalFailure(format!("{e:?}")))?;
+        Ok((data, trace_count))
+    }
+
+    /// Build the request sending the encoded `data`, compressed with the current compressor.
+    fn payload_request(
+        &self,
+        data: Vec<u8>,
+        trace_count: usize,
+    ) -> Result<http::Request<Vec<u8>>, OTelSdkError> {
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        let (data, content_encoding) = match self.compressor.read() {
+            Ok(compressor) => compressor
+                .compress(data)
+                .map_err(|e| OTelSdkError::InternalFailure(format!("{e:?}")))?,
+            Err(_) => (data, None),
+        };
+        let request_url = match self.request_url.read() {
+            Ok(request_url) => request_url.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
         let req = Request::builder()
             .method(Method::POST)
-            .uri(self.request_url.clone())
+            .uri(request_url)
             .header(http::header::CONTENT_TYPE, self.api_version.content_type())
             .header(DATADOG_TRACE_COUNT_HEADER, trace_count)
             .header(DATADOG_META_LANG_HEADER, "rust")
             .header(
                 DATADOG_META_TRACER_VERSION_HEADER,
                 env!("CARGO_PKG_VERSION"),
-            )
+            );
+        let req = match &self.hostname_header {
+            Some(hostname) => req.header(DATADOG_HOSTNAME_HEADER, hostname),
+            None => req,
+        };
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        let req = match content_encoding {
+            Some(content_encoding) => req.header(http::header::CONTENT_ENCODING, content_encoding),
+            None => req,
+        };
+        let req = req
             .body(data)
             .map_err(|e| OTelSdkError::InternalFailure(format!("{e:?}")))?;
         Ok(req)
     }
+
+    /// Track the connections to a discovered agent, resolving its endpoint again after repeated
+    /// failures.
+    fn track_connection(&self, connected: bool) {
+        let Some(discovery) = &self.discovery else {
+            return;
+        };
+        if connected {
+            discovery.record_success();
+            return;
+        }
+        if !discovery.record_failure() {
+            return;
+        }
+        let endpoint = discovery.discover();
+        match endpoint.request_url(self.api_version.path()) {
+            Ok(request_url) => {
+                let mut current = match self.request_url.write() {
+                    Ok(current) => current,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+                if *current != request_url {
+                    opentelemetry::otel_debug!(
+                        name: "DatadogExporter.AgentEndpointChanged",
+                        previous = current.to_string(),
+                        current = request_url.to_string()
+                    );
+                    *current = request_url;
+                }
+            }
+            Err(err) => opentelemetry::otel_debug!(
+                name: "DatadogExporter.AgentDiscoveryFailed",
+                endpoint = format!("{endpoint:?}"),
+                error = err.to_string()
+            ),
+        }
+    }
+
+    /// Whether the payloads are currently compressed.
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    fn is_compressing(&self) -> bool {
+        let compressor = match self.compressor.read() {
+            Ok(compressor) => *compressor,
+            Err(poisoned) => *poisoned.into_inner(),
+        };
+        compressor.compression() != opentelemetry_contrib::compression::Compression::None
+    }
+
+    /// Fall back to a compression the agent accepts when it rejected the last one, returning
+    /// whether it did.
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    fn negotiate_compression<B>(&self, response: &http::Response<B>) -> bool {
+        if response.status() != http::StatusCode::UNSUPPORTED_MEDIA_TYPE {
+            return false;
+        }
+        let accept_encoding = response
+            .headers()
+            .get(http::header::ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok());
+        if let Ok(mut compressor) = self.compressor.write() {
+            *compressor = compressor.negotiate(accept_encoding);
+        }
+        true
+    }
 }
 
 impl Debug for DatadogExporter {
@@ -145,22 +341,44 @@ pub fn new_pipeline() -> DatadogPipelineBuilder {
 
 /// Builder for `ExporterConfig` struct.
 pub struct DatadogPipelineBuilder {
-    agent_endpoint: String,
+    agent_endpoint: Option<String>,
+    socket_supported: bool,
     trace_config: Option<Config>,
     api_version: ApiVersion,
     client: Option<Arc<dyn HttpClient>>,
     mapping: Mapping,
     unified_tags: UnifiedTags,
+    hostname: Option<String>,
+    hostname_reporting: Option<HostnameReporting>,
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    compressor: opentelemetry_contrib::compression::Compressor,
+    #[cfg(feature = "sampling-rules")]
+    sampling_rules: Option<SamplingRules>,
+    #[cfg(feature = "agent-sampling")]
+    agent_sampler: Option<DatadogAgentSampler>,
+    cardinality_guard: Option<TagCardinalityGuard>,
+    error_roll_up: bool,
 }
 
 impl Default for DatadogPipelineBuilder {
     fn default() -> Self {
         DatadogPipelineBuilder {
-            agent_endpoint: DEFAULT_AGENT_ENDPOINT.to_string(),
+            agent_endpoint: None,
+            socket_supported: false,
             trace_config: None,
             mapping: Mapping::empty(),
             api_version: ApiVersion::Version05,
             unified_tags: UnifiedTags::new(),
+            hostname: None,
+            hostname_reporting: None,
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            compressor: Default::default(),
+            #[cfg(feature = "sampling-rules")]
+            sampling_rules: None,
+            #[cfg(feature = "agent-sampling")]
+            agent_sampler: None,
+            cardinality_guard: None,
+            error_roll_up: false,
             #[cfg(all(
                 not(feature = "reqwest-client"),
                 not(feature = "reqwest-blocking-client"),
@@ -204,7 +422,25 @@ impl Debug for DatadogPipelineBuilder {
 impl DatadogPipelineBuilder {
     /// Building a new exporter.
     ///
-    /// This is useful if you are manually constructing a pipeline.
+    /// This is useful if you are manually constructing a pipeline, e.g. to export the spans of a
+    /// tracer provider to Datadog and to an OTLP collector:
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), opentelemetry_datadog::Error> {
+    /// use opentelemetry_datadog::DatadogExporter;
+    /// use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+    ///
+    /// let provider = SdkTracerProvider::builder()
+    ///     .with_resource(Resource::builder().with_service_name("my_app").build())
+    ///     .with_batch_exporter(DatadogExporter::builder().build_exporter()?)
+    ///     // .with_batch_exporter(otlp_exporter)
+    ///     .build();
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Unless set with [`with_service_name`](Self::with_service_name), the service name of the
+    /// spans is the `service.name` of the resource of the tracer provider.
     pub fn build_exporter(mut self) -> Result<DatadogExporter, Error> {
         let (_, service_name) = self.build_config_and_service_name();
         self.build_exporter_with_service_name(service_name)
@@ -246,21 +482,9 @@ impl DatadogPipelineBuilder {
 
     // parse the endpoint and append the path based on versions.
     // keep the query and host the same.
+    #[cfg(test)]
     fn build_endpoint(agent_endpoint: &str, version: &str) -> Result<Uri, Error> {
-        // build agent endpoint based on version
-        let mut endpoint = agent_endpoint
-            .parse::<Url>()
-            .map_err::<Error, _>(Into::into)?;
-        let mut paths = endpoint
-            .path_segments()
-            .map(|c| c.filter(|s| !s.is_empty()).collect::<Vec<_>>())
-            .unwrap_or_default();
-        paths.push(version);
-
-        let path_str = paths.join("/");
-        endpoint.set_path(path_str.as_str());
-
-        endpoint.as_str().parse().map_err::<Error, _>(Into::into)
+        AgentEndpoint::parse(agent_endpoint).request_url(version)
     }
 
     fn build_exporter_with_service_name(
@@ -269,21 +493,75 @@ impl DatadogPipelineBuilder {
     ) -> Result<DatadogExporter, Error> {
         if let Some(client) = self.client {
             let model_config = ModelConfig { service_name };
+            let service_name_from_resource = self.unified_tags.service().is_none();
+            let (endpoint, discovery) = match &self.agent_endpoint {
+                Some(agent_endpoint) => (AgentEndpoint::parse(agent_endpoint), None),
+                None => {
+                    let discovery = AgentDiscovery::new(self.socket_supported);
+                    (discovery.discover(), Some(discovery))
+                }
+            };
 
-            let exporter = DatadogExporter::new(
+            let mut unified_tags = self.unified_tags;
+            let hostname_reporting = self
+                .hostname_reporting
+                .unwrap_or_else(HostnameReporting::from_env);
+            let hostname = match hostname_reporting {
+                HostnameReporting::Disabled => None,
+                _ => hostname::resolve(self.hostname.as_deref()),
+            };
+            if let (HostnameReporting::Tag, Some(hostname)) = (hostname_reporting, &hostname) {
+                unified_tags.add_tag(HOSTNAME_TAG.to_string(), hostname.clone());
+            }
+
+            let mut exporter = DatadogExporter::new(
                 model_config,
-                Self::build_endpoint(&self.agent_endpoint, self.api_version.path())?,
+                endpoint.request_url(self.api_version.path())?,
                 self.api_version,
                 client,
                 self.mapping,
-                self.unified_tags,
+                unified_tags,
+                #[cfg(any(feature = "gzip", feature = "zstd"))]
+                self.compressor,
             );
+            exporter.discovery = discovery;
+            exporter.error_roll_up = self.error_roll_up;
+            exporter.cardinality_guard = self.cardinality_guard;
+            exporter.service_name_from_resource = service_name_from_resource;
+            if hostname_reporting == HostnameReporting::Header {
+                exporter.hostname_header = hostname.clone();
+            }
+            exporter.hostname = hostname;
+            #[cfg(feature = "sampling-rules")]
+            {
+                exporter.sampling_rules = match self.sampling_rules {
+                    Some(sampling_rules) => Some(sampling_rules),
+                    None => SamplingRules::from_env()?,
+                };
+            }
+            #[cfg(feature = "agent-sampling")]
+            if let Some(agent_sampler) = self.agent_sampler {
+                let agent_rates = agent_sampler.rates();
+                agent_rates.set_service(
+                    &exporter.model_config.service_name,
+                    exporter.unified_tags.env.value.as_deref(),
+                );
+                exporter.agent_rates = Some(agent_rates);
+            }
             Ok(exporter)
         } else {
             Err(Error::NoHttpClient)
         }
     }
 
+    /// Build a batch span processor exporting to Datadog with `config`, to add to a tracer
+    /// provider with other processors. See [`build_exporter`](Self::build_exporter).
+    pub fn build_batch_processor(self, config: BatchConfig) -> Result<BatchSpanProcessor, Error> {
+        Ok(BatchSpanProcessor::builder(self.build_exporter()?)
+            .with_batch_config(config)
+            .build())
+    }
+
     /// Install the Datadog trace exporter pipeline using a simple span processor.
     pub fn install_simple(mut self) -> Result<SdkTracerProvider, Error> {
         let (config, service_name) = self.build_config_and_service_name();
@@ -323,17 +601,126 @@ impl DatadogPipelineBuilder {
         self
     }
 
+    /// Add a tag to every exported span.
+    ///
+    /// Spans are also tagged with the `key:value` pairs of the `DD_TAGS` environment variable,
+    /// separated by commas or spaces, and with the `git.repository_url` and `git.commit.sha`
+    /// tags of the Datadog source code integration, from the `DD_GIT_REPOSITORY_URL` and
+    /// `DD_GIT_COMMIT_SHA` environment variables at runtime, or when building the crate.
+    pub fn with_tag<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.unified_tags.add_tag(key.into(), value.into());
+        self
+    }
+
+    /// Assign the hostname the traces are attributed to, when reported.
+    ///
+    /// The `DD_HOSTNAME` environment variable takes precedence. Without either, the hostname of
+    /// the operating system is used, or the EC2 instance id when running on EC2 with its default
+    /// hostname.
+    pub fn with_hostname<T: Into<String>>(mut self, hostname: T) -> Self {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
+    /// Choose how the hostname is reported to the agent, see [`HostnameReporting`].
+    ///
+    /// Defaults to [`HostnameReporting::Tag`] if the `DD_TRACE_REPORT_HOSTNAME` environment
+    /// variable is `true`, and [`HostnameReporting::Disabled`] otherwise.
+    pub fn with_hostname_reporting(mut self, hostname_reporting: HostnameReporting) -> Self {
+        self.hostname_reporting = Some(hostname_reporting);
+        self
+    }
+
     /// Assign the Datadog collector endpoint.
     ///
-    /// The endpoint of the datadog agent, by default it is `http://127.0.0.1:8126`.
+    /// `unix://` endpoints are sent to the path of a Unix domain socket, with the URI format of
+    /// the `hyperlocal` crate, which needs an [`HttpClient`] able to connect to it.
+    ///
+    /// By default the endpoint is discovered like the Datadog tracing libraries do, from the
+    /// first of:
+    ///
+    /// * the `DD_TRACE_AGENT_URL` environment variable,
+    /// * the `DD_AGENT_HOST` and `DD_TRACE_AGENT_PORT` environment variables, defaulting to
+    ///   `127.0.0.1` and `8126`,
+    /// * the `/var/run/datadog/apm.socket` socket if it exists, only when a client was set with
+    ///   [`with_unix_socket_client`](Self::with_unix_socket_client),
+    /// * `http://127.0.0.1:8126`.
+    ///
+    /// A discovered endpoint is resolved again after repeated connection failures.
     pub fn with_agent_endpoint<T: Into<String>>(mut self, endpoint: T) -> Self {
-        self.agent_endpoint = endpoint.into();
+        self.agent_endpoint = Some(endpoint.into());
         self
     }
 
     /// Choose the http client used by uploader
     pub fn with_http_client<T: HttpClient + 'static>(mut self, client: T) -> Self {
         self.client = Some(Arc::new(client));
+        self.socket_supported = false;
+        self
+    }
+
+    /// Choose an http client able to connect to Unix domain sockets, with the URI format of the
+    /// `hyperlocal` crate, so the agent socket is discovered as well.
+    ///
+    /// The `reqwest` and `surf` clients can't connect to Unix domain sockets.
+    pub fn with_unix_socket_client<T: HttpClient + 'static>(mut self, client: T) -> Self {
+        self.client = Some(Arc::new(client));
+        self.socket_supported = true;
+        self
+    }
+
+    /// Compress the payloads sent to the agent, bodies smaller than the compressor threshold are
+    /// sent uncompressed.
+    ///
+    /// If the agent rejects the encoding with `415 Unsupported Media Type`, the payload is sent
+    /// again once, and later payloads too, with another supported encoding listed in its
+    /// `Accept-Encoding` header, or uncompressed.
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    pub fn with_compression(
+        mut self,
+        compressor: opentelemetry_contrib::compression::Compressor,
+    ) -> Self {
+        self.compressor = compressor;
+        self
+    }
+
+    /// Sample the traces before exporting them with Datadog sampling rules, see
+    /// [`SamplingRules`].
+    ///
+    /// Defaults to the rules of the `DD_TRACE_SAMPLING_RULES` environment variable, if set.
+    #[cfg(feature = "sampling-rules")]
+    pub fn with_sampling_rules(mut self, sampling_rules: SamplingRules) -> Self {
+        self.sampling_rules = Some(sampling_rules);
+        self
+    }
+
+    /// Update the rates of `sampler` from the `rate_by_service` of the agent responses, see
+    /// [`DatadogAgentSampler`].
+    #[cfg(feature = "agent-sampling")]
+    pub fn with_agent_sampler(mut self, sampler: &DatadogAgentSampler) -> Self {
+        self.agent_sampler = Some(sampler.clone());
+        self
+    }
+
+    /// Limit the number of distinct values of each tag, see [`TagCardinalityGuard`].
+    pub fn with_tag_cardinality_guard(mut self, guard: TagCardinalityGuard) -> Self {
+        self.cardinality_guard = Some(guard);
+        self
+    }
+
+    /// Mark the local root span of a trace as an error when one of its child spans is, with the
+    /// `error.type`, `error.msg` and `error.stack` tags of the child, so that the error rates
+    /// measured by Datadog on service entry spans count the errors recorded on child spans.
+    /// Disabled by default.
+    ///
+    /// Spans are also tagged with the `error.*` tags of their `exception` events, as the Datadog
+    /// agent does for OTLP spans.
+    ///
+    /// The errors are only rolled up when the root span is exported in the same batch as the
+    /// failed child span: a child exported in an earlier batch, e.g. when the batch processor
+    /// exports on a timer while the request is still running, leaves the root span unmarked.
+    pub fn with_error_roll_up(mut self, error_roll_up: bool) -> Self {
+        self.error_roll_up = error_roll_up;
         self
     }
 
@@ -406,32 +793,88 @@ fn group_into_traces(spans: &mut [SpanData]) -> Vec<&[SpanData]> {
 async fn send_request(
     client: Arc<dyn HttpClient>,
     request: http::Request<Vec<u8>>,
-) -> OTelSdkResult {
+) -> Result<http::Response<bytes::Bytes>, OTelSdkError> {
     #[allow(deprecated)]
-    let response = client
+    client
         .send(request)
         .await
-        .map_err(|e| OTelSdkError::InternalFailure(format!("HTTP request failed: {e}")))?;
-
-    response
-        .error_for_status()
-        .map_err(|e| OTelSdkError::InternalFailure(format!("HTTP response error: {e}")))?;
-
-    Ok(())
+        .map_err(|e| OTelSdkError::InternalFailure(format!("HTTP request failed: {e}")))
 }
 
 impl SpanExporter for DatadogExporter {
     /// Export spans to datadog-agent
     async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
-        let request = match self.build_request(batch) {
+        #[cfg(feature = "self-metrics")]
+        let (count, start) = (batch.len() as u64, std::time::Instant::now());
+
+        let request = self.encode(batch).and_then(|(data, trace_count)| {
+            // Kept to send the batch again if the agent rejects its compression.
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            let resend = self.is_compressing().then(|| (data.clone(), trace_count));
+            #[cfg(not(any(feature = "gzip", feature = "zstd")))]
+            let resend = None::<(Vec<u8>, usize)>;
+            Ok((self.payload_request(data, trace_count)?, resend))
+        });
+        let (request, _resend) = match request {
             Ok(req) => req,
-            Err(err) => return Err(err),
+            Err(err) => {
+                #[cfg(feature = "self-metrics")]
+                self.metrics
+                    .record_failure(count, start.elapsed(), "encode");
+                return Err(err);
+            }
         };
 
-        let client = self.client.clone();
-        send_request(client, request).await
+        #[allow(unused_mut)]
+        let mut result = send_request(self.client.clone(), request).await;
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        if let Some((data, trace_count)) = _resend {
+            if result
+                .as_ref()
+                .is_ok_and(|response| self.negotiate_compression(response))
+            {
+                result = match self.payload_request(data, trace_count) {
+                    Ok(request) => send_request(self.client.clone(), request).await,
+                    Err(err) => Err(err),
+                };
+            }
+        }
+        self.track_connection(result.is_ok());
+        let result = result.and_then(|response| {
+            response
+                .error_for_status()
+                .map(|_response| {
+                    #[cfg(feature = "agent-sampling")]
+                    if let Some(agent_rates) = &self.agent_rates {
+                        agent_rates.update(_response.body());
+                    }
+                })
+                .map_err(|e| OTelSdkError::InternalFailure(format!("HTTP response error: {e}")))
+        });
+
+        #[cfg(feature = "self-metrics")]
+        match &result {
+            Ok(()) => self.metrics.record_success(count, start.elapsed()),
+            Err(_) => self
+                .metrics
+                .record_failure(count, start.elapsed(), "transport"),
+        }
+        result
     }
     fn set_resource(&mut self, resource: &Resource) {
+        if self.service_name_from_resource {
+            if let Some(service_name) = resource.get(&Key::new(semcov::resource::SERVICE_NAME)) {
+                self.model_config.service_name = service_name.to_string();
+            }
+        }
+        self.unified_tags.add_resource_tags(resource);
+        #[cfg(feature = "agent-sampling")]
+        if let Some(agent_rates) = &self.agent_rates {
+            agent_rates.set_service(
+                &self.model_config.service_name,
+                self.unified_tags.env.value.as_deref(),
+            );
+        }
         self.resource = Some(resource.clone());
     }
 }
@@ -537,6 +980,40 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn test_service_name_from_resource() {
+        let resource = Resource::builder_empty()
+            .with_service_name("resource_service")
+            .build();
+
+        let mut exporter = DatadogExporter::builder()
+            .with_http_client(DummyClient)
+            .build_exporter()
+            .unwrap();
+        exporter.set_resource(&resource);
+        assert_eq!(exporter.model_config.service_name, "resource_service");
+
+        let mut exporter = DatadogExporter::builder()
+            .with_service_name("test_service")
+            .with_http_client(DummyClient)
+            .build_exporter()
+            .unwrap();
+        exporter.set_resource(&resource);
+        assert_eq!(exporter.model_config.service_name, "test_service");
+    }
+
+    #[test]
+    fn test_build_batch_processor() {
+        let processor = DatadogExporter::builder()
+            .with_http_client(DummyClient)
+            .build_batch_processor(BatchConfig::default())
+            .unwrap();
+        let provider = SdkTracerProvider::builder()
+            .with_span_processor(processor)
+            .build();
+        let _ = provider.shutdown();
+    }
+
     #[test]
     fn test_install_simple() {
         new_pipeline()
@@ -554,4 +1031,87 @@ mod tests {
             .install_batch()
             .unwrap();
     }
+
+    #[cfg(feature = "sampling-rules")]
+    #[test]
+    fn test_sampling_rules() {
+        let exporter = new_pipeline()
+            .with_http_client(DummyClient)
+            .with_sampling_rules(
+                SamplingRules::from_json(r#"[{"resource": "resource", "sample_rate": 0}]"#)
+                    .unwrap(),
+            )
+            .build_exporter()
+            .unwrap();
+
+        let request = exporter
+            .build_request(vec![get_span(1, 1, 1), get_span(2, 2, 2)])
+            .unwrap();
+        assert_eq!(request.headers()[DATADOG_TRACE_COUNT_HEADER], "0");
+    }
+
+    #[cfg(feature = "sampling-rules")]
+    #[test]
+    fn test_tag_cardinality_guard_after_sampling() {
+        let exporter = new_pipeline()
+            .with_http_client(DummyClient)
+            .with_sampling_rules(
+                SamplingRules::from_json(r#"[{"resource": "dropped", "sample_rate": 0}]"#).unwrap(),
+            )
+            .with_tag_cardinality_guard(TagCardinalityGuard::new(1))
+            .build_exporter()
+            .unwrap();
+
+        let mut dropped = get_span(1, 1, 1);
+        dropped.name = "dropped".into();
+        dropped.attributes.push(KeyValue::new("user.id", "a"));
+        let mut kept = get_span(2, 2, 2);
+        kept.attributes.push(KeyValue::new("user.id", "b"));
+        let request = exporter.build_request(vec![dropped, kept]).unwrap();
+        assert_eq!(request.headers()[DATADOG_TRACE_COUNT_HEADER], "1");
+
+        // Only the value of the kept trace was counted.
+        let mut spans = vec![get_span(3, 3, 3), get_span(4, 4, 4)];
+        spans[0].attributes.push(KeyValue::new("user.id", "b"));
+        spans[1].attributes.push(KeyValue::new("user.id", "a"));
+        exporter
+            .cardinality_guard
+            .as_ref()
+            .unwrap()
+            .apply(&mut spans);
+        assert_eq!(spans[0].attributes[1].value, "b".into());
+        assert_eq!(
+            spans[1].attributes[1].value,
+            cardinality::OVERFLOW_VALUE.into()
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_negotiate_compression() {
+        use opentelemetry_contrib::compression::{Compression, Compressor};
+
+        let exporter = new_pipeline()
+            .with_http_client(DummyClient)
+            .with_compression(Compressor::new(Compression::Gzip))
+            .build_exporter()
+            .unwrap();
+
+        assert!(!exporter.negotiate_compression(&http::Response::new(())));
+        assert_eq!(
+            exporter.compressor.read().unwrap().compression(),
+            Compression::Gzip
+        );
+
+        let rejected = http::Response::builder()
+            .status(http::StatusCode::UNSUPPORTED_MEDIA_TYPE)
+            .header(http::header::ACCEPT_ENCODING, "identity")
+            .body(())
+            .unwrap();
+        assert!(exporter.negotiate_compression(&rejected));
+        assert_eq!(
+            exporter.compressor.read().unwrap().compression(),
+            Compression::None
+        );
+    }
 }
@@ -0,0 +1,75 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use opentelemetry::{Key, KeyValue};
+use opentelemetry_sdk::trace::SpanData;
+
+/// Prefix of the span attributes carrying a `meta_struct` payload.
+const META_STRUCT_PREFIX: &str = "_dd.meta_struct.";
+
+/// Create a span attribute attaching `payload` to the `meta_struct` field of the Datadog span,
+/// under `name`.
+///
+/// `meta_struct` holds msgpack encoded payloads the agent forwards verbatim, such as security
+/// signals or dynamic instrumentation snapshots. `payload` must be valid msgpack, it is not
+/// inspected by the exporter.
+///
+/// The attribute is not exported as a tag. `meta_struct` is only supported by
+/// [`ApiVersion::Version03`] and [`ApiVersion::Version07`], [`ApiVersion::Version05`] drops the
+/// payloads.
+///
+/// ```
+/// use opentelemetry::trace::{Span, Tracer};
+///
+/// # fn record(tracer: &impl Tracer, appsec_payload: &[u8]) {
+/// let mut span = tracer.start("request");
+/// span.set_attribute(opentelemetry_datadog::meta_struct_attribute("appsec", appsec_payload));
+/// # }
+/// ```
+///
+/// [`ApiVersion::Version03`]: crate::ApiVersion::Version03
+/// [`ApiVersion::Version05`]: crate::ApiVersion::Version05
+/// [`ApiVersion::Version07`]: crate::ApiVersion::Version07
+pub fn meta_struct_attribute(name: &str, payload: &[u8]) -> KeyValue {
+    KeyValue::new(
+        format!("{META_STRUCT_PREFIX}{name}"),
+        STANDARD.encode(payload),
+    )
+}
+
+/// Whether the attribute `key` carries a `meta_struct` payload rather than a tag.
+pub(crate) fn is_meta_struct(key: &Key) -> bool {
+    key.as_str().starts_with(META_STRUCT_PREFIX)
+}
+
+/// The `meta_struct` payloads of `span`, by name.
+///
+/// Payloads which are not valid base64, i.e. not created by [`meta_struct_attribute`], are
+/// skipped.
+pub(crate) fn decode(span: &SpanData) -> Vec<(&str, Vec<u8>)> {
+    span.attributes
+        .iter()
+        .filter_map(|kv| {
+            let name = kv.key.as_str().strip_prefix(META_STRUCT_PREFIX)?;
+            let payload = STANDARD.decode(kv.value.as_str().as_bytes()).ok()?;
+            Some((name, payload))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter::model::tests::get_span;
+
+    #[test]
+    fn test_decode() {
+        let mut span = get_span(7, 1, 99);
+        span.attributes
+            .push(meta_struct_attribute("appsec", &[0x81, 0xa1, 0x61, 0x01]));
+        span.attributes
+            .push(KeyValue::new("_dd.meta_struct.invalid", "not base64!"));
+
+        assert!(is_meta_struct(&span.attributes[1].key));
+        assert!(!is_meta_struct(&span.attributes[0].key));
+        assert_eq!(decode(&span), [("appsec", vec![0x81, 0xa1, 0x61, 0x01])]);
+    }
+}
@@ -11,9 +11,11 @@ use self::unified_tags::UnifiedTags;
 
 use super::Mapping;
 
+pub(crate) mod meta_struct;
 pub mod unified_tags;
 mod v03;
 mod v05;
+mod v07;
 
 // todo: we should follow the same mapping defined in https://github.com/DataDog/datadog-agent/blob/main/pkg/trace/api/otlp.go
 
@@ -23,6 +25,9 @@ static SAMPLING_PRIORITY_KEY: &str = "_sampling_priority_v1";
 // https://github.com/DataDog/datadog-agent/blob/ec96f3c24173ec66ba235bda7710504400d9a000/pkg/trace/traceutil/span.go#L20
 static DD_MEASURED_KEY: &str = "_dd.measured";
 
+// https://github.com/DataDog/dd-trace-go/blob/v1.72.1/ddtrace/tracer/sampler.go#L22
+static RULE_SAMPLING_RATE_KEY: &str = "_dd.rule_psr";
+
 /// Custom mapping between opentelemetry spans and datadog spans.
 ///
 /// User can provide custom function to change the mapping. It currently supports customizing the following
@@ -77,6 +82,45 @@ fn default_resource_mapping<'a>(span: &'a SpanData, _config: &'a ModelConfig) ->
     span.name.as_ref()
 }
 
+impl Mapping {
+    pub(crate) fn service_name<'a>(
+        &'a self,
+        span: &'a SpanData,
+        config: &'a ModelConfig,
+    ) -> &'a str {
+        match &self.service_name {
+            Some(f) => f(span, config),
+            None => default_service_name_mapping(span, config),
+        }
+    }
+
+    pub(crate) fn name<'a>(&'a self, span: &'a SpanData, config: &'a ModelConfig) -> &'a str {
+        match &self.name {
+            Some(f) => f(span, config),
+            None => default_name_mapping(span, config),
+        }
+    }
+
+    pub(crate) fn resource<'a>(&'a self, span: &'a SpanData, config: &'a ModelConfig) -> &'a str {
+        match &self.resource {
+            Some(f) => f(span, config),
+            None => default_resource_mapping(span, config),
+        }
+    }
+}
+
+/// The span of `trace` whose parent is not part of the trace, or the first span.
+pub(crate) fn local_root(trace: &[SpanData]) -> Option<&SpanData> {
+    trace
+        .iter()
+        .find(|span| {
+            !trace
+                .iter()
+                .any(|other| other.span_context.span_id() == span.parent_span_id)
+        })
+        .or_else(|| trace.first())
+}
+
 /// Wrap type for errors from opentelemetry datadog exporter
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -125,10 +169,13 @@ impl From<uri::InvalidUri> for Error {
 #[derive(Debug, Copy, Clone)]
 #[non_exhaustive]
 pub enum ApiVersion {
-    /// Version 0.3
+    /// Version 0.3, supporting [`meta_struct`](crate::meta_struct_attribute)
     Version03,
     /// Version 0.5 - requires datadog-agent v7.22.0 or above
     Version05,
+    /// Version 0.7, grouping the spans of a trace in a chunk carrying the sampling priority and
+    /// origin of the trace - requires datadog-agent v7.34.0 or above
+    Version07,
 }
 
 impl ApiVersion {
@@ -136,6 +183,7 @@ impl ApiVersion {
         match self {
             ApiVersion::Version03 => "/v0.3/traces",
             ApiVersion::Version05 => "/v0.5/traces",
+            ApiVersion::Version07 => "/v0.7/traces",
         }
     }
 
@@ -143,52 +191,47 @@ impl ApiVersion {
         match self {
             ApiVersion::Version03 => "application/msgpack",
             ApiVersion::Version05 => "application/msgpack",
+            ApiVersion::Version07 => "application/msgpack",
         }
     }
 
     pub(crate) fn encode(
         self,
         model_config: &ModelConfig,
-        traces: Vec<&[trace::SpanData]>,
+        traces: Vec<(&[trace::SpanData], Option<f64>)>,
         mapping: &Mapping,
         unified_tags: &UnifiedTags,
         resource: Option<&Resource>,
+        hostname: Option<&str>,
     ) -> Result<Vec<u8>, Error> {
         match self {
             Self::Version03 => v03::encode(
                 model_config,
                 traces,
-                |span, config| match &mapping.service_name {
-                    Some(f) => f(span, config),
-                    None => default_service_name_mapping(span, config),
-                },
-                |span, config| match &mapping.name {
-                    Some(f) => f(span, config),
-                    None => default_name_mapping(span, config),
-                },
-                |span, config| match &mapping.resource {
-                    Some(f) => f(span, config),
-                    None => default_resource_mapping(span, config),
-                },
+                |span, config| mapping.service_name(span, config),
+                |span, config| mapping.name(span, config),
+                |span, config| mapping.resource(span, config),
+                &unified_tags.tags,
                 resource,
             ),
             Self::Version05 => v05::encode(
                 model_config,
                 traces,
-                |span, config| match &mapping.service_name {
-                    Some(f) => f(span, config),
-                    None => default_service_name_mapping(span, config),
-                },
-                |span, config| match &mapping.name {
-                    Some(f) => f(span, config),
-                    None => default_name_mapping(span, config),
-                },
-                |span, config| match &mapping.resource {
-                    Some(f) => f(span, config),
-                    None => default_resource_mapping(span, config),
-                },
+                |span, config| mapping.service_name(span, config),
+                |span, config| mapping.name(span, config),
+                |span, config| mapping.resource(span, config),
+                unified_tags,
+                resource,
+            ),
+            Self::Version07 => v07::encode(
+                model_config,
+                traces,
+                |span, config| mapping.service_name(span, config),
+                |span, config| mapping.name(span, config),
+                |span, config| mapping.resource(span, config),
                 unified_tags,
                 resource,
+                hostname,
             ),
         }
     }
@@ -259,10 +302,11 @@ pub(crate) mod tests {
             .build();
         let encoded = STANDARD.encode(ApiVersion::Version03.encode(
             &model_config,
-            traces.iter().map(|x| &x[..]).collect(),
+            traces.iter().map(|x| (&x[..], None)).collect(),
             &Mapping::empty(),
             &UnifiedTags::new(),
             Some(&resource),
+            None,
         )?);
 
         assert_eq!(encoded.as_str(), "kZGMpHR5cGWjd2Vip3NlcnZpY2Wsc2VydmljZV9uYW1lpG5hbWWpY29tcG9uZW\
@@ -273,6 +317,81 @@ pub(crate) mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_encode_v03_meta_struct() -> Result<(), Box<dyn std::error::Error>> {
+        let mut span = get_span(7, 1, 99);
+        span.attributes
+            .push(meta_struct::meta_struct_attribute("appsec", &[0xc3]));
+        let model_config = ModelConfig {
+            service_name: "service_name".to_string(),
+            ..Default::default()
+        };
+        let encoded = ApiVersion::Version03.encode(
+            &model_config,
+            vec![(&[span][..], None)],
+            &Mapping::empty(),
+            &UnifiedTags::new(),
+            None,
+            None,
+        )?;
+
+        // map with a single bin 8 entry of 1 byte
+        let mut expected_meta_struct = vec![0xab];
+        expected_meta_struct.extend_from_slice(b"meta_struct");
+        expected_meta_struct.extend_from_slice(&[0x81, 0xa6]);
+        expected_meta_struct.extend_from_slice(b"appsec");
+        expected_meta_struct.extend_from_slice(&[0xc4, 0x01, 0xc3]);
+        assert!(encoded.ends_with(&expected_meta_struct));
+        assert!(!encoded
+            .windows(b"_dd.meta_struct".len())
+            .any(|window| window == b"_dd.meta_struct"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_v07() -> Result<(), Box<dyn std::error::Error>> {
+        let mut root = get_span(7, 0, 1);
+        root.attributes
+            .push(KeyValue::new("_dd.origin", "synthetics"));
+        let trace = [root, get_span(7, 1, 2)];
+        let model_config = ModelConfig {
+            service_name: "service_name".to_string(),
+            ..Default::default()
+        };
+        let mut unified_tags = UnifiedTags::new();
+        unified_tags.set_env(Some(String::from("test-env")));
+        unified_tags.set_version(None);
+
+        let encoded = ApiVersion::Version07.encode(
+            &model_config,
+            vec![(&trace[..], Some(0.5))],
+            &Mapping::empty(),
+            &unified_tags,
+            None,
+            Some("test-host"),
+        )?;
+
+        let mut reader = &encoded[..];
+        assert_eq!(rmp::decode::read_map_len(&mut reader)?, 5);
+        let mut buf = [0; 32];
+        assert_eq!(
+            rmp::decode::read_str(&mut reader, &mut buf).unwrap(),
+            "language_name"
+        );
+        let contains = |bytes: &[u8]| encoded.windows(bytes.len()).any(|window| window == bytes);
+        // A single chunk, with the priority, origin and decision maker of the trace.
+        assert!(contains(b"\xa6chunks\x91\x84\xa8priority\xd2"));
+        assert!(contains(b"\xa6origin\xaasynthetics"));
+        assert!(contains(b"\xa4tags\x81\xa8_dd.p.dm\xa2-3\xa5spans\x92"));
+        assert!(contains(b"\xa3env\xa8test-env"));
+        assert!(contains(b"\xa8hostname\xa9test-host"));
+        assert!(!contains(SAMPLING_PRIORITY_KEY.as_bytes()));
+        assert!(contains(RULE_SAMPLING_RATE_KEY.as_bytes()));
+
+        Ok(())
+    }
+
     #[test]
     fn test_encode_v05() -> Result<(), Box<dyn std::error::Error>> {
         let traces = get_traces();
@@ -291,10 +410,11 @@ pub(crate) mod tests {
 
         let _encoded = STANDARD.encode(ApiVersion::Version05.encode(
             &model_config,
-            traces.iter().map(|x| &x[..]).collect(),
+            traces.iter().map(|x| (&x[..], None)).collect(),
             &Mapping::empty(),
             &unified_tags,
             Some(&resource),
+            None,
         )?);
 
         // TODO: Need someone to generate the expected result or instructions to do so.
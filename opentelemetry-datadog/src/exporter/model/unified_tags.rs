@@ -1,18 +1,106 @@
+use opentelemetry::Key;
+use opentelemetry_sdk::Resource;
+use opentelemetry_semantic_conventions as semcov;
+
+/// Environment variable holding the tags added to every span.
+const DD_TAGS: &str = "DD_TAGS";
+/// Environment variables holding the source code metadata, read at runtime or when building.
+const DD_GIT_REPOSITORY_URL: &str = "DD_GIT_REPOSITORY_URL";
+const DD_GIT_COMMIT_SHA: &str = "DD_GIT_COMMIT_SHA";
+
+/// Container and Kubernetes resource attributes, and the Datadog tags holding them, see
+/// https://docs.datadoghq.com/opentelemetry/schema_semantics/semantic_mapping
+const RESOURCE_TAGS: [(&str, &str); 13] = [
+    (semcov::resource::CONTAINER_ID, "container_id"),
+    (semcov::resource::CONTAINER_NAME, "container_name"),
+    (semcov::resource::CONTAINER_IMAGE_NAME, "image_name"),
+    (semcov::resource::K8S_CLUSTER_NAME, "kube_cluster_name"),
+    (semcov::resource::K8S_NAMESPACE_NAME, "kube_namespace"),
+    (semcov::resource::K8S_POD_NAME, "pod_name"),
+    (semcov::resource::K8S_CONTAINER_NAME, "kube_container_name"),
+    (semcov::resource::K8S_DEPLOYMENT_NAME, "kube_deployment"),
+    (semcov::resource::K8S_DAEMONSET_NAME, "kube_daemon_set"),
+    (semcov::resource::K8S_STATEFULSET_NAME, "kube_stateful_set"),
+    (semcov::resource::K8S_REPLICASET_NAME, "kube_replica_set"),
+    (semcov::resource::K8S_JOB_NAME, "kube_job"),
+    (semcov::resource::K8S_CRONJOB_NAME, "kube_cronjob"),
+];
+
 /// Unified tags - See: https://docs.datadoghq.com/getting_started/tagging/unified_service_tagging
 pub struct UnifiedTags {
     pub service: UnifiedTagField,
     pub env: UnifiedTagField,
     pub version: UnifiedTagField,
+    /// Tags added to every span, from `DD_TAGS` and the source code metadata, see
+    /// https://docs.datadoghq.com/integrations/guide/source-code-integration
+    pub tags: Vec<(String, String)>,
 }
 
 impl UnifiedTags {
     pub fn new() -> Self {
-        UnifiedTags {
+        let mut unified_tags = UnifiedTags {
             service: UnifiedTagField::new(UnifiedTagEnum::Service),
             env: UnifiedTagField::new(UnifiedTagEnum::Env),
             version: UnifiedTagField::new(UnifiedTagEnum::Version),
+            tags: Vec::new(),
+        };
+        unified_tags.add_global_tags(|name| std::env::var(name).ok());
+        unified_tags
+    }
+
+    fn add_global_tags(&mut self, var: impl Fn(&str) -> Option<String>) {
+        for (key, value) in var(DD_TAGS).as_deref().map(parse_tags).unwrap_or_default() {
+            // `DD_SERVICE`, `DD_ENV` and `DD_VERSION` take precedence over `DD_TAGS`
+            match key.as_str() {
+                "service" => self.service.value = self.service.value.take().or(Some(value)),
+                "env" => self.env.value = self.env.value.take().or(Some(value)),
+                "version" => self.version.value = self.version.value.take().or(Some(value)),
+                _ => self.add_tag(key, value),
+            }
+        }
+        let repository_url = var(DD_GIT_REPOSITORY_URL)
+            .or_else(|| option_env!("DD_GIT_REPOSITORY_URL").map(Into::into));
+        let commit_sha =
+            var(DD_GIT_COMMIT_SHA).or_else(|| option_env!("DD_GIT_COMMIT_SHA").map(Into::into));
+        for (key, value) in [
+            ("git.repository_url", repository_url),
+            ("git.commit.sha", commit_sha),
+        ] {
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                self.add_tag(key.to_string(), value);
+            }
+        }
+    }
+
+    /// Add `key` to the tags of every span, replacing its previous value.
+    pub fn add_tag(&mut self, key: String, value: String) {
+        match self.tags.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => self.tags.push((key, value)),
         }
     }
+
+    /// Add the container and Kubernetes attributes of `resource`, e.g. found by the detectors
+    /// of `opentelemetry-resource-detectors`, to the tags of every span under their Datadog
+    /// names, like `kube_namespace` for `k8s.namespace.name`.
+    ///
+    /// Tags already set, from `DD_TAGS` or [`DatadogPipelineBuilder::with_tag`], are kept.
+    ///
+    /// [`DatadogPipelineBuilder::with_tag`]: crate::DatadogPipelineBuilder::with_tag
+    pub fn add_resource_tags(&mut self, resource: &Resource) {
+        for (attribute, tag) in RESOURCE_TAGS {
+            if self.tags.iter().any(|(key, _)| key == tag) {
+                continue;
+            }
+            if let Some(value) = resource.get(&Key::from_static_str(attribute)) {
+                let value = value.to_string();
+                if !value.is_empty() {
+                    self.tags.push((tag.to_string(), value));
+                }
+            }
+        }
+    }
+
     pub fn set_service(&mut self, service: Option<String>) {
         self.service.value = service;
     }
@@ -26,10 +114,24 @@ impl UnifiedTags {
         self.service.value.clone()
     }
     pub fn compute_attribute_size(&self) -> u32 {
-        self.service.len() + self.env.len() + self.version.len()
+        self.service.len() + self.env.len() + self.version.len() + self.tags.len() as u32
     }
 }
 
+/// Parse tags in the `DD_TAGS` format, `key:value` pairs separated by commas or spaces.
+fn parse_tags(tags: &str) -> Vec<(String, String)> {
+    let separator = if tags.contains(',') { ',' } else { ' ' };
+    tags.split(separator)
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| match tag.split_once(':') {
+            Some((key, value)) => (key.trim().to_string(), value.trim().to_string()),
+            None => (tag.to_string(), String::new()),
+        })
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
 pub struct UnifiedTagField {
     pub value: Option<String>,
     pub kind: UnifiedTagEnum,
@@ -86,6 +188,7 @@ impl UnifiedTagEnum {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use opentelemetry::KeyValue;
 
     #[test]
     fn test_service() {
@@ -107,6 +210,103 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(
+            parse_tags("team:billing, region:eu-west-1,flag"),
+            [
+                ("team".to_string(), "billing".to_string()),
+                ("region".to_string(), "eu-west-1".to_string()),
+                ("flag".to_string(), String::new()),
+            ]
+        );
+        assert_eq!(
+            parse_tags("a:1  b:http://host:80"),
+            [
+                ("a".to_string(), "1".to_string()),
+                ("b".to_string(), "http://host:80".to_string()),
+            ]
+        );
+        assert!(parse_tags(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_global_tags() {
+        let mut unified_tags = UnifiedTags {
+            service: UnifiedTagField {
+                value: None,
+                kind: UnifiedTagEnum::Service,
+            },
+            env: UnifiedTagField {
+                value: Some("prod".to_string()),
+                kind: UnifiedTagEnum::Env,
+            },
+            version: UnifiedTagField {
+                value: None,
+                kind: UnifiedTagEnum::Version,
+            },
+            tags: Vec::new(),
+        };
+        unified_tags.add_global_tags(|name| match name {
+            DD_TAGS => Some("env:staging,service:api,team:billing,team:payments".to_string()),
+            DD_GIT_COMMIT_SHA => Some("abc123".to_string()),
+            DD_GIT_REPOSITORY_URL => Some("github.com/org/repo".to_string()),
+            _ => None,
+        });
+
+        assert_eq!(unified_tags.env.value.as_deref(), Some("prod"));
+        assert_eq!(unified_tags.service(), Some("api".to_string()));
+        assert_eq!(
+            unified_tags.tags,
+            [
+                ("team".to_string(), "payments".to_string()),
+                (
+                    "git.repository_url".to_string(),
+                    "github.com/org/repo".to_string()
+                ),
+                ("git.commit.sha".to_string(), "abc123".to_string()),
+            ]
+        );
+        assert_eq!(unified_tags.compute_attribute_size(), 5);
+    }
+
+    #[test]
+    fn test_resource_tags() {
+        let mut unified_tags = UnifiedTags {
+            service: UnifiedTagField {
+                value: None,
+                kind: UnifiedTagEnum::Service,
+            },
+            env: UnifiedTagField {
+                value: None,
+                kind: UnifiedTagEnum::Env,
+            },
+            version: UnifiedTagField {
+                value: None,
+                kind: UnifiedTagEnum::Version,
+            },
+            tags: vec![("kube_namespace".to_string(), "override".to_string())],
+        };
+        let resource = Resource::builder_empty()
+            .with_attributes([
+                KeyValue::new("k8s.namespace.name", "payments"),
+                KeyValue::new("k8s.pod.name", "api-7d9f-x2"),
+                KeyValue::new("container.id", "4a2f9c"),
+                KeyValue::new("host.name", "node-1"),
+            ])
+            .build();
+        unified_tags.add_resource_tags(&resource);
+
+        assert_eq!(
+            unified_tags.tags,
+            [
+                ("kube_namespace".to_string(), "override".to_string()),
+                ("container_id".to_string(), "4a2f9c".to_string()),
+                ("pod_name".to_string(), "api-7d9f-x2".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn test_version() {
         temp_env::with_var("DD_VERSION", Some("test-version-1.2.3"), || {
@@ -1,4 +1,4 @@
-use crate::exporter::model::{Error, SAMPLING_PRIORITY_KEY};
+use crate::exporter::model::{meta_struct, Error, RULE_SAMPLING_RATE_KEY, SAMPLING_PRIORITY_KEY};
 use crate::exporter::ModelConfig;
 use opentelemetry::trace::Status;
 use opentelemetry_sdk::trace::SpanData;
@@ -7,10 +7,11 @@ use std::time::SystemTime;
 
 pub(crate) fn encode<S, N, R>(
     model_config: &ModelConfig,
-    traces: Vec<&[SpanData]>,
+    traces: Vec<(&[SpanData], Option<f64>)>,
     get_service_name: S,
     get_name: N,
     get_resource: R,
+    tags: &[(String, String)],
     resource: Option<&Resource>,
 ) -> Result<Vec<u8>, Error>
 where
@@ -21,110 +22,167 @@ where
     let mut encoded = Vec::new();
     rmp::encode::write_array_len(&mut encoded, traces.len() as u32)?;
 
-    for trace in traces.into_iter() {
+    for (trace, rule_rate) in traces.into_iter() {
         rmp::encode::write_array_len(&mut encoded, trace.len() as u32)?;
 
         for span in trace {
-            // Safe until the year 2262 when Datadog will need to change their API
-            let start = span
-                .start_time
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_nanos() as i64;
-
-            let duration = span
-                .end_time
-                .duration_since(span.start_time)
-                .map(|x| x.as_nanos() as i64)
-                .unwrap_or(0);
-
-            let mut span_type_found = false;
-            for kv in &span.attributes {
-                if kv.key.as_str() == "span.type" {
-                    span_type_found = true;
-                    rmp::encode::write_map_len(&mut encoded, 12)?;
-                    rmp::encode::write_str(&mut encoded, "type")?;
-                    rmp::encode::write_str(&mut encoded, kv.value.as_str().as_ref())?;
-                    break;
-                }
-            }
-
-            if !span_type_found {
-                rmp::encode::write_map_len(&mut encoded, 11)?;
-            }
-
-            // Datadog span name is OpenTelemetry component name - see module docs for more information
-            rmp::encode::write_str(&mut encoded, "service")?;
-            rmp::encode::write_str(&mut encoded, get_service_name(span, model_config))?;
-
-            rmp::encode::write_str(&mut encoded, "name")?;
-            rmp::encode::write_str(&mut encoded, get_name(span, model_config))?;
-
-            rmp::encode::write_str(&mut encoded, "resource")?;
-            rmp::encode::write_str(&mut encoded, get_resource(span, model_config))?;
-
-            rmp::encode::write_str(&mut encoded, "trace_id")?;
-            rmp::encode::write_u64(
+            let sampling_priority = if span.span_context.is_sampled() {
+                1.0
+            } else {
+                0.0
+            };
+            write_span(
                 &mut encoded,
-                u128::from_be_bytes(span.span_context.trace_id().to_bytes()) as u64,
-            )?;
-
-            rmp::encode::write_str(&mut encoded, "span_id")?;
-            rmp::encode::write_u64(
-                &mut encoded,
-                u64::from_be_bytes(span.span_context.span_id().to_bytes()),
+                model_config,
+                span,
+                &get_service_name,
+                &get_name,
+                &get_resource,
+                tags,
+                resource,
+                Some(sampling_priority),
+                rule_rate,
             )?;
+        }
+    }
 
-            rmp::encode::write_str(&mut encoded, "parent_id")?;
-            rmp::encode::write_u64(
-                &mut encoded,
-                u64::from_be_bytes(span.parent_span_id.to_bytes()),
-            )?;
+    Ok(encoded)
+}
 
-            rmp::encode::write_str(&mut encoded, "start")?;
-            rmp::encode::write_i64(&mut encoded, start)?;
+/// Write `span` in the span format shared by the v0.3 and v0.7 APIs, with the
+/// `_sampling_priority_v1` metric if `sampling_priority` is set.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn write_span<S, N, R>(
+    encoded: &mut Vec<u8>,
+    model_config: &ModelConfig,
+    span: &SpanData,
+    get_service_name: &S,
+    get_name: &N,
+    get_resource: &R,
+    tags: &[(String, String)],
+    resource: Option<&Resource>,
+    sampling_priority: Option<f64>,
+    rule_rate: Option<f64>,
+) -> Result<(), Error>
+where
+    for<'a> S: Fn(&'a SpanData, &'a ModelConfig) -> &'a str,
+    for<'a> N: Fn(&'a SpanData, &'a ModelConfig) -> &'a str,
+    for<'a> R: Fn(&'a SpanData, &'a ModelConfig) -> &'a str,
+{
+    // Safe until the year 2262 when Datadog will need to change their API
+    let start = span
+        .start_time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as i64;
+
+    let duration = span
+        .end_time
+        .duration_since(span.start_time)
+        .map(|x| x.as_nanos() as i64)
+        .unwrap_or(0);
+
+    let span_type = span
+        .attributes
+        .iter()
+        .find(|kv| kv.key.as_str() == "span.type");
+    let meta_struct = meta_struct::decode(span);
+
+    rmp::encode::write_map_len(
+        encoded,
+        11 + u32::from(span_type.is_some()) + u32::from(!meta_struct.is_empty()),
+    )?;
+    if let Some(kv) = span_type {
+        rmp::encode::write_str(encoded, "type")?;
+        rmp::encode::write_str(encoded, kv.value.as_str().as_ref())?;
+    }
 
-            rmp::encode::write_str(&mut encoded, "duration")?;
-            rmp::encode::write_i64(&mut encoded, duration)?;
+    // Datadog span name is OpenTelemetry component name - see module docs for more information
+    rmp::encode::write_str(encoded, "service")?;
+    rmp::encode::write_str(encoded, get_service_name(span, model_config))?;
+
+    rmp::encode::write_str(encoded, "name")?;
+    rmp::encode::write_str(encoded, get_name(span, model_config))?;
+
+    rmp::encode::write_str(encoded, "resource")?;
+    rmp::encode::write_str(encoded, get_resource(span, model_config))?;
+
+    rmp::encode::write_str(encoded, "trace_id")?;
+    rmp::encode::write_u64(
+        encoded,
+        u128::from_be_bytes(span.span_context.trace_id().to_bytes()) as u64,
+    )?;
+
+    rmp::encode::write_str(encoded, "span_id")?;
+    rmp::encode::write_u64(
+        encoded,
+        u64::from_be_bytes(span.span_context.span_id().to_bytes()),
+    )?;
+
+    rmp::encode::write_str(encoded, "parent_id")?;
+    rmp::encode::write_u64(encoded, u64::from_be_bytes(span.parent_span_id.to_bytes()))?;
+
+    rmp::encode::write_str(encoded, "start")?;
+    rmp::encode::write_i64(encoded, start)?;
+
+    rmp::encode::write_str(encoded, "duration")?;
+    rmp::encode::write_i64(encoded, duration)?;
+
+    rmp::encode::write_str(encoded, "error")?;
+    rmp::encode::write_i32(
+        encoded,
+        match span.status {
+            Status::Error { .. } => 1,
+            _ => 0,
+        },
+    )?;
+
+    let span_tags = span
+        .attributes
+        .iter()
+        .filter(|kv| !meta_struct::is_meta_struct(&kv.key));
+
+    rmp::encode::write_str(encoded, "meta")?;
+    rmp::encode::write_map_len(
+        encoded,
+        (span_tags.clone().count() + resource.map(|r| r.len()).unwrap_or(0) + tags.len()) as u32,
+    )?;
+    if let Some(resource) = resource {
+        for (key, value) in resource.iter() {
+            rmp::encode::write_str(encoded, key.as_str())?;
+            rmp::encode::write_str(encoded, value.as_str().as_ref())?;
+        }
+    }
+    for (key, value) in tags {
+        rmp::encode::write_str(encoded, key)?;
+        rmp::encode::write_str(encoded, value)?;
+    }
+    for kv in span_tags {
+        rmp::encode::write_str(encoded, kv.key.as_str())?;
+        rmp::encode::write_str(encoded, kv.value.as_str().as_ref())?;
+    }
 
-            rmp::encode::write_str(&mut encoded, "error")?;
-            rmp::encode::write_i32(
-                &mut encoded,
-                match span.status {
-                    Status::Error { .. } => 1,
-                    _ => 0,
-                },
-            )?;
+    rmp::encode::write_str(encoded, "metrics")?;
+    rmp::encode::write_map_len(
+        encoded,
+        u32::from(sampling_priority.is_some()) + u32::from(rule_rate.is_some()),
+    )?;
+    if let Some(sampling_priority) = sampling_priority {
+        rmp::encode::write_str(encoded, SAMPLING_PRIORITY_KEY)?;
+        rmp::encode::write_f64(encoded, sampling_priority)?;
+    }
+    if let Some(rule_rate) = rule_rate {
+        rmp::encode::write_str(encoded, RULE_SAMPLING_RATE_KEY)?;
+        rmp::encode::write_f64(encoded, rule_rate)?;
+    }
 
-            rmp::encode::write_str(&mut encoded, "meta")?;
-            rmp::encode::write_map_len(
-                &mut encoded,
-                (span.attributes.len() + resource.map(|r| r.len()).unwrap_or(0)) as u32,
-            )?;
-            if let Some(resource) = resource {
-                for (key, value) in resource.iter() {
-                    rmp::encode::write_str(&mut encoded, key.as_str())?;
-                    rmp::encode::write_str(&mut encoded, value.as_str().as_ref())?;
-                }
-            }
-            for kv in span.attributes.iter() {
-                rmp::encode::write_str(&mut encoded, kv.key.as_str())?;
-                rmp::encode::write_str(&mut encoded, kv.value.as_str().as_ref())?;
-            }
-
-            rmp::encode::write_str(&mut encoded, "metrics")?;
-            rmp::encode::write_map_len(&mut encoded, 1)?;
-            rmp::encode::write_str(&mut encoded, SAMPLING_PRIORITY_KEY)?;
-            rmp::encode::write_f64(
-                &mut encoded,
-                if span.span_context.is_sampled() {
-                    1.0
-                } else {
-                    0.0
-                },
-            )?;
+    if !meta_struct.is_empty() {
+        rmp::encode::write_str(encoded, "meta_struct")?;
+        rmp::encode::write_map_len(encoded, meta_struct.len() as u32)?;
+        for (name, payload) in &meta_struct {
+            rmp::encode::write_str(encoded, name)?;
+            rmp::encode::write_bin(encoded, payload)?;
         }
     }
-
-    Ok(encoded)
+    Ok(())
 }
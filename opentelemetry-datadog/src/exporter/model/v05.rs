@@ -1,5 +1,7 @@
 use crate::exporter::intern::StringInterner;
-use crate::exporter::model::{DD_MEASURED_KEY, SAMPLING_PRIORITY_KEY};
+use crate::exporter::model::{
+    meta_struct, DD_MEASURED_KEY, RULE_SAMPLING_RATE_KEY, SAMPLING_PRIORITY_KEY,
+};
 use crate::exporter::{Error, ModelConfig};
 use crate::propagator::DatadogTraceState;
 use opentelemetry::trace::Status;
@@ -11,17 +13,6 @@ use super::unified_tags::{UnifiedTagField, UnifiedTags};
 
 const SPAN_NUM_ELEMENTS: u32 = 12;
 const METRICS_LEN: u32 = 2;
-const GIT_META_TAGS_COUNT: u32 = if matches!(
-    (
-        option_env!("DD_GIT_REPOSITORY_URL"),
-        option_env!("DD_GIT_COMMIT_SHA")
-    ),
-    (Some(_), Some(_))
-) {
-    2
-} else {
-    0
-};
 
 // Protocol documentation sourced from https://github.com/DataDog/datadog-agent/blob/c076ea9a1ffbde4c76d35343dbc32aecbbf99cb9/pkg/trace/api/version.go
 //
@@ -70,7 +61,7 @@ const GIT_META_TAGS_COUNT: u32 = if matches!(
 //
 pub(crate) fn encode<S, N, R>(
     model_config: &ModelConfig,
-    traces: Vec<&[SpanData]>,
+    traces: Vec<(&[SpanData], Option<f64>)>,
     get_service_name: S,
     get_name: N,
     get_resource: R,
@@ -112,6 +103,10 @@ fn write_unified_tags<'a>(
     write_unified_tag(encoded, interner, &unified_tags.service)?;
     write_unified_tag(encoded, interner, &unified_tags.env)?;
     write_unified_tag(encoded, interner, &unified_tags.version)?;
+    for (key, value) in &unified_tags.tags {
+        rmp::encode::write_u32(encoded, interner.intern(key))?;
+        rmp::encode::write_u32(encoded, interner.intern(value))?;
+    }
     Ok(())
 }
 
@@ -128,12 +123,12 @@ fn write_unified_tag<'a>(
 }
 
 #[cfg(not(feature = "agent-sampling"))]
-fn get_sampling_priority(_span: &SpanData) -> f64 {
+pub(super) fn get_sampling_priority(_span: &SpanData) -> f64 {
     1.0
 }
 
 #[cfg(feature = "agent-sampling")]
-fn get_sampling_priority(span: &SpanData) -> f64 {
+pub(super) fn get_sampling_priority(span: &SpanData) -> f64 {
     if span.span_context.trace_state().priority_sampling_enabled() {
         1.0
     } else {
@@ -156,7 +151,7 @@ fn encode_traces<'interner, S, N, R>(
     get_service_name: S,
     get_name: N,
     get_resource: R,
-    traces: &'interner [&[SpanData]],
+    traces: &'interner [(&[SpanData], Option<f64>)],
     unified_tags: &'interner UnifiedTags,
     resource: Option<&'interner Resource>,
 ) -> Result<Vec<u8>, Error>
@@ -168,7 +163,7 @@ where
     let mut encoded = Vec::new();
     rmp::encode::write_array_len(&mut encoded, traces.len() as u32)?;
 
-    for trace in traces.iter() {
+    for (trace, rule_rate) in traces.iter() {
         rmp::encode::write_array_len(&mut encoded, trace.len() as u32)?;
 
         for span in trace.iter() {
@@ -226,11 +221,16 @@ where
                 },
             )?;
 
+            // meta_struct is not part of the v0.5 format
+            let tags = span
+                .attributes
+                .iter()
+                .filter(|kv| !meta_struct::is_meta_struct(&kv.key));
+
             rmp::encode::write_map_len(
                 &mut encoded,
-                (span.attributes.len() + resource.map(|r| r.len()).unwrap_or(0)) as u32
-                    + unified_tags.compute_attribute_size()
-                    + GIT_META_TAGS_COUNT,
+                (tags.clone().count() + resource.map(|r| r.len()).unwrap_or(0)) as u32
+                    + unified_tags.compute_attribute_size(),
             )?;
             if let Some(resource) = resource {
                 for (key, value) in resource.iter() {
@@ -241,22 +241,12 @@ where
 
             write_unified_tags(&mut encoded, interner, unified_tags)?;
 
-            for kv in span.attributes.iter() {
+            for kv in tags {
                 rmp::encode::write_u32(&mut encoded, interner.intern(kv.key.as_str()))?;
                 rmp::encode::write_u32(&mut encoded, interner.intern_value(&kv.value))?;
             }
 
-            if let (Some(repository_url), Some(commit_sha)) = (
-                option_env!("DD_GIT_REPOSITORY_URL"),
-                option_env!("DD_GIT_COMMIT_SHA"),
-            ) {
-                rmp::encode::write_u32(&mut encoded, interner.intern("git.repository_url"))?;
-                rmp::encode::write_u32(&mut encoded, interner.intern(repository_url))?;
-                rmp::encode::write_u32(&mut encoded, interner.intern("git.commit.sha"))?;
-                rmp::encode::write_u32(&mut encoded, interner.intern(commit_sha))?;
-            }
-
-            rmp::encode::write_map_len(&mut encoded, METRICS_LEN)?;
+            rmp::encode::write_map_len(&mut encoded, METRICS_LEN + u32::from(rule_rate.is_some()))?;
             rmp::encode::write_u32(&mut encoded, interner.intern(SAMPLING_PRIORITY_KEY))?;
             let sampling_priority = get_sampling_priority(span);
             rmp::encode::write_f64(&mut encoded, sampling_priority)?;
@@ -264,6 +254,11 @@ where
             rmp::encode::write_u32(&mut encoded, interner.intern(DD_MEASURED_KEY))?;
             let measuring = get_measuring(span);
             rmp::encode::write_f64(&mut encoded, measuring)?;
+
+            if let Some(rule_rate) = rule_rate {
+                rmp::encode::write_u32(&mut encoded, interner.intern(RULE_SAMPLING_RATE_KEY))?;
+                rmp::encode::write_f64(&mut encoded, *rule_rate)?;
+            }
             rmp::encode::write_u32(&mut encoded, span_type)?;
         }
     }
@@ -0,0 +1,109 @@
+use crate::exporter::model::unified_tags::UnifiedTags;
+use crate::exporter::model::v03::write_span;
+use crate::exporter::model::v05::get_sampling_priority;
+use crate::exporter::model::{local_root, Error};
+use crate::exporter::ModelConfig;
+use opentelemetry_sdk::trace::SpanData;
+use opentelemetry_sdk::Resource;
+
+static ORIGIN_KEY: &str = "_dd.origin";
+static DECISION_MAKER_KEY: &str = "_dd.p.dm";
+// https://github.com/DataDog/dd-trace-go/blob/v1.72.1/ddtrace/ext/priority.go
+static DECISION_MAKER_RULE: &str = "-3";
+
+/// Encode the traces as a tracer payload, whose chunks, one per trace, carry the sampling
+/// priority and origin of the trace instead of its spans.
+///
+/// See https://github.com/DataDog/datadog-agent/blob/7.62.0/pkg/proto/datadog/trace/tracer_payload.proto
+///
+/// The origin is read from the `_dd.origin` attribute of the local root span, e.g. set to
+/// `synthetics` for the traces of Synthetic tests, and the hostname of the payload is the
+/// reported `hostname`, if any.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn encode<S, N, R>(
+    model_config: &ModelConfig,
+    traces: Vec<(&[SpanData], Option<f64>)>,
+    get_service_name: S,
+    get_name: N,
+    get_resource: R,
+    unified_tags: &UnifiedTags,
+    resource: Option<&Resource>,
+    hostname: Option<&str>,
+) -> Result<Vec<u8>, Error>
+where
+    for<'a> S: Fn(&'a SpanData, &'a ModelConfig) -> &'a str,
+    for<'a> N: Fn(&'a SpanData, &'a ModelConfig) -> &'a str,
+    for<'a> R: Fn(&'a SpanData, &'a ModelConfig) -> &'a str,
+{
+    let mut encoded = Vec::new();
+    let env = unified_tags.env.value.as_deref();
+    let app_version = unified_tags.version.value.as_deref();
+    rmp::encode::write_map_len(
+        &mut encoded,
+        3 + u32::from(env.is_some())
+            + u32::from(app_version.is_some())
+            + u32::from(hostname.is_some()),
+    )?;
+    rmp::encode::write_str(&mut encoded, "language_name")?;
+    rmp::encode::write_str(&mut encoded, "rust")?;
+    rmp::encode::write_str(&mut encoded, "tracer_version")?;
+    rmp::encode::write_str(&mut encoded, env!("CARGO_PKG_VERSION"))?;
+    if let Some(env) = env {
+        rmp::encode::write_str(&mut encoded, "env")?;
+        rmp::encode::write_str(&mut encoded, env)?;
+    }
+    if let Some(app_version) = app_version {
+        rmp::encode::write_str(&mut encoded, "app_version")?;
+        rmp::encode::write_str(&mut encoded, app_version)?;
+    }
+    if let Some(hostname) = hostname {
+        rmp::encode::write_str(&mut encoded, "hostname")?;
+        rmp::encode::write_str(&mut encoded, hostname)?;
+    }
+
+    rmp::encode::write_str(&mut encoded, "chunks")?;
+    rmp::encode::write_array_len(&mut encoded, traces.len() as u32)?;
+    for (trace, rule_rate) in traces.into_iter() {
+        let root = local_root(trace);
+        let priority = root.map_or(1.0, get_sampling_priority);
+        let origin = root.and_then(|root| {
+            root.attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == ORIGIN_KEY)
+        });
+
+        rmp::encode::write_map_len(&mut encoded, 4)?;
+        rmp::encode::write_str(&mut encoded, "priority")?;
+        rmp::encode::write_i32(&mut encoded, priority as i32)?;
+        rmp::encode::write_str(&mut encoded, "origin")?;
+        match origin {
+            Some(kv) => rmp::encode::write_str(&mut encoded, kv.value.as_str().as_ref())?,
+            None => rmp::encode::write_str(&mut encoded, "")?,
+        }
+        rmp::encode::write_str(&mut encoded, "tags")?;
+        rmp::encode::write_map_len(&mut encoded, u32::from(rule_rate.is_some()))?;
+        if rule_rate.is_some() {
+            rmp::encode::write_str(&mut encoded, DECISION_MAKER_KEY)?;
+            rmp::encode::write_str(&mut encoded, DECISION_MAKER_RULE)?;
+        }
+
+        rmp::encode::write_str(&mut encoded, "spans")?;
+        rmp::encode::write_array_len(&mut encoded, trace.len() as u32)?;
+        for span in trace {
+            write_span(
+                &mut encoded,
+                model_config,
+                span,
+                &get_service_name,
+                &get_name,
+                &get_resource,
+                &unified_tags.tags,
+                resource,
+                None,
+                rule_rate,
+            )?;
+        }
+    }
+
+    Ok(encoded)
+}
@@ -0,0 +1,15 @@
+//! Client-side trace sampling, with Datadog sampling rules or the rates of the Datadog agent.
+#[cfg(feature = "sampling-rules")]
+mod rules;
+
+#[cfg(feature = "sampling-rules")]
+pub use rules::SamplingRules;
+
+// https://github.com/DataDog/dd-trace-go/blob/v1.72.1/ddtrace/tracer/sampler.go#L89
+const KNUTH_FACTOR: u64 = 1_111_111_111_111_111_111;
+
+/// Whether the trace `trace_id` is kept with the sampling `rate`, as in the Datadog tracing
+/// libraries. Deterministic on the trace id, so every service keeping the trace keeps all of it.
+pub(crate) fn keep(trace_id: u64, rate: f64) -> bool {
+    rate >= 1.0 || (trace_id.wrapping_mul(KNUTH_FACTOR) as f64) < rate * u64::MAX as f64
+}
@@ -0,0 +1,262 @@
+//! Client-side trace sampling with Datadog sampling rules.
+use crate::exporter::model::local_root;
+use crate::exporter::sampling::keep;
+use crate::exporter::{Error, Mapping, ModelConfig};
+use opentelemetry_sdk::trace::SpanData;
+use regex::Regex;
+use serde::Deserialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Environment variable holding the sampling rules.
+const DD_TRACE_SAMPLING_RULES: &str = "DD_TRACE_SAMPLING_RULES";
+
+#[derive(Deserialize)]
+struct RuleConfig {
+    service: Option<String>,
+    name: Option<String>,
+    resource: Option<String>,
+    sample_rate: f64,
+    max_per_second: Option<f64>,
+}
+
+/// A sampling rule, see [`SamplingRules`].
+#[derive(Debug)]
+struct SamplingRule {
+    service: Option<Regex>,
+    name: Option<Regex>,
+    resource: Option<Regex>,
+    sample_rate: f64,
+    limiter: Option<RateLimiter>,
+}
+
+impl SamplingRule {
+    fn from_config(config: RuleConfig) -> Result<Self, Error> {
+        if !(0.0..=1.0).contains(&config.sample_rate) {
+            return Err(Error::Other(format!(
+                "sample_rate must be between 0 and 1, got {}",
+                config.sample_rate
+            )));
+        }
+        Ok(SamplingRule {
+            service: config.service.as_deref().map(full_match).transpose()?,
+            name: config.name.as_deref().map(full_match).transpose()?,
+            resource: config.resource.as_deref().map(full_match).transpose()?,
+            sample_rate: config.sample_rate,
+            limiter: config.max_per_second.map(RateLimiter::new),
+        })
+    }
+
+    fn matches(&self, service: &str, name: &str, resource: &str) -> bool {
+        let is_match = |regex: &Option<Regex>, value: &str| {
+            regex.as_ref().map_or(true, |regex| regex.is_match(value))
+        };
+        is_match(&self.service, service)
+            && is_match(&self.name, name)
+            && is_match(&self.resource, resource)
+    }
+
+    fn sample(&self, trace_id: u64) -> bool {
+        keep(trace_id, self.sample_rate)
+            && self.limiter.as_ref().map_or(true, RateLimiter::try_acquire)
+    }
+}
+
+fn full_match(pattern: &str) -> Result<Regex, Error> {
+    Regex::new(&format!("^(?:{pattern})$"))
+        .map_err(|e| Error::Other(format!("invalid sampling rule pattern {pattern}: {e}")))
+}
+
+/// Keeps at most `max_per_second` items every second.
+#[derive(Debug)]
+struct RateLimiter {
+    max_per_second: f64,
+    window: Mutex<(Instant, f64)>,
+}
+
+impl RateLimiter {
+    fn new(max_per_second: f64) -> Self {
+        RateLimiter {
+            max_per_second,
+            window: Mutex::new((Instant::now(), 0.0)),
+        }
+    }
+
+    fn try_acquire(&self) -> bool {
+        let Ok(mut window) = self.window.lock() else {
+            return true;
+        };
+        let (start, count) = &mut *window;
+        if start.elapsed() >= Duration::from_secs(1) {
+            *start = Instant::now();
+            *count = 0.0;
+        }
+        if *count < self.max_per_second {
+            *count += 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Datadog sampling rules applied to the traces before they are exported.
+///
+/// The rules use the format of the `DD_TRACE_SAMPLING_RULES` environment variable of the
+/// Datadog tracing libraries, a JSON array of rules:
+///
+/// ```json
+/// [
+///   {"service": "my-app", "resource": "GET /health", "sample_rate": 0.0},
+///   {"name": "http\\..*", "resource": "GET /products/.*", "sample_rate": 0.1, "max_per_second": 50}
+/// ]
+/// ```
+///
+/// `service`, `name` and `resource` are regular expressions matched against the whole value of
+/// the Datadog fields of the local root span of a trace, after the [`FieldMappingFn`]s are
+/// applied. The first matching rule decides whether the trace is exported: it is kept with a
+/// probability of `sample_rate`, and at most `max_per_second` traces matching the rule are kept
+/// every second. Traces not matching any rule are always exported.
+///
+/// The rate of the matching rule is added as the `_dd.rule_psr` metric of the exported spans,
+/// so Datadog can extrapolate the trace metrics.
+///
+/// [`FieldMappingFn`]: crate::FieldMappingFn
+#[derive(Debug, Default)]
+pub struct SamplingRules {
+    rules: Vec<SamplingRule>,
+}
+
+impl SamplingRules {
+    /// Parse rules in the `DD_TRACE_SAMPLING_RULES` JSON format.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let configs: Vec<RuleConfig> = serde_json::from_str(json)
+            .map_err(|e| Error::Other(format!("invalid sampling rules: {e}")))?;
+        Ok(SamplingRules {
+            rules: configs
+                .into_iter()
+                .map(SamplingRule::from_config)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Parse the rules of the `DD_TRACE_SAMPLING_RULES` environment variable, if it is set.
+    pub fn from_env() -> Result<Option<Self>, Error> {
+        match std::env::var(DD_TRACE_SAMPLING_RULES) {
+            Ok(json) => Self::from_json(&json).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Whether `trace` should be exported, and the rate of the rule that decided it.
+    pub(crate) fn sample(
+        &self,
+        trace: &[SpanData],
+        mapping: &Mapping,
+        model_config: &ModelConfig,
+    ) -> (bool, Option<f64>) {
+        let Some(root) = local_root(trace) else {
+            return (true, None);
+        };
+        let service = mapping.service_name(root, model_config);
+        let name = mapping.name(root, model_config);
+        let resource = mapping.resource(root, model_config);
+        match self
+            .rules
+            .iter()
+            .find(|rule| rule.matches(service, name, resource))
+        {
+            Some(rule) => {
+                let trace_id = u128::from_be_bytes(root.span_context.trace_id().to_bytes()) as u64;
+                (rule.sample(trace_id), Some(rule.sample_rate))
+            }
+            None => (true, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter::model::tests::get_span;
+
+    fn products_resource<'a>(_span: &'a SpanData, _config: &'a ModelConfig) -> &'a str {
+        "GET /products"
+    }
+
+    fn config() -> ModelConfig {
+        ModelConfig {
+            service_name: "my-app".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_from_json() {
+        let rules = SamplingRules::from_json(
+            r#"[{"service": "my-.*", "name": "component", "sample_rate": 0.5, "max_per_second": 10}]"#,
+        )
+        .unwrap();
+        assert_eq!(rules.rules.len(), 1);
+        assert_eq!(rules.rules[0].sample_rate, 0.5);
+
+        assert!(SamplingRules::from_json(r#"[{"sample_rate": 2}]"#).is_err());
+        assert!(SamplingRules::from_json(r#"[{"name": "(", "sample_rate": 1}]"#).is_err());
+        assert!(SamplingRules::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn test_sample_by_resource() {
+        let rules = SamplingRules::from_json(
+            r#"[
+                {"resource": "resource", "sample_rate": 0.0},
+                {"service": "my-app", "sample_rate": 1.0}
+            ]"#,
+        )
+        .unwrap();
+        let mapping = Mapping::empty();
+        let config = config();
+
+        let trace = [get_span(7, 1, 2), get_span(7, 2, 3)];
+        assert_eq!(rules.sample(&trace, &mapping, &config), (false, Some(0.0)));
+
+        let mapping = Mapping::new(Some(std::sync::Arc::new(products_resource)), None, None);
+        assert_eq!(rules.sample(&trace, &mapping, &config), (true, Some(1.0)));
+
+        let config = ModelConfig {
+            service_name: "other-app".to_string(),
+        };
+        assert_eq!(rules.sample(&trace, &mapping, &config), (true, None));
+    }
+
+    #[test]
+    fn test_sample_rate_is_deterministic() {
+        let rule = SamplingRule::from_config(RuleConfig {
+            service: None,
+            name: None,
+            resource: None,
+            sample_rate: 0.5,
+            max_per_second: None,
+        })
+        .unwrap();
+        let kept = (0..1000u64).filter(|id| rule.sample(*id)).count();
+        assert!((400..600).contains(&kept), "kept {kept}");
+        assert!((0..1000u64).all(|id| rule.sample(id) == rule.sample(id)));
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let limiter = RateLimiter::new(2.0);
+        assert!(limiter.try_acquire());
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+    }
+
+    #[test]
+    fn test_local_root() {
+        let trace = [get_span(7, 2, 3), get_span(7, 1, 2)];
+        assert_eq!(
+            local_root(&trace).unwrap().span_context.span_id(),
+            trace[1].span_context.span_id()
+        );
+    }
+}
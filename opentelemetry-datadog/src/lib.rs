@@ -155,11 +155,19 @@
 //! ```
 
 mod exporter;
-
+mod propagation_style;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
+#[cfg(feature = "agent-sampling")]
+pub use exporter::DatadogAgentSampler;
+#[cfg(feature = "sampling-rules")]
+pub use exporter::SamplingRules;
 pub use exporter::{
-    new_pipeline, ApiVersion, DatadogExporter, DatadogPipelineBuilder, Error, FieldMappingFn,
-    ModelConfig,
+    meta_struct_attribute, new_pipeline, ApiVersion, DatadogExporter, DatadogPipelineBuilder,
+    Error, FieldMappingFn, HostnameReporting, ModelConfig, TagCardinalityGuard,
 };
+pub use propagation_style::{propagator_from_env, PropagationStyle, StylePropagator};
 pub use propagator::{DatadogPropagator, DatadogTraceState, DatadogTraceStateBuilder};
 
 mod propagator {
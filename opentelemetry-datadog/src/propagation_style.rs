@@ -0,0 +1,444 @@
+//! Propagation styles configured as in the Datadog tracing libraries, with the
+//! `DD_TRACE_PROPAGATION_STYLE_INJECT`, `DD_TRACE_PROPAGATION_STYLE_EXTRACT` and
+//! `DD_TRACE_PROPAGATION_STYLE` environment variables.
+use crate::DatadogPropagator;
+use opentelemetry::{
+    otel_warn,
+    propagation::{text_map_propagator::FieldIter, Extractor, Injector, TextMapPropagator},
+    trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
+    Context,
+};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use std::sync::Arc;
+
+const DD_TRACE_PROPAGATION_STYLE: &str = "DD_TRACE_PROPAGATION_STYLE";
+const DD_TRACE_PROPAGATION_STYLE_INJECT: &str = "DD_TRACE_PROPAGATION_STYLE_INJECT";
+const DD_TRACE_PROPAGATION_STYLE_EXTRACT: &str = "DD_TRACE_PROPAGATION_STYLE_EXTRACT";
+
+const DEFAULT_STYLES: [PropagationStyle; 2] =
+    [PropagationStyle::Datadog, PropagationStyle::TraceContext];
+
+const B3_TRACE_ID_HEADER: &str = "x-b3-traceid";
+const B3_SPAN_ID_HEADER: &str = "x-b3-spanid";
+const B3_SAMPLED_HEADER: &str = "x-b3-sampled";
+const B3_FLAGS_HEADER: &str = "x-b3-flags";
+
+/// Marks a context extracted from `x-b3-*` headers without a sampling decision, so the remote
+/// span context is injected without one as well.
+#[derive(Clone, Copy, Debug)]
+struct B3SamplingDeferred;
+
+/// A trace context propagation style of the Datadog tracing libraries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PropagationStyle {
+    /// The `x-datadog-*` headers of the [`DatadogPropagator`], `datadog` in the environment
+    /// variables.
+    Datadog,
+    /// The W3C `traceparent` and `tracestate` headers, `tracecontext` in the environment
+    /// variables.
+    TraceContext,
+    /// The `x-b3-*` headers of Zipkin, `b3multi` in the environment variables.
+    B3Multi,
+}
+
+impl PropagationStyle {
+    /// The style named `name` in the environment variables, case-insensitively.
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "datadog" => Some(PropagationStyle::Datadog),
+            "tracecontext" => Some(PropagationStyle::TraceContext),
+            "b3multi" => Some(PropagationStyle::B3Multi),
+            _ => None,
+        }
+    }
+
+    fn propagator(self) -> Arc<dyn TextMapPropagator + Send + Sync> {
+        match self {
+            PropagationStyle::Datadog => Arc::new(DatadogPropagator::new()),
+            PropagationStyle::TraceContext => Arc::new(TraceContextPropagator::new()),
+            PropagationStyle::B3Multi => Arc::new(B3MultiPropagator),
+        }
+    }
+}
+
+/// Injects the span context in the headers of every injection style, and extracts it from the
+/// first extraction style whose headers hold a valid span context, as the Datadog tracing
+/// libraries do.
+///
+/// ```
+/// use opentelemetry::global;
+/// use opentelemetry_datadog::{PropagationStyle, StylePropagator};
+///
+/// global::set_text_map_propagator(StylePropagator::new(
+///     [PropagationStyle::Datadog, PropagationStyle::TraceContext],
+///     [PropagationStyle::TraceContext, PropagationStyle::Datadog, PropagationStyle::B3Multi],
+/// ));
+/// ```
+#[derive(Clone, Debug)]
+pub struct StylePropagator {
+    inject: Vec<PropagationStyle>,
+    extract: Vec<PropagationStyle>,
+    injectors: Vec<Arc<dyn TextMapPropagator + Send + Sync>>,
+    extractors: Vec<Arc<dyn TextMapPropagator + Send + Sync>>,
+    fields: Vec<String>,
+}
+
+impl StylePropagator {
+    /// Inject the `inject` styles and extract the `extract` styles, in order.
+    pub fn new(
+        inject: impl IntoIterator<Item = PropagationStyle>,
+        extract: impl IntoIterator<Item = PropagationStyle>,
+    ) -> Self {
+        let inject: Vec<_> = inject.into_iter().collect();
+        let extract: Vec<_> = extract.into_iter().collect();
+        let injectors: Vec<_> = inject.iter().map(|style| style.propagator()).collect();
+        let extractors: Vec<_> = extract.iter().map(|style| style.propagator()).collect();
+        let mut fields = Vec::new();
+        for propagator in injectors.iter().chain(&extractors) {
+            for field in propagator.fields() {
+                if !fields.iter().any(|known| known == field) {
+                    fields.push(field.to_string());
+                }
+            }
+        }
+        StylePropagator {
+            inject,
+            extract,
+            injectors,
+            extractors,
+            fields,
+        }
+    }
+
+    /// The styles injected.
+    pub fn inject_styles(&self) -> &[PropagationStyle] {
+        &self.inject
+    }
+
+    /// The styles extracted, in order of precedence.
+    pub fn extract_styles(&self) -> &[PropagationStyle] {
+        &self.extract
+    }
+}
+
+/// A propagator of the styles of the `DD_TRACE_PROPAGATION_STYLE_INJECT` and
+/// `DD_TRACE_PROPAGATION_STYLE_EXTRACT` environment variables, comma separated lists of
+/// `datadog`, `tracecontext`, `b3multi` or `none`.
+///
+/// Unset variables default to `DD_TRACE_PROPAGATION_STYLE`, then to `datadog,tracecontext`, as
+/// in the Datadog tracing libraries, so services configured through the environment propagate
+/// the same headers whatever their language. `none` disables the propagation, and unknown
+/// styles are ignored with a warning.
+///
+/// ```
+/// use opentelemetry::global;
+///
+/// global::set_text_map_propagator(opentelemetry_datadog::propagator_from_env());
+/// ```
+pub fn propagator_from_env() -> StylePropagator {
+    propagator_from_env_with(|name| std::env::var(name).ok())
+}
+
+fn propagator_from_env_with(var: impl Fn(&str) -> Option<String>) -> StylePropagator {
+    let styles = |name: &str| {
+        var(name)
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| parse_styles(&value))
+    };
+    let default = styles(DD_TRACE_PROPAGATION_STYLE).unwrap_or_else(|| DEFAULT_STYLES.to_vec());
+    StylePropagator::new(
+        styles(DD_TRACE_PROPAGATION_STYLE_INJECT).unwrap_or_else(|| default.clone()),
+        styles(DD_TRACE_PROPAGATION_STYLE_EXTRACT).unwrap_or(default),
+    )
+}
+
+/// The styles of a comma separated list, without duplicates.
+fn parse_styles(value: &str) -> Vec<PropagationStyle> {
+    let mut styles = Vec::new();
+    for name in value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        if name.eq_ignore_ascii_case("none") {
+            continue;
+        }
+        match PropagationStyle::from_name(name) {
+            Some(style) if !styles.contains(&style) => styles.push(style),
+            Some(_) => {}
+            None => otel_warn!(name: "DatadogPropagator.UnknownPropagationStyle", style = name),
+        }
+    }
+    styles
+}
+
+impl TextMapPropagator for StylePropagator {
+    fn inject_context(&self, cx: &Context, injector: &mut dyn Injector) {
+        for propagator in &self.injectors {
+            propagator.inject_context(cx, injector);
+        }
+    }
+
+    fn extract_with_context(&self, cx: &Context, extractor: &dyn Extractor) -> Context {
+        self.extractors
+            .iter()
+            .find_map(|propagator| {
+                let extracted = propagator.extract_with_context(&Context::new(), extractor);
+                let span_context = extracted.span().span_context().clone();
+                span_context.is_valid().then(|| {
+                    let cx = cx.with_remote_span_context(span_context);
+                    match extracted.get::<B3SamplingDeferred>() {
+                        Some(deferred) => cx.with_value(*deferred),
+                        None => cx,
+                    }
+                })
+            })
+            .unwrap_or_else(|| cx.clone())
+    }
+
+    fn fields(&self) -> FieldIter<'_> {
+        FieldIter::new(&self.fields)
+    }
+}
+
+/// Propagates the span context in the `x-b3-*` headers.
+#[derive(Debug)]
+struct B3MultiPropagator;
+
+// TODO Replace this with LazyLock when MSRV is 1.80+
+static B3_HEADER_FIELDS: std::sync::OnceLock<[String; 4]> = std::sync::OnceLock::new();
+
+impl TextMapPropagator for B3MultiPropagator {
+    fn inject_context(&self, cx: &Context, injector: &mut dyn Injector) {
+        let span = cx.span();
+        let span_context = span.span_context();
+        if !span_context.is_valid() {
+            return;
+        }
+        injector.set(B3_TRACE_ID_HEADER, span_context.trace_id().to_string());
+        injector.set(B3_SPAN_ID_HEADER, span_context.span_id().to_string());
+        // The spans started locally carry the decision of the sampler.
+        let deferred = span_context.is_remote() && cx.get::<B3SamplingDeferred>().is_some();
+        if !deferred {
+            let sampled = if span_context.is_sampled() { "1" } else { "0" };
+            injector.set(B3_SAMPLED_HEADER, sampled.to_string());
+        }
+    }
+
+    fn extract_with_context(&self, cx: &Context, extractor: &dyn Extractor) -> Context {
+        let trace_id = extractor
+            .get(B3_TRACE_ID_HEADER)
+            .map(str::trim)
+            .filter(|id| id.len() == 16 || id.len() == 32)
+            .and_then(|id| TraceId::from_hex(id).ok());
+        let span_id = extractor
+            .get(B3_SPAN_ID_HEADER)
+            .map(str::trim)
+            .filter(|id| id.len() == 16)
+            .and_then(|id| SpanId::from_hex(id).ok());
+        let (Some(trace_id), Some(span_id)) = (trace_id, span_id) else {
+            return cx.clone();
+        };
+
+        let debug = extractor.get(B3_FLAGS_HEADER).map(str::trim) == Some("1");
+        let (trace_flags, deferred) = match extractor.get(B3_SAMPLED_HEADER).map(str::trim) {
+            _ if debug => (TraceFlags::SAMPLED, false),
+            Some("1") | Some("true") => (TraceFlags::SAMPLED, false),
+            Some("0") | Some("false") => (TraceFlags::default(), false),
+            _ => (TraceFlags::default(), true),
+        };
+        let span_context =
+            SpanContext::new(trace_id, span_id, trace_flags, true, TraceState::default());
+        if !span_context.is_valid() {
+            return cx.clone();
+        }
+        let cx = cx.with_remote_span_context(span_context);
+        if deferred {
+            cx.with_value(B3SamplingDeferred)
+        } else {
+            cx
+        }
+    }
+
+    fn fields(&self) -> FieldIter<'_> {
+        FieldIter::new(B3_HEADER_FIELDS.get_or_init(|| {
+            [
+                B3_TRACE_ID_HEADER.to_owned(),
+                B3_SPAN_ID_HEADER.to_owned(),
+                B3_SAMPLED_HEADER.to_owned(),
+                B3_FLAGS_HEADER.to_owned(),
+            ]
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry_sdk::testing::trace::TestSpan;
+    use std::collections::HashMap;
+
+    fn from_env(vars: &[(&str, &str)]) -> StylePropagator {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        propagator_from_env_with(|name| vars.get(name).map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn test_propagator_from_env() {
+        use PropagationStyle::*;
+
+        let propagator = from_env(&[]);
+        assert_eq!(propagator.inject_styles(), [Datadog, TraceContext]);
+        assert_eq!(propagator.extract_styles(), [Datadog, TraceContext]);
+
+        let propagator = from_env(&[(DD_TRACE_PROPAGATION_STYLE, "b3multi")]);
+        assert_eq!(propagator.inject_styles(), [B3Multi]);
+        assert_eq!(propagator.extract_styles(), [B3Multi]);
+
+        let propagator = from_env(&[
+            (DD_TRACE_PROPAGATION_STYLE, "b3multi"),
+            (
+                DD_TRACE_PROPAGATION_STYLE_INJECT,
+                "TraceContext, datadog,datadog",
+            ),
+            (DD_TRACE_PROPAGATION_STYLE_EXTRACT, "none"),
+        ]);
+        assert_eq!(propagator.inject_styles(), [TraceContext, Datadog]);
+        assert!(propagator.extract_styles().is_empty());
+
+        let propagator = from_env(&[(DD_TRACE_PROPAGATION_STYLE_EXTRACT, "b3,tracecontext")]);
+        assert_eq!(propagator.inject_styles(), [Datadog, TraceContext]);
+        assert_eq!(propagator.extract_styles(), [TraceContext]);
+    }
+
+    #[test]
+    fn test_inject() {
+        let propagator = StylePropagator::new(
+            [PropagationStyle::TraceContext, PropagationStyle::B3Multi],
+            [],
+        );
+        let span_context = SpanContext::new(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        );
+        let mut headers = HashMap::new();
+        propagator.inject_context(
+            &Context::current_with_span(TestSpan(span_context)),
+            &mut headers,
+        );
+
+        assert_eq!(
+            headers.get("traceparent").map(String::as_str),
+            Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+        );
+        assert_eq!(
+            headers.get(B3_TRACE_ID_HEADER).map(String::as_str),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+        assert_eq!(
+            headers.get(B3_SPAN_ID_HEADER).map(String::as_str),
+            Some("00f067aa0ba902b7")
+        );
+        assert_eq!(
+            headers.get(B3_SAMPLED_HEADER).map(String::as_str),
+            Some("1")
+        );
+        assert!(!headers.contains_key("x-datadog-trace-id"));
+    }
+
+    #[test]
+    fn test_extract_precedence() {
+        let headers: HashMap<String, String> = [
+            ("x-datadog-trace-id", "1234"),
+            ("x-datadog-parent-id", "12"),
+            (B3_TRACE_ID_HEADER, "00000000000000000000000000000abc"),
+            (B3_SPAN_ID_HEADER, "0000000000000def"),
+            (B3_FLAGS_HEADER, "1"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+        let extract = |styles: &[PropagationStyle]| {
+            let propagator = StylePropagator::new([], styles.iter().copied());
+            propagator.extract(&headers).span().span_context().clone()
+        };
+
+        // The W3C headers are missing, the next style is extracted.
+        let span_context = extract(&[PropagationStyle::TraceContext, PropagationStyle::B3Multi]);
+        assert_eq!(span_context.trace_id(), TraceId::from(0xabc));
+        assert_eq!(span_context.span_id(), SpanId::from(0xdef));
+        assert!(span_context.is_sampled());
+
+        let span_context = extract(&[PropagationStyle::Datadog, PropagationStyle::B3Multi]);
+        assert_eq!(span_context.trace_id(), TraceId::from(1234));
+
+        assert!(!extract(&[]).is_valid());
+    }
+
+    #[test]
+    fn test_b3_deferred() {
+        let propagator = StylePropagator::new(
+            [PropagationStyle::TraceContext, PropagationStyle::B3Multi],
+            [PropagationStyle::B3Multi],
+        );
+        let headers: HashMap<String, String> = [
+            (B3_TRACE_ID_HEADER, "4bf92f3577b34da6a3ce929d0e0e4736"),
+            (B3_SPAN_ID_HEADER, "00f067aa0ba902b7"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+        let cx = propagator.extract(&headers);
+        let span_context = cx.span().span_context().clone();
+        assert!(span_context.is_valid());
+        assert_eq!(span_context.trace_flags(), TraceFlags::default());
+
+        // The remote span context is injected without a sampling decision.
+        let mut injected = HashMap::new();
+        propagator.inject_context(&cx, &mut injected);
+        assert!(!injected.contains_key(B3_SAMPLED_HEADER));
+        assert_eq!(
+            injected.get("traceparent").map(String::as_str),
+            Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00")
+        );
+
+        // A local span carries the decision of the sampler.
+        let local = SpanContext::new(
+            span_context.trace_id(),
+            SpanId::from(1),
+            TraceFlags::SAMPLED,
+            false,
+            TraceState::default(),
+        );
+        let mut injected = HashMap::new();
+        propagator.inject_context(&cx.with_span(TestSpan(local)), &mut injected);
+        assert_eq!(
+            injected.get(B3_SAMPLED_HEADER).map(String::as_str),
+            Some("1")
+        );
+
+        // The W3C random flag isn't a deferred decision.
+        let random = SpanContext::new(
+            span_context.trace_id(),
+            SpanId::from(1),
+            TraceFlags::new(0x02),
+            true,
+            TraceState::default(),
+        );
+        let mut injected = HashMap::new();
+        propagator.inject_context(
+            &Context::new().with_remote_span_context(random),
+            &mut injected,
+        );
+        assert_eq!(
+            injected.get(B3_SAMPLED_HEADER).map(String::as_str),
+            Some("0")
+        );
+    }
+}
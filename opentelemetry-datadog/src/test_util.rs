@@ -0,0 +1,595 @@
+//! Fake Datadog agent for the tests of applications using the exporter.
+//!
+//! [`FakeAgent`] is an HTTP client capturing the payloads the exporter sends to the agent,
+//! decoded into [`AgentPayload`]s, so tests can assert what the spans of the application look
+//! like to Datadog without running an agent. [`snapshot`] renders the captured traces as text
+//! without the ids and timings changing on every run, to compare with [`assert_snapshot`].
+//!
+//! ```
+//! use opentelemetry::trace::{Tracer, TracerProvider};
+//! use opentelemetry_datadog::test_util::{snapshot, FakeAgent};
+//! use opentelemetry_sdk::trace::SdkTracerProvider;
+//!
+//! let agent = FakeAgent::new();
+//! let exporter = opentelemetry_datadog::new_pipeline()
+//!     .with_service_name("checkout")
+//!     .with_http_client(agent.clone())
+//!     .build_exporter()
+//!     .unwrap();
+//! let provider = SdkTracerProvider::builder()
+//!     .with_simple_exporter(exporter)
+//!     .build();
+//!
+//! provider.tracer("app").in_span("GET /cart", |_cx| {});
+//!
+//! let spans = agent.spans();
+//! assert_eq!(spans[0].service, "checkout");
+//! assert_eq!(spans[0].resource, "GET /cart");
+//! assert!(snapshot(&agent.traces()).contains("resource=GET /cart"));
+//! ```
+use crate::Error;
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{header, Request, Response, StatusCode};
+use opentelemetry_http::{HttpClient, HttpError};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Environment variable rewriting the snapshot files compared by [`assert_snapshot`].
+pub const UPDATE_SNAPSHOTS_ENV: &str = "OTEL_DATADOG_UPDATE_SNAPSHOTS";
+
+/// A payload received by the agent.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct AgentPayload {
+    /// Path the payload was sent to, e.g. `/v0.5/traces`.
+    pub endpoint: String,
+    /// Tracer metadata of the payload, e.g. `language_name` or `env`. Only sent to the v0.7
+    /// endpoint.
+    pub metadata: BTreeMap<String, String>,
+    /// Traces of the payload.
+    pub traces: Vec<AgentTrace>,
+}
+
+/// A trace, or trace chunk of the v0.7 API, received by the agent.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct AgentTrace {
+    /// Sampling priority of the chunk. Only sent to the v0.7 endpoint.
+    pub priority: Option<i32>,
+    /// Origin of the chunk, e.g. `synthetics`. Only sent to the v0.7 endpoint.
+    pub origin: Option<String>,
+    /// Tags of the chunk, e.g. `_dd.p.dm`. Only sent to the v0.7 endpoint.
+    pub tags: BTreeMap<String, String>,
+    /// Spans of the trace.
+    pub spans: Vec<AgentSpan>,
+}
+
+/// A span received by the agent.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct AgentSpan {
+    /// Service of the span.
+    pub service: String,
+    /// Name of the span, the operation.
+    pub name: String,
+    /// Resource of the span.
+    pub resource: String,
+    /// Type of the span, e.g. `web`. Empty when not set.
+    pub span_type: String,
+    /// Lower 64 bits of the trace id.
+    pub trace_id: u64,
+    /// Id of the span.
+    pub span_id: u64,
+    /// Id of the parent span, `0` for root spans.
+    pub parent_id: u64,
+    /// Start of the span, in nanoseconds since the Unix epoch.
+    pub start: i64,
+    /// Duration of the span, in nanoseconds.
+    pub duration: i64,
+    /// `1` if the span is an error, `0` otherwise.
+    pub error: i32,
+    /// String tags of the span.
+    pub meta: BTreeMap<String, String>,
+    /// Numeric tags of the span, e.g. `_sampling_priority_v1`.
+    pub metrics: BTreeMap<String, f64>,
+    /// Msgpack payloads of the span, by name. Only sent to the v0.3 and v0.7 endpoints.
+    pub meta_struct: BTreeMap<String, Vec<u8>>,
+}
+
+/// An [`HttpClient`] standing in for the Datadog agent, capturing the decoded payloads sent to
+/// it.
+///
+/// The payloads of the v0.3, v0.4, v0.5 and v0.7 trace endpoints are accepted, with the
+/// response of an agent without sampling rates. Payloads which can't be decoded are rejected
+/// with `400 Bad Request`, and compressed payloads with `415 Unsupported Media Type`, as by an
+/// agent without compression support.
+///
+/// Clones share the captured payloads, so the agent can be given to the exporter and queried
+/// by the test.
+#[derive(Clone, Debug, Default)]
+pub struct FakeAgent {
+    payloads: Arc<Mutex<Vec<AgentPayload>>>,
+}
+
+impl FakeAgent {
+    /// Create an agent without payloads.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The payloads received, in order.
+    pub fn payloads(&self) -> Vec<AgentPayload> {
+        self.payloads.lock().unwrap().clone()
+    }
+
+    /// The traces of the payloads received, in order.
+    pub fn traces(&self) -> Vec<AgentTrace> {
+        self.payloads()
+            .into_iter()
+            .flat_map(|payload| payload.traces)
+            .collect()
+    }
+
+    /// The spans of the payloads received, in order.
+    pub fn spans(&self) -> Vec<AgentSpan> {
+        self.traces()
+            .into_iter()
+            .flat_map(|trace| trace.spans)
+            .collect()
+    }
+
+    /// Forget the payloads received.
+    pub fn clear(&self) {
+        self.payloads.lock().unwrap().clear();
+    }
+
+    fn receive(&self, request: Request<Bytes>) -> Response<Bytes> {
+        let encoding = request
+            .headers()
+            .get(header::CONTENT_ENCODING)
+            .filter(|encoding| *encoding != "identity");
+        if encoding.is_some() {
+            return Response::builder()
+                .status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+                .header(header::ACCEPT_ENCODING, "identity")
+                .body(Bytes::new())
+                .unwrap();
+        }
+
+        match decode_payload(request.uri().path(), request.body()) {
+            Ok(payload) => {
+                self.payloads.lock().unwrap().push(payload);
+                Response::new(Bytes::from_static(br#"{"rate_by_service":{}}"#))
+            }
+            Err(err) => Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Bytes::from(err.to_string()))
+                .unwrap(),
+        }
+    }
+}
+
+#[async_trait]
+impl HttpClient for FakeAgent {
+    async fn send(&self, request: Request<Vec<u8>>) -> Result<Response<Bytes>, HttpError> {
+        Ok(self.receive(request.map(Bytes::from)))
+    }
+
+    async fn send_bytes(&self, request: Request<Bytes>) -> Result<Response<Bytes>, HttpError> {
+        Ok(self.receive(request))
+    }
+}
+
+/// Decode a payload sent to the trace `endpoint` of the agent, e.g. `/v0.5/traces`.
+pub fn decode_payload(endpoint: &str, payload: &[u8]) -> Result<AgentPayload, Error> {
+    let mut reader = Reader(payload);
+    let (metadata, traces) =
+        if endpoint.ends_with("/v0.3/traces") || endpoint.ends_with("/v0.4/traces") {
+            (BTreeMap::new(), read_traces(&mut reader)?)
+        } else if endpoint.ends_with("/v0.5/traces") {
+            (BTreeMap::new(), read_v05(&mut reader)?)
+        } else if endpoint.ends_with("/v0.7/traces") {
+            read_v07(&mut reader)?
+        } else {
+            return Err(Error::Other(format!("unsupported endpoint {endpoint}")));
+        };
+    if !reader.0.is_empty() {
+        return Err(Error::MessagePackError);
+    }
+    Ok(AgentPayload {
+        endpoint: endpoint.to_string(),
+        metadata,
+        traces,
+    })
+}
+
+/// Render `traces` as text, one line per trace, span and tag, in a form stable across runs.
+///
+/// The trace and span ids are replaced by their order of appearance, and the parents outside
+/// of `traces` by `remote`. The start and duration of the spans are left out. Build the tracer
+/// provider with a fixed resource, as the resource attributes are tags of every span.
+pub fn snapshot(traces: &[AgentTrace]) -> String {
+    let mut span_ids = HashMap::from([(0, "none".to_string())]);
+    for span in traces.iter().flat_map(|trace| &trace.spans) {
+        let next = span_ids.len().to_string();
+        span_ids.entry(span.span_id).or_insert(next);
+    }
+    let mut trace_ids = HashMap::new();
+
+    let mut out = String::new();
+    for trace in traces {
+        let first = trace.spans.first().map_or(0, |span| span.trace_id);
+        let next = trace_ids.len() + 1;
+        let _ = write!(out, "trace {}", trace_ids.entry(first).or_insert(next));
+        if let Some(priority) = trace.priority {
+            let _ = write!(out, " priority={priority}");
+        }
+        if let Some(origin) = trace.origin.as_deref().filter(|origin| !origin.is_empty()) {
+            let _ = write!(out, " origin={origin}");
+        }
+        out.push('\n');
+        for (key, value) in &trace.tags {
+            let _ = writeln!(out, "  tag {key}={value}");
+        }
+
+        for span in &trace.spans {
+            let parent = span_ids
+                .get(&span.parent_id)
+                .map_or("remote", String::as_str);
+            let _ = writeln!(
+                out,
+                "  span {} parent={parent} service={} name={} resource={} type={} error={}",
+                span_ids[&span.span_id],
+                span.service,
+                span.name,
+                span.resource,
+                span.span_type,
+                span.error,
+            );
+            for (key, value) in &span.meta {
+                let _ = writeln!(out, "    meta {key}={value}");
+            }
+            for (key, value) in &span.metrics {
+                let _ = writeln!(out, "    metric {key}={value}");
+            }
+            for (key, value) in &span.meta_struct {
+                let _ = writeln!(out, "    meta_struct {key}=<{} bytes>", value.len());
+            }
+        }
+    }
+    out
+}
+
+/// Assert that `actual` is the content of the snapshot file at `path`.
+///
+/// The file is written instead when it doesn't exist or [`UPDATE_SNAPSHOTS_ENV`] is set, to
+/// review the change with the rest of the test.
+///
+/// # Panics
+///
+/// If `actual` differs from the snapshot, or the snapshot can't be read or written.
+#[track_caller]
+pub fn assert_snapshot(path: impl AsRef<Path>, actual: &str) {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() || !path.exists() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(path, actual)
+            .unwrap_or_else(|err| panic!("can't write snapshot {}: {err}", path.display()));
+        return;
+    }
+    let expected = std::fs::read_to_string(path)
+        .unwrap_or_else(|err| panic!("can't read snapshot {}: {err}", path.display()));
+    assert_eq!(
+        expected,
+        actual,
+        "snapshot {} differs, set {UPDATE_SNAPSHOTS_ENV} to update it",
+        path.display(),
+    );
+}
+
+/// Reader of the msgpack values written by the encoders of the exporter.
+struct Reader<'a>(&'a [u8]);
+
+fn invalid<E>(_: E) -> Error {
+    Error::MessagePackError
+}
+
+impl<'a> Reader<'a> {
+    fn array_len(&mut self) -> Result<u32, Error> {
+        rmp::decode::read_array_len(&mut self.0).map_err(invalid)
+    }
+
+    fn map_len(&mut self) -> Result<u32, Error> {
+        rmp::decode::read_map_len(&mut self.0).map_err(invalid)
+    }
+
+    fn str(&mut self) -> Result<&'a str, Error> {
+        let (value, rest) = rmp::decode::read_str_from_slice(self.0).map_err(invalid)?;
+        self.0 = rest;
+        Ok(value)
+    }
+
+    fn bin(&mut self) -> Result<&'a [u8], Error> {
+        let len = rmp::decode::read_bin_len(&mut self.0).map_err(invalid)? as usize;
+        if len > self.0.len() {
+            return Err(Error::MessagePackError);
+        }
+        let (value, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(value)
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        rmp::decode::read_int(&mut self.0).map_err(invalid)
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        rmp::decode::read_int(&mut self.0).map_err(invalid)
+    }
+
+    fn i64(&mut self) -> Result<i64, Error> {
+        rmp::decode::read_int(&mut self.0).map_err(invalid)
+    }
+
+    fn i32(&mut self) -> Result<i32, Error> {
+        rmp::decode::read_int(&mut self.0).map_err(invalid)
+    }
+
+    fn f64(&mut self) -> Result<f64, Error> {
+        rmp::decode::read_f64(&mut self.0).map_err(invalid)
+    }
+
+    fn str_map(&mut self) -> Result<BTreeMap<String, String>, Error> {
+        (0..self.map_len()?)
+            .map(|_| Ok((self.str()?.to_string(), self.str()?.to_string())))
+            .collect()
+    }
+}
+
+/// Read the array of traces of the v0.3 and v0.4 APIs.
+fn read_traces(reader: &mut Reader<'_>) -> Result<Vec<AgentTrace>, Error> {
+    (0..reader.array_len()?)
+        .map(|_| {
+            Ok(AgentTrace {
+                spans: read_spans(reader)?,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+fn read_spans(reader: &mut Reader<'_>) -> Result<Vec<AgentSpan>, Error> {
+    (0..reader.array_len()?)
+        .map(|_| read_span(reader))
+        .collect()
+}
+
+/// Read a span in the map format of the v0.3, v0.4 and v0.7 APIs.
+fn read_span(reader: &mut Reader<'_>) -> Result<AgentSpan, Error> {
+    let mut span = AgentSpan::default();
+    for _ in 0..reader.map_len()? {
+        match reader.str()? {
+            "type" => span.span_type = reader.str()?.to_string(),
+            "service" => span.service = reader.str()?.to_string(),
+            "name" => span.name = reader.str()?.to_string(),
+            "resource" => span.resource = reader.str()?.to_string(),
+            "trace_id" => span.trace_id = reader.u64()?,
+            "span_id" => span.span_id = reader.u64()?,
+            "parent_id" => span.parent_id = reader.u64()?,
+            "start" => span.start = reader.i64()?,
+            "duration" => span.duration = reader.i64()?,
+            "error" => span.error = reader.i32()?,
+            "meta" => span.meta = reader.str_map()?,
+            "metrics" => {
+                for _ in 0..reader.map_len()? {
+                    let key = reader.str()?.to_string();
+                    span.metrics.insert(key, reader.f64()?);
+                }
+            }
+            "meta_struct" => {
+                for _ in 0..reader.map_len()? {
+                    let key = reader.str()?.to_string();
+                    span.meta_struct.insert(key, reader.bin()?.to_vec());
+                }
+            }
+            field => return Err(Error::Other(format!("unexpected span field {field}"))),
+        }
+    }
+    Ok(span)
+}
+
+/// Read the dictionary and the traces of array spans of the v0.5 API.
+fn read_v05(reader: &mut Reader<'_>) -> Result<Vec<AgentTrace>, Error> {
+    if reader.array_len()? != 2 {
+        return Err(Error::MessagePackError);
+    }
+    let dictionary = (0..reader.array_len()?)
+        .map(|_| reader.str())
+        .collect::<Result<Vec<_>, _>>()?;
+    let string = |reader: &mut Reader<'_>| {
+        let index = reader.u32()? as usize;
+        dictionary
+            .get(index)
+            .map(|value| value.to_string())
+            .ok_or_else(|| Error::Other(format!("string {index} not in the dictionary")))
+    };
+
+    let mut traces = Vec::new();
+    for _ in 0..reader.array_len()? {
+        let mut spans = Vec::new();
+        for _ in 0..reader.array_len()? {
+            if reader.array_len()? != 12 {
+                return Err(Error::MessagePackError);
+            }
+            let mut span = AgentSpan {
+                service: string(reader)?,
+                name: string(reader)?,
+                resource: string(reader)?,
+                trace_id: reader.u64()?,
+                span_id: reader.u64()?,
+                parent_id: reader.u64()?,
+                start: reader.i64()?,
+                duration: reader.i64()?,
+                error: reader.i32()?,
+                ..Default::default()
+            };
+            for _ in 0..reader.map_len()? {
+                let key = string(reader)?;
+                span.meta.insert(key, string(reader)?);
+            }
+            for _ in 0..reader.map_len()? {
+                let key = string(reader)?;
+                span.metrics.insert(key, reader.f64()?);
+            }
+            span.span_type = string(reader)?;
+            spans.push(span);
+        }
+        traces.push(AgentTrace {
+            spans,
+            ..Default::default()
+        });
+    }
+    Ok(traces)
+}
+
+/// Read the tracer payload of the v0.7 API.
+fn read_v07(reader: &mut Reader<'_>) -> Result<(BTreeMap<String, String>, Vec<AgentTrace>), Error> {
+    let mut metadata = BTreeMap::new();
+    let mut traces = Vec::new();
+    for _ in 0..reader.map_len()? {
+        match reader.str()? {
+            "chunks" => {
+                for _ in 0..reader.array_len()? {
+                    let mut trace = AgentTrace::default();
+                    for _ in 0..reader.map_len()? {
+                        match reader.str()? {
+                            "priority" => trace.priority = Some(reader.i32()?),
+                            "origin" => trace.origin = Some(reader.str()?.to_string()),
+                            "tags" => trace.tags = reader.str_map()?,
+                            "spans" => trace.spans = read_spans(reader)?,
+                            field => {
+                                return Err(Error::Other(format!("unexpected chunk field {field}")))
+                            }
+                        }
+                    }
+                    traces.push(trace);
+                }
+            }
+            key => {
+                metadata.insert(key.to_string(), reader.str()?.to_string());
+            }
+        }
+    }
+    Ok((metadata, traces))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_pipeline, ApiVersion};
+    use opentelemetry::trace::{TraceContextExt, Tracer, TracerProvider};
+    use opentelemetry::KeyValue;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+
+    fn record(api_version: ApiVersion) -> FakeAgent {
+        let agent = FakeAgent::new();
+        let exporter = new_pipeline()
+            .with_service_name("checkout")
+            .with_api_version(api_version)
+            .with_http_client(agent.clone())
+            .build_exporter()
+            .unwrap();
+        let provider = SdkTracerProvider::builder()
+            .with_resource(Resource::builder_empty().build())
+            .with_simple_exporter(exporter)
+            .build();
+        let tracer = provider.tracer("app");
+
+        tracer.in_span("GET /cart", |cx| {
+            cx.span().set_attribute(KeyValue::new("span.type", "web"));
+            cx.span()
+                .set_attribute(KeyValue::new("http.request.method", "GET"));
+            tracer.in_span("SELECT cart", |_cx| {});
+        });
+        let _ = provider.shutdown();
+        agent
+    }
+
+    #[test]
+    fn test_decode_payloads() {
+        for (api_version, endpoint) in [
+            (ApiVersion::Version03, "/v0.3/traces"),
+            (ApiVersion::Version05, "/v0.5/traces"),
+            (ApiVersion::Version07, "/v0.7/traces"),
+        ] {
+            let agent = record(api_version);
+            let payloads = agent.payloads();
+            assert_eq!(payloads.len(), 2, "{endpoint}");
+            assert_eq!(payloads[0].endpoint, endpoint);
+
+            let spans = agent.spans();
+            let (child, root) = (&spans[0], &spans[1]);
+            assert_eq!(root.service, "checkout");
+            assert_eq!(root.name, "app");
+            assert_eq!(root.resource, "GET /cart");
+            assert_eq!(root.span_type, "web");
+            assert_eq!(root.parent_id, 0);
+            assert_eq!(child.parent_id, root.span_id);
+            assert_eq!(child.trace_id, root.trace_id);
+            assert_eq!(
+                root.meta.get("http.request.method").map(String::as_str),
+                Some("GET")
+            );
+            assert!(root.duration >= 0);
+        }
+
+        let payload = &record(ApiVersion::Version07).payloads()[0];
+        assert_eq!(payload.metadata["language_name"], "rust");
+        assert!(payload.traces[0].priority.is_some());
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let agent = record(ApiVersion::Version03);
+        let mut traces = agent.traces();
+        traces.reverse();
+
+        assert_eq!(
+            snapshot(&traces),
+            "trace 1\n\
+             \x20 span 1 parent=none service=checkout name=app resource=GET /cart type=web error=0\n\
+             \x20   meta http.request.method=GET\n\
+             \x20   meta span.type=web\n\
+             \x20   metric _sampling_priority_v1=1\n\
+             trace 1\n\
+             \x20 span 2 parent=1 service=checkout name=app resource=SELECT cart type= error=0\n\
+             \x20   metric _sampling_priority_v1=1\n"
+        );
+    }
+
+    #[test]
+    fn test_rejected_payloads() {
+        let agent = FakeAgent::new();
+        let response = agent.receive(
+            Request::post("http://localhost:8126/v0.5/traces")
+                .body(Bytes::from_static(&[0xc1]))
+                .unwrap(),
+        );
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = agent.receive(
+            Request::post("http://localhost:8126/v0.5/traces")
+                .header(header::CONTENT_ENCODING, "gzip")
+                .body(Bytes::new())
+                .unwrap(),
+        );
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(agent.payloads().is_empty());
+    }
+}
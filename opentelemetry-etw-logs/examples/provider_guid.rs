@@ -0,0 +1,27 @@
+//! Prints the ETW provider GUID of a provider name, to register the provider ahead of time in
+//! WPR profiles or `logman` sessions.
+//!
+//! run with `$ cargo run --example provider_guid -- <provider-name>`
+
+use opentelemetry_etw_logs::Processor;
+
+fn main() {
+    let provider_name = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "provider-name".to_string());
+
+    match Processor::provider_guid(&provider_name) {
+        Ok(guid) => {
+            println!("{guid}");
+            println!();
+            println!("WPR profile:");
+            println!("  <EventProvider Id=\"EventProvider_{provider_name}\" Name=\"{guid}\" />");
+            println!("logman:");
+            println!("  logman create trace MySession -p \"{{{guid}}}\" -o MySession.etl");
+        }
+        Err(err) => {
+            eprintln!("Invalid provider name '{provider_name}': {err}");
+            std::process::exit(1);
+        }
+    }
+}
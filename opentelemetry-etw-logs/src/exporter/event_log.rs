@@ -0,0 +1,239 @@
+//! Writes the severe log records to the Windows Event Log, in addition to ETW, so they show in
+//! Event Viewer even when no ETW session listens to the provider.
+use opentelemetry::logs::{AnyValue, Severity};
+use opentelemetry::otel_debug;
+use opentelemetry_sdk::logs::SdkLogRecord;
+use std::fmt::Write;
+
+use super::part_c::EVENT_ID;
+
+/// Maximum length of an Event Log string, in UTF-16 code units.
+const MAX_MESSAGE_LEN: usize = 31_839;
+
+const EVENTLOG_ERROR_TYPE: u16 = 0x1;
+const EVENTLOG_WARNING_TYPE: u16 = 0x2;
+const EVENTLOG_INFORMATION_TYPE: u16 = 0x4;
+
+/// An event source of the Application log, to which the records of at least `min_severity`
+/// are reported. Nothing is reported on other platforms than Windows.
+pub(crate) struct EventLog {
+    min_severity: Severity,
+    #[cfg(windows)]
+    handle: isize,
+}
+
+impl EventLog {
+    /// Register the event source `source`, `None` if it fails.
+    ///
+    /// Sources not registered in the registry by an installer are reported under their name in
+    /// the Application log, Event Viewer noting that their message file can't be found.
+    pub(crate) fn register(source: &str, min_severity: Severity) -> Option<Self> {
+        #[cfg(windows)]
+        {
+            let source_name: Vec<u16> = source.encode_utf16().chain(Some(0)).collect();
+            // SAFETY: `source_name` is a null terminated UTF-16 string, and a null server name
+            // is the local computer.
+            let handle = unsafe {
+                windows_sys::Win32::System::EventLog::RegisterEventSourceW(
+                    std::ptr::null(),
+                    source_name.as_ptr(),
+                )
+            };
+            if handle == 0 {
+                otel_debug!(
+                    name: "ETW.EventLogRegistrationFailed",
+                    source = source,
+                    error = std::io::Error::last_os_error().to_string()
+                );
+                return None;
+            }
+            otel_debug!(name: "ETW.EventLogRegistered", source = source);
+            Some(EventLog {
+                min_severity,
+                handle,
+            })
+        }
+        #[cfg(not(windows))]
+        {
+            otel_debug!(name: "ETW.EventLogUnsupported", source = source);
+            Some(EventLog { min_severity })
+        }
+    }
+
+    /// Whether records of `severity` are reported.
+    pub(crate) fn enabled(&self, severity: Severity) -> bool {
+        severity as i32 >= self.min_severity as i32
+    }
+
+    /// Report `log_record` if its `severity` is enabled.
+    pub(crate) fn write(&self, log_record: &SdkLogRecord, severity: Severity) {
+        if !self.enabled(severity) {
+            return;
+        }
+        let message: Vec<u16> = message(log_record, severity)
+            .encode_utf16()
+            .take(MAX_MESSAGE_LEN)
+            .chain(Some(0))
+            .collect();
+        self.report(event_type(severity), event_id(log_record), &message);
+    }
+
+    #[cfg(windows)]
+    fn report(&self, event_type: u16, event_id: u32, message: &[u16]) {
+        let strings = [message.as_ptr()];
+        // SAFETY: the handle is registered until dropped, and `strings` holds one null
+        // terminated UTF-16 string.
+        let reported = unsafe {
+            windows_sys::Win32::System::EventLog::ReportEventW(
+                self.handle,
+                event_type,
+                0,
+                event_id,
+                std::ptr::null_mut(),
+                1,
+                0,
+                strings.as_ptr(),
+                std::ptr::null(),
+            )
+        };
+        if reported == 0 {
+            otel_debug!(
+                name: "ETW.EventLogWriteFailed",
+                error = std::io::Error::last_os_error().to_string()
+            );
+        }
+    }
+
+    #[cfg(not(windows))]
+    fn report(&self, _event_type: u16, _event_id: u32, _message: &[u16]) {}
+}
+
+#[cfg(windows)]
+impl Drop for EventLog {
+    fn drop(&mut self) {
+        // SAFETY: the handle was returned by `RegisterEventSourceW` and isn't used afterwards.
+        unsafe {
+            windows_sys::Win32::System::EventLog::DeregisterEventSource(self.handle);
+        }
+    }
+}
+
+/// The Event Log type of `severity`.
+fn event_type(severity: Severity) -> u16 {
+    if severity as i32 >= Severity::Error as i32 {
+        EVENTLOG_ERROR_TYPE
+    } else if severity as i32 >= Severity::Warn as i32 {
+        EVENTLOG_WARNING_TYPE
+    } else {
+        EVENTLOG_INFORMATION_TYPE
+    }
+}
+
+/// The `event_id` attribute of `log_record` if it fits the 16 bits shown by Event Viewer, else
+/// 0.
+fn event_id(log_record: &SdkLogRecord) -> u32 {
+    log_record
+        .attributes_iter()
+        .find_map(|(key, value)| match (key.as_str(), value) {
+            (EVENT_ID, AnyValue::Int(event_id)) => u16::try_from(*event_id).ok(),
+            _ => None,
+        })
+        .map_or(0, u32::from)
+}
+
+/// The message of `log_record`: its body, followed by its severity, name, target, trace context
+/// and attributes, one per line.
+fn message(log_record: &SdkLogRecord, severity: Severity) -> String {
+    let mut message = String::new();
+    if let Some(body) = log_record.body() {
+        write_value(&mut message, body);
+        message.push_str("\n\n");
+    }
+    let severity_text = log_record.severity_text().unwrap_or(severity.name());
+    let _ = write!(message, "severity: {severity_text}");
+    if let Some(name) = log_record.event_name() {
+        let _ = write!(message, "\nevent.name: {name}");
+    }
+    if let Some(target) = log_record.target() {
+        let _ = write!(message, "\ntarget: {target}");
+    }
+    if let Some(trace_context) = log_record.trace_context() {
+        let _ = write!(
+            message,
+            "\ntrace_id: {}\nspan_id: {}",
+            trace_context.trace_id, trace_context.span_id
+        );
+    }
+    for (key, value) in log_record.attributes_iter() {
+        let _ = write!(message, "\n{key}: ");
+        write_value(&mut message, value);
+    }
+    message
+}
+
+fn write_value(message: &mut String, value: &AnyValue) {
+    let _ = match value {
+        AnyValue::String(value) => write!(message, "{value}"),
+        AnyValue::Int(value) => write!(message, "{value}"),
+        AnyValue::Double(value) => write!(message, "{value}"),
+        AnyValue::Boolean(value) => write!(message, "{value}"),
+        value => write!(message, "{value:?}"),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::common::test_utils;
+    use super::*;
+    use opentelemetry::logs::LogRecord;
+    use opentelemetry::trace::{SpanId, TraceId};
+
+    #[test]
+    fn test_enabled() {
+        let event_log = EventLog::register("ContosoProvider", Severity::Warn).unwrap();
+        assert!(event_log.enabled(Severity::Fatal));
+        assert!(event_log.enabled(Severity::Warn));
+        assert!(!event_log.enabled(Severity::Info));
+
+        assert_eq!(event_type(Severity::Fatal), EVENTLOG_ERROR_TYPE);
+        assert_eq!(event_type(Severity::Warn4), EVENTLOG_WARNING_TYPE);
+        assert_eq!(event_type(Severity::Info), EVENTLOG_INFORMATION_TYPE);
+    }
+
+    #[test]
+    fn test_message() {
+        let mut record = test_utils::new_sdk_log_record();
+        record.set_body(AnyValue::from("payment failed"));
+        record.set_severity_text("ERROR");
+        record.set_event_name("PaymentFailed");
+        record.set_target("payments");
+        record.set_trace_context(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+            None,
+        );
+        record.add_attribute("amount", 42.5);
+        record.add_attribute(EVENT_ID, 20_i64);
+
+        assert_eq!(
+            message(&record, Severity::Error),
+            "payment failed\n\n\
+             severity: ERROR\n\
+             event.name: PaymentFailed\n\
+             target: payments\n\
+             trace_id: 4bf92f3577b34da6a3ce929d0e0e4736\n\
+             span_id: 00f067aa0ba902b7\n\
+             amount: 42.5\n\
+             event_id: 20"
+        );
+        assert_eq!(event_id(&record), 20);
+
+        let mut record = test_utils::new_sdk_log_record();
+        record.add_attribute(EVENT_ID, 100_000_i64);
+        assert_eq!(
+            message(&record, Severity::Fatal),
+            "severity: FATAL\nevent_id: 100000"
+        );
+        assert_eq!(event_id(&record), 0);
+    }
+}
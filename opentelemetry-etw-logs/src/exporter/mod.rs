@@ -3,6 +3,7 @@ use std::cell::RefCell;
 use std::collections::HashSet;
 use std::fmt::Debug;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use tracelogging_dynamic as tld;
@@ -12,12 +13,18 @@ use opentelemetry::{logs::AnyValue, otel_debug, Key, Value};
 use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
 
 pub(crate) mod common;
+#[cfg(feature = "event_log_fallback")]
+mod event_log;
 pub(crate) mod options;
 mod part_a;
 mod part_b;
 mod part_c;
 
 pub(crate) use options::Options;
+pub use options::{EventNaming, OversizedEventPolicy};
+
+/// Win32 error returned by `EventWrite` for events larger than 64KB.
+const ERROR_ARITHMETIC_OVERFLOW: u32 = 534;
 
 // Thread-local EventBuilder to avoid heap allocations on every export.
 thread_local! {
@@ -36,6 +43,11 @@ pub(crate) struct ETWExporter {
     resource: Resource,
     options: Options,
     resource_attribute_keys: HashSet<Cow<'static, str>>,
+    oversized_dropped: AtomicU64,
+    #[cfg(feature = "event_log_fallback")]
+    event_log: Option<event_log::EventLog>,
+    #[cfg(feature = "self-metrics")]
+    metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics,
 }
 
 fn enabled_callback_noop(
@@ -70,12 +82,24 @@ impl ETWExporter {
         }
 
         let resource_attribute_keys = options.resource_attribute_keys().clone();
+        #[cfg(feature = "event_log_fallback")]
+        let event_log = options
+            .event_log_fallback()
+            .and_then(|(source, min_severity)| event_log::EventLog::register(source, min_severity));
 
         ETWExporter {
             provider,
             resource: Default::default(),
             resource_attribute_keys,
             options,
+            oversized_dropped: AtomicU64::new(0),
+            #[cfg(feature = "event_log_fallback")]
+            event_log,
+            #[cfg(feature = "self-metrics")]
+            metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics::new(
+                "etw",
+                opentelemetry_contrib::exporter_metrics::Signal::Logs,
+            ),
         }
     }
 
@@ -97,10 +121,90 @@ impl ETWExporter {
         let otel_level = log_record.severity_number().unwrap_or(Severity::Debug);
         let level = common::convert_severity_to_level(otel_level);
 
+        // Written regardless of ETW sessions, which is the point of the fallback.
+        #[cfg(feature = "event_log_fallback")]
+        if let Some(event_log) = &self.event_log {
+            event_log.write(log_record, otel_level);
+        }
+
         if !self.enabled(level) {
             return;
         };
 
+        #[cfg(feature = "self-metrics")]
+        let start = std::time::Instant::now();
+
+        let max_body_size = self.options.max_body_size();
+        let oversized = log_record
+            .body()
+            .and_then(|body| part_b::BodySlice::oversized(body, max_body_size));
+
+        let result = match (oversized, self.options.oversized_event_policy()) {
+            (None, _) => self.write_event(
+                log_record,
+                log_record.body().map(part_b::Body::Value),
+                level,
+                otel_level,
+            ),
+            (Some(slice), OversizedEventPolicy::Truncate) => self.write_event(
+                log_record,
+                Some(part_b::Body::Truncated(slice.truncate(max_body_size))),
+                level,
+                otel_level,
+            ),
+            (Some(slice), OversizedEventPolicy::Split) => {
+                let chunks = slice.split(max_body_size);
+                let count = chunks.len() as u32;
+                chunks
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, slice)| {
+                        self.write_event(
+                            log_record,
+                            Some(part_b::Body::Chunk {
+                                slice,
+                                index: index as u32,
+                                count,
+                            }),
+                            level,
+                            otel_level,
+                        )
+                    })
+                    .find(|result| *result != 0)
+                    .unwrap_or(0)
+            }
+            (Some(_), OversizedEventPolicy::Drop) => {
+                self.record_oversized_dropped();
+                return;
+            }
+        };
+
+        // event.write() above returns 0 for success or a Win32 error from EventWrite for failure.
+        // The return value is for diagnostic purposes only and should generally be ignored in retail builds.
+        match result {
+            0 => {
+                #[cfg(feature = "self-metrics")]
+                self.metrics.record_success(1, start.elapsed());
+            }
+            // The event is still larger than ETW allows, e.g. because of its attributes.
+            ERROR_ARITHMETIC_OVERFLOW => self.record_oversized_dropped(),
+            _ => {
+                #[cfg(feature = "self-metrics")]
+                self.metrics.record_failure(1, start.elapsed(), "write");
+                debug_assert!(false, "Failed to write event to ETW. ETW reason: {result}");
+            }
+        }
+    }
+
+    /// Writes a single event for `log_record` with the given `body`, returning the result of
+    /// `EventWrite`.
+    fn write_event(
+        &self,
+        log_record: &opentelemetry_sdk::logs::SdkLogRecord,
+        body: Option<part_b::Body<'_>>,
+        level: tld::Level,
+        otel_level: Severity,
+    ) -> u32 {
         let event_tags: u32 = 0; // TBD name and event_tag values
         let field_tag: u32 = 0;
 
@@ -119,20 +223,29 @@ impl ETWExporter {
 
             let event_id = part_c::populate_part_c(event, log_record, &self.resource, field_tag);
 
-            part_b::populate_part_b(event, log_record, otel_level, event_id);
+            let severity_text = log_record.severity_text().or_else(|| {
+                (self.options.event_naming() == EventNaming::FromRecord)
+                    .then_some(otel_level.name())
+            });
+            part_b::populate_part_b(event, log_record, body, otel_level, severity_text, event_id);
 
             // Write event to ETW
-            let result = event.write(&self.provider, None, None);
-
-            // event.write() above returns 0 for success or a Win32 error from EventWrite for failure.
-            // The return value is for diagnostic purposes only and should generally be ignored in retail builds.
-            match result {
-                0 => (),
-                _ => debug_assert!(false, "Failed to write event to ETW. ETW reason: {result}"),
-            }
+            event.write(&self.provider, None, None)
         })
     }
 
+    fn record_oversized_dropped(&self) {
+        self.oversized_dropped.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "self-metrics")]
+        self.metrics.record_dropped(1, "oversized");
+        otel_debug!(name: "ETW.OversizedEventDropped", message = "Log record dropped as its ETW event is larger than 64KB.");
+    }
+
+    /// Returns the number of log records dropped because their event was too large for ETW.
+    pub(crate) fn oversized_dropped(&self) -> u64 {
+        self.oversized_dropped.load(Ordering::Relaxed)
+    }
+
     pub(crate) fn shutdown(&self) -> OTelSdkResult {
         let res = self.provider.as_ref().unregister();
         if res != 0 {
@@ -149,6 +262,12 @@ impl ETWExporter {
         _target: &str,
         _name: Option<&str>,
     ) -> bool {
+        #[cfg(feature = "event_log_fallback")]
+        if let Some(event_log) = &self.event_log {
+            if event_log.enabled(level) {
+                return true;
+            }
+        }
         self.enabled(common::convert_severity_to_level(level))
     }
 
@@ -5,11 +5,47 @@ use std::error::Error;
 
 type BoxedEventNameCallback = Box<dyn EventNameCallback>;
 
+/// Default maximum size of a log body in a single ETW event, in bytes.
+///
+/// ETW rejects events larger than 64KB, this leaves room for the other fields of the event.
+pub(crate) const DEFAULT_MAX_BODY_SIZE: usize = 32 * 1024;
+
+/// What to do with a log record whose body is larger than the maximum body size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OversizedEventPolicy {
+    /// Truncate the body to the maximum size and add a `truncated` field set to `true` to PartB.
+    #[default]
+    Truncate,
+    /// Split the body into several events of at most the maximum size, each with the same
+    /// fields and with `chunkIndex` and `chunkCount` fields in PartB.
+    Split,
+    /// Drop the log record.
+    Drop,
+}
+
+/// How the ETW events of the log records are named.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EventNaming {
+    /// Name all the events "Log".
+    #[default]
+    Fixed,
+    /// Name the events after the `event.name` of their log record, else its target, else "Log",
+    /// so that pipelines can route them by name. PartB always has a `severityText` field, the
+    /// severity text of the log record or the name of its severity, e.g. `WARN`, next to
+    /// `severityNumber`.
+    FromRecord,
+}
+
 #[derive(Debug)]
 pub(crate) struct Options {
     provider_name: Cow<'static, str>,
     event_name_callback: Option<BoxedEventNameCallback>,
     resource_attribute_keys: HashSet<Cow<'static, str>>,
+    oversized_event_policy: OversizedEventPolicy,
+    max_body_size: usize,
+    event_naming: EventNaming,
+    #[cfg(feature = "event_log_fallback")]
+    event_log_fallback: Option<(Cow<'static, str>, opentelemetry::logs::Severity)>,
 }
 
 impl Options {
@@ -18,6 +54,11 @@ impl Options {
             provider_name: provider_name.into(),
             event_name_callback: None,
             resource_attribute_keys: HashSet::new(),
+            oversized_event_policy: OversizedEventPolicy::default(),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            event_naming: EventNaming::default(),
+            #[cfg(feature = "event_log_fallback")]
+            event_log_fallback: None,
         }
     }
 
@@ -41,6 +82,60 @@ impl Options {
         self
     }
 
+    /// Returns the policy applied to log records with an oversized body.
+    pub(crate) fn oversized_event_policy(&self) -> OversizedEventPolicy {
+        self.oversized_event_policy
+    }
+
+    /// Returns the maximum size of a log body in a single ETW event, in bytes.
+    pub(crate) fn max_body_size(&self) -> usize {
+        self.max_body_size
+    }
+
+    /// Sets the policy applied to log records with an oversized body.
+    pub(crate) fn with_oversized_event_policy(mut self, policy: OversizedEventPolicy) -> Self {
+        self.oversized_event_policy = policy;
+        self
+    }
+
+    /// Sets the maximum size of a log body in a single ETW event, in bytes.
+    pub(crate) fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Returns how the ETW events are named.
+    pub(crate) fn event_naming(&self) -> EventNaming {
+        self.event_naming
+    }
+
+    /// Sets how the ETW events are named.
+    pub(crate) fn with_event_naming(mut self, event_naming: EventNaming) -> Self {
+        self.event_naming = event_naming;
+        self
+    }
+
+    /// Returns the Event Log source and minimum severity of the records also written to the
+    /// Windows Event Log, if any.
+    #[cfg(feature = "event_log_fallback")]
+    pub(crate) fn event_log_fallback(&self) -> Option<(&str, opentelemetry::logs::Severity)> {
+        self.event_log_fallback
+            .as_ref()
+            .map(|(source, min_severity)| (source.as_ref(), *min_severity))
+    }
+
+    /// Sets the Event Log source and minimum severity of the records also written to the Windows
+    /// Event Log.
+    #[cfg(feature = "event_log_fallback")]
+    pub(crate) fn with_event_log_fallback(
+        mut self,
+        source: impl Into<Cow<'static, str>>,
+        min_severity: opentelemetry::logs::Severity,
+    ) -> Self {
+        self.event_log_fallback = Some((source.into(), min_severity));
+        self
+    }
+
     /// Returns the default event name that will be used for the ETW events.
     pub(crate) fn default_event_name(&self) -> &str {
         "Log"
@@ -57,6 +152,14 @@ impl Options {
                 return result;
             }
         }
+        if self.event_naming == EventNaming::FromRecord {
+            let name = log_record
+                .event_name()
+                .or(log_record.target().map(|target| target.as_ref()));
+            if let Some(name) = name.filter(|name| validate_etw_event_name(name).is_ok()) {
+                return name;
+            }
+        }
         self.default_event_name()
     }
 
@@ -116,6 +219,35 @@ mod tests {
         assert_eq!(result, "Log");
     }
 
+    #[test]
+    fn test_get_event_name_from_record() {
+        use opentelemetry::logs::LogRecord;
+
+        let mut log_record = test_utils::new_sdk_log_record();
+
+        let options = test_utils::test_options().with_event_naming(EventNaming::FromRecord);
+
+        let result = options.get_etw_event_name(&log_record);
+        assert_eq!(result, "Log");
+
+        log_record.set_target("target-name");
+        let result = options.get_etw_event_name(&log_record);
+        assert_eq!(result, "target-name");
+
+        log_record.set_event_name("event-name");
+        let result = options.get_etw_event_name(&log_record);
+        assert_eq!(result, "event-name");
+
+        log_record.set_event_name("");
+        let result = options.get_etw_event_name(&log_record);
+        assert_eq!(result, "Log");
+
+        // The callback takes precedence.
+        let options = options.etw_event_name_from_callback(|_| "CustomEvent");
+        let result = options.get_etw_event_name(&log_record);
+        assert_eq!(result, "CustomEvent");
+    }
+
     #[test]
     fn test_get_event_name_from_callback_returning_const_value() {
         use opentelemetry::logs::LogRecord;
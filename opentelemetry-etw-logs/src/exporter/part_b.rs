@@ -1,20 +1,130 @@
-use opentelemetry::{logs::Severity, Key};
+use opentelemetry::{
+    logs::{AnyValue, Severity},
+    Key,
+};
 use tracelogging_dynamic as tld;
 
+/// The body written to an event, see [`super::OversizedEventPolicy`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Body<'a> {
+    /// The whole body of the log record.
+    Value(&'a AnyValue),
+    /// The start of a body larger than the maximum body size.
+    Truncated(BodySlice<'a>),
+    /// One of the chunks of a body larger than the maximum body size.
+    Chunk {
+        slice: BodySlice<'a>,
+        index: u32,
+        count: u32,
+    },
+}
+
+/// A part of a string or bytes body.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum BodySlice<'a> {
+    Str(&'a str),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> BodySlice<'a> {
+    /// Returns the body as a slice if it is larger than `max_size` bytes.
+    ///
+    /// Only string and bytes bodies are considered, as they are the ones carrying large payloads
+    /// such as stack dumps.
+    pub(crate) fn oversized(body: &'a AnyValue, max_size: usize) -> Option<Self> {
+        let slice = match body {
+            AnyValue::String(s) => BodySlice::Str(s.as_str()),
+            AnyValue::Bytes(b) => BodySlice::Bytes(b.as_slice()),
+            _ => return None,
+        };
+        (slice.len() > max_size).then_some(slice)
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            BodySlice::Str(s) => s.len(),
+            BodySlice::Bytes(b) => b.len(),
+        }
+    }
+
+    /// Returns the longest prefix of at most `max_size` bytes, not splitting UTF-8 characters.
+    pub(crate) fn truncate(self, max_size: usize) -> Self {
+        match self {
+            BodySlice::Str(s) => BodySlice::Str(&s[..floor_char_boundary(s, max_size)]),
+            BodySlice::Bytes(b) => BodySlice::Bytes(&b[..max_size.min(b.len())]),
+        }
+    }
+
+    /// Splits the body into chunks of at most `max_size` bytes, not splitting UTF-8 characters.
+    ///
+    /// A chunk is longer than `max_size` only if `max_size` is smaller than a single character.
+    pub(crate) fn split(self, max_size: usize) -> Vec<Self> {
+        let max_size = max_size.max(1);
+        match self {
+            BodySlice::Str(mut rest) => {
+                let mut chunks = Vec::with_capacity(rest.len().div_ceil(max_size));
+                while !rest.is_empty() {
+                    let mut end = floor_char_boundary(rest, max_size);
+                    if end == 0 {
+                        end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+                    }
+                    let (chunk, tail) = rest.split_at(end);
+                    chunks.push(BodySlice::Str(chunk));
+                    rest = tail;
+                }
+                chunks
+            }
+            BodySlice::Bytes(b) => b.chunks(max_size).map(BodySlice::Bytes).collect(),
+        }
+    }
+
+    fn add_to_event(self, event: &mut tld::EventBuilder, name: &str) {
+        match self {
+            BodySlice::Str(s) => {
+                event.add_str8(name, s, tld::OutType::Default, 0);
+            }
+            BodySlice::Bytes(b) => {
+                event.add_binary(name, b, tld::OutType::Default, 0);
+            }
+        }
+    }
+}
+
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    if index >= s.len() {
+        return s.len();
+    }
+    (0..=index)
+        .rev()
+        .find(|i| s.is_char_boundary(*i))
+        .unwrap_or(0)
+}
+
 pub(crate) fn populate_part_b(
     event: &mut tld::EventBuilder,
     log_record: &opentelemetry_sdk::logs::SdkLogRecord,
+    body: Option<Body<'_>>,
     level: Severity,
+    severity_text: Option<&str>,
     event_id: Option<i64>,
 ) {
     // Count fields in PartB
     const COUNT_TYPE_NAME: u8 = 1u8;
     const COUNT_SEVERITY_NUMBER: u8 = 1u8;
 
+    let count_body = match body {
+        None => 0,
+        Some(Body::Value(_)) => 1,
+        // body and truncated
+        Some(Body::Truncated(_)) => 2,
+        // body, chunkIndex and chunkCount
+        Some(Body::Chunk { .. }) => 3,
+    };
+
     let field_count = COUNT_TYPE_NAME
         + COUNT_SEVERITY_NUMBER
-        + log_record.body().is_some() as u8
-        + log_record.severity_text().is_some() as u8
+        + count_body
+        + severity_text.is_some() as u8
         + event_id.is_some() as u8
         + log_record.event_name().is_some() as u8;
 
@@ -24,13 +134,29 @@ pub(crate) fn populate_part_b(
     // Fill fields of PartB struct
     event.add_str8("_typeName", "Log", tld::OutType::Default, 0);
 
-    if let Some(body) = log_record.body() {
-        super::common::add_attribute_to_event(event, &Key::new("body"), body);
+    match body {
+        None => (),
+        Some(Body::Value(body)) => {
+            super::common::add_attribute_to_event(event, &Key::new("body"), body);
+        }
+        Some(Body::Truncated(slice)) => {
+            slice.add_to_event(event, "body");
+            event.add_bool32("truncated", true as i32, tld::OutType::Default, 0);
+        }
+        Some(Body::Chunk {
+            slice,
+            index,
+            count,
+        }) => {
+            slice.add_to_event(event, "body");
+            event.add_u32("chunkIndex", index, tld::OutType::Default, 0);
+            event.add_u32("chunkCount", count, tld::OutType::Default, 0);
+        }
     }
 
     event.add_i16("severityNumber", level as i16, tld::OutType::Default, 0);
 
-    if let Some(severity_text) = &log_record.severity_text() {
+    if let Some(severity_text) = severity_text {
         event.add_str8("severityText", severity_text, tld::OutType::Default, 0);
     }
 
@@ -59,4 +185,62 @@ mod test {
         let instrumentation = test_utils::new_instrumentation_scope();
         exporter.export_log_data(&log_record, &instrumentation);
     }
+
+    #[test]
+    fn test_oversized() {
+        use super::BodySlice;
+        use opentelemetry::logs::AnyValue;
+
+        let body = AnyValue::from("0123456789");
+        assert_eq!(BodySlice::oversized(&body, 10), None);
+        assert_eq!(
+            BodySlice::oversized(&body, 4),
+            Some(BodySlice::Str("0123456789"))
+        );
+        assert_eq!(BodySlice::oversized(&AnyValue::Int(1), 0), None);
+    }
+
+    #[test]
+    fn test_truncate() {
+        use super::BodySlice;
+
+        assert_eq!(BodySlice::Str("0123").truncate(2), BodySlice::Str("01"));
+        // "é" is 2 bytes long, and is not split
+        assert_eq!(BodySlice::Str("aéb").truncate(2), BodySlice::Str("a"));
+        assert_eq!(
+            BodySlice::Bytes(&[1, 2, 3]).truncate(2),
+            BodySlice::Bytes(&[1, 2])
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        use super::BodySlice;
+
+        assert_eq!(
+            BodySlice::Str("01234").split(2),
+            [
+                BodySlice::Str("01"),
+                BodySlice::Str("23"),
+                BodySlice::Str("4")
+            ]
+        );
+        assert_eq!(
+            BodySlice::Str("aéé").split(2),
+            [
+                BodySlice::Str("a"),
+                BodySlice::Str("é"),
+                BodySlice::Str("é")
+            ]
+        );
+        // A character larger than the maximum size is kept whole
+        assert_eq!(
+            BodySlice::Str("éé").split(1),
+            [BodySlice::Str("é"), BodySlice::Str("é")]
+        );
+        assert_eq!(
+            BodySlice::Bytes(&[1, 2, 3]).split(2),
+            [BodySlice::Bytes(&[1, 2]), BodySlice::Bytes(&[3])]
+        );
+    }
 }
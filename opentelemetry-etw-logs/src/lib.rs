@@ -65,10 +65,14 @@
 #[cfg(feature = "serde_json")]
 mod converters;
 mod exporter;
+mod panic_hook;
 mod processor;
 
+pub use exporter::{EventNaming, OversizedEventPolicy};
+pub use panic_hook::{install_panic_hook, PanicHookGuard, PANIC_EVENT_NAME};
 pub use processor::Processor;
 pub use processor::ProcessorBuilder;
+pub use processor::ProviderNameError;
 
 #[cfg(all(test, target_os = "windows"))]
 mod integration_tests {
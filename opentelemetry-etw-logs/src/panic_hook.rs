@@ -0,0 +1,219 @@
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::fmt;
+use std::panic::{self, Location};
+use std::sync::Arc;
+
+use opentelemetry::logs::{AnyValue, LogRecord, Logger, LoggerProvider, Severity};
+use opentelemetry::{otel_debug, otel_warn, Context, InstrumentationScope};
+use opentelemetry_sdk::logs::{SdkLogger, SdkLoggerProvider};
+
+/// Name of the event of the log record emitted when the process panics.
+pub const PANIC_EVENT_NAME: &str = "Panic";
+
+thread_local! {
+    /// Whether the panic hook runs on this thread, so a panic while emitting or flushing the
+    /// record of another one doesn't re-enter it.
+    static IN_PANIC_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Installs a panic hook emitting a log record for the panic, then flushing `provider`, before
+/// running the previous hook.
+///
+/// The log record is emitted before the process unwinds or aborts, so the crash is written to
+/// ETW by the [`Processor`](crate::Processor) of the provider, and the records buffered by its
+/// other processors, e.g. a batch processor, are exported.
+///
+/// It is a [`Severity::Fatal`] record named [`PANIC_EVENT_NAME`], whose body is the panic
+/// message, with the `exception.message`, `exception.stacktrace`, `code.filepath`,
+/// `code.lineno` and `thread.name` attributes. The backtrace is captured whatever
+/// `RUST_BACKTRACE` is. Every panic is recorded, including the ones caught by
+/// [`catch_unwind`](std::panic::catch_unwind) or an async runtime, except the panics of the
+/// threads on which telemetry is suppressed, e.g. the worker of a batch processor, and the
+/// panics raised while the hook runs, whose flush would wait for themselves.
+///
+/// The hook only holds a weak reference to the provider, owned by the returned
+/// [`PanicHookGuard`]. Dropping the guard, e.g. when `main` returns, flushes the provider and
+/// stops the hook from emitting records. The provider isn't shut down by the guard:
+/// [`SdkLoggerProvider::shutdown`] must still be called.
+///
+/// ```no_run
+/// use opentelemetry_etw_logs::Processor;
+/// use opentelemetry_sdk::logs::SdkLoggerProvider;
+///
+/// let provider = SdkLoggerProvider::builder()
+///     .with_log_processor(Processor::builder("myprovider").build().unwrap())
+///     .build();
+/// let guard = opentelemetry_etw_logs::install_panic_hook(&provider);
+///
+/// // ...
+///
+/// drop(guard);
+/// provider.shutdown().unwrap();
+/// ```
+pub fn install_panic_hook(provider: &SdkLoggerProvider) -> PanicHookGuard {
+    let guard = PanicHookGuard::new(provider);
+    let state = Arc::downgrade(&guard.state);
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let reentered = IN_PANIC_HOOK.with(|in_hook| in_hook.replace(true));
+        if !reentered && !Context::is_current_telemetry_suppressed() {
+            if let Some(state) = state.upgrade() {
+                let backtrace = Backtrace::force_capture().to_string();
+                emit_panic(&state.logger, info.payload(), info.location(), backtrace);
+                if let Err(err) = state.provider.force_flush() {
+                    otel_warn!(name: "ETW.PanicHookFlushFailed", error = format!("{err}"));
+                }
+            }
+        }
+        if !reentered {
+            IN_PANIC_HOOK.with(|in_hook| in_hook.set(false));
+        }
+        previous(info);
+    }));
+    guard
+}
+
+/// Owns the logger provider of the panic hook installed by [`install_panic_hook`].
+///
+/// Dropping it flushes the provider, and the hook no longer emits log records.
+#[must_use = "the panic hook no longer emits log records once the guard is dropped"]
+pub struct PanicHookGuard {
+    state: Arc<PanicHookState>,
+}
+
+struct PanicHookState {
+    logger: SdkLogger,
+    provider: SdkLoggerProvider,
+}
+
+impl PanicHookGuard {
+    fn new(provider: &SdkLoggerProvider) -> Self {
+        let logger = provider.logger_with_scope(
+            InstrumentationScope::builder("opentelemetry-etw-logs")
+                .with_version(env!("CARGO_PKG_VERSION"))
+                .build(),
+        );
+        PanicHookGuard {
+            state: Arc::new(PanicHookState {
+                logger,
+                provider: provider.clone(),
+            }),
+        }
+    }
+}
+
+impl Drop for PanicHookGuard {
+    fn drop(&mut self) {
+        if let Err(err) = self.state.provider.force_flush() {
+            otel_debug!(name: "ETW.PanicHookGuardFlushFailed", error = format!("{err}"));
+        }
+    }
+}
+
+impl fmt::Debug for PanicHookGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PanicHookGuard").finish_non_exhaustive()
+    }
+}
+
+/// Emits the log record of a panic with `payload` at `location`.
+fn emit_panic(
+    logger: &SdkLogger,
+    payload: &(dyn Any + Send),
+    location: Option<&Location<'_>>,
+    backtrace: String,
+) {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string());
+
+    let mut record = logger.create_log_record();
+    record.set_event_name(PANIC_EVENT_NAME);
+    record.set_severity_number(Severity::Fatal);
+    record.set_severity_text("FATAL");
+    record.set_timestamp(opentelemetry::time::now());
+    record.set_body(AnyValue::from(message.clone()));
+    record.add_attribute("exception.message", message);
+    record.add_attribute("exception.stacktrace", backtrace);
+    if let Some(location) = location {
+        record.add_attribute("code.filepath", location.file().to_string());
+        record.add_attribute("code.lineno", i64::from(location.line()));
+    }
+    if let Some(name) = std::thread::current().name() {
+        record.add_attribute("thread.name", name.to_string());
+    }
+    logger.emit(record);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry_sdk::logs::{BatchLogProcessor, InMemoryLogExporter, SimpleLogProcessor};
+
+    #[test]
+    fn test_emit_panic() {
+        let exporter = InMemoryLogExporter::default();
+        let provider = SdkLoggerProvider::builder()
+            .with_log_processor(SimpleLogProcessor::new(exporter.clone()))
+            .build();
+        let logger = provider.logger("test");
+
+        let payload: Box<dyn Any + Send> = Box::new(String::from("index out of bounds"));
+        emit_panic(
+            &logger,
+            payload.as_ref(),
+            Some(Location::caller()),
+            "0: main".to_string(),
+        );
+        emit_panic(&logger, &42, None, String::new());
+
+        let logs = exporter.get_emitted_logs().unwrap();
+        assert_eq!(logs.len(), 2);
+        let record = &logs[0].record;
+        assert_eq!(record.event_name(), Some(PANIC_EVENT_NAME));
+        assert_eq!(record.severity_number(), Some(Severity::Fatal));
+        assert_eq!(
+            record.body(),
+            Some(&AnyValue::from("index out of bounds".to_string()))
+        );
+        let attribute = |key: &str| {
+            record
+                .attributes_iter()
+                .find(|(k, _)| k.as_str() == key)
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(
+            attribute("exception.stacktrace"),
+            Some(AnyValue::from("0: main".to_string()))
+        );
+        assert_eq!(
+            attribute("code.filepath"),
+            Some(AnyValue::from(file!().to_string()))
+        );
+
+        assert_eq!(
+            logs[1].record.body(),
+            Some(&AnyValue::from("Box<dyn Any>".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_guard_flushes_on_drop() {
+        let exporter = InMemoryLogExporter::default();
+        let provider = SdkLoggerProvider::builder()
+            .with_log_processor(BatchLogProcessor::builder(exporter.clone()).build())
+            .build();
+        let guard = PanicHookGuard::new(&provider);
+        let state = Arc::downgrade(&guard.state);
+        emit_panic(&guard.state.logger, &"boom", None, String::new());
+
+        drop(guard);
+        assert!(state.upgrade().is_none());
+        assert_eq!(exporter.get_emitted_logs().unwrap().len(), 1);
+        provider.shutdown().unwrap();
+    }
+}
@@ -4,7 +4,8 @@ use opentelemetry_sdk::logs::SdkLogRecord;
 use opentelemetry_sdk::Resource;
 use std::borrow::Cow;
 use std::error::Error;
-use std::fmt::Debug;
+use std::fmt::{Debug, Display, Formatter};
+use tracelogging_dynamic as tld;
 
 use crate::exporter::*;
 
@@ -59,6 +60,24 @@ impl Processor {
         ProcessorBuilder::new_etw_compat_only(provider_name)
     }
 
+    /// Returns the GUID of the ETW provider with the given name, e.g. `b8c0ba4e-...`.
+    ///
+    /// ETW provider GUIDs are derived from the provider name, so they can be computed ahead of
+    /// time to register the provider in WPR profiles, `logman` sessions or collection agents.
+    /// The name is validated with the rules of [`Processor::builder_etw_compat_only`].
+    pub fn provider_guid(provider_name: &str) -> Result<String, ProviderNameError> {
+        validate_provider_name(provider_name, ProviderNameCompatMode::EtwCompatOnly)?;
+        let guid = tld::Guid::from_name(provider_name).to_utf8_bytes();
+        Ok(String::from_utf8_lossy(&guid).into_owned())
+    }
+
+    /// Returns the number of log records dropped because their ETW event was too large.
+    ///
+    /// See [`ProcessorBuilder::with_oversized_event_policy`].
+    pub fn oversized_dropped(&self) -> u64 {
+        self.event_exporter.oversized_dropped()
+    }
+
     /// Creates a new instance of the [`Processor`] using the given options.
     pub(crate) fn new(options: Options) -> Self {
         let exporter: ETWExporter = ETWExporter::new(options);
@@ -68,6 +87,51 @@ impl Processor {
     }
 }
 
+/// Error returned when a provider name breaks the TraceLogging naming rules.
+///
+/// It is returned by [`ProcessorBuilder::build`], boxed, and by [`Processor::provider_guid`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProviderNameError {
+    /// The provider name is empty.
+    Empty,
+    /// The provider name is 234 characters long or more.
+    TooLong,
+    /// The provider name contains a character that is not allowed, hyphens being only allowed
+    /// by [`Processor::builder_etw_compat_only`].
+    InvalidCharacter {
+        /// The first character not allowed.
+        character: char,
+        /// Whether hyphens are allowed.
+        hyphen_allowed: bool,
+    },
+}
+
+impl Display for ProviderNameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProviderNameError::Empty => f.write_str("Provider name must not be empty."),
+            ProviderNameError::TooLong => {
+                f.write_str("Provider name must be less than 234 characters long.")
+            }
+            ProviderNameError::InvalidCharacter {
+                hyphen_allowed: false,
+                ..
+            } => {
+                f.write_str("Provider name must contain only ASCII alphanumeric characters or '_'.")
+            }
+            ProviderNameError::InvalidCharacter {
+                hyphen_allowed: true,
+                ..
+            } => f.write_str(
+                "Provider name must contain only ASCII alphanumeric characters, '_' or '-'.",
+            ),
+        }
+    }
+}
+
+impl Error for ProviderNameError {}
+
 #[derive(Debug, Copy, Clone)]
 enum ProviderNameCompatMode {
     /// Cross-compatible with UserEvents (Linux).
@@ -187,6 +251,80 @@ impl ProcessorBuilder {
         self
     }
 
+    /// Sets what to do with log records whose body is larger than the maximum body size.
+    ///
+    /// ETW fails to write events larger than 64KB, so large bodies such as stack dumps would
+    /// otherwise be lost. By default, the body is truncated and a `truncated` field set to `true`
+    /// is added to PartB. Only string and bytes bodies are considered.
+    ///
+    /// Log records dropped by [`OversizedEventPolicy::Drop`], or whose event is still too large,
+    /// e.g. because of their attributes, are counted by [`Processor::oversized_dropped`].
+    pub fn with_oversized_event_policy(mut self, policy: OversizedEventPolicy) -> Self {
+        self.options = self.options.with_oversized_event_policy(policy);
+        self
+    }
+
+    /// Sets the maximum size of a log body in a single ETW event, in bytes. Defaults to 32KB.
+    ///
+    /// The size must leave enough room for the other fields of the event, all of them counting
+    /// toward the 64KB limit of ETW.
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.options = self.options.with_max_body_size(max_body_size);
+        self
+    }
+
+    /// Sets how the ETW events of the log records are named. Defaults to [`EventNaming::Fixed`],
+    /// naming all the events "Log".
+    ///
+    /// With [`EventNaming::FromRecord`], events are named after the `event.name` of their log
+    /// record, else its target, and PartB always has a `severityText` field. A name callback set
+    /// with `etw_event_name_from_callback` takes precedence.
+    ///
+    /// ```
+    /// use opentelemetry_etw_logs::{EventNaming, Processor};
+    ///
+    /// let processor = Processor::builder("myprovider")
+    ///     .with_event_naming(EventNaming::FromRecord)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn with_event_naming(mut self, event_naming: EventNaming) -> Self {
+        self.options = self.options.with_event_naming(event_naming);
+        self
+    }
+
+    /// Also writes the log records of at least `min_severity` to the Windows Event Log, as
+    /// `source` in the Application log, so operators looking only at Event Viewer see them
+    /// even when no ETW session listens to the provider.
+    ///
+    /// Records are written with the Event Log type of their severity, error, warning or
+    /// information, the `event_id` attribute as event ID when it fits in 16 bits, and a message
+    /// made of their body, severity, name, target, trace context and attributes. Unless an
+    /// installer registered `source` under
+    /// `HKLM\SYSTEM\CurrentControlSet\Services\EventLog\Application`, Event Viewer prefixes
+    /// the message with a note that the description of the event ID can't be found.
+    ///
+    /// Nothing is written on other platforms than Windows, nor if the source can't be registered.
+    ///
+    /// ```
+    /// use opentelemetry::logs::Severity;
+    /// use opentelemetry_etw_logs::Processor;
+    ///
+    /// let processor = Processor::builder("myprovider")
+    ///     .with_event_log_fallback("MyService", Severity::Error)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "event_log_fallback")]
+    pub fn with_event_log_fallback(
+        mut self,
+        source: impl Into<Cow<'static, str>>,
+        min_severity: opentelemetry::logs::Severity,
+    ) -> Self {
+        self.options = self.options.with_event_log_fallback(source, min_severity);
+        self
+    }
+
     /// Builds the processor with given options, returning `Error` if it fails.
     pub fn build(self) -> Result<Processor, Box<dyn Error>> {
         self.validate()?;
@@ -196,6 +334,13 @@ impl ProcessorBuilder {
 
     fn validate(&self) -> Result<(), Box<dyn Error>> {
         validate_provider_name(self.options.provider_name(), self.provider_name_compat_mode)?;
+        if self.options.max_body_size() == 0 {
+            return Err("Maximum body size must be greater than 0.".into());
+        }
+        #[cfg(feature = "event_log_fallback")]
+        if let Some(("", _)) = self.options.event_log_fallback() {
+            return Err("Event Log source cannot be empty.".into());
+        }
         Ok(())
     }
 }
@@ -203,39 +348,28 @@ impl ProcessorBuilder {
 fn validate_provider_name(
     provider_name: &str,
     compat_mode: ProviderNameCompatMode,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), ProviderNameError> {
     if provider_name.is_empty() {
-        return Err("Provider name must not be empty.".into());
+        return Err(ProviderNameError::Empty);
     }
     if provider_name.len() >= 234 {
-        return Err("Provider name must be less than 234 characters long.".into());
-    }
-
-    match compat_mode {
-        ProviderNameCompatMode::CrossCompat => {
-            if !provider_name
-                .chars()
-                .all(|c| c.is_ascii_alphanumeric() || c == '_')
-            {
-                return Err(
-                    "Provider name must contain only ASCII alphanumeric characters or '_'.".into(),
-                );
-            }
-        }
-        ProviderNameCompatMode::EtwCompatOnly => {
-            if !provider_name
-                .chars()
-                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
-            {
-                return Err(
-                    "Provider name must contain only ASCII alphanumeric characters, '_' or '-'."
-                        .into(),
-                );
-            }
-        }
+        return Err(ProviderNameError::TooLong);
     }
 
-    Ok(())
+    let hyphen_allowed = match compat_mode {
+        ProviderNameCompatMode::CrossCompat => false,
+        ProviderNameCompatMode::EtwCompatOnly => true,
+    };
+    match provider_name
+        .chars()
+        .find(|&c| !(c.is_ascii_alphanumeric() || c == '_' || (hyphen_allowed && c == '-')))
+    {
+        Some(character) => Err(ProviderNameError::InvalidCharacter {
+            character,
+            hyphen_allowed,
+        }),
+        None => Ok(()),
+    }
 }
 
 #[cfg(test)]
@@ -379,6 +513,64 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_oversized_event_policy() {
+        use opentelemetry::logs::LogRecord;
+
+        for policy in [
+            OversizedEventPolicy::Truncate,
+            OversizedEventPolicy::Split,
+            OversizedEventPolicy::Drop,
+        ] {
+            let processor = Processor::builder("provider_name")
+                .with_oversized_event_policy(policy)
+                .with_max_body_size(16)
+                .build()
+                .unwrap();
+
+            let mut record = SdkLoggerProvider::builder()
+                .build()
+                .logger("test")
+                .create_log_record();
+            record.set_body("a stack dump larger than 16 bytes".into());
+            processor.emit(&mut record, &Default::default());
+
+            let expected = u64::from(policy == OversizedEventPolicy::Drop);
+            assert_eq!(processor.oversized_dropped(), expected, "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn test_validate_max_body_size() {
+        assert_eq!(
+            Processor::builder("provider_name")
+                .with_max_body_size(0)
+                .build()
+                .unwrap_err()
+                .to_string(),
+            "Maximum body size must be greater than 0."
+        );
+    }
+
+    #[cfg(feature = "event_log_fallback")]
+    #[test]
+    fn test_validate_event_log_fallback() {
+        use opentelemetry::logs::Severity;
+
+        assert_eq!(
+            Processor::builder("provider_name")
+                .with_event_log_fallback("", Severity::Error)
+                .build()
+                .unwrap_err()
+                .to_string(),
+            "Event Log source cannot be empty."
+        );
+        assert!(Processor::builder("provider_name")
+            .with_event_log_fallback("ContosoService", Severity::Error)
+            .build()
+            .is_ok());
+    }
+
     #[test]
     fn test_validate_empty_name() {
         assert_eq!(
@@ -427,6 +619,35 @@ mod tests {
             .is_ok());
     }
 
+    #[test]
+    fn test_validate_name_typed_error() {
+        let err = Processor::builder("i_have_a_-_").build().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ProviderNameError>(),
+            Some(&ProviderNameError::InvalidCharacter {
+                character: '-',
+                hyphen_allowed: false
+            })
+        );
+    }
+
+    #[test]
+    fn test_provider_guid() {
+        let guid = Processor::provider_guid("provider-name").unwrap();
+        assert_eq!(guid.len(), 36);
+        assert_eq!(guid.matches('-').count(), 4);
+        assert_eq!(Processor::provider_guid("provider-name").unwrap(), guid);
+        assert_ne!(Processor::provider_guid("provider_name").unwrap(), guid);
+        assert_eq!(Processor::provider_guid(""), Err(ProviderNameError::Empty));
+        assert_eq!(
+            Processor::provider_guid("provider name"),
+            Err(ProviderNameError::InvalidCharacter {
+                character: ' ',
+                hyphen_allowed: true
+            })
+        );
+    }
+
     #[test]
     fn test_validate_provider_name_cross_compat() {
         let compat_mode: ProviderNameCompatMode = ProviderNameCompatMode::CrossCompat;
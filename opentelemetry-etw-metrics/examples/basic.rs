@@ -1,17 +1,14 @@
 //! run with `$ cargo run --example basic
 use opentelemetry::{global, metrics::MeterProvider as _, KeyValue};
 use opentelemetry_etw_metrics::MetricsExporter;
-use opentelemetry_sdk::{
-    metrics::{PeriodicReader, SdkMeterProvider},
-    Resource,
-};
+use opentelemetry_sdk::{metrics::SdkMeterProvider, Resource};
+use std::time::Duration;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
 const SERVICE_NAME: &str = "service-name";
 
 fn setup_meter_provider() -> SdkMeterProvider {
-    let exporter = MetricsExporter::new();
-    let reader = PeriodicReader::builder(exporter).build();
+    let reader = MetricsExporter::new().periodic_reader(Duration::from_secs(60));
     SdkMeterProvider::builder()
         .with_resource(
             Resource::builder()
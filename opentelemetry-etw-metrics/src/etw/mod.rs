@@ -32,15 +32,56 @@ pub fn register() {
     });
 }
 
-/// Write an event to the ETW provider.
+/// Keyword of the events when no keyword filter is configured.
+pub(crate) const DEFAULT_KEYWORD: u64 = 1;
+
+/// Whether an ETW session enabled the provider.
+pub fn enabled() -> bool {
+    PROVIDER.enabled(tlg::Level::Informational, 0)
+}
+
+/// Whether an ETW session enabled the provider for `keyword`.
+pub fn keyword_enabled(keyword: u64) -> bool {
+    PROVIDER.enabled(tlg::Level::Informational, keyword)
+}
+
+/// The keyword of an event must be a constant, so the event is written by the arm of the bit
+/// of the keyword.
+macro_rules! write_event_with_keyword_bit {
+    ($buffer:expr, $activity_id:expr, $bit:expr, $($n:literal)*) => {
+        match $bit {
+            $(
+                $n => tracelogging::write_event!(
+                    PROVIDER,
+                    "otlp_metrics",
+                    id_version(81, 0),
+                    level(tracelogging::Level::Informational),
+                    keyword(1u64 << $n),
+                    activity_id($activity_id),
+                    raw_data($buffer)
+                ),
+            )*
+            _ => unreachable!("keyword bit out of range"),
+        }
+    };
+}
+
+/// Write an event with `keyword`, a single bit, and `activity_id` to the ETW provider.
+///
+/// The bytes of `activity_id` are read in the order of its string representation, all zeros
+/// when the event isn't linked to a trace.
 #[allow(clippy::repr_packed_without_abi)]
-pub fn write(buffer: &[u8]) -> u32 {
-    tracelogging::write_event!(
-        PROVIDER,
-        "otlp_metrics",
-        id_version(81, 0),
-        level(tracelogging::Level::Informational),
-        raw_data(&buffer)
+pub fn write(buffer: &[u8], keyword: u64, activity_id: &[u8; 16]) -> u32 {
+    debug_assert!(keyword.is_power_of_two());
+    let activity_id = tlg::Guid::from_bytes_be(activity_id);
+    write_event_with_keyword_bit!(
+        &buffer,
+        &activity_id,
+        keyword.trailing_zeros(),
+        0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15
+        16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31
+        32 33 34 35 36 37 38 39 40 41 42 43 44 45 46 47
+        48 49 50 51 52 53 54 55 56 57 58 59 60 61 62 63
     )
 }
 
@@ -1,11 +1,12 @@
 use crate::etw;
 
-use opentelemetry::otel_warn;
+use opentelemetry::{otel_debug, otel_warn, InstrumentationScope};
 use opentelemetry_proto::tonic::{
     collector::metrics::v1::ExportMetricsServiceRequest,
     metrics::v1::{
-        metric::Data as TonicMetricData, ExponentialHistogram as TonicExponentialHistogram,
-        Gauge as TonicGauge, Histogram as TonicHistogram, Metric as TonicMetric,
+        metric::Data as TonicMetricData, Exemplar as TonicExemplar,
+        ExponentialHistogram as TonicExponentialHistogram, Gauge as TonicGauge,
+        Histogram as TonicHistogram, Metric as TonicMetric,
         ResourceMetrics as TonicResourceMetrics, ScopeMetrics as TonicScopeMetrics,
         Sum as TonicSum, Summary as TonicSummary,
     },
@@ -15,20 +16,112 @@ use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
 use opentelemetry_sdk::metrics::{
     data::{AggregatedMetrics, ResourceMetrics},
     exporter::PushMetricExporter,
-    Temporality,
+    PeriodicReader, Temporality,
 };
 
 use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 use prost::Message;
 
-pub struct MetricsExporter {}
+type InstrumentFilter = Box<dyn Fn(&InstrumentationScope, &str) -> bool + Send + Sync>;
+
+pub struct MetricsExporter {
+    pause_when_disabled: bool,
+    paused: AtomicBool,
+    temporality: Temporality,
+    keyword_filters: Vec<(u64, InstrumentFilter)>,
+    #[cfg(feature = "self-metrics")]
+    metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics,
+}
 
 impl MetricsExporter {
     pub fn new() -> MetricsExporter {
         etw::register();
 
-        MetricsExporter {}
+        MetricsExporter {
+            pause_when_disabled: true,
+            paused: AtomicBool::new(false),
+            temporality: Temporality::Delta,
+            keyword_filters: Vec::new(),
+            #[cfg(feature = "self-metrics")]
+            metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics::new(
+                "etw",
+                opentelemetry_contrib::exporter_metrics::Signal::Metrics,
+            ),
+        }
+    }
+
+    /// Set the aggregation temporality of the exported metrics, `Delta` by default.
+    pub fn with_temporality(mut self, temporality: Temporality) -> Self {
+        self.temporality = temporality;
+        self
+    }
+
+    /// Write the metrics of the instruments matched by `filter`, called with the scope of the
+    /// meter and the name of the instrument, with the ETW `keyword`, so that sessions enabling
+    /// the provider with different keywords receive different sets of instruments, e.g. `0x1`
+    /// for the request metrics and `0x2` for the runtime metrics.
+    ///
+    /// An instrument is written with the keyword of the first filter matching it, and not at
+    /// all if no filter does. Without filters, all the instruments are written with the keyword
+    /// `0x1`. `keyword` must be a single bit, other values are ignored.
+    pub fn with_keyword_filter<F>(mut self, keyword: u64, filter: F) -> Self
+    where
+        F: Fn(&InstrumentationScope, &str) -> bool + Send + Sync + 'static,
+    {
+        if keyword.is_power_of_two() {
+            self.keyword_filters.push((keyword, Box::new(filter)));
+        } else {
+            otel_warn!(name: "MetricExporter.InvalidKeyword", keyword = keyword, message = "The keyword of a filter must be a single bit, the filter is ignored.");
+        }
+        self
+    }
+
+    /// Whether to skip the serialization of the metrics while no ETW session enabled the
+    /// provider, which is the default.
+    ///
+    /// The provider state is checked on every export, so the export resumes at the first
+    /// interval after a session enables it.
+    pub fn with_pause_when_disabled(mut self, pause_when_disabled: bool) -> Self {
+        self.pause_when_disabled = pause_when_disabled;
+        self
+    }
+
+    /// Create a reader exporting the metrics with this exporter every `interval`.
+    pub fn periodic_reader(self, interval: Duration) -> PeriodicReader<MetricsExporter> {
+        PeriodicReader::builder(self)
+            .with_interval(interval)
+            .build()
+    }
+
+    /// The keyword to write the metrics of an instrument with, if any.
+    fn keyword(&self, scope: &InstrumentationScope, name: &str) -> Option<u64> {
+        if self.keyword_filters.is_empty() {
+            return Some(etw::DEFAULT_KEYWORD);
+        }
+        self.keyword_filters
+            .iter()
+            .find(|(_, filter)| filter(scope, name))
+            .map(|(keyword, _)| *keyword)
+    }
+
+    /// Whether the metrics should be exported, logging when the export is paused or resumed.
+    fn is_active(&self) -> bool {
+        if !self.pause_when_disabled {
+            return true;
+        }
+        let enabled = etw::enabled();
+        let was_paused = self.paused.swap(!enabled, Ordering::Relaxed);
+        if was_paused == enabled {
+            if enabled {
+                otel_debug!(name: "MetricExportResumed");
+            } else {
+                otel_debug!(name: "MetricExportPaused");
+            }
+        }
+        enabled
     }
 }
 
@@ -44,35 +137,76 @@ impl Debug for MetricsExporter {
     }
 }
 
-fn emit_export_metric_service_request(
-    export_metric_service_request: &ExportMetricsServiceRequest,
-    encoding_buffer: &mut Vec<u8>,
-) -> OTelSdkResult {
-    if (export_metric_service_request.encoded_len()) > etw::MAX_EVENT_SIZE {
-        otel_warn!(name: "MetricExportFailedDueToMaxSizeLimit", size = export_metric_service_request.encoded_len(), max_size = etw::MAX_EVENT_SIZE);
-    } else {
-        // `encoding_buffer` is assumed to be reused, so ensure it is empty before using it for encoding
-        encoding_buffer.clear();
-
-        export_metric_service_request
-            .encode(encoding_buffer)
-            .map_err(|err| OTelSdkError::InternalFailure(err.to_string()))?;
-
-        let result = etw::write(encoding_buffer);
-        // TODO: Better logging/internal metrics needed here for non-failure
-        // case Uncomment the line below to see the exported bytes until a
-        // better logging solution is implemented
-        // println!("Exported {} bytes to ETW", byte_array.len());
-        if result != 0 {
-            otel_warn!(name: "MetricExportFailed", error_code = result);
-        }
+/// The activity ID of the event of a data point, linking it to the span of its first exemplar
+/// recorded in a sampled span, all zeros when there is none.
+///
+/// The activity ID is derived as in `opentelemetry-user-events-trace`: the first 8 bytes of the
+/// trace ID followed by the span ID, so its string representation reads as their hex IDs and
+/// matches the activity ID of the span. The full IDs of the exemplars stay in the payload.
+fn exemplar_activity_id(exemplars: &[TonicExemplar]) -> [u8; 16] {
+    let mut activity_id = [0; 16];
+    if let Some(exemplar) = exemplars
+        .iter()
+        .find(|exemplar| exemplar.trace_id.len() == 16 && exemplar.span_id.len() == 8)
+    {
+        activity_id[..8].copy_from_slice(&exemplar.trace_id[..8]);
+        activity_id[8..].copy_from_slice(&exemplar.span_id);
     }
+    activity_id
+}
 
-    Ok(())
+impl MetricsExporter {
+    fn emit_export_metric_service_request(
+        &self,
+        export_metric_service_request: &ExportMetricsServiceRequest,
+        encoding_buffer: &mut Vec<u8>,
+        keyword: u64,
+        activity_id: &[u8; 16],
+    ) -> OTelSdkResult {
+        #[cfg(feature = "self-metrics")]
+        let start = std::time::Instant::now();
+        if (export_metric_service_request.encoded_len()) > etw::MAX_EVENT_SIZE {
+            otel_warn!(name: "MetricExportFailedDueToMaxSizeLimit", size = export_metric_service_request.encoded_len(), max_size = etw::MAX_EVENT_SIZE);
+            #[cfg(feature = "self-metrics")]
+            self.metrics.record_failure(1, start.elapsed(), "size");
+        } else {
+            // `encoding_buffer` is assumed to be reused, so ensure it is empty before using it for encoding
+            encoding_buffer.clear();
+
+            export_metric_service_request
+                .encode(encoding_buffer)
+                .map_err(|err| {
+                    #[cfg(feature = "self-metrics")]
+                    self.metrics
+                        .record_failure(1, start.elapsed(), "serialization");
+                    OTelSdkError::InternalFailure(err.to_string())
+                })?;
+
+            let result = etw::write(encoding_buffer, keyword, activity_id);
+            // TODO: Better logging/internal metrics needed here for non-failure
+            // case Uncomment the line below to see the exported bytes until a
+            // better logging solution is implemented
+            // println!("Exported {} bytes to ETW", byte_array.len());
+            if result != 0 {
+                otel_warn!(name: "MetricExportFailed", error_code = result);
+            }
+            #[cfg(feature = "self-metrics")]
+            match result {
+                0 => self.metrics.record_success(1, start.elapsed()),
+                _ => self.metrics.record_failure(1, start.elapsed(), "write"),
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl PushMetricExporter for MetricsExporter {
     async fn export(&self, metrics: &ResourceMetrics) -> OTelSdkResult {
+        if !self.is_active() {
+            return Ok(());
+        }
+
         let schema_url: String = metrics
             .resource()
             .schema_url()
@@ -84,6 +218,13 @@ impl PushMetricExporter for MetricsExporter {
 
         for scope_metric in metrics.scope_metrics() {
             for metric in scope_metric.metrics() {
+                let Some(keyword) = self.keyword(scope_metric.scope(), metric.name()) else {
+                    continue;
+                };
+                if self.pause_when_disabled && !etw::keyword_enabled(keyword) {
+                    continue;
+                }
+
                 let proto_data: TonicMetricData = match metric.data() {
                     AggregatedMetrics::F64(data) => data.into(),
                     AggregatedMetrics::I64(data) => data.into(),
@@ -112,20 +253,24 @@ impl PushMetricExporter for MetricsExporter {
                 match proto_data {
                     TonicMetricData::Histogram(hist) => {
                         for data_point in hist.data_points {
+                            let activity_id = exemplar_activity_id(&data_point.exemplars);
                             export_metrics_service_request.resource_metrics[0].scope_metrics[0]
                                 .metrics[0]
                                 .data = Some(TonicMetricData::Histogram(TonicHistogram {
                                 aggregation_temporality: hist.aggregation_temporality,
                                 data_points: vec![data_point],
                             }));
-                            emit_export_metric_service_request(
+                            self.emit_export_metric_service_request(
                                 &export_metrics_service_request,
                                 &mut encoding_buffer,
+                                keyword,
+                                &activity_id,
                             )?;
                         }
                     }
                     TonicMetricData::ExponentialHistogram(exp_hist) => {
                         for data_point in exp_hist.data_points {
+                            let activity_id = exemplar_activity_id(&data_point.exemplars);
                             export_metrics_service_request.resource_metrics[0].scope_metrics[0]
                                 .metrics[0]
                                 .data = Some(TonicMetricData::ExponentialHistogram(
@@ -134,27 +279,33 @@ impl PushMetricExporter for MetricsExporter {
                                     data_points: vec![data_point],
                                 },
                             ));
-                            emit_export_metric_service_request(
+                            self.emit_export_metric_service_request(
                                 &export_metrics_service_request,
                                 &mut encoding_buffer,
+                                keyword,
+                                &activity_id,
                             )?;
                         }
                     }
                     TonicMetricData::Gauge(gauge) => {
                         for data_point in gauge.data_points {
+                            let activity_id = exemplar_activity_id(&data_point.exemplars);
                             export_metrics_service_request.resource_metrics[0].scope_metrics[0]
                                 .metrics[0]
                                 .data = Some(TonicMetricData::Gauge(TonicGauge {
                                 data_points: vec![data_point],
                             }));
-                            emit_export_metric_service_request(
+                            self.emit_export_metric_service_request(
                                 &export_metrics_service_request,
                                 &mut encoding_buffer,
+                                keyword,
+                                &activity_id,
                             )?;
                         }
                     }
                     TonicMetricData::Sum(sum) => {
                         for data_point in sum.data_points {
+                            let activity_id = exemplar_activity_id(&data_point.exemplars);
                             export_metrics_service_request.resource_metrics[0].scope_metrics[0]
                                 .metrics[0]
                                 .data = Some(TonicMetricData::Sum(TonicSum {
@@ -162,9 +313,11 @@ impl PushMetricExporter for MetricsExporter {
                                 aggregation_temporality: sum.aggregation_temporality,
                                 is_monotonic: sum.is_monotonic,
                             }));
-                            emit_export_metric_service_request(
+                            self.emit_export_metric_service_request(
                                 &export_metrics_service_request,
                                 &mut encoding_buffer,
+                                keyword,
+                                &activity_id,
                             )?;
                         }
                     }
@@ -175,9 +328,11 @@ impl PushMetricExporter for MetricsExporter {
                                 .data = Some(TonicMetricData::Summary(TonicSummary {
                                 data_points: vec![data],
                             }));
-                            emit_export_metric_service_request(
+                            self.emit_export_metric_service_request(
                                 &export_metrics_service_request,
                                 &mut encoding_buffer,
+                                keyword,
+                                &[0; 16],
                             )?;
                         }
                     }
@@ -204,23 +359,88 @@ impl PushMetricExporter for MetricsExporter {
     }
 
     fn temporality(&self) -> Temporality {
-        Temporality::Delta
+        self.temporality
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use opentelemetry::{metrics::MeterProvider as _, KeyValue};
+    use opentelemetry::{metrics::MeterProvider as _, InstrumentationScope, KeyValue};
     use opentelemetry_sdk::{
-        metrics::{PeriodicReader, SdkMeterProvider},
+        metrics::{exporter::PushMetricExporter, PeriodicReader, SdkMeterProvider, Temporality},
         Resource,
     };
 
     use crate::etw;
 
+    #[test]
+    fn pause_when_disabled() {
+        let exporter = super::MetricsExporter::new();
+        assert_eq!(exporter.is_active(), etw::enabled());
+        assert!(exporter.with_pause_when_disabled(false).is_active());
+    }
+
+    #[test]
+    fn keyword_filters() {
+        let request = InstrumentationScope::builder("request").build();
+        let runtime = InstrumentationScope::builder("runtime").build();
+
+        let exporter = super::MetricsExporter::new();
+        assert_eq!(
+            exporter.keyword(&request, "http.server.request.duration"),
+            Some(1)
+        );
+
+        let exporter = exporter
+            .with_keyword_filter(0x1, |scope, _| scope.name() == "request")
+            .with_keyword_filter(0x2, |_, name| name.starts_with("process.runtime."))
+            .with_keyword_filter(0x6, |_, _| true);
+        assert_eq!(exporter.keyword_filters.len(), 2);
+        assert_eq!(
+            exporter.keyword(&request, "http.server.request.duration"),
+            Some(0x1)
+        );
+        assert_eq!(
+            exporter.keyword(&request, "process.runtime.gc.count"),
+            Some(0x1)
+        );
+        assert_eq!(
+            exporter.keyword(&runtime, "process.runtime.gc.count"),
+            Some(0x2)
+        );
+        assert_eq!(exporter.keyword(&runtime, "thread.count"), None);
+    }
+
+    #[test]
+    fn exemplar_activity_id() {
+        use opentelemetry_proto::tonic::metrics::v1::Exemplar;
+
+        let exemplar = |trace_id: Vec<u8>, span_id: Vec<u8>| Exemplar {
+            trace_id,
+            span_id,
+            ..Default::default()
+        };
+        assert_eq!(super::exemplar_activity_id(&[]), [0; 16]);
+        assert_eq!(
+            super::exemplar_activity_id(&[
+                exemplar(vec![], vec![]),
+                exemplar((1..=16).collect(), (0x11..=0x18).collect()),
+            ]),
+            [1, 2, 3, 4, 5, 6, 7, 8, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18]
+        );
+    }
+
+    #[test]
+    fn temporality() {
+        let exporter = super::MetricsExporter::new();
+        assert_eq!(exporter.temporality(), Temporality::Delta);
+        let exporter = exporter.with_temporality(Temporality::Cumulative);
+        assert_eq!(exporter.temporality(), Temporality::Cumulative);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn emit_metrics_that_combined_exceed_etw_max_event_size() {
-        let exporter = super::MetricsExporter::new();
+        let exporter = super::MetricsExporter::new().with_pause_when_disabled(false);
         let reader = PeriodicReader::builder(exporter).build();
         let meter_provider = SdkMeterProvider::builder()
             .with_resource(
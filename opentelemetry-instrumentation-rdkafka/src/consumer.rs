@@ -0,0 +1,290 @@
+use crate::{extract_context, Telemetry, MESSAGING_SYSTEM};
+use opentelemetry::trace::{SpanKind, TraceContextExt, Tracer};
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_semantic_conventions as semconv;
+use rdkafka::consumer::{ConsumerContext, DefaultConsumerContext, StreamConsumer};
+use rdkafka::error::KafkaResult;
+use rdkafka::message::{BorrowedMessage, Message};
+use std::fmt::{self, Debug};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Instant;
+
+const OPERATION_NAME: &str = "process";
+
+/// A [`StreamConsumer`] creating a `CONSUMER` span for every message received.
+///
+/// The span is a child of the context extracted from the message headers with
+/// the global propagator.
+pub struct InstrumentedConsumer<C: ConsumerContext + 'static = DefaultConsumerContext> {
+    consumer: StreamConsumer<C>,
+    consumer_group: Option<String>,
+    telemetry: Arc<Telemetry>,
+}
+
+impl<C: ConsumerContext + 'static> InstrumentedConsumer<C> {
+    /// Wrap `consumer`, using the global tracer and meter providers.
+    pub fn new(consumer: StreamConsumer<C>) -> Self {
+        InstrumentedConsumer {
+            consumer,
+            consumer_group: None,
+            telemetry: Arc::new(Telemetry::global()),
+        }
+    }
+
+    /// Set the `messaging.consumer.group.name` attribute, usually the
+    /// `group.id` the consumer was created with.
+    pub fn with_consumer_group(mut self, consumer_group: impl Into<String>) -> Self {
+        self.consumer_group = Some(consumer_group.into());
+        self
+    }
+
+    /// The wrapped consumer.
+    pub fn inner(&self) -> &StreamConsumer<C> {
+        &self.consumer
+    }
+
+    /// Receive the next message, see [`StreamConsumer::recv`].
+    ///
+    /// The span lasts until the returned [`ConsumedMessage`] is dropped.
+    pub async fn recv(&self) -> KafkaResult<ConsumedMessage<'_>> {
+        let message = self.consumer.recv().await?;
+        Ok(ConsumedMessage::new(
+            message,
+            self.telemetry.clone(),
+            self.consumer_group.as_deref(),
+        ))
+    }
+}
+
+impl<C: ConsumerContext + 'static> Debug for InstrumentedConsumer<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InstrumentedConsumer")
+            .field("consumer_group", &self.consumer_group)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A message received by an [`InstrumentedConsumer`].
+///
+/// Dereferences to the [`BorrowedMessage`]. The `process` span ends when this
+/// is dropped, use [`ConsumedMessage::context`] to make it the parent of the
+/// processing work.
+pub struct ConsumedMessage<'a> {
+    message: BorrowedMessage<'a>,
+    process: Process,
+}
+
+impl<'a> ConsumedMessage<'a> {
+    fn new(
+        message: BorrowedMessage<'a>,
+        telemetry: Arc<Telemetry>,
+        consumer_group: Option<&str>,
+    ) -> Self {
+        let process = Process::start(telemetry, &message, consumer_group);
+        ConsumedMessage { message, process }
+    }
+
+    /// The received message.
+    pub fn message(&self) -> &BorrowedMessage<'a> {
+        &self.message
+    }
+
+    /// The context holding the `process` span.
+    pub fn context(&self) -> &Context {
+        &self.process.cx
+    }
+}
+
+impl<'a> Deref for ConsumedMessage<'a> {
+    type Target = BorrowedMessage<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.message
+    }
+}
+
+impl Debug for ConsumedMessage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsumedMessage")
+            .field("message", &self.message)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The `process` span of a message and its metrics, recorded on drop.
+struct Process {
+    cx: Context,
+    start: Instant,
+    telemetry: Arc<Telemetry>,
+    metric_attributes: Vec<KeyValue>,
+}
+
+impl Process {
+    fn start<M: Message>(
+        telemetry: Arc<Telemetry>,
+        message: &M,
+        consumer_group: Option<&str>,
+    ) -> Self {
+        let topic = message.topic().to_string();
+        let partition = message.partition().to_string();
+        let mut metric_attributes = vec![
+            KeyValue::new(semconv::attribute::MESSAGING_SYSTEM, MESSAGING_SYSTEM),
+            KeyValue::new(semconv::attribute::MESSAGING_OPERATION_NAME, OPERATION_NAME),
+            KeyValue::new(
+                semconv::attribute::MESSAGING_DESTINATION_NAME,
+                topic.clone(),
+            ),
+            KeyValue::new(
+                semconv::attribute::MESSAGING_DESTINATION_PARTITION_ID,
+                partition,
+            ),
+        ];
+        if let Some(consumer_group) = consumer_group {
+            metric_attributes.push(KeyValue::new(
+                semconv::attribute::MESSAGING_CONSUMER_GROUP_NAME,
+                consumer_group.to_string(),
+            ));
+        }
+
+        let mut attributes = metric_attributes.clone();
+        attributes.push(KeyValue::new(
+            semconv::attribute::MESSAGING_OPERATION_TYPE,
+            OPERATION_NAME,
+        ));
+        attributes.push(KeyValue::new(
+            semconv::attribute::MESSAGING_KAFKA_OFFSET,
+            message.offset(),
+        ));
+        if let Some(key) = message.key().and_then(|key| std::str::from_utf8(key).ok()) {
+            attributes.push(KeyValue::new(
+                semconv::attribute::MESSAGING_KAFKA_MESSAGE_KEY,
+                key.to_string(),
+            ));
+        }
+        if let Some(payload) = message.payload() {
+            attributes.push(KeyValue::new(
+                semconv::attribute::MESSAGING_MESSAGE_BODY_SIZE,
+                payload.len() as i64,
+            ));
+        }
+
+        telemetry.consumed_messages.add(1, &metric_attributes);
+
+        let parent = extract_context(message.headers());
+        let span = telemetry
+            .tracer
+            .span_builder(format!("{OPERATION_NAME} {topic}"))
+            .with_kind(SpanKind::Consumer)
+            .with_attributes(attributes)
+            .start_with_context(&telemetry.tracer, &parent);
+
+        Process {
+            cx: Context::current_with_span(span),
+            start: Instant::now(),
+            telemetry,
+            metric_attributes,
+        }
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        self.cx.span().end();
+        self.telemetry
+            .process_duration
+            .record(self.start.elapsed().as_secs_f64(), &self.metric_attributes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inject_context;
+    use opentelemetry::global::{self, BoxedTracer};
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry::trace::{
+        SpanContext, SpanId, TraceFlags, TraceId, TraceState, TracerProvider,
+    };
+    use opentelemetry::Value;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::{InMemorySpanExporterBuilder, SdkTracerProvider};
+    use rdkafka::message::{OwnedHeaders, OwnedMessage, Timestamp};
+
+    #[test]
+    fn test_process_span() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let exporter = InMemorySpanExporterBuilder::new().build();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let telemetry = Arc::new(Telemetry::new(
+            BoxedTracer::new(Box::new(provider.tracer("test_tracer"))),
+            SdkMeterProvider::builder().build().meter("test"),
+        ));
+
+        let producer = SpanContext::new(
+            TraceId::from_hex("0af7651916cd43dd8448eb211c80319c").unwrap(),
+            SpanId::from_hex("b7ad6b7169203331").unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        );
+        let headers = inject_context(
+            &Context::new().with_remote_span_context(producer.clone()),
+            OwnedHeaders::new(),
+        );
+        let message = OwnedMessage::new(
+            Some(b"created".to_vec()),
+            Some(b"order-1".to_vec()),
+            "orders".to_string(),
+            Timestamp::NotAvailable,
+            3,
+            42,
+            Some(headers),
+        );
+
+        let process = Process::start(telemetry, &message, Some("billing"));
+        drop(process);
+
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 1);
+        let span = &spans[0];
+        assert_eq!(span.name, "process orders");
+        assert_eq!(span.span_kind, SpanKind::Consumer);
+        assert_eq!(span.span_context.trace_id(), producer.trace_id());
+        assert_eq!(span.parent_span_id, producer.span_id());
+
+        let attribute = |key: &str| {
+            span.attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(
+            attribute(semconv::attribute::MESSAGING_SYSTEM),
+            Some(Value::from("kafka"))
+        );
+        assert_eq!(
+            attribute(semconv::attribute::MESSAGING_DESTINATION_PARTITION_ID),
+            Some(Value::from("3"))
+        );
+        assert_eq!(
+            attribute(semconv::attribute::MESSAGING_KAFKA_OFFSET),
+            Some(Value::from(42))
+        );
+        assert_eq!(
+            attribute(semconv::attribute::MESSAGING_KAFKA_MESSAGE_KEY),
+            Some(Value::from("order-1"))
+        );
+        assert_eq!(
+            attribute(semconv::attribute::MESSAGING_CONSUMER_GROUP_NAME),
+            Some(Value::from("billing"))
+        );
+        assert_eq!(
+            attribute(semconv::attribute::MESSAGING_MESSAGE_BODY_SIZE),
+            Some(Value::from(7))
+        );
+    }
+}
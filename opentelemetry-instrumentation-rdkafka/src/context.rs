@@ -0,0 +1,109 @@
+use crate::{INSTRUMENTATION_NAME, MESSAGING_SYSTEM};
+use opentelemetry::global;
+use opentelemetry::metrics::Gauge;
+use opentelemetry::KeyValue;
+use opentelemetry_semantic_conventions as semconv;
+use rdkafka::client::ClientContext;
+use rdkafka::consumer::ConsumerContext;
+use rdkafka::statistics::Statistics;
+use std::fmt::{self, Debug};
+
+const CONSUMER_LAG_METRIC: &str = "messaging.kafka.consumer.lag";
+
+/// A client context recording the consumer lag of every assigned partition.
+///
+/// The lag comes from the librdkafka statistics, which are only emitted when
+/// `statistics.interval.ms` is set:
+///
+/// ```no_run
+/// # fn example() -> rdkafka::error::KafkaResult<()> {
+/// use opentelemetry_instrumentation_rdkafka::{InstrumentedConsumer, InstrumentedContext};
+/// use rdkafka::consumer::StreamConsumer;
+/// use rdkafka::ClientConfig;
+///
+/// let consumer: StreamConsumer<InstrumentedContext> = ClientConfig::new()
+///     .set("bootstrap.servers", "localhost:9092")
+///     .set("group.id", "billing")
+///     .set("statistics.interval.ms", "5000")
+///     .create_with_context(InstrumentedContext::new().with_consumer_group("billing"))?;
+/// let consumer = InstrumentedConsumer::new(consumer).with_consumer_group("billing");
+/// # Ok(())
+/// # }
+/// ```
+pub struct InstrumentedContext {
+    consumer_group: Option<String>,
+    consumer_lag: Gauge<i64>,
+}
+
+impl InstrumentedContext {
+    /// Create a context using the global meter provider.
+    pub fn new() -> Self {
+        InstrumentedContext {
+            consumer_group: None,
+            consumer_lag: global::meter(INSTRUMENTATION_NAME)
+                .i64_gauge(CONSUMER_LAG_METRIC)
+                .with_description("Number of messages the consumer is behind the partition end.")
+                .with_unit("{message}")
+                .build(),
+        }
+    }
+
+    /// Set the `messaging.consumer.group.name` attribute of the lag metric.
+    pub fn with_consumer_group(mut self, consumer_group: impl Into<String>) -> Self {
+        self.consumer_group = Some(consumer_group.into());
+        self
+    }
+
+    fn record_lag(&self, statistics: &Statistics) {
+        for (topic_name, topic) in &statistics.topics {
+            for (partition_id, partition) in &topic.partitions {
+                // Partition -1 is the internal unassigned partition, and a negative lag
+                // means it isn't known yet.
+                if *partition_id < 0 || partition.consumer_lag < 0 {
+                    continue;
+                }
+                let mut attributes = vec![
+                    KeyValue::new(semconv::attribute::MESSAGING_SYSTEM, MESSAGING_SYSTEM),
+                    KeyValue::new(
+                        semconv::attribute::MESSAGING_DESTINATION_NAME,
+                        topic_name.clone(),
+                    ),
+                    KeyValue::new(
+                        semconv::attribute::MESSAGING_DESTINATION_PARTITION_ID,
+                        partition_id.to_string(),
+                    ),
+                ];
+                if let Some(consumer_group) = &self.consumer_group {
+                    attributes.push(KeyValue::new(
+                        semconv::attribute::MESSAGING_CONSUMER_GROUP_NAME,
+                        consumer_group.clone(),
+                    ));
+                }
+                self.consumer_lag
+                    .record(partition.consumer_lag, &attributes);
+            }
+        }
+    }
+}
+
+impl Default for InstrumentedContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for InstrumentedContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InstrumentedContext")
+            .field("consumer_group", &self.consumer_group)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ClientContext for InstrumentedContext {
+    fn stats(&self, statistics: Statistics) {
+        self.record_lag(&statistics);
+    }
+}
+
+impl ConsumerContext for InstrumentedContext {}
@@ -0,0 +1,233 @@
+//! # OpenTelemetry instrumentation for rust-rdkafka
+//!
+//! Tracing and metrics for [rust-rdkafka](https://github.com/fede1024/rust-rdkafka)
+//! producers and consumers, following the OpenTelemetry
+//! [messaging semantic conventions](https://opentelemetry.io/docs/specs/semconv/messaging/kafka/).
+//!
+//! - [`InstrumentedProducer`] wraps a [`FutureProducer`](rdkafka::producer::FutureProducer).
+//!   Every message gets a `PRODUCER` span whose context is injected into the
+//!   message headers with the global propagator.
+//! - [`InstrumentedConsumer`] wraps a [`StreamConsumer`](rdkafka::consumer::StreamConsumer).
+//!   Every received message gets a `CONSUMER` span, child of the context
+//!   extracted from the message headers, which lasts until the
+//!   [`ConsumedMessage`] is dropped.
+//! - [`InstrumentedContext`] records the consumer lag reported by the
+//!   librdkafka statistics, enabled with the `statistics.interval.ms` setting.
+//!
+//! Spans carry `messaging.system`, `messaging.destination.name`,
+//! `messaging.destination.partition.id`, `messaging.kafka.offset`,
+//! `messaging.kafka.message.key` and `messaging.consumer.group.name`.
+//!
+//! ## Metrics
+//!
+//! - `messaging.client.sent.messages`: number of messages sent.
+//! - `messaging.client.operation.duration`: duration of the sends.
+//! - `messaging.client.consumed.messages`: number of messages received.
+//! - `messaging.process.duration`: time spent processing received messages.
+//! - `messaging.kafka.consumer.lag`: consumer lag by topic and partition.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! # async fn example() -> rdkafka::error::KafkaResult<()> {
+//! use opentelemetry::trace::FutureExt;
+//! use opentelemetry_instrumentation_rdkafka::{InstrumentedConsumer, InstrumentedProducer};
+//! use rdkafka::consumer::{Consumer, StreamConsumer};
+//! use rdkafka::producer::{FutureProducer, FutureRecord};
+//! use rdkafka::ClientConfig;
+//! use std::time::Duration;
+//!
+//! let producer = InstrumentedProducer::new(
+//!     ClientConfig::new()
+//!         .set("bootstrap.servers", "localhost:9092")
+//!         .create::<FutureProducer>()?,
+//! );
+//! producer
+//!     .send(
+//!         FutureRecord::to("orders").key("order-1").payload("created"),
+//!         Duration::from_secs(5),
+//!     )
+//!     .await
+//!     .map_err(|(err, _)| err)?;
+//!
+//! let consumer = InstrumentedConsumer::new(
+//!     ClientConfig::new()
+//!         .set("bootstrap.servers", "localhost:9092")
+//!         .set("group.id", "billing")
+//!         .create::<StreamConsumer>()?,
+//! )
+//! .with_consumer_group("billing");
+//! consumer.inner().subscribe(&["orders"])?;
+//!
+//! let message = consumer.recv().await?;
+//! let cx = message.context().clone();
+//! async {
+//!     // process the message
+//! }
+//! .with_context(cx)
+//! .await;
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::HashMap;
+
+use opentelemetry::global::{self, BoxedTracer};
+use opentelemetry::metrics::{Counter, Histogram, Meter};
+use opentelemetry::Context;
+use opentelemetry_semantic_conventions as semconv;
+use rdkafka::error::KafkaError;
+use rdkafka::message::{Header, Headers, OwnedHeaders};
+
+mod consumer;
+mod context;
+mod producer;
+
+pub use consumer::{ConsumedMessage, InstrumentedConsumer};
+pub use context::InstrumentedContext;
+pub use producer::InstrumentedProducer;
+
+const INSTRUMENTATION_NAME: &str = "opentelemetry-instrumentation-rdkafka";
+const MESSAGING_SYSTEM: &str = "kafka";
+
+/// Tracer and instruments shared by the producer and consumer wrappers.
+pub(crate) struct Telemetry {
+    pub(crate) tracer: BoxedTracer,
+    pub(crate) sent_messages: Counter<u64>,
+    pub(crate) operation_duration: Histogram<f64>,
+    pub(crate) consumed_messages: Counter<u64>,
+    pub(crate) process_duration: Histogram<f64>,
+}
+
+impl Telemetry {
+    pub(crate) fn global() -> Self {
+        Self::new(
+            global::tracer(INSTRUMENTATION_NAME),
+            global::meter(INSTRUMENTATION_NAME),
+        )
+    }
+
+    pub(crate) fn new(tracer: BoxedTracer, meter: Meter) -> Self {
+        Telemetry {
+            tracer,
+            sent_messages: meter
+                .u64_counter(semconv::metric::MESSAGING_CLIENT_SENT_MESSAGES)
+                .with_description("Number of messages producer attempted to send to the broker.")
+                .with_unit("{message}")
+                .build(),
+            operation_duration: meter
+                .f64_histogram(semconv::metric::MESSAGING_CLIENT_OPERATION_DURATION)
+                .with_description(
+                    "Duration of messaging operation initiated by a producer or consumer client.",
+                )
+                .with_unit("s")
+                .build(),
+            consumed_messages: meter
+                .u64_counter(semconv::metric::MESSAGING_CLIENT_CONSUMED_MESSAGES)
+                .with_description("Number of messages that were delivered to the application.")
+                .with_unit("{message}")
+                .build(),
+            process_duration: meter
+                .f64_histogram(semconv::metric::MESSAGING_PROCESS_DURATION)
+                .with_description("Duration of processing operation.")
+                .with_unit("s")
+                .build(),
+        }
+    }
+}
+
+/// Inject `cx` into `headers` with the global propagator.
+pub(crate) fn inject_context(cx: &Context, headers: OwnedHeaders) -> OwnedHeaders {
+    let mut carrier = HashMap::new();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(cx, &mut carrier));
+    carrier.iter().fold(headers, |headers, (key, value)| {
+        headers.insert(Header {
+            key: key.as_str(),
+            value: Some(value.as_str()),
+        })
+    })
+}
+
+/// Extract a context from message headers with the global propagator.
+///
+/// Headers whose value isn't valid UTF-8 are ignored.
+pub(crate) fn extract_context<H: Headers + ?Sized>(headers: Option<&H>) -> Context {
+    let carrier: HashMap<String, String> = headers
+        .map(|headers| {
+            headers
+                .iter()
+                .filter_map(|header| {
+                    let value = std::str::from_utf8(header.value?).ok()?;
+                    Some((header.key.to_owned(), value.to_owned()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    global::get_text_map_propagator(|propagator| propagator.extract(&carrier))
+}
+
+/// A low cardinality `error.type` for a Kafka error.
+pub(crate) fn error_type(err: &KafkaError) -> String {
+    match err.rdkafka_error_code() {
+        Some(code) => format!("{code:?}"),
+        None => "_OTHER".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{
+        SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState,
+    };
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use rdkafka::message::{Message, OwnedMessage, Timestamp};
+
+    #[test]
+    fn test_inject_extract_context() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let span_context = SpanContext::new(
+            TraceId::from_hex("0af7651916cd43dd8448eb211c80319c").unwrap(),
+            SpanId::from_hex("b7ad6b7169203331").unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        );
+        let cx = Context::new().with_remote_span_context(span_context.clone());
+
+        let headers = inject_context(
+            &cx,
+            OwnedHeaders::new().insert(Header {
+                key: "content-type",
+                value: Some("application/json"),
+            }),
+        );
+        assert_eq!(headers.count(), 2);
+
+        let message = OwnedMessage::new(
+            None,
+            None,
+            "orders".to_string(),
+            Timestamp::NotAvailable,
+            0,
+            0,
+            Some(headers),
+        );
+        let extracted = extract_context(message.headers());
+        assert_eq!(
+            extracted.span().span_context().trace_id(),
+            span_context.trace_id()
+        );
+        assert_eq!(
+            extracted.span().span_context().span_id(),
+            span_context.span_id()
+        );
+        assert!(extracted.span().span_context().is_remote());
+    }
+
+    #[test]
+    fn test_extract_context_without_headers() {
+        let cx = extract_context::<OwnedHeaders>(None);
+        assert!(!cx.span().span_context().is_valid());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::{error_type, inject_context, Telemetry, MESSAGING_SYSTEM};
+use opentelemetry::trace::{SpanKind, Status, TraceContextExt, Tracer};
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_semantic_conventions as semconv;
+use rdkafka::client::{ClientContext, DefaultClientContext};
+use rdkafka::message::{OwnedHeaders, ToBytes};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+use std::time::Instant;
+
+const OPERATION_NAME: &str = "send";
+
+/// A [`FutureProducer`] creating a `PRODUCER` span for every message sent.
+///
+/// The span context is injected into the message headers with the global
+/// propagator, so consumers can continue the trace.
+pub struct InstrumentedProducer<C: ClientContext + 'static = DefaultClientContext> {
+    producer: FutureProducer<C>,
+    telemetry: Arc<Telemetry>,
+}
+
+impl<C: ClientContext + 'static> InstrumentedProducer<C> {
+    /// Wrap `producer`, using the global tracer and meter providers.
+    pub fn new(producer: FutureProducer<C>) -> Self {
+        InstrumentedProducer {
+            producer,
+            telemetry: Arc::new(Telemetry::global()),
+        }
+    }
+
+    /// The wrapped producer.
+    pub fn inner(&self) -> &FutureProducer<C> {
+        &self.producer
+    }
+
+    /// Send `record`, see [`FutureProducer::send`].
+    ///
+    /// The span ends when the broker acknowledges the message or the delivery
+    /// fails.
+    pub async fn send<K, P, T>(
+        &self,
+        mut record: FutureRecord<'_, K, P>,
+        queue_timeout: T,
+    ) -> rdkafka::producer::future_producer::OwnedDeliveryResult
+    where
+        K: ToBytes + ?Sized,
+        P: ToBytes + ?Sized,
+        T: Into<Timeout>,
+    {
+        let topic = record.topic.to_string();
+        let mut attributes = vec![
+            KeyValue::new(semconv::attribute::MESSAGING_SYSTEM, MESSAGING_SYSTEM),
+            KeyValue::new(semconv::attribute::MESSAGING_OPERATION_NAME, OPERATION_NAME),
+            KeyValue::new(semconv::attribute::MESSAGING_OPERATION_TYPE, OPERATION_NAME),
+            KeyValue::new(
+                semconv::attribute::MESSAGING_DESTINATION_NAME,
+                topic.clone(),
+            ),
+        ];
+        if let Some(partition) = record.partition {
+            attributes.push(KeyValue::new(
+                semconv::attribute::MESSAGING_DESTINATION_PARTITION_ID,
+                partition.to_string(),
+            ));
+        }
+        if let Some(key) = record
+            .key
+            .and_then(|key| std::str::from_utf8(key.to_bytes()).ok())
+        {
+            attributes.push(KeyValue::new(
+                semconv::attribute::MESSAGING_KAFKA_MESSAGE_KEY,
+                key.to_string(),
+            ));
+        }
+        if let Some(payload) = record.payload {
+            attributes.push(KeyValue::new(
+                semconv::attribute::MESSAGING_MESSAGE_BODY_SIZE,
+                payload.to_bytes().len() as i64,
+            ));
+        }
+
+        let tracer = &self.telemetry.tracer;
+        let span = tracer
+            .span_builder(format!("{OPERATION_NAME} {topic}"))
+            .with_kind(SpanKind::Producer)
+            .with_attributes(attributes)
+            .start(tracer);
+        let cx = Context::current_with_span(span);
+
+        let headers = record.headers.take().unwrap_or_else(OwnedHeaders::new);
+        record = record.headers(inject_context(&cx, headers));
+
+        let start = Instant::now();
+        let result = self.producer.send(record, queue_timeout).await;
+        let duration = start.elapsed().as_secs_f64();
+
+        let mut metric_attributes = vec![
+            KeyValue::new(semconv::attribute::MESSAGING_SYSTEM, MESSAGING_SYSTEM),
+            KeyValue::new(semconv::attribute::MESSAGING_OPERATION_NAME, OPERATION_NAME),
+            KeyValue::new(semconv::attribute::MESSAGING_DESTINATION_NAME, topic),
+        ];
+        let span = cx.span();
+        match &result {
+            Ok((partition, offset)) => {
+                span.set_attribute(KeyValue::new(
+                    semconv::attribute::MESSAGING_DESTINATION_PARTITION_ID,
+                    partition.to_string(),
+                ));
+                span.set_attribute(KeyValue::new(
+                    semconv::attribute::MESSAGING_KAFKA_OFFSET,
+                    *offset,
+                ));
+                metric_attributes.push(KeyValue::new(
+                    semconv::attribute::MESSAGING_DESTINATION_PARTITION_ID,
+                    partition.to_string(),
+                ));
+            }
+            Err((err, _)) => {
+                let error_type = error_type(err);
+                span.set_attribute(KeyValue::new(
+                    semconv::attribute::ERROR_TYPE,
+                    error_type.clone(),
+                ));
+                span.set_status(Status::error(err.to_string()));
+                metric_attributes.push(KeyValue::new(semconv::attribute::ERROR_TYPE, error_type));
+            }
+        }
+        span.end();
+
+        self.telemetry
+            .operation_duration
+            .record(duration, &metric_attributes);
+        self.telemetry.sent_messages.add(1, &metric_attributes);
+
+        result
+    }
+}
+
+impl<C: ClientContext + 'static> Debug for InstrumentedProducer<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InstrumentedProducer")
+            .finish_non_exhaustive()
+    }
+}
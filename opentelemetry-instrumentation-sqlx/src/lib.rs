@@ -0,0 +1,723 @@
+//! # OpenTelemetry instrumentation for sqlx
+//!
+//! [`InstrumentedPool`] wraps a [`sqlx::Pool`] and can be used anywhere an
+//! executor is expected. Every query executed through it creates a `CLIENT`
+//! span and records metrics following the OpenTelemetry
+//! [database semantic conventions](https://opentelemetry.io/docs/specs/semconv/database/).
+//!
+//! ## Spans
+//!
+//! Spans are named after the operation, e.g. `SELECT`, followed by the
+//! database name when one is configured, and carry the following attributes:
+//!
+//! - `db.system`: `postgresql`, `mysql` or `sqlite`.
+//! - `db.statement`: the query with string and numeric literals replaced by `?`,
+//!   so values embedded in the SQL text don't leak into traces. MySQL
+//!   double-quoted strings and backslash escapes are recognized.
+//! - `db.operation.name` and `db.namespace`.
+//! - `db.response.returned_rows`: the number of rows returned.
+//! - `db.query.slow`: `true` when the query took longer than the configured
+//!   slow-query threshold.
+//!
+//! ## Metrics
+//!
+//! - `db.client.operation.duration`: duration of the queries.
+//! - `db.client.connection.wait_time`: time spent acquiring a connection from
+//!   the pool.
+//! - `db.client.connection.count`: number of `used` and `idle` connections.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), sqlx::Error> {
+//! use opentelemetry_instrumentation_sqlx::InstrumentedPool;
+//! use sqlx::sqlite::SqlitePool;
+//! use std::time::Duration;
+//!
+//! let pool = SqlitePool::connect("sqlite://orders.db").await?;
+//! let pool = InstrumentedPool::builder(pool)
+//!     .with_namespace("orders")
+//!     .with_slow_query_threshold(Duration::from_millis(500))
+//!     .build();
+//!
+//! let rows = sqlx::query("SELECT id FROM orders WHERE status = 'open'")
+//!     .fetch_all(&pool)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_core::future::BoxFuture;
+use futures_core::stream::BoxStream;
+use futures_util::TryStreamExt;
+use opentelemetry::global::{self, BoxedSpan, BoxedTracer};
+use opentelemetry::metrics::{Histogram, Meter, ObservableUpDownCounter};
+use opentelemetry::trace::{Span, SpanKind, Status, Tracer};
+use opentelemetry::KeyValue;
+use opentelemetry_semantic_conventions as semconv;
+use sqlx::pool::PoolConnection;
+use sqlx::{Database, Describe, Either, Error, Execute, Executor, Pool};
+
+const INSTRUMENTATION_NAME: &str = "opentelemetry-instrumentation-sqlx";
+
+const DB_SYSTEM_LABEL: &str = "db.system";
+const DB_STATEMENT_LABEL: &str = "db.statement";
+const DB_QUERY_SLOW_LABEL: &str = "db.query.slow";
+const DB_NAMESPACE_LABEL: &str = semconv::attribute::DB_NAMESPACE;
+const DB_OPERATION_NAME_LABEL: &str = semconv::attribute::DB_OPERATION_NAME;
+const DB_RESPONSE_RETURNED_ROWS_LABEL: &str = semconv::attribute::DB_RESPONSE_RETURNED_ROWS;
+const DB_CLIENT_CONNECTION_POOL_NAME_LABEL: &str =
+    semconv::attribute::DB_CLIENT_CONNECTION_POOL_NAME;
+const DB_CLIENT_CONNECTION_STATE_LABEL: &str = semconv::attribute::DB_CLIENT_CONNECTION_STATE;
+const ERROR_TYPE_LABEL: &str = semconv::attribute::ERROR_TYPE;
+
+const DB_CLIENT_OPERATION_DURATION_METRIC: &str = semconv::metric::DB_CLIENT_OPERATION_DURATION;
+const DB_CLIENT_CONNECTION_WAIT_TIME_METRIC: &str = semconv::metric::DB_CLIENT_CONNECTION_WAIT_TIME;
+const DB_CLIENT_CONNECTION_COUNT_METRIC: &str = semconv::metric::DB_CLIENT_CONNECTION_COUNT;
+
+const OTEL_DEFAULT_DB_CLIENT_DURATION_BOUNDS: [f64; 9] =
+    [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0];
+
+const DEFAULT_POOL_NAME: &str = "default";
+
+/// State shared by all clones of an [`InstrumentedPool`].
+struct PoolState {
+    tracer: BoxedTracer,
+    db_system: String,
+    namespace: Option<String>,
+    slow_query_threshold: Option<Duration>,
+    pool_name_kv: KeyValue,
+    operation_duration: Histogram<f64>,
+    connection_wait_time: Histogram<f64>,
+    _connection_count: ObservableUpDownCounter<i64>,
+}
+
+impl PoolState {
+    /// Acquire a connection, recording the time spent waiting for it.
+    async fn acquire<DB: Database>(&self, pool: &Pool<DB>) -> Result<PoolConnection<DB>, Error> {
+        let start = Instant::now();
+        let conn = pool.acquire().await;
+        self.connection_wait_time.record(
+            start.elapsed().as_secs_f64(),
+            std::slice::from_ref(&self.pool_name_kv),
+        );
+        conn
+    }
+}
+
+/// Builder for [`InstrumentedPool`].
+pub struct InstrumentedPoolBuilder<DB: Database> {
+    pool: Pool<DB>,
+    pool_name: Option<String>,
+    namespace: Option<String>,
+    slow_query_threshold: Option<Duration>,
+    meter: Option<Meter>,
+    tracer: Option<BoxedTracer>,
+}
+
+impl<DB: Database> InstrumentedPoolBuilder<DB> {
+    /// Set the `db.client.connection.pool.name` of the connection metrics, `default` by default.
+    pub fn with_pool_name(mut self, pool_name: impl Into<String>) -> Self {
+        self.pool_name = Some(pool_name.into());
+        self
+    }
+
+    /// Set the name of the database, recorded as `db.namespace`.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Flag queries taking at least `threshold` with `db.query.slow`.
+    pub fn with_slow_query_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_query_threshold = Some(threshold);
+        self
+    }
+
+    /// Override the meter used for metrics collection, the global meter by default.
+    #[cfg(test)]
+    fn with_meter(mut self, meter: Meter) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+
+    /// Override the tracer, the global tracer by default.
+    #[cfg(test)]
+    fn with_tracer(mut self, tracer: BoxedTracer) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
+
+    /// Build the instrumented pool.
+    pub fn build(self) -> InstrumentedPool<DB> {
+        let meter = self
+            .meter
+            .unwrap_or_else(|| global::meter(INSTRUMENTATION_NAME));
+        let tracer = self
+            .tracer
+            .unwrap_or_else(|| global::tracer(INSTRUMENTATION_NAME));
+        let pool_name_kv = KeyValue::new(
+            DB_CLIENT_CONNECTION_POOL_NAME_LABEL,
+            self.pool_name
+                .unwrap_or_else(|| DEFAULT_POOL_NAME.to_string()),
+        );
+
+        let observed_pool = self.pool.clone();
+        let used_attributes = [
+            pool_name_kv.clone(),
+            KeyValue::new(DB_CLIENT_CONNECTION_STATE_LABEL, "used"),
+        ];
+        let idle_attributes = [
+            pool_name_kv.clone(),
+            KeyValue::new(DB_CLIENT_CONNECTION_STATE_LABEL, "idle"),
+        ];
+        let connection_count = meter
+            .i64_observable_up_down_counter(Cow::from(DB_CLIENT_CONNECTION_COUNT_METRIC))
+            .with_description("The number of connections that are currently in the pool.")
+            .with_unit("{connection}")
+            .with_callback(move |observer| {
+                let idle = observed_pool.num_idle() as i64;
+                let used = i64::from(observed_pool.size()) - idle;
+                observer.observe(used.max(0), &used_attributes);
+                observer.observe(idle, &idle_attributes);
+            })
+            .build();
+
+        let state = PoolState {
+            tracer,
+            db_system: db_system(DB::NAME),
+            namespace: self.namespace,
+            slow_query_threshold: self.slow_query_threshold,
+            pool_name_kv,
+            operation_duration: meter
+                .f64_histogram(Cow::from(DB_CLIENT_OPERATION_DURATION_METRIC))
+                .with_description("Duration of database client operations.")
+                .with_unit("s")
+                .with_boundaries(Vec::from(OTEL_DEFAULT_DB_CLIENT_DURATION_BOUNDS))
+                .build(),
+            connection_wait_time: meter
+                .f64_histogram(Cow::from(DB_CLIENT_CONNECTION_WAIT_TIME_METRIC))
+                .with_description("The time it took to obtain an open connection from the pool.")
+                .with_unit("s")
+                .with_boundaries(Vec::from(OTEL_DEFAULT_DB_CLIENT_DURATION_BOUNDS))
+                .build(),
+            _connection_count: connection_count,
+        };
+
+        InstrumentedPool {
+            pool: self.pool,
+            state: Arc::new(state),
+        }
+    }
+}
+
+/// A [`sqlx::Pool`] creating spans and recording metrics for the queries it executes.
+///
+/// `&InstrumentedPool` implements [`Executor`], so it can be passed to `fetch_*` and `execute`
+/// like `&Pool`. The connection count metric keeps a handle on the pool, call
+/// [`Pool::close`] through [`InstrumentedPool::inner`] to release its connections.
+pub struct InstrumentedPool<DB: Database> {
+    pool: Pool<DB>,
+    state: Arc<PoolState>,
+}
+
+impl<DB: Database> InstrumentedPool<DB> {
+    /// Create a builder instrumenting `pool`.
+    pub fn builder(pool: Pool<DB>) -> InstrumentedPoolBuilder<DB> {
+        InstrumentedPoolBuilder {
+            pool,
+            pool_name: None,
+            namespace: None,
+            slow_query_threshold: None,
+            meter: None,
+            tracer: None,
+        }
+    }
+
+    /// Instrument `pool` with the default configuration and the global providers.
+    pub fn new(pool: Pool<DB>) -> Self {
+        Self::builder(pool).build()
+    }
+
+    /// The wrapped pool.
+    pub fn inner(&self) -> &Pool<DB> {
+        &self.pool
+    }
+
+    /// Acquire a connection, recording the time spent waiting for it.
+    ///
+    /// Queries executed on the returned connection aren't instrumented.
+    pub async fn acquire(&self) -> Result<PoolConnection<DB>, Error> {
+        self.state.acquire(&self.pool).await
+    }
+}
+
+impl<DB: Database> Clone for InstrumentedPool<DB> {
+    fn clone(&self) -> Self {
+        InstrumentedPool {
+            pool: self.pool.clone(),
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<DB: Database> fmt::Debug for InstrumentedPool<DB> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InstrumentedPool")
+            .field("pool", &self.pool)
+            .field("db_system", &self.state.db_system)
+            .finish()
+    }
+}
+
+impl<'p, DB: Database> Executor<'p> for &'_ InstrumentedPool<DB>
+where
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    type Database = DB;
+
+    fn fetch_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxStream<'e, Result<Either<DB::QueryResult, DB::Row>, Error>>
+    where
+        E: 'q + Execute<'q, Self::Database>,
+    {
+        let pool = self.pool.clone();
+        let state = Arc::clone(&self.state);
+
+        Box::pin(async_stream::try_stream! {
+            // Started on the first poll, as the stream may be created long before.
+            let mut query_span = QuerySpan::start(&state, query.sql());
+            let mut conn = query_span.record(state.acquire(&pool).await)?;
+            let mut results = conn.fetch_many(query);
+            while let Some(result) = query_span.record(results.try_next().await)? {
+                if let Either::Right(_) = result {
+                    query_span.rows += 1;
+                }
+                yield result;
+            }
+        })
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxFuture<'e, Result<Option<DB::Row>, Error>>
+    where
+        E: 'q + Execute<'q, Self::Database>,
+    {
+        let pool = self.pool.clone();
+        let state = Arc::clone(&self.state);
+
+        Box::pin(async move {
+            let mut query_span = QuerySpan::start(&state, query.sql());
+            let mut conn = query_span.record(state.acquire(&pool).await)?;
+            let row = query_span.record(conn.fetch_optional(query).await)?;
+            query_span.rows = u64::from(row.is_some());
+            Ok(row)
+        })
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [DB::TypeInfo],
+    ) -> BoxFuture<'e, Result<DB::Statement<'q>, Error>> {
+        (&self.pool).prepare_with(sql, parameters)
+    }
+
+    fn describe<'e, 'q: 'e>(self, sql: &'q str) -> BoxFuture<'e, Result<Describe<DB>, Error>> {
+        (&self.pool).describe(sql)
+    }
+}
+
+/// The span of a query, ended and recorded when dropped.
+struct QuerySpan {
+    state: Arc<PoolState>,
+    span: BoxedSpan,
+    start: Instant,
+    operation: Option<String>,
+    error_type: Option<Cow<'static, str>>,
+    rows: u64,
+}
+
+impl QuerySpan {
+    fn start(state: &Arc<PoolState>, sql: &str) -> Self {
+        let statement = sanitize_statement(sql, state.db_system == "mysql");
+        let operation = operation_name(&statement);
+
+        let span_name = match (&operation, &state.namespace) {
+            (Some(operation), Some(namespace)) => format!("{operation} {namespace}"),
+            (Some(operation), None) => operation.clone(),
+            (None, Some(namespace)) => namespace.clone(),
+            (None, None) => state.db_system.clone(),
+        };
+
+        let mut attributes = vec![
+            KeyValue::new(DB_SYSTEM_LABEL, state.db_system.clone()),
+            KeyValue::new(DB_STATEMENT_LABEL, statement),
+        ];
+        if let Some(operation) = &operation {
+            attributes.push(KeyValue::new(DB_OPERATION_NAME_LABEL, operation.clone()));
+        }
+        if let Some(namespace) = &state.namespace {
+            attributes.push(KeyValue::new(DB_NAMESPACE_LABEL, namespace.clone()));
+        }
+
+        let span = state
+            .tracer
+            .span_builder(span_name)
+            .with_kind(SpanKind::Client)
+            .with_attributes(attributes)
+            .start(&state.tracer);
+
+        QuerySpan {
+            state: Arc::clone(state),
+            span,
+            start: Instant::now(),
+            operation,
+            error_type: None,
+            rows: 0,
+        }
+    }
+
+    /// Record the error of a failed step of the query.
+    fn record<T>(&mut self, result: Result<T, Error>) -> Result<T, Error> {
+        if let Err(err) = &result {
+            self.error_type = Some(error_type(err));
+            self.span.record_error(err);
+            self.span.set_status(Status::error(err.to_string()));
+        }
+        result
+    }
+}
+
+impl Drop for QuerySpan {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+
+        self.span.set_attribute(KeyValue::new(
+            DB_RESPONSE_RETURNED_ROWS_LABEL,
+            self.rows as i64,
+        ));
+        if self
+            .state
+            .slow_query_threshold
+            .is_some_and(|threshold| elapsed >= threshold)
+        {
+            self.span
+                .set_attribute(KeyValue::new(DB_QUERY_SLOW_LABEL, true));
+        }
+        self.span.end();
+
+        let mut attributes = vec![KeyValue::new(DB_SYSTEM_LABEL, self.state.db_system.clone())];
+        if let Some(namespace) = &self.state.namespace {
+            attributes.push(KeyValue::new(DB_NAMESPACE_LABEL, namespace.clone()));
+        }
+        if let Some(operation) = self.operation.take() {
+            attributes.push(KeyValue::new(DB_OPERATION_NAME_LABEL, operation));
+        }
+        if let Some(error_type) = self.error_type.take() {
+            attributes.push(KeyValue::new(ERROR_TYPE_LABEL, error_type));
+        }
+        self.state
+            .operation_duration
+            .record(elapsed.as_secs_f64(), &attributes);
+    }
+}
+
+/// Map [`Database::NAME`] to a `db.system` value.
+fn db_system(name: &str) -> String {
+    match name {
+        "PostgreSQL" => "postgresql".to_string(),
+        "MySQL" => "mysql".to_string(),
+        "SQLite" => "sqlite".to_string(),
+        other => other.to_lowercase(),
+    }
+}
+
+/// Replace string and numeric literals in `sql` with `?`.
+///
+/// Quoted identifiers and placeholders such as `$1` or `?1` are kept. On `mysql`, double quotes
+/// delimit strings rather than identifiers and a backslash escapes the next character, as it
+/// does in PostgreSQL `E'...'` strings.
+fn sanitize_statement(sql: &str, mysql: bool) -> String {
+    let mut sanitized = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    let mut previous = None;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' if c == '\'' || mysql => {
+                let escape_string = c == '\''
+                    && sanitized
+                        .strip_suffix(|p: char| p == 'E' || p == 'e')
+                        .is_some_and(|rest| {
+                            !rest.ends_with(|p: char| p.is_alphanumeric() || p == '_')
+                        });
+                if escape_string {
+                    sanitized.pop();
+                }
+                let backslash_escapes = mysql || escape_string;
+                // A doubled quote escapes a quote inside the literal.
+                loop {
+                    match chars.next() {
+                        Some('\\') if backslash_escapes => {
+                            chars.next();
+                        }
+                        Some(q) if q == c && chars.peek() == Some(&c) => {
+                            chars.next();
+                        }
+                        Some(q) if q == c => break,
+                        None => break,
+                        Some(_) => {}
+                    }
+                }
+                sanitized.push('?');
+                previous = Some('?');
+            }
+            c if c.is_ascii_digit()
+                && !previous
+                    .is_some_and(|p: char| p.is_alphanumeric() || matches!(p, '_' | '$' | '?')) =>
+            {
+                while chars
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '.')
+                {
+                    chars.next();
+                }
+                sanitized.push('?');
+                previous = Some('?');
+            }
+            c => {
+                sanitized.push(c);
+                previous = Some(c);
+            }
+        }
+    }
+    sanitized
+}
+
+/// The leading keyword of a statement, e.g. `SELECT`.
+fn operation_name(statement: &str) -> Option<String> {
+    let keyword: String = statement
+        .trim_start_matches(|c: char| c.is_whitespace() || c == '(')
+        .chars()
+        .take_while(char::is_ascii_alphabetic)
+        .collect();
+    (!keyword.is_empty()).then(|| keyword.to_ascii_uppercase())
+}
+
+/// A low cardinality `error.type` for a sqlx error.
+fn error_type(err: &Error) -> Cow<'static, str> {
+    match err {
+        Error::Database(db_err) => db_err
+            .code()
+            .map(|code| Cow::Owned(code.into_owned()))
+            .unwrap_or(Cow::Borrowed("database")),
+        Error::RowNotFound => Cow::Borrowed("row_not_found"),
+        Error::PoolTimedOut => Cow::Borrowed("pool_timed_out"),
+        Error::PoolClosed => Cow::Borrowed("pool_closed"),
+        Error::Io(_) => Cow::Borrowed("io"),
+        Error::Tls(_) => Cow::Borrowed("tls"),
+        Error::Protocol(_) => Cow::Borrowed("protocol"),
+        Error::ColumnNotFound(_) | Error::ColumnIndexOutOfBounds { .. } => Cow::Borrowed("column"),
+        Error::ColumnDecode { .. } | Error::Decode(_) => Cow::Borrowed("decode"),
+        _ => Cow::Borrowed("_OTHER"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry::Value;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use opentelemetry_sdk::trace::{
+        InMemorySpanExporter, InMemorySpanExporterBuilder, SdkTracerProvider,
+    };
+    use sqlx::sqlite::SqlitePoolOptions;
+    use sqlx::Sqlite;
+
+    async fn instrumented_pool(
+        configure: impl FnOnce(InstrumentedPoolBuilder<Sqlite>) -> InstrumentedPoolBuilder<Sqlite>,
+    ) -> (
+        InstrumentedPool<Sqlite>,
+        SdkTracerProvider,
+        InMemorySpanExporter,
+    ) {
+        let exporter = InMemorySpanExporterBuilder::new().build();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let tracer = BoxedTracer::new(Box::new(provider.tracer("test_tracer")));
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let builder = InstrumentedPool::builder(pool)
+            .with_tracer(tracer)
+            .with_meter(SdkMeterProvider::builder().build().meter("test"));
+        (configure(builder).build(), provider, exporter)
+    }
+
+    fn attribute(span: &opentelemetry_sdk::trace::SpanData, key: &str) -> Option<Value> {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| kv.value.clone())
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_query_span() {
+        let (pool, provider, exporter) =
+            instrumented_pool(|builder| builder.with_namespace("main")).await;
+
+        let rows = sqlx::query("SELECT 1 AS id UNION ALL SELECT 2 WHERE 'a' = 'a'")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+
+        provider.force_flush().unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 1);
+        let span = &spans[0];
+        assert_eq!(span.name, "SELECT main");
+        assert_eq!(span.span_kind, SpanKind::Client);
+        assert_eq!(
+            attribute(span, DB_SYSTEM_LABEL),
+            Some(Value::from("sqlite"))
+        );
+        assert_eq!(
+            attribute(span, DB_STATEMENT_LABEL),
+            Some(Value::from("SELECT ? AS id UNION ALL SELECT ? WHERE ? = ?"))
+        );
+        assert_eq!(
+            attribute(span, DB_NAMESPACE_LABEL),
+            Some(Value::from("main"))
+        );
+        assert_eq!(
+            attribute(span, DB_RESPONSE_RETURNED_ROWS_LABEL),
+            Some(Value::from(2))
+        );
+        assert_eq!(attribute(span, DB_QUERY_SLOW_LABEL), None);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_query_error() {
+        let (pool, provider, exporter) = instrumented_pool(|builder| builder).await;
+
+        let result = sqlx::query("SELECT * FROM missing")
+            .fetch_optional(&pool)
+            .await;
+        assert!(result.is_err());
+
+        provider.force_flush().unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].name, "SELECT");
+        assert!(matches!(spans[0].status, Status::Error { .. }));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_slow_query() {
+        let (pool, provider, exporter) =
+            instrumented_pool(|builder| builder.with_slow_query_threshold(Duration::ZERO)).await;
+
+        sqlx::query("SELECT 1").execute(&pool).await.unwrap();
+
+        provider.force_flush().unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(
+            attribute(&spans[0], DB_QUERY_SLOW_LABEL),
+            Some(Value::from(true))
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_span_starts_on_first_poll() {
+        let (pool, provider, exporter) = instrumented_pool(|builder| builder).await;
+
+        drop(sqlx::query("SELECT 1").fetch_optional(&pool));
+        drop(sqlx::query("SELECT 1").fetch_all(&pool));
+
+        provider.force_flush().unwrap();
+        assert!(exporter.get_finished_spans().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sanitize_statement() {
+        assert_eq!(
+            sanitize_statement(
+                "SELECT * FROM users WHERE name = 'O''Brien' AND age > 42",
+                false
+            ),
+            "SELECT * FROM users WHERE name = ? AND age > ?"
+        );
+        assert_eq!(
+            sanitize_statement(
+                "INSERT INTO t2 (a, \"b1\") VALUES ($1, ?2, 3.5, 0x1F)",
+                false
+            ),
+            "INSERT INTO t2 (a, \"b1\") VALUES ($1, ?2, ?, ?)"
+        );
+        assert_eq!(
+            sanitize_statement("SELECT 'unterminated", false),
+            "SELECT ?"
+        );
+        // Backslashes don't escape standard strings.
+        assert_eq!(
+            sanitize_statement(r"SELECT * FROM t WHERE path = 'C:\' AND id = 'x'", false),
+            "SELECT * FROM t WHERE path = ? AND id = ?"
+        );
+        assert_eq!(
+            sanitize_statement(r"SELECT E'it\'s', type FROM t", false),
+            "SELECT ?, type FROM t"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_statement_mysql() {
+        assert_eq!(
+            sanitize_statement(
+                r#"SELECT * FROM t WHERE a = 'it\'s' AND b = "say \"hi\"" AND c = "x""y""#,
+                true
+            ),
+            "SELECT * FROM t WHERE a = ? AND b = ? AND c = ?"
+        );
+        assert_eq!(
+            sanitize_statement(r"SELECT `name` FROM t WHERE a = 'C:\\' AND b = 1", true),
+            "SELECT `name` FROM t WHERE a = ? AND b = ?"
+        );
+    }
+
+    #[test]
+    fn test_operation_name() {
+        assert_eq!(
+            operation_name("  select * from t").as_deref(),
+            Some("SELECT")
+        );
+        assert_eq!(
+            operation_name("(SELECT 1) UNION (SELECT 2)").as_deref(),
+            Some("SELECT")
+        );
+        assert_eq!(operation_name("-- comment"), None);
+    }
+
+    #[test]
+    fn test_db_system() {
+        assert_eq!(db_system("PostgreSQL"), "postgresql");
+        assert_eq!(db_system("MySQL"), "mysql");
+        assert_eq!(db_system("SQLite"), "sqlite");
+        assert_eq!(db_system("MSSQL"), "mssql");
+    }
+}
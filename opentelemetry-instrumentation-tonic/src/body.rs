@@ -0,0 +1,177 @@
+//! Response body ending the span of the call with the response stream.
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::Instant;
+
+use http::{HeaderMap, StatusCode};
+use http_body::{Body, Frame, SizeHint};
+use opentelemetry::trace::{Status, TraceContextExt};
+use opentelemetry::{Context as OtelContext, KeyValue};
+
+use crate::status;
+use crate::{LayerState, RPC_GRPC_STATUS_CODE};
+
+pin_project_lite::pin_project! {
+    /// Response body of a [`GrpcService`], ending the span of the call when the response
+    /// trailers are received, the body ends or it is dropped.
+    ///
+    /// [`GrpcService`]: crate::GrpcService
+    pub struct GrpcBody<B> {
+        #[pin]
+        inner: B,
+        call: Option<CallEnd>,
+    }
+}
+
+impl<B> GrpcBody<B> {
+    pub(crate) fn new(inner: B, call: CallEnd) -> Self {
+        GrpcBody {
+            inner,
+            call: Some(call),
+        }
+    }
+}
+
+impl<B: Body> Body for GrpcBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        match &frame {
+            Some(Ok(frame)) => {
+                if let (Some(trailers), Some(call)) = (frame.trailers_ref(), this.call.as_mut()) {
+                    call.observe_trailers(trailers);
+                }
+            }
+            Some(Err(_)) => {
+                if let Some(call) = this.call.take() {
+                    call.fail("response stream failed".to_string());
+                }
+            }
+            None => {
+                if let Some(call) = this.call.take() {
+                    call.finish(status::UNKNOWN);
+                }
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl<B> std::fmt::Debug for GrpcBody<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GrpcBody").finish_non_exhaustive()
+    }
+}
+
+/// The end of a call, recording the status on the span and the duration metric.
+///
+/// Dropped before a status is received, e.g. when the caller cancels a stream, the call ends
+/// as `CANCELLED`.
+pub(crate) struct CallEnd {
+    cx: OtelContext,
+    state: Arc<LayerState>,
+    start: Instant,
+    metric_attributes: Vec<KeyValue>,
+    status: Option<(i32, Option<String>)>,
+    error: Option<String>,
+    finished: bool,
+}
+
+impl CallEnd {
+    pub(crate) fn new(
+        cx: OtelContext,
+        state: Arc<LayerState>,
+        metric_attributes: Vec<KeyValue>,
+    ) -> Self {
+        CallEnd {
+            cx,
+            state,
+            start: Instant::now(),
+            metric_attributes,
+            status: None,
+            error: None,
+            finished: false,
+        }
+    }
+
+    /// Read the status of a trailers-only response, or of a response rejected before reaching
+    /// a gRPC server.
+    pub(crate) fn observe_response(&mut self, http_status: StatusCode, headers: &HeaderMap) {
+        if http_status == StatusCode::OK {
+            self.observe_trailers(headers);
+        } else {
+            self.status = Some((
+                status::from_http_status(http_status),
+                Some(format!("HTTP {}", http_status.as_u16())),
+            ));
+        }
+    }
+
+    pub(crate) fn observe_trailers(&mut self, trailers: &HeaderMap) {
+        if let Some(status) = status::from_headers(trailers) {
+            self.status = Some(status);
+        }
+    }
+
+    /// End the call without a status, e.g. on a transport error.
+    pub(crate) fn fail(mut self, description: String) {
+        self.error = Some(description);
+        self.finish(status::UNKNOWN);
+    }
+
+    /// End the call, with the `missing` status code if none was received.
+    pub(crate) fn finish(mut self, missing: i32) {
+        self.end(missing);
+    }
+
+    fn end(&mut self, missing: i32) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+
+        let span = self.cx.span();
+        let mut metric_attributes = std::mem::take(&mut self.metric_attributes);
+        match self.error.take() {
+            Some(description) => span.set_status(Status::error(description)),
+            None => {
+                let (code, message) = self.status.take().unwrap_or((missing, None));
+                span.set_attribute(KeyValue::new(RPC_GRPC_STATUS_CODE, i64::from(code)));
+                metric_attributes.push(KeyValue::new(RPC_GRPC_STATUS_CODE, i64::from(code)));
+                if status::is_error(code, &self.state.kind) {
+                    span.set_status(Status::error(
+                        message.unwrap_or_else(|| status::name(code).to_string()),
+                    ));
+                }
+            }
+        }
+        if let Some(duration) = &self.state.duration {
+            duration.record(
+                self.start.elapsed().as_secs_f64() * 1000.0,
+                &metric_attributes,
+            );
+        }
+        span.end();
+    }
+}
+
+impl Drop for CallEnd {
+    fn drop(&mut self) {
+        self.end(status::CANCELLED);
+    }
+}
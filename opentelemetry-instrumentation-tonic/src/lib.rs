@@ -0,0 +1,569 @@
+//! # OpenTelemetry middleware for tonic
+//!
+//! [`GrpcLayer`] is a [`tower_layer::Layer`] for [tonic](https://github.com/hyperium/tonic)
+//! servers and channels creating a `SERVER` or `CLIENT` span for every call, following the
+//! OpenTelemetry [gRPC semantic conventions](https://opentelemetry.io/docs/specs/semconv/rpc/grpc/).
+//!
+//! - The span is named `{service}/{method}` and carries `rpc.system`, `rpc.service`,
+//!   `rpc.method` and `rpc.grpc.status_code`, client spans also `server.address` and
+//!   `server.port`.
+//! - The span ends with the response stream, when the status is received in the response
+//!   headers or trailers. Its status is set to an error for every status but `OK` on clients,
+//!   and for the statuses of a server failure on servers: `UNKNOWN`, `DEADLINE_EXCEEDED`,
+//!   `UNIMPLEMENTED`, `INTERNAL`, `UNAVAILABLE` and `DATA_LOSS`. A stream dropped before its
+//!   status ends as `CANCELLED`.
+//! - The trace context is extracted from and injected into the request metadata with the
+//!   global propagator. With the `grpc-trace-bin` feature, the binary `grpc-trace-bin`
+//!   metadata of the OpenCensus gRPC integrations is injected too, and extracted when the
+//!   global propagator finds no parent.
+//! - With [`GrpcLayerBuilder::with_method_metrics`], the duration of the calls is recorded in
+//!   the `rpc.server.duration` or `rpc.client.duration` histogram, per method and status.
+//!
+//! ## Example
+//!
+//! ```ignore
+//! use opentelemetry_instrumentation_tonic::{GrpcLayer, GrpcLayerBuilder};
+//!
+//! // Server
+//! tonic::transport::Server::builder()
+//!     .layer(GrpcLayer::server())
+//!     .add_service(GreeterServer::new(greeter))
+//!     .serve(addr)
+//!     .await?;
+//!
+//! // Client
+//! let channel = Endpoint::from_static("http://[::1]:50051").connect().await?;
+//! let channel = tower::ServiceBuilder::new()
+//!     .layer(GrpcLayerBuilder::client().with_method_metrics(true).build())
+//!     .service(channel);
+//! let mut client = GreeterClient::new(channel);
+//! ```
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use opentelemetry::global::{self, BoxedTracer};
+use opentelemetry::metrics::{Histogram, Meter};
+use opentelemetry::trace::{FutureExt as OtelFutureExt, SpanKind, TraceContextExt, Tracer};
+use opentelemetry::{Context as OtelContext, KeyValue};
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
+use tower_layer::Layer;
+use tower_service::Service;
+
+mod body;
+mod status;
+#[cfg(feature = "grpc-trace-bin")]
+mod trace_bin;
+
+use body::CallEnd;
+pub use body::GrpcBody;
+
+const INSTRUMENTATION_NAME: &str = "opentelemetry-instrumentation-tonic";
+
+const RPC_SYSTEM: &str = "rpc.system";
+const RPC_SERVICE: &str = "rpc.service";
+const RPC_METHOD: &str = "rpc.method";
+pub(crate) const RPC_GRPC_STATUS_CODE: &str = "rpc.grpc.status_code";
+const SERVER_ADDRESS: &str = "server.address";
+const SERVER_PORT: &str = "server.port";
+
+const RPC_SERVER_DURATION_METRIC: &str = "rpc.server.duration";
+const RPC_CLIENT_DURATION_METRIC: &str = "rpc.client.duration";
+const RPC_DURATION_UNIT: &str = "ms";
+
+/// Builder for [`GrpcLayer`].
+pub struct GrpcLayerBuilder {
+    kind: SpanKind,
+    method_metrics: bool,
+    #[cfg(feature = "grpc-trace-bin")]
+    grpc_trace_bin: bool,
+    tracer: Option<BoxedTracer>,
+    meter: Option<Meter>,
+}
+
+impl GrpcLayerBuilder {
+    /// Configure a layer for a tonic server.
+    pub fn server() -> Self {
+        Self::new(SpanKind::Server)
+    }
+
+    /// Configure a layer for a tonic channel.
+    pub fn client() -> Self {
+        Self::new(SpanKind::Client)
+    }
+
+    fn new(kind: SpanKind) -> Self {
+        GrpcLayerBuilder {
+            kind,
+            method_metrics: false,
+            #[cfg(feature = "grpc-trace-bin")]
+            grpc_trace_bin: true,
+            tracer: None,
+            meter: None,
+        }
+    }
+
+    /// Record the duration of the calls per method and status with the global meter provider.
+    ///
+    /// Disabled by default, as the number of methods of a service is not bounded by this
+    /// layer.
+    pub fn with_method_metrics(mut self, enabled: bool) -> Self {
+        self.method_metrics = enabled;
+        self
+    }
+
+    /// Propagate the span context in the `grpc-trace-bin` metadata, in addition to the global
+    /// propagator. Enabled by default.
+    #[cfg(feature = "grpc-trace-bin")]
+    pub fn with_grpc_trace_bin(mut self, enabled: bool) -> Self {
+        self.grpc_trace_bin = enabled;
+        self
+    }
+
+    /// Override the tracer, the global tracer by default.
+    #[cfg(test)]
+    fn with_tracer(mut self, tracer: BoxedTracer) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
+
+    /// Override the meter, the global meter by default.
+    #[cfg(test)]
+    fn with_meter(mut self, meter: Meter) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+
+    /// Build the layer.
+    pub fn build(self) -> GrpcLayer {
+        let duration = self.method_metrics.then(|| {
+            let (name, description) = match self.kind {
+                SpanKind::Server => (RPC_SERVER_DURATION_METRIC, "Duration of inbound RPCs."),
+                _ => (RPC_CLIENT_DURATION_METRIC, "Duration of outbound RPCs."),
+            };
+            self.meter
+                .unwrap_or_else(|| global::meter(INSTRUMENTATION_NAME))
+                .f64_histogram(name)
+                .with_description(description)
+                .with_unit(RPC_DURATION_UNIT)
+                .build()
+        });
+        GrpcLayer {
+            state: Arc::new(LayerState {
+                kind: self.kind,
+                tracer: self
+                    .tracer
+                    .unwrap_or_else(|| global::tracer(INSTRUMENTATION_NAME)),
+                duration,
+                #[cfg(feature = "grpc-trace-bin")]
+                grpc_trace_bin: self.grpc_trace_bin,
+            }),
+        }
+    }
+}
+
+/// [`Layer`] creating spans for the gRPC calls of a tonic server or channel.
+#[derive(Clone)]
+pub struct GrpcLayer {
+    state: Arc<LayerState>,
+}
+
+impl GrpcLayer {
+    /// Create a layer for a tonic server with the default configuration.
+    pub fn server() -> Self {
+        GrpcLayerBuilder::server().build()
+    }
+
+    /// Create a layer for a tonic channel with the default configuration.
+    pub fn client() -> Self {
+        GrpcLayerBuilder::client().build()
+    }
+}
+
+impl fmt::Debug for GrpcLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GrpcLayer")
+            .field("kind", &self.state.kind)
+            .field("method_metrics", &self.state.duration.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for GrpcLayer {
+    type Service = GrpcService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcService {
+            inner,
+            state: self.state.clone(),
+        }
+    }
+}
+
+pub(crate) struct LayerState {
+    pub(crate) kind: SpanKind,
+    tracer: BoxedTracer,
+    pub(crate) duration: Option<Histogram<f64>>,
+    #[cfg(feature = "grpc-trace-bin")]
+    grpc_trace_bin: bool,
+}
+
+/// [`Service`] created by [`GrpcLayer`].
+#[derive(Clone)]
+pub struct GrpcService<S> {
+    inner: S,
+    state: Arc<LayerState>,
+}
+
+impl<S: fmt::Debug> fmt::Debug for GrpcService<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GrpcService")
+            .field("inner", &self.inner)
+            .field("kind", &self.state.kind)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> GrpcService<S> {
+    fn parent_context(&self, headers: &http::HeaderMap) -> OtelContext {
+        if self.state.kind != SpanKind::Server {
+            return OtelContext::current();
+        }
+        let parent_cx = global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(headers))
+        });
+        #[cfg(feature = "grpc-trace-bin")]
+        if self.state.grpc_trace_bin && !parent_cx.span().span_context().is_valid() {
+            if let Some(span_context) = trace_bin::extract(headers) {
+                return parent_cx.with_remote_span_context(span_context);
+            }
+        }
+        parent_cx
+    }
+
+    fn inject(&self, cx: &OtelContext, headers: &mut http::HeaderMap) {
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(cx, &mut HeaderInjector(headers))
+        });
+        #[cfg(feature = "grpc-trace-bin")]
+        if self.state.grpc_trace_bin {
+            trace_bin::inject(cx, headers);
+        }
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for GrpcService<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: fmt::Display,
+    ResBody: Send + 'static,
+{
+    type Response = http::Response<GrpcBody<ResBody>>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<ReqBody>) -> Self::Future {
+        let path = req.uri().path();
+        let (span_name, service, method) = match path
+            .strip_prefix('/')
+            .and_then(|name| Some((name, name.rsplit_once('/')?)))
+        {
+            Some((name, (service, method))) => (
+                name.to_string(),
+                Some(service.to_string()),
+                method.to_string(),
+            ),
+            None => (path.to_string(), None, path.to_string()),
+        };
+
+        let mut metric_attributes = vec![KeyValue::new(RPC_SYSTEM, "grpc")];
+        if let Some(service) = service {
+            metric_attributes.push(KeyValue::new(RPC_SERVICE, service));
+        }
+        metric_attributes.push(KeyValue::new(RPC_METHOD, method));
+
+        let mut span_attributes = metric_attributes.clone();
+        if self.state.kind == SpanKind::Client {
+            if let Some(host) = req.uri().host() {
+                span_attributes.push(KeyValue::new(SERVER_ADDRESS, host.to_string()));
+            }
+            if let Some(port) = req.uri().port_u16() {
+                span_attributes.push(KeyValue::new(SERVER_PORT, i64::from(port)));
+            }
+        }
+
+        let parent_cx = self.parent_context(req.headers());
+        let tracer = &self.state.tracer;
+        let span = tracer
+            .span_builder(span_name)
+            .with_kind(self.state.kind.clone())
+            .with_attributes(span_attributes)
+            .start_with_context(tracer, &parent_cx);
+        let cx = parent_cx.with_span(span);
+        if self.state.kind == SpanKind::Client {
+            self.inject(&cx, req.headers_mut());
+        }
+
+        let mut call = CallEnd::new(cx.clone(), self.state.clone(), metric_attributes);
+        let future = self.inner.call(req);
+        Box::pin(
+            async move {
+                match future.await {
+                    Ok(response) => {
+                        let (parts, body) = response.into_parts();
+                        call.observe_response(parts.status, &parts.headers);
+                        Ok(http::Response::from_parts(parts, GrpcBody::new(body, call)))
+                    }
+                    Err(err) => {
+                        call.fail(err.to_string());
+                        Err(err)
+                    }
+                }
+            }
+            .with_context(cx),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderMap;
+    use http_body::{Body, Frame};
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry::trace::{Status, TracerProvider};
+    use opentelemetry::Value;
+    use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
+    use opentelemetry_sdk::metrics::{InMemoryMetricExporter, PeriodicReader, SdkMeterProvider};
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    /// Response body holding only the trailers.
+    struct TrailersBody(Option<HeaderMap>);
+
+    impl Body for TrailersBody {
+        type Data = &'static [u8];
+        type Error = Infallible;
+
+        fn poll_frame(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+            Poll::Ready(
+                self.get_mut()
+                    .0
+                    .take()
+                    .map(|trailers| Ok(Frame::trailers(trailers))),
+            )
+        }
+    }
+
+    fn with_test_tracer(
+        builder: GrpcLayerBuilder,
+    ) -> (GrpcLayerBuilder, SdkTracerProvider, InMemorySpanExporter) {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let tracer = BoxedTracer::new(Box::new(provider.tracer("test_tracer")));
+        (builder.with_tracer(tracer), provider, exporter)
+    }
+
+    async fn drain<B: Body + Unpin>(mut body: B) {
+        while std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx))
+            .await
+            .is_some()
+        {}
+    }
+
+    fn attribute(span: &SpanData, key: &str) -> Option<Value> {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| kv.value.clone())
+    }
+
+    fn request(uri: &str) -> http::Request<()> {
+        http::Request::builder()
+            .method("POST")
+            .uri(uri)
+            .body(())
+            .unwrap()
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_server_trailers_only() {
+        let (builder, provider, exporter) = with_test_tracer(GrpcLayerBuilder::server());
+        let service = builder.build().layer(tower::service_fn(|_| async {
+            Ok::<_, Infallible>(
+                http::Response::builder()
+                    .header("grpc-status", "5")
+                    .body(TrailersBody(None))
+                    .unwrap(),
+            )
+        }));
+
+        let response = service
+            .oneshot(request("/helloworld.Greeter/SayHello"))
+            .await
+            .unwrap();
+        assert!(exporter.get_finished_spans().unwrap().is_empty());
+        drain(response.into_body()).await;
+
+        provider.force_flush().unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 1);
+        let span = &spans[0];
+        assert_eq!(span.name, "helloworld.Greeter/SayHello");
+        assert_eq!(span.span_kind, SpanKind::Server);
+        assert_eq!(attribute(span, RPC_SYSTEM), Some(Value::from("grpc")));
+        assert_eq!(
+            attribute(span, RPC_SERVICE),
+            Some(Value::from("helloworld.Greeter"))
+        );
+        assert_eq!(attribute(span, RPC_METHOD), Some(Value::from("SayHello")));
+        assert_eq!(attribute(span, RPC_GRPC_STATUS_CODE), Some(Value::from(5)));
+        // NOT_FOUND results from the request.
+        assert_eq!(span.status, Status::Unset);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_client_trailers() {
+        let (builder, provider, exporter) = with_test_tracer(GrpcLayerBuilder::client());
+        let service = builder.build().layer(tower::service_fn(|_| async {
+            let mut trailers = HeaderMap::new();
+            trailers.insert("grpc-status", "13".parse().unwrap());
+            trailers.insert("grpc-message", "database%20unavailable".parse().unwrap());
+            Ok::<_, Infallible>(http::Response::new(TrailersBody(Some(trailers))))
+        }));
+
+        let response = service
+            .oneshot(request(
+                "http://localhost:50051/helloworld.Greeter/SayHello",
+            ))
+            .await
+            .unwrap();
+        drain(response.into_body()).await;
+
+        provider.force_flush().unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+        let span = &spans[0];
+        assert_eq!(span.span_kind, SpanKind::Client);
+        assert_eq!(
+            attribute(span, SERVER_ADDRESS),
+            Some(Value::from("localhost"))
+        );
+        assert_eq!(attribute(span, SERVER_PORT), Some(Value::from(50051)));
+        assert_eq!(attribute(span, RPC_GRPC_STATUS_CODE), Some(Value::from(13)));
+        assert_eq!(span.status, Status::error("database unavailable"));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_cancelled_stream() {
+        let (builder, provider, exporter) = with_test_tracer(GrpcLayerBuilder::client());
+        let service = builder.build().layer(tower::service_fn(|_| async {
+            Ok::<_, Infallible>(http::Response::new(TrailersBody(None)))
+        }));
+
+        let response = service
+            .oneshot(request("/helloworld.Greeter/SayHello"))
+            .await
+            .unwrap();
+        drop(response);
+
+        provider.force_flush().unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(
+            attribute(&spans[0], RPC_GRPC_STATUS_CODE),
+            Some(Value::from(1))
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_method_metrics() {
+        let exporter = InMemoryMetricExporter::default();
+        let meter_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter.clone()).build())
+            .build();
+        let (builder, _provider, _spans) = with_test_tracer(
+            GrpcLayerBuilder::server()
+                .with_method_metrics(true)
+                .with_meter(meter_provider.meter("test")),
+        );
+        let service = builder.build().layer(tower::service_fn(|_| async {
+            Ok::<_, Infallible>(
+                http::Response::builder()
+                    .header("grpc-status", "0")
+                    .body(TrailersBody(None))
+                    .unwrap(),
+            )
+        }));
+
+        let response = service
+            .oneshot(request("/helloworld.Greeter/SayHello"))
+            .await
+            .unwrap();
+        drain(response.into_body()).await;
+
+        meter_provider.force_flush().unwrap();
+        let metrics = exporter.get_finished_metrics().unwrap();
+        let metric = metrics[0]
+            .scope_metrics()
+            .flat_map(|scope| scope.metrics())
+            .find(|metric| metric.name() == RPC_SERVER_DURATION_METRIC)
+            .expect("duration metric should be recorded");
+        let AggregatedMetrics::F64(MetricData::Histogram(histogram)) = metric.data() else {
+            panic!("duration metric should be a f64 histogram");
+        };
+        let data_point = histogram.data_points().next().unwrap();
+        assert_eq!(data_point.count(), 1);
+        let mut attributes: Vec<_> = data_point
+            .attributes()
+            .map(|kv| kv.key.as_str().to_string())
+            .collect();
+        attributes.sort();
+        assert_eq!(
+            attributes,
+            [RPC_GRPC_STATUS_CODE, RPC_METHOD, RPC_SERVICE, RPC_SYSTEM]
+        );
+    }
+
+    #[cfg(feature = "grpc-trace-bin")]
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_inject_grpc_trace_bin() {
+        let (builder, provider, exporter) = with_test_tracer(GrpcLayerBuilder::client());
+        let service =
+            builder
+                .build()
+                .layer(tower::service_fn(|req: http::Request<()>| async move {
+                    let traced = req.headers().contains_key("grpc-trace-bin");
+                    Ok::<_, Infallible>(
+                        http::Response::builder()
+                            .header("grpc-status", if traced { "0" } else { "3" })
+                            .body(TrailersBody(None))
+                            .unwrap(),
+                    )
+                }));
+
+        let response = service
+            .oneshot(request("/helloworld.Greeter/SayHello"))
+            .await
+            .unwrap();
+        drain(response.into_body()).await;
+
+        provider.force_flush().unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(
+            attribute(&spans[0], RPC_GRPC_STATUS_CODE),
+            Some(Value::from(0))
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! gRPC status codes, see https://github.com/grpc/grpc/blob/master/doc/statuscodes.md
+use http::{HeaderMap, StatusCode};
+use opentelemetry::trace::SpanKind;
+
+const GRPC_STATUS_HEADER: &str = "grpc-status";
+const GRPC_MESSAGE_HEADER: &str = "grpc-message";
+
+pub(crate) const OK: i32 = 0;
+pub(crate) const CANCELLED: i32 = 1;
+pub(crate) const UNKNOWN: i32 = 2;
+const INVALID_ARGUMENT: i32 = 3;
+const DEADLINE_EXCEEDED: i32 = 4;
+const NOT_FOUND: i32 = 5;
+const ALREADY_EXISTS: i32 = 6;
+const PERMISSION_DENIED: i32 = 7;
+const RESOURCE_EXHAUSTED: i32 = 8;
+const FAILED_PRECONDITION: i32 = 9;
+const ABORTED: i32 = 10;
+const OUT_OF_RANGE: i32 = 11;
+const UNIMPLEMENTED: i32 = 12;
+const INTERNAL: i32 = 13;
+const UNAVAILABLE: i32 = 14;
+const DATA_LOSS: i32 = 15;
+const UNAUTHENTICATED: i32 = 16;
+
+/// The status of a call, from the `grpc-status` and `grpc-message` headers or trailers.
+pub(crate) fn from_headers(headers: &HeaderMap) -> Option<(i32, Option<String>)> {
+    let code = headers
+        .get(GRPC_STATUS_HEADER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    let message = headers
+        .get(GRPC_MESSAGE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(percent_decode)
+        .filter(|message| !message.is_empty());
+    Some((code, message))
+}
+
+/// The status of a call answered with a non-200 HTTP status, e.g. by a proxy, see
+/// https://github.com/grpc/grpc/blob/master/doc/http-grpc-status-mapping.md
+pub(crate) fn from_http_status(status: StatusCode) -> i32 {
+    match status.as_u16() {
+        400 => INTERNAL,
+        401 => UNAUTHENTICATED,
+        403 => PERMISSION_DENIED,
+        404 => UNIMPLEMENTED,
+        429 | 502 | 503 | 504 => UNAVAILABLE,
+        _ => UNKNOWN,
+    }
+}
+
+/// Whether the status code is an error of the span.
+///
+/// Every status but `OK` is an error for clients, while servers only report the codes of a
+/// server failure, as the others result from the request.
+pub(crate) fn is_error(code: i32, kind: &SpanKind) -> bool {
+    match kind {
+        SpanKind::Server => matches!(
+            code,
+            UNKNOWN | DEADLINE_EXCEEDED | UNIMPLEMENTED | INTERNAL | UNAVAILABLE | DATA_LOSS
+        ),
+        _ => code != OK,
+    }
+}
+
+/// The name of the status code, e.g. `NOT_FOUND`.
+pub(crate) fn name(code: i32) -> &'static str {
+    match code {
+        OK => "OK",
+        CANCELLED => "CANCELLED",
+        UNKNOWN => "UNKNOWN",
+        INVALID_ARGUMENT => "INVALID_ARGUMENT",
+        DEADLINE_EXCEEDED => "DEADLINE_EXCEEDED",
+        NOT_FOUND => "NOT_FOUND",
+        ALREADY_EXISTS => "ALREADY_EXISTS",
+        PERMISSION_DENIED => "PERMISSION_DENIED",
+        RESOURCE_EXHAUSTED => "RESOURCE_EXHAUSTED",
+        FAILED_PRECONDITION => "FAILED_PRECONDITION",
+        ABORTED => "ABORTED",
+        OUT_OF_RANGE => "OUT_OF_RANGE",
+        UNIMPLEMENTED => "UNIMPLEMENTED",
+        INTERNAL => "INTERNAL",
+        UNAVAILABLE => "UNAVAILABLE",
+        DATA_LOSS => "DATA_LOSS",
+        UNAUTHENTICATED => "UNAUTHENTICATED",
+        _ => "UNKNOWN_CODE",
+    }
+}
+
+/// Decode the percent-encoding of `grpc-message`, keeping invalid sequences as is.
+///
+/// `value` is ASCII, as read from a header.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Ok(byte) = u8::from_str_radix(&value[i + 1..i + 3], 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(from_headers(&headers), None);
+
+        headers.insert(GRPC_STATUS_HEADER, "13".parse().unwrap());
+        headers.insert(GRPC_MESSAGE_HEADER, "no%20route%3A 100%".parse().unwrap());
+        assert_eq!(
+            from_headers(&headers),
+            Some((INTERNAL, Some("no route: 100%".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_is_error() {
+        assert!(!is_error(OK, &SpanKind::Client));
+        assert!(is_error(NOT_FOUND, &SpanKind::Client));
+        assert!(!is_error(NOT_FOUND, &SpanKind::Server));
+        assert!(is_error(INTERNAL, &SpanKind::Server));
+        assert_eq!(
+            from_http_status(StatusCode::SERVICE_UNAVAILABLE),
+            UNAVAILABLE
+        );
+        assert_eq!(
+            name(from_http_status(StatusCode::NOT_FOUND)),
+            "UNIMPLEMENTED"
+        );
+    }
+}
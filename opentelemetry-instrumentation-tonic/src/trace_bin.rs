@@ -0,0 +1,63 @@
+//! Propagation of the span context in the binary `grpc-trace-bin` metadata, as sent by the
+//! OpenCensus gRPC integrations.
+use http::{HeaderMap, HeaderName, HeaderValue};
+use opentelemetry::trace::{SpanContext, TraceContextExt};
+use opentelemetry::Context;
+use opentelemetry_contrib::trace::propagator::binary::{Base64Format, BinaryPropagator};
+
+const GRPC_TRACE_BIN_HEADER: &str = "grpc-trace-bin";
+
+/// The span context of the `grpc-trace-bin` header, if it holds a valid one.
+pub(crate) fn extract(headers: &HeaderMap) -> Option<SpanContext> {
+    let value = headers.get(GRPC_TRACE_BIN_HEADER)?.to_str().ok()?.trim();
+    // Binary metadata is sent without the base64 padding, which the decoder requires.
+    let mut padded = value.to_string();
+    while padded.len() % 4 != 0 {
+        padded.push('=');
+    }
+    let span_context = BinaryPropagator::new().deserialize_from_base64(&padded);
+    span_context.is_valid().then_some(span_context)
+}
+
+/// Set the `grpc-trace-bin` header to the span context of `cx`, if valid.
+pub(crate) fn inject(cx: &Context, headers: &mut HeaderMap) {
+    let span = cx.span();
+    let span_context = span.span_context();
+    if !span_context.is_valid() {
+        return;
+    }
+    let value = BinaryPropagator::new().serialize_into_base64(span_context);
+    if let Ok(value) = HeaderValue::from_str(value.trim_end_matches('=')) {
+        headers.insert(HeaderName::from_static(GRPC_TRACE_BIN_HEADER), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{SpanId, TraceFlags, TraceId, TraceState};
+
+    #[test]
+    fn test_round_trip() {
+        let span_context = SpanContext::new(
+            TraceId::from(0x4bf9_2f35_77b3_4da6_a3ce_929d_0e0e_4736),
+            SpanId::from(0x00f0_67aa_0ba9_02b7),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        );
+        let mut headers = HeaderMap::new();
+        inject(
+            &Context::new().with_remote_span_context(span_context.clone()),
+            &mut headers,
+        );
+        assert_eq!(
+            headers[GRPC_TRACE_BIN_HEADER],
+            "AABL+S81d7NNpqPOkp0ODkc2AQDwZ6oLqQK3AgE"
+        );
+        assert_eq!(extract(&headers), Some(span_context));
+
+        headers.insert(GRPC_TRACE_BIN_HEADER, HeaderValue::from_static("invalid"));
+        assert_eq!(extract(&headers), None);
+    }
+}
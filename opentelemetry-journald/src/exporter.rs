@@ -0,0 +1,437 @@
+use crate::native;
+use opentelemetry::logs::{AnyValue, Severity};
+use opentelemetry::otel_debug;
+use opentelemetry::Key;
+use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
+use opentelemetry_sdk::logs::{LogBatch, SdkLogRecord};
+use opentelemetry_sdk::Resource;
+use std::fmt::{self, Debug, Write};
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_SOCKET_PATH: &str = "/run/systemd/journal/socket";
+
+/// Builder for [`JournaldExporter`].
+#[derive(Debug)]
+pub struct JournaldExporterBuilder {
+    socket_path: PathBuf,
+    syslog_identifier: Option<String>,
+    attribute_prefix: String,
+}
+
+impl JournaldExporterBuilder {
+    /// Set the `SYSLOG_IDENTIFIER` of the entries.
+    ///
+    /// Defaults to the `service.name` resource attribute.
+    pub fn with_syslog_identifier(mut self, identifier: impl Into<String>) -> Self {
+        self.syslog_identifier = Some(identifier.into());
+        self
+    }
+
+    /// Prefix the journal field names of attributes, e.g. `OTEL_`.
+    ///
+    /// Without a prefix an attribute named like a well known field such as `message` adds a
+    /// second value to that field.
+    pub fn with_attribute_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.attribute_prefix = prefix.into();
+        self
+    }
+
+    /// Set the path of the journald socket, `/run/systemd/journal/socket` by default.
+    pub fn with_socket_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.socket_path = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Connect to the journald socket.
+    ///
+    /// Fails if the socket doesn't exist, e.g. when journald isn't running.
+    pub fn build(self) -> io::Result<JournaldExporter> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(&self.socket_path)?;
+        otel_debug!(name: "Journald.Created", socket_path = format!("{}", self.socket_path.display()));
+        Ok(JournaldExporter {
+            socket,
+            socket_path: self.socket_path,
+            syslog_identifier: self.syslog_identifier,
+            attribute_prefix: self.attribute_prefix,
+        })
+    }
+}
+
+/// Exporter writing log records to systemd-journald.
+///
+/// See the [crate documentation](crate) for the journal fields written.
+pub struct JournaldExporter {
+    socket: UnixDatagram,
+    socket_path: PathBuf,
+    syslog_identifier: Option<String>,
+    attribute_prefix: String,
+}
+
+impl JournaldExporter {
+    /// Create a builder for the exporter.
+    pub fn builder() -> JournaldExporterBuilder {
+        JournaldExporterBuilder {
+            socket_path: PathBuf::from(DEFAULT_SOCKET_PATH),
+            syslog_identifier: None,
+            attribute_prefix: String::new(),
+        }
+    }
+
+    fn encode(&self, record: &SdkLogRecord) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(256);
+
+        let message = record.body().map(format_body).unwrap_or_default();
+        native::append_field(&mut payload, "MESSAGE", &message);
+
+        let priority = record.severity_number().map_or(6, priority);
+        native::append_field(&mut payload, "PRIORITY", &[b'0' + priority]);
+
+        if let Some(identifier) = &self.syslog_identifier {
+            native::append_field(&mut payload, "SYSLOG_IDENTIFIER", identifier.as_bytes());
+        }
+
+        if let Some(trace_context) = record.trace_context() {
+            native::append_field(
+                &mut payload,
+                "TRACE_ID",
+                trace_context.trace_id.to_string().as_bytes(),
+            );
+            native::append_field(
+                &mut payload,
+                "SPAN_ID",
+                trace_context.span_id.to_string().as_bytes(),
+            );
+            if let Some(trace_flags) = trace_context.trace_flags {
+                native::append_field(
+                    &mut payload,
+                    "TRACE_FLAGS",
+                    format!("{:02x}", trace_flags.to_u8()).as_bytes(),
+                );
+            }
+        }
+
+        if let Some(event_name) = record.event_name().filter(|name| !name.is_empty()) {
+            native::append_field(&mut payload, "EVENT_NAME", event_name.as_bytes());
+        }
+
+        for (key, value) in record.attributes_iter() {
+            self.append_attribute(&mut payload, key, value);
+        }
+
+        payload
+    }
+
+    fn append_attribute(&self, payload: &mut Vec<u8>, key: &Key, value: &AnyValue) {
+        match native::field_name(&self.attribute_prefix, key.as_str()) {
+            Some(name) => native::append_field(payload, &name, &format_body(value)),
+            None => {
+                otel_debug!(name: "Journald.AttributeIgnored", key = key.as_str(), message = "The key can't be turned into a journal field name.");
+            }
+        }
+    }
+}
+
+impl Debug for JournaldExporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "journald log exporter (socket path: {})",
+            self.socket_path.display()
+        )
+    }
+}
+
+impl opentelemetry_sdk::logs::LogExporter for JournaldExporter {
+    async fn export(&self, batch: LogBatch<'_>) -> OTelSdkResult {
+        let mut result = Ok(());
+        for (record, _instrumentation) in batch.iter() {
+            if let Err(err) = native::send(&self.socket, &self.encode(record)) {
+                result = Err(OTelSdkError::InternalFailure(format!(
+                    "Failed to write log record to journald: {err}"
+                )));
+            }
+        }
+        result
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        if self.syslog_identifier.is_none() {
+            self.syslog_identifier = resource
+                .get(&Key::from_static_str("service.name"))
+                .map(|name| name.to_string());
+        }
+    }
+}
+
+/// Map a severity to a syslog priority.
+const fn priority(severity: Severity) -> u8 {
+    match severity {
+        Severity::Trace
+        | Severity::Trace2
+        | Severity::Trace3
+        | Severity::Trace4
+        | Severity::Debug
+        | Severity::Debug2
+        | Severity::Debug3
+        | Severity::Debug4 => 7,
+        Severity::Info | Severity::Info2 | Severity::Info3 | Severity::Info4 => 6,
+        Severity::Warn | Severity::Warn2 | Severity::Warn3 | Severity::Warn4 => 4,
+        Severity::Error | Severity::Error2 | Severity::Error3 | Severity::Error4 => 3,
+        Severity::Fatal | Severity::Fatal2 | Severity::Fatal3 | Severity::Fatal4 => 2,
+    }
+}
+
+/// Format a value as journal field value.
+///
+/// Strings and bytes are written as is, lists and maps as JSON.
+fn format_body(value: &AnyValue) -> Vec<u8> {
+    match value {
+        AnyValue::String(s) => s.as_str().as_bytes().to_vec(),
+        AnyValue::Bytes(b) => b.to_vec(),
+        _ => {
+            let mut json = String::new();
+            write_json(&mut json, value);
+            json.into_bytes()
+        }
+    }
+}
+
+fn write_json(out: &mut String, value: &AnyValue) {
+    match value {
+        AnyValue::Boolean(b) => {
+            let _ = write!(out, "{b}");
+        }
+        AnyValue::Int(i) => {
+            let _ = write!(out, "{i}");
+        }
+        AnyValue::Double(f) if f.is_finite() => {
+            let _ = write!(out, "{f}");
+        }
+        AnyValue::Double(f) => write_json_string(out, &f.to_string()),
+        AnyValue::String(s) => write_json_string(out, s.as_str()),
+        AnyValue::Bytes(b) => {
+            let hex = b.iter().fold(String::new(), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            });
+            write_json_string(out, &hex);
+        }
+        AnyValue::ListAny(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_json(out, value);
+            }
+            out.push(']');
+        }
+        AnyValue::Map(map) => {
+            out.push('{');
+            for (i, (key, value)) in map.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_json_string(out, key.as_str());
+                out.push(':');
+                write_json(out, value);
+            }
+            out.push('}');
+        }
+        _ => out.push_str("null"),
+    }
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::logs::{LogRecord, Logger, LoggerProvider};
+    use opentelemetry::trace::{SpanId, TraceFlags, TraceId};
+    use opentelemetry::InstrumentationScope;
+    use opentelemetry_sdk::logs::{LogExporter, SdkLoggerProvider};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A datagram socket standing in for journald.
+    struct FakeJournal {
+        socket: UnixDatagram,
+        path: PathBuf,
+    }
+
+    impl FakeJournal {
+        fn new() -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "otel-journald-{}-{}.socket",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::SeqCst)
+            ));
+            let _ = std::fs::remove_file(&path);
+            let socket = UnixDatagram::bind(&path).unwrap();
+            FakeJournal { socket, path }
+        }
+
+        fn exporter(&self) -> JournaldExporterBuilder {
+            JournaldExporter::builder().with_socket_path(&self.path)
+        }
+
+        fn recv(&self) -> Vec<u8> {
+            let mut buf = vec![0u8; 64 * 1024];
+            let len = self.socket.recv(&mut buf).unwrap();
+            buf.truncate(len);
+            buf
+        }
+    }
+
+    impl Drop for FakeJournal {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn new_sdk_log_record() -> SdkLogRecord {
+        SdkLoggerProvider::builder()
+            .build()
+            .logger("test")
+            .create_log_record()
+    }
+
+    fn export(exporter: &JournaldExporter, record: &SdkLogRecord) -> OTelSdkResult {
+        let scope = InstrumentationScope::default();
+        let records = [(record, &scope)];
+        futures_executor::block_on(exporter.export(LogBatch::new(&records)))
+    }
+
+    #[test]
+    fn test_export() {
+        let journal = FakeJournal::new();
+        let mut exporter = journal.exporter().build().unwrap();
+        exporter.set_resource(
+            &Resource::builder_empty()
+                .with_service_name("my-service")
+                .build(),
+        );
+
+        let mut record = new_sdk_log_record();
+        record.set_body("hello world".into());
+        record.set_severity_number(Severity::Warn);
+        record.set_event_name("my-event");
+        record.set_trace_context(
+            TraceId::from_hex("0af7651916cd43dd8448eb211c80319c").unwrap(),
+            SpanId::from_hex("b7ad6b7169203331").unwrap(),
+            Some(TraceFlags::SAMPLED),
+        );
+        record.add_attribute("http.request.method", "GET");
+        record.add_attribute("retry", 3);
+        record.add_attribute("trace", "first\nsecond");
+        export(&exporter, &record).unwrap();
+
+        let mut expected = b"MESSAGE=hello world\n\
+PRIORITY=4\n\
+SYSLOG_IDENTIFIER=my-service\n\
+TRACE_ID=0af7651916cd43dd8448eb211c80319c\n\
+SPAN_ID=b7ad6b7169203331\n\
+TRACE_FLAGS=01\n\
+EVENT_NAME=my-event\n\
+HTTP_REQUEST_METHOD=GET\n\
+RETRY=3\n\
+TRACE\n"
+            .to_vec();
+        expected.extend_from_slice(&12u64.to_le_bytes());
+        expected.extend_from_slice(b"first\nsecond\n");
+        assert_eq!(journal.recv(), expected);
+    }
+
+    #[test]
+    fn test_export_options() {
+        let journal = FakeJournal::new();
+        let mut exporter = journal
+            .exporter()
+            .with_syslog_identifier("my-app")
+            .with_attribute_prefix("OTEL_")
+            .build()
+            .unwrap();
+        exporter.set_resource(
+            &Resource::builder_empty()
+                .with_service_name("my-service")
+                .build(),
+        );
+
+        let mut record = new_sdk_log_record();
+        record.add_attribute("message", "attribute");
+        export(&exporter, &record).unwrap();
+
+        assert_eq!(
+            journal.recv(),
+            b"MESSAGE=\nPRIORITY=6\nSYSLOG_IDENTIFIER=my-app\nOTEL_MESSAGE=attribute\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_build_without_socket() {
+        let journal = FakeJournal::new();
+        let path = journal.path.clone();
+        drop(journal);
+
+        assert!(JournaldExporter::builder()
+            .with_socket_path(path)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_priority() {
+        assert_eq!(priority(Severity::Trace), 7);
+        assert_eq!(priority(Severity::Debug4), 7);
+        assert_eq!(priority(Severity::Info), 6);
+        assert_eq!(priority(Severity::Warn2), 4);
+        assert_eq!(priority(Severity::Error), 3);
+        assert_eq!(priority(Severity::Fatal), 2);
+    }
+
+    #[test]
+    fn test_format_body() {
+        assert_eq!(format_body(&AnyValue::Int(42)), b"42".to_vec());
+        assert_eq!(format_body(&AnyValue::Boolean(true)), b"true".to_vec());
+        assert_eq!(
+            format_body(&AnyValue::Bytes(Box::new(vec![0, 1, 255]))),
+            vec![0, 1, 255]
+        );
+        assert_eq!(
+            format_body(&AnyValue::ListAny(Box::new(vec![
+                AnyValue::Int(1),
+                AnyValue::String("a\"b".into()),
+                AnyValue::Bytes(Box::new(vec![0xab])),
+            ]))),
+            br#"[1,"a\"b","ab"]"#.to_vec()
+        );
+
+        let mut map = HashMap::new();
+        map.insert(Key::new("key"), AnyValue::Double(1.5));
+        assert_eq!(
+            format_body(&AnyValue::Map(Box::new(map))),
+            br#"{"key":1.5}"#.to_vec()
+        );
+    }
+}
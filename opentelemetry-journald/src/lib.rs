@@ -0,0 +1,66 @@
+//! # OpenTelemetry systemd-journald Exporter for Logs
+//!
+//! This crate provides a log exporter writing log records to
+//! [systemd-journald](https://www.freedesktop.org/software/systemd/man/latest/systemd-journald.service.html)
+//! using its [native protocol](https://systemd.io/JOURNAL_NATIVE_PROTOCOL/),
+//! the same datagram socket `sd_journal_sendv()` writes to. No collector or
+//! `libsystemd` is needed, and the records can be queried with `journalctl`.
+//!
+//! ## Journal Fields
+//!
+//! Every log record becomes one journal entry with the following fields:
+//!
+//! - `MESSAGE`: the body of the log record.
+//! - `PRIORITY`: the severity mapped to a syslog level, see below.
+//! - `SYSLOG_IDENTIFIER`: the configured identifier, or the `service.name`
+//!   resource attribute.
+//! - `TRACE_ID`, `SPAN_ID` and `TRACE_FLAGS`: the trace context of the record,
+//!   when present.
+//! - `EVENT_NAME`: the event name of the record, when present.
+//! - One field per attribute. Journal field names may only contain uppercase
+//!   letters, digits and underscores, so `http.request.method` is written as
+//!   `HTTP_REQUEST_METHOD`.
+//!
+//! | OpenTelemetry severity | `PRIORITY`      |
+//! | ---------------------- | --------------- |
+//! | `TRACE*`, `DEBUG*`     | 7 (`debug`)     |
+//! | `INFO*`                | 6 (`info`)      |
+//! | `WARN*`                | 4 (`warning`)   |
+//! | `ERROR*`               | 3 (`err`)       |
+//! | `FATAL*`               | 2 (`crit`)      |
+//!
+//! Records without a severity are logged with the `info` priority.
+//!
+//! ## Synchronous Export
+//!
+//! Each record is sent to journald as soon as it is exported, so the exporter is
+//! meant to be used with a simple log processor.
+//!
+//! ## Getting Started
+//!
+//! ```no_run
+//! use opentelemetry_journald::JournaldExporter;
+//! use opentelemetry_sdk::logs::SdkLoggerProvider;
+//! use opentelemetry_sdk::Resource;
+//!
+//! let exporter = JournaldExporter::builder()
+//!     .with_attribute_prefix("OTEL_")
+//!     .build()
+//!     .expect("journald socket is not available");
+//!
+//! let provider = SdkLoggerProvider::builder()
+//!     .with_resource(
+//!         Resource::builder_empty()
+//!             .with_service_name("example")
+//!             .build(),
+//!     )
+//!     .with_simple_exporter(exporter)
+//!     .build();
+//! ```
+#[cfg(target_os = "linux")]
+mod exporter;
+#[cfg(target_os = "linux")]
+mod native;
+
+#[cfg(target_os = "linux")]
+pub use exporter::{JournaldExporter, JournaldExporterBuilder};
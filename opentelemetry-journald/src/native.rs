@@ -0,0 +1,168 @@
+//! Encoding and transport of the journald [native protocol](https://systemd.io/JOURNAL_NATIVE_PROTOCOL/).
+use std::fs::File;
+use std::io::{self, Write};
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::UnixDatagram;
+use std::ptr;
+
+/// Journal field names are limited to 64 characters.
+const MAX_FIELD_NAME_LEN: usize = 64;
+
+/// Append a field to a native protocol payload.
+///
+/// Values without a newline use the `NAME=value\n` form, other values are written as the name,
+/// a newline, the value length as little endian 64 bit integer, the value and a newline.
+pub(crate) fn append_field(payload: &mut Vec<u8>, name: &str, value: &[u8]) {
+    payload.extend_from_slice(name.as_bytes());
+    if value.contains(&b'\n') {
+        payload.push(b'\n');
+        payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        payload.push(b'=');
+    }
+    payload.extend_from_slice(value);
+    payload.push(b'\n');
+}
+
+/// Turn `key` into a valid journal field name, prefixed by `prefix`.
+///
+/// Field names only contain uppercase ASCII letters, digits and underscores and don't start with a
+/// digit or an underscore, which is reserved for fields set by journald itself. Returns `None` if
+/// nothing is left.
+pub(crate) fn field_name(prefix: &str, key: &str) -> Option<String> {
+    let name: String = prefix
+        .chars()
+        .chain(key.chars())
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let mut name = name
+        .trim_start_matches(|c: char| c == '_' || c.is_ascii_digit())
+        .to_string();
+    name.truncate(MAX_FIELD_NAME_LEN);
+    (!name.is_empty()).then_some(name)
+}
+
+/// Send a payload over a socket connected to journald.
+///
+/// Payloads exceeding the maximum datagram size are written to a sealed memfd and its descriptor
+/// is passed instead, like `sd_journal_sendv()` does.
+pub(crate) fn send(socket: &UnixDatagram, payload: &[u8]) -> io::Result<()> {
+    match socket.send(payload) {
+        Ok(_) => Ok(()),
+        Err(err)
+            if matches!(
+                err.raw_os_error(),
+                Some(libc::EMSGSIZE) | Some(libc::ENOBUFS)
+            ) =>
+        {
+            send_memfd(socket, payload)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn send_memfd(socket: &UnixDatagram, payload: &[u8]) -> io::Result<()> {
+    // SAFETY: the name is a valid nul terminated string.
+    let fd = unsafe {
+        libc::memfd_create(
+            b"opentelemetry-journald\0".as_ptr().cast(),
+            libc::MFD_ALLOW_SEALING | libc::MFD_CLOEXEC,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: the descriptor was just created and is owned by nobody else.
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    file.write_all(payload)?;
+
+    // journald only accepts sealed memfds.
+    let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+    // SAFETY: `file` keeps the descriptor open.
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, seals) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    send_fd(socket, file.as_raw_fd())
+}
+
+fn send_fd(socket: &UnixDatagram, fd: RawFd) -> io::Result<()> {
+    // SAFETY: CMSG_SPACE and CMSG_LEN only compute sizes.
+    let (space, len) = unsafe {
+        (
+            libc::CMSG_SPACE(mem::size_of::<RawFd>() as u32) as usize,
+            libc::CMSG_LEN(mem::size_of::<RawFd>() as u32),
+        )
+    };
+    // u64 elements keep the buffer aligned for `cmsghdr`.
+    let mut control = vec![0u64; space.div_ceil(mem::size_of::<u64>())];
+
+    // SAFETY: an all zero `msghdr` is valid, no name or data is sent.
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = space as _;
+
+    // SAFETY: the control buffer is large enough for one header carrying one descriptor, and the
+    // socket is connected so no destination is needed.
+    let sent = unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = len as _;
+        ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<RawFd>(), fd);
+        libc::sendmsg(socket.as_raw_fd(), &msg, libc::MSG_NOSIGNAL)
+    };
+    if sent < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_field() {
+        let mut payload = Vec::new();
+        append_field(&mut payload, "MESSAGE", b"hello");
+        append_field(&mut payload, "STACK", b"a\nb");
+        assert_eq!(
+            payload,
+            b"MESSAGE=hello\nSTACK\n\x03\0\0\0\0\0\0\0a\nb\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_field_name() {
+        assert_eq!(
+            field_name("", "http.request.method").as_deref(),
+            Some("HTTP_REQUEST_METHOD")
+        );
+        assert_eq!(
+            field_name("OTEL_", "user-id").as_deref(),
+            Some("OTEL_USER_ID")
+        );
+        assert_eq!(field_name("", "_1st.key").as_deref(), Some("ST_KEY"));
+        assert_eq!(field_name("", "clé").as_deref(), Some("CL_"));
+        assert_eq!(field_name("", "__").as_deref(), None);
+        assert_eq!(field_name("", &"a".repeat(100)).map(|n| n.len()), Some(64));
+    }
+
+    #[test]
+    fn test_send_memfd() {
+        let (sender, receiver) = UnixDatagram::pair().unwrap();
+        send_memfd(&sender, b"MESSAGE=hello\n").unwrap();
+
+        // The memfd travels as ancillary data, the datagram itself is empty.
+        let mut buf = [0u8; 16];
+        assert_eq!(receiver.recv(&mut buf).unwrap(), 0);
+    }
+}
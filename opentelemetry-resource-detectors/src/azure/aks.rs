@@ -0,0 +1,175 @@
+use super::imds::{self, ComputeMetadata};
+use super::{non_empty_env, CLOUD_PROVIDER_AZURE};
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::resource::{Resource, ResourceDetector};
+use opentelemetry_semantic_conventions as semconv;
+use std::time::Duration;
+
+const KUBERNETES_SERVICE_HOST_ENV_VAR: &str = "KUBERNETES_SERVICE_HOST";
+
+const CLOUD_PLATFORM_AZURE_AKS: &str = "azure_aks";
+const AKS_CLUSTER_NAME_TAG: &str = "aks-managed-cluster-name";
+const AKS_NODE_RESOURCE_GROUP_PREFIX: &str = "MC_";
+
+/// Detect Azure Kubernetes Service information.
+///
+/// The detector only runs inside a Kubernetes pod (`KUBERNETES_SERVICE_HOST` is set) and then
+/// queries the [Instance Metadata Service] of the node. It returns an empty resource when either
+/// check fails. Combine it with [`K8sResourceDetector`](crate::K8sResourceDetector) for pod level
+/// attributes.
+///
+/// This resource detector returns the following information:
+///
+/// - Cloud provider and platform (`cloud.provider`, `cloud.platform`).
+/// - Region (`cloud.region`).
+/// - Cluster name (`k8s.cluster.name`), taken from the `aks-managed-cluster-name` node tag or
+///   derived from the `MC_<resource group>_<cluster>_<region>` node resource group name.
+///
+/// [Instance Metadata Service]: https://learn.microsoft.com/azure/virtual-machines/instance-metadata-service
+#[derive(Debug)]
+pub struct AzureAksResourceDetector {
+    endpoint: String,
+    timeout: Duration,
+}
+
+impl AzureAksResourceDetector {
+    /// Create a detector querying the default IMDS endpoint.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum time spent connecting to and reading from IMDS.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Default for AzureAksResourceDetector {
+    fn default() -> Self {
+        Self {
+            endpoint: imds::IMDS_ENDPOINT.to_string(),
+            timeout: imds::DEFAULT_IMDS_TIMEOUT,
+        }
+    }
+}
+
+impl ResourceDetector for AzureAksResourceDetector {
+    fn detect(&self) -> Resource {
+        if non_empty_env(KUBERNETES_SERVICE_HOST_ENV_VAR).is_none() {
+            return Resource::builder_empty().build();
+        }
+        let Some(metadata) = imds::fetch_compute_metadata(&self.endpoint, self.timeout) else {
+            return Resource::builder_empty().build();
+        };
+
+        Resource::builder_empty()
+            .with_attributes(
+                [
+                    Some(KeyValue::new(
+                        semconv::attribute::CLOUD_PROVIDER,
+                        CLOUD_PROVIDER_AZURE,
+                    )),
+                    Some(KeyValue::new(
+                        semconv::attribute::CLOUD_PLATFORM,
+                        CLOUD_PLATFORM_AZURE_AKS,
+                    )),
+                    cluster_name(&metadata)
+                        .map(|name| KeyValue::new(semconv::attribute::K8S_CLUSTER_NAME, name)),
+                    Some(metadata.location)
+                        .filter(|location| !location.is_empty())
+                        .map(|location| KeyValue::new(semconv::attribute::CLOUD_REGION, location)),
+                ]
+                .into_iter()
+                .flatten(),
+            )
+            .build()
+    }
+}
+
+fn cluster_name(metadata: &ComputeMetadata) -> Option<String> {
+    if let Some(tag) = metadata
+        .tags_list
+        .iter()
+        .find(|tag| tag.name == AKS_CLUSTER_NAME_TAG && !tag.value.is_empty())
+    {
+        return Some(tag.value.clone());
+    }
+
+    // MC_<resource group>_<cluster>_<region>; the resource group may itself contain underscores.
+    let name = metadata
+        .resource_group_name
+        .strip_prefix(AKS_NODE_RESOURCE_GROUP_PREFIX)?
+        .strip_suffix(metadata.location.as_str())?
+        .strip_suffix('_')?;
+    let (_, cluster) = name.rsplit_once('_')?;
+    Some(cluster.to_string()).filter(|cluster| !cluster.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::azure::imds::tests::{serve_once, COMPUTE_METADATA};
+    use crate::azure::imds::ComputeTag;
+    use opentelemetry::{Key, Value};
+
+    #[test]
+    fn test_azure_aks_resource_detector() {
+        let endpoint = serve_once(format!("HTTP/1.1 200 OK\r\n\r\n{COMPUTE_METADATA}"));
+        temp_env::with_var(KUBERNETES_SERVICE_HOST_ENV_VAR, Some("10.0.0.1"), || {
+            let resource = AzureAksResourceDetector {
+                endpoint,
+                timeout: Duration::from_secs(5),
+            }
+            .detect();
+
+            assert_eq!(resource.len(), 4);
+            assert_eq!(
+                resource.get(&Key::from_static_str(semconv::attribute::CLOUD_PLATFORM)),
+                Some(Value::from("azure_aks"))
+            );
+            assert_eq!(
+                resource.get(&Key::from_static_str(semconv::attribute::K8S_CLUSTER_NAME)),
+                Some(Value::from("my-cluster"))
+            );
+        });
+    }
+
+    #[test]
+    fn test_azure_aks_resource_detector_outside_kubernetes() {
+        temp_env::with_var_unset(KUBERNETES_SERVICE_HOST_ENV_VAR, || {
+            let resource = AzureAksResourceDetector::default().detect();
+            assert_eq!(resource.len(), 0);
+        });
+    }
+
+    #[test]
+    fn test_cluster_name() {
+        let from_tag = ComputeMetadata {
+            resource_group_name: "custom-node-rg".to_string(),
+            tags_list: vec![ComputeTag {
+                name: AKS_CLUSTER_NAME_TAG.to_string(),
+                value: "tagged-cluster".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert_eq!(cluster_name(&from_tag), Some("tagged-cluster".to_string()));
+
+        let from_resource_group = ComputeMetadata {
+            location: "eastus".to_string(),
+            resource_group_name: "MC_my_rg_cluster-1_eastus".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            cluster_name(&from_resource_group),
+            Some("cluster-1".to_string())
+        );
+
+        let custom_resource_group = ComputeMetadata {
+            location: "eastus".to_string(),
+            resource_group_name: "custom-node-rg".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(cluster_name(&custom_resource_group), None);
+    }
+}
@@ -0,0 +1,144 @@
+use super::{
+    app_service_resource_id, non_empty_env, CLOUD_PROVIDER_AZURE,
+    FUNCTIONS_EXTENSION_VERSION_ENV_VAR, REGION_NAME_ENV_VAR, WEBSITE_INSTANCE_ID_ENV_VAR,
+    WEBSITE_SITE_NAME_ENV_VAR,
+};
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::resource::{Resource, ResourceDetector};
+use opentelemetry_semantic_conventions as semconv;
+
+// For a complete list of environment variables set by App Service, see:
+// https://learn.microsoft.com/azure/app-service/reference-app-settings
+const WEBSITE_HOSTNAME_ENV_VAR: &str = "WEBSITE_HOSTNAME";
+const WEBSITE_SLOT_NAME_ENV_VAR: &str = "WEBSITE_SLOT_NAME";
+const WEBSITE_HOME_STAMPNAME_ENV_VAR: &str = "WEBSITE_HOME_STAMPNAME";
+
+const CLOUD_PLATFORM_AZURE_APP_SERVICE: &str = "azure_app_service";
+const AZURE_APP_SERVICE_STAMP: &str = "azure.app.service.stamp";
+
+/// Detect Azure App Service information.
+///
+/// The detector only reads environment variables. It returns an empty resource when
+/// `WEBSITE_SITE_NAME` is not set, or when `FUNCTIONS_EXTENSION_VERSION` is set, in which case
+/// [`AzureFunctionsResourceDetector`](super::AzureFunctionsResourceDetector) applies instead.
+///
+/// This resource detector returns the following information:
+///
+/// - Cloud provider and platform (`cloud.provider`, `cloud.platform`).
+/// - Region (`cloud.region`) and ARM resource ID (`cloud.resource_id`).
+/// - Site name (`service.name`) and instance (`service.instance.id`).
+/// - Default host name (`host.id`).
+/// - Deployment slot (`deployment.environment.name`).
+/// - Scale unit (`azure.app.service.stamp`).
+#[derive(Debug)]
+pub struct AzureAppServiceResourceDetector;
+
+impl ResourceDetector for AzureAppServiceResourceDetector {
+    fn detect(&self) -> Resource {
+        let Some(site_name) = non_empty_env(WEBSITE_SITE_NAME_ENV_VAR) else {
+            return Resource::builder_empty().build();
+        };
+        if non_empty_env(FUNCTIONS_EXTENSION_VERSION_ENV_VAR).is_some() {
+            return Resource::builder_empty().build();
+        }
+
+        Resource::builder_empty()
+            .with_attributes(
+                [
+                    Some(KeyValue::new(
+                        semconv::attribute::CLOUD_PROVIDER,
+                        CLOUD_PROVIDER_AZURE,
+                    )),
+                    Some(KeyValue::new(
+                        semconv::attribute::CLOUD_PLATFORM,
+                        CLOUD_PLATFORM_AZURE_APP_SERVICE,
+                    )),
+                    Some(KeyValue::new(semconv::attribute::SERVICE_NAME, site_name)),
+                    non_empty_env(REGION_NAME_ENV_VAR)
+                        .map(|region| KeyValue::new(semconv::attribute::CLOUD_REGION, region)),
+                    app_service_resource_id()
+                        .map(|id| KeyValue::new(semconv::attribute::CLOUD_RESOURCE_ID, id)),
+                    non_empty_env(WEBSITE_INSTANCE_ID_ENV_VAR)
+                        .map(|id| KeyValue::new(semconv::attribute::SERVICE_INSTANCE_ID, id)),
+                    non_empty_env(WEBSITE_HOSTNAME_ENV_VAR)
+                        .map(|host| KeyValue::new(semconv::attribute::HOST_ID, host)),
+                    non_empty_env(WEBSITE_SLOT_NAME_ENV_VAR).map(|slot| {
+                        KeyValue::new(semconv::attribute::DEPLOYMENT_ENVIRONMENT_NAME, slot)
+                    }),
+                    non_empty_env(WEBSITE_HOME_STAMPNAME_ENV_VAR)
+                        .map(|stamp| KeyValue::new(AZURE_APP_SERVICE_STAMP, stamp)),
+                ]
+                .into_iter()
+                .flatten(),
+            )
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::azure::{WEBSITE_OWNER_NAME_ENV_VAR, WEBSITE_RESOURCE_GROUP_ENV_VAR};
+    use opentelemetry::{Key, Value};
+
+    #[test]
+    fn test_azure_app_service_resource_detector() {
+        temp_env::with_vars(
+            [
+                (WEBSITE_SITE_NAME_ENV_VAR, Some("my-site")),
+                (
+                    WEBSITE_OWNER_NAME_ENV_VAR,
+                    Some("00000000-0000-0000-0000-000000000000+my-rg-WestEuropewebspace"),
+                ),
+                (WEBSITE_RESOURCE_GROUP_ENV_VAR, Some("my-rg")),
+                (REGION_NAME_ENV_VAR, Some("West Europe")),
+                (WEBSITE_INSTANCE_ID_ENV_VAR, Some("instance-1")),
+                (WEBSITE_HOSTNAME_ENV_VAR, Some("my-site.azurewebsites.net")),
+                (WEBSITE_SLOT_NAME_ENV_VAR, Some("production")),
+                (WEBSITE_HOME_STAMPNAME_ENV_VAR, Some("waws-prod-am2-123")),
+                (FUNCTIONS_EXTENSION_VERSION_ENV_VAR, None),
+            ],
+            || {
+                let resource = AzureAppServiceResourceDetector.detect();
+
+                assert_eq!(resource.len(), 9);
+                assert_eq!(
+                    resource.get(&Key::from_static_str(semconv::attribute::CLOUD_PLATFORM)),
+                    Some(Value::from("azure_app_service"))
+                );
+                assert_eq!(
+                    resource.get(&Key::from_static_str(semconv::attribute::CLOUD_RESOURCE_ID)),
+                    Some(Value::from(
+                        "/subscriptions/00000000-0000-0000-0000-000000000000/resourceGroups/my-rg/providers/Microsoft.Web/sites/my-site"
+                    ))
+                );
+                assert_eq!(
+                    resource.get(&Key::from_static_str(semconv::attribute::SERVICE_NAME)),
+                    Some(Value::from("my-site"))
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_azure_app_service_resource_detector_skips_functions() {
+        temp_env::with_vars(
+            [
+                (WEBSITE_SITE_NAME_ENV_VAR, Some("my-function-app")),
+                (FUNCTIONS_EXTENSION_VERSION_ENV_VAR, Some("~4")),
+            ],
+            || {
+                let resource = AzureAppServiceResourceDetector.detect();
+                assert_eq!(resource.len(), 0);
+            },
+        );
+    }
+
+    #[test]
+    fn test_azure_app_service_resource_detector_off_azure() {
+        temp_env::with_vars_unset([WEBSITE_SITE_NAME_ENV_VAR], || {
+            let resource = AzureAppServiceResourceDetector.detect();
+            assert_eq!(resource.len(), 0);
+        });
+    }
+}
@@ -0,0 +1,122 @@
+use super::{
+    app_service_resource_id, non_empty_env, CLOUD_PROVIDER_AZURE,
+    FUNCTIONS_EXTENSION_VERSION_ENV_VAR, REGION_NAME_ENV_VAR, WEBSITE_INSTANCE_ID_ENV_VAR,
+    WEBSITE_SITE_NAME_ENV_VAR,
+};
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::resource::{Resource, ResourceDetector};
+use opentelemetry_semantic_conventions as semconv;
+
+// For a complete list of environment variables set by Azure Functions, see:
+// https://learn.microsoft.com/azure/azure-functions/functions-app-settings
+const WEBSITE_MEMORY_LIMIT_MB_ENV_VAR: &str = "WEBSITE_MEMORY_LIMIT_MB";
+
+const CLOUD_PLATFORM_AZURE_FUNCTIONS: &str = "azure_functions";
+
+/// Detect Azure Functions information.
+///
+/// The detector only reads environment variables and returns an empty resource unless both
+/// `WEBSITE_SITE_NAME` and `FUNCTIONS_EXTENSION_VERSION` are set.
+///
+/// This resource detector returns the following information:
+///
+/// - Cloud provider and platform (`cloud.provider`, `cloud.platform`).
+/// - Region (`cloud.region`) and ARM resource ID (`cloud.resource_id`).
+/// - Function app name, runtime version and instance (`faas.name`, `faas.version`, `faas.instance`).
+/// - Memory limit in bytes (`faas.max_memory`).
+#[derive(Debug)]
+pub struct AzureFunctionsResourceDetector;
+
+impl ResourceDetector for AzureFunctionsResourceDetector {
+    fn detect(&self) -> Resource {
+        let (Some(site_name), Some(extension_version)) = (
+            non_empty_env(WEBSITE_SITE_NAME_ENV_VAR),
+            non_empty_env(FUNCTIONS_EXTENSION_VERSION_ENV_VAR),
+        ) else {
+            return Resource::builder_empty().build();
+        };
+
+        // Convert memory limit from MB (string) to Bytes (int) as required by semantic conventions.
+        let max_memory = non_empty_env(WEBSITE_MEMORY_LIMIT_MB_ENV_VAR)
+            .and_then(|limit| limit.parse::<i64>().ok())
+            .map(|limit| limit * 1024 * 1024);
+
+        Resource::builder_empty()
+            .with_attributes(
+                [
+                    Some(KeyValue::new(
+                        semconv::attribute::CLOUD_PROVIDER,
+                        CLOUD_PROVIDER_AZURE,
+                    )),
+                    Some(KeyValue::new(
+                        semconv::attribute::CLOUD_PLATFORM,
+                        CLOUD_PLATFORM_AZURE_FUNCTIONS,
+                    )),
+                    Some(KeyValue::new(semconv::attribute::FAAS_NAME, site_name)),
+                    Some(KeyValue::new(
+                        semconv::attribute::FAAS_VERSION,
+                        extension_version,
+                    )),
+                    non_empty_env(REGION_NAME_ENV_VAR)
+                        .map(|region| KeyValue::new(semconv::attribute::CLOUD_REGION, region)),
+                    app_service_resource_id()
+                        .map(|id| KeyValue::new(semconv::attribute::CLOUD_RESOURCE_ID, id)),
+                    non_empty_env(WEBSITE_INSTANCE_ID_ENV_VAR)
+                        .map(|id| KeyValue::new(semconv::attribute::FAAS_INSTANCE, id)),
+                    max_memory
+                        .map(|limit| KeyValue::new(semconv::attribute::FAAS_MAX_MEMORY, limit)),
+                ]
+                .into_iter()
+                .flatten(),
+            )
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::azure::WEBSITE_OWNER_NAME_ENV_VAR;
+    use opentelemetry::{Key, Value};
+
+    #[test]
+    fn test_azure_functions_resource_detector() {
+        temp_env::with_vars(
+            [
+                (WEBSITE_SITE_NAME_ENV_VAR, Some("my-function-app")),
+                (FUNCTIONS_EXTENSION_VERSION_ENV_VAR, Some("~4")),
+                (REGION_NAME_ENV_VAR, Some("East US")),
+                (WEBSITE_INSTANCE_ID_ENV_VAR, Some("instance-1")),
+                (WEBSITE_MEMORY_LIMIT_MB_ENV_VAR, Some("1536")),
+                (WEBSITE_OWNER_NAME_ENV_VAR, None),
+            ],
+            || {
+                let resource = AzureFunctionsResourceDetector.detect();
+
+                assert_eq!(resource.len(), 7);
+                assert_eq!(
+                    resource.get(&Key::from_static_str(semconv::attribute::CLOUD_PLATFORM)),
+                    Some(Value::from("azure_functions"))
+                );
+                assert_eq!(
+                    resource.get(&Key::from_static_str(semconv::attribute::FAAS_MAX_MEMORY)),
+                    Some(Value::from(1536 * 1024 * 1024_i64))
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_azure_functions_resource_detector_on_app_service() {
+        temp_env::with_vars(
+            [
+                (WEBSITE_SITE_NAME_ENV_VAR, Some("my-site")),
+                (FUNCTIONS_EXTENSION_VERSION_ENV_VAR, None),
+            ],
+            || {
+                let resource = AzureFunctionsResourceDetector.detect();
+                assert_eq!(resource.len(), 0);
+            },
+        );
+    }
+}
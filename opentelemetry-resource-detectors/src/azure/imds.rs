@@ -0,0 +1,151 @@
+//! Minimal client for the Azure Instance Metadata Service (IMDS).
+//!
+//! IMDS is a plain HTTP endpoint on a link-local address, so a blocking request over a
+//! [`TcpStream`] is enough and keeps the crate free of an HTTP client dependency.
+use opentelemetry::otel_debug;
+use serde::Deserialize;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+pub(super) const IMDS_ENDPOINT: &str = "169.254.169.254:80";
+pub(super) const DEFAULT_IMDS_TIMEOUT: Duration = Duration::from_secs(1);
+
+const COMPUTE_METADATA_PATH: &str = "/metadata/instance/compute?api-version=2021-12-13&format=json";
+
+/// Subset of the IMDS `compute` document used by the detectors.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(super) struct ComputeMetadata {
+    pub(super) location: String,
+    pub(super) name: String,
+    pub(super) os_type: String,
+    pub(super) resource_group_name: String,
+    pub(super) resource_id: String,
+    pub(super) sku: String,
+    pub(super) version: String,
+    pub(super) vm_id: String,
+    pub(super) vm_scale_set_name: String,
+    pub(super) vm_size: String,
+    pub(super) tags_list: Vec<ComputeTag>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(super) struct ComputeTag {
+    pub(super) name: String,
+    pub(super) value: String,
+}
+
+/// Fetch the `compute` metadata, returning `None` when IMDS is unreachable or answers unexpectedly.
+pub(super) fn fetch_compute_metadata(endpoint: &str, timeout: Duration) -> Option<ComputeMetadata> {
+    let body = match get(endpoint, COMPUTE_METADATA_PATH, timeout) {
+        Ok(body) => body,
+        Err(err) => {
+            otel_debug!(name: "AzureImds.RequestFailed", endpoint = endpoint, error = err.to_string());
+            return None;
+        }
+    };
+
+    match serde_json::from_str(&body) {
+        Ok(metadata) => Some(metadata),
+        Err(err) => {
+            otel_debug!(name: "AzureImds.InvalidResponse", endpoint = endpoint, error = err.to_string());
+            None
+        }
+    }
+}
+
+fn get(endpoint: &str, path: &str, timeout: Duration) -> io::Result<String> {
+    let addr = endpoint.to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "endpoint resolved to no address",
+        )
+    })?;
+
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    // HTTP/1.0 makes the server close the connection after the body and rules out chunked encoding.
+    write!(
+        stream,
+        "GET {path} HTTP/1.0\r\nHost: {endpoint}\r\nMetadata: true\r\n\r\n"
+    )?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP response"))?;
+    match head.split_whitespace().nth(1) {
+        Some("200") => Ok(body.to_owned()),
+        status => Err(io::Error::other(format!(
+            "unexpected HTTP status {}",
+            status.unwrap_or("<none>")
+        ))),
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    pub(crate) const COMPUTE_METADATA: &str = r#"{
+        "location": "westeurope",
+        "name": "my-vm",
+        "osType": "Linux",
+        "resourceGroupName": "MC_my-rg_my-cluster_westeurope",
+        "resourceId": "/subscriptions/00000000-0000-0000-0000-000000000000/resourceGroups/MC_my-rg_my-cluster_westeurope/providers/Microsoft.Compute/virtualMachineScaleSets/aks-nodepool1-12345678-vmss/virtualMachines/0",
+        "sku": "22_04-lts-gen2",
+        "version": "22.04.202401010",
+        "vmId": "02aab8a4-74ef-476e-8182-f6d2ba4166a6",
+        "vmScaleSetName": "aks-nodepool1-12345678-vmss",
+        "vmSize": "Standard_D2s_v3",
+        "tagsList": [{"name": "aks-managed-poolName", "value": "nodepool1"}]
+    }"#;
+
+    /// Serve a single canned HTTP response and return the address to query.
+    pub(crate) fn serve_once(response: String) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_parse_compute_metadata() {
+        let metadata: ComputeMetadata = serde_json::from_str(COMPUTE_METADATA).unwrap();
+        assert_eq!(metadata.location, "westeurope");
+        assert_eq!(metadata.vm_id, "02aab8a4-74ef-476e-8182-f6d2ba4166a6");
+        assert_eq!(metadata.tags_list.len(), 1);
+    }
+
+    #[test]
+    fn test_fetch_compute_metadata_non_200() {
+        let endpoint =
+            serve_once("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string());
+        assert!(fetch_compute_metadata(&endpoint, DEFAULT_IMDS_TIMEOUT).is_none());
+    }
+
+    #[test]
+    fn test_fetch_compute_metadata_unreachable() {
+        // Bind and drop a listener to get a local port that refuses connections.
+        let endpoint = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        assert!(fetch_compute_metadata(&endpoint, DEFAULT_IMDS_TIMEOUT).is_none());
+    }
+}
@@ -0,0 +1,48 @@
+//! Azure resource detectors
+//!
+//! Detect the Azure environment the application is running in.
+//!
+//! - [`AzureVmResourceDetector`] - query the Instance Metadata Service (IMDS) of a virtual machine.
+//! - [`AzureAppServiceResourceDetector`] - read the `WEBSITE_*` environment variables of App Service.
+//! - [`AzureFunctionsResourceDetector`] - read the environment variables of Azure Functions.
+//! - [`AzureAksResourceDetector`] - combine IMDS and Kubernetes hints on AKS nodes.
+mod aks;
+mod app_service;
+mod functions;
+mod imds;
+mod vm;
+
+pub use aks::AzureAksResourceDetector;
+pub use app_service::AzureAppServiceResourceDetector;
+pub use functions::AzureFunctionsResourceDetector;
+pub use vm::AzureVmResourceDetector;
+
+use std::env;
+
+const CLOUD_PROVIDER_AZURE: &str = "azure";
+
+const WEBSITE_SITE_NAME_ENV_VAR: &str = "WEBSITE_SITE_NAME";
+const WEBSITE_OWNER_NAME_ENV_VAR: &str = "WEBSITE_OWNER_NAME";
+const WEBSITE_RESOURCE_GROUP_ENV_VAR: &str = "WEBSITE_RESOURCE_GROUP";
+const WEBSITE_INSTANCE_ID_ENV_VAR: &str = "WEBSITE_INSTANCE_ID";
+const REGION_NAME_ENV_VAR: &str = "REGION_NAME";
+const FUNCTIONS_EXTENSION_VERSION_ENV_VAR: &str = "FUNCTIONS_EXTENSION_VERSION";
+
+/// Read an environment variable, treating an empty value as absent.
+fn non_empty_env(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// Build the ARM resource ID of an App Service site (also used by Azure Functions).
+///
+/// `WEBSITE_OWNER_NAME` has the form `<subscription id>+<webspace>`.
+fn app_service_resource_id() -> Option<String> {
+    let owner = non_empty_env(WEBSITE_OWNER_NAME_ENV_VAR)?;
+    let subscription_id = owner.split('+').next().filter(|id| !id.is_empty())?;
+    let resource_group = non_empty_env(WEBSITE_RESOURCE_GROUP_ENV_VAR)?;
+    let site_name = non_empty_env(WEBSITE_SITE_NAME_ENV_VAR)?;
+
+    Some(format!(
+        "/subscriptions/{subscription_id}/resourceGroups/{resource_group}/providers/Microsoft.Web/sites/{site_name}"
+    ))
+}
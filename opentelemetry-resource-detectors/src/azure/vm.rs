@@ -0,0 +1,146 @@
+use super::imds::{self, ComputeMetadata};
+use super::CLOUD_PROVIDER_AZURE;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::resource::{Resource, ResourceDetector};
+use opentelemetry_semantic_conventions as semconv;
+use std::time::Duration;
+
+const CLOUD_PLATFORM_AZURE_VM: &str = "azure_vm";
+const AZURE_VM_SCALESET_NAME: &str = "azure.vm.scaleset.name";
+const AZURE_VM_SKU: &str = "azure.vm.sku";
+
+/// Detect Azure virtual machine information.
+///
+/// The detector queries the [Instance Metadata Service] and returns an empty resource when it is
+/// unreachable, so it is safe to use off Azure. The request is bounded by a timeout of one second
+/// by default, see [`AzureVmResourceDetector::with_timeout`].
+///
+/// This resource detector returns the following information:
+///
+/// - Cloud provider and platform (`cloud.provider`, `cloud.platform`).
+/// - Region (`cloud.region`) and ARM resource ID (`cloud.resource_id`).
+/// - VM ID, name and size (`host.id`, `host.name`, `host.type`).
+/// - OS type and image version (`os.type`, `os.version`).
+/// - Scale set name and image SKU (`azure.vm.scaleset.name`, `azure.vm.sku`).
+///
+/// [Instance Metadata Service]: https://learn.microsoft.com/azure/virtual-machines/instance-metadata-service
+#[derive(Debug)]
+pub struct AzureVmResourceDetector {
+    endpoint: String,
+    timeout: Duration,
+}
+
+impl AzureVmResourceDetector {
+    /// Create a detector querying the default IMDS endpoint.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum time spent connecting to and reading from IMDS.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Default for AzureVmResourceDetector {
+    fn default() -> Self {
+        Self {
+            endpoint: imds::IMDS_ENDPOINT.to_string(),
+            timeout: imds::DEFAULT_IMDS_TIMEOUT,
+        }
+    }
+}
+
+impl ResourceDetector for AzureVmResourceDetector {
+    fn detect(&self) -> Resource {
+        let Some(metadata) = imds::fetch_compute_metadata(&self.endpoint, self.timeout) else {
+            return Resource::builder_empty().build();
+        };
+
+        Resource::builder_empty()
+            .with_attributes(vm_attributes(metadata))
+            .build()
+    }
+}
+
+fn vm_attributes(metadata: ComputeMetadata) -> Vec<KeyValue> {
+    [
+        (semconv::attribute::CLOUD_REGION, metadata.location),
+        (semconv::attribute::CLOUD_RESOURCE_ID, metadata.resource_id),
+        (semconv::attribute::HOST_ID, metadata.vm_id),
+        (semconv::attribute::HOST_NAME, metadata.name),
+        (semconv::attribute::HOST_TYPE, metadata.vm_size),
+        (semconv::attribute::OS_TYPE, metadata.os_type.to_lowercase()),
+        (semconv::attribute::OS_VERSION, metadata.version),
+        (AZURE_VM_SCALESET_NAME, metadata.vm_scale_set_name),
+        (AZURE_VM_SKU, metadata.sku),
+    ]
+    .into_iter()
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(key, value)| KeyValue::new(key, value))
+    .chain([
+        KeyValue::new(semconv::attribute::CLOUD_PROVIDER, CLOUD_PROVIDER_AZURE),
+        KeyValue::new(semconv::attribute::CLOUD_PLATFORM, CLOUD_PLATFORM_AZURE_VM),
+    ])
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::azure::imds::tests::{serve_once, COMPUTE_METADATA};
+    use opentelemetry::{Key, Value};
+
+    #[test]
+    fn test_azure_vm_resource_detector() {
+        let endpoint = serve_once(format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{COMPUTE_METADATA}"
+        ));
+        let resource = AzureVmResourceDetector {
+            endpoint,
+            timeout: Duration::from_secs(5),
+        }
+        .detect();
+
+        assert_eq!(resource.len(), 11);
+        assert_eq!(
+            resource.get(&Key::from_static_str(semconv::attribute::CLOUD_PLATFORM)),
+            Some(Value::from("azure_vm"))
+        );
+        assert_eq!(
+            resource.get(&Key::from_static_str(semconv::attribute::HOST_ID)),
+            Some(Value::from("02aab8a4-74ef-476e-8182-f6d2ba4166a6"))
+        );
+        assert_eq!(
+            resource.get(&Key::from_static_str(semconv::attribute::OS_TYPE)),
+            Some(Value::from("linux"))
+        );
+        assert_eq!(
+            resource.get(&Key::from_static_str(AZURE_VM_SCALESET_NAME)),
+            Some(Value::from("aks-nodepool1-12345678-vmss"))
+        );
+    }
+
+    #[test]
+    fn test_azure_vm_resource_detector_skips_empty_fields() {
+        let attributes = vm_attributes(ComputeMetadata {
+            location: "eastus".to_string(),
+            ..Default::default()
+        });
+
+        assert_eq!(attributes.len(), 3);
+    }
+
+    #[test]
+    fn test_azure_vm_resource_detector_off_azure() {
+        let endpoint = serve_once("HTTP/1.1 500 Internal Server Error\r\n\r\n".to_string());
+        let resource = AzureVmResourceDetector {
+            endpoint,
+            timeout: Duration::from_secs(5),
+        }
+        .detect();
+
+        assert_eq!(resource.len(), 0);
+    }
+}
@@ -0,0 +1,381 @@
+//! Cached resource detection
+//!
+//! Short-lived processes such as CLIs, cron jobs or CI steps detect the same resource on every
+//! run, paying the latency of the cloud metadata endpoints each time. [`CachingDetector`] keeps
+//! the resource of a detector in a local file and reuses it until it expires.
+use opentelemetry::{otel_debug, otel_warn, Array, KeyValue, StringValue, Value};
+use opentelemetry_sdk::resource::ResourceDetector;
+use opentelemetry_sdk::Resource;
+use serde_json::{json, Map, Value as Json};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+const DEFAULT_NEGATIVE_TTL: Duration = Duration::from_secs(60);
+const CACHE_VERSION: u64 = 1;
+
+/// Run a detector at most once per TTL, reusing the resource it detected from a cache file.
+///
+/// The file holds the attributes and schema URL of the detected resource, and when they were
+/// detected. An empty resource, as returned by the detectors when detection fails, is only
+/// reused for the shorter negative TTL, a minute by default, so a transient failure isn't
+/// remembered for long while processes running outside of a cloud still don't probe its metadata
+/// endpoint on every run. Cache files that are missing, expired, written in the future or
+/// unreadable are ignored, the detector runs and the file is replaced.
+///
+/// Use a file per detector, in a directory only writable by the user running the process, as
+/// the attributes are read back as they are.
+///
+/// ```no_run
+/// use opentelemetry_resource_detectors::{CachingDetector, HostResourceDetector};
+/// use opentelemetry_sdk::Resource;
+/// use std::time::Duration;
+///
+/// let path = std::env::temp_dir().join("otel-host-resource.json");
+/// let detector = CachingDetector::new(HostResourceDetector::default(), &path)
+///     .with_ttl(Duration::from_secs(600));
+///
+/// let resource = Resource::builder().with_detector(Box::new(detector)).build();
+/// ```
+pub struct CachingDetector {
+    detector: Box<dyn ResourceDetector + Send + Sync>,
+    path: PathBuf,
+    ttl: Duration,
+    negative_ttl: Duration,
+}
+
+impl CachingDetector {
+    /// Cache the resource of `detector` in the file at `path` for an hour.
+    pub fn new<D>(detector: D, path: impl Into<PathBuf>) -> Self
+    where
+        D: ResourceDetector + Send + Sync + 'static,
+    {
+        CachingDetector {
+            detector: Box::new(detector),
+            path: path.into(),
+            ttl: DEFAULT_TTL,
+            negative_ttl: DEFAULT_NEGATIVE_TTL,
+        }
+    }
+
+    /// Set how long a cached resource is reused.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set how long a cached empty resource is reused. Empty resources aren't cached when it is
+    /// zero.
+    pub fn with_negative_ttl(mut self, negative_ttl: Duration) -> Self {
+        self.negative_ttl = negative_ttl;
+        self
+    }
+
+    fn ttl(&self, resource: &Resource) -> Duration {
+        if resource.is_empty() {
+            self.negative_ttl
+        } else {
+            self.ttl
+        }
+    }
+
+    /// The path of the cache file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Remove the cache file, so the next detection runs the detector, e.g. after the process
+    /// moved to another host.
+    pub fn invalidate(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    fn read(&self, now: SystemTime) -> Option<Resource> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    otel_debug!(name: "CachingDetector.ReadFailed", path = self.path.display().to_string(), error = err.to_string());
+                }
+                return None;
+            }
+        };
+        let Some(cache) = decode(&contents) else {
+            otel_debug!(name: "CachingDetector.InvalidCache", path = self.path.display().to_string());
+            return None;
+        };
+        let age = now.duration_since(cache.detected_at).ok()?;
+        (age < self.ttl(&cache.resource)).then_some(cache.resource)
+    }
+
+    fn write(&self, resource: &Resource, now: SystemTime) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let detected_at = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        // Concurrent processes replace the file at once instead of reading a partial write.
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(format!(".{}.tmp", std::process::id()));
+        fs::write(&tmp, encode(resource, detected_at))?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+impl ResourceDetector for CachingDetector {
+    fn detect(&self) -> Resource {
+        let now = SystemTime::now();
+        if let Some(resource) = self.read(now) {
+            otel_debug!(name: "CachingDetector.CacheHit", path = self.path.display().to_string());
+            return resource;
+        }
+        let resource = self.detector.detect();
+        if self.ttl(&resource).is_zero() {
+            return resource;
+        }
+        if let Err(err) = self.write(&resource, now) {
+            otel_warn!(name: "CachingDetector.WriteFailed", path = self.path.display().to_string(), error = err.to_string());
+        }
+        resource
+    }
+}
+
+struct Cache {
+    detected_at: SystemTime,
+    resource: Resource,
+}
+
+fn encode(resource: &Resource, detected_at: u64) -> String {
+    let attributes: Vec<Json> = resource
+        .iter()
+        .filter_map(|(key, value)| {
+            let (kind, value) = match value {
+                Value::Bool(value) => ("bool", json!(value)),
+                Value::I64(value) => ("int", json!(value)),
+                Value::F64(value) => ("double", encode_double(*value)),
+                Value::String(value) => ("string", json!(value.as_str())),
+                Value::Array(Array::Bool(values)) => ("bool_array", json!(values)),
+                Value::Array(Array::I64(values)) => ("int_array", json!(values)),
+                Value::Array(Array::F64(values)) => (
+                    "double_array",
+                    Json::Array(values.iter().copied().map(encode_double).collect()),
+                ),
+                Value::Array(Array::String(values)) => (
+                    "string_array",
+                    json!(values.iter().map(StringValue::as_str).collect::<Vec<_>>()),
+                ),
+                _ => return None,
+            };
+            let mut attribute = Map::new();
+            attribute.insert("key".to_string(), json!(key.as_str()));
+            attribute.insert(kind.to_string(), value);
+            Some(Json::Object(attribute))
+        })
+        .collect();
+    json!({
+        "version": CACHE_VERSION,
+        "detected_at": detected_at,
+        "schema_url": resource.schema_url(),
+        "attributes": attributes,
+    })
+    .to_string()
+}
+
+/// JSON has no NaN nor infinities, they are written as the strings `f64` parses back.
+fn encode_double(value: f64) -> Json {
+    if value.is_finite() {
+        json!(value)
+    } else {
+        json!(value.to_string())
+    }
+}
+
+fn decode_double(value: &Json) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str()?.parse::<f64>().ok())
+}
+
+fn decode(contents: &str) -> Option<Cache> {
+    let cache: Json = serde_json::from_str(contents).ok()?;
+    if cache.get("version")?.as_u64()? != CACHE_VERSION {
+        return None;
+    }
+    let detected_at = cache.get("detected_at")?.as_u64()?;
+    let detected_at = UNIX_EPOCH.checked_add(Duration::from_secs(detected_at))?;
+    let attributes = cache
+        .get("attributes")?
+        .as_array()?
+        .iter()
+        .map(decode_attribute)
+        .collect::<Option<Vec<_>>>()?;
+    let resource = match cache.get("schema_url").and_then(Json::as_str) {
+        Some(schema_url) => Resource::builder_empty()
+            .with_schema_url(attributes, schema_url.to_string())
+            .build(),
+        None => Resource::builder_empty()
+            .with_attributes(attributes)
+            .build(),
+    };
+    Some(Cache {
+        detected_at,
+        resource,
+    })
+}
+
+fn decode_attribute(attribute: &Json) -> Option<KeyValue> {
+    let key = attribute.get("key")?.as_str()?.to_string();
+    let array = |kind: &str| attribute.get(kind).and_then(Json::as_array);
+    let value = if let Some(value) = attribute.get("bool") {
+        Value::Bool(value.as_bool()?)
+    } else if let Some(value) = attribute.get("int") {
+        Value::I64(value.as_i64()?)
+    } else if let Some(value) = attribute.get("double") {
+        Value::F64(decode_double(value)?)
+    } else if let Some(value) = attribute.get("string") {
+        Value::from(value.as_str()?.to_string())
+    } else if let Some(values) = array("bool_array") {
+        Value::Array(Array::Bool(
+            values.iter().map(Json::as_bool).collect::<Option<_>>()?,
+        ))
+    } else if let Some(values) = array("int_array") {
+        Value::Array(Array::I64(
+            values.iter().map(Json::as_i64).collect::<Option<_>>()?,
+        ))
+    } else if let Some(values) = array("double_array") {
+        Value::Array(Array::F64(
+            values.iter().map(decode_double).collect::<Option<_>>()?,
+        ))
+    } else if let Some(values) = array("string_array") {
+        Value::Array(Array::String(
+            values
+                .iter()
+                .map(|value| value.as_str().map(|value| value.to_string().into()))
+                .collect::<Option<_>>()?,
+        ))
+    } else {
+        return None;
+    };
+    Some(KeyValue::new(key, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct CountingDetector(Arc<AtomicUsize>);
+
+    impl ResourceDetector for CountingDetector {
+        fn detect(&self) -> Resource {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Resource::builder_empty()
+                .with_schema_url(
+                    [
+                        KeyValue::new("cloud.provider", "gcp"),
+                        KeyValue::new("host.cpu.count", 8_i64),
+                        KeyValue::new("host.cpu.ratio", 0.5),
+                        KeyValue::new("host.spot", true),
+                        KeyValue::new(
+                            "host.ip",
+                            Value::Array(Array::String(vec!["10.0.0.1".into(), "::1".into()])),
+                        ),
+                    ],
+                    "https://opentelemetry.io/schemas/1.26.0",
+                )
+                .build()
+        }
+    }
+
+    fn cache_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "otel-resource-cache-{}-{name}.json",
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn test_cache_hit() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let detector = CachingDetector::new(CountingDetector(count.clone()), cache_path("hit"));
+        detector.invalidate().unwrap();
+
+        let detected = detector.detect();
+        let cached = detector.detect();
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+        assert_eq!(cached, detected);
+        assert_eq!(
+            cached.schema_url(),
+            Some("https://opentelemetry.io/schemas/1.26.0")
+        );
+
+        detector.invalidate().unwrap();
+        detector.detect();
+        assert_eq!(count.load(Ordering::Relaxed), 2);
+        detector.invalidate().unwrap();
+        assert!(!detector.path().exists());
+    }
+
+    #[test]
+    fn test_cache_expiry() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let detector = CachingDetector::new(CountingDetector(count.clone()), cache_path("ttl"))
+            .with_ttl(Duration::from_secs(60));
+        let now = SystemTime::now();
+        detector
+            .write(&CountingDetector(Arc::default()).detect(), now)
+            .unwrap();
+
+        assert!(detector.read(now + Duration::from_secs(30)).is_some());
+        assert!(detector.read(now + Duration::from_secs(60)).is_none());
+        // Written in the future, e.g. before the clock was set back.
+        assert!(detector.read(now - Duration::from_secs(30)).is_none());
+
+        let detector = detector.with_negative_ttl(Duration::from_secs(10));
+        detector
+            .write(&Resource::builder_empty().build(), now)
+            .unwrap();
+        assert!(detector.read(now + Duration::from_secs(5)).is_some());
+        assert!(detector.read(now + Duration::from_secs(10)).is_none());
+
+        fs::write(detector.path(), "{\"version\":1}").unwrap();
+        assert!(detector.read(now).is_none());
+        let out_of_range = format!(
+            "{{\"version\":1,\"detected_at\":{},\"attributes\":[]}}",
+            u64::MAX
+        );
+        fs::write(detector.path(), out_of_range).unwrap();
+        assert!(detector.read(now).is_none());
+        detector.invalidate().unwrap();
+    }
+
+    #[test]
+    fn test_non_finite_doubles() {
+        let resource = Resource::builder_empty()
+            .with_attributes([
+                KeyValue::new("nan", f64::NAN),
+                KeyValue::new(
+                    "infinities",
+                    Value::Array(Array::F64(vec![f64::INFINITY, f64::NEG_INFINITY, 1.5])),
+                ),
+            ])
+            .build();
+        let cache = decode(&encode(&resource, 0)).unwrap();
+
+        let value = |key: &'static str| cache.resource.get(&opentelemetry::Key::from(key));
+        assert!(matches!(value("nan"), Some(Value::F64(value)) if value.is_nan()));
+        assert_eq!(
+            value("infinities"),
+            Some(Value::Array(Array::F64(vec![
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                1.5
+            ])))
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Composite resource detector
+//!
+//! Run several detectors concurrently, each bounded by its own timeout.
+use crate::merge_all;
+use opentelemetry::{otel_debug, otel_warn};
+use opentelemetry_sdk::resource::ResourceDetector;
+use opentelemetry_sdk::Resource;
+use std::any::type_name;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_DETECTOR_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Entry {
+    name: &'static str,
+    detector: Arc<dyn ResourceDetector + Send + Sync>,
+    timeout: Option<Duration>,
+}
+
+/// Run a list of detectors in parallel and merge their results.
+///
+/// Every detector runs on its own thread, so slow probes such as cloud metadata endpoints don't
+/// add up when the application isn't running on that cloud. A detector that doesn't finish
+/// within its timeout, or panics, is skipped and reported through internal logs; its thread is
+/// left to finish in the background.
+///
+/// Results are merged with [`merge_all`] in the order the detectors were added: when two detectors
+/// report the same attribute, the value of the detector added last wins, like
+/// [`ResourceBuilder::with_detectors`](opentelemetry_sdk::resource::ResourceBuilder::with_detectors).
+///
+/// ```
+/// use opentelemetry_resource_detectors::{CompositeDetector, HostResourceDetector, OsResourceDetector};
+/// use opentelemetry_sdk::Resource;
+/// use std::time::Duration;
+///
+/// let detector = CompositeDetector::new()
+///     .with_timeout(Duration::from_secs(1))
+///     .with_detector(OsResourceDetector)
+///     .with_detector_timeout(HostResourceDetector::default(), Duration::from_millis(200));
+///
+/// let resource = Resource::builder().with_detector(Box::new(detector)).build();
+/// ```
+pub struct CompositeDetector {
+    detectors: Vec<Entry>,
+    timeout: Duration,
+}
+
+impl CompositeDetector {
+    /// Create an empty composite detector with a default per-detector timeout of 5 seconds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the timeout applied to detectors added without an explicit one.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Add a detector bounded by the default timeout.
+    pub fn with_detector<D>(mut self, detector: D) -> Self
+    where
+        D: ResourceDetector + Send + Sync + 'static,
+    {
+        self.detectors.push(Entry {
+            name: type_name::<D>(),
+            detector: Arc::new(detector),
+            timeout: None,
+        });
+        self
+    }
+
+    /// Add a detector bounded by its own timeout.
+    pub fn with_detector_timeout<D>(mut self, detector: D, timeout: Duration) -> Self
+    where
+        D: ResourceDetector + Send + Sync + 'static,
+    {
+        self.detectors.push(Entry {
+            name: type_name::<D>(),
+            detector: Arc::new(detector),
+            timeout: Some(timeout),
+        });
+        self
+    }
+}
+
+impl Default for CompositeDetector {
+    fn default() -> Self {
+        Self {
+            detectors: Vec::new(),
+            timeout: DEFAULT_DETECTOR_TIMEOUT,
+        }
+    }
+}
+
+impl ResourceDetector for CompositeDetector {
+    fn detect(&self) -> Resource {
+        let start = Instant::now();
+        let (sender, receiver) = mpsc::channel();
+
+        let mut deadlines = Vec::with_capacity(self.detectors.len());
+        for (index, entry) in self.detectors.iter().enumerate() {
+            deadlines.push(Some(start + entry.timeout.unwrap_or(self.timeout)));
+
+            let detector = Arc::clone(&entry.detector);
+            let sender = sender.clone();
+            let spawned = thread::Builder::new()
+                .name("otel-resource-detector".to_string())
+                .spawn(move || {
+                    let result = catch_unwind(AssertUnwindSafe(|| detector.detect()));
+                    // The receiver is gone if the detector timed out; nothing left to do.
+                    let _ = sender.send((index, result.ok(), start.elapsed()));
+                });
+            if let Err(err) = spawned {
+                otel_warn!(name: "CompositeDetector.SpawnFailed", detector = entry.name, error = err.to_string());
+                deadlines[index] = None;
+            }
+        }
+        drop(sender);
+
+        let mut results: Vec<Option<Resource>> = vec![None; self.detectors.len()];
+        while let Some(deadline) = deadlines.iter().flatten().min().copied() {
+            match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok((index, result, elapsed)) => {
+                    let name = self.detectors[index].name;
+                    deadlines[index] = None;
+                    match result {
+                        Some(resource) => {
+                            otel_debug!(name: "CompositeDetector.DetectorCompleted", detector = name, elapsed_ms = elapsed.as_millis() as u64, attributes = resource.len() as u64);
+                            results[index] = Some(resource);
+                        }
+                        None => {
+                            otel_warn!(name: "CompositeDetector.DetectorPanicked", detector = name, elapsed_ms = elapsed.as_millis() as u64);
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    let now = Instant::now();
+                    for (index, deadline) in deadlines.iter_mut().enumerate() {
+                        if deadline.is_some_and(|deadline| deadline <= now) {
+                            *deadline = None;
+                            otel_warn!(name: "CompositeDetector.DetectorTimedOut", detector = self.detectors[index].name, elapsed_ms = start.elapsed().as_millis() as u64);
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        otel_debug!(name: "CompositeDetector.DetectionCompleted", detectors = self.detectors.len() as u64, elapsed_ms = start.elapsed().as_millis() as u64);
+
+        merge_all(results.into_iter().flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompositeDetector;
+    use opentelemetry::{Key, KeyValue, Value};
+    use opentelemetry_sdk::resource::ResourceDetector;
+    use opentelemetry_sdk::Resource;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    struct StaticDetector {
+        attributes: Vec<KeyValue>,
+        delay: Duration,
+    }
+
+    impl StaticDetector {
+        fn new(key: &'static str, value: &'static str) -> Self {
+            Self {
+                attributes: vec![KeyValue::new(key, value)],
+                delay: Duration::ZERO,
+            }
+        }
+
+        fn with_delay(mut self, delay: Duration) -> Self {
+            self.delay = delay;
+            self
+        }
+    }
+
+    impl ResourceDetector for StaticDetector {
+        fn detect(&self) -> Resource {
+            thread::sleep(self.delay);
+            Resource::builder_empty()
+                .with_attributes(self.attributes.clone())
+                .build()
+        }
+    }
+
+    struct PanickingDetector;
+
+    impl ResourceDetector for PanickingDetector {
+        fn detect(&self) -> Resource {
+            panic!("detector failure")
+        }
+    }
+
+    #[test]
+    fn test_composite_detector_merges_by_precedence() {
+        let resource = CompositeDetector::new()
+            .with_detector(StaticDetector::new("key", "first"))
+            .with_detector(
+                StaticDetector::new("key", "second").with_delay(Duration::from_millis(50)),
+            )
+            .with_detector(StaticDetector::new("other", "value"))
+            .detect();
+
+        assert_eq!(resource.len(), 2);
+        assert_eq!(
+            resource.get(&Key::from_static_str("key")),
+            Some(Value::from("second"))
+        );
+    }
+
+    struct SchemaDetector(&'static str);
+
+    impl ResourceDetector for SchemaDetector {
+        fn detect(&self) -> Resource {
+            Resource::builder_empty()
+                .with_schema_url([KeyValue::new("schema", self.0)], self.0)
+                .build()
+        }
+    }
+
+    #[test]
+    fn test_composite_detector_keeps_schema_url() {
+        let resource = CompositeDetector::new()
+            .with_detector(SchemaDetector("https://opentelemetry.io/schemas/1.26.0"))
+            .with_detector(StaticDetector::new("key", "value"))
+            .detect();
+
+        assert_eq!(
+            resource.schema_url(),
+            Some("https://opentelemetry.io/schemas/1.26.0")
+        );
+        assert_eq!(resource.len(), 2);
+    }
+
+    #[test]
+    fn test_composite_detector_runs_in_parallel() {
+        let start = Instant::now();
+        let resource = CompositeDetector::new()
+            .with_detector(StaticDetector::new("a", "a").with_delay(Duration::from_millis(300)))
+            .with_detector(StaticDetector::new("b", "b").with_delay(Duration::from_millis(300)))
+            .with_detector(StaticDetector::new("c", "c").with_delay(Duration::from_millis(300)))
+            .detect();
+
+        assert_eq!(resource.len(), 3);
+        assert!(start.elapsed() < Duration::from_millis(900));
+    }
+
+    #[test]
+    fn test_composite_detector_skips_timed_out_detector() {
+        let start = Instant::now();
+        let resource = CompositeDetector::new()
+            .with_timeout(Duration::from_secs(5))
+            .with_detector(StaticDetector::new("fast", "value"))
+            .with_detector_timeout(
+                StaticDetector::new("slow", "value").with_delay(Duration::from_secs(5)),
+                Duration::from_millis(100),
+            )
+            .detect();
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(resource.len(), 1);
+        assert!(resource.get(&Key::from_static_str("slow")).is_none());
+    }
+
+    #[test]
+    fn test_composite_detector_skips_panicking_detector() {
+        let resource = CompositeDetector::new()
+            .with_detector(PanickingDetector)
+            .with_detector(StaticDetector::new("key", "value"))
+            .detect();
+
+        assert_eq!(resource.len(), 1);
+    }
+
+    #[test]
+    fn test_composite_detector_empty() {
+        assert_eq!(CompositeDetector::new().detect().len(), 0);
+    }
+}
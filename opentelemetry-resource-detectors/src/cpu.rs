@@ -0,0 +1,266 @@
+//! CPU information for the host resource detector.
+//!
+//! Only compiled with the `host-cpu` feature, as reading the processor description may spawn a
+//! process (`sysctl` on macOS, `reg` on Windows) which isn't desirable for every application.
+use opentelemetry::KeyValue;
+use opentelemetry_semantic_conventions as semconv;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::process::Command;
+
+/// Processor description following the `host.cpu.*` semantic conventions.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct CpuInfo {
+    pub(crate) vendor_id: Option<String>,
+    pub(crate) family: Option<String>,
+    pub(crate) model_id: Option<String>,
+    pub(crate) model_name: Option<String>,
+    pub(crate) stepping: Option<String>,
+    pub(crate) cache_l2_size: Option<i64>,
+}
+
+impl CpuInfo {
+    pub(crate) fn into_attributes(self) -> impl Iterator<Item = KeyValue> {
+        [
+            self.vendor_id
+                .map(|value| KeyValue::new(semconv::attribute::HOST_CPU_VENDOR_ID, value)),
+            self.family
+                .map(|value| KeyValue::new(semconv::attribute::HOST_CPU_FAMILY, value)),
+            self.model_id
+                .map(|value| KeyValue::new(semconv::attribute::HOST_CPU_MODEL_ID, value)),
+            self.model_name
+                .map(|value| KeyValue::new(semconv::attribute::HOST_CPU_MODEL_NAME, value)),
+            self.stepping
+                .map(|value| KeyValue::new(semconv::attribute::HOST_CPU_STEPPING, value)),
+            self.cache_l2_size
+                .map(|value| KeyValue::new(semconv::attribute::HOST_CPU_CACHE_L2_SIZE, value)),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn cpu_info() -> Option<CpuInfo> {
+    use std::fs::read_to_string;
+
+    let mut info = parse_proc_cpuinfo(&read_to_string("/proc/cpuinfo").ok()?);
+    info.cache_l2_size = l2_cache_size_from_sysfs();
+    Some(info)
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn cpu_info() -> Option<CpuInfo> {
+    let output = Command::new("sysctl")
+        .arg("machdep.cpu.vendor")
+        .arg("machdep.cpu.family")
+        .arg("machdep.cpu.model")
+        .arg("machdep.cpu.brand_string")
+        .arg("machdep.cpu.stepping")
+        .arg("hw.l2cachesize")
+        .output()
+        .ok()?
+        .stdout;
+    Some(parse_sysctl(&String::from_utf8(output).ok()?))
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) fn cpu_info() -> Option<CpuInfo> {
+    let output = Command::new("reg")
+        .arg("query")
+        .arg(r"HKEY_LOCAL_MACHINE\HARDWARE\DESCRIPTION\System\CentralProcessor\0")
+        .output()
+        .ok()?
+        .stdout;
+    Some(parse_central_processor(&String::from_utf8(output).ok()?))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub(crate) fn cpu_info() -> Option<CpuInfo> {
+    None
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Parse the first processor block of `/proc/cpuinfo`.
+#[cfg(any(target_os = "linux", test))]
+fn parse_proc_cpuinfo(content: &str) -> CpuInfo {
+    let mut info = CpuInfo::default();
+    // Only the first processor is described, the others are identical on all but exotic systems.
+    for line in content.lines().take_while(|line| !line.trim().is_empty()) {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        match key.trim() {
+            // x86
+            "vendor_id" => info.vendor_id = non_empty(value),
+            "cpu family" => info.family = non_empty(value),
+            "model" => info.model_id = non_empty(value),
+            "model name" => info.model_name = non_empty(value),
+            "stepping" => info.stepping = non_empty(value),
+            // arm
+            "CPU implementer" => {
+                info.vendor_id = info.vendor_id.take().or_else(|| non_empty(value))
+            }
+            "CPU architecture" => info.family = info.family.take().or_else(|| non_empty(value)),
+            "CPU part" => info.model_id = info.model_id.take().or_else(|| non_empty(value)),
+            "CPU revision" => info.stepping = info.stepping.take().or_else(|| non_empty(value)),
+            _ => {}
+        }
+    }
+    info
+}
+
+#[cfg(target_os = "linux")]
+fn l2_cache_size_from_sysfs() -> Option<i64> {
+    use std::fs::{read_dir, read_to_string};
+
+    read_dir("/sys/devices/system/cpu/cpu0/cache")
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            read_to_string(path.join("level")).is_ok_and(|level| level.trim() == "2")
+                && read_to_string(path.join("type")).is_ok_and(|kind| kind.trim() != "Instruction")
+        })
+        .and_then(|path| read_to_string(path.join("size")).ok())
+        .and_then(|size| parse_cache_size(&size))
+}
+
+/// Parse a sysfs cache size such as `256K` or `2M` into bytes.
+#[cfg(any(target_os = "linux", test))]
+fn parse_cache_size(size: &str) -> Option<i64> {
+    let size = size.trim();
+    let (digits, multiplier) = match size.strip_suffix('K') {
+        Some(digits) => (digits, 1024),
+        None => match size.strip_suffix('M') {
+            Some(digits) => (digits, 1024 * 1024),
+            None => (size, 1),
+        },
+    };
+    digits.parse::<i64>().ok().map(|value| value * multiplier)
+}
+
+/// Parse the output of `sysctl machdep.cpu.* hw.l2cachesize`.
+#[cfg(any(target_os = "macos", test))]
+fn parse_sysctl(output: &str) -> CpuInfo {
+    let mut info = CpuInfo::default();
+    for (key, value) in output.lines().filter_map(|line| line.split_once(':')) {
+        match key.trim() {
+            "machdep.cpu.vendor" => info.vendor_id = non_empty(value),
+            "machdep.cpu.family" => info.family = non_empty(value),
+            "machdep.cpu.model" => info.model_id = non_empty(value),
+            "machdep.cpu.brand_string" => info.model_name = non_empty(value),
+            "machdep.cpu.stepping" => info.stepping = non_empty(value),
+            "hw.l2cachesize" => info.cache_l2_size = value.trim().parse().ok(),
+            _ => {}
+        }
+    }
+    info
+}
+
+/// Parse the output of `reg query HKLM\HARDWARE\DESCRIPTION\System\CentralProcessor\0`.
+#[cfg(any(target_os = "windows", test))]
+fn parse_central_processor(output: &str) -> CpuInfo {
+    let value = |key: &str| {
+        output.lines().find_map(|line| {
+            let (name, rest) = line.trim().split_once(char::is_whitespace)?;
+            let (_, data) = rest.trim_start().split_once(char::is_whitespace)?;
+            (name == key).then(|| data.trim().to_string())
+        })
+    };
+
+    // Identifier has the form `Intel64 Family 6 Model 158 Stepping 10`.
+    let identifier = value("Identifier").unwrap_or_default();
+    let identifier_field = |name: &str| {
+        let mut words = identifier.split_whitespace();
+        words.find(|word| *word == name)?;
+        words.next().map(str::to_string)
+    };
+
+    CpuInfo {
+        vendor_id: value("VendorIdentifier"),
+        family: identifier_field("Family"),
+        model_id: identifier_field("Model"),
+        model_name: value("ProcessorNameString"),
+        stepping: identifier_field("Stepping"),
+        cache_l2_size: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_cpuinfo_x86() {
+        let content = "processor\t: 0\nvendor_id\t: GenuineIntel\ncpu family\t: 6\nmodel\t\t: 158\nmodel name\t: Intel(R) Core(TM) i7-8700 CPU @ 3.20GHz\nstepping\t: 10\ncache size\t: 12288 KB\n\nprocessor\t: 1\nvendor_id\t: Other\n";
+        assert_eq!(
+            parse_proc_cpuinfo(content),
+            CpuInfo {
+                vendor_id: Some("GenuineIntel".to_string()),
+                family: Some("6".to_string()),
+                model_id: Some("158".to_string()),
+                model_name: Some("Intel(R) Core(TM) i7-8700 CPU @ 3.20GHz".to_string()),
+                stepping: Some("10".to_string()),
+                cache_l2_size: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_proc_cpuinfo_arm() {
+        let content = "processor\t: 0\nBogoMIPS\t: 243.75\nCPU implementer\t: 0x41\nCPU architecture: 8\nCPU variant\t: 0x3\nCPU part\t: 0xd0c\nCPU revision\t: 1\n";
+        let info = parse_proc_cpuinfo(content);
+        assert_eq!(info.vendor_id.as_deref(), Some("0x41"));
+        assert_eq!(info.family.as_deref(), Some("8"));
+        assert_eq!(info.model_id.as_deref(), Some("0xd0c"));
+        assert_eq!(info.stepping.as_deref(), Some("1"));
+        assert_eq!(info.model_name, None);
+    }
+
+    #[test]
+    fn test_parse_cache_size() {
+        assert_eq!(parse_cache_size("256K\n"), Some(256 * 1024));
+        assert_eq!(parse_cache_size("2M"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_cache_size("4096"), Some(4096));
+        assert_eq!(parse_cache_size("unknown"), None);
+    }
+
+    #[test]
+    fn test_parse_sysctl() {
+        let output = "machdep.cpu.vendor: GenuineIntel\nmachdep.cpu.family: 6\nmachdep.cpu.model: 158\nmachdep.cpu.brand_string: Intel(R) Core(TM) i9-9880H CPU @ 2.30GHz\nmachdep.cpu.stepping: 13\nhw.l2cachesize: 262144\n";
+        let info = parse_sysctl(output);
+        assert_eq!(info.vendor_id.as_deref(), Some("GenuineIntel"));
+        assert_eq!(info.stepping.as_deref(), Some("13"));
+        assert_eq!(info.cache_l2_size, Some(262144));
+    }
+
+    #[test]
+    fn test_parse_central_processor() {
+        let output = "\r\nHKEY_LOCAL_MACHINE\\HARDWARE\\DESCRIPTION\\System\\CentralProcessor\\0\r\n    ~MHz    REG_DWORD    0xd50\r\n    Identifier    REG_SZ    Intel64 Family 6 Model 158 Stepping 10\r\n    ProcessorNameString    REG_SZ    Intel(R) Core(TM) i7-8700 CPU @ 3.20GHz\r\n    VendorIdentifier    REG_SZ    GenuineIntel\r\n";
+        assert_eq!(
+            parse_central_processor(output),
+            CpuInfo {
+                vendor_id: Some("GenuineIntel".to_string()),
+                family: Some("6".to_string()),
+                model_id: Some("158".to_string()),
+                model_name: Some("Intel(R) Core(TM) i7-8700 CPU @ 3.20GHz".to_string()),
+                stepping: Some("10".to_string()),
+                cache_l2_size: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_cpu_info_into_attributes() {
+        let info = CpuInfo {
+            vendor_id: Some("GenuineIntel".to_string()),
+            cache_l2_size: Some(1024),
+            ..Default::default()
+        };
+        assert_eq!(info.into_attributes().count(), 2);
+    }
+}
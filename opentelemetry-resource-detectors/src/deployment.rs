@@ -0,0 +1,300 @@
+//! Deployment resource detector
+//!
+//! Detect the deployment environment, service version and source revision from the environment
+//! variables set by common CI/CD pipelines and hosting platforms.
+use opentelemetry::{Key, KeyValue};
+use opentelemetry_sdk::resource::ResourceDetector;
+use opentelemetry_sdk::Resource;
+use opentelemetry_semantic_conventions as semconv;
+use std::env;
+
+const DEPLOYMENT_ENVIRONMENT_ENV_VARS: &[&str] = &[
+    "DEPLOYMENT_ENVIRONMENT",
+    "DEPLOY_ENV",
+    "ENVIRONMENT",
+    "APP_ENV",
+    // Railway
+    "RAILWAY_ENVIRONMENT_NAME",
+    // Vercel
+    "VERCEL_ENV",
+];
+
+const SERVICE_VERSION_ENV_VARS: &[&str] = &[
+    "SERVICE_VERSION",
+    "APP_VERSION",
+    // Heroku (requires the runtime-dyno-metadata labs feature)
+    "HEROKU_RELEASE_VERSION",
+];
+
+const CONTAINER_IMAGE_NAME_ENV_VARS: &[&str] = &[
+    // Fly.io
+    "FLY_IMAGE_REF",
+];
+
+const K8S_NAMESPACE_NAME_ENV_VARS: &[&str] = &["KUBE_NAMESPACE"];
+
+const VCS_REVISION_ENV_VARS: &[&str] = &[
+    "GIT_SHA",
+    "GIT_COMMIT",
+    "GIT_COMMIT_SHA",
+    // Heroku
+    "SOURCE_VERSION",
+    "HEROKU_SLUG_COMMIT",
+    // Railway
+    "RAILWAY_GIT_COMMIT_SHA",
+    // Vercel
+    "VERCEL_GIT_COMMIT_SHA",
+    // GitHub Actions
+    "GITHUB_SHA",
+    // GitLab CI
+    "CI_COMMIT_SHA",
+];
+
+const VCS_REF_NAME_ENV_VARS: &[&str] = &[
+    "GIT_BRANCH",
+    // Railway
+    "RAILWAY_GIT_BRANCH",
+    // Vercel
+    "VERCEL_GIT_COMMIT_REF",
+    // GitHub Actions
+    "GITHUB_REF_NAME",
+    // GitLab CI
+    "CI_COMMIT_REF_NAME",
+];
+
+const VCS_REPOSITORY_URL_ENV_VARS: &[&str] = &[
+    "GIT_REPOSITORY_URL",
+    // GitLab CI
+    "CI_PROJECT_URL",
+];
+
+/// Detect deployment information from environment variables.
+///
+/// Each attribute is mapped to an ordered list of environment variables, the first one that is
+/// set to a non-empty value wins. The default table covers generic names (`DEPLOY_ENV`,
+/// `GIT_SHA`, ...), `KUBE_NAMESPACE`, and the variables of Heroku, Railway, Fly.io, Vercel,
+/// GitHub Actions and GitLab CI:
+///
+/// | Attribute                     | Environment variables (in order)                                                                                                          |
+/// |-------------------------------|-------------------------------------------------------------------------------------------------------------------------------------------|
+/// | `deployment.environment.name` | `DEPLOYMENT_ENVIRONMENT`, `DEPLOY_ENV`, `ENVIRONMENT`, `APP_ENV`, `RAILWAY_ENVIRONMENT_NAME`, `VERCEL_ENV`                                |
+/// | `service.version`             | `SERVICE_VERSION`, `APP_VERSION`, `HEROKU_RELEASE_VERSION`                                                                                |
+/// | `container.image.name`        | `FLY_IMAGE_REF`                                                                                                                           |
+/// | `k8s.namespace.name`          | `KUBE_NAMESPACE`                                                                                                                          |
+/// | `vcs.ref.head.revision`       | `GIT_SHA`, `GIT_COMMIT`, `GIT_COMMIT_SHA`, `SOURCE_VERSION`, `HEROKU_SLUG_COMMIT`, `RAILWAY_GIT_COMMIT_SHA`, `VERCEL_GIT_COMMIT_SHA`, `GITHUB_SHA`, `CI_COMMIT_SHA` |
+/// | `vcs.ref.head.name`           | `GIT_BRANCH`, `RAILWAY_GIT_BRANCH`, `VERCEL_GIT_COMMIT_REF`, `GITHUB_REF_NAME`, `CI_COMMIT_REF_NAME`                                       |
+/// | `vcs.repository.url.full`     | `GIT_REPOSITORY_URL`, `CI_PROJECT_URL`                                                                                                    |
+///
+/// The table can be adjusted with [`with_mapping`](Self::with_mapping) and
+/// [`without_attribute`](Self::without_attribute):
+///
+/// ```
+/// use opentelemetry_resource_detectors::DeploymentResourceDetector;
+///
+/// let detector = DeploymentResourceDetector::new()
+///     .with_mapping("deployment.environment.name", ["MY_COMPANY_STAGE"])
+///     .with_mapping("team.name", ["TEAM"])
+///     .without_attribute("vcs.ref.head.name");
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeploymentResourceDetector {
+    mappings: Vec<(Key, Vec<String>)>,
+}
+
+impl DeploymentResourceDetector {
+    /// Create a detector with the default mapping table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map `attribute` to `env_vars`, replacing any existing mapping of that attribute.
+    pub fn with_mapping<K, I, V>(mut self, attribute: K, env_vars: I) -> Self
+    where
+        K: Into<Key>,
+        I: IntoIterator<Item = V>,
+        V: Into<String>,
+    {
+        let attribute = attribute.into();
+        let env_vars = env_vars.into_iter().map(Into::into).collect();
+        match self.mappings.iter_mut().find(|(key, _)| *key == attribute) {
+            Some(mapping) => mapping.1 = env_vars,
+            None => self.mappings.push((attribute, env_vars)),
+        }
+        self
+    }
+
+    /// Stop detecting `attribute`.
+    pub fn without_attribute(mut self, attribute: impl Into<Key>) -> Self {
+        let attribute = attribute.into();
+        self.mappings.retain(|(key, _)| *key != attribute);
+        self
+    }
+}
+
+impl Default for DeploymentResourceDetector {
+    fn default() -> Self {
+        Self {
+            mappings: Vec::new(),
+        }
+        .with_mapping(
+            semconv::attribute::DEPLOYMENT_ENVIRONMENT_NAME,
+            DEPLOYMENT_ENVIRONMENT_ENV_VARS.iter().copied(),
+        )
+        .with_mapping(
+            semconv::attribute::SERVICE_VERSION,
+            SERVICE_VERSION_ENV_VARS.iter().copied(),
+        )
+        .with_mapping(
+            semconv::attribute::CONTAINER_IMAGE_NAME,
+            CONTAINER_IMAGE_NAME_ENV_VARS.iter().copied(),
+        )
+        .with_mapping(
+            semconv::attribute::K8S_NAMESPACE_NAME,
+            K8S_NAMESPACE_NAME_ENV_VARS.iter().copied(),
+        )
+        .with_mapping(
+            semconv::attribute::VCS_REF_HEAD_REVISION,
+            VCS_REVISION_ENV_VARS.iter().copied(),
+        )
+        .with_mapping(
+            semconv::attribute::VCS_REF_HEAD_NAME,
+            VCS_REF_NAME_ENV_VARS.iter().copied(),
+        )
+        .with_mapping(
+            semconv::attribute::VCS_REPOSITORY_URL_FULL,
+            VCS_REPOSITORY_URL_ENV_VARS.iter().copied(),
+        )
+    }
+}
+
+impl ResourceDetector for DeploymentResourceDetector {
+    fn detect(&self) -> Resource {
+        Resource::builder_empty()
+            .with_attributes(self.mappings.iter().filter_map(|(key, env_vars)| {
+                env_vars
+                    .iter()
+                    .filter_map(|name| env::var(name).ok())
+                    .find(|value| !value.is_empty())
+                    .map(|value| KeyValue::new(key.clone(), value))
+            }))
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::Value;
+
+    fn all_env_vars() -> Vec<&'static str> {
+        [
+            DEPLOYMENT_ENVIRONMENT_ENV_VARS,
+            SERVICE_VERSION_ENV_VARS,
+            CONTAINER_IMAGE_NAME_ENV_VARS,
+            K8S_NAMESPACE_NAME_ENV_VARS,
+            VCS_REVISION_ENV_VARS,
+            VCS_REF_NAME_ENV_VARS,
+            VCS_REPOSITORY_URL_ENV_VARS,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_deployment_resource_detector_precedence() {
+        temp_env::with_vars_unset(all_env_vars(), || {
+            temp_env::with_vars(
+                [
+                    ("DEPLOY_ENV", Some("staging")),
+                    ("KUBE_NAMESPACE", Some("team-a")),
+                    ("ENVIRONMENT", Some("")),
+                    ("HEROKU_RELEASE_VERSION", Some("v42")),
+                    ("FLY_IMAGE_REF", Some("registry.fly.io/app:deployment-01")),
+                    ("GITHUB_SHA", Some("0123456789abcdef")),
+                    ("GIT_SHA", Some("fedcba9876543210")),
+                    ("GITHUB_REF_NAME", Some("main")),
+                ],
+                || {
+                    let resource = DeploymentResourceDetector::new().detect();
+
+                    assert_eq!(resource.len(), 6);
+                    assert_eq!(
+                        resource.get(&Key::from_static_str(
+                            semconv::attribute::DEPLOYMENT_ENVIRONMENT_NAME
+                        )),
+                        Some(Value::from("staging"))
+                    );
+                    assert_eq!(
+                        resource.get(&Key::from_static_str(semconv::attribute::SERVICE_VERSION)),
+                        Some(Value::from("v42"))
+                    );
+                    assert_eq!(
+                        resource.get(&Key::from_static_str(
+                            semconv::attribute::CONTAINER_IMAGE_NAME
+                        )),
+                        Some(Value::from("registry.fly.io/app:deployment-01"))
+                    );
+                    assert_eq!(
+                        resource.get(&Key::from_static_str(
+                            semconv::attribute::K8S_NAMESPACE_NAME
+                        )),
+                        Some(Value::from("team-a"))
+                    );
+                    assert_eq!(
+                        resource.get(&Key::from_static_str(
+                            semconv::attribute::VCS_REF_HEAD_REVISION
+                        )),
+                        Some(Value::from("fedcba9876543210"))
+                    );
+                    assert_eq!(
+                        resource.get(&Key::from_static_str(semconv::attribute::VCS_REF_HEAD_NAME)),
+                        Some(Value::from("main"))
+                    );
+                },
+            );
+        });
+    }
+
+    #[test]
+    fn test_deployment_resource_detector_custom_mapping() {
+        temp_env::with_vars_unset(all_env_vars(), || {
+            temp_env::with_vars(
+                [
+                    ("MY_STAGE", Some("prod")),
+                    ("DEPLOY_ENV", Some("staging")),
+                    ("TEAM", Some("payments")),
+                    ("GIT_BRANCH", Some("main")),
+                ],
+                || {
+                    let resource = DeploymentResourceDetector::new()
+                        .with_mapping(
+                            semconv::attribute::DEPLOYMENT_ENVIRONMENT_NAME,
+                            ["MY_STAGE"],
+                        )
+                        .with_mapping("team.name", ["TEAM"])
+                        .without_attribute(semconv::attribute::VCS_REF_HEAD_NAME)
+                        .detect();
+
+                    assert_eq!(resource.len(), 2);
+                    assert_eq!(
+                        resource.get(&Key::from_static_str(
+                            semconv::attribute::DEPLOYMENT_ENVIRONMENT_NAME
+                        )),
+                        Some(Value::from("prod"))
+                    );
+                    assert_eq!(
+                        resource.get(&Key::from_static_str("team.name")),
+                        Some(Value::from("payments"))
+                    );
+                },
+            );
+        });
+    }
+
+    #[test]
+    fn test_deployment_resource_detector_without_env_vars() {
+        temp_env::with_vars_unset(all_env_vars(), || {
+            let resource = DeploymentResourceDetector::new().detect();
+            assert_eq!(resource.len(), 0);
+        });
+    }
+}
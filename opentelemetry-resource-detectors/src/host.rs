@@ -1,15 +1,17 @@
 //! HOST resource detector
 //!
-//! Detect the unique host ID.
+//! Detect the unique host ID, host name and architecture.
 use opentelemetry::KeyValue;
 use opentelemetry_sdk::resource::ResourceDetector;
 use opentelemetry_sdk::Resource;
+#[cfg(windows)]
+use std::env;
 use std::env::consts::ARCH;
 #[cfg(target_os = "linux")]
 use std::fs::read_to_string;
 #[cfg(target_os = "linux")]
 use std::path::Path;
-#[cfg(target_os = "macos")]
+#[cfg(any(unix, windows))]
 use std::process::Command;
 
 /// Detect host information.
@@ -18,8 +20,13 @@ use std::process::Command;
 ///
 /// - [`host.id from non-containerized systems`](https://opentelemetry.io/docs/specs/semconv/resource/host/#collecting-hostid-from-non-containerized-systems)
 /// - Host architecture (host.arch).
+/// - Host name (host.name).
+/// - With the `host-cpu` feature, the processor description (`host.cpu.vendor.id`,
+///   `host.cpu.family`, `host.cpu.model.id`, `host.cpu.model.name`, `host.cpu.stepping` and
+///   `host.cpu.cache.l2.size`) of the first CPU.
 pub struct HostResourceDetector {
     host_id_detect: fn() -> Option<String>,
+    host_name_detect: fn() -> Option<String>,
 }
 
 impl ResourceDetector for HostResourceDetector {
@@ -39,22 +46,79 @@ impl ResourceDetector for HostResourceDetector {
                         opentelemetry_semantic_conventions::attribute::HOST_ARCH,
                         ARCH,
                     )),
+                    // Get host.name
+                    (self.host_name_detect)().map(|host_name| {
+                        KeyValue::new(
+                            opentelemetry_semantic_conventions::attribute::HOST_NAME,
+                            host_name,
+                        )
+                    }),
                 ]
                 .into_iter()
-                .flatten(),
+                .flatten()
+                .chain(cpu_attributes()),
             )
             .build()
     }
 }
 
+#[cfg(feature = "host-cpu")]
+fn cpu_attributes() -> Vec<KeyValue> {
+    crate::cpu::cpu_info()
+        .map(|info| info.into_attributes().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(not(feature = "host-cpu"))]
+fn cpu_attributes() -> Vec<KeyValue> {
+    Vec::new()
+}
+
+/// Detect the unique host ID only.
+///
+/// This resource detector returns [`host.id`] read from the platform specific source:
+///
+/// - Linux: `/etc/machine-id`, falling back to `/var/lib/dbus/machine-id`.
+/// - macOS: `IOPlatformUUID` reported by `ioreg`.
+/// - Windows: `MachineGuid` under `HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Cryptography`.
+///
+/// An empty resource is returned if the ID cannot be read. Unlike [`HostResourceDetector`], no
+/// other host attributes are added, which makes it suitable to combine with detectors that
+/// already report `host.arch`.
+///
+/// [`host.id`]: https://opentelemetry.io/docs/specs/semconv/resource/host/#collecting-hostid-from-non-containerized-systems
+pub struct HostIdResourceDetector {
+    host_id_detect: fn() -> Option<String>,
+}
+
+impl ResourceDetector for HostIdResourceDetector {
+    fn detect(&self) -> Resource {
+        Resource::builder_empty()
+            .with_attributes((self.host_id_detect)().map(|host_id| {
+                KeyValue::new(
+                    opentelemetry_semantic_conventions::attribute::HOST_ID,
+                    host_id,
+                )
+            }))
+            .build()
+    }
+}
+
+impl Default for HostIdResourceDetector {
+    fn default() -> Self {
+        Self { host_id_detect }
+    }
+}
+
 #[cfg(target_os = "linux")]
 fn host_id_detect() -> Option<String> {
     let machine_id_path = Path::new("/etc/machine-id");
     let dbus_machine_id_path = Path::new("/var/lib/dbus/machine-id");
-    read_to_string(machine_id_path)
-        .or_else(|_| read_to_string(dbus_machine_id_path))
+    [machine_id_path, dbus_machine_id_path]
+        .into_iter()
+        .filter_map(|path| read_to_string(path).ok())
         .map(|id| id.trim().to_string())
-        .ok()
+        .find(|id| !id.is_empty())
 }
 
 #[cfg(target_os = "macos")]
@@ -67,29 +131,107 @@ fn host_id_detect() -> Option<String> {
         .ok()?
         .stdout;
 
-    let output = String::from_utf8(output).ok()?;
+    parse_ioreg_output(&String::from_utf8(output).ok()?)
+}
+
+#[cfg(target_os = "windows")]
+fn host_id_detect() -> Option<String> {
+    let output = Command::new("reg")
+        .arg("query")
+        .arg(r"HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Cryptography")
+        .arg("/v")
+        .arg("MachineGuid")
+        .output()
+        .ok()?
+        .stdout;
+
+    parse_reg_query_output(&String::from_utf8(output).ok()?)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn host_id_detect() -> Option<String> {
+    None
+}
+
+/// Extract `IOPlatformUUID` from the output of `ioreg -rd1 -c IOPlatformExpertDevice`.
+///
+/// The relevant line looks like `"IOPlatformUUID" = "D6A8C1B8-..."`.
+#[cfg(any(target_os = "macos", test))]
+fn parse_ioreg_output(output: &str) -> Option<String> {
     let line = output
         .lines()
         .find(|line| line.contains("IOPlatformUUID"))?;
 
-    Some(line.split_once('=')?.1.trim().trim_matches('"').to_owned())
+    Some(line.split_once('=')?.1.trim().trim_matches('"').to_owned()).filter(|id| !id.is_empty())
 }
 
-// TODO: Implement non-linux platforms
-#[cfg(not(any(target_os = "linux", target_os = "macos")))]
-fn host_id_detect() -> Option<String> {
+/// Extract `MachineGuid` from the output of `reg query ... /v MachineGuid`.
+///
+/// The relevant line looks like `    MachineGuid    REG_SZ    1f2c8e7a-...`.
+#[cfg(any(target_os = "windows", test))]
+fn parse_reg_query_output(output: &str) -> Option<String> {
+    let line = output
+        .lines()
+        .find(|line| line.trim_start().starts_with("MachineGuid"))?;
+
+    let mut fields = line.split_whitespace();
+    match (fields.next(), fields.next(), fields.next()) {
+        (Some("MachineGuid"), Some("REG_SZ"), Some(id)) => Some(id.to_owned()),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn host_name_detect() -> Option<String> {
+    read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or_else(hostname_command)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn host_name_detect() -> Option<String> {
+    hostname_command()
+}
+
+#[cfg(windows)]
+fn host_name_detect() -> Option<String> {
+    env::var("COMPUTERNAME")
+        .ok()
+        .filter(|name| !name.is_empty())
+        .or_else(hostname_command)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn host_name_detect() -> Option<String> {
     None
 }
 
+#[cfg(any(unix, windows))]
+fn hostname_command() -> Option<String> {
+    let output = Command::new("hostname").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let name = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    Some(name).filter(|name| !name.is_empty())
+}
+
 impl Default for HostResourceDetector {
     fn default() -> Self {
-        Self { host_id_detect }
+        Self {
+            host_id_detect,
+            host_name_detect,
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::HostResourceDetector;
+    use super::{
+        parse_ioreg_output, parse_reg_query_output, HostIdResourceDetector, HostResourceDetector,
+    };
     use opentelemetry::{Key, Value};
     use opentelemetry_sdk::resource::ResourceDetector;
 
@@ -97,7 +239,13 @@ mod tests {
     #[test]
     fn test_host_resource_detector_linux() {
         let resource = HostResourceDetector::default().detect();
-        assert_eq!(resource.len(), 2);
+        #[cfg(not(feature = "host-cpu"))]
+        assert_eq!(resource.len(), 3);
+        assert!(resource
+            .get(&Key::from_static_str(
+                opentelemetry_semantic_conventions::attribute::HOST_NAME
+            ))
+            .is_some());
         assert!(resource
             .get(&Key::from_static_str(
                 opentelemetry_semantic_conventions::attribute::HOST_ID
@@ -114,7 +262,13 @@ mod tests {
     #[test]
     fn test_host_resource_detector_macos() {
         let resource = HostResourceDetector::default().detect();
-        assert_eq!(resource.len(), 2);
+        #[cfg(not(feature = "host-cpu"))]
+        assert_eq!(resource.len(), 3);
+        assert!(resource
+            .get(&Key::from_static_str(
+                opentelemetry_semantic_conventions::attribute::HOST_NAME
+            ))
+            .is_some());
         assert!(resource
             .get(&Key::from_static_str(
                 opentelemetry_semantic_conventions::attribute::HOST_ID
@@ -153,4 +307,98 @@ mod tests {
             Some(Value::from("aarch64"))
         )
     }
+
+    #[test]
+    fn test_host_id_resource_detector() {
+        let resource = HostIdResourceDetector {
+            host_id_detect: || Some("test-host-id".to_string()),
+        }
+        .detect();
+
+        assert_eq!(resource.len(), 1);
+        assert_eq!(
+            resource.get(&Key::from_static_str(
+                opentelemetry_semantic_conventions::attribute::HOST_ID
+            )),
+            Some(Value::from("test-host-id"))
+        );
+    }
+
+    #[test]
+    fn test_host_id_resource_detector_without_id() {
+        let resource = HostIdResourceDetector {
+            host_id_detect: || None,
+        }
+        .detect();
+
+        assert_eq!(resource.len(), 0);
+    }
+
+    #[test]
+    fn test_parse_ioreg_output() {
+        let output = r#"+-o J314sAP  <class IOPlatformExpertDevice, id 0x100000209, registered, matched, active, busy 0 (40 ms), retain 37>
+    {
+      "IOPlatformSerialNumber" = "XXXXXXXXXX"
+      "IOPlatformUUID" = "D6A8C1B8-4B2E-5A6F-9C3D-0E1F2A3B4C5D"
+    }"#;
+        assert_eq!(
+            parse_ioreg_output(output),
+            Some("D6A8C1B8-4B2E-5A6F-9C3D-0E1F2A3B4C5D".to_string())
+        );
+        assert_eq!(parse_ioreg_output("\"IOPlatformUUID\" = \"\""), None);
+        assert_eq!(parse_ioreg_output(""), None);
+    }
+
+    #[test]
+    fn test_parse_reg_query_output() {
+        let output = "\r\nHKEY_LOCAL_MACHINE\\SOFTWARE\\Microsoft\\Cryptography\r\n    MachineGuid    REG_SZ    1f2c8e7a-3b4d-4c5e-8f90-a1b2c3d4e5f6\r\n\r\n";
+        assert_eq!(
+            parse_reg_query_output(output),
+            Some("1f2c8e7a-3b4d-4c5e-8f90-a1b2c3d4e5f6".to_string())
+        );
+        assert_eq!(
+            parse_reg_query_output(
+                "ERROR: The system was unable to find the specified registry key or value."
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_host_resource_detector_with_injected_values() {
+        let resource = HostResourceDetector {
+            host_id_detect: || Some("test-host-id".to_string()),
+            host_name_detect: || Some("test-host".to_string()),
+        }
+        .detect();
+
+        assert_eq!(
+            resource.get(&Key::from_static_str(
+                opentelemetry_semantic_conventions::attribute::HOST_NAME
+            )),
+            Some(Value::from("test-host"))
+        );
+        assert_eq!(
+            resource.get(&Key::from_static_str(
+                opentelemetry_semantic_conventions::attribute::HOST_ID
+            )),
+            Some(Value::from("test-host-id"))
+        );
+    }
+
+    // ARM processors, including Apple silicon, don't report a CPU family.
+    #[cfg(all(
+        feature = "host-cpu",
+        any(target_os = "linux", target_os = "macos"),
+        any(target_arch = "x86", target_arch = "x86_64")
+    ))]
+    #[test]
+    fn test_host_resource_detector_cpu() {
+        let resource = HostResourceDetector::default().detect();
+        assert!(resource
+            .get(&Key::from_static_str(
+                opentelemetry_semantic_conventions::attribute::HOST_CPU_FAMILY
+            ))
+            .is_some());
+    }
 }
@@ -5,14 +5,56 @@
 //!
 //! - [`OsResourceDetector`] - detect OS from runtime.
 //! - [`ProcessResourceDetector`] - detect process information.
-//! - [`HostResourceDetector`] - detect unique host ID.
+//! - [`HostResourceDetector`] - detect unique host ID and host architecture.
+//! - [`HostIdResourceDetector`] - detect unique host ID only.
 //! - [`K8sResourceDetector`] - detect Kubernetes information.
+//! - [`DeploymentResourceDetector`] - detect deployment environment and source revision from CI/CD
+//!   environment variables.
+//! - `AzureVmResourceDetector`, `AzureAppServiceResourceDetector`, `AzureFunctionsResourceDetector`
+//!   and `AzureAksResourceDetector` - detect Azure environments, requires the `azure` feature.
+//!
+//! [`CompositeDetector`] runs several detectors in parallel with per-detector timeouts.
+//!
+//! [`merge_all`] merges the resources of several detectors and the user, following the schema URL
+//! merge rules of the specification.
+//!
+//! [`detect_async`] runs a detector off the calling thread and [`RefreshingResource`] re-runs it
+//! periodically for attributes that change or appear after startup.
+//!
+//! `CachingDetector` reuses the resource of a detector from a local file until it expires, for
+//! short-lived processes, requires the `cache` feature.
+//!
+//! [`detectors_from_env`] creates the detectors named in `OTEL_RESOURCE_DETECTORS`, see
+//! [`DetectorRegistry`] for the available names.
+#[cfg(feature = "azure")]
+mod azure;
+#[cfg(feature = "cache")]
+mod cache;
+mod composite;
+#[cfg(feature = "host-cpu")]
+mod cpu;
+mod deployment;
 mod host;
 mod k8s;
+mod merge;
 mod os;
 mod process;
+mod refresh;
+mod registry;
 
-pub use host::HostResourceDetector;
+#[cfg(feature = "azure")]
+pub use azure::{
+    AzureAksResourceDetector, AzureAppServiceResourceDetector, AzureFunctionsResourceDetector,
+    AzureVmResourceDetector,
+};
+#[cfg(feature = "cache")]
+pub use cache::CachingDetector;
+pub use composite::CompositeDetector;
+pub use deployment::DeploymentResourceDetector;
+pub use host::{HostIdResourceDetector, HostResourceDetector};
 pub use k8s::K8sResourceDetector;
+pub use merge::merge_all;
 pub use os::OsResourceDetector;
 pub use process::ProcessResourceDetector;
+pub use refresh::{detect_async, DetectFuture, RefreshingResource, RefreshingResourceBuilder};
+pub use registry::{detectors_from_env, DetectorRegistry, OTEL_RESOURCE_DETECTORS};
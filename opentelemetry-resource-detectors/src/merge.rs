@@ -0,0 +1,165 @@
+//! Schema URL aware merging of resources
+//!
+//! Combine the resources of several detectors and the one supplied by the user following the
+//! merge rules of the resource SDK specification.
+use opentelemetry::{otel_debug, otel_warn, Key, KeyValue, Value};
+use opentelemetry_sdk::Resource;
+use std::collections::HashMap;
+
+/// Merge `resources` into a single resource.
+///
+/// Resources are merged in order, so when several resources have the same attribute, the value
+/// of the last one wins: pass the user supplied resource last for it to take precedence over
+/// the detected ones. Overwritten values are reported through internal debug logs, so attributes
+/// silently disappearing from multi-detector setups can be tracked down.
+///
+/// The schema URL of the merged resource is the one shared by all the resources that have one.
+/// If two resources have different schema URLs, attribute names might not have the same meaning,
+/// the merged resource has no schema URL and a warning is logged.
+///
+/// ```
+/// use opentelemetry::KeyValue;
+/// use opentelemetry_resource_detectors::{merge_all, OsResourceDetector};
+/// use opentelemetry_sdk::resource::ResourceDetector;
+/// use opentelemetry_sdk::Resource;
+///
+/// let user = Resource::builder_empty()
+///     .with_schema_url(
+///         [KeyValue::new("service.name", "checkout")],
+///         "https://opentelemetry.io/schemas/1.26.0",
+///     )
+///     .build();
+/// let resource = merge_all([OsResourceDetector.detect(), user]);
+/// assert_eq!(resource.schema_url(), Some("https://opentelemetry.io/schemas/1.26.0"));
+/// ```
+pub fn merge_all<I>(resources: I) -> Resource
+where
+    I: IntoIterator<Item = Resource>,
+{
+    let mut attributes: HashMap<Key, Value> = HashMap::new();
+    let mut schema_url: Option<String> = None;
+    let mut schema_conflict = false;
+
+    for resource in resources {
+        match (&schema_url, resource.schema_url()) {
+            (_, None) => {}
+            (None, Some(url)) => {
+                if !schema_conflict {
+                    schema_url = Some(url.to_owned());
+                }
+            }
+            (Some(current), Some(url)) if current == url => {}
+            (Some(current), Some(url)) => {
+                otel_warn!(name: "MergeResources.SchemaUrlConflict", schema_url = current.clone(), other_schema_url = url.to_owned(), message = "Resources with different schema URLs were merged, the merged resource has no schema URL.");
+                schema_url = None;
+                schema_conflict = true;
+            }
+        }
+
+        for (key, value) in resource.iter() {
+            if let Some(previous) = attributes.insert(key.clone(), value.clone()) {
+                if previous != *value {
+                    otel_debug!(name: "MergeResources.AttributeOverwritten", key = key.to_string(), previous = previous.to_string(), value = value.to_string());
+                }
+            }
+        }
+    }
+
+    let attributes = attributes
+        .into_iter()
+        .map(|(key, value)| KeyValue::new(key, value));
+    match schema_url {
+        Some(schema_url) => Resource::builder_empty()
+            .with_schema_url(attributes, schema_url)
+            .build(),
+        None => Resource::builder_empty()
+            .with_attributes(attributes)
+            .build(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_all;
+    use opentelemetry::{Key, KeyValue, Value};
+    use opentelemetry_sdk::Resource;
+
+    const SCHEMA_1: &str = "https://opentelemetry.io/schemas/1.25.0";
+    const SCHEMA_2: &str = "https://opentelemetry.io/schemas/1.26.0";
+
+    fn resource(
+        attributes: &[(&'static str, &'static str)],
+        schema_url: Option<&'static str>,
+    ) -> Resource {
+        let attributes = attributes
+            .iter()
+            .map(|(key, value)| KeyValue::new(*key, *value));
+        match schema_url {
+            Some(schema_url) => Resource::builder_empty()
+                .with_schema_url(attributes, schema_url)
+                .build(),
+            None => Resource::builder_empty()
+                .with_attributes(attributes)
+                .build(),
+        }
+    }
+
+    #[test]
+    fn test_merge_all_precedence() {
+        let merged = merge_all([
+            resource(&[("host.name", "detected"), ("os.type", "linux")], None),
+            resource(&[("host.name", "user")], None),
+        ]);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(
+            merged.get(&Key::from_static_str("host.name")),
+            Some(Value::from("user"))
+        );
+        assert_eq!(
+            merged.get(&Key::from_static_str("os.type")),
+            Some(Value::from("linux"))
+        );
+    }
+
+    #[test]
+    fn test_merge_all_schema_url() {
+        // Resources without a schema URL take the one of the others.
+        let merged = merge_all([
+            resource(&[("a", "a")], None),
+            resource(&[("b", "b")], Some(SCHEMA_1)),
+            resource(&[("c", "c")], None),
+        ]);
+        assert_eq!(merged.schema_url(), Some(SCHEMA_1));
+
+        let merged = merge_all([
+            resource(&[("a", "a")], Some(SCHEMA_1)),
+            resource(&[("b", "b")], Some(SCHEMA_1)),
+        ]);
+        assert_eq!(merged.schema_url(), Some(SCHEMA_1));
+
+        assert_eq!(
+            merge_all([resource(&[("a", "a")], None)]).schema_url(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_merge_all_schema_url_conflict() {
+        let merged = merge_all([
+            resource(&[("a", "a")], Some(SCHEMA_1)),
+            resource(&[("b", "b")], Some(SCHEMA_2)),
+            resource(&[("c", "c")], Some(SCHEMA_1)),
+        ]);
+
+        assert_eq!(merged.schema_url(), None);
+        assert_eq!(merged.len(), 3);
+    }
+
+    #[test]
+    fn test_merge_all_empty() {
+        let merged = merge_all(Vec::new());
+        assert!(merged.is_empty());
+        assert_eq!(merged.schema_url(), None);
+    }
+}
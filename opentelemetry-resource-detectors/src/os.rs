@@ -1,41 +1,240 @@
 //! OS resource detector
 //!
-//! Detect the runtime operating system type.
+//! Detect the runtime operating system type, name and version.
 use opentelemetry::KeyValue;
 use opentelemetry_sdk::resource::ResourceDetector;
 use opentelemetry_sdk::Resource;
 use std::env::consts::OS;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::process::Command;
 
 /// Detect runtime operating system information.
 ///
 /// This detector uses Rust's [`OS constant`] to detect the operating system type and
 /// maps the result to the supported value defined in [`OpenTelemetry spec`].
 ///
+/// The name, version, build and a human readable description (`os.name`, `os.version`,
+/// `os.build_id`, `os.description`) are read from platform specific sources when available:
+///
+/// - Linux: `/etc/os-release` (falling back to `/usr/lib/os-release`) and the kernel release.
+/// - macOS: the output of `sw_vers`.
+/// - Windows: `HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows NT\CurrentVersion`.
+///
 /// [`OS constant`]: https://doc.rust-lang.org/std/env/consts/constant.OS.html
-/// [`OpenTelemetry spec`]: https://github.com/open-telemetry/opentelemetry-specification/blob/main/specification/resource/semantic_conventions/os.md
+/// [`OpenTelemetry spec`]: https://github.com/open-telemetry/semantic-conventions/blob/main/docs/resource/os.md
 pub struct OsResourceDetector;
 
 impl ResourceDetector for OsResourceDetector {
     fn detect(&self) -> Resource {
+        let info = os_info().unwrap_or_default();
         Resource::builder_empty()
-            .with_attributes(vec![KeyValue::new(
-                opentelemetry_semantic_conventions::attribute::OS_TYPE,
-                OS,
-            )])
+            .with_attributes(
+                [
+                    Some(KeyValue::new(
+                        opentelemetry_semantic_conventions::attribute::OS_TYPE,
+                        OS,
+                    )),
+                    info.name.map(|name| {
+                        KeyValue::new(opentelemetry_semantic_conventions::attribute::OS_NAME, name)
+                    }),
+                    info.version.map(|version| {
+                        KeyValue::new(
+                            opentelemetry_semantic_conventions::attribute::OS_VERSION,
+                            version,
+                        )
+                    }),
+                    info.build_id.map(|build_id| {
+                        KeyValue::new(
+                            opentelemetry_semantic_conventions::attribute::OS_BUILD_ID,
+                            build_id,
+                        )
+                    }),
+                    info.description.map(|description| {
+                        KeyValue::new(
+                            opentelemetry_semantic_conventions::attribute::OS_DESCRIPTION,
+                            description,
+                        )
+                    }),
+                ]
+                .into_iter()
+                .flatten(),
+            )
             .build()
     }
 }
 
+#[derive(Debug, Default, PartialEq)]
+struct OsInfo {
+    name: Option<String>,
+    version: Option<String>,
+    build_id: Option<String>,
+    description: Option<String>,
+}
+
+#[cfg(target_os = "linux")]
+fn os_info() -> Option<OsInfo> {
+    use std::fs::read_to_string;
+
+    let os_release = read_to_string("/etc/os-release")
+        .or_else(|_| read_to_string("/usr/lib/os-release"))
+        .ok()?;
+    let kernel_release = read_to_string("/proc/sys/kernel/osrelease").ok();
+
+    Some(parse_os_release(&os_release, kernel_release.as_deref()))
+}
+
+#[cfg(target_os = "macos")]
+fn os_info() -> Option<OsInfo> {
+    let output = Command::new("sw_vers").output().ok()?.stdout;
+    Some(parse_sw_vers(&String::from_utf8(output).ok()?))
+}
+
+#[cfg(target_os = "windows")]
+fn os_info() -> Option<OsInfo> {
+    let output = Command::new("reg")
+        .arg("query")
+        .arg(r"HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows NT\CurrentVersion")
+        .output()
+        .ok()?
+        .stdout;
+    parse_windows_current_version(&String::from_utf8(output).ok()?)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn os_info() -> Option<OsInfo> {
+    None
+}
+
+/// Parse the content of an [os-release](https://www.freedesktop.org/software/systemd/man/latest/os-release.html) file.
+#[cfg(any(target_os = "linux", test))]
+fn parse_os_release(content: &str, kernel_release: Option<&str>) -> OsInfo {
+    let field = |key: &str| {
+        content
+            .lines()
+            .filter_map(|line| line.trim().split_once('='))
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| {
+                value
+                    .trim()
+                    .trim_matches(|c| c == '"' || c == '\'')
+                    .to_string()
+            })
+            .filter(|value| !value.is_empty())
+    };
+
+    let name = field("NAME");
+    let version = field("VERSION_ID");
+    let pretty_name = field("PRETTY_NAME").or_else(|| {
+        Some(
+            format!(
+                "{} {}",
+                name.as_deref()?,
+                version.as_deref().unwrap_or_default()
+            )
+            .trim_end()
+            .to_string(),
+        )
+    });
+    let description = match (pretty_name, kernel_release.map(str::trim)) {
+        (Some(pretty_name), Some(kernel)) if !kernel.is_empty() => {
+            Some(format!("{pretty_name} (Linux {kernel})"))
+        }
+        (pretty_name, _) => pretty_name,
+    };
+
+    OsInfo {
+        name,
+        version,
+        build_id: field("BUILD_ID"),
+        description,
+    }
+}
+
+/// Parse the output of `sw_vers`.
+#[cfg(any(target_os = "macos", test))]
+fn parse_sw_vers(output: &str) -> OsInfo {
+    let field = |key: &str| {
+        output
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim() == key)
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let name = field("ProductName");
+    let version = field("ProductVersion");
+    let build_id = field("BuildVersion");
+    let description = match (&name, &version, &build_id) {
+        (Some(name), Some(version), Some(build_id)) => {
+            Some(format!("{name} {version} ({build_id})"))
+        }
+        (Some(name), Some(version), None) => Some(format!("{name} {version}")),
+        _ => None,
+    };
+
+    OsInfo {
+        name,
+        version,
+        build_id,
+        description,
+    }
+}
+
+/// Parse the output of `reg query "HKLM\SOFTWARE\Microsoft\Windows NT\CurrentVersion"`.
+#[cfg(any(target_os = "windows", test))]
+fn parse_windows_current_version(output: &str) -> Option<OsInfo> {
+    // Value lines have the form `    <name>    <type>    <data>`, where data may contain spaces.
+    let value = |key: &str| {
+        output.lines().find_map(|line| {
+            let (name, rest) = line.trim().split_once(char::is_whitespace)?;
+            let (_, data) = rest.trim_start().split_once(char::is_whitespace)?;
+            (name == key).then(|| data.trim().to_string())
+        })
+    };
+    let dword = |key: &str| {
+        value(key).and_then(|data| u32::from_str_radix(data.trim_start_matches("0x"), 16).ok())
+    };
+
+    let build = value("CurrentBuildNumber")?.parse::<u32>().ok()?;
+    let major = dword("CurrentMajorVersionNumber").unwrap_or(10);
+    let minor = dword("CurrentMinorVersionNumber").unwrap_or(0);
+    let version = format!("{major}.{minor}.{build}");
+    let build_id = match dword("UBR") {
+        Some(ubr) => format!("{build}.{ubr}"),
+        None => build.to_string(),
+    };
+
+    // Windows 11 keeps reporting "Windows 10" as product name, the build number tells them apart.
+    let name = value("ProductName").map(|name| {
+        if build >= 22000 {
+            name.replacen("Windows 10", "Windows 11", 1)
+        } else {
+            name
+        }
+    });
+    let description = name.as_ref().map(|name| match value("DisplayVersion") {
+        Some(display_version) => format!("{name} {display_version} ({version})"),
+        None => format!("{name} ({version})"),
+    });
+
+    Some(OsInfo {
+        name,
+        version: Some(version),
+        build_id: Some(build_id),
+        description,
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::OsResourceDetector;
+    use super::*;
     use opentelemetry::{Key, Value};
-    use opentelemetry_sdk::resource::ResourceDetector;
 
     #[test]
     fn test_os_resource_detector() {
         let resource = OsResourceDetector.detect();
-        assert_eq!(resource.len(), 1);
+        assert!(!resource.is_empty());
 
         #[cfg(target_os = "linux")]
         let expected_os = "linux";
@@ -53,4 +252,78 @@ mod tests {
             Some(Value::from(expected_os))
         )
     }
+
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    #[test]
+    fn test_os_resource_detector_version() {
+        let resource = OsResourceDetector.detect();
+
+        // Minimal containers may not ship an os-release file.
+        let expected = os_info().and_then(|info| info.description).map(Value::from);
+        assert_eq!(
+            resource.get(&Key::from_static_str(
+                opentelemetry_semantic_conventions::attribute::OS_DESCRIPTION
+            )),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_parse_os_release() {
+        let content = r#"PRETTY_NAME="Ubuntu 22.04.4 LTS"
+NAME="Ubuntu"
+VERSION_ID="22.04"
+VERSION="22.04.4 LTS (Jammy Jellyfish)"
+ID=ubuntu
+"#;
+        assert_eq!(
+            parse_os_release(content, Some("5.15.0-105-generic\n")),
+            OsInfo {
+                name: Some("Ubuntu".to_string()),
+                version: Some("22.04".to_string()),
+                build_id: None,
+                description: Some("Ubuntu 22.04.4 LTS (Linux 5.15.0-105-generic)".to_string()),
+            }
+        );
+
+        let content = "NAME='Container Linux'\nBUILD_ID=2023-10-01\n";
+        assert_eq!(
+            parse_os_release(content, None),
+            OsInfo {
+                name: Some("Container Linux".to_string()),
+                version: None,
+                build_id: Some("2023-10-01".to_string()),
+                description: Some("Container Linux".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_sw_vers() {
+        let output = "ProductName:\t\tmacOS\nProductVersion:\t\t14.4.1\nBuildVersion:\t\t23E224\n";
+        assert_eq!(
+            parse_sw_vers(output),
+            OsInfo {
+                name: Some("macOS".to_string()),
+                version: Some("14.4.1".to_string()),
+                build_id: Some("23E224".to_string()),
+                description: Some("macOS 14.4.1 (23E224)".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_windows_current_version() {
+        let output = "\r\nHKEY_LOCAL_MACHINE\\SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\r\n    SystemRoot    REG_SZ    C:\\WINDOWS\r\n    CurrentBuildNumber    REG_SZ    22631\r\n    ProductName    REG_SZ    Windows 10 Pro\r\n    CurrentMajorVersionNumber    REG_DWORD    0xa\r\n    CurrentMinorVersionNumber    REG_DWORD    0x0\r\n    UBR    REG_DWORD    0xd47\r\n    DisplayVersion    REG_SZ    23H2\r\n";
+        assert_eq!(
+            parse_windows_current_version(output),
+            Some(OsInfo {
+                name: Some("Windows 11 Pro".to_string()),
+                version: Some("10.0.22631".to_string()),
+                build_id: Some("22631.3399".to_string()),
+                description: Some("Windows 11 Pro 23H2 (10.0.22631)".to_string()),
+            })
+        );
+        assert_eq!(parse_windows_current_version("ERROR: access denied"), None);
+    }
 }
@@ -0,0 +1,405 @@
+//! Asynchronous and periodically refreshed detection
+//!
+//! Some attributes are slow to detect or only become known after startup, e.g. the ECS task
+//! metadata or the spot instance lifecycle. [`detect_async`] runs a detector off the calling
+//! thread and [`RefreshingResource`] keeps re-running it in the background.
+use opentelemetry::otel_debug;
+use opentelemetry_sdk::resource::ResourceDetector;
+use opentelemetry_sdk::Resource;
+use std::fmt;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+struct DetectState {
+    resource: Option<Resource>,
+    waker: Option<Waker>,
+}
+
+/// Future returned by [`detect_async`].
+///
+/// Resolves to an empty resource if the detector panics.
+pub struct DetectFuture {
+    state: Arc<Mutex<DetectState>>,
+}
+
+impl Future for DetectFuture {
+    type Output = Resource;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        match state.resource.take() {
+            Some(resource) => Poll::Ready(resource),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl fmt::Debug for DetectFuture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DetectFuture").finish_non_exhaustive()
+    }
+}
+
+/// Run `detector` on a dedicated thread and return a future resolving to its resource.
+///
+/// The future doesn't depend on any async runtime, so blocking probes such as metadata
+/// endpoints don't stall the executor.
+///
+/// ```no_run
+/// # async fn example() {
+/// use opentelemetry_resource_detectors::{detect_async, HostResourceDetector};
+///
+/// let resource = detect_async(HostResourceDetector::default()).await;
+/// # }
+/// ```
+pub fn detect_async<D>(detector: D) -> DetectFuture
+where
+    D: ResourceDetector + Send + 'static,
+{
+    let state = Arc::new(Mutex::new(DetectState::default()));
+    let shared = Arc::clone(&state);
+    thread::spawn(move || {
+        let resource = run_detector(&detector);
+        let mut state = shared.lock().unwrap_or_else(|err| err.into_inner());
+        state.resource = Some(resource);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    });
+    DetectFuture { state }
+}
+
+fn run_detector(detector: &dyn ResourceDetector) -> Resource {
+    catch_unwind(AssertUnwindSafe(|| detector.detect())).unwrap_or_else(|_| {
+        otel_debug!(name: "RefreshingResource.DetectorPanicked");
+        Resource::builder_empty().build()
+    })
+}
+
+type OnChange = Box<dyn Fn(&Resource) + Send + Sync>;
+
+/// Builder for [`RefreshingResource`].
+pub struct RefreshingResourceBuilder {
+    detector: Box<dyn ResourceDetector + Send>,
+    interval: Duration,
+    on_change: Option<OnChange>,
+}
+
+impl RefreshingResourceBuilder {
+    /// Set the time between two detections, 60 seconds by default.
+    pub fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set a callback invoked from the background thread every time the detected resource
+    /// differs from the previous one, including the first detection.
+    pub fn with_on_change<F>(mut self, on_change: F) -> Self
+    where
+        F: Fn(&Resource) + Send + Sync + 'static,
+    {
+        self.on_change = Some(Box::new(on_change));
+        self
+    }
+
+    /// Start detecting in the background.
+    pub fn build(self) -> RefreshingResource {
+        let current = Arc::new(RwLock::new(Resource::builder_empty().build()));
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let shared = Arc::clone(&current);
+        let RefreshingResourceBuilder {
+            detector,
+            interval,
+            on_change,
+        } = self;
+        let handle = thread::Builder::new()
+            .name("otel-resource-refresh".to_string())
+            .spawn(move || {
+                let mut first = true;
+                loop {
+                    let resource = run_detector(detector.as_ref());
+                    // Dropped while detecting, don't publish nor notify anymore.
+                    if let Err(TryRecvError::Disconnected) = stopped.try_recv() {
+                        break;
+                    }
+                    let changed = {
+                        let mut current = shared.write().unwrap_or_else(|err| err.into_inner());
+                        let changed = first || *current != resource;
+                        *current = resource.clone();
+                        changed
+                    };
+                    first = false;
+                    if changed {
+                        otel_debug!(name: "RefreshingResource.Changed", attributes = resource.len() as u64);
+                        if let Some(on_change) = &on_change {
+                            on_change(&resource);
+                        }
+                    }
+
+                    match stopped.recv_timeout(interval) {
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            })
+            .ok();
+
+        RefreshingResource {
+            current,
+            stop: Mutex::new(Some(stop)),
+            handle: Mutex::new(handle),
+        }
+    }
+}
+
+/// A resource kept up to date by re-running a detector in the background.
+///
+/// Detection starts on a background thread as soon as the resource is built, so creating it
+/// never blocks. Until the first detection completes [`RefreshingResource::resource`] returns an
+/// empty resource. The background thread stops when [`RefreshingResource::stop`] is called, or
+/// when the value is dropped. Dropping doesn't wait for a detection in progress, the thread
+/// exits once it completes.
+///
+/// `RefreshingResource` also implements [`ResourceDetector`], returning the latest snapshot.
+///
+/// ```no_run
+/// use opentelemetry_resource_detectors::{K8sResourceDetector, RefreshingResource};
+/// use std::time::Duration;
+///
+/// let resource = RefreshingResource::builder(K8sResourceDetector)
+///     .with_refresh_interval(Duration::from_secs(30))
+///     .with_on_change(|resource| println!("resource changed: {resource:?}"))
+///     .build();
+///
+/// let snapshot = resource.resource();
+/// ```
+pub struct RefreshingResource {
+    current: Arc<RwLock<Resource>>,
+    stop: Mutex<Option<Sender<()>>>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl RefreshingResource {
+    /// Create a builder refreshing the output of `detector`.
+    pub fn builder<D>(detector: D) -> RefreshingResourceBuilder
+    where
+        D: ResourceDetector + Send + 'static,
+    {
+        RefreshingResourceBuilder {
+            detector: Box::new(detector),
+            interval: DEFAULT_REFRESH_INTERVAL,
+            on_change: None,
+        }
+    }
+
+    /// The latest detected resource.
+    pub fn resource(&self) -> Resource {
+        self.current
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    /// Stop refreshing and wait for the background thread to exit.
+    ///
+    /// The last detected resource remains available.
+    pub fn stop(&self) {
+        self.stop
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take();
+        if let Some(handle) = self
+            .handle
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take()
+        {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl ResourceDetector for RefreshingResource {
+    fn detect(&self) -> Resource {
+        self.resource()
+    }
+}
+
+impl Drop for RefreshingResource {
+    fn drop(&mut self) {
+        // Signal the thread and detach it rather than joining, a hung detector would otherwise
+        // block the drop.
+        self.stop
+            .get_mut()
+            .unwrap_or_else(|err| err.into_inner())
+            .take();
+        self.handle
+            .get_mut()
+            .unwrap_or_else(|err| err.into_inner())
+            .take();
+    }
+}
+
+impl fmt::Debug for RefreshingResource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefreshingResource")
+            .field("resource", &self.resource())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::{Key, KeyValue, Value};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+    use std::thread::Thread;
+    use std::time::Instant;
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    /// Returns the number of times it was called as the `count` attribute.
+    struct CountingDetector(Arc<AtomicUsize>);
+
+    impl ResourceDetector for CountingDetector {
+        fn detect(&self) -> Resource {
+            let count = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+            Resource::builder_empty()
+                .with_attribute(KeyValue::new("count", count as i64))
+                .build()
+        }
+    }
+
+    struct PanickingDetector;
+
+    impl ResourceDetector for PanickingDetector {
+        fn detect(&self) -> Resource {
+            panic!("detector failure")
+        }
+    }
+
+    fn wait_until(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(Instant::now() < deadline, "condition not met in time");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_detect_async() {
+        let resource = block_on(detect_async(CountingDetector(Arc::default())));
+        assert_eq!(
+            resource.get(&Key::from_static_str("count")),
+            Some(Value::from(1))
+        );
+    }
+
+    #[test]
+    fn test_detect_async_panicking_detector() {
+        let resource = block_on(detect_async(PanickingDetector));
+        assert_eq!(resource.len(), 0);
+    }
+
+    #[test]
+    fn test_refreshing_resource() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let changes = Arc::new(AtomicUsize::new(0));
+        let observed = Arc::clone(&changes);
+
+        let resource = RefreshingResource::builder(CountingDetector(Arc::clone(&calls)))
+            .with_refresh_interval(Duration::from_millis(20))
+            .with_on_change(move |_| {
+                observed.fetch_add(1, Ordering::SeqCst);
+            })
+            .build();
+
+        wait_until(|| changes.load(Ordering::SeqCst) >= 3);
+        resource.stop();
+
+        let after_stop = calls.load(Ordering::SeqCst);
+        assert_eq!(
+            resource.detect().get(&Key::from_static_str("count")),
+            Some(Value::from(after_stop as i64))
+        );
+        thread::sleep(Duration::from_millis(60));
+        assert_eq!(calls.load(Ordering::SeqCst), after_stop);
+    }
+
+    #[test]
+    fn test_refreshing_resource_notifies_only_on_change() {
+        struct StaticDetector;
+
+        impl ResourceDetector for StaticDetector {
+            fn detect(&self) -> Resource {
+                Resource::builder_empty()
+                    .with_attribute(KeyValue::new("key", "value"))
+                    .build()
+            }
+        }
+
+        let changes = Arc::new(AtomicUsize::new(0));
+        let observed = Arc::clone(&changes);
+        let resource = RefreshingResource::builder(StaticDetector)
+            .with_refresh_interval(Duration::from_millis(10))
+            .with_on_change(move |_| {
+                observed.fetch_add(1, Ordering::SeqCst);
+            })
+            .build();
+
+        wait_until(|| resource.resource().len() == 1);
+        thread::sleep(Duration::from_millis(100));
+        drop(resource);
+
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_refreshing_resource_drop_does_not_wait_for_detector() {
+        struct HungDetector;
+
+        impl ResourceDetector for HungDetector {
+            fn detect(&self) -> Resource {
+                thread::sleep(Duration::from_secs(5));
+                Resource::builder_empty().build()
+            }
+        }
+
+        let resource = RefreshingResource::builder(HungDetector).build();
+        let start = Instant::now();
+        drop(resource);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,281 @@
+//! Detector registry
+//!
+//! Select resource detectors by name, typically from the `OTEL_RESOURCE_DETECTORS` environment
+//! variable.
+use crate::{
+    DeploymentResourceDetector, HostIdResourceDetector, HostResourceDetector, K8sResourceDetector,
+    OsResourceDetector, ProcessResourceDetector,
+};
+use opentelemetry::otel_warn;
+use opentelemetry_sdk::resource::ResourceDetector;
+use std::env;
+use std::fmt;
+
+/// Environment variable holding the comma separated list of detectors to enable.
+pub const OTEL_RESOURCE_DETECTORS: &str = "OTEL_RESOURCE_DETECTORS";
+
+const ALL_DETECTORS: &str = "all";
+const NO_DETECTORS: &str = "none";
+
+type DetectorFactory = Box<dyn Fn() -> Box<dyn ResourceDetector> + Send + Sync>;
+
+/// A set of resource detector factories keyed by name.
+///
+/// [`DetectorRegistry::default`] contains the detectors of this crate:
+///
+/// | Name                | Detector                          |
+/// |---------------------|-----------------------------------|
+/// | `process`           | [`ProcessResourceDetector`]       |
+/// | `os`                | [`OsResourceDetector`]            |
+/// | `host`              | [`HostResourceDetector`]          |
+/// | `host_id`           | [`HostIdResourceDetector`]        |
+/// | `k8s`               | [`K8sResourceDetector`]           |
+/// | `deployment`        | [`DeploymentResourceDetector`]    |
+/// | `azure_vm`          | `AzureVmResourceDetector`         |
+/// | `azure_app_service` | `AzureAppServiceResourceDetector` |
+/// | `azure_functions`   | `AzureFunctionsResourceDetector`  |
+/// | `azure_aks`         | `AzureAksResourceDetector`        |
+///
+/// The Azure detectors are only registered with the `azure` feature. Detectors from other crates,
+/// for example `aws_lambda` or `gcp`, can be added with [`DetectorRegistry::with_detector`].
+///
+/// ```
+/// use opentelemetry_resource_detectors::{DetectorRegistry, OsResourceDetector};
+/// use opentelemetry_sdk::Resource;
+///
+/// let registry = DetectorRegistry::default()
+///     .with_detector("my_os", || Box::new(OsResourceDetector));
+///
+/// // OTEL_RESOURCE_DETECTORS=process,my_os
+/// let detectors = registry.detectors_from_env();
+/// let resource = Resource::builder().with_detectors(&detectors).build();
+/// ```
+pub struct DetectorRegistry {
+    factories: Vec<(String, DetectorFactory)>,
+}
+
+impl DetectorRegistry {
+    /// Create a registry without any detector.
+    pub fn new() -> Self {
+        Self {
+            factories: Vec::new(),
+        }
+    }
+
+    /// Register a detector factory under `name`, replacing any factory with the same name.
+    ///
+    /// Names are matched case-insensitively.
+    pub fn with_detector<F>(mut self, name: impl Into<String>, factory: F) -> Self
+    where
+        F: Fn() -> Box<dyn ResourceDetector> + Send + Sync + 'static,
+    {
+        let name = name.into().to_ascii_lowercase();
+        let factory: DetectorFactory = Box::new(factory);
+        match self
+            .factories
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some(entry) => entry.1 = factory,
+            None => self.factories.push((name, factory)),
+        }
+        self
+    }
+
+    /// Names of the registered detectors, in registration order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Create the detectors listed in a comma separated `selection`.
+    ///
+    /// Detectors are returned in the order they are listed, so when used with
+    /// [`ResourceBuilder::with_detectors`](opentelemetry_sdk::resource::ResourceBuilder::with_detectors)
+    /// the last one wins on conflicting attributes. `all` selects every registered detector and
+    /// `none` selects nothing, even when other detectors are listed with it. Unknown names are
+    /// reported through internal logs and skipped.
+    pub fn detectors(&self, selection: &str) -> Vec<Box<dyn ResourceDetector>> {
+        let names: Vec<String> = selection
+            .split(',')
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        if names.iter().any(|name| name == NO_DETECTORS) {
+            return Vec::new();
+        }
+
+        let mut detectors = Vec::new();
+        for name in names {
+            match name.as_str() {
+                ALL_DETECTORS => {
+                    detectors.extend(self.factories.iter().map(|(_, factory)| factory()))
+                }
+                _ => match self
+                    .factories
+                    .iter()
+                    .find(|(existing, _)| *existing == name)
+                {
+                    Some((_, factory)) => detectors.push(factory()),
+                    None => {
+                        otel_warn!(name: "DetectorRegistry.UnknownDetector", detector = name);
+                    }
+                },
+            }
+        }
+        detectors
+    }
+
+    /// Create the detectors listed in the `OTEL_RESOURCE_DETECTORS` environment variable.
+    ///
+    /// Returns no detector when the variable is not set.
+    pub fn detectors_from_env(&self) -> Vec<Box<dyn ResourceDetector>> {
+        env::var(OTEL_RESOURCE_DETECTORS)
+            .map(|selection| self.detectors(&selection))
+            .unwrap_or_default()
+    }
+}
+
+impl Default for DetectorRegistry {
+    fn default() -> Self {
+        Self::new()
+            .with_detector("process", || Box::new(ProcessResourceDetector::default()))
+            .with_detector("os", || Box::new(OsResourceDetector))
+            .with_detector("host", || Box::new(HostResourceDetector::default()))
+            .with_detector("host_id", || Box::new(HostIdResourceDetector::default()))
+            .with_detector("k8s", || Box::new(K8sResourceDetector))
+            .with_detector("deployment", || {
+                Box::new(DeploymentResourceDetector::default())
+            })
+            .with_azure_detectors()
+    }
+}
+
+impl DetectorRegistry {
+    #[cfg(feature = "azure")]
+    fn with_azure_detectors(self) -> Self {
+        self.with_detector("azure_vm", || {
+            Box::new(crate::AzureVmResourceDetector::default())
+        })
+        .with_detector("azure_app_service", || {
+            Box::new(crate::AzureAppServiceResourceDetector)
+        })
+        .with_detector("azure_functions", || {
+            Box::new(crate::AzureFunctionsResourceDetector)
+        })
+        .with_detector("azure_aks", || {
+            Box::new(crate::AzureAksResourceDetector::default())
+        })
+    }
+
+    #[cfg(not(feature = "azure"))]
+    fn with_azure_detectors(self) -> Self {
+        self
+    }
+}
+
+impl fmt::Debug for DetectorRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// Create the detectors listed in `OTEL_RESOURCE_DETECTORS` from the [default registry].
+///
+/// For example `OTEL_RESOURCE_DETECTORS=process,os,host` enables the process, OS and host
+/// detectors. See [`DetectorRegistry::detectors`] for the accepted values.
+///
+/// [default registry]: DetectorRegistry::default
+pub fn detectors_from_env() -> Vec<Box<dyn ResourceDetector>> {
+    DetectorRegistry::default().detectors_from_env()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::{Key, KeyValue, Value};
+    use opentelemetry_sdk::Resource;
+
+    struct StaticDetector(&'static str);
+
+    impl ResourceDetector for StaticDetector {
+        fn detect(&self) -> Resource {
+            Resource::builder_empty()
+                .with_attribute(KeyValue::new("detector", self.0))
+                .build()
+        }
+    }
+
+    fn test_registry() -> DetectorRegistry {
+        DetectorRegistry::new()
+            .with_detector("first", || Box::new(StaticDetector("first")))
+            .with_detector("second", || Box::new(StaticDetector("second")))
+    }
+
+    fn detect_all(detectors: &[Box<dyn ResourceDetector>]) -> Resource {
+        Resource::builder_empty().with_detectors(detectors).build()
+    }
+
+    #[test]
+    fn test_detectors_in_listed_order() {
+        let detectors = test_registry().detectors("second, FIRST");
+        assert_eq!(detectors.len(), 2);
+        assert_eq!(
+            detect_all(&detectors).get(&Key::from_static_str("detector")),
+            Some(Value::from("first"))
+        );
+    }
+
+    #[test]
+    fn test_detectors_all_and_none() {
+        assert_eq!(test_registry().detectors("all").len(), 2);
+        assert_eq!(test_registry().detectors("none").len(), 0);
+        assert_eq!(test_registry().detectors("first, none, all").len(), 0);
+        assert_eq!(test_registry().detectors("").len(), 0);
+    }
+
+    #[test]
+    fn test_detectors_skips_unknown() {
+        let detectors = test_registry().detectors("aws_ec2,first,gcp");
+        assert_eq!(detectors.len(), 1);
+    }
+
+    #[test]
+    fn test_with_detector_replaces_existing() {
+        let registry = test_registry().with_detector("First", || Box::new(StaticDetector("new")));
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["first", "second"]);
+        assert_eq!(
+            detect_all(&registry.detectors("first")).get(&Key::from_static_str("detector")),
+            Some(Value::from("new"))
+        );
+    }
+
+    #[test]
+    fn test_default_registry_names() {
+        let registry = DetectorRegistry::default();
+        let names = registry.names().collect::<Vec<_>>();
+        for name in ["process", "os", "host", "host_id", "k8s", "deployment"] {
+            assert!(names.contains(&name), "missing {name}");
+        }
+    }
+
+    #[test]
+    fn test_detectors_from_env() {
+        temp_env::with_var(OTEL_RESOURCE_DETECTORS, Some("os,process"), || {
+            let resource = detect_all(&detectors_from_env());
+            assert!(resource
+                .get(&Key::from_static_str(
+                    opentelemetry_semantic_conventions::attribute::OS_TYPE
+                ))
+                .is_some());
+            assert!(resource
+                .get(&Key::from_static_str(
+                    opentelemetry_semantic_conventions::attribute::PROCESS_PID
+                ))
+                .is_some());
+        });
+
+        temp_env::with_var_unset(OTEL_RESOURCE_DETECTORS, || {
+            assert!(detectors_from_env().is_empty());
+        });
+    }
+}
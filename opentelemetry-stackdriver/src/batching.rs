@@ -0,0 +1,185 @@
+//! Splitting of the spans written to Cloud Trace into requests by their encoded size rather
+//! than their count, so batches of spans with large attributes stay under the request size
+//! limit of the API while batches of small spans are written in few requests.
+use crate::proto::devtools::cloudtrace::v2::{BatchWriteSpansRequest, Span};
+use prost::Message;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tonic::codec::{Codec, EncodeBuf, Encoder, ProstCodec};
+use tonic::Status;
+
+/// Default limit of the encoded size of the spans of a request, well under the 4 MiB messages
+/// gRPC servers accept by default.
+pub(crate) const DEFAULT_MAX_REQUEST_BYTES: usize = 3 * 1024 * 1024;
+/// Smallest limit the requests shrink to while the API responds `RESOURCE_EXHAUSTED`.
+const MIN_REQUEST_BYTES: usize = 64 * 1024;
+
+/// Limit of the encoded size of the spans of a request, shared by the concurrent exports.
+///
+/// The limit is halved every time Cloud Trace responds `RESOURCE_EXHAUSTED`, and grows back by
+/// a sixteenth of the maximum after every successful write.
+#[derive(Debug)]
+pub(crate) struct BatchSizer {
+    min: usize,
+    max: usize,
+    limit: AtomicUsize,
+}
+
+impl BatchSizer {
+    pub(crate) fn new(max: usize) -> Self {
+        let max = max.max(1);
+        BatchSizer {
+            min: MIN_REQUEST_BYTES.min(max),
+            max,
+            limit: AtomicUsize::new(max),
+        }
+    }
+
+    /// The current limit, in bytes.
+    pub(crate) fn limit(&self) -> usize {
+        self.limit.load(Ordering::Relaxed)
+    }
+
+    /// Grow the limit after a successful write.
+    pub(crate) fn on_success(&self) {
+        let step = (self.max / 16).max(1);
+        self.update(|limit| limit.saturating_add(step).min(self.max));
+    }
+
+    /// Shrink the limit after a `RESOURCE_EXHAUSTED` response.
+    pub(crate) fn on_exhausted(&self) {
+        self.update(|limit| (limit / 2).max(self.min));
+    }
+
+    fn update(&self, f: impl Fn(usize) -> usize) {
+        let _ = self
+            .limit
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |limit| Some(f(limit)));
+    }
+}
+
+/// Take the first spans of `spans` fitting in a request of at most `limit` encoded bytes of
+/// spans.
+///
+/// A first span larger than the limit is written alone.
+pub(crate) fn take_request(spans: &mut Vec<Span>, limit: usize) -> Vec<Span> {
+    let mut size = 0;
+    let count = spans
+        .iter()
+        .take_while(|span| {
+            size += encoded_len(span);
+            size <= limit
+        })
+        .count()
+        .max(1)
+        .min(spans.len());
+    let rest = spans.split_off(count);
+    mem::replace(spans, rest)
+}
+
+/// Encoded size of `span` as an element of the `spans` field of a request: the field tag, the
+/// length prefix and the span.
+fn encoded_len(span: &Span) -> usize {
+    let len = span.encoded_len();
+    1 + prost::length_delimiter_len(len) + len
+}
+
+/// Codec of the `BatchWriteSpans` calls encoding a shared request, so the exporter still owns
+/// its spans after the call, to write them again if the request is rejected, without copying
+/// them beforehand.
+#[derive(Debug, Default)]
+pub(crate) struct SharedRequestCodec;
+
+impl Codec for SharedRequestCodec {
+    type Encode = Arc<BatchWriteSpansRequest>;
+    type Decode = ();
+    type Encoder = SharedRequestEncoder;
+    type Decoder = <ProstCodec<BatchWriteSpansRequest, ()> as Codec>::Decoder;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        SharedRequestEncoder
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        ProstCodec::<BatchWriteSpansRequest, ()>::default().decoder()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SharedRequestEncoder;
+
+impl Encoder for SharedRequestEncoder {
+    type Item = Arc<BatchWriteSpansRequest>;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        item.encode(dst)
+            .map_err(|e| Status::internal(format!("failed to encode request: {e}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::devtools::cloudtrace::v2::BatchWriteSpansRequest;
+
+    fn span(name_len: usize) -> Span {
+        Span {
+            name: "n".repeat(name_len),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_take_request() {
+        let mut spans = vec![span(100), span(100), span(5_000), span(100), span(100)];
+        let sizes = spans.iter().map(encoded_len).collect::<Vec<_>>();
+        let mut requests = Vec::new();
+        while !spans.is_empty() {
+            requests.push(take_request(&mut spans, 1_000));
+        }
+        let lens = requests.iter().map(Vec::len).collect::<Vec<_>>();
+        // The large span is written alone.
+        assert_eq!(lens, [2, 1, 2]);
+        assert_eq!(requests[1][0].name.len(), 5_000);
+
+        // The sizes add up to the encoded size of the request.
+        let request = BatchWriteSpansRequest {
+            name: String::new(),
+            spans: requests.into_iter().flatten().collect(),
+        };
+        assert_eq!(request.encoded_len(), sizes.iter().sum::<usize>());
+
+        let mut spans = vec![span(10); 50];
+        assert_eq!(take_request(&mut spans, usize::MAX).len(), 50);
+        assert!(spans.is_empty());
+        assert!(take_request(&mut spans, 1_000).is_empty());
+    }
+
+    #[test]
+    fn test_batch_sizer() {
+        let sizer = BatchSizer::new(1024 * 1024);
+        assert_eq!(sizer.limit(), 1024 * 1024);
+
+        sizer.on_exhausted();
+        sizer.on_exhausted();
+        assert_eq!(sizer.limit(), 256 * 1024);
+        for _ in 0..10 {
+            sizer.on_exhausted();
+        }
+        assert_eq!(sizer.limit(), MIN_REQUEST_BYTES);
+
+        sizer.on_success();
+        assert_eq!(sizer.limit(), MIN_REQUEST_BYTES + 64 * 1024);
+        for _ in 0..20 {
+            sizer.on_success();
+        }
+        assert_eq!(sizer.limit(), 1024 * 1024);
+
+        // Limits below the minimum are kept.
+        let sizer = BatchSizer::new(1_000);
+        sizer.on_exhausted();
+        assert_eq!(sizer.limit(), 1_000);
+    }
+}
@@ -0,0 +1,192 @@
+//! Resolution of the Google API endpoints and quota project, from the builder or the
+//! environment.
+use http::uri::Uri;
+use tonic::metadata::AsciiMetadataValue;
+use tonic::Request;
+
+use crate::Error;
+
+/// Environment variable holding the Cloud Trace endpoint.
+pub(crate) const TRACE_ENDPOINT_ENV: &str = "STACKDRIVER_TRACE_ENDPOINT";
+/// Environment variable holding the Cloud Logging endpoint.
+pub(crate) const LOGGING_ENDPOINT_ENV: &str = "STACKDRIVER_LOGGING_ENDPOINT";
+/// Environment variable holding the region of the Cloud Trace endpoint.
+pub(crate) const REGION_ENV: &str = "STACKDRIVER_REGION";
+/// Environment variable holding the quota project, as read by the Google client libraries.
+pub(crate) const QUOTA_PROJECT_ENV: &str = "GOOGLE_CLOUD_QUOTA_PROJECT";
+
+const TRACE_ENDPOINT: &str = "https://cloudtrace.googleapis.com:443";
+const LOGGING_ENDPOINT: &str = "https://logging.googleapis.com:443";
+/// Header billing the requests to the quota of a project.
+const QUOTA_PROJECT_HEADER: &str = "x-goog-user-project";
+
+/// The Cloud Trace endpoint: `endpoint`, else the endpoint of `region`, else the endpoint or
+/// the region of the environment, else the global endpoint.
+pub(crate) fn trace_endpoint(
+    endpoint: Option<Uri>,
+    region: Option<String>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Uri, Error> {
+    if let Some(endpoint) = endpoint {
+        return Ok(endpoint);
+    }
+    let endpoint = match region {
+        Some(region) => regional_trace_endpoint(&region),
+        None => match (env(TRACE_ENDPOINT_ENV), env(REGION_ENV)) {
+            (Some(endpoint), _) => endpoint,
+            (None, Some(region)) => regional_trace_endpoint(&region),
+            (None, None) => TRACE_ENDPOINT.to_string(),
+        },
+    };
+    parse(endpoint)
+}
+
+/// The Cloud Logging endpoint: `endpoint`, else the endpoint of the environment, else the
+/// global endpoint.
+pub(crate) fn logging_endpoint(
+    endpoint: Option<Uri>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Uri, Error> {
+    match endpoint {
+        Some(endpoint) => Ok(endpoint),
+        None => parse(env(LOGGING_ENDPOINT_ENV).unwrap_or_else(|| LOGGING_ENDPOINT.to_string())),
+    }
+}
+
+/// The `x-goog-user-project` header value of `quota_project`, else of the environment.
+pub(crate) fn quota_project(
+    quota_project: Option<String>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Option<AsciiMetadataValue>, Error> {
+    quota_project
+        .or_else(|| env(QUOTA_PROJECT_ENV))
+        .map(|project| AsciiMetadataValue::try_from(project).map_err(|e| Error::Other(e.into())))
+        .transpose()
+}
+
+/// Bill `request` to `quota_project`, if set.
+pub(crate) fn set_quota_project<T>(
+    request: &mut Request<T>,
+    quota_project: Option<&AsciiMetadataValue>,
+) {
+    if let Some(quota_project) = quota_project {
+        request
+            .metadata_mut()
+            .insert(QUOTA_PROJECT_HEADER, quota_project.clone());
+    }
+}
+
+/// A non-empty environment variable.
+pub(crate) fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn regional_trace_endpoint(region: &str) -> String {
+    format!("https://{region}-cloudtrace.googleapis.com:443")
+}
+
+fn parse(endpoint: String) -> Result<Uri, Error> {
+    endpoint
+        .parse()
+        .map_err(|e: http::uri::InvalidUri| Error::Other(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_trace_endpoint() {
+        let endpoint =
+            |endpoint: Option<&'static str>, region: Option<&str>, vars: &[(&str, &str)]| {
+                trace_endpoint(
+                    endpoint.map(Uri::from_static),
+                    region.map(str::to_string),
+                    env(vars),
+                )
+                .unwrap()
+                .to_string()
+            };
+        assert_eq!(
+            endpoint(None, None, &[]),
+            "https://cloudtrace.googleapis.com:443/"
+        );
+        assert_eq!(
+            endpoint(None, None, &[(REGION_ENV, "europe-west3")]),
+            "https://europe-west3-cloudtrace.googleapis.com:443/"
+        );
+        assert_eq!(
+            endpoint(
+                None,
+                None,
+                &[
+                    (TRACE_ENDPOINT_ENV, "http://localhost:8080"),
+                    (REGION_ENV, "europe-west3")
+                ]
+            ),
+            "http://localhost:8080/"
+        );
+        assert_eq!(
+            endpoint(
+                None,
+                Some("us-east1"),
+                &[(TRACE_ENDPOINT_ENV, "http://localhost:8080")]
+            ),
+            "https://us-east1-cloudtrace.googleapis.com:443/"
+        );
+        assert_eq!(
+            endpoint(Some("http://localhost:9090"), Some("us-east1"), &[]),
+            "http://localhost:9090/"
+        );
+        assert!(trace_endpoint(None, Some("not a region".into()), env(&[])).is_err());
+    }
+
+    #[test]
+    fn test_logging_endpoint() {
+        assert_eq!(
+            logging_endpoint(None, env(&[])).unwrap().to_string(),
+            "https://logging.googleapis.com:443/"
+        );
+        assert_eq!(
+            logging_endpoint(
+                None,
+                env(&[(LOGGING_ENDPOINT_ENV, "http://localhost:8080")])
+            )
+            .unwrap()
+            .to_string(),
+            "http://localhost:8080/"
+        );
+    }
+
+    #[test]
+    fn test_quota_project() {
+        let vars = [(QUOTA_PROJECT_ENV, "billing-project")];
+        let quota_project_header = |quota_project: Option<&str>, vars: &[(&str, &str)]| {
+            let value = super::quota_project(quota_project.map(str::to_string), env(vars)).unwrap();
+            let mut request = Request::new(());
+            set_quota_project(&mut request, value.as_ref());
+            request
+                .metadata()
+                .get(QUOTA_PROJECT_HEADER)
+                .map(|value| value.to_str().unwrap().to_string())
+        };
+        assert_eq!(quota_project_header(None, &[]), None);
+        assert_eq!(
+            quota_project_header(None, &vars).as_deref(),
+            Some("billing-project")
+        );
+        assert_eq!(
+            quota_project_header(Some("other-project"), &vars).as_deref(),
+            Some("other-project")
+        );
+        assert!(quota_project(Some("invalid\nproject".into()), env(&[])).is_err());
+    }
+}
@@ -13,18 +13,18 @@
 
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt,
     future::Future,
     sync::{
         atomic::{AtomicUsize, Ordering},
-        Arc, RwLock,
+        Arc, Mutex, RwLock,
     },
     time::{Duration, Instant},
 };
 
 use futures_util::stream::StreamExt;
-use opentelemetry::{otel_error, trace::SpanId, Key, KeyValue, Value};
+use opentelemetry::{otel_debug, otel_error, trace::SpanId, Key, KeyValue, Value};
 use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
 use opentelemetry_sdk::{
     trace::{SpanData, SpanExporter},
@@ -32,6 +32,8 @@ use opentelemetry_sdk::{
 };
 use opentelemetry_semantic_conventions as semconv;
 use thiserror::Error;
+use tonic::client::Grpc;
+use tonic::codegen::http::uri::PathAndQuery;
 #[cfg(feature = "gcp-authorizer")]
 use tonic::metadata::MetadataValue;
 #[cfg(any(
@@ -40,7 +42,7 @@ use tonic::metadata::MetadataValue;
     feature = "tls-webpki-roots"
 ))]
 use tonic::transport::ClientTlsConfig;
-use tonic::{transport::Channel, Code, Request};
+use tonic::{metadata::AsciiMetadataValue, transport::Channel, Code, Request};
 
 #[allow(clippy::derive_partial_eq_without_eq)] // tonic doesn't derive Eq for generated types
 #[allow(clippy::doc_overindented_list_items)]
@@ -49,11 +51,27 @@ pub mod proto;
 #[cfg(feature = "propagator")]
 pub mod google_trace_context_propagator;
 
+#[cfg(feature = "pubsub")]
+pub mod pubsub;
+
+#[cfg(feature = "logs")]
+mod logs;
+#[cfg(feature = "logs")]
+pub use logs::{StackDriverLogExporter, StackDriverLogExporterBuilder};
+
+mod batching;
+use batching::{BatchSizer, SharedRequestCodec};
+mod endpoint;
+mod quota;
+use quota::RateLimiter;
+
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
 use proto::devtools::cloudtrace::v2::span::time_event::Annotation;
 use proto::devtools::cloudtrace::v2::span::{
     Attributes, Link, Links, SpanKind, TimeEvent, TimeEvents,
 };
-use proto::devtools::cloudtrace::v2::trace_service_client::TraceServiceClient;
 use proto::devtools::cloudtrace::v2::{
     AttributeValue, BatchWriteSpansRequest, Span, TruncatableString,
 };
@@ -63,6 +81,10 @@ use proto::logging::v2::{
 };
 use proto::rpc::Status;
 
+/// Span attribute holding the project a span is written to, instead of the project of the
+/// authorizer.
+pub const GCP_PROJECT_ID_ATTRIBUTE: &str = "gcp.project_id";
+
 /// Exports opentelemetry tracing spans to Google StackDriver.
 ///
 /// As of the time of this writing, the opentelemetry crate exposes no link information
@@ -73,6 +95,9 @@ pub struct StackDriverExporter {
     pending_count: Arc<AtomicUsize>,
     maximum_shutdown_duration: Duration,
     resource: Arc<RwLock<Option<Resource>>>,
+    truncation_stats: Arc<Mutex<TruncationStats>>,
+    #[cfg(feature = "self-metrics")]
+    metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics,
 }
 
 impl StackDriverExporter {
@@ -83,14 +108,31 @@ impl StackDriverExporter {
     pub fn pending_count(&self) -> usize {
         self.pending_count.load(Ordering::Relaxed)
     }
+
+    /// Truncation performed on all the spans exported so far to meet the Cloud Trace limits.
+    pub fn truncation_stats(&self) -> TruncationStats {
+        match self.truncation_stats.lock() {
+            Ok(stats) => *stats,
+            Err(poisoned) => *poisoned.into_inner(),
+        }
+    }
 }
 
 impl SpanExporter for StackDriverExporter {
     async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        #[cfg(feature = "self-metrics")]
+        let count = batch.len() as u64;
         match self.tx.clone().try_send(batch) {
-            Err(e) => Err(OTelSdkError::InternalFailure(format!("{e:?}"))),
+            Err(e) => {
+                #[cfg(feature = "self-metrics")]
+                self.metrics.record_dropped(count, "queue_full");
+                Err(OTelSdkError::InternalFailure(format!("{e:?}")))
+            }
             Ok(()) => {
                 self.pending_count.fetch_add(1, Ordering::Relaxed);
+                #[cfg(feature = "self-metrics")]
+                self.metrics
+                    .record_queue_utilization(self.pending_count(), QUEUE_CAPACITY);
                 Ok(())
             }
         }
@@ -122,11 +164,15 @@ impl fmt::Debug for StackDriverExporter {
             pending_count,
             maximum_shutdown_duration,
             resource: _,
+            truncation_stats,
+            #[cfg(feature = "self-metrics")]
+            metrics: _,
         } = self;
         f.debug_struct("StackDriverExporter")
             .field("tx", &"(elided)")
             .field("pending_count", pending_count)
             .field("maximum_shutdown_duration", maximum_shutdown_duration)
+            .field("truncation_stats", truncation_stats)
             .finish()
     }
 }
@@ -137,6 +183,13 @@ pub struct Builder {
     maximum_shutdown_duration: Option<Duration>,
     num_concurrent_requests: Option<usize>,
     log_context: Option<LogContext>,
+    truncation_callback: Option<TruncationCallback>,
+    max_spans_per_second: Option<f64>,
+    max_request_bytes: Option<usize>,
+    trace_endpoint: Option<http::uri::Uri>,
+    logging_endpoint: Option<http::uri::Uri>,
+    region: Option<String>,
+    quota_project: Option<String>,
 }
 
 impl Builder {
@@ -162,6 +215,78 @@ impl Builder {
         self
     }
 
+    /// Call `callback` with the truncation performed on every batch whose spans exceeded the
+    /// Cloud Trace limits.
+    ///
+    /// The callback runs on the export future, it should not block.
+    pub fn truncation_callback(
+        mut self,
+        callback: impl Fn(&TruncationStats) + Send + Sync + 'static,
+    ) -> Self {
+        self.truncation_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Write at most `max_spans_per_second` spans every second to Cloud Trace, on average.
+    ///
+    /// Set it below the "spans ingested" quota of the projects, so bursts of spans are delayed
+    /// instead of being rejected by the API. Batches also wait and are retried when Cloud Trace
+    /// reports the quota as exceeded. The waits are logged and, with the `self-metrics` feature,
+    /// recorded as the `otel.exporter.throttle.duration` metric.
+    pub fn max_spans_per_second(mut self, max_spans_per_second: f64) -> Self {
+        self.max_spans_per_second = Some(max_spans_per_second);
+        self
+    }
+
+    /// Write at most `max_request_bytes` of encoded spans in each Cloud Trace request, splitting
+    /// the batches of spans with large attributes into several requests.
+    ///
+    /// If not set, defaults to 3 MiB. While Cloud Trace responds `RESOURCE_EXHAUSTED`, the size
+    /// of the requests is halved, down to 64 KiB, then grows back to `max_request_bytes` as the
+    /// writes succeed.
+    pub fn max_request_bytes(mut self, max_request_bytes: usize) -> Self {
+        self.max_request_bytes = Some(max_request_bytes);
+        self
+    }
+
+    /// Send the spans to the Cloud Trace API at `endpoint`, e.g. a mock server in tests.
+    ///
+    /// If not set, defaults to the endpoint of the [`region`](Self::region), then to the
+    /// `STACKDRIVER_TRACE_ENDPOINT` environment variable, the endpoint of the
+    /// `STACKDRIVER_REGION` environment variable, and `https://cloudtrace.googleapis.com:443`.
+    /// `http` endpoints are reached without TLS.
+    pub fn trace_endpoint(mut self, endpoint: http::uri::Uri) -> Self {
+        self.trace_endpoint = Some(endpoint);
+        self
+    }
+
+    /// Send the log entries of the `log_context` to the Cloud Logging API at `endpoint`.
+    ///
+    /// If not set, defaults to the `STACKDRIVER_LOGGING_ENDPOINT` environment variable, then to
+    /// `https://logging.googleapis.com:443`.
+    pub fn logging_endpoint(mut self, endpoint: http::uri::Uri) -> Self {
+        self.logging_endpoint = Some(endpoint);
+        self
+    }
+
+    /// Send the spans to the regional Cloud Trace endpoint of `region`, e.g.
+    /// `europe-west3-cloudtrace.googleapis.com` for `europe-west3`, to keep the data in the
+    /// region.
+    pub fn region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    /// Bill the requests to the quota of `project`, sent as the `x-goog-user-project` header,
+    /// rather than to the project of the credentials.
+    ///
+    /// If not set, defaults to the `GOOGLE_CLOUD_QUOTA_PROJECT` environment variable. The
+    /// credentials need the `serviceusage.services.use` permission on the project.
+    pub fn quota_project(mut self, project: impl Into<String>) -> Self {
+        self.quota_project = Some(project.into());
+        self
+    }
+
     pub async fn build<A: Authorizer>(
         self,
         authenticator: A,
@@ -173,49 +298,29 @@ impl Builder {
             maximum_shutdown_duration,
             num_concurrent_requests,
             log_context,
+            truncation_callback,
+            max_spans_per_second,
+            max_request_bytes,
+            trace_endpoint,
+            logging_endpoint,
+            region,
+            quota_project,
         } = self;
-        let uri = http::uri::Uri::from_static("https://cloudtrace.googleapis.com:443");
-
-        #[cfg(any(
-            feature = "tls-ring",
-            feature = "tls-native-roots",
-            feature = "tls-webpki-roots"
-        ))]
-        let tls_config = ClientTlsConfig::new().with_enabled_roots();
-
-        let trace_channel_builder = Channel::builder(uri);
-        #[cfg(any(
-            feature = "tls-ring",
-            feature = "tls-native-roots",
-            feature = "tls-webpki-roots"
-        ))]
-        let trace_channel_builder = trace_channel_builder
-            .tls_config(tls_config.clone())
-            .map_err(|e| Error::Transport(e.into()))?;
-
-        let trace_channel = trace_channel_builder
-            .connect()
-            .await
-            .map_err(|e| Error::Transport(e.into()))?;
+        let quota_project = endpoint::quota_project(quota_project, endpoint::env_var)?;
+        let trace_channel = connect(endpoint::trace_endpoint(
+            trace_endpoint,
+            region,
+            endpoint::env_var,
+        )?)
+        .await?;
 
         let log_client = match log_context {
             Some(log_context) => {
-                let log_channel_builder = Channel::builder(http::uri::Uri::from_static(
-                    "https://logging.googleapis.com:443",
-                ));
-                #[cfg(any(
-                    feature = "tls-ring",
-                    feature = "tls-native-roots",
-                    feature = "tls-webpki-roots"
-                ))]
-                let log_channel_builder = log_channel_builder
-                    .tls_config(tls_config)
-                    .map_err(|e| Error::Transport(e.into()))?;
-
-                let log_channel = log_channel_builder
-                    .connect()
-                    .await
-                    .map_err(|e| Error::Transport(e.into()))?;
+                let log_channel = connect(endpoint::logging_endpoint(
+                    logging_endpoint,
+                    endpoint::env_var,
+                )?)
+                .await?;
 
                 Some(LogClient {
                     client: LoggingServiceV2Client::new(log_channel),
@@ -225,7 +330,7 @@ impl Builder {
             None => None,
         };
 
-        let (tx, rx) = futures_channel::mpsc::channel(64);
+        let (tx, rx) = futures_channel::mpsc::channel(QUEUE_CAPACITY);
         let pending_count = Arc::new(AtomicUsize::new(0));
         let scopes = Arc::new(match log_client {
             Some(_) => vec![TRACE_APPEND, LOGGING_WRITE],
@@ -235,8 +340,21 @@ impl Builder {
         let count_clone = pending_count.clone();
         let resource = Arc::new(RwLock::new(None));
         let ctx_resource = resource.clone();
+        let truncation_stats = Arc::new(Mutex::new(TruncationStats::default()));
+        let ctx_truncation_stats = truncation_stats.clone();
+        let rate_limiter = max_spans_per_second.map(|rate| Arc::new(RateLimiter::new(rate)));
+        let batch_sizer = Arc::new(BatchSizer::new(
+            max_request_bytes.unwrap_or(batching::DEFAULT_MAX_REQUEST_BYTES),
+        ));
+        #[cfg(feature = "self-metrics")]
+        let metrics = opentelemetry_contrib::exporter_metrics::ExporterMetrics::new(
+            "stackdriver",
+            opentelemetry_contrib::exporter_metrics::Signal::Traces,
+        );
+        #[cfg(feature = "self-metrics")]
+        let ctx_metrics = metrics.clone();
         let future = async move {
-            let trace_client = TraceServiceClient::new(trace_channel);
+            let trace_client = Grpc::new(trace_channel);
             let authorizer = &authenticator;
             let log_client = log_client.clone();
             rx.for_each_concurrent(num_concurrent_requests, move |batch| {
@@ -252,6 +370,13 @@ impl Builder {
                     pending_count,
                     scopes,
                     resource,
+                    truncation_stats: ctx_truncation_stats.clone(),
+                    truncation_callback: truncation_callback.clone(),
+                    rate_limiter: rate_limiter.clone(),
+                    batch_sizer: batch_sizer.clone(),
+                    quota_project: quota_project.clone(),
+                    #[cfg(feature = "self-metrics")]
+                    metrics: ctx_metrics.clone(),
                 }
                 .export(batch)
             })
@@ -264,19 +389,50 @@ impl Builder {
             maximum_shutdown_duration: maximum_shutdown_duration
                 .unwrap_or_else(|| Duration::from_secs(5)),
             resource,
+            truncation_stats,
+            #[cfg(feature = "self-metrics")]
+            metrics,
         };
 
         Ok((exporter, future))
     }
 }
 
+/// Connect a channel to the Google API at `uri`.
+async fn connect(uri: http::uri::Uri) -> Result<Channel, Error> {
+    let channel_builder = Channel::builder(uri);
+    #[cfg(any(
+        feature = "tls-ring",
+        feature = "tls-native-roots",
+        feature = "tls-webpki-roots"
+    ))]
+    let channel_builder = match channel_builder.uri().scheme_str() {
+        Some("https") => channel_builder
+            .tls_config(ClientTlsConfig::new().with_enabled_roots())
+            .map_err(|e| Error::Transport(e.into()))?,
+        _ => channel_builder,
+    };
+
+    channel_builder
+        .connect()
+        .await
+        .map_err(|e| Error::Transport(e.into()))
+}
+
 struct ExporterContext<'a, A> {
-    trace_client: TraceServiceClient<Channel>,
+    trace_client: Grpc<Channel>,
     log_client: Option<LogClient>,
     authorizer: &'a A,
     pending_count: Arc<AtomicUsize>,
     scopes: Arc<Vec<&'static str>>,
     resource: Arc<RwLock<Option<Resource>>>,
+    truncation_stats: Arc<Mutex<TruncationStats>>,
+    truncation_callback: Option<TruncationCallback>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    batch_sizer: Arc<BatchSizer>,
+    quota_project: Option<AsciiMetadataValue>,
+    #[cfg(feature = "self-metrics")]
+    metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics,
 }
 
 impl<A: Authorizer> ExporterContext<'_, A>
@@ -287,22 +443,48 @@ where
         use proto::devtools::cloudtrace::v2::span::time_event::Value;
 
         let mut entries = Vec::new();
-        let mut spans = Vec::with_capacity(batch.len());
+        // Cloud Trace writes the spans of a request to a single project.
+        let mut projects = BTreeMap::<String, Vec<Span>>::new();
+        let mut truncation = TruncationStats::default();
+        let monitored_resource = self
+            .resource
+            .read()
+            .ok()
+            .and_then(|resource| {
+                resource.as_ref().map(|resource| {
+                    MonitoredResource::from_resource(resource, self.authorizer.project_id())
+                })
+            })
+            .map(proto::api::MonitoredResource::from);
         for span in batch {
+            let project_id = span_project_id(&span.attributes)
+                .unwrap_or_else(|| self.authorizer.project_id())
+                .to_owned();
             let trace_id = hex::encode(span.span_context.trace_id().to_bytes());
             let span_id = hex::encode(span.span_context.span_id().to_bytes());
+            let mut dropped_annotations_count = span.events.dropped_count as i32;
             let time_event = match &self.log_client {
-                None => span
-                    .events
-                    .into_iter()
-                    .map(|event| TimeEvent {
-                        time: Some(event.timestamp.into()),
-                        value: Some(Value::Annotation(Annotation {
-                            description: Some(to_truncate(event.name.into_owned())),
-                            ..Default::default()
-                        })),
-                    })
-                    .collect(),
+                None => {
+                    let dropped = span.events.len().saturating_sub(MAX_ANNOTATIONS_PER_SPAN);
+                    truncation.dropped_events += dropped as u64;
+                    dropped_annotations_count += dropped as i32;
+                    span.events
+                        .into_iter()
+                        .take(MAX_ANNOTATIONS_PER_SPAN)
+                        .map(|event| {
+                            let description =
+                                to_truncate(event.name.into_owned(), MAX_ANNOTATION_LEN);
+                            truncation.count_truncated(&description);
+                            TimeEvent {
+                                time: Some(event.timestamp.into()),
+                                value: Some(Value::Annotation(Annotation {
+                                    description: Some(description),
+                                    ..Default::default()
+                                })),
+                            }
+                        })
+                        .collect()
+                }
                 Some(client) => {
                     entries.extend(span.events.into_iter().map(|event| {
                         let (mut level, mut target, mut labels) =
@@ -324,7 +506,6 @@ where
                                 }
                             }
                         }
-                        let project_id = self.authorizer.project_id();
                         let log_id = &client.context.log_id;
                         LogEntry {
                             log_name: format!("projects/{project_id}/logs/{log_id}"),
@@ -349,20 +530,42 @@ where
                 }
             };
 
+            let status = status(span.status, &span.attributes);
+            if let Some(status) = &status {
+                truncation.count_truncated(&status.message);
+            }
             let resource = self.resource.read().ok();
-            let attributes = match resource {
+            let mut attributes = match resource {
                 Some(resource) => Attributes::new(span.attributes, resource.as_ref()),
                 None => Attributes::new(span.attributes, None),
             };
+            if let Some(monitored_resource) = &monitored_resource {
+                attributes.push_monitored_resource(monitored_resource);
+            }
+            if let Some(kind) = span_kind_label(&span.span_kind) {
+                attributes.push(
+                    Cow::Owned(Key::from_static_str(SPAN_KIND_LABEL)),
+                    Cow::Owned(Value::from(kind)),
+                );
+            }
+            truncation.count_attributes(&attributes);
+
+            let display_name = to_truncate(span.name.into_owned(), MAX_DISPLAY_NAME_LEN);
+            truncation.count_truncated(&display_name);
+            let links = transform_links(&span.links);
+            if let Some(links) = &links {
+                truncation.dropped_links += (links.dropped_links_count as u64)
+                    .saturating_sub(u64::from(span.links.dropped_count));
+            }
 
-            spans.push(Span {
-                name: format!(
-                    "projects/{}/traces/{}/spans/{}",
-                    self.authorizer.project_id(),
-                    hex::encode(span.span_context.trace_id().to_bytes()),
-                    hex::encode(span.span_context.span_id().to_bytes())
-                ),
-                display_name: Some(to_truncate(span.name.into_owned())),
+            let name = format!(
+                "projects/{project_id}/traces/{}/spans/{}",
+                hex::encode(span.span_context.trace_id().to_bytes()),
+                hex::encode(span.span_context.span_id().to_bytes())
+            );
+            projects.entry(project_id).or_default().push(Span {
+                name,
+                display_name: Some(display_name),
                 span_id: hex::encode(span.span_context.span_id().to_bytes()),
                 // From the API docs: If this is a root span,
                 // then this field must be empty.
@@ -375,25 +578,33 @@ where
                 attributes: Some(attributes),
                 time_events: Some(TimeEvents {
                     time_event,
+                    dropped_annotations_count,
                     ..Default::default()
                 }),
-                links: transform_links(&span.links),
-                status: status(span.status),
+                links,
+                status: status.map(|status| Status {
+                    code: status.code as i32,
+                    message: status.message.value,
+                    details: vec![],
+                }),
                 span_kind: SpanKind::from(span.span_kind) as i32,
                 ..Default::default()
             });
         }
 
-        let mut req = Request::new(BatchWriteSpansRequest {
-            name: format!("projects/{}", self.authorizer.project_id()),
-            spans,
-        });
+        if !truncation.is_empty() {
+            match self.truncation_stats.lock() {
+                Ok(mut stats) => stats.add(&truncation),
+                Err(poisoned) => poisoned.into_inner().add(&truncation),
+            }
+            if let Some(callback) = &self.truncation_callback {
+                callback(&truncation);
+            }
+        }
 
         self.pending_count.fetch_sub(1, Ordering::Relaxed);
-        if let Err(e) = self.authorizer.authorize(&mut req, &self.scopes).await {
-            otel_error!(name: "ExportAuthorizeError", error = format!("{e:?}"));
-        } else if let Err(e) = self.trace_client.batch_write_spans(req).await {
-            otel_error!(name: "ExportTransportError", error = format!("{e:?}"));
+        for (project_id, spans) in projects {
+            self.write_spans(project_id, spans).await;
         }
 
         let client = match &mut self.log_client {
@@ -414,12 +625,132 @@ where
             resource: None,
         });
 
+        endpoint::set_quota_project(&mut req, self.quota_project.as_ref());
         if let Err(e) = self.authorizer.authorize(&mut req, &self.scopes).await {
             otel_error!(name: "ExportAuthorizeError", error = format!("{e:?}"));
         } else if let Err(e) = client.client.write_log_entries(req).await {
             otel_error!(name: "ExportTransportError", error =  format!("{e:?}"));
         }
     }
+
+    /// Write `spans` to `project_id`, one request of the current size limit at a time.
+    ///
+    /// While the quota of the project is exceeded, the spans of the rejected request are split
+    /// again with the reduced size limit and retried after a backoff.
+    async fn write_spans(&mut self, project_id: String, mut spans: Vec<Span>) {
+        let name = format!("projects/{project_id}");
+        let mut retries = 0;
+        let mut backoff = QUOTA_RETRY_BACKOFF;
+        while !spans.is_empty() {
+            let req = BatchWriteSpansRequest {
+                name: name.clone(),
+                spans: batching::take_request(&mut spans, self.batch_sizer.limit()),
+            };
+            #[cfg(feature = "self-metrics")]
+            let (count, start) = (req.spans.len() as u64, Instant::now());
+
+            let result = match self.write_request(req).await {
+                Err(("quota_exceeded", mut rejected)) if retries < MAX_QUOTA_RETRIES => {
+                    retries += 1;
+                    self.throttle(backoff).await;
+                    backoff *= 2;
+                    rejected.append(&mut spans);
+                    spans = rejected;
+                    continue;
+                }
+                Ok(()) => {
+                    retries = 0;
+                    backoff = QUOTA_RETRY_BACKOFF;
+                    Ok(())
+                }
+                Err((error_type, _)) => {
+                    if error_type == "quota_exceeded" {
+                        otel_error!(name: "ExportQuotaExceeded", retries = retries);
+                    }
+                    Err(error_type)
+                }
+            };
+            #[cfg(feature = "self-metrics")]
+            match result {
+                Ok(()) => self.metrics.record_success(count, start.elapsed()),
+                Err(error_type) => self
+                    .metrics
+                    .record_failure(count, start.elapsed(), error_type),
+            }
+            #[cfg(not(feature = "self-metrics"))]
+            let _ = result;
+        }
+    }
+
+    /// Write `req`, waiting on the rate limit, returning the error type if it failed, with the
+    /// spans of the request if it was rejected by the quota.
+    async fn write_request(
+        &mut self,
+        req: BatchWriteSpansRequest,
+    ) -> Result<(), (&'static str, Vec<Span>)> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            let wait = rate_limiter.reserve(req.spans.len() as f64);
+            self.throttle(wait).await;
+        }
+
+        let req = Arc::new(req);
+        let mut request = Request::new(req.clone());
+        endpoint::set_quota_project(&mut request, self.quota_project.as_ref());
+        if let Err(e) = self.authorizer.authorize(&mut request, &self.scopes).await {
+            otel_error!(name: "ExportAuthorizeError", error = format!("{e:?}"));
+            return Err(("authorize", Vec::new()));
+        }
+        if let Err(e) = self.trace_client.ready().await {
+            otel_error!(name: "ExportTransportError", error = format!("{e:?}"));
+            return Err(("transport", Vec::new()));
+        }
+        let path = PathAndQuery::from_static(
+            "/google.devtools.cloudtrace.v2.TraceService/BatchWriteSpans",
+        );
+        let result = self
+            .trace_client
+            .unary(request, path, SharedRequestCodec)
+            .await;
+        match result {
+            Ok(_) => {
+                self.batch_sizer.on_success();
+                Ok(())
+            }
+            Err(status) if status.code() == Code::ResourceExhausted => {
+                self.batch_sizer.on_exhausted();
+                otel_debug!(name: "ExportQuotaExceeded", error = format!("{status:?}"));
+                // The request is released once sent, its spans are only copied if it isn't.
+                let spans =
+                    Arc::try_unwrap(req).map_or_else(|req| req.spans.clone(), |req| req.spans);
+                Err(("quota_exceeded", spans))
+            }
+            Err(status) => {
+                otel_error!(name: "ExportTransportError", error = format!("{status:?}"));
+                Err(("transport", Vec::new()))
+            }
+        }
+    }
+
+    async fn throttle(&self, wait: Duration) {
+        if wait.is_zero() {
+            return;
+        }
+        otel_debug!(name: "ExportThrottled", wait_ms = wait.as_millis() as u64);
+        #[cfg(feature = "self-metrics")]
+        self.metrics.record_throttle(wait);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// The project of the [`GCP_PROJECT_ID_ATTRIBUTE`] attribute of a span, which routes it to
+/// another project than the one of the authorizer.
+fn span_project_id(attributes: &[KeyValue]) -> Option<&str> {
+    attributes.iter().find_map(|kv| match &kv.value {
+        Value::String(project_id) if kv.key.as_str() == GCP_PROJECT_ID_ATTRIBUTE => {
+            Some(project_id.as_str())
+        }
+        _ => None,
+    })
 }
 
 #[cfg(feature = "gcp-authorizer")]
@@ -497,11 +828,23 @@ impl From<Value> for AttributeValue {
         use proto::devtools::cloudtrace::v2::attribute_value;
         let new_value = match v {
             Value::Bool(v) => attribute_value::Value::BoolValue(v),
-            Value::F64(v) => attribute_value::Value::StringValue(to_truncate(v.to_string())),
+            Value::F64(v) => attribute_value::Value::StringValue(to_truncate(
+                v.to_string(),
+                MAX_ATTRIBUTE_VALUE_LEN,
+            )),
             Value::I64(v) => attribute_value::Value::IntValue(v),
-            Value::String(v) => attribute_value::Value::StringValue(to_truncate(v.to_string())),
-            Value::Array(_) => attribute_value::Value::StringValue(to_truncate(v.to_string())),
-            _ => attribute_value::Value::StringValue(to_truncate("".to_string())),
+            Value::String(v) => attribute_value::Value::StringValue(to_truncate(
+                v.to_string(),
+                MAX_ATTRIBUTE_VALUE_LEN,
+            )),
+            Value::Array(_) => attribute_value::Value::StringValue(to_truncate(
+                v.to_string(),
+                MAX_ATTRIBUTE_VALUE_LEN,
+            )),
+            _ => attribute_value::Value::StringValue(to_truncate(
+                "".to_string(),
+                MAX_ATTRIBUTE_VALUE_LEN,
+            )),
         };
         AttributeValue {
             value: Some(new_value),
@@ -509,13 +852,71 @@ impl From<Value> for AttributeValue {
     }
 }
 
-fn to_truncate(s: String) -> TruncatableString {
+/// Truncate `s` to at most `limit` bytes, not splitting UTF-8 characters.
+fn to_truncate(mut s: String, limit: usize) -> TruncatableString {
+    let mut truncated_byte_count = 0;
+    if s.len() > limit {
+        let mut end = limit;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        truncated_byte_count = (s.len() - end) as i32;
+        s.truncate(end);
+    }
     TruncatableString {
         value: s,
-        ..Default::default()
+        truncated_byte_count,
+    }
+}
+
+/// Truncation performed by the exporter to meet the [Cloud Trace limits].
+///
+/// [Cloud Trace limits]: https://cloud.google.com/trace/docs/quotas#trace-limits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TruncationStats {
+    /// Attributes dropped as the span had more than 32 attributes, or a key longer than 128
+    /// bytes.
+    pub dropped_attributes: u64,
+    /// Span names longer than 128 bytes, and attribute values or event names longer than 256
+    /// bytes, which were truncated.
+    pub truncated_strings: u64,
+    /// Events dropped as the span had more than 32 events.
+    pub dropped_events: u64,
+    /// Links dropped as the span had more than 128 links.
+    pub dropped_links: u64,
+}
+
+impl TruncationStats {
+    /// Whether no truncation was performed.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn add(&mut self, other: &TruncationStats) {
+        self.dropped_attributes += other.dropped_attributes;
+        self.truncated_strings += other.truncated_strings;
+        self.dropped_events += other.dropped_events;
+        self.dropped_links += other.dropped_links;
+    }
+
+    fn count_truncated(&mut self, s: &TruncatableString) {
+        self.truncated_strings += u64::from(s.truncated_byte_count > 0);
+    }
+
+    fn count_attributes(&mut self, attributes: &Attributes) {
+        use proto::devtools::cloudtrace::v2::attribute_value;
+
+        self.dropped_attributes += attributes.dropped_attributes_count as u64;
+        for value in attributes.attribute_map.values() {
+            if let Some(attribute_value::Value::StringValue(s)) = &value.value {
+                self.count_truncated(s);
+            }
+        }
     }
 }
 
+type TruncationCallback = Arc<dyn Fn(&TruncationStats) + Send + Sync>;
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("authorizer error: {0}")]
@@ -541,6 +942,8 @@ enum LogSeverity {
     Info = 200,
     Warning = 400,
     Error = 500,
+    #[cfg(feature = "logs")]
+    Critical = 600,
 }
 
 #[derive(Clone)]
@@ -562,8 +965,17 @@ pub struct LogContext {
 
 impl From<LogContext> for InternalLogContext {
     fn from(cx: LogContext) -> Self {
+        Self {
+            log_id: cx.log_id,
+            resource: cx.resource.into(),
+        }
+    }
+}
+
+impl From<MonitoredResource> for proto::api::MonitoredResource {
+    fn from(resource: MonitoredResource) -> Self {
         let mut labels = HashMap::default();
-        let resource = match cx.resource {
+        match resource {
             MonitoredResource::AppEngine {
                 project_id,
                 module_id,
@@ -753,11 +1165,6 @@ impl From<LogContext> for InternalLogContext {
                     labels,
                 }
             }
-        };
-
-        Self {
-            log_id: cx.log_id,
-            resource,
         }
     }
 }
@@ -822,7 +1229,106 @@ pub enum MonitoredResource {
     },
 }
 
+impl MonitoredResource {
+    /// Map an OpenTelemetry `Resource` to the monitored resource it runs on.
+    ///
+    /// The type is selected from the `cloud.platform` attribute set by the GCP resource
+    /// detectors: `gce_instance` on Compute Engine, `k8s_container` on Kubernetes (or when
+    /// `k8s.cluster.name` is set), `cloud_run_revision` or `cloud_run_job` on Cloud Run,
+    /// `gae_app` on App Engine and `cloud_function` on Cloud Functions. Other resources map to
+    /// `generic_task`, identified by `service.namespace`, `service.name` and
+    /// `service.instance.id`.
+    ///
+    /// The project is `cloud.account.id` if set with a `gcp` `cloud.provider`, `project_id`
+    /// otherwise. The spans exported by [`StackDriverExporter`] are labelled with this mapping,
+    /// while log entries are written to the resource of their [`LogContext`], which can be set
+    /// to this mapping to attach every signal of a process to the same monitored resource.
+    pub fn from_resource(resource: &Resource, project_id: &str) -> Self {
+        let get = |key: &'static str| {
+            resource
+                .get(&Key::from_static_str(key))
+                .map(|value| value.to_string())
+        };
+        let project_id = get(semconv::resource::CLOUD_ACCOUNT_ID)
+            .filter(|_| get(semconv::resource::CLOUD_PROVIDER).as_deref() == Some("gcp"))
+            .unwrap_or_else(|| project_id.to_owned());
+        let zone = get(semconv::resource::CLOUD_AVAILABILITY_ZONE);
+        let region = get(semconv::resource::CLOUD_REGION);
+
+        match get(semconv::resource::CLOUD_PLATFORM).as_deref() {
+            Some("gcp_compute_engine") => MonitoredResource::ComputeEngine {
+                project_id,
+                instance_id: get(semconv::resource::HOST_ID),
+                zone,
+            },
+            Some("gcp_cloud_run")
+                if get(semconv::resource::GCP_CLOUD_RUN_JOB_EXECUTION).is_some() =>
+            {
+                MonitoredResource::CloudRunJob {
+                    project_id,
+                    job_name: get(semconv::resource::FAAS_NAME),
+                    location: region,
+                }
+            }
+            Some("gcp_cloud_run") => MonitoredResource::CloudRunRevision {
+                project_id,
+                service_name: get(semconv::resource::FAAS_NAME),
+                revision_name: get(semconv::resource::FAAS_VERSION),
+                location: region,
+                configuration_name: get(semconv::resource::FAAS_NAME),
+            },
+            Some("gcp_app_engine") => MonitoredResource::AppEngine {
+                project_id,
+                module_id: get(semconv::resource::FAAS_NAME),
+                version_id: get(semconv::resource::FAAS_VERSION),
+                zone,
+            },
+            Some("gcp_cloud_functions") => MonitoredResource::CloudFunction {
+                project_id,
+                function_name: get(semconv::resource::FAAS_NAME),
+                region,
+            },
+            platform
+                if platform == Some("gcp_kubernetes_engine")
+                    || get(semconv::resource::K8S_CLUSTER_NAME).is_some() =>
+            {
+                MonitoredResource::KubernetesEngine {
+                    project_id,
+                    location: zone.or(region),
+                    cluster_name: get(semconv::resource::K8S_CLUSTER_NAME),
+                    namespace_name: get(semconv::resource::K8S_NAMESPACE_NAME),
+                    pod_name: get(semconv::resource::K8S_POD_NAME),
+                    container_name: get(semconv::resource::K8S_CONTAINER_NAME),
+                }
+            }
+            _ => MonitoredResource::GenericTask {
+                project_id,
+                location: Some(zone.or(region).unwrap_or_else(|| "global".to_owned())),
+                namespace: get(semconv::resource::SERVICE_NAMESPACE),
+                job: get(semconv::resource::SERVICE_NAME),
+                task_id: get(semconv::resource::SERVICE_INSTANCE_ID)
+                    .or_else(|| get(semconv::resource::HOST_ID))
+                    .or_else(|| get(semconv::resource::HOST_NAME)),
+            },
+        }
+    }
+}
+
 impl Attributes {
+    /// Add the labels of the monitored resource the span was produced on, which Cloud Trace
+    /// reads from the `g.co/r/{type}/{label}` attributes.
+    fn push_monitored_resource(&mut self, resource: &proto::api::MonitoredResource) {
+        for (label, value) in &resource.labels {
+            if label == "project_id" {
+                continue;
+            }
+            self.push(
+                Cow::Owned(Key::from(format!("g.co/r/{}/{label}", resource.r#type))),
+                Cow::Owned(Value::from(value.clone())),
+            );
+        }
+    }
+
     /// Combines `EvictedHashMap` and `Resource` attributes into a maximum of 32.
     ///
     /// The `Resource` takes precedence over the `EvictedHashMap` attributes.
@@ -884,9 +1390,11 @@ fn transform_links(links: &opentelemetry_sdk::trace::SpanLinks) -> Option<Links>
     }
 
     Some(Links {
-        dropped_links_count: links.dropped_count as i32,
+        dropped_links_count: (links.dropped_count as usize
+            + links.len().saturating_sub(MAX_LINKS_PER_SPAN)) as i32,
         link: links
             .iter()
+            .take(MAX_LINKS_PER_SPAN)
             .map(|link| Link {
                 trace_id: hex::encode(link.span_context.trace_id().to_bytes()),
                 span_id: hex::encode(link.span_context.span_id().to_bytes()),
@@ -952,24 +1460,95 @@ impl From<opentelemetry::trace::SpanKind> for SpanKind {
     }
 }
 
-fn status(value: opentelemetry::trace::Status) -> Option<Status> {
+/// Label holding the span kind, for the tools reading it rather than the `span_kind` field.
+const SPAN_KIND_LABEL: &str = "/span/kind";
+
+/// The value of the `/span/kind` label, left out for internal spans as Cloud Trace does.
+fn span_kind_label(span_kind: &opentelemetry::trace::SpanKind) -> Option<&'static str> {
+    match span_kind {
+        opentelemetry::trace::SpanKind::Client => Some("client"),
+        opentelemetry::trace::SpanKind::Server => Some("server"),
+        opentelemetry::trace::SpanKind::Producer => Some("producer"),
+        opentelemetry::trace::SpanKind::Consumer => Some("consumer"),
+        opentelemetry::trace::SpanKind::Internal => None,
+    }
+}
+
+/// Status of a span, before conversion to the proto.
+struct SpanStatus {
+    code: Code,
+    message: TruncatableString,
+}
+
+/// The Cloud Trace status of a span.
+///
+/// Unset statuses are left out, which Cloud Trace shows as OK. Errors take the code of the
+/// `rpc.grpc.status_code` attribute, or the code matching the `http.response.status_code`
+/// attribute, `UNKNOWN` otherwise, and their description truncated to 256 bytes.
+fn status(value: opentelemetry::trace::Status, attributes: &[KeyValue]) -> Option<SpanStatus> {
     match value {
-        opentelemetry::trace::Status::Ok => Some(Status {
-            code: Code::Ok as i32,
-            message: "".to_owned(),
-            details: vec![],
+        opentelemetry::trace::Status::Ok => Some(SpanStatus {
+            code: Code::Ok,
+            message: TruncatableString::default(),
         }),
         opentelemetry::trace::Status::Unset => None,
-        opentelemetry::trace::Status::Error { description } => Some(Status {
-            code: Code::Unknown as i32,
-            message: description.into(),
-            details: vec![],
+        opentelemetry::trace::Status::Error { description } => Some(SpanStatus {
+            code: error_code(attributes),
+            message: to_truncate(description.into_owned(), MAX_STATUS_MESSAGE_LEN),
         }),
     }
 }
+
+fn error_code(attributes: &[KeyValue]) -> Code {
+    let int_attribute = |key: &str| {
+        attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .and_then(|kv| match &kv.value {
+                Value::I64(value) => Some(*value),
+                Value::String(value) => value.as_str().parse().ok(),
+                _ => None,
+            })
+    };
+    if let Some(code) = int_attribute(semconv::attribute::RPC_GRPC_STATUS_CODE)
+        .and_then(|code| i32::try_from(code).ok())
+        .filter(|code| *code != Code::Ok as i32)
+    {
+        return Code::from_i32(code);
+    }
+    let http_status = int_attribute(semconv::attribute::HTTP_RESPONSE_STATUS_CODE)
+        .or_else(|| int_attribute(semconv::attribute::HTTP_STATUS_CODE));
+    // https://github.com/googleapis/googleapis/blob/master/google/rpc/code.proto
+    match http_status {
+        Some(400) => Code::InvalidArgument,
+        Some(401) => Code::Unauthenticated,
+        Some(403) => Code::PermissionDenied,
+        Some(404) => Code::NotFound,
+        Some(409) => Code::Aborted,
+        Some(429) => Code::ResourceExhausted,
+        Some(499) => Code::Cancelled,
+        Some(500) => Code::Internal,
+        Some(501) => Code::Unimplemented,
+        Some(503) => Code::Unavailable,
+        Some(504) => Code::DeadlineExceeded,
+        _ => Code::Unknown,
+    }
+}
 const TRACE_APPEND: &str = "https://www.googleapis.com/auth/trace.append";
 const LOGGING_WRITE: &str = "https://www.googleapis.com/auth/logging.write";
 const MAX_ATTRIBUTES_PER_SPAN: usize = 32;
+const MAX_ANNOTATIONS_PER_SPAN: usize = 32;
+const MAX_LINKS_PER_SPAN: usize = 128;
+const MAX_DISPLAY_NAME_LEN: usize = 128;
+const MAX_ATTRIBUTE_VALUE_LEN: usize = 256;
+const MAX_ANNOTATION_LEN: usize = 256;
+const MAX_STATUS_MESSAGE_LEN: usize = 256;
+/// Number of batches waiting to be uploaded before exports are rejected.
+const QUEUE_CAPACITY: usize = 64;
+/// Retries of a batch rejected because the quota of its project is exceeded.
+const MAX_QUOTA_RETRIES: u32 = 3;
+/// Wait before the first retry of a batch rejected because of the quota, doubled every retry.
+const QUOTA_RETRY_BACKOFF: Duration = Duration::from_secs(1);
 
 #[cfg(test)]
 mod tests {
@@ -977,6 +1556,218 @@ mod tests {
     use opentelemetry::{KeyValue, Value};
     use opentelemetry_semantic_conventions as semcov;
 
+    fn monitored_resource(attributes: &[(&'static str, &'static str)]) -> MonitoredResource {
+        let resource = Resource::builder_empty()
+            .with_attributes(attributes.iter().map(|(k, v)| KeyValue::new(*k, *v)))
+            .build();
+        MonitoredResource::from_resource(&resource, "default-project")
+    }
+
+    fn labels(resource: MonitoredResource) -> (String, Vec<(String, String)>) {
+        let resource = proto::api::MonitoredResource::from(resource);
+        let mut labels = resource.labels.into_iter().collect::<Vec<_>>();
+        labels.sort();
+        (resource.r#type, labels)
+    }
+
+    fn expected(r#type: &str, labels: &[(&str, &str)]) -> (String, Vec<(String, String)>) {
+        let mut labels = labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>();
+        labels.sort();
+        (r#type.to_string(), labels)
+    }
+
+    #[test]
+    fn test_monitored_resource_gce_instance() {
+        let resource = monitored_resource(&[
+            (semcov::resource::CLOUD_PROVIDER, "gcp"),
+            (semcov::resource::CLOUD_PLATFORM, "gcp_compute_engine"),
+            (semcov::resource::CLOUD_ACCOUNT_ID, "my-project"),
+            (semcov::resource::CLOUD_AVAILABILITY_ZONE, "us-central1-a"),
+            (semcov::resource::HOST_ID, "1234"),
+        ]);
+        assert_eq!(
+            labels(resource),
+            expected(
+                "gce_instance",
+                &[
+                    ("project_id", "my-project"),
+                    ("zone", "us-central1-a"),
+                    ("instance_id", "1234"),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_monitored_resource_k8s_container() {
+        let resource = monitored_resource(&[
+            (semcov::resource::CLOUD_PLATFORM, "gcp_kubernetes_engine"),
+            (semcov::resource::CLOUD_REGION, "us-central1"),
+            (semcov::resource::K8S_CLUSTER_NAME, "cluster"),
+            (semcov::resource::K8S_NAMESPACE_NAME, "default"),
+            (semcov::resource::K8S_POD_NAME, "pod"),
+            (semcov::resource::K8S_CONTAINER_NAME, "app"),
+        ]);
+        assert_eq!(
+            labels(resource),
+            expected(
+                "k8s_container",
+                &[
+                    ("project_id", "default-project"),
+                    ("location", "us-central1"),
+                    ("cluster_name", "cluster"),
+                    ("namespace_name", "default"),
+                    ("pod_name", "pod"),
+                    ("container_name", "app"),
+                ]
+            )
+        );
+
+        // Kubernetes outside of GKE
+        let resource = monitored_resource(&[(semcov::resource::K8S_CLUSTER_NAME, "cluster")]);
+        assert_eq!(labels(resource).0, "k8s_container");
+    }
+
+    #[test]
+    fn test_monitored_resource_cloud_run() {
+        let resource = monitored_resource(&[
+            (semcov::resource::CLOUD_PLATFORM, "gcp_cloud_run"),
+            (semcov::resource::CLOUD_REGION, "europe-west1"),
+            (semcov::resource::FAAS_NAME, "service"),
+            (semcov::resource::FAAS_VERSION, "service-00001-abc"),
+        ]);
+        assert_eq!(
+            labels(resource),
+            expected(
+                "cloud_run_revision",
+                &[
+                    ("project_id", "default-project"),
+                    ("location", "europe-west1"),
+                    ("service_name", "service"),
+                    ("configuration_name", "service"),
+                    ("revision_name", "service-00001-abc"),
+                ]
+            )
+        );
+
+        let resource = monitored_resource(&[
+            (semcov::resource::CLOUD_PLATFORM, "gcp_cloud_run"),
+            (semcov::resource::CLOUD_REGION, "europe-west1"),
+            (semcov::resource::FAAS_NAME, "job"),
+            (semcov::resource::GCP_CLOUD_RUN_JOB_EXECUTION, "job-abc"),
+        ]);
+        assert_eq!(
+            labels(resource),
+            expected(
+                "cloud_run_job",
+                &[
+                    ("project_id", "default-project"),
+                    ("location", "europe-west1"),
+                    ("job_name", "job"),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_monitored_resource_serverless() {
+        let resource = monitored_resource(&[
+            (semcov::resource::CLOUD_PLATFORM, "gcp_app_engine"),
+            (semcov::resource::FAAS_NAME, "default"),
+            (semcov::resource::FAAS_VERSION, "v1"),
+        ]);
+        assert_eq!(
+            labels(resource),
+            expected(
+                "gae_app",
+                &[
+                    ("project_id", "default-project"),
+                    ("module_id", "default"),
+                    ("version_id", "v1"),
+                ]
+            )
+        );
+
+        let resource = monitored_resource(&[
+            (semcov::resource::CLOUD_PLATFORM, "gcp_cloud_functions"),
+            (semcov::resource::CLOUD_REGION, "us-east1"),
+            (semcov::resource::FAAS_NAME, "function"),
+        ]);
+        assert_eq!(
+            labels(resource),
+            expected(
+                "cloud_function",
+                &[
+                    ("project_id", "default-project"),
+                    ("region", "us-east1"),
+                    ("function_name", "function"),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_monitored_resource_generic_task() {
+        let resource = monitored_resource(&[
+            (semcov::resource::SERVICE_NAMESPACE, "shop"),
+            (semcov::resource::SERVICE_NAME, "checkout"),
+            (semcov::resource::SERVICE_INSTANCE_ID, "instance-1"),
+        ]);
+        assert_eq!(
+            labels(resource),
+            expected(
+                "generic_task",
+                &[
+                    ("project_id", "default-project"),
+                    ("location", "global"),
+                    ("namespace", "shop"),
+                    ("job", "checkout"),
+                    ("task_id", "instance-1"),
+                ]
+            )
+        );
+
+        let resource = monitored_resource(&[
+            (semcov::resource::CLOUD_PROVIDER, "aws"),
+            (semcov::resource::CLOUD_PLATFORM, "aws_ec2"),
+            (semcov::resource::CLOUD_ACCOUNT_ID, "123456789012"),
+            (semcov::resource::CLOUD_REGION, "eu-west-3"),
+            (semcov::resource::HOST_NAME, "host"),
+        ]);
+        assert_eq!(
+            labels(resource),
+            expected(
+                "generic_task",
+                &[
+                    ("project_id", "default-project"),
+                    ("location", "eu-west-3"),
+                    ("task_id", "host"),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_attributes_monitored_resource_labels() {
+        let mut attributes = Attributes::new(vec![], None);
+        attributes.push_monitored_resource(&proto::api::MonitoredResource::from(
+            monitored_resource(&[
+                (semcov::resource::CLOUD_PLATFORM, "gcp_compute_engine"),
+                (semcov::resource::HOST_ID, "1234"),
+            ]),
+        ));
+        assert_eq!(attributes.attribute_map.len(), 1);
+        assert_eq!(
+            attributes
+                .attribute_map
+                .get("g.co/r/gce_instance/instance_id"),
+            Some(&AttributeValue::from(Value::from("1234")))
+        );
+    }
+
     #[test]
     fn test_attributes_mapping() {
         let capacity = 10;
@@ -1133,4 +1924,142 @@ mod tests {
         assert_eq!(actual.attribute_map.len(), 1);
         assert_eq!(actual.dropped_attributes_count, 1);
     }
+
+    #[test]
+    fn test_to_truncate() {
+        let actual = to_truncate("short".to_string(), 8);
+        assert_eq!(actual.value, "short");
+        assert_eq!(actual.truncated_byte_count, 0);
+
+        // "é" is 2 bytes long, and is not split
+        let actual = to_truncate("aaéé".to_string(), 5);
+        assert_eq!(actual.value, "aaé");
+        assert_eq!(actual.truncated_byte_count, 2);
+    }
+
+    #[test]
+    fn test_span_kind() {
+        use opentelemetry::trace::SpanKind as OtelSpanKind;
+
+        // Values of `google.devtools.cloudtrace.v2.Span.SpanKind`.
+        for (kind, value, label) in [
+            (OtelSpanKind::Internal, 1, None),
+            (OtelSpanKind::Server, 2, Some("server")),
+            (OtelSpanKind::Client, 3, Some("client")),
+            (OtelSpanKind::Producer, 4, Some("producer")),
+            (OtelSpanKind::Consumer, 5, Some("consumer")),
+        ] {
+            assert_eq!(span_kind_label(&kind), label);
+            assert_eq!(SpanKind::from(kind) as i32, value);
+        }
+    }
+
+    #[test]
+    fn test_status() {
+        use opentelemetry::trace::Status as OtelStatus;
+
+        assert!(status(OtelStatus::Unset, &[]).is_none());
+
+        let ok = status(OtelStatus::Ok, &[]).unwrap();
+        assert_eq!(ok.code as i32, 0);
+        assert_eq!(ok.message.value, "");
+
+        // Values of `google.rpc.Code`.
+        let error = status(OtelStatus::error("failed"), &[]).unwrap();
+        assert_eq!(error.code as i32, 2);
+        assert_eq!(error.message.value, "failed");
+        let error = status(
+            OtelStatus::error("not found"),
+            &[KeyValue::new(
+                semcov::attribute::HTTP_RESPONSE_STATUS_CODE,
+                404i64,
+            )],
+        )
+        .unwrap();
+        assert_eq!(error.code as i32, 5);
+        let error = status(
+            OtelStatus::error("unavailable"),
+            &[
+                KeyValue::new(semcov::attribute::HTTP_RESPONSE_STATUS_CODE, 200i64),
+                KeyValue::new(semcov::attribute::RPC_GRPC_STATUS_CODE, 14i64),
+            ],
+        )
+        .unwrap();
+        assert_eq!(error.code as i32, 14);
+
+        let error = status(
+            OtelStatus::error("e".repeat(MAX_STATUS_MESSAGE_LEN + 10)),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(error.message.value.len(), MAX_STATUS_MESSAGE_LEN);
+        assert_eq!(error.message.truncated_byte_count, 10);
+    }
+
+    #[test]
+    fn test_truncation_stats() {
+        let mut attributes: Vec<KeyValue> = (0..MAX_ATTRIBUTES_PER_SPAN)
+            .map(|i| KeyValue::new(format!("key{i}"), i as i64))
+            .collect();
+        attributes[0] = KeyValue::new("key0", "v".repeat(MAX_ATTRIBUTE_VALUE_LEN + 1));
+        attributes.push(KeyValue::new("dropped", "value"));
+
+        let mut stats = TruncationStats::default();
+        stats.count_attributes(&Attributes::new(attributes, None));
+        stats.count_truncated(&to_truncate("name".repeat(64), MAX_DISPLAY_NAME_LEN));
+        assert_eq!(
+            stats,
+            TruncationStats {
+                dropped_attributes: 1,
+                truncated_strings: 2,
+                ..Default::default()
+            }
+        );
+
+        let mut total = TruncationStats::default();
+        assert!(total.is_empty());
+        total.add(&stats);
+        total.add(&stats);
+        assert_eq!(total.dropped_attributes, 2);
+        assert_eq!(total.truncated_strings, 4);
+    }
+
+    #[test]
+    fn test_transform_links_limit() {
+        use opentelemetry::trace::{SpanContext, TraceFlags, TraceId, TraceState};
+
+        let link = opentelemetry::trace::Link::with_context(SpanContext::new(
+            TraceId::from(1u128),
+            SpanId::from(1u64),
+            TraceFlags::SAMPLED,
+            false,
+            TraceState::default(),
+        ));
+        let mut links = opentelemetry_sdk::trace::SpanLinks::default();
+        links.links = vec![link; MAX_LINKS_PER_SPAN + 2];
+        links.dropped_count = 1;
+
+        let actual = transform_links(&links).unwrap();
+        assert_eq!(actual.link.len(), MAX_LINKS_PER_SPAN);
+        assert_eq!(actual.dropped_links_count, 3);
+    }
+
+    #[test]
+    fn test_span_project_id() {
+        assert_eq!(span_project_id(&[KeyValue::new("http.route", "/")]), None);
+        assert_eq!(
+            span_project_id(&[
+                KeyValue::new("http.route", "/"),
+                KeyValue::new(GCP_PROJECT_ID_ATTRIBUTE, "other-project"),
+            ]),
+            Some("other-project")
+        );
+        assert_eq!(
+            span_project_id(&[KeyValue::new(
+                semcov::resource::CLOUD_ACCOUNT_ID,
+                "other-project"
+            )]),
+            None
+        );
+    }
 }
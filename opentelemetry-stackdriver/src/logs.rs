@@ -0,0 +1,309 @@
+//! Export of OpenTelemetry log records to Cloud Logging.
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use opentelemetry::logs::{AnyValue, Severity};
+use opentelemetry::{otel_error, Key};
+use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
+use opentelemetry_sdk::logs::{LogBatch, LogExporter, SdkLogRecord};
+use prost_types::{value::Kind, ListValue, Struct};
+use tonic::{metadata::AsciiMetadataValue, transport::Channel, Request};
+
+use crate::proto::logging::v2::{
+    log_entry::Payload, logging_service_v2_client::LoggingServiceV2Client, LogEntry,
+    LogEntrySourceLocation, WriteLogEntriesRequest,
+};
+use crate::{
+    connect, endpoint, Authorizer, Error, InternalLogContext, LogContext, LogSeverity,
+    LOGGING_WRITE,
+};
+
+/// Exports OpenTelemetry log records to Google Cloud Logging.
+///
+/// Map bodies are written as the `jsonPayload` of the log entries, so their fields can be
+/// queried in the Logs Explorer, other bodies as the `textPayload`. String, numeric and boolean
+/// attributes become labels, except the `code.*` attributes, which fill the `sourceLocation`.
+pub struct StackDriverLogExporter<A> {
+    client: LoggingServiceV2Client<Channel>,
+    context: Arc<InternalLogContext>,
+    authorizer: Arc<A>,
+    quota_project: Option<AsciiMetadataValue>,
+}
+
+impl<A: Authorizer> StackDriverLogExporter<A>
+where
+    Error: From<A::Error>,
+{
+    /// Connect to Cloud Logging, writing the entries to the log and resource of `log_context`.
+    ///
+    /// The endpoint and quota project are the defaults of [`StackDriverLogExporterBuilder`].
+    pub async fn new(authorizer: A, log_context: LogContext) -> Result<Self, Error> {
+        StackDriverLogExporterBuilder::default()
+            .build(authorizer, log_context)
+            .await
+    }
+}
+
+/// Helper type to build a [`StackDriverLogExporter`].
+#[derive(Clone, Debug, Default)]
+pub struct StackDriverLogExporterBuilder {
+    logging_endpoint: Option<http::uri::Uri>,
+    quota_project: Option<String>,
+}
+
+impl StackDriverLogExporterBuilder {
+    /// Send the log entries to the Cloud Logging API at `endpoint`, e.g. a mock server in tests.
+    ///
+    /// If not set, defaults to the `STACKDRIVER_LOGGING_ENDPOINT` environment variable, then to
+    /// `https://logging.googleapis.com:443`. `http` endpoints are reached without TLS.
+    pub fn logging_endpoint(mut self, endpoint: http::uri::Uri) -> Self {
+        self.logging_endpoint = Some(endpoint);
+        self
+    }
+
+    /// Bill the requests to the quota of `project`, sent as the `x-goog-user-project` header,
+    /// rather than to the project of the credentials.
+    ///
+    /// If not set, defaults to the `GOOGLE_CLOUD_QUOTA_PROJECT` environment variable. The
+    /// credentials need the `serviceusage.services.use` permission on the project.
+    pub fn quota_project(mut self, project: impl Into<String>) -> Self {
+        self.quota_project = Some(project.into());
+        self
+    }
+
+    /// Connect to Cloud Logging, writing the entries to the log and resource of `log_context`.
+    pub async fn build<A: Authorizer>(
+        self,
+        authorizer: A,
+        log_context: LogContext,
+    ) -> Result<StackDriverLogExporter<A>, Error>
+    where
+        Error: From<A::Error>,
+    {
+        let channel = connect(endpoint::logging_endpoint(
+            self.logging_endpoint,
+            endpoint::env_var,
+        )?)
+        .await?;
+        Ok(StackDriverLogExporter {
+            client: LoggingServiceV2Client::new(channel),
+            context: Arc::new(InternalLogContext::from(log_context)),
+            authorizer: Arc::new(authorizer),
+            quota_project: endpoint::quota_project(self.quota_project, endpoint::env_var)?,
+        })
+    }
+}
+
+impl<A> fmt::Debug for StackDriverLogExporter<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StackDriverLogExporter")
+            .field("log_id", &self.context.log_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<A: Authorizer> LogExporter for StackDriverLogExporter<A>
+where
+    Error: From<A::Error>,
+{
+    async fn export(&self, batch: LogBatch<'_>) -> OTelSdkResult {
+        let project_id = self.authorizer.project_id();
+        let entries = batch
+            .iter()
+            .map(|(record, _)| log_entry(record, project_id))
+            .collect();
+
+        let mut req = Request::new(WriteLogEntriesRequest {
+            log_name: format!("projects/{project_id}/logs/{}", self.context.log_id),
+            entries,
+            dry_run: false,
+            labels: HashMap::default(),
+            partial_success: true,
+            resource: Some(self.context.resource.clone()),
+        });
+
+        endpoint::set_quota_project(&mut req, self.quota_project.as_ref());
+        if let Err(e) = self.authorizer.authorize(&mut req, &[LOGGING_WRITE]).await {
+            otel_error!(name: "ExportAuthorizeError", error = format!("{e:?}"));
+            return Err(OTelSdkError::InternalFailure(format!("{e:?}")));
+        }
+        if let Err(e) = self.client.clone().write_log_entries(req).await {
+            otel_error!(name: "ExportTransportError", error = format!("{e:?}"));
+            return Err(OTelSdkError::InternalFailure(format!("{e:?}")));
+        }
+        Ok(())
+    }
+}
+
+/// Convert `record` to a log entry, without its log name and resource which are set on the
+/// request.
+fn log_entry(record: &SdkLogRecord, project_id: &str) -> LogEntry {
+    let mut labels = HashMap::new();
+    let mut source_location = LogEntrySourceLocation::default();
+    let mut has_source_location = false;
+    for (key, value) in record.attributes_iter() {
+        has_source_location |= set_source_location(&mut source_location, key, value);
+        if !key.as_str().starts_with("code.") {
+            if let Some(value) = label_value(value) {
+                labels.insert(key.to_string(), value);
+            }
+        }
+    }
+
+    let mut entry = LogEntry {
+        severity: record
+            .severity_number()
+            .map_or(LogSeverity::Default, LogSeverity::from) as i32,
+        timestamp: record
+            .timestamp()
+            .or_else(|| record.observed_timestamp())
+            .map(Into::into),
+        labels,
+        source_location: has_source_location.then_some(source_location),
+        payload: record.body().map(payload),
+        ..Default::default()
+    };
+    if let Some(trace_context) = record.trace_context() {
+        entry.trace = format!("projects/{project_id}/traces/{}", trace_context.trace_id);
+        entry.span_id = trace_context.span_id.to_string();
+        entry.trace_sampled = trace_context
+            .trace_flags
+            .is_some_and(|flags| flags.is_sampled());
+    }
+    entry
+}
+
+/// Fill the field of `location` matching the `code.*` attribute `key`, supporting the current
+/// and deprecated semantic conventions.
+fn set_source_location(location: &mut LogEntrySourceLocation, key: &Key, value: &AnyValue) -> bool {
+    match (key.as_str(), value) {
+        ("code.file.path" | "code.filepath", AnyValue::String(file)) => {
+            location.file = file.to_string();
+        }
+        ("code.line.number" | "code.lineno", AnyValue::Int(line)) => location.line = *line,
+        ("code.function.name" | "code.function", AnyValue::String(function)) => {
+            location.function = function.to_string();
+        }
+        _ => return false,
+    }
+    true
+}
+
+fn label_value(value: &AnyValue) -> Option<String> {
+    match value {
+        AnyValue::String(s) => Some(s.to_string()),
+        AnyValue::Int(i) => Some(i.to_string()),
+        AnyValue::Double(d) => Some(d.to_string()),
+        AnyValue::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn payload(body: &AnyValue) -> Payload {
+    match body {
+        AnyValue::Map(map) => Payload::JsonPayload(Struct {
+            fields: map
+                .iter()
+                .map(|(key, value)| (key.to_string(), json_value(value)))
+                .collect(),
+        }),
+        AnyValue::ListAny(_) | AnyValue::Bytes(_) => Payload::JsonPayload(Struct {
+            fields: [("message".to_string(), json_value(body))].into(),
+        }),
+        scalar => Payload::TextPayload(label_value(scalar).unwrap_or_default()),
+    }
+}
+
+fn json_value(value: &AnyValue) -> prost_types::Value {
+    let kind = match value {
+        AnyValue::Int(i) => Kind::NumberValue(*i as f64),
+        AnyValue::Double(d) => Kind::NumberValue(*d),
+        AnyValue::String(s) => Kind::StringValue(s.to_string()),
+        AnyValue::Boolean(b) => Kind::BoolValue(*b),
+        AnyValue::Bytes(bytes) => Kind::StringValue(hex::encode(bytes.as_slice())),
+        AnyValue::ListAny(values) => Kind::ListValue(ListValue {
+            values: values.iter().map(json_value).collect(),
+        }),
+        AnyValue::Map(map) => Kind::StructValue(Struct {
+            fields: map
+                .iter()
+                .map(|(key, value)| (key.to_string(), json_value(value)))
+                .collect(),
+        }),
+        _ => Kind::NullValue(0),
+    };
+    prost_types::Value { kind: Some(kind) }
+}
+
+impl From<Severity> for LogSeverity {
+    fn from(severity: Severity) -> Self {
+        match severity as i32 {
+            i32::MIN..=8 => LogSeverity::Debug,
+            9..=12 => LogSeverity::Info,
+            13..=16 => LogSeverity::Warning,
+            17..=20 => LogSeverity::Error,
+            _ => LogSeverity::Critical,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::logs::{LogRecord, Logger, LoggerProvider};
+    use opentelemetry_sdk::logs::SdkLoggerProvider;
+
+    fn record() -> SdkLogRecord {
+        SdkLoggerProvider::builder()
+            .build()
+            .logger("test")
+            .create_log_record()
+    }
+
+    #[test]
+    fn test_map_body_json_payload() {
+        let mut record = record();
+        record.set_severity_number(Severity::Warn);
+        record.set_body(AnyValue::Map(Box::new(
+            [
+                (Key::new("user"), AnyValue::from("alice")),
+                (Key::new("count"), AnyValue::Int(3)),
+            ]
+            .into(),
+        )));
+        record.add_attribute("http.route", "/users");
+        record.add_attribute("code.file.path", "src/main.rs");
+        record.add_attribute("code.line.number", AnyValue::Int(42));
+
+        let entry = log_entry(&record, "project");
+        assert_eq!(entry.severity, LogSeverity::Warning as i32);
+        assert_eq!(
+            entry.labels,
+            HashMap::from([("http.route".to_string(), "/users".to_string())])
+        );
+        let source_location = entry.source_location.unwrap();
+        assert_eq!(source_location.file, "src/main.rs");
+        assert_eq!(source_location.line, 42);
+        let Some(Payload::JsonPayload(payload)) = entry.payload else {
+            panic!("expected a json payload");
+        };
+        assert_eq!(
+            payload.fields["user"].kind,
+            Some(Kind::StringValue("alice".to_string()))
+        );
+        assert_eq!(payload.fields["count"].kind, Some(Kind::NumberValue(3.0)));
+    }
+
+    #[test]
+    fn test_scalar_body_text_payload() {
+        let mut record = record();
+        record.set_body("hello".into());
+
+        let entry = log_entry(&record, "project");
+        assert_eq!(entry.severity, LogSeverity::Default as i32);
+        assert!(entry.source_location.is_none());
+        assert_eq!(
+            entry.payload,
+            Some(Payload::TextPayload("hello".to_string()))
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! Trace context propagation through Pub/Sub messages.
+//!
+//! The Google Cloud client libraries propagate the W3C trace context of a published message in
+//! its `googclient_traceparent` and `googclient_tracestate` attributes. [`inject_context`] and
+//! [`extract_context`] read and write these attributes, so the spans of the subscribers of a
+//! topic continue the traces of its publishers, whatever library they use.
+//!
+//! [`publish_span`] and [`subscribe_span`] build the spans of the publication and the
+//! processing of a message, with the messaging attributes of the client libraries.
+//!
+//! ```no_run
+//! use opentelemetry::trace::{TraceContextExt, Tracer};
+//! use opentelemetry::{global, Context};
+//! use opentelemetry_stackdriver::pubsub;
+//! use std::collections::HashMap;
+//!
+//! let tracer = global::tracer("orders");
+//! let topic = "projects/my-project/topics/orders";
+//!
+//! // Publisher
+//! let span = tracer.build(pubsub::publish_span(topic));
+//! let cx = Context::current_with_span(span);
+//! let mut attributes = HashMap::new();
+//! pubsub::inject_context(&cx, &mut attributes);
+//! // publish the message with `attributes`...
+//!
+//! // Subscriber, with the `attributes` and id of the received message
+//! let parent = pubsub::extract_context(&attributes);
+//! let span = tracer.build_with_context(
+//!     pubsub::subscribe_span("projects/my-project/subscriptions/billing", "1234"),
+//!     &parent,
+//! );
+//! ```
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::{SpanBuilder, SpanKind};
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use std::collections::HashMap;
+
+/// Prefix of the message attributes carrying the trace context.
+pub const ATTRIBUTE_PREFIX: &str = "googclient_";
+
+const MESSAGING_SYSTEM: &str = "gcp_pubsub";
+
+/// [`Injector`] writing the fields of a propagator to the attributes of a Pub/Sub message,
+/// prefixed with [`ATTRIBUTE_PREFIX`].
+#[derive(Debug)]
+pub struct PubsubInjector<'a>(&'a mut HashMap<String, String>);
+
+impl<'a> PubsubInjector<'a> {
+    /// Write to the message `attributes`.
+    pub fn new(attributes: &'a mut HashMap<String, String>) -> Self {
+        PubsubInjector(attributes)
+    }
+}
+
+impl Injector for PubsubInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.insert(format!("{ATTRIBUTE_PREFIX}{key}"), value);
+    }
+}
+
+/// [`Extractor`] reading the fields of a propagator from the attributes of a Pub/Sub message,
+/// prefixed with [`ATTRIBUTE_PREFIX`].
+#[derive(Debug)]
+pub struct PubsubExtractor<'a>(&'a HashMap<String, String>);
+
+impl<'a> PubsubExtractor<'a> {
+    /// Read from the message `attributes`.
+    pub fn new(attributes: &'a HashMap<String, String>) -> Self {
+        PubsubExtractor(attributes)
+    }
+}
+
+impl Extractor for PubsubExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .get(&format!("{ATTRIBUTE_PREFIX}{key}"))
+            .map(String::as_str)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0
+            .keys()
+            .filter_map(|key| key.strip_prefix(ATTRIBUTE_PREFIX))
+            .collect()
+    }
+}
+
+/// Write the W3C trace context of `cx` to the message `attributes`, as the Google Cloud client
+/// libraries do.
+///
+/// Use a [`PubsubInjector`] to write the fields of another propagator.
+pub fn inject_context(cx: &Context, attributes: &mut HashMap<String, String>) {
+    TraceContextPropagator::new().inject_context(cx, &mut PubsubInjector::new(attributes));
+}
+
+/// The context of the W3C trace context written to the message `attributes` by the publisher,
+/// with the current context as parent.
+pub fn extract_context(attributes: &HashMap<String, String>) -> Context {
+    TraceContextPropagator::new().extract(&PubsubExtractor::new(attributes))
+}
+
+/// The builder of the producer span publishing a message to `topic`, the topic id or its
+/// `projects/{project}/topics/{topic}` resource name.
+pub fn publish_span(topic: &str) -> SpanBuilder {
+    let (project, topic) = split_resource_name(topic, "topics");
+    let mut attributes = vec![
+        KeyValue::new("messaging.system", MESSAGING_SYSTEM),
+        KeyValue::new("messaging.destination.name", topic.to_string()),
+        KeyValue::new("messaging.operation.name", "publish"),
+        KeyValue::new("messaging.operation.type", "send"),
+    ];
+    attributes.extend(project.map(|project| KeyValue::new("gcp.project_id", project.to_string())));
+    SpanBuilder::from_name(format!("{topic} publish"))
+        .with_kind(SpanKind::Producer)
+        .with_attributes(attributes)
+}
+
+/// The builder of the consumer span processing the message `message_id` received from
+/// `subscription`, the subscription id or its `projects/{project}/subscriptions/{subscription}`
+/// resource name.
+///
+/// Build it with the context returned by [`extract_context`] to continue the trace of the
+/// publisher.
+pub fn subscribe_span(subscription: &str, message_id: &str) -> SpanBuilder {
+    let (project, subscription) = split_resource_name(subscription, "subscriptions");
+    let mut attributes = vec![
+        KeyValue::new("messaging.system", MESSAGING_SYSTEM),
+        KeyValue::new(
+            "messaging.destination.subscription.name",
+            subscription.to_string(),
+        ),
+        KeyValue::new("messaging.operation.name", "subscribe"),
+        KeyValue::new("messaging.operation.type", "process"),
+        KeyValue::new("messaging.message.id", message_id.to_string()),
+    ];
+    attributes.extend(project.map(|project| KeyValue::new("gcp.project_id", project.to_string())));
+    SpanBuilder::from_name(format!("{subscription} subscribe"))
+        .with_kind(SpanKind::Consumer)
+        .with_attributes(attributes)
+}
+
+/// Split `projects/{project}/{collection}/{id}` into the project and the id, or return `name`
+/// as the id.
+fn split_resource_name<'a>(name: &'a str, collection: &str) -> (Option<&'a str>, &'a str) {
+    let parsed = name.strip_prefix("projects/").and_then(|rest| {
+        let (project, rest) = rest.split_once('/')?;
+        let id = rest.strip_prefix(collection)?.strip_prefix('/')?;
+        Some((Some(project), id))
+    });
+    parsed.unwrap_or((None, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::testing::trace::TestSpan;
+    use opentelemetry::trace::{
+        SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState,
+    };
+
+    #[test]
+    fn test_propagation() {
+        let span_context = SpanContext::new(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+            TraceFlags::SAMPLED,
+            false,
+            TraceState::NONE,
+        );
+        let cx = Context::new().with_span(TestSpan(span_context.clone()));
+
+        let mut attributes = HashMap::from([("key".to_string(), "value".to_string())]);
+        inject_context(&cx, &mut attributes);
+        assert_eq!(
+            attributes["googclient_traceparent"],
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+        let keys = PubsubExtractor::new(&attributes).keys();
+        assert!(keys.contains(&"traceparent"));
+        assert!(!keys.contains(&"key"));
+
+        let extracted = extract_context(&attributes);
+        let extracted = extracted.span().span_context().clone();
+        assert_eq!(extracted.trace_id(), span_context.trace_id());
+        assert_eq!(extracted.span_id(), span_context.span_id());
+        assert!(extracted.is_remote());
+
+        assert!(!extract_context(&HashMap::new()).has_active_span());
+    }
+
+    #[test]
+    fn test_spans() {
+        let publish = publish_span("projects/my-project/topics/orders");
+        assert_eq!(publish.name, "orders publish");
+        assert_eq!(publish.span_kind, Some(SpanKind::Producer));
+        let attributes = publish.attributes.unwrap();
+        assert!(attributes.contains(&KeyValue::new("messaging.destination.name", "orders")));
+        assert!(attributes.contains(&KeyValue::new("gcp.project_id", "my-project")));
+
+        let subscribe = subscribe_span("billing", "1234");
+        assert_eq!(subscribe.name, "billing subscribe");
+        assert_eq!(subscribe.span_kind, Some(SpanKind::Consumer));
+        let attributes = subscribe.attributes.unwrap();
+        assert!(attributes.contains(&KeyValue::new("messaging.message.id", "1234")));
+        assert!(!attributes
+            .iter()
+            .any(|kv| kv.key.as_str() == "gcp.project_id"));
+
+        assert_eq!(
+            split_resource_name("projects/p/subscriptions/s", "topics"),
+            (None, "projects/p/subscriptions/s")
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Client-side rate limiting of the writes to the Google Cloud APIs, so bursts of telemetry
+//! are spread out instead of being rejected once the project quota is exceeded.
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Token bucket refilled with `rate` quota units every second, holding at most one second of
+/// units.
+///
+/// Units are reserved ahead, so concurrent writes wait their turn instead of all retrying when
+/// the bucket is refilled.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    rate: f64,
+    bucket: Mutex<(Instant, f64)>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(rate: f64) -> Self {
+        RateLimiter {
+            rate,
+            bucket: Mutex::new((Instant::now(), rate)),
+        }
+    }
+
+    /// Reserve `units`, returning how long to wait before using them.
+    pub(crate) fn reserve(&self, units: f64) -> Duration {
+        self.reserve_at(Instant::now(), units)
+    }
+
+    fn reserve_at(&self, now: Instant, units: f64) -> Duration {
+        if self.rate <= 0.0 {
+            return Duration::ZERO;
+        }
+        let mut bucket = match self.bucket.lock() {
+            Ok(bucket) => bucket,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let (last, available) = &mut *bucket;
+        let elapsed = now.saturating_duration_since(*last).as_secs_f64();
+        *available = (*available + elapsed * self.rate).min(self.rate) - units;
+        *last = now.max(*last);
+        if *available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-*available / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve() {
+        let limiter = RateLimiter::new(100.0);
+        let start = Instant::now();
+        assert_eq!(limiter.reserve_at(start, 60.0), Duration::ZERO);
+        assert_eq!(limiter.reserve_at(start, 40.0), Duration::ZERO);
+        // Reserved ahead of the refill.
+        assert_eq!(limiter.reserve_at(start, 50.0), Duration::from_millis(500));
+        assert_eq!(limiter.reserve_at(start, 50.0), Duration::from_secs(1));
+
+        let later = start + Duration::from_secs(2);
+        assert_eq!(limiter.reserve_at(later, 100.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let limiter = RateLimiter::new(0.0);
+        assert_eq!(limiter.reserve(1_000.0), Duration::ZERO);
+    }
+}
@@ -0,0 +1,352 @@
+//! In-process mock of the Cloud Trace and Cloud Logging APIs, to test pipelines exporting to
+//! Google Cloud without reaching it.
+//!
+//! ```no_run
+//! use opentelemetry_stackdriver::test_util::{MockServer, NoopAuthorizer};
+//! use opentelemetry_stackdriver::StackDriverExporter;
+//! use std::time::Duration;
+//!
+//! # async fn run() -> Result<(), opentelemetry_stackdriver::Error> {
+//! let server = MockServer::start().await?;
+//! let (exporter, driver) = StackDriverExporter::builder()
+//!     .trace_endpoint(server.endpoint())
+//!     .logging_endpoint(server.endpoint())
+//!     .build(NoopAuthorizer::new("my-project"))
+//!     .await?;
+//! tokio::spawn(driver);
+//!
+//! // Export spans through a tracer provider using `exporter`...
+//!
+//! let spans = server.wait_for_spans(1, Duration::from_secs(5)).await;
+//! assert_eq!(spans[0].display_name.as_ref().unwrap().value, "my-span");
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_channel::oneshot;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::codec::ProstCodec;
+use tonic::codegen::{http, Body, BoxFuture, Service, StdError};
+use tonic::server::{Grpc, NamedService, UnaryService};
+use tonic::{Request, Response, Status};
+
+use crate::proto::devtools::cloudtrace::v2::{BatchWriteSpansRequest, Span};
+use crate::proto::logging::v2::{LogEntry, WriteLogEntriesRequest, WriteLogEntriesResponse};
+use crate::{Authorizer, Error};
+
+const BATCH_WRITE_SPANS: &str = "/google.devtools.cloudtrace.v2.TraceService/BatchWriteSpans";
+const WRITE_LOG_ENTRIES: &str = "/google.logging.v2.LoggingServiceV2/WriteLogEntries";
+
+#[derive(Debug, Default)]
+struct Received {
+    span_requests: Vec<BatchWriteSpansRequest>,
+    log_requests: Vec<WriteLogEntriesRequest>,
+    failures: VecDeque<Status>,
+}
+
+/// A mock Cloud Trace and Cloud Logging server, recording the requests it receives.
+///
+/// The server runs on the tokio runtime it was started on, until the `MockServer` and all its
+/// clones are dropped. Exporters reach it through [`Builder::trace_endpoint`] and
+/// [`Builder::logging_endpoint`], authorized by a [`NoopAuthorizer`].
+///
+/// [`Builder::trace_endpoint`]: crate::Builder::trace_endpoint
+/// [`Builder::logging_endpoint`]: crate::Builder::logging_endpoint
+#[derive(Clone, Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    received: Arc<Mutex<Received>>,
+    _shutdown: Arc<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    /// Start a server listening on a free local port.
+    pub async fn start() -> Result<Self, Error> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let received = Arc::new(Mutex::new(Received::default()));
+        let (shutdown, signal) = oneshot::channel::<()>();
+
+        let server = tonic::transport::Server::builder()
+            .add_service(TraceService(received.clone()))
+            .add_service(LoggingService(received.clone()))
+            .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async {
+                let _ = signal.await;
+            });
+        tokio::spawn(server);
+
+        Ok(MockServer {
+            addr,
+            received,
+            _shutdown: Arc::new(shutdown),
+        })
+    }
+
+    /// The URI to pass to the exporter builder.
+    pub fn endpoint(&self) -> http::Uri {
+        http::Uri::try_from(format!("http://{}", self.addr))
+            .expect("a socket address is a valid authority")
+    }
+
+    /// Fail the next request with `status`, e.g. `Status::resource_exhausted` to test the
+    /// handling of exceeded quotas. Failures queue up, one per request.
+    pub fn fail_next(&self, status: Status) {
+        self.with_received(|received| received.failures.push_back(status));
+    }
+
+    /// The `BatchWriteSpans` requests received so far.
+    pub fn span_requests(&self) -> Vec<BatchWriteSpansRequest> {
+        self.with_received(|received| received.span_requests.clone())
+    }
+
+    /// The spans received so far, in the order they were received.
+    pub fn spans(&self) -> Vec<Span> {
+        self.with_received(|received| {
+            received
+                .span_requests
+                .iter()
+                .flat_map(|request| request.spans.iter().cloned())
+                .collect()
+        })
+    }
+
+    /// The `WriteLogEntries` requests received so far.
+    pub fn log_requests(&self) -> Vec<WriteLogEntriesRequest> {
+        self.with_received(|received| received.log_requests.clone())
+    }
+
+    /// The log entries received so far, in the order they were received.
+    pub fn log_entries(&self) -> Vec<LogEntry> {
+        self.with_received(|received| {
+            received
+                .log_requests
+                .iter()
+                .flat_map(|request| request.entries.iter().cloned())
+                .collect()
+        })
+    }
+
+    /// Wait until at least `count` spans are received, or `timeout` elapsed, returning the
+    /// spans received.
+    pub async fn wait_for_spans(&self, count: usize, timeout: Duration) -> Vec<Span> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let spans = self.spans();
+            if spans.len() >= count || tokio::time::Instant::now() >= deadline {
+                return spans;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    fn with_received<T>(&self, f: impl FnOnce(&mut Received) -> T) -> T {
+        match self.received.lock() {
+            Ok(mut received) => f(&mut received),
+            Err(poisoned) => f(&mut poisoned.into_inner()),
+        }
+    }
+}
+
+/// An [`Authorizer`] adding no credentials to the requests, for the [`MockServer`].
+#[derive(Clone, Debug)]
+pub struct NoopAuthorizer {
+    project_id: String,
+}
+
+impl NoopAuthorizer {
+    /// Authorize the requests of `project_id`.
+    pub fn new(project_id: impl Into<String>) -> Self {
+        NoopAuthorizer {
+            project_id: project_id.into(),
+        }
+    }
+}
+
+impl Authorizer for NoopAuthorizer {
+    type Error = Error;
+
+    fn project_id(&self) -> &str {
+        &self.project_id
+    }
+
+    async fn authorize<T: Send + Sync>(
+        &self,
+        _req: &mut Request<T>,
+        _scopes: &[&str],
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Records a unary request, answering with `respond`, unless a failure is queued.
+struct Record<Req, Res> {
+    received: Arc<Mutex<Received>>,
+    respond: fn(&mut Received, Req) -> Res,
+}
+
+impl<Req: Send + 'static, Res: Send + 'static> UnaryService<Req> for Record<Req, Res> {
+    type Response = Res;
+    type Future = BoxFuture<Response<Res>, Status>;
+
+    fn call(&mut self, request: Request<Req>) -> Self::Future {
+        let result = match self.received.lock() {
+            Ok(mut received) => match received.failures.pop_front() {
+                Some(status) => Err(status),
+                None => Ok(Response::new((self.respond)(
+                    &mut received,
+                    request.into_inner(),
+                ))),
+            },
+            Err(_) => Err(Status::internal("mock server state poisoned")),
+        };
+        Box::pin(std::future::ready(result))
+    }
+}
+
+fn record_spans(received: &mut Received, request: BatchWriteSpansRequest) {
+    received.span_requests.push(request);
+}
+
+fn record_log_entries(
+    received: &mut Received,
+    request: WriteLogEntriesRequest,
+) -> WriteLogEntriesResponse {
+    received.log_requests.push(request);
+    WriteLogEntriesResponse {}
+}
+
+#[derive(Clone)]
+struct TraceService(Arc<Mutex<Received>>);
+
+impl NamedService for TraceService {
+    const NAME: &'static str = "google.devtools.cloudtrace.v2.TraceService";
+}
+
+impl<B> Service<http::Request<B>> for TraceService
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let received = self.0.clone();
+        Box::pin(async move {
+            Ok(match req.uri().path() {
+                BATCH_WRITE_SPANS => {
+                    let record = Record {
+                        received,
+                        respond: record_spans,
+                    };
+                    Grpc::new(ProstCodec::default()).unary(record, req).await
+                }
+                _ => Status::unimplemented("not mocked").into_http(),
+            })
+        })
+    }
+}
+
+#[derive(Clone)]
+struct LoggingService(Arc<Mutex<Received>>);
+
+impl NamedService for LoggingService {
+    const NAME: &'static str = "google.logging.v2.LoggingServiceV2";
+}
+
+impl<B> Service<http::Request<B>> for LoggingService
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let received = self.0.clone();
+        Box::pin(async move {
+            Ok(match req.uri().path() {
+                WRITE_LOG_ENTRIES => {
+                    let record = Record {
+                        received,
+                        respond: record_log_entries,
+                    };
+                    Grpc::new(ProstCodec::default()).unary(record, req).await
+                }
+                _ => Status::unimplemented("not mocked").into_http(),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StackDriverExporter;
+    use opentelemetry::trace::{SpanContext, SpanId, SpanKind, Status as SpanStatus};
+    use opentelemetry::trace::{TraceFlags, TraceId, TraceState};
+    use opentelemetry::InstrumentationScope;
+    use opentelemetry_sdk::trace::{SpanData, SpanEvents, SpanExporter, SpanLinks};
+    use std::time::SystemTime;
+
+    fn span(name: &'static str) -> SpanData {
+        SpanData {
+            span_context: SpanContext::new(
+                TraceId::from(1),
+                SpanId::from(2),
+                TraceFlags::SAMPLED,
+                false,
+                TraceState::default(),
+            ),
+            parent_span_id: SpanId::INVALID,
+            parent_span_is_remote: false,
+            span_kind: SpanKind::Server,
+            name: name.into(),
+            start_time: SystemTime::UNIX_EPOCH,
+            end_time: SystemTime::UNIX_EPOCH,
+            attributes: vec![],
+            dropped_attributes_count: 0,
+            events: SpanEvents::default(),
+            links: SpanLinks::default(),
+            status: SpanStatus::Unset,
+            instrumentation_scope: InstrumentationScope::builder("component").build(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_server_receives_spans() {
+        let server = MockServer::start().await.unwrap();
+        let (exporter, driver) = StackDriverExporter::builder()
+            .trace_endpoint(server.endpoint())
+            .build(NoopAuthorizer::new("my-project"))
+            .await
+            .unwrap();
+        let driver = tokio::spawn(driver);
+
+        exporter.export(vec![span("my-span")]).await.unwrap();
+        let spans = server.wait_for_spans(1, Duration::from_secs(5)).await;
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].display_name.as_ref().unwrap().value, "my-span");
+        assert_eq!(server.span_requests()[0].name, "projects/my-project");
+
+        drop(exporter);
+        driver.await.unwrap();
+    }
+}
@@ -0,0 +1,334 @@
+use crate::format::{Facility, Header};
+use crate::transport::{Connection, Transport};
+use opentelemetry::otel_debug;
+use opentelemetry::Key;
+use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
+use opentelemetry_sdk::logs::LogBatch;
+use opentelemetry_sdk::Resource;
+use std::fmt::{self, Debug};
+use std::io;
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const DEFAULT_ENDPOINT: &str = "127.0.0.1:514";
+const DEFAULT_SD_ID: &str = "otel@32473";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Builder for [`SyslogExporter`].
+#[derive(Debug)]
+pub struct SyslogExporterBuilder {
+    transport: Transport,
+    facility: Facility,
+    hostname: Option<String>,
+    app_name: Option<String>,
+    sd_id: String,
+    timeout: Duration,
+}
+
+impl SyslogExporterBuilder {
+    /// Send messages over UDP to `endpoint`, e.g. `syslog.example.com:514`.
+    pub fn with_udp(mut self, endpoint: impl Into<String>) -> Self {
+        self.transport = Transport::Udp {
+            endpoint: endpoint.into(),
+        };
+        self
+    }
+
+    /// Send messages over TCP to `endpoint`, e.g. `syslog.example.com:601`.
+    pub fn with_tcp(mut self, endpoint: impl Into<String>) -> Self {
+        self.transport = Transport::Tcp {
+            endpoint: endpoint.into(),
+        };
+        self
+    }
+
+    /// Send messages over TLS to `endpoint`, e.g. `syslog.example.com:6514`.
+    ///
+    /// The host part of `endpoint` is used as server name to verify the certificate.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(
+        mut self,
+        endpoint: impl Into<String>,
+        config: Arc<rustls::ClientConfig>,
+    ) -> Result<Self, rustls::pki_types::InvalidDnsNameError> {
+        let endpoint = endpoint.into();
+        let host = endpoint
+            .rsplit_once(':')
+            .map_or(endpoint.as_str(), |(host, _)| host)
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+        let server_name = rustls::pki_types::ServerName::try_from(host)?.to_owned();
+        self.transport = Transport::Tls {
+            endpoint,
+            server_name,
+            config,
+        };
+        Ok(self)
+    }
+
+    /// Set the facility of the messages, [`Facility::User`] by default.
+    pub fn with_facility(mut self, facility: Facility) -> Self {
+        self.facility = facility;
+        self
+    }
+
+    /// Set the `HOSTNAME` of the messages.
+    ///
+    /// Defaults to the `host.name` resource attribute.
+    pub fn with_hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
+    /// Set the `APP-NAME` of the messages.
+    ///
+    /// Defaults to the `service.name` resource attribute.
+    pub fn with_app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = Some(app_name.into());
+        self
+    }
+
+    /// Set the SD-ID of the structured data element holding the attributes, `otel@32473` by
+    /// default.
+    ///
+    /// Custom SD-IDs have the form `name@<private enterprise number>`.
+    pub fn with_sd_id(mut self, sd_id: impl Into<String>) -> Self {
+        self.sd_id = sd_id.into();
+        self
+    }
+
+    /// Set the connect and write timeout of stream transports, 5 seconds by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Build the exporter.
+    ///
+    /// Fails if the SD-ID isn't valid. The connection is only established on the first export.
+    pub fn build(self) -> io::Result<SyslogExporter> {
+        if crate::format::sd_name(&self.sd_id).as_deref() != Some(self.sd_id.as_str()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid SD-ID: {}", self.sd_id),
+            ));
+        }
+        otel_debug!(name: "Syslog.Created", endpoint = self.transport.endpoint());
+        Ok(SyslogExporter {
+            transport: self.transport,
+            header: Header {
+                facility: self.facility,
+                hostname: self.hostname,
+                app_name: self.app_name,
+                procid: std::process::id().to_string(),
+                sd_id: self.sd_id,
+            },
+            timeout: self.timeout,
+            connection: Mutex::new(None),
+        })
+    }
+}
+
+/// Exporter sending log records as RFC 5424 syslog messages.
+///
+/// See the [crate documentation](crate) for the message format.
+pub struct SyslogExporter {
+    transport: Transport,
+    header: Header,
+    timeout: Duration,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl SyslogExporter {
+    /// Create a builder for the exporter, sending over UDP to `127.0.0.1:514` by default.
+    pub fn builder() -> SyslogExporterBuilder {
+        SyslogExporterBuilder {
+            transport: Transport::Udp {
+                endpoint: DEFAULT_ENDPOINT.to_string(),
+            },
+            facility: Facility::default(),
+            hostname: None,
+            app_name: None,
+            sd_id: DEFAULT_SD_ID.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Send a message, reconnecting once if the connection was broken.
+    fn send(&self, connection: &mut Option<Connection>, message: &[u8]) -> io::Result<()> {
+        if let Some(current) = connection.as_mut() {
+            match current.send(message) {
+                Ok(()) => return Ok(()),
+                Err(err) => {
+                    otel_debug!(name: "Syslog.Reconnecting", error = format!("{err}"));
+                    *connection = None;
+                }
+            }
+        }
+
+        let mut new = self.transport.connect(self.timeout)?;
+        new.send(message)?;
+        *connection = Some(new);
+        Ok(())
+    }
+}
+
+impl Debug for SyslogExporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "syslog log exporter (endpoint: {})",
+            self.transport.endpoint()
+        )
+    }
+}
+
+impl opentelemetry_sdk::logs::LogExporter for SyslogExporter {
+    async fn export(&self, batch: LogBatch<'_>) -> OTelSdkResult {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| OTelSdkError::InternalFailure("Failed to acquire lock".to_string()))?;
+
+        let mut result = Ok(());
+        for (record, _instrumentation) in batch.iter() {
+            let message = self.header.format(record);
+            if let Err(err) = self.send(&mut connection, message.as_bytes()) {
+                result = Err(OTelSdkError::InternalFailure(format!(
+                    "Failed to send log record to syslog: {err}"
+                )));
+            }
+        }
+        result
+    }
+
+    fn shutdown(&self) -> OTelSdkResult {
+        if let Ok(mut connection) = self.connection.lock() {
+            connection.take();
+        }
+        Ok(())
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        if self.header.hostname.is_none() {
+            self.header.hostname = resource
+                .get(&Key::from_static_str("host.name"))
+                .map(|name| name.to_string());
+        }
+        if self.header.app_name.is_none() {
+            self.header.app_name = resource
+                .get(&Key::from_static_str("service.name"))
+                .map(|name| name.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::logs::{LogRecord, Logger, LoggerProvider, Severity};
+    use opentelemetry::InstrumentationScope;
+    use opentelemetry_sdk::logs::{LogExporter, SdkLogRecord, SdkLoggerProvider};
+    use std::io::Read;
+    use std::net::{TcpListener, UdpSocket};
+
+    fn new_sdk_log_record(body: &'static str) -> SdkLogRecord {
+        let mut record = SdkLoggerProvider::builder()
+            .build()
+            .logger("test")
+            .create_log_record();
+        record.set_body(body.into());
+        record.set_severity_number(Severity::Warn);
+        record
+    }
+
+    fn export(exporter: &SyslogExporter, records: &[SdkLogRecord]) -> OTelSdkResult {
+        let scope = InstrumentationScope::default();
+        let records: Vec<_> = records.iter().map(|record| (record, &scope)).collect();
+        futures_executor::block_on(exporter.export(LogBatch::new(&records)))
+    }
+
+    #[test]
+    fn test_export_udp() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut exporter = SyslogExporter::builder()
+            .with_udp(server.local_addr().unwrap().to_string())
+            .with_hostname("my-host")
+            .build()
+            .unwrap();
+        exporter.set_resource(
+            &Resource::builder_empty()
+                .with_service_name("my-service")
+                .build(),
+        );
+
+        export(&exporter, &[new_sdk_log_record("hello")]).unwrap();
+
+        let mut buf = [0u8; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        let message = std::str::from_utf8(&buf[..len]).unwrap();
+        assert!(message.starts_with("<12>1 "), "{message}");
+        assert!(
+            message.ends_with(&format!(
+                " my-host my-service {} - - hello",
+                std::process::id()
+            )),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn test_export_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let exporter = SyslogExporter::builder()
+            .with_tcp(listener.local_addr().unwrap().to_string())
+            .build()
+            .unwrap();
+
+        export(
+            &exporter,
+            &[new_sdk_log_record("first"), new_sdk_log_record("second")],
+        )
+        .unwrap();
+        exporter.shutdown().unwrap();
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = String::new();
+        stream.read_to_string(&mut received).unwrap();
+
+        // Each frame is the message length, a space and the message.
+        let mut messages = Vec::new();
+        let mut rest = received.as_str();
+        while let Some((len, tail)) = rest.split_once(' ') {
+            let len: usize = len.parse().unwrap();
+            messages.push(&tail[..len]);
+            rest = &tail[len..];
+        }
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].ends_with(" first"));
+        assert!(messages[1].ends_with(" second"));
+    }
+
+    #[test]
+    fn test_export_tcp_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let exporter = SyslogExporter::builder()
+            .with_tcp(endpoint)
+            .build()
+            .unwrap();
+        assert!(export(&exporter, &[new_sdk_log_record("hello")]).is_err());
+    }
+
+    #[test]
+    fn test_build_invalid_sd_id() {
+        assert!(SyslogExporter::builder()
+            .with_sd_id("invalid id")
+            .build()
+            .is_err());
+    }
+}
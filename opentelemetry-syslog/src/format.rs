@@ -0,0 +1,334 @@
+//! RFC 5424 message formatting.
+use chrono::{DateTime, SecondsFormat, Utc};
+use opentelemetry::logs::{AnyValue, Severity};
+use opentelemetry_sdk::logs::SdkLogRecord;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+const NILVALUE: &str = "-";
+const MAX_HOSTNAME_LEN: usize = 255;
+const MAX_APP_NAME_LEN: usize = 48;
+const MAX_MSGID_LEN: usize = 32;
+const MAX_SD_NAME_LEN: usize = 32;
+
+/// Syslog facility, see [RFC 5424 section 6.2.1](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.1).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Facility {
+    Kern = 0,
+    #[default]
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    Authpriv = 10,
+    Ftp = 11,
+    Ntp = 12,
+    Audit = 13,
+    Alert = 14,
+    Clock = 15,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+/// Map a severity to a syslog severity.
+pub(crate) const fn severity(severity: Severity) -> u8 {
+    match severity {
+        Severity::Trace
+        | Severity::Trace2
+        | Severity::Trace3
+        | Severity::Trace4
+        | Severity::Debug
+        | Severity::Debug2
+        | Severity::Debug3
+        | Severity::Debug4 => 7,
+        Severity::Info | Severity::Info2 | Severity::Info3 | Severity::Info4 => 6,
+        Severity::Warn | Severity::Warn2 | Severity::Warn3 | Severity::Warn4 => 4,
+        Severity::Error | Severity::Error2 | Severity::Error3 | Severity::Error4 => 3,
+        Severity::Fatal | Severity::Fatal2 | Severity::Fatal3 | Severity::Fatal4 => 2,
+    }
+}
+
+/// The parts of the header shared by all messages of an exporter.
+#[derive(Debug)]
+pub(crate) struct Header {
+    pub(crate) facility: Facility,
+    pub(crate) hostname: Option<String>,
+    pub(crate) app_name: Option<String>,
+    pub(crate) procid: String,
+    pub(crate) sd_id: String,
+}
+
+impl Header {
+    /// Format `record` as RFC 5424 message.
+    pub(crate) fn format(&self, record: &SdkLogRecord) -> String {
+        let mut message = String::with_capacity(256);
+
+        let severity = record.severity_number().map_or(6, severity);
+        let pri = self.facility as u8 * 8 + severity;
+        let timestamp: DateTime<Utc> = record
+            .timestamp()
+            .or(record.observed_timestamp())
+            .unwrap_or_else(SystemTime::now)
+            .into();
+        let _ = write!(
+            message,
+            "<{pri}>1 {} ",
+            timestamp.to_rfc3339_opts(SecondsFormat::Micros, true)
+        );
+        push_header_field(&mut message, self.hostname.as_deref(), MAX_HOSTNAME_LEN);
+        message.push(' ');
+        push_header_field(&mut message, self.app_name.as_deref(), MAX_APP_NAME_LEN);
+        message.push(' ');
+        message.push_str(&self.procid);
+        message.push(' ');
+        push_header_field(&mut message, record.event_name(), MAX_MSGID_LEN);
+        message.push(' ');
+
+        self.push_structured_data(&mut message, record);
+
+        if let Some(body) = record.body() {
+            message.push(' ');
+            push_value(&mut message, body);
+        }
+        message
+    }
+
+    fn push_structured_data(&self, message: &mut String, record: &SdkLogRecord) {
+        let start = message.len();
+        message.push('[');
+        message.push_str(&self.sd_id);
+        let empty = message.len();
+
+        if let Some(trace_context) = record.trace_context() {
+            let _ = write!(
+                message,
+                " trace_id=\"{}\" span_id=\"{}\"",
+                trace_context.trace_id, trace_context.span_id
+            );
+            if let Some(trace_flags) = trace_context.trace_flags {
+                let _ = write!(message, " trace_flags=\"{:02x}\"", trace_flags.to_u8());
+            }
+        }
+
+        for (key, value) in record.attributes_iter() {
+            if let Some(name) = sd_name(key.as_str()) {
+                let _ = write!(message, " {name}=\"");
+                let mut param = String::new();
+                push_value(&mut param, value);
+                push_escaped_param_value(message, &param);
+                message.push('"');
+            }
+        }
+
+        if message.len() == empty {
+            message.truncate(start);
+            message.push_str(NILVALUE);
+        } else {
+            message.push(']');
+        }
+    }
+}
+
+/// Push a header field, restricted to printable US-ASCII and `max_len` characters.
+fn push_header_field(message: &mut String, value: Option<&str>, max_len: usize) {
+    let value = value.unwrap_or_default();
+    if value.is_empty() {
+        message.push_str(NILVALUE);
+        return;
+    }
+    message.extend(
+        value
+            .chars()
+            .take(max_len)
+            .map(|c| if c.is_ascii_graphic() { c } else { '_' }),
+    );
+}
+
+/// Turn `key` into a valid SD-NAME, or `None` if nothing is left.
+pub(crate) fn sd_name(key: &str) -> Option<String> {
+    let name: String = key
+        .chars()
+        .filter(|c| c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"'))
+        .take(MAX_SD_NAME_LEN)
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Escape `"`, `\` and `]` as required for PARAM-VALUE.
+fn push_escaped_param_value(message: &mut String, value: &str) {
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            message.push('\\');
+        }
+        message.push(c);
+    }
+}
+
+/// Format a value, strings are written as is, lists and maps as JSON.
+fn push_value(out: &mut String, value: &AnyValue) {
+    match value {
+        AnyValue::String(s) => out.push_str(s.as_str()),
+        _ => push_json(out, value),
+    }
+}
+
+fn push_json(out: &mut String, value: &AnyValue) {
+    match value {
+        AnyValue::Boolean(b) => {
+            let _ = write!(out, "{b}");
+        }
+        AnyValue::Int(i) => {
+            let _ = write!(out, "{i}");
+        }
+        AnyValue::Double(f) if f.is_finite() => {
+            let _ = write!(out, "{f}");
+        }
+        AnyValue::Double(f) => push_json_string(out, &f.to_string()),
+        AnyValue::String(s) => push_json_string(out, s.as_str()),
+        AnyValue::Bytes(b) => {
+            let hex = b.iter().fold(String::new(), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            });
+            push_json_string(out, &hex);
+        }
+        AnyValue::ListAny(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                push_json(out, value);
+            }
+            out.push(']');
+        }
+        AnyValue::Map(map) => {
+            out.push('{');
+            for (i, (key, value)) in map.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                push_json_string(out, key.as_str());
+                out.push(':');
+                push_json(out, value);
+            }
+            out.push('}');
+        }
+        _ => out.push_str("null"),
+    }
+}
+
+fn push_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::logs::{LogRecord, Logger, LoggerProvider};
+    use opentelemetry::trace::{SpanId, TraceFlags, TraceId};
+    use opentelemetry_sdk::logs::SdkLoggerProvider;
+    use std::time::Duration;
+
+    fn new_sdk_log_record() -> SdkLogRecord {
+        let mut record = SdkLoggerProvider::builder()
+            .build()
+            .logger("test")
+            .create_log_record();
+        record.set_timestamp(SystemTime::UNIX_EPOCH + Duration::from_micros(1_700_000_000_123_456));
+        record
+    }
+
+    fn header() -> Header {
+        Header {
+            facility: Facility::Local0,
+            hostname: Some("my-host".to_string()),
+            app_name: Some("my-service".to_string()),
+            procid: "42".to_string(),
+            sd_id: "otel@32473".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        let mut record = new_sdk_log_record();
+        record.set_body("hello world".into());
+        record.set_severity_number(Severity::Error);
+        record.set_event_name("login");
+        record.set_trace_context(
+            TraceId::from_hex("0af7651916cd43dd8448eb211c80319c").unwrap(),
+            SpanId::from_hex("b7ad6b7169203331").unwrap(),
+            Some(TraceFlags::SAMPLED),
+        );
+        record.add_attribute("user.name", "a \"quoted\" [name]");
+        record.add_attribute("retry", 3);
+
+        assert_eq!(
+            header().format(&record),
+            "<131>1 2023-11-14T22:13:20.123456Z my-host my-service 42 login \
+[otel@32473 trace_id=\"0af7651916cd43dd8448eb211c80319c\" span_id=\"b7ad6b7169203331\" \
+trace_flags=\"01\" user.name=\"a \\\"quoted\\\" [name\\]\" retry=\"3\"] hello world"
+        );
+    }
+
+    #[test]
+    fn test_format_nil_values() {
+        let header = Header {
+            facility: Facility::User,
+            hostname: None,
+            app_name: Some("my service".to_string()),
+            ..header()
+        };
+        let record = new_sdk_log_record();
+
+        assert_eq!(
+            header.format(&record),
+            "<14>1 2023-11-14T22:13:20.123456Z - my_service 42 - -"
+        );
+    }
+
+    #[test]
+    fn test_sd_name() {
+        assert_eq!(
+            sd_name("http.request.method").as_deref(),
+            Some("http.request.method")
+        );
+        assert_eq!(sd_name("a b=\"c]").as_deref(), Some("abc"));
+        assert_eq!(sd_name("=]").as_deref(), None);
+        assert_eq!(sd_name(&"a".repeat(40)).map(|n| n.len()), Some(32));
+    }
+
+    #[test]
+    fn test_severity() {
+        assert_eq!(severity(Severity::Trace), 7);
+        assert_eq!(severity(Severity::Info3), 6);
+        assert_eq!(severity(Severity::Warn), 4);
+        assert_eq!(severity(Severity::Error4), 3);
+        assert_eq!(severity(Severity::Fatal), 2);
+    }
+}
@@ -0,0 +1,74 @@
+//! # OpenTelemetry Syslog Exporter for Logs
+//!
+//! This crate provides a log exporter sending log records as
+//! [RFC 5424](https://datatracker.ietf.org/doc/html/rfc5424) syslog messages,
+//! so applications instrumented with the OpenTelemetry logs bridge can feed
+//! existing syslog pipelines such as rsyslog, syslog-ng or a SIEM.
+//!
+//! ## Transports
+//!
+//! - UDP ([RFC 5426](https://datatracker.ietf.org/doc/html/rfc5426)), one
+//!   message per datagram. This is the default, sending to `127.0.0.1:514`.
+//! - TCP ([RFC 6587](https://datatracker.ietf.org/doc/html/rfc6587)) using
+//!   octet-counting framing.
+//! - TLS ([RFC 5425](https://datatracker.ietf.org/doc/html/rfc5425)) using
+//!   octet-counting framing. Requires the `tls` feature, the
+//!   [`rustls::ClientConfig`](https://docs.rs/rustls/latest/rustls/client/struct.ClientConfig.html)
+//!   is provided by the application.
+//!
+//! Stream connections are established on the first export and re-established
+//! after a write failure.
+//!
+//! ## Message Format
+//!
+//! - `PRI`: the configured [`Facility`] combined with the severity of the
+//!   record, see below.
+//! - `TIMESTAMP`: the timestamp of the record, or its observed timestamp.
+//! - `HOSTNAME`: the configured hostname, or the `host.name` resource attribute.
+//! - `APP-NAME`: the configured application name, or the `service.name`
+//!   resource attribute.
+//! - `PROCID`: the id of the current process.
+//! - `MSGID`: the event name of the record.
+//! - `STRUCTURED-DATA`: one SD-ELEMENT, `otel@32473` by default, holding the
+//!   `trace_id`, `span_id` and `trace_flags` of the record followed by its
+//!   attributes.
+//! - `MSG`: the body of the record, UTF-8 encoded.
+//!
+//! | OpenTelemetry severity | Syslog severity   |
+//! | ---------------------- | ----------------- |
+//! | `TRACE*`, `DEBUG*`     | 7 (Debug)         |
+//! | `INFO*`                | 6 (Informational) |
+//! | `WARN*`                | 4 (Warning)       |
+//! | `ERROR*`               | 3 (Error)         |
+//! | `FATAL*`               | 2 (Critical)      |
+//!
+//! Records without a severity are sent as Informational.
+//!
+//! ## Getting Started
+//!
+//! ```no_run
+//! use opentelemetry_sdk::logs::SdkLoggerProvider;
+//! use opentelemetry_sdk::Resource;
+//! use opentelemetry_syslog::{Facility, SyslogExporter};
+//!
+//! let exporter = SyslogExporter::builder()
+//!     .with_tcp("syslog.example.com:601")
+//!     .with_facility(Facility::Local0)
+//!     .build()
+//!     .expect("invalid syslog exporter configuration");
+//!
+//! let provider = SdkLoggerProvider::builder()
+//!     .with_resource(
+//!         Resource::builder_empty()
+//!             .with_service_name("example")
+//!             .build(),
+//!     )
+//!     .with_batch_exporter(exporter)
+//!     .build();
+//! ```
+mod exporter;
+mod format;
+mod transport;
+
+pub use exporter::{SyslogExporter, SyslogExporterBuilder};
+pub use format::Facility;
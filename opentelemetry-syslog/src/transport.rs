@@ -0,0 +1,135 @@
+//! UDP, TCP and TLS transports.
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub(crate) enum Transport {
+    Udp {
+        endpoint: String,
+    },
+    Tcp {
+        endpoint: String,
+    },
+    #[cfg(feature = "tls")]
+    Tls {
+        endpoint: String,
+        server_name: rustls::pki_types::ServerName<'static>,
+        config: Arc<rustls::ClientConfig>,
+    },
+}
+
+impl Transport {
+    pub(crate) fn endpoint(&self) -> &str {
+        match self {
+            Transport::Udp { endpoint } | Transport::Tcp { endpoint } => endpoint,
+            #[cfg(feature = "tls")]
+            Transport::Tls { endpoint, .. } => endpoint,
+        }
+    }
+
+    pub(crate) fn connect(&self, timeout: Duration) -> io::Result<Connection> {
+        match self {
+            Transport::Udp { endpoint } => {
+                let mut last_err = None;
+                for addr in endpoint.to_socket_addrs()? {
+                    let local: SocketAddr = if addr.is_ipv4() {
+                        ([0, 0, 0, 0], 0).into()
+                    } else {
+                        ([0u16; 8], 0).into()
+                    };
+                    match UdpSocket::bind(local).and_then(|socket| {
+                        socket.connect(addr)?;
+                        Ok(socket)
+                    }) {
+                        Ok(socket) => return Ok(Connection::Udp(socket)),
+                        Err(err) => last_err = Some(err),
+                    }
+                }
+                Err(last_err.unwrap_or_else(|| unresolved(endpoint)))
+            }
+            Transport::Tcp { endpoint } => Ok(Connection::Tcp(connect_tcp(endpoint, timeout)?)),
+            #[cfg(feature = "tls")]
+            Transport::Tls {
+                endpoint,
+                server_name,
+                config,
+            } => {
+                let stream = connect_tcp(endpoint, timeout)?;
+                let connection = rustls::ClientConnection::new(config.clone(), server_name.clone())
+                    .map_err(io::Error::other)?;
+                Ok(Connection::Tls(Box::new(rustls::StreamOwned::new(
+                    connection, stream,
+                ))))
+            }
+        }
+    }
+}
+
+fn connect_tcp(endpoint: &str, timeout: Duration) -> io::Result<TcpStream> {
+    let mut last_err = None;
+    for addr in endpoint.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => {
+                stream.set_write_timeout(Some(timeout))?;
+                stream.set_nodelay(true)?;
+                return Ok(stream);
+            }
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| unresolved(endpoint)))
+}
+
+fn unresolved(endpoint: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{endpoint} didn't resolve to any address"),
+    )
+}
+
+pub(crate) enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+}
+
+impl Connection {
+    /// Send one message, framed with octet counting on stream transports.
+    pub(crate) fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        match self {
+            Connection::Udp(socket) => socket.send(message).map(|_| ()),
+            Connection::Tcp(stream) => write_framed(stream, message),
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => write_framed(stream, message),
+        }
+    }
+}
+
+fn write_framed<W: Write>(writer: &mut W, message: &[u8]) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(message.len() + 8);
+    frame.extend_from_slice(message.len().to_string().as_bytes());
+    frame.push(b' ');
+    frame.extend_from_slice(message);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_framed() {
+        let mut out = Vec::new();
+        write_framed(&mut out, b"<14>1 - - - - - - hello").unwrap();
+        write_framed(&mut out, b"<14>1 - - - - - -").unwrap();
+        assert_eq!(
+            out,
+            b"23 <14>1 - - - - - - hello17 <14>1 - - - - - -".to_vec()
+        );
+    }
+}
@@ -26,12 +26,15 @@
 // sudo -E ~/.cargo/bin/cargo bench --bench logs --all-features
 
 use criterion::{criterion_group, criterion_main, Criterion};
+use opentelemetry::logs::{LogRecord, Logger, LoggerProvider, Severity};
+use opentelemetry::trace::{SpanId, TraceFlags, TraceId};
 use opentelemetry_appender_tracing::layer as tracing_layer;
 use opentelemetry_sdk::logs::SdkLoggerProvider;
 use opentelemetry_sdk::Resource;
 #[cfg(feature = "experimental_eventname_callback")]
 use opentelemetry_user_events_logs::EventNameCallback;
 use opentelemetry_user_events_logs::Processor;
+use std::time::SystemTime;
 use tracing::error;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::Registry;
@@ -184,8 +187,33 @@ fn benchmark_6_attributes(c: &mut Criterion) {
     });
 }
 
+fn benchmark_simple_record(c: &mut Criterion) {
+    let provider = setup_provider_default();
+    let logger = provider.logger("benchmark");
+
+    c.bench_function("User_Event_Simple_Record", |b| {
+        b.iter(|| {
+            let mut record = logger.create_log_record();
+            record.set_severity_number(Severity::Error);
+            record.set_body("Unable to process checkout.".into());
+            record.set_timestamp(SystemTime::now());
+            record.set_trace_context(
+                TraceId::from(0x0af7_6519_16cd_43dd_8448_eb21_1c80_319c),
+                SpanId::from(0xb7ad_6b71_6920_3331),
+                Some(TraceFlags::SAMPLED),
+            );
+            record.add_attribute("field1", "field1");
+            record.add_attribute("field2", 2);
+            record.add_attribute("field3", true);
+            record.add_attribute("field4", 4.0);
+            logger.emit(record);
+        });
+    });
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     benchmark_4_attributes(c);
+    benchmark_simple_record(c);
     benchmark_6_attributes(c);
     #[cfg(feature = "experimental_eventname_callback")]
     benchmark_4_attributes_event_name_custom(c);
@@ -127,17 +127,27 @@
 //!   ```bash
 //!   perf record -e user_events:myprovider_L2K1,user_events:myprovider_L3K1
 //!   ```
+//!
+//! - **In tests**: The `test-util` feature adds `test_util::EventListener`, returning the
+//!   events written to tracepoints as decoded by `perf-decode`, or captured in-process where
+//!   user_events aren't available.
 
 #![warn(missing_debug_implementations, missing_docs)]
 
 mod logs;
 
+pub use logs::activity_id;
+pub use logs::FieldNameStyle;
 pub use logs::Processor;
 pub use logs::ProcessorBuilder;
+pub use logs::PROVIDER_ATTRIBUTE;
 
 #[cfg(feature = "experimental_eventname_callback")]
 pub use logs::EventNameCallback;
 
+#[cfg(feature = "test-util")]
+pub use logs::test_util;
+
 #[cfg(test)]
 mod tests {
 
@@ -1,4 +1,8 @@
-use eventheader::{FieldFormat, Level};
+use crate::logs::activity::activity_id;
+use crate::logs::field_name::{FieldNameStyle, FieldNameWriter};
+use crate::logs::format::{write_hex, write_rfc3339, RFC3339_MAX_LEN};
+use crate::logs::writer::EventWriter;
+use eventheader::Level;
 use eventheader_dynamic::{EventBuilder, EventSet, Provider};
 use opentelemetry::{otel_debug, otel_info, Value};
 use opentelemetry_sdk::Resource;
@@ -40,19 +44,38 @@ where
     provider: Mutex<Provider>,
     name: String,
     event_sets: Vec<Arc<EventSet>>,
+    additional_providers: Vec<AdditionalProvider>,
     cloud_role: Option<String>,
     cloud_role_instance: Option<String>,
     attributes_from_resource: Vec<(Key, AnyValue)>,
     resource_attribute_keys: HashSet<Cow<'static, str>>,
     event_name_callback: C,
+    field_name_style: FieldNameStyle,
+    #[cfg(feature = "self-metrics")]
+    metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics,
 }
 
+/// A provider to which the records naming it in their [`PROVIDER_ATTRIBUTE`] are written.
+struct AdditionalProvider {
+    provider: Mutex<Provider>,
+    name: String,
+    #[cfg(feature = "test-util")]
+    keyword: u64,
+    event_sets: Vec<Arc<EventSet>>,
+}
+
+/// Attribute of a log record naming the provider, registered with
+/// `ProcessorBuilder::with_additional_provider`, to which it is written instead of the default
+/// one. The attribute isn't written to the event.
+pub const PROVIDER_ATTRIBUTE: &str = "microsoft.custom_event.provider";
+
 // Constants for the UserEventsExporter
 const EVENT_ID: &str = "event_id";
 const NO_LISTENER_ERROR: i32 = 9;
 const PAYLOAD_SIZE_EXCEEDED_ERROR: i32 = 34;
 const CS_VERSION: u32 = 1024; // 0x400 in hex
 const DEFAULT_LOG_TYPE_NAME: &str = "Log";
+const DEFAULT_KEYWORD: u64 = 1;
 
 // Constants for EventSet registration error codes
 const REGISTRATION_SUCCESS: i32 = 0;
@@ -60,8 +83,10 @@ const TRACEFS_NOT_MOUNTED_ERROR: i32 = 95;
 const PERMISSION_DENIED_ERROR: i32 = 13;
 
 /// Register event sets with the EventHeader provider
-fn register_events(eventheader_provider: &mut eventheader_dynamic::Provider) -> Vec<Arc<EventSet>> {
-    let keyword: u64 = 1;
+fn register_events(
+    eventheader_provider: &mut eventheader_dynamic::Provider,
+    keyword: u64,
+) -> Vec<Arc<EventSet>> {
     // Levels are added in the same order as their int representation,
     // to ensure that the index of the Vec matches the int representation.
     let levels = [
@@ -148,47 +173,109 @@ where
         provider_name: &str,
         resource_attributes: HashSet<Cow<'static, str>>,
         event_name_callback: C,
+        field_name_style: FieldNameStyle,
+        additional_providers: &[(&str, u64)],
     ) -> Self {
         let mut eventheader_provider: Provider =
             Provider::new(provider_name, &Provider::new_options());
-        let event_sets = register_events(&mut eventheader_provider);
+        let event_sets = register_events(&mut eventheader_provider, DEFAULT_KEYWORD);
         otel_debug!(name: "UserEvents.Created", provider_name = provider_name);
         let name = eventheader_provider.name().to_string();
+        let additional_providers = additional_providers
+            .iter()
+            .map(|&(provider_name, keyword)| {
+                let mut provider = Provider::new(provider_name, &Provider::new_options());
+                let event_sets = register_events(&mut provider, keyword);
+                otel_debug!(name: "UserEvents.AdditionalProviderCreated", provider_name = provider_name, keyword = keyword);
+                AdditionalProvider {
+                    name: provider.name().to_string(),
+                    #[cfg(feature = "test-util")]
+                    keyword,
+                    provider: Mutex::new(provider),
+                    event_sets,
+                }
+            })
+            .collect();
         UserEventsExporter {
             provider: Mutex::new(eventheader_provider),
             name,
             event_sets,
+            additional_providers,
             cloud_role: None,
             cloud_role_instance: None,
             resource_attribute_keys: resource_attributes,
             attributes_from_resource: Vec::new(),
             event_name_callback,
+            field_name_style,
+            #[cfg(feature = "self-metrics")]
+            metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics::new(
+                "user_events",
+                opentelemetry_contrib::exporter_metrics::Signal::Logs,
+            ),
         }
     }
 
-    fn add_attribute_to_event(&self, eb: &mut EventBuilder, (key, value): (&Key, &AnyValue)) {
-        let field_name = key.as_str();
+    fn add_attribute_to_event(
+        &self,
+        eb: &mut impl EventWriter,
+        field_name: &str,
+        value: &AnyValue,
+    ) {
         match value {
             AnyValue::Boolean(b) => {
-                eb.add_value(field_name, *b, FieldFormat::Boolean, 0);
+                eb.add_bool(field_name, *b);
             }
             AnyValue::Int(i) => {
-                eb.add_value(field_name, *i, FieldFormat::SignedInt, 0);
+                eb.add_i64(field_name, *i);
             }
             AnyValue::Double(f) => {
-                eb.add_value(field_name, *f, FieldFormat::Float, 0);
+                eb.add_f64(field_name, *f);
             }
             AnyValue::String(s) => {
-                eb.add_str(field_name, s.as_str(), FieldFormat::Default, 0);
+                eb.add_str(field_name, s.as_str());
             }
             // For unsupported types, add the key with an empty string as the value.
             // TODO: Add support for complex types with json serialization in future.
             _ => {
-                eb.add_str(field_name, "", FieldFormat::Default, 0);
+                eb.add_str(field_name, "");
             }
         }
     }
 
+    /// The additional provider named by the [`PROVIDER_ATTRIBUTE`] of `log_record`, if any.
+    fn additional_provider(
+        &self,
+        log_record: &opentelemetry_sdk::logs::SdkLogRecord,
+    ) -> Option<&AdditionalProvider> {
+        if self.additional_providers.is_empty() {
+            return None;
+        }
+        let provider_name =
+            log_record
+                .attributes_iter()
+                .find_map(|(key, value)| match (key.as_str(), value) {
+                    (PROVIDER_ATTRIBUTE, AnyValue::String(name)) => Some(name.as_str()),
+                    _ => None,
+                })?;
+        let provider = self
+            .additional_providers
+            .iter()
+            .find(|provider| provider.name == provider_name);
+        if provider.is_none() {
+            otel_debug!(name: "UserEvents.UnknownProvider", provider_name = provider_name);
+        }
+        provider
+    }
+
+    /// The event sets of the provider of `log_record`, the additional provider named by its
+    /// [`PROVIDER_ATTRIBUTE`] if any, the default provider otherwise.
+    fn event_sets(&self, log_record: &opentelemetry_sdk::logs::SdkLogRecord) -> &[Arc<EventSet>] {
+        match self.additional_provider(log_record) {
+            Some(provider) => &provider.event_sets,
+            None => &self.event_sets,
+        }
+    }
+
     /// Gets the event name from the log record using the provided callback
     #[inline]
     fn get_event_name(&self, record: &opentelemetry_sdk::logs::SdkLogRecord) -> &'static str {
@@ -198,58 +285,185 @@ where
     /// Builds Part A of the Common Schema format
     fn build_part_a(
         &self,
-        eb: &mut EventBuilder,
+        eb: &mut impl EventWriter,
         log_record: &opentelemetry_sdk::logs::SdkLogRecord,
     ) {
         let mut cs_a_count = 0;
         let mut cs_a_bookmark: usize = 0;
-        eb.add_struct_with_bookmark("PartA", 2, 0, &mut cs_a_bookmark);
+        eb.add_struct("PartA", 2, &mut cs_a_bookmark);
 
         let event_time: SystemTime = log_record
             .timestamp()
             .or(log_record.observed_timestamp())
             .unwrap_or_else(SystemTime::now);
-        let time: String =
-            chrono::DateTime::to_rfc3339(&chrono::DateTime::<chrono::Utc>::from(event_time));
 
         cs_a_count += 1; // for event_time
                          // Add time to PartA
-        eb.add_str("time", time, FieldFormat::Default, 0);
+        let mut time_buf = [0; RFC3339_MAX_LEN];
+        match write_rfc3339(event_time, &mut time_buf) {
+            Some(time) => eb.add_str("time", time),
+            // Times outside of the years 1970 to 9999 are left to chrono.
+            None => eb.add_str(
+                "time",
+                &chrono::DateTime::<chrono::Utc>::from(event_time).to_rfc3339(),
+            ),
+        };
 
         if let Some(trace_context) = log_record.trace_context() {
             cs_a_count += 2; // for ext_dt_traceId and ext_dt_spanId
+            let mut trace_id_buf = [0; 32];
             eb.add_str(
                 "ext_dt_traceId",
-                trace_context.trace_id.to_string(),
-                FieldFormat::Default,
-                0,
+                write_hex(&trace_context.trace_id.to_bytes(), &mut trace_id_buf),
             );
+            let mut span_id_buf = [0; 16];
             eb.add_str(
                 "ext_dt_spanId",
-                trace_context.span_id.to_string(),
-                FieldFormat::Default,
-                0,
+                write_hex(&trace_context.span_id.to_bytes(), &mut span_id_buf),
             );
         }
 
         if let Some(cloud_role) = &self.cloud_role {
             cs_a_count += 1;
-            eb.add_str("ext_cloud_role", cloud_role, FieldFormat::Default, 0);
+            eb.add_str("ext_cloud_role", cloud_role);
         }
 
         if let Some(cloud_role_instance) = &self.cloud_role_instance {
             cs_a_count += 1;
-            eb.add_str(
-                "ext_cloud_roleInstance",
-                cloud_role_instance,
-                FieldFormat::Default,
-                0,
-            );
+            eb.add_str("ext_cloud_roleInstance", cloud_role_instance);
         }
 
         eb.set_struct_field_count(cs_a_bookmark, cs_a_count);
     }
 
+    /// Encodes `log_record` in the Common Schema format into `eb`.
+    ///
+    /// The builder's buffers are reused across records, and the Part A fields are formatted on
+    /// the stack, so records with a scalar body and attributes are encoded without allocating.
+    fn encode(
+        &self,
+        eb: &mut impl EventWriter,
+        log_record: &opentelemetry_sdk::logs::SdkLogRecord,
+        otel_severity: Severity,
+    ) {
+        // EventBuilder doc suggests that event name should not be
+        // reused for events with different schema.
+        // In well-behaved application, event-name should be unique
+        // for each event.
+        // TODO: What if the event name is not provided? "Log" is used as default.
+        // TODO: Should event_tag be non-zero?
+        let event_name = self.get_event_name(log_record);
+        eb.reset(event_name);
+
+        eb.add_u32("__csver__", CS_VERSION);
+
+        // populate CS PartA
+        self.build_part_a(eb, log_record);
+
+        //populate CS PartC
+        // TODO: See if should hold on to this, and add PartB first then PartC
+        let (mut is_event_id, mut event_id) = (false, 0);
+        let (mut is_part_c_present, mut cs_c_bookmark, mut cs_c_count) = (false, 0, 0);
+        let mut field_names = FieldNameWriter::new(self.field_name_style);
+        // The keys of the fields of PartC, among which the field names must not collide.
+        let part_c_keys = || {
+            log_record
+                .attributes_iter()
+                .map(|(key, _)| key.as_str())
+                .chain(
+                    self.attributes_from_resource
+                        .iter()
+                        .map(|(key, _)| key.as_str()),
+                )
+        };
+
+        for (key, value) in log_record.attributes_iter() {
+            match (key.as_str(), value) {
+                (EVENT_ID, AnyValue::Int(value)) => {
+                    is_event_id = true;
+                    event_id = *value;
+                    continue;
+                }
+                (PROVIDER_ATTRIBUTE, _) => continue,
+                _ => {
+                    if !is_part_c_present {
+                        eb.add_struct("PartC", 1, &mut cs_c_bookmark);
+                        is_part_c_present = true;
+                    }
+                    let field_name = field_names.field_name(key.as_str(), part_c_keys());
+                    self.add_attribute_to_event(eb, field_name, value);
+                    cs_c_count += 1;
+                }
+            }
+        }
+
+        if !self.attributes_from_resource.is_empty() {
+            if !is_part_c_present {
+                eb.add_struct("PartC", 1, &mut cs_c_bookmark);
+                is_part_c_present = true;
+            }
+
+            for (key, value) in &self.attributes_from_resource {
+                let field_name = field_names.field_name(key.as_str(), part_c_keys());
+                self.add_attribute_to_event(eb, field_name, value);
+                cs_c_count += 1;
+            }
+        }
+
+        if is_part_c_present {
+            eb.set_struct_field_count(cs_c_bookmark, cs_c_count);
+        }
+
+        // populate CS PartB
+        let mut cs_b_bookmark: usize = 0;
+        let mut cs_b_count = 0;
+        eb.add_struct("PartB", 1, &mut cs_b_bookmark);
+        eb.add_str("_typeName", DEFAULT_LOG_TYPE_NAME);
+        cs_b_count += 1;
+
+        if let Some(body) = log_record.body() {
+            match body {
+                AnyValue::String(value) => {
+                    eb.add_str("body", value.as_str());
+                }
+                AnyValue::Int(value) => {
+                    eb.add_i64("body", *value);
+                }
+                AnyValue::Boolean(value) => {
+                    eb.add_bool("body", *value);
+                }
+                AnyValue::Double(value) => {
+                    eb.add_f64("body", *value);
+                }
+                &_ => {
+                    // TODO: Handle other types using json instead of empty string
+                    eb.add_str("body", "");
+                }
+            }
+            cs_b_count += 1;
+        }
+
+        eb.add_i16("severityNumber", otel_severity as i16);
+        cs_b_count += 1;
+
+        if log_record.severity_text().is_some() {
+            eb.add_str("severityText", log_record.severity_text().as_ref().unwrap());
+            cs_b_count += 1;
+        }
+        if is_event_id {
+            eb.add_i64("eventId", event_id);
+            cs_b_count += 1;
+        }
+
+        let partb_name = log_record.event_name().filter(|s| !s.trim().is_empty());
+        if let Some(name) = partb_name {
+            eb.add_str("name", name);
+            cs_b_count += 1;
+        }
+
+        eb.set_struct_field_count(cs_b_bookmark, cs_b_count);
+    }
+
     pub(crate) fn export_log_data(
         &self,
         log_record: &opentelemetry_sdk::logs::SdkLogRecord,
@@ -262,9 +476,21 @@ where
             ))?;
         let level = get_severity_level(otel_severity);
 
+        #[cfg(feature = "test-util")]
+        if crate::logs::test_util::mock_listening() {
+            let (provider_name, keyword) = match self.additional_provider(log_record) {
+                Some(provider) => (provider.name.as_str(), provider.keyword),
+                None => (self.name.as_str(), DEFAULT_KEYWORD),
+            };
+            let mut event = crate::logs::test_util::MockEvent::default();
+            self.encode(&mut event, log_record, otel_severity);
+            event.record(provider_name, level.as_int(), keyword);
+            return Ok(());
+        }
+
         // EventSets are stored in the same order as their int representation,
         // so we can use the level as index to the Vec.
-        let event_set = match self.event_sets.get(level.as_int() as usize) {
+        let event_set = match self.event_sets(log_record).get(level.as_int() as usize) {
             Some(event_set) => event_set,
             None => {
                 // This is considered Error as we cannot find the EventSet.
@@ -277,118 +503,16 @@ where
         };
 
         if event_set.enabled() {
+            #[cfg(feature = "self-metrics")]
+            let start = std::time::Instant::now();
             let _res = EBW.with(|eb| {
                 let mut eb = eb.borrow_mut();
-                // EventBuilder doc suggests that event name should not be
-                // reused for events with different schema. 
-                // In well-behaved application, event-name should be unique
-                // for each event.
-                // TODO: What if the event name is not provided? "Log" is used as default.
-                // TODO: Should event_tag be non-zero?
-                let event_name = self.get_event_name(log_record);
-                eb.reset(event_name, 0);
-
-                eb.add_value("__csver__", CS_VERSION, FieldFormat::UnsignedInt, 0);
-
-                // populate CS PartA
-                self.build_part_a(&mut eb, log_record);
-
-                //populate CS PartC
-                // TODO: See if should hold on to this, and add PartB first then PartC
-                let (mut is_event_id, mut event_id) = (false, 0);
-                let (mut is_part_c_present, mut cs_c_bookmark, mut cs_c_count) = (false, 0, 0);
-
-                for (key, value) in log_record.attributes_iter() {
-                    match (key.as_str(), value) {
-                        (EVENT_ID, AnyValue::Int(value)) => {
-                            is_event_id = true;
-                            event_id = *value;
-                            continue;
-                        }
-                        _ => {
-                            if !is_part_c_present {
-                                eb.add_struct_with_bookmark("PartC", 1, 0, &mut cs_c_bookmark);
-                                is_part_c_present = true;
-                            }
-                            self.add_attribute_to_event(&mut eb, (key, value));
-                            cs_c_count += 1;
-                        }
-                    }
-                }
+                self.encode(&mut *eb, log_record, otel_severity);
 
-                if !self.attributes_from_resource.is_empty() {
-                    if !is_part_c_present {
-                        eb.add_struct_with_bookmark("PartC", 1, 0, &mut cs_c_bookmark);
-                        is_part_c_present = true;
-                    }
-
-                    for (key, value) in &self.attributes_from_resource {
-                        self.add_attribute_to_event(&mut eb, (key, value));
-                        cs_c_count += 1;
-                    }
-                }
-
-                if is_part_c_present {
-                    eb.set_struct_field_count(cs_c_bookmark, cs_c_count);
-                }
-
-                // populate CS PartB
-                let mut cs_b_bookmark: usize = 0;
-                let mut cs_b_count = 0;
-                eb.add_struct_with_bookmark("PartB", 1, 0, &mut cs_b_bookmark);
-                eb.add_str("_typeName", DEFAULT_LOG_TYPE_NAME, FieldFormat::Default, 0);
-                cs_b_count += 1;
-
-                if let Some(body) = log_record.body() {
-                    match body {
-                        AnyValue::String(value) => {
-                            eb.add_str("body", value.as_str(), FieldFormat::Default, 0);
-                        }
-                        AnyValue::Int(value) => {
-                            eb.add_value("body", *value, FieldFormat::SignedInt, 0);
-                        }
-                        AnyValue::Boolean(value) => {
-                            eb.add_value("body", *value, FieldFormat::Boolean, 0);
-                        }
-                        AnyValue::Double(value) => {
-                            eb.add_value("body", *value, FieldFormat::Float, 0);
-                        }
-                        &_ => {
-                            // TODO: Handle other types using json instead of empty string
-                            eb.add_str("body", "", FieldFormat::Default, 0);
-                        }
-                    }
-                    cs_b_count += 1;
-                }
-
-                eb.add_value("severityNumber", otel_severity as i16, FieldFormat::SignedInt, 0);
-                cs_b_count += 1;
-
-                if log_record.severity_text().is_some() {
-                    eb.add_str(
-                        "severityText",
-                        log_record.severity_text().as_ref().unwrap(),
-                        FieldFormat::Default,
-                        0,
-                    );
-                    cs_b_count += 1;
-                }
-                if is_event_id {
-                    eb.add_value("eventId", event_id, FieldFormat::SignedInt, 0);
-                    cs_b_count += 1;
-                }
-
-                let partb_name = log_record
-                    .event_name()
-                    .filter(|s| !s.trim().is_empty());
-                if let Some(name) = partb_name {
-                    eb.add_str("name", name, FieldFormat::Default, 0);
-                    cs_b_count += 1;
-                }
-
-                eb.set_struct_field_count(cs_b_bookmark, cs_b_count);
-
-                let result = eb.write(event_set, None, None);
+                let record_activity_id = log_record
+                    .trace_context()
+                    .map(|cx| activity_id(cx.trace_id, cx.span_id));
+                let result = eb.write(event_set, record_activity_id.as_ref(), None);
                 if result > 0 {
                     // Specially treat the case where there is no listener or payload size exceeds the limit.
                     if result == NO_LISTENER_ERROR {
@@ -405,6 +529,11 @@ where
                     Ok(())
                 }
             });
+            #[cfg(feature = "self-metrics")]
+            match &_res {
+                Ok(()) => self.metrics.record_success(1, start.elapsed()),
+                Err(_) => self.metrics.record_failure(1, start.elapsed(), "write"),
+            }
             Ok(())
         } else {
             // Return success when the event is not enabled
@@ -443,6 +572,11 @@ where
         // as Loggers are typically *not* dropped.
         if let Ok(mut provider) = self.provider.lock() {
             provider.unregister();
+            for additional in &self.additional_providers {
+                if let Ok(mut provider) = additional.provider.lock() {
+                    provider.unregister();
+                }
+            }
             Ok(())
         } else {
             Err(OTelSdkError::InternalFailure(
@@ -452,13 +586,22 @@ where
     }
 
     fn event_enabled(&self, level: Severity, _target: &str, _name: Option<&str>) -> bool {
+        #[cfg(feature = "test-util")]
+        if crate::logs::test_util::mock_listening() {
+            return true;
+        }
         // EventSets are stored in the same order as their int representation,
         // so we can use the level as index to the Vec.
-        let level = get_severity_level(level);
-        match self.event_sets.get(level.as_int() as usize) {
-            Some(event_set) => event_set.enabled(),
-            None => false,
-        }
+        // The provider of a record is only known from its attributes, so the level is enabled
+        // when any provider has a listener for it.
+        let level = get_severity_level(level).as_int() as usize;
+        std::iter::once(&self.event_sets)
+            .chain(
+                self.additional_providers
+                    .iter()
+                    .map(|provider| &provider.event_sets),
+            )
+            .any(|event_sets| event_sets.get(level).is_some_and(|set| set.enabled()))
     }
 
     fn set_resource(&mut self, resource: &Resource) {
@@ -494,14 +637,125 @@ fn val_to_any_value(val: &Value) -> AnyValue {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use opentelemetry::logs::{LogRecord, Logger, LoggerProvider};
+    use opentelemetry::trace::{SpanId, TraceFlags, TraceId};
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    /// Counts the allocations of the current thread.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn encode_simple_record_without_allocation() {
+        let mut exporter = UserEventsExporter::new(
+            "test_provider",
+            HashSet::new(),
+            DefaultEventNameCallback,
+            FieldNameStyle::default(),
+            &[],
+        );
+        opentelemetry_sdk::logs::LogExporter::set_resource(
+            &mut exporter,
+            &Resource::builder_empty()
+                .with_service_name("test-service")
+                .build(),
+        );
+        let mut record = opentelemetry_sdk::logs::SdkLoggerProvider::builder()
+            .build()
+            .logger("test")
+            .create_log_record();
+        record.set_severity_number(Severity::Error);
+        record.set_body("Unable to process checkout.".into());
+        record.set_timestamp(UNIX_EPOCH + Duration::from_nanos(1_700_000_000_123_456_789));
+        record.set_trace_context(
+            TraceId::from_hex("0af7651916cd43dd8448eb211c80319c").unwrap(),
+            SpanId::from_hex("b7ad6b7169203331").unwrap(),
+            Some(TraceFlags::SAMPLED),
+        );
+        record.add_attribute("event_id", 20);
+        record.add_attribute("user.name", "otel");
+        record.add_attribute("retry", true);
+        record.add_attribute("duration", 1.5);
+
+        let mut eb = EventBuilder::new();
+        // The first record sizes the builder's buffers.
+        exporter.encode(&mut eb, &record, Severity::Error);
+
+        let before = ALLOCATIONS.with(Cell::get);
+        exporter.encode(&mut eb, &record, Severity::Error);
+        assert_eq!(ALLOCATIONS.with(Cell::get) - before, 0);
+    }
 
     #[test]
     fn exporter_debug() {
-        let exporter =
-            UserEventsExporter::new("test_provider", HashSet::new(), DefaultEventNameCallback);
+        let exporter = UserEventsExporter::new(
+            "test_provider",
+            HashSet::new(),
+            DefaultEventNameCallback,
+            FieldNameStyle::default(),
+            &[],
+        );
         assert_eq!(
             format!("{exporter:?}"),
             "user_events log exporter (provider name: test_provider)"
         );
     }
+
+    #[test]
+    fn event_sets_of_provider_attribute() {
+        let exporter = UserEventsExporter::new(
+            "test_provider",
+            HashSet::new(),
+            DefaultEventNameCallback,
+            FieldNameStyle::default(),
+            &[("test_audit", 2)],
+        );
+        let logger = opentelemetry_sdk::logs::SdkLoggerProvider::builder()
+            .build()
+            .logger("test");
+
+        let record = logger.create_log_record();
+        assert!(std::ptr::eq(
+            exporter.event_sets(&record),
+            exporter.event_sets.as_slice()
+        ));
+
+        let mut record = logger.create_log_record();
+        record.add_attribute(PROVIDER_ATTRIBUTE, "test_audit");
+        assert!(std::ptr::eq(
+            exporter.event_sets(&record),
+            exporter.additional_providers[0].event_sets.as_slice()
+        ));
+
+        let mut record = logger.create_log_record();
+        record.add_attribute(PROVIDER_ATTRIBUTE, "unknown");
+        assert!(std::ptr::eq(
+            exporter.event_sets(&record),
+            exporter.event_sets.as_slice()
+        ));
+    }
 }
@@ -0,0 +1,206 @@
+//! Normalization of the attribute keys into EventHeader field names.
+use opentelemetry::otel_debug;
+
+/// Longest field name written, in bytes.
+pub(crate) const MAX_FIELD_NAME_LEN: usize = 255;
+
+/// How the keys of the attributes are written as field names of the events.
+///
+/// Dotted keys, as the OpenTelemetry semantic conventions use, are awkward to query for some
+/// consumers of the events. With [`FieldNameStyle::Underscores`] or
+/// [`FieldNameStyle::CamelCase`], `http.request.method` is written as `http_request_method` or
+/// `httpRequestMethod`.
+///
+/// Whatever the style, names are truncated to 255 bytes. When the names of two attributes of a
+/// record collide once normalized, e.g. `http.method` and `http_method` with
+/// [`FieldNameStyle::Underscores`], both are written with their original keys.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FieldNameStyle {
+    /// Write the keys as they are.
+    #[default]
+    Verbatim,
+    /// Replace the dots of the keys with underscores.
+    Underscores,
+    /// Join the dot or underscore separated segments of the keys, upper-casing the first ASCII
+    /// letter of each segment but the first.
+    CamelCase,
+}
+
+impl FieldNameStyle {
+    /// The characters of the name of `key`, before truncation.
+    fn chars(self, key: &str) -> impl Iterator<Item = char> + '_ {
+        let mut segment_start = false;
+        let mut first = true;
+        key.chars().filter_map(move |c| {
+            let c = match self {
+                FieldNameStyle::Verbatim => c,
+                FieldNameStyle::Underscores if c == '.' => '_',
+                FieldNameStyle::Underscores => c,
+                FieldNameStyle::CamelCase if c == '.' || c == '_' => {
+                    segment_start = true;
+                    return None;
+                }
+                FieldNameStyle::CamelCase if segment_start && !first => {
+                    segment_start = false;
+                    c.to_ascii_uppercase()
+                }
+                FieldNameStyle::CamelCase => {
+                    segment_start = false;
+                    c
+                }
+            };
+            first = false;
+            Some(c)
+        })
+    }
+}
+
+/// The characters of `chars` fitting in [`MAX_FIELD_NAME_LEN`] bytes.
+fn truncate(chars: impl Iterator<Item = char>) -> impl Iterator<Item = char> {
+    chars
+        .scan(0, |len, c| {
+            *len += c.len_utf8();
+            Some((*len, c))
+        })
+        .take_while(|(len, _)| *len <= MAX_FIELD_NAME_LEN)
+        .map(|(_, c)| c)
+}
+
+/// Writes the field names of the attributes of a record into a stack buffer, so normalizing
+/// doesn't allocate.
+pub(crate) struct FieldNameWriter {
+    style: FieldNameStyle,
+    buf: [u8; MAX_FIELD_NAME_LEN],
+}
+
+impl FieldNameWriter {
+    pub(crate) fn new(style: FieldNameStyle) -> Self {
+        FieldNameWriter {
+            style,
+            buf: [0; MAX_FIELD_NAME_LEN],
+        }
+    }
+
+    /// The field name of `key`, among the attributes of a record with the keys `keys`.
+    ///
+    /// The keys are scanned for collisions on every call, which is quadratic in the number of
+    /// attributes of the record but needs no allocation, records having few attributes.
+    pub(crate) fn field_name<'a, I>(&'a mut self, key: &'a str, keys: I) -> &'a str
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        if self.style == FieldNameStyle::Verbatim {
+            return truncate_str(key);
+        }
+        let style = self.style;
+        let normalized = || truncate(style.chars(key));
+        if normalized().eq(truncate(key.chars())) {
+            // Unchanged names only collide with changed ones, written with their original key.
+            return truncate_str(key);
+        }
+        let collision = keys.into_iter().any(|other| {
+            let other = other.as_ref();
+            other != key && truncate(style.chars(other)).eq(normalized())
+        });
+        if collision {
+            otel_debug!(name: "UserEvents.FieldNameCollision", key = key);
+            return truncate_str(key);
+        }
+
+        let mut len = 0;
+        for c in normalized() {
+            len += c.encode_utf8(&mut self.buf[len..]).len();
+        }
+        // Only whole characters were written.
+        std::str::from_utf8(&self.buf[..len]).unwrap_or_default()
+    }
+}
+
+/// `key` truncated to [`MAX_FIELD_NAME_LEN`] bytes, at a character boundary.
+fn truncate_str(key: &str) -> &str {
+    if key.len() <= MAX_FIELD_NAME_LEN {
+        return key;
+    }
+    let mut end = MAX_FIELD_NAME_LEN;
+    while !key.is_char_boundary(end) {
+        end -= 1;
+    }
+    &key[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_name(style: FieldNameStyle, key: &str, keys: &[&str]) -> String {
+        FieldNameWriter::new(style)
+            .field_name(key, keys)
+            .to_string()
+    }
+
+    #[test]
+    fn test_styles() {
+        for (key, underscores, camel_case) in [
+            (
+                "http.request.method",
+                "http_request_method",
+                "httpRequestMethod",
+            ),
+            (
+                "user_agent.original",
+                "user_agent_original",
+                "userAgentOriginal",
+            ),
+            ("retry", "retry", "retry"),
+            (".leading", "_leading", "leading"),
+            ("a..b", "a__b", "aB"),
+            ("server.2xx", "server_2xx", "server2xx"),
+            ("é.ü", "é_ü", "éü"),
+        ] {
+            assert_eq!(field_name(FieldNameStyle::Verbatim, key, &[key]), key);
+            assert_eq!(
+                field_name(FieldNameStyle::Underscores, key, &[key]),
+                underscores
+            );
+            assert_eq!(
+                field_name(FieldNameStyle::CamelCase, key, &[key]),
+                camel_case
+            );
+        }
+    }
+
+    #[test]
+    fn test_truncation() {
+        let key = format!("{}.{}", "a".repeat(200), "é".repeat(100));
+        for style in [
+            FieldNameStyle::Verbatim,
+            FieldNameStyle::Underscores,
+            FieldNameStyle::CamelCase,
+        ] {
+            let name = field_name(style, &key, &[key.as_str()]);
+            assert!(name.len() <= MAX_FIELD_NAME_LEN);
+            assert!(name.len() > MAX_FIELD_NAME_LEN - 2);
+            assert!(name.starts_with(&"a".repeat(200)));
+        }
+    }
+
+    #[test]
+    fn test_collisions() {
+        let keys = ["http.method", "http_method", "user.id"];
+        let style = FieldNameStyle::Underscores;
+        assert_eq!(field_name(style, "http.method", &keys), "http.method");
+        assert_eq!(field_name(style, "http_method", &keys), "http_method");
+        assert_eq!(field_name(style, "user.id", &keys), "user_id");
+
+        let keys = ["user.id", "user_id", "userId"];
+        let style = FieldNameStyle::CamelCase;
+        assert_eq!(field_name(style, "user.id", &keys), "user.id");
+        assert_eq!(field_name(style, "user_id", &keys), "user_id");
+        assert_eq!(field_name(style, "userId", &keys), "userId");
+
+        // Repeated keys are the same attribute, not a collision.
+        let keys = ["user.id", "user.id"];
+        assert_eq!(field_name(style, "user.id", &keys), "userId");
+    }
+}
@@ -0,0 +1,146 @@
+//! Formatting of the Part A fields into stack buffers, keeping the encoding of records free of
+//! heap allocations.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Length of the longest timestamp written by [`write_rfc3339`], with nanoseconds.
+pub(crate) const RFC3339_MAX_LEN: usize = "2024-01-01T00:00:00.123456789+00:00".len();
+
+/// Days from 1970-01-01 to 9999-12-31, the last day with a four digit year.
+const MAX_DAYS: u64 = 2_932_896;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Write `time` in the RFC 3339 format of `chrono::DateTime::to_rfc3339` to `buf`, with the
+/// sub-second digits omitted, or as milliseconds, microseconds or nanoseconds, whichever is
+/// exact.
+///
+/// Returns `None` for times before 1970 or after 9999, which need the chrono formatting.
+pub(crate) fn write_rfc3339(time: SystemTime, buf: &mut [u8; RFC3339_MAX_LEN]) -> Option<&str> {
+    let since_epoch = time.duration_since(UNIX_EPOCH).ok()?;
+    let secs = since_epoch.as_secs();
+    let days = secs / 86_400;
+    if days > MAX_DAYS {
+        return None;
+    }
+    let (year, month, day) = civil_from_days(days);
+    let secs_of_day = secs % 86_400;
+
+    let mut len = 0;
+    let mut put = |value: u64, width: usize, suffix: Option<u8>| {
+        write_decimal(&mut buf[len..len + width], value);
+        len += width;
+        if let Some(suffix) = suffix {
+            buf[len] = suffix;
+            len += 1;
+        }
+    };
+    put(year, 4, Some(b'-'));
+    put(month, 2, Some(b'-'));
+    put(day, 2, Some(b'T'));
+    put(secs_of_day / 3600, 2, Some(b':'));
+    put(secs_of_day % 3600 / 60, 2, Some(b':'));
+    put(secs_of_day % 60, 2, None);
+
+    let nanos = u64::from(since_epoch.subsec_nanos());
+    let (fraction, width) = match nanos {
+        0 => (0, 0),
+        _ if nanos % 1_000_000 == 0 => (nanos / 1_000_000, 3),
+        _ if nanos % 1_000 == 0 => (nanos / 1_000, 6),
+        _ => (nanos, 9),
+    };
+    if width > 0 {
+        buf[len] = b'.';
+        len += 1;
+        write_decimal(&mut buf[len..len + width], fraction);
+        len += width;
+    }
+    buf[len..len + 6].copy_from_slice(b"+00:00");
+    len += 6;
+
+    std::str::from_utf8(&buf[..len]).ok()
+}
+
+/// Write `bytes` as lowercase hexadecimal to `buf`, which must be twice as long.
+pub(crate) fn write_hex<'a>(bytes: &[u8], buf: &'a mut [u8]) -> &'a str {
+    let buf = &mut buf[..bytes.len() * 2];
+    for (byte, digits) in bytes.iter().zip(buf.chunks_exact_mut(2)) {
+        digits[0] = HEX_DIGITS[usize::from(byte >> 4)];
+        digits[1] = HEX_DIGITS[usize::from(byte & 0xf)];
+    }
+    // Only ASCII digits were written.
+    std::str::from_utf8(buf).unwrap_or_default()
+}
+
+/// Write `value` zero-padded to the length of `buf`.
+fn write_decimal(buf: &mut [u8], mut value: u64) {
+    for digit in buf.iter_mut().rev() {
+        *digit = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+}
+
+/// The proleptic Gregorian date of the day `days` after 1970-01-01, see
+/// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{SpanId, TraceId};
+    use std::time::Duration;
+
+    #[test]
+    fn test_write_rfc3339() {
+        let mut buf = [0; RFC3339_MAX_LEN];
+        for (secs, nanos) in [
+            (0, 0),
+            (951_782_400, 0), // 2000-02-29
+            (1_709_251_199, 999_999_999),
+            (1_709_251_200, 120_000_000),
+            (4_102_444_800, 123_456_000),
+            (253_402_300_799, 1),
+        ] {
+            let time = UNIX_EPOCH + Duration::new(secs, nanos);
+            assert_eq!(
+                write_rfc3339(time, &mut buf),
+                Some(
+                    chrono::DateTime::<chrono::Utc>::from(time)
+                        .to_rfc3339()
+                        .as_str()
+                )
+            );
+        }
+        assert_eq!(
+            write_rfc3339(UNIX_EPOCH - Duration::from_secs(1), &mut buf),
+            None
+        );
+    }
+
+    #[test]
+    fn test_write_hex() {
+        let trace_id = TraceId::from_hex("0102030405060708090a0b0c0d0e0fab").unwrap();
+        let mut buf = [0; 32];
+        assert_eq!(
+            write_hex(&trace_id.to_bytes(), &mut buf),
+            trace_id.to_string()
+        );
+        let span_id = SpanId::from_hex("00f067aa0ba902b7").unwrap();
+        let mut buf = [0; 16];
+        assert_eq!(
+            write_hex(&span_id.to_bytes(), &mut buf),
+            span_id.to_string()
+        );
+    }
+}
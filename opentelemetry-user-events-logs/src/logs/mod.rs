@@ -1,5 +1,15 @@
+mod activity;
 mod exporter;
+mod field_name;
+mod format;
 mod processor;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+mod writer;
+
+pub use activity::activity_id;
+pub use exporter::PROVIDER_ATTRIBUTE;
+pub use field_name::FieldNameStyle;
 
 #[cfg(feature = "experimental_eventname_callback")]
 pub use exporter::EventNameCallback;
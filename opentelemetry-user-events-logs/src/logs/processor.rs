@@ -1,3 +1,4 @@
+use opentelemetry::logs::Severity;
 use opentelemetry::InstrumentationScope;
 use opentelemetry_sdk::logs::LogExporter;
 use opentelemetry_sdk::Resource;
@@ -10,6 +11,7 @@ use std::collections::HashSet;
 use std::error::Error;
 
 use crate::logs::exporter::{DefaultEventNameCallback, EventNameCallback, UserEventsExporter};
+use crate::logs::field_name::FieldNameStyle;
 
 /// Processes and exports logs to user_events.
 ///
@@ -21,6 +23,43 @@ where
     C: EventNameCallback,
 {
     exporter: UserEventsExporter<C>,
+    severity_filter: SeverityFilter,
+}
+
+/// Minimum severity of the records to export, overridable per target.
+#[derive(Debug, Default)]
+struct SeverityFilter {
+    min_severity: Option<Severity>,
+    // Sorted by decreasing target length, so the first match is the most specific.
+    overrides: Vec<(Cow<'static, str>, Severity)>,
+}
+
+impl SeverityFilter {
+    fn add_override(&mut self, target: Cow<'static, str>, severity: Severity) {
+        self.overrides.retain(|(existing, _)| *existing != target);
+        self.overrides.push((target, severity));
+        self.overrides
+            .sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()));
+    }
+
+    /// Whether records of `severity` from `target` pass the filter.
+    ///
+    /// An override applies to its target and to the modules below it, e.g. an override of
+    /// `my_crate::db` applies to `my_crate::db::pool` but not to `my_crate::dbx`.
+    #[inline]
+    fn is_enabled(&self, severity: Severity, target: &str) -> bool {
+        let min_severity = self
+            .overrides
+            .iter()
+            .find(|(prefix, _)| {
+                target
+                    .strip_prefix(prefix.as_ref())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map(|(_, severity)| Some(*severity))
+            .unwrap_or(self.min_severity);
+        min_severity.map_or(true, |min_severity| severity as i32 >= min_severity as i32)
+    }
 }
 
 impl<C> std::fmt::Debug for Processor<C>
@@ -30,6 +69,7 @@ where
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Processor")
             .field("exporter", &self.exporter)
+            .field("severity_filter", &self.severity_filter)
             .finish()
     }
 }
@@ -46,6 +86,19 @@ where
     C: EventNameCallback,
 {
     fn emit(&self, record: &mut SdkLogRecord, scope: &InstrumentationScope) {
+        // Drop filtered out records before any serialization work. Records without a
+        // severity are left to the exporter, which reports them as errors.
+        if let Some(severity) = record.severity_number() {
+            let target = record
+                .target()
+                .map_or(scope.name(), |target| target.as_ref());
+            if !self.severity_filter.is_enabled(severity, target)
+                || !self.exporter.event_enabled(severity, target, None)
+            {
+                return;
+            }
+        }
+
         let log_tuple = &[(record as &SdkLogRecord, scope)];
         // TODO: Using futures_executor::block_on can make the code non reentrant safe
         // if that crate starts emitting logs that are bridged to OTel.
@@ -70,7 +123,8 @@ where
         target: &str,
         name: Option<&str>,
     ) -> bool {
-        self.exporter.event_enabled(level, target, name)
+        self.severity_filter.is_enabled(level, target)
+            && self.exporter.event_enabled(level, target, name)
     }
 
     fn set_resource(&mut self, resource: &Resource) {
@@ -86,6 +140,9 @@ where
     provider_name: &'a str,
     resource_attribute_keys: HashSet<Cow<'static, str>>,
     event_name_callback: C,
+    severity_filter: SeverityFilter,
+    field_name_style: FieldNameStyle,
+    additional_providers: Vec<(&'a str, u64)>,
 }
 
 impl<'a, C> std::fmt::Debug for ProcessorBuilder<'a, C>
@@ -97,6 +154,9 @@ where
             .field("provider_name", &self.provider_name)
             .field("resource_attribute_keys", &self.resource_attribute_keys)
             .field("event_name_callback", &std::any::type_name::<C>())
+            .field("severity_filter", &self.severity_filter)
+            .field("field_name_style", &self.field_name_style)
+            .field("additional_providers", &self.additional_providers)
             .finish()
     }
 }
@@ -130,6 +190,9 @@ impl<'a> ProcessorBuilder<'a, DefaultEventNameCallback> {
             provider_name,
             resource_attribute_keys: HashSet::new(),
             event_name_callback: DefaultEventNameCallback,
+            severity_filter: SeverityFilter::default(),
+            field_name_style: FieldNameStyle::default(),
+            additional_providers: Vec::new(),
         }
     }
 }
@@ -176,6 +239,83 @@ where
         self
     }
 
+    /// Sets the minimum severity of the records to export.
+    ///
+    /// Records below it are dropped in `emit()` before any serialization, and `event_enabled()`
+    /// returns `false` for them, so logging bridges can skip creating them. Records are also
+    /// dropped early when no listener is enabled on the tracepoint of their level.
+    ///
+    /// By default, all the records with an enabled tracepoint are exported.
+    pub fn with_min_severity(mut self, severity: Severity) -> Self {
+        self.severity_filter.min_severity = Some(severity);
+        self
+    }
+
+    /// Overrides the minimum severity for the records of `target` and of the modules below it.
+    ///
+    /// The target is the `target` of the record if set, the name of the instrumentation scope
+    /// otherwise. When several overrides apply, the longest target wins.
+    ///
+    /// ```
+    /// use opentelemetry::logs::Severity;
+    /// use opentelemetry_user_events_logs::Processor;
+    ///
+    /// let processor = Processor::builder("myprovider")
+    ///     .with_min_severity(Severity::Warn)
+    ///     .with_severity_override("my_crate::db", Severity::Debug)
+    ///     .build();
+    /// ```
+    pub fn with_severity_override(
+        mut self,
+        target: impl Into<Cow<'static, str>>,
+        severity: Severity,
+    ) -> Self {
+        self.severity_filter.add_override(target.into(), severity);
+        self
+    }
+
+    /// Sets how the attribute keys are written as field names, see [`FieldNameStyle`].
+    ///
+    /// By default, the keys are written as they are.
+    ///
+    /// ```
+    /// use opentelemetry_user_events_logs::{FieldNameStyle, Processor};
+    ///
+    /// // `http.request.method` is written as `http_request_method`.
+    /// let processor = Processor::builder("myprovider")
+    ///     .with_field_name_style(FieldNameStyle::Underscores)
+    ///     .build();
+    /// ```
+    pub fn with_field_name_style(mut self, style: FieldNameStyle) -> Self {
+        self.field_name_style = style;
+        self
+    }
+
+    /// Registers an additional provider, with the tracepoints of its `keyword`, to which the
+    /// records whose [`PROVIDER_ATTRIBUTE`] is `provider_name` are written instead of the default
+    /// provider, e.g. to route audit or security events to their own channel.
+    ///
+    /// The provider name follows the rules of the default one. Records naming a provider that
+    /// isn't registered are written to the default provider.
+    ///
+    /// ```
+    /// use opentelemetry_user_events_logs::Processor;
+    ///
+    /// // Records with the `microsoft.custom_event.provider` attribute set to `myaudit` are
+    /// // written to the `myaudit_L{level}K2` tracepoints.
+    /// let processor = Processor::builder("myprovider")
+    ///     .with_additional_provider("myaudit", 2)
+    ///     .build();
+    /// ```
+    ///
+    /// [`PROVIDER_ATTRIBUTE`]: crate::PROVIDER_ATTRIBUTE
+    pub fn with_additional_provider(mut self, provider_name: &'a str, keyword: u64) -> Self {
+        self.additional_providers
+            .retain(|(name, _)| *name != provider_name);
+        self.additional_providers.push((provider_name, keyword));
+        self
+    }
+
     /// Sets a callback for determining event names
     #[cfg(feature = "experimental_eventname_callback")]
     pub fn with_event_name_callback<NewC>(self, callback: NewC) -> ProcessorBuilder<'a, NewC>
@@ -186,35 +326,52 @@ where
             provider_name: self.provider_name,
             resource_attribute_keys: self.resource_attribute_keys,
             event_name_callback: callback,
+            severity_filter: self.severity_filter,
+            field_name_style: self.field_name_style,
+            additional_providers: self.additional_providers,
         }
     }
 
     /// Builds the processor with the configured callback
     pub fn build(self) -> Result<Processor<C>, Box<dyn Error>> {
-        // Validate provider name
-        if self.provider_name.is_empty() {
-            return Err("Provider name cannot be empty.".into());
-        }
-        if self.provider_name.len() >= 234 {
-            return Err("Provider name must be less than 234 characters.".into());
-        }
-        if !self
-            .provider_name
-            .chars()
-            .all(|c| c.is_ascii_alphanumeric() || c == '_')
-        {
-            return Err("Provider name must contain only ASCII letters, digits, and '_'.".into());
+        validate_provider_name(self.provider_name)?;
+        for (provider_name, _) in &self.additional_providers {
+            validate_provider_name(provider_name)?;
+            if *provider_name == self.provider_name {
+                return Err("Additional provider name must differ from the provider name.".into());
+            }
         }
 
         let exporter = UserEventsExporter::new(
             self.provider_name,
             self.resource_attribute_keys,
             self.event_name_callback,
+            self.field_name_style,
+            &self.additional_providers,
         );
-        Ok(Processor { exporter })
+        Ok(Processor {
+            exporter,
+            severity_filter: self.severity_filter,
+        })
     }
 }
 
+fn validate_provider_name(provider_name: &str) -> Result<(), Box<dyn Error>> {
+    if provider_name.is_empty() {
+        return Err("Provider name cannot be empty.".into());
+    }
+    if provider_name.len() >= 234 {
+        return Err("Provider name must be less than 234 characters.".into());
+    }
+    if !provider_name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err("Provider name must contain only ASCII letters, digits, and '_'.".into());
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -312,6 +469,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_processor_builder_with_additional_provider() {
+        let processor = Processor::builder("test_provider")
+            .with_additional_provider("test_audit", 2)
+            .build();
+        assert!(processor.is_ok());
+
+        let processor = Processor::builder("test_provider")
+            .with_additional_provider("test-audit", 2)
+            .build();
+        assert_eq!(
+            processor.unwrap_err().to_string(),
+            "Provider name must contain only ASCII letters, digits, and '_'."
+        );
+
+        let processor = Processor::builder("test_provider")
+            .with_additional_provider("test_provider", 2)
+            .build();
+        assert!(processor.is_err());
+    }
+
     #[test]
     fn test_shutdown() {
         let processor = Processor::builder("test_provider").build().unwrap();
@@ -337,6 +515,41 @@ mod tests {
         processor.emit(&mut record, &instrumentation);
     }
 
+    #[test]
+    fn test_severity_filter() {
+        let mut filter = SeverityFilter::default();
+        assert!(filter.is_enabled(Severity::Trace, "my_crate"));
+
+        filter.min_severity = Some(Severity::Warn);
+        filter.add_override("my_crate::db".into(), Severity::Debug);
+        filter.add_override("my_crate::db::pool".into(), Severity::Error);
+        filter.add_override("my_crate".into(), Severity::Info);
+
+        assert!(!filter.is_enabled(Severity::Info, "other_crate"));
+        assert!(filter.is_enabled(Severity::Warn, "other_crate"));
+        assert!(filter.is_enabled(Severity::Info, "my_crate::http"));
+        assert!(!filter.is_enabled(Severity::Debug, "my_crate::http"));
+        assert!(filter.is_enabled(Severity::Debug, "my_crate::db"));
+        assert!(filter.is_enabled(Severity::Debug, "my_crate::db::query"));
+        assert!(!filter.is_enabled(Severity::Warn, "my_crate::db::pool"));
+        assert!(!filter.is_enabled(Severity::Debug, "my_crate::dbx"));
+
+        filter.add_override("my_crate::db".into(), Severity::Fatal);
+        assert!(!filter.is_enabled(Severity::Error, "my_crate::db"));
+        assert_eq!(filter.overrides.len(), 3);
+    }
+
+    #[test]
+    fn test_event_enabled_below_min_severity() {
+        let processor = Processor::builder("test_provider")
+            .with_min_severity(Severity::Warn)
+            .build()
+            .unwrap();
+
+        assert!(!processor.event_enabled(Severity::Info, "test", Some("test")));
+        assert!(!processor.event_enabled(Severity::Debug, "test", Some("test")));
+    }
+
     #[test]
     fn test_event_enabled() {
         let processor = Processor::builder("test_provider").build().unwrap();
@@ -0,0 +1,474 @@
+//! Listening to the events written by the exporter, to assert in tests exactly which events a
+//! process emitted.
+//!
+//! An [`EventListener`] listens in one of two modes:
+//!
+//! - [`EventListener::perf`] records the tracepoints with `perf record` and decodes them with
+//!   `perf-decode`, the EventHeader decoder of [LinuxTracepoints], for end-to-end tests on
+//!   kernels with user_events enabled. `perf` needs the permission to record tracepoints, e.g.
+//!   running the tests as root.
+//! - [`EventListener::mock`] captures in-process the events the exporters write on the current
+//!   thread, encoded as they are for user_events, instead of writing them to user_events.
+//!
+//! [`EventListener::start`] records with `perf` when user_events, `perf` and `perf-decode` are
+//! available and captures in-process otherwise, so the same test runs everywhere. The events
+//! are decoded to the JSON format of `perf-decode` in both modes.
+//!
+//! ```no_run
+//! use opentelemetry::logs::{LogRecord, Logger, LoggerProvider, Severity};
+//! use opentelemetry_sdk::logs::SdkLoggerProvider;
+//! use opentelemetry_user_events_logs::test_util::EventListener;
+//! use opentelemetry_user_events_logs::Processor;
+//!
+//! let provider = SdkLoggerProvider::builder()
+//!     .with_log_processor(Processor::builder("myprovider").build().unwrap())
+//!     .build();
+//! let listener = EventListener::start(&["user_events:myprovider_L2K1"]).unwrap();
+//!
+//! let logger = provider.logger("test");
+//! let mut record = logger.create_log_record();
+//! record.set_severity_number(Severity::Error);
+//! record.set_body("payment failed".into());
+//! logger.emit(record);
+//!
+//! let events = listener.stop().unwrap();
+//! assert_eq!(events.len(), 1);
+//! assert_eq!(events[0].name(), "Log");
+//! assert_eq!(events[0].part_b().unwrap()["body"], "payment failed");
+//! ```
+//!
+//! [LinuxTracepoints]: https://github.com/microsoft/LinuxTracepoints
+use crate::logs::writer::EventWriter;
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+const USER_EVENTS_STATUS: &str = "/sys/kernel/tracing/user_events_status";
+const TRACEPOINT_PREFIX: &str = "user_events:";
+// perf doesn't tell when it starts recording, the integration tests wait as long.
+const PERF_START_DELAY: Duration = Duration::from_secs(1);
+
+thread_local! {
+    /// The events captured by the mock listener of the thread, with their tracepoint.
+    static MOCK_EVENTS: RefCell<Option<Vec<(String, DecodedEvent)>>> = const { RefCell::new(None) };
+}
+
+/// An event decoded from its EventHeader encoding, in the JSON format of `perf-decode`.
+///
+/// The `n` field is `provider:event_name`, the `PartA`, `PartB` and `PartC` structs hold the
+/// fields of the Common Schema parts, and `meta` holds the `level` and `keyword`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedEvent(Value);
+
+impl DecodedEvent {
+    /// The name of the provider of the event.
+    pub fn provider(&self) -> &str {
+        self.full_name()
+            .split_once(':')
+            .map_or("", |(provider, _)| provider)
+    }
+
+    /// The name of the event, `Log` unless set by an `EventNameCallback`.
+    pub fn name(&self) -> &str {
+        self.full_name()
+            .split_once(':')
+            .map_or(self.full_name(), |(_, name)| name)
+    }
+
+    fn full_name(&self) -> &str {
+        self.0.get("n").and_then(Value::as_str).unwrap_or("")
+    }
+
+    /// The level of the event, 1 for critical errors to 5 for verbose events.
+    pub fn level(&self) -> Option<u8> {
+        let level = self.0.get("meta")?.get("level")?.as_u64()?;
+        level.try_into().ok()
+    }
+
+    /// The keyword of the event.
+    pub fn keyword(&self) -> Option<u64> {
+        let keyword = self.0.get("meta")?.get("keyword")?.as_str()?;
+        u64::from_str_radix(keyword.trim_start_matches("0x"), 16).ok()
+    }
+
+    /// The fields of Part A, the time, trace context and cloud role of the event.
+    pub fn part_a(&self) -> Option<&Map<String, Value>> {
+        self.0.get("PartA")?.as_object()
+    }
+
+    /// The fields of Part B, the body, severity, event id and name of the record.
+    pub fn part_b(&self) -> Option<&Map<String, Value>> {
+        self.0.get("PartB")?.as_object()
+    }
+
+    /// The fields of Part C, the attributes of the record and the exported resource
+    /// attributes.
+    pub fn part_c(&self) -> Option<&Map<String, Value>> {
+        self.0.get("PartC")?.as_object()
+    }
+
+    /// The decoded event.
+    pub fn as_json(&self) -> &Value {
+        &self.0
+    }
+}
+
+/// The events of the JSON output of `perf-decode` for a `perf.data` file, in the order they
+/// were recorded.
+pub fn parse_perf_decode(output: &str) -> Result<Vec<DecodedEvent>, serde_json::Error> {
+    // perf-decode starts its output with a byte order mark.
+    let output = output.trim_start_matches('\u{FEFF}');
+    let files: Map<String, Value> = serde_json::from_str(output)?;
+    Ok(files
+        .into_iter()
+        .flat_map(|(_, events)| match events {
+            Value::Array(events) => events,
+            _ => Vec::new(),
+        })
+        .map(DecodedEvent)
+        .collect())
+}
+
+/// Whether the kernel supports user_events, with tracefs mounted at `/sys/kernel/tracing`.
+pub fn user_events_available() -> bool {
+    Path::new(USER_EVENTS_STATUS).exists()
+}
+
+fn command_available(program: &str) -> bool {
+    Command::new(program)
+        .arg("--help")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// Listens to the events of user_events tracepoints, e.g. `user_events:myprovider_L2K1`, with
+/// `perf` or in-process.
+#[derive(Debug)]
+pub struct EventListener {
+    mode: Mode,
+}
+
+#[derive(Debug)]
+enum Mode {
+    Perf { perf: Child, output: PathBuf },
+    Mock { tracepoints: Vec<String> },
+    Stopped,
+}
+
+impl EventListener {
+    /// Listen to `tracepoints` with `perf` when user_events, `perf` and `perf-decode` are
+    /// available, in-process otherwise.
+    pub fn start(tracepoints: &[&str]) -> io::Result<Self> {
+        if user_events_available() && command_available("perf") && command_available("perf-decode")
+        {
+            EventListener::perf(tracepoints)
+        } else {
+            Ok(EventListener::mock(tracepoints))
+        }
+    }
+
+    /// Record `tracepoints` with `perf record`, returning once it records.
+    pub fn perf(tracepoints: &[&str]) -> io::Result<Self> {
+        static RECORDINGS: AtomicUsize = AtomicUsize::new(0);
+        let output = std::env::temp_dir().join(format!(
+            "user_events_{}_{}.data",
+            std::process::id(),
+            RECORDINGS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let perf = Command::new("perf")
+            .arg("record")
+            .arg("-o")
+            .arg(&output)
+            .arg("-e")
+            .arg(tracepoints.join(","))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        std::thread::sleep(PERF_START_DELAY);
+        Ok(EventListener {
+            mode: Mode::Perf { perf, output },
+        })
+    }
+
+    /// Capture the events of `tracepoints` written on the current thread in-process, or all
+    /// its events when `tracepoints` is empty.
+    ///
+    /// The exporters report all the levels as enabled to the logging bridges while the thread
+    /// has a mock listener. A thread has at most one mock listener, a new one replaces it.
+    pub fn mock(tracepoints: &[&str]) -> Self {
+        MOCK_EVENTS.with(|events| *events.borrow_mut() = Some(Vec::new()));
+        let tracepoints = tracepoints
+            .iter()
+            .map(|&tracepoint| {
+                let tracepoint = tracepoint
+                    .strip_prefix(TRACEPOINT_PREFIX)
+                    .unwrap_or(tracepoint);
+                tracepoint.to_string()
+            })
+            .collect();
+        EventListener {
+            mode: Mode::Mock { tracepoints },
+        }
+    }
+
+    /// Whether the events are captured in-process.
+    pub fn is_mock(&self) -> bool {
+        matches!(self.mode, Mode::Mock { .. })
+    }
+
+    /// Stop listening, returning the events of the tracepoints, in the order they were written.
+    pub fn stop(mut self) -> io::Result<Vec<DecodedEvent>> {
+        match std::mem::replace(&mut self.mode, Mode::Stopped) {
+            Mode::Perf { mut perf, output } => {
+                let events = stop_perf(&mut perf).and_then(|()| decode(&output));
+                let _ = std::fs::remove_file(&output);
+                events
+            }
+            Mode::Mock { tracepoints } => {
+                let events = MOCK_EVENTS
+                    .with(|events| events.borrow_mut().take())
+                    .unwrap_or_default();
+                Ok(events
+                    .into_iter()
+                    .filter(|(tracepoint, _)| {
+                        tracepoints.is_empty() || tracepoints.contains(tracepoint)
+                    })
+                    .map(|(_, event)| event)
+                    .collect())
+            }
+            Mode::Stopped => Ok(Vec::new()),
+        }
+    }
+}
+
+impl Drop for EventListener {
+    fn drop(&mut self) {
+        match &mut self.mode {
+            Mode::Perf { perf, output } => {
+                let _ = perf.kill();
+                let _ = perf.wait();
+                let _ = std::fs::remove_file(output);
+            }
+            Mode::Mock { .. } => MOCK_EVENTS.with(|events| *events.borrow_mut() = None),
+            Mode::Stopped => {}
+        }
+    }
+}
+
+/// Interrupt `perf`, which writes the recorded events when interrupted.
+fn stop_perf(perf: &mut Child) -> io::Result<()> {
+    if let Some(status) = perf.try_wait()? {
+        return Err(io::Error::other(format!(
+            "perf record exited with {status}"
+        )));
+    }
+    let interrupted = Command::new("kill")
+        .args(["-INT", &perf.id().to_string()])
+        .status()?;
+    if !interrupted.success() {
+        return Err(io::Error::other("failed to interrupt perf record"));
+    }
+    perf.wait()?;
+    Ok(())
+}
+
+fn decode(output: &Path) -> io::Result<Vec<DecodedEvent>> {
+    let decoded = Command::new("perf-decode").arg(output).output()?;
+    if !decoded.status.success() {
+        return Err(io::Error::other(format!(
+            "perf-decode failed: {}",
+            String::from_utf8_lossy(&decoded.stderr)
+        )));
+    }
+    parse_perf_decode(&String::from_utf8_lossy(&decoded.stdout)).map_err(io::Error::other)
+}
+
+/// Whether the current thread has a mock listener.
+pub(crate) fn mock_listening() -> bool {
+    MOCK_EVENTS.with(|events| events.borrow().is_some())
+}
+
+/// An event written by the exporter while the thread has a mock listener.
+#[derive(Debug, Default)]
+pub(crate) struct MockEvent {
+    name: String,
+    fields: Vec<(String, MockField)>,
+}
+
+#[derive(Debug)]
+enum MockField {
+    Value(Value),
+    /// A struct made of the given number of following fields.
+    Struct(u8),
+}
+
+impl MockEvent {
+    fn add(&mut self, name: &str, value: Value) {
+        self.fields
+            .push((name.to_string(), MockField::Value(value)));
+    }
+
+    /// Capture the event, written to the tracepoint of `provider`, `level` and `keyword`.
+    pub(crate) fn record(self, provider: &str, level: u8, keyword: u64) {
+        let mut object = Map::new();
+        object.insert(
+            "n".to_string(),
+            Value::from(format!("{provider}:{}", self.name)),
+        );
+        let mut fields = self.fields.into_iter();
+        while let Some((name, field)) = fields.next() {
+            let value = nest(field, &mut fields);
+            object.insert(name, value);
+        }
+        object.insert(
+            "meta".to_string(),
+            serde_json::json!({ "level": level, "keyword": format!("{keyword:#x}") }),
+        );
+        let tracepoint = format!("{provider}_L{level:x}K{keyword:x}");
+        MOCK_EVENTS.with(|events| {
+            if let Some(events) = events.borrow_mut().as_mut() {
+                events.push((tracepoint, DecodedEvent(Value::Object(object))));
+            }
+        });
+    }
+}
+
+/// The value of `field`, taking the fields of a struct from `fields`.
+fn nest(field: MockField, fields: &mut impl Iterator<Item = (String, MockField)>) -> Value {
+    match field {
+        MockField::Value(value) => value,
+        MockField::Struct(field_count) => {
+            let mut object = Map::new();
+            for _ in 0..field_count {
+                let Some((name, field)) = fields.next() else {
+                    break;
+                };
+                let value = nest(field, fields);
+                object.insert(name, value);
+            }
+            Value::Object(object)
+        }
+    }
+}
+
+impl EventWriter for MockEvent {
+    fn reset(&mut self, name: &str) {
+        self.name = name.to_string();
+        self.fields.clear();
+    }
+
+    fn add_bool(&mut self, name: &str, value: bool) {
+        self.add(name, Value::from(value));
+    }
+
+    fn add_i16(&mut self, name: &str, value: i16) {
+        self.add(name, Value::from(value));
+    }
+
+    fn add_i64(&mut self, name: &str, value: i64) {
+        self.add(name, Value::from(value));
+    }
+
+    fn add_u32(&mut self, name: &str, value: u32) {
+        self.add(name, Value::from(value));
+    }
+
+    fn add_f64(&mut self, name: &str, value: f64) {
+        self.add(name, Value::from(value));
+    }
+
+    fn add_str(&mut self, name: &str, value: &str) {
+        self.add(name, Value::from(value));
+    }
+
+    fn add_struct(&mut self, name: &str, field_count: u8, bookmark: &mut usize) {
+        *bookmark = self.fields.len();
+        self.fields
+            .push((name.to_string(), MockField::Struct(field_count)));
+    }
+
+    fn set_struct_field_count(&mut self, bookmark: usize, field_count: u8) {
+        if let Some((_, MockField::Struct(count))) = self.fields.get_mut(bookmark) {
+            *count = field_count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Processor;
+    use opentelemetry::logs::{AnyValue, LogRecord, Logger, LoggerProvider, Severity};
+    use opentelemetry_sdk::logs::SdkLoggerProvider;
+    use opentelemetry_sdk::Resource;
+
+    fn emit(provider: &SdkLoggerProvider, severity: Severity, body: &str) {
+        let logger = provider.logger("test");
+        let mut record = logger.create_log_record();
+        record.set_severity_number(severity);
+        record.set_body(AnyValue::from(body.to_string()));
+        record.add_attribute("user_name", "otel user");
+        record.add_attribute("event_id", 20_i64);
+        logger.emit(record);
+    }
+
+    #[test]
+    fn mock_listener() {
+        let provider = SdkLoggerProvider::builder()
+            .with_resource(Resource::builder().with_service_name("myrolename").build())
+            .with_log_processor(Processor::builder("myprovider").build().unwrap())
+            .build();
+
+        let listener = EventListener::mock(&["user_events:myprovider_L2K1"]);
+        assert!(listener.is_mock());
+        emit(&provider, Severity::Error, "payment failed");
+        emit(&provider, Severity::Warn, "retrying");
+        let events = listener.stop().unwrap();
+
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.provider(), "myprovider");
+        assert_eq!(event.name(), "Log");
+        assert_eq!(event.level(), Some(2));
+        assert_eq!(event.keyword(), Some(1));
+        assert_eq!(event.as_json()["__csver__"], 1024);
+        let part_a = event.part_a().unwrap();
+        assert_eq!(part_a["ext_cloud_role"], "myrolename");
+        assert!(part_a.contains_key("time"));
+        let part_b = event.part_b().unwrap();
+        assert_eq!(part_b["_typeName"], "Log");
+        assert_eq!(part_b["body"], "payment failed");
+        assert_eq!(part_b["severityNumber"], 17);
+        assert_eq!(part_b["eventId"], 20);
+        assert_eq!(event.part_c().unwrap()["user_name"], "otel user");
+
+        // All the events are captured without tracepoints, and none without a listener.
+        let listener = EventListener::mock(&[]);
+        emit(&provider, Severity::Warn, "retrying");
+        let events = listener.stop().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].level(), Some(3));
+        assert!(!mock_listening());
+    }
+
+    #[test]
+    fn parse_perf_decode_output() {
+        let output = "\u{FEFF}{ \"./perf.data\": [ { \"n\": \"myprovider:Log\", \"__csver__\": 1024, \
+            \"PartA\": { \"time\": \"2025-03-07T16:31:28.279214367+00:00\" }, \
+            \"PartB\": { \"_typeName\": \"Log\", \"severityNumber\": 17 }, \
+            \"meta\": { \"time\": 81252.403220286, \"cpu\": 4, \"level\": 2, \"keyword\": \"0x1\" } } ] }";
+        let events = parse_perf_decode(output).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].provider(), "myprovider");
+        assert_eq!(events[0].level(), Some(2));
+        assert_eq!(events[0].keyword(), Some(1));
+        assert_eq!(events[0].part_b().unwrap()["severityNumber"], 17);
+        assert_eq!(events[0].part_c(), None);
+    }
+}
@@ -0,0 +1,65 @@
+use eventheader::FieldFormat;
+use eventheader_dynamic::EventBuilder;
+
+/// The fields the exporter writes to an event, implemented by [`EventBuilder`], and by the
+/// in-process capture of the `test-util` mock listener, so both see the same encoding.
+pub(crate) trait EventWriter {
+    fn reset(&mut self, name: &str);
+    fn add_bool(&mut self, name: &str, value: bool);
+    fn add_i16(&mut self, name: &str, value: i16);
+    fn add_i64(&mut self, name: &str, value: i64);
+    fn add_u32(&mut self, name: &str, value: u32);
+    fn add_f64(&mut self, name: &str, value: f64);
+    fn add_str(&mut self, name: &str, value: &str);
+    /// Start a struct of `field_count` fields, which can be updated with
+    /// [`set_struct_field_count`](Self::set_struct_field_count) through `bookmark`.
+    fn add_struct(&mut self, name: &str, field_count: u8, bookmark: &mut usize);
+    fn set_struct_field_count(&mut self, bookmark: usize, field_count: u8);
+}
+
+impl EventWriter for EventBuilder {
+    #[inline]
+    fn reset(&mut self, name: &str) {
+        EventBuilder::reset(self, name, 0);
+    }
+
+    #[inline]
+    fn add_bool(&mut self, name: &str, value: bool) {
+        self.add_value(name, value, FieldFormat::Boolean, 0);
+    }
+
+    #[inline]
+    fn add_i16(&mut self, name: &str, value: i16) {
+        self.add_value(name, value, FieldFormat::SignedInt, 0);
+    }
+
+    #[inline]
+    fn add_i64(&mut self, name: &str, value: i64) {
+        self.add_value(name, value, FieldFormat::SignedInt, 0);
+    }
+
+    #[inline]
+    fn add_u32(&mut self, name: &str, value: u32) {
+        self.add_value(name, value, FieldFormat::UnsignedInt, 0);
+    }
+
+    #[inline]
+    fn add_f64(&mut self, name: &str, value: f64) {
+        self.add_value(name, value, FieldFormat::Float, 0);
+    }
+
+    #[inline]
+    fn add_str(&mut self, name: &str, value: &str) {
+        EventBuilder::add_str(self, name, value, FieldFormat::Default, 0);
+    }
+
+    #[inline]
+    fn add_struct(&mut self, name: &str, field_count: u8, bookmark: &mut usize) {
+        self.add_struct_with_bookmark(name, field_count, 0, bookmark);
+    }
+
+    #[inline]
+    fn set_struct_field_count(&mut self, bookmark: usize, field_count: u8) {
+        EventBuilder::set_struct_field_count(self, bookmark, field_count);
+    }
+}
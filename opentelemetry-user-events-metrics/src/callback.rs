@@ -0,0 +1,189 @@
+use opentelemetry::metrics::{AsyncInstrument, Counter, Meter};
+use opentelemetry::{otel_warn, KeyValue};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+type Observations<T> = Vec<(T, Vec<KeyValue>)>;
+type PendingResult<T> = Receiver<thread::Result<Observations<T>>>;
+
+/// Guards observable instrument callbacks, so a slow or panicking callback can't stall the
+/// collection of all the metrics.
+///
+/// A guarded callback computes its observations on a separate thread. If it panics, or doesn't
+/// complete within the timeout, its observations are skipped for this collection, a warning is
+/// logged and the `user_events.metrics.callback.failures` counter is incremented, with the
+/// `callback` and `error.type` attributes. A callback still running from a previous collection
+/// is not started again until it completes.
+///
+/// ```no_run
+/// use opentelemetry::metrics::MeterProvider;
+/// use opentelemetry_sdk::metrics::SdkMeterProvider;
+/// use opentelemetry_user_events_metrics::CallbackGuard;
+/// use std::time::Duration;
+///
+/// # let provider = SdkMeterProvider::default();
+/// let meter = provider.meter("my-app");
+/// let guard = CallbackGuard::new(&meter).with_timeout(Duration::from_millis(200));
+/// let _gauge = meter
+///     .u64_observable_gauge("queue.depth")
+///     .with_callback(guard.guard("queue.depth", || vec![(42u64, vec![])]))
+///     .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct CallbackGuard {
+    timeout: Duration,
+    failures: Counter<u64>,
+}
+
+impl CallbackGuard {
+    /// Create a guard recording its failures with `meter`.
+    pub fn new(meter: &Meter) -> Self {
+        CallbackGuard {
+            timeout: DEFAULT_TIMEOUT,
+            failures: meter
+                .u64_counter("user_events.metrics.callback.failures")
+                .with_description("Number of observable callbacks which panicked or timed out.")
+                .with_unit("{failure}")
+                .build(),
+        }
+    }
+
+    /// Skip the observations of callbacks not completing within `timeout`, 1 second by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Guard the callback `name`, which returns the observations of the instrument.
+    pub fn guard<T, F>(
+        &self,
+        name: &'static str,
+        callback: F,
+    ) -> impl Fn(&dyn AsyncInstrument<T>) + Send + Sync + 'static
+    where
+        T: Send + 'static,
+        F: Fn() -> Observations<T> + Send + Sync + 'static,
+    {
+        let guard = self.clone();
+        let callback = Arc::new(callback);
+        let pending: Mutex<Option<PendingResult<T>>> = Mutex::new(None);
+        move |observer: &dyn AsyncInstrument<T>| {
+            let Ok(mut pending) = pending.lock() else {
+                return;
+            };
+            if let Some(result) = pending.as_ref() {
+                match result.try_recv() {
+                    Err(mpsc::TryRecvError::Empty) => {
+                        guard.report(name, "busy");
+                        return;
+                    }
+                    // The late observations are stale, collect new ones.
+                    _ => *pending = None,
+                }
+            }
+
+            let (tx, rx) = mpsc::channel();
+            let callback = callback.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("otel-callback-{name}"))
+                .spawn(move || {
+                    let _ = tx.send(panic::catch_unwind(AssertUnwindSafe(&*callback)));
+                });
+            if spawned.is_err() {
+                guard.report(name, "spawn");
+                return;
+            }
+
+            match rx.recv_timeout(guard.timeout) {
+                Ok(Ok(observations)) => {
+                    for (value, attributes) in observations {
+                        observer.observe(value, &attributes);
+                    }
+                }
+                Ok(Err(_)) | Err(RecvTimeoutError::Disconnected) => guard.report(name, "panic"),
+                Err(RecvTimeoutError::Timeout) => {
+                    *pending = Some(rx);
+                    guard.report(name, "timeout");
+                }
+            }
+        }
+    }
+
+    fn report(&self, name: &'static str, error_type: &'static str) {
+        otel_warn!(
+            name: "CallbackFailed",
+            callback = name,
+            error_type = error_type
+        );
+        self.failures.add(
+            1,
+            &[
+                KeyValue::new("callback", name),
+                KeyValue::new("error.type", error_type),
+            ],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+
+    #[derive(Default)]
+    struct Observer(Mutex<Vec<u64>>);
+
+    impl AsyncInstrument<u64> for Observer {
+        fn observe(&self, measurement: u64, _attributes: &[KeyValue]) {
+            self.0.lock().unwrap().push(measurement);
+        }
+    }
+
+    fn guard() -> CallbackGuard {
+        CallbackGuard::new(&SdkMeterProvider::default().meter("test"))
+            .with_timeout(Duration::from_millis(100))
+    }
+
+    #[test]
+    fn test_guard_observes() {
+        let callback = guard().guard::<u64, _>("ok", || vec![(1, vec![]), (2, vec![])]);
+        let observer = Observer::default();
+        callback(&observer);
+        assert_eq!(*observer.0.lock().unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn test_guard_panic() {
+        let callback = guard().guard::<u64, _>("panic", || panic!("callback failed"));
+        let observer = Observer::default();
+        callback(&observer);
+        assert!(observer.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_guard_timeout() {
+        let (release, wait) = mpsc::channel::<()>();
+        let wait = Mutex::new(wait);
+        let callback = guard().guard::<u64, _>("slow", move || {
+            let _ = wait.lock().unwrap().recv();
+            vec![(1, vec![])]
+        });
+        let observer = Observer::default();
+        callback(&observer);
+        // Still running, not started again.
+        callback(&observer);
+        assert!(observer.0.lock().unwrap().is_empty());
+
+        release.send(()).unwrap();
+        release.send(()).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        callback(&observer);
+        assert_eq!(*observer.0.lock().unwrap(), [1]);
+    }
+}
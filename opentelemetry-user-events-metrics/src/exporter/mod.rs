@@ -1,4 +1,4 @@
-use opentelemetry::{otel_debug, otel_info};
+use opentelemetry::{otel_debug, otel_info, InstrumentationScope, Key};
 use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
 use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
 use opentelemetry_sdk::metrics::data::AggregatedMetrics;
@@ -7,6 +7,7 @@ use opentelemetry_sdk::metrics::{
     data::{MetricData, ResourceMetrics},
     Temporality,
 };
+use opentelemetry_sdk::Resource;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::tracepoint;
@@ -61,8 +62,30 @@ impl Numeric for f64 {
     }
 }
 
+/// The fields of the OTLP metrics protocol serialized in the events.
+///
+/// Listeners decoding the events with an older version of the OTLP protos than the one of this
+/// crate may not parse the fields added since. [`OtlpCompatibility::Legacy`] leaves out the
+/// optional fields which are not needed to read the data points, so such listeners keep parsing
+/// the events when this crate upgrades its `opentelemetry-proto` dependency.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OtlpCompatibility {
+    /// Serialize every field supported by the `opentelemetry-proto` version of this crate.
+    #[default]
+    Latest,
+    /// Leave out the `min` and `max` of histograms, the `zero_threshold` of exponential
+    /// histograms and the attributes of the instrumentation scope.
+    Legacy,
+}
+
 pub struct MetricsExporter {
     trace_point: Pin<Box<ehi::TracepointState>>,
+    resource_attributes: Option<Vec<Key>>,
+    scope_info: bool,
+    otlp_compatibility: OtlpCompatibility,
+    #[cfg(feature = "self-metrics")]
+    metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics,
 }
 
 impl MetricsExporter {
@@ -73,7 +96,80 @@ impl MetricsExporter {
         unsafe {
             let _result = tracepoint::register(trace_point.as_ref());
         }
-        MetricsExporter { trace_point }
+        MetricsExporter {
+            trace_point,
+            resource_attributes: None,
+            scope_info: true,
+            otlp_compatibility: OtlpCompatibility::default(),
+            #[cfg(feature = "self-metrics")]
+            metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics::new(
+                "user_events",
+                opentelemetry_contrib::exporter_metrics::Signal::Metrics,
+            ),
+        }
+    }
+
+    /// Only serialize the Resource attributes with one of `keys`, instead of all of them.
+    ///
+    /// The Resource is repeated in every event, as each data point is emitted separately, so
+    /// large Resources, e.g. with process or container details, take a part of the 64kB event
+    /// size limit while the listener may already know them.
+    pub fn with_resource_attributes<K: Into<Key>>(
+        mut self,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Self {
+        self.resource_attributes = Some(keys.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Whether the name and version of the instrumentation scope are serialized in every
+    /// event, `true` by default.
+    pub fn with_scope_info(mut self, scope_info: bool) -> Self {
+        self.scope_info = scope_info;
+        self
+    }
+
+    /// Select the fields of the OTLP protocol serialized in the events,
+    /// [`OtlpCompatibility::Latest`] by default.
+    pub fn with_otlp_compatibility(mut self, otlp_compatibility: OtlpCompatibility) -> Self {
+        self.otlp_compatibility = otlp_compatibility;
+        self
+    }
+
+    /// Whether the optional fields added to the OTLP protocol are serialized.
+    fn optional_fields(&self) -> bool {
+        self.otlp_compatibility == OtlpCompatibility::Latest
+    }
+
+    fn resource_proto(
+        &self,
+        resource: &Resource,
+    ) -> opentelemetry_proto::tonic::resource::v1::Resource {
+        let mut resource_proto: opentelemetry_proto::tonic::resource::v1::Resource =
+            resource.into();
+        if let Some(keys) = &self.resource_attributes {
+            resource_proto
+                .attributes
+                .retain(|kv| keys.iter().any(|key| key.as_str() == kv.key));
+        }
+        resource_proto
+    }
+
+    fn scope_proto(
+        &self,
+        scope: &InstrumentationScope,
+    ) -> opentelemetry_proto::tonic::common::v1::InstrumentationScope {
+        let mut scope_proto: opentelemetry_proto::tonic::common::v1::InstrumentationScope =
+            (scope, None).into();
+        if !self.scope_info {
+            scope_proto.name.clear();
+            scope_proto.version.clear();
+        }
+        if !self.optional_fields() {
+            scope_proto.attributes.clear();
+            scope_proto.dropped_attributes_count = 0;
+        }
+        scope_proto
     }
 }
 
@@ -307,8 +403,14 @@ impl MetricsExporter {
                     explicit_bounds: dp.bounds().collect(),
                     exemplars: Vec::new(), // No support for exemplars
                     flags: default_flags,
-                    min: dp.min().map(|v| v.into_f64()),
-                    max: dp.max().map(|v| v.into_f64()),
+                    min: dp
+                        .min()
+                        .filter(|_| self.optional_fields())
+                        .map(|v| v.into_f64()),
+                    max: dp
+                        .max()
+                        .filter(|_| self.optional_fields())
+                        .map(|v| v.into_f64()),
                 };
 
             let histogram_point_proto = opentelemetry_proto::tonic::metrics::v1::Histogram {
@@ -387,9 +489,13 @@ impl MetricsExporter {
                 }),
                 exemplars: Vec::new(), // No support for exemplars
                 flags: default_flags,
-                min: dp.min().map(|v| v.into_f64()),
-                max: dp.max().map(|v| v.into_f64()),
-                zero_threshold: dp.zero_threshold(),
+                min: dp.min().filter(|_| self.optional_fields()).map(|v| v.into_f64()),
+                max: dp.max().filter(|_| self.optional_fields()).map(|v| v.into_f64()),
+                zero_threshold: if self.optional_fields() {
+                    dp.zero_threshold()
+                } else {
+                    0.0
+                },
             };
 
             let histogram_point_proto =
@@ -423,6 +529,25 @@ impl MetricsExporter {
         byte_array: &mut Vec<u8>,
         metric: &opentelemetry_sdk::metrics::data::Metric,
     ) -> Result<(), String> {
+        #[cfg(feature = "self-metrics")]
+        let start = std::time::Instant::now();
+        let result =
+            self.encode_and_write(export_metric_service_request_common, byte_array, metric);
+        #[cfg(feature = "self-metrics")]
+        match result.as_ref() {
+            Ok(_) => self.metrics.record_success(1, start.elapsed()),
+            Err(&(error_type, _)) => self.metrics.record_failure(1, start.elapsed(), error_type),
+        }
+        result.map_err(|(_, error_msg)| error_msg)
+    }
+
+    /// Encode and write one event, returning the `error.type` and message of a failure.
+    fn encode_and_write(
+        &self,
+        export_metric_service_request_common: &ExportMetricsServiceRequest,
+        byte_array: &mut Vec<u8>,
+        metric: &opentelemetry_sdk::metrics::data::Metric,
+    ) -> Result<(), (&'static str, String)> {
         match export_metric_service_request_common.encode(byte_array) {
             Ok(_) => {
                 otel_debug!(name: "SerializationSucceeded", 
@@ -437,7 +562,7 @@ impl MetricsExporter {
                         metric_name = metric.name(),
                         size = byte_array.len()
                     );
-                    Err(error_msg)
+                    Err(("size", error_msg))
                 } else {
                     // Write to the tracepoint
                     let result = tracepoint::write(&self.trace_point, byte_array);
@@ -447,7 +572,7 @@ impl MetricsExporter {
                     } else {
                         let error_msg = "Failed to write to tracepoint".to_string();
                         otel_debug!(name: "TracepointWriteFailed", message = &error_msg, metric_name = metric.name(), result = result);
-                        Err(error_msg)
+                        Err(("write", error_msg))
                     }
                 }
             }
@@ -457,7 +582,7 @@ impl MetricsExporter {
                     error = &error_msg,
                     metric_name = metric.name(),
                     size = byte_array.len());
-                Err(error_msg)
+                Err(("serialization", error_msg))
             }
         }
     }
@@ -505,7 +630,7 @@ impl MetricsExporter {
         let mut has_failures = false;
         let mut export_metric_service_request_common = ExportMetricsServiceRequest {
             resource_metrics: vec![opentelemetry_proto::tonic::metrics::v1::ResourceMetrics {
-                resource: Some((resource_metric.resource()).into()),
+                resource: Some(self.resource_proto(resource_metric.resource())),
                 scope_metrics: vec![],
                 schema_url: resource_metric
                     .resource()
@@ -523,7 +648,7 @@ impl MetricsExporter {
         for scope_metric in resource_metric.scope_metrics() {
             // Create reusable scope_metric_proto template with empty metrics
             let scope_metric_proto = opentelemetry_proto::tonic::metrics::v1::ScopeMetrics {
-                scope: Some((scope_metric.scope(), None).into()),
+                scope: Some(self.scope_proto(scope_metric.scope())),
                 metrics: vec![],
                 schema_url: scope_metric
                     .scope()
@@ -532,6 +657,18 @@ impl MetricsExporter {
                     .to_string(),
             };
 
+            // Every event of the scope repeats the Resource and the scope.
+            otel_debug!(
+                name: "CommonFieldsSize",
+                resource_size = export_metric_service_request_common.resource_metrics[0]
+                    .resource
+                    .as_ref()
+                    .map_or(0, |resource| resource.encoded_len()),
+                scope_size = scope_metric_proto
+                    .scope
+                    .as_ref()
+                    .map_or(0, |scope| scope.encoded_len())
+            );
             export_metric_service_request_common.resource_metrics[0].scope_metrics =
                 vec![scope_metric_proto];
 
@@ -617,3 +754,40 @@ impl PushMetricExporter for MetricsExporter {
         self.shutdown_with_timeout(Duration::from_secs(5))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::KeyValue;
+
+    #[test]
+    fn test_resource_and_scope_controls() {
+        let resource = Resource::builder_empty()
+            .with_attributes([
+                KeyValue::new("service.name", "service"),
+                KeyValue::new("process.command_line", "/usr/bin/service --verbose"),
+            ])
+            .build();
+        let scope = InstrumentationScope::builder("meter")
+            .with_version("1.0")
+            .with_attributes([KeyValue::new("component", "storage")])
+            .build();
+
+        let exporter = MetricsExporter::new();
+        assert_eq!(exporter.resource_proto(&resource).attributes.len(), 2);
+        assert_eq!(exporter.scope_proto(&scope).name, "meter");
+        assert_eq!(exporter.scope_proto(&scope).attributes.len(), 1);
+
+        let exporter = MetricsExporter::new()
+            .with_resource_attributes(["service.name"])
+            .with_scope_info(false)
+            .with_otlp_compatibility(OtlpCompatibility::Legacy);
+        let resource_proto = exporter.resource_proto(&resource);
+        assert_eq!(resource_proto.attributes.len(), 1);
+        assert_eq!(resource_proto.attributes[0].key, "service.name");
+        let scope_proto = exporter.scope_proto(&scope);
+        assert!(scope_proto.name.is_empty());
+        assert!(scope_proto.version.is_empty());
+        assert!(scope_proto.attributes.is_empty());
+    }
+}
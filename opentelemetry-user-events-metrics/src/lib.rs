@@ -1,7 +1,9 @@
+mod callback;
 mod exporter;
 mod tracepoint;
 
-pub use exporter::MetricsExporter;
+pub use callback::CallbackGuard;
+pub use exporter::{MetricsExporter, OtlpCompatibility};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,30 @@
+use opentelemetry::trace::{SpanId, TraceId};
+
+/// The activity ID of the span `span_id` of the trace `trace_id`, set on the span events, with
+/// the activity ID of their parent span as related activity ID.
+///
+/// The ID is the first 8 bytes of the trace ID followed by the 8 bytes of the span ID, the
+/// derivation also used by `opentelemetry_user_events_logs::activity_id`, so tools reading the
+/// events, such as the Geneva Linux agent, can join the span and log events of a trace. Other
+/// emitters should use it for their events to be joined as well.
+pub fn activity_id(trace_id: TraceId, span_id: SpanId) -> [u8; 16] {
+    let mut activity_id = [0; 16];
+    activity_id[..8].copy_from_slice(&trace_id.to_bytes()[..8]);
+    activity_id[8..].copy_from_slice(&span_id.to_bytes());
+    activity_id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activity_id() {
+        let trace_id = TraceId::from_hex("0102030405060708090a0b0c0d0e0f10").unwrap();
+        let span_id = SpanId::from_hex("1112131415161718").unwrap();
+        assert_eq!(
+            activity_id(trace_id, span_id),
+            [1, 2, 3, 4, 5, 6, 7, 8, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::trace::activity::activity_id;
 use chrono::{DateTime, Utc};
 use eventheader::{FieldFormat, Level, Opcode};
 use eventheader_dynamic::{EventBuilder, EventSet, Provider};
@@ -51,6 +52,8 @@ pub(crate) struct UserEventsSpanExporter {
     event_set: Arc<EventSet>,
     cloud_role: Option<String>,
     cloud_role_instance: Option<String>,
+    #[cfg(feature = "self-metrics")]
+    metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics,
 }
 
 impl Debug for UserEventsSpanExporter {
@@ -124,6 +127,11 @@ impl UserEventsSpanExporter {
             event_set,
             cloud_role: None,
             cloud_role_instance: None,
+            #[cfg(feature = "self-metrics")]
+            metrics: opentelemetry_contrib::exporter_metrics::ExporterMetrics::new(
+                "user_events",
+                opentelemetry_contrib::exporter_metrics::Signal::Traces,
+            ),
         })
     }
 
@@ -151,6 +159,8 @@ impl UserEventsSpanExporter {
 
     pub(crate) fn export_span(&self, span: &SpanData) -> OTelSdkResult {
         if self.event_set.enabled() {
+            #[cfg(feature = "self-metrics")]
+            let start = std::time::Instant::now();
             let well_known_attrs = get_well_known_attributes();
             let mut eb = EventBuilder::new();
             eb.reset("Span", 0);
@@ -265,7 +275,20 @@ impl UserEventsSpanExporter {
                 }
             }
 
-            let result = eb.write(&self.event_set, None, None);
+            let span_activity_id =
+                activity_id(span.span_context.trace_id(), span.span_context.span_id());
+            let parent_activity_id = has_parent_id
+                .then(|| activity_id(span.span_context.trace_id(), span.parent_span_id));
+            let result = eb.write(
+                &self.event_set,
+                Some(&span_activity_id),
+                parent_activity_id.as_ref(),
+            );
+            #[cfg(feature = "self-metrics")]
+            match result {
+                0 => self.metrics.record_success(1, start.elapsed()),
+                _ => self.metrics.record_failure(1, start.elapsed(), "write"),
+            }
             if result > 0 {
                 // Specially log the case where there is no listener and size exceeding.
                 if result == 9 {
@@ -3,9 +3,12 @@ use opentelemetry::otel_warn;
 use opentelemetry_sdk::trace::TracerProviderBuilder;
 use reentrant_spanprocessor::ReentrantSpanProcessor;
 
+mod activity;
 mod exporter;
 mod reentrant_spanprocessor;
 
+pub use activity::activity_id;
+
 /// Extension trait for adding a user event span exporter to the tracer provider builder.
 ///
 /// See the [crate-level documentation](crate) for details on resource attribute
@@ -13,17 +13,40 @@ cargo_feature() {
 cargo clippy --workspace --all-targets --all-features -- -Dwarnings
 
 cargo_feature opentelemetry-aws "default"
+cargo_feature opentelemetry-aws "lambda-telemetry"
+cargo_feature opentelemetry-aws "http"
+cargo_feature opentelemetry-aws "step-functions"
 
 cargo_feature opentelemetry-datadog "reqwest-blocking-client,intern-std"
 cargo_feature opentelemetry-datadog "reqwest-client,intern-std"
+cargo_feature opentelemetry-datadog "reqwest-client,intern-std,self-metrics"
+cargo_feature opentelemetry-datadog "reqwest-client,intern-std,gzip,zstd"
+cargo_feature opentelemetry-datadog "reqwest-client,intern-std,sampling-rules"
+cargo_feature opentelemetry-datadog "reqwest-client,intern-std,test-util"
+cargo_feature opentelemetry-datadog "reqwest-client,intern-std,agent-sampling"
 # TODO: Clippy doesn't seem to like surf client.
 #  cargo_feature opentelemetry-datadog "surf-client,intern-std"
 
 cargo_feature opentelemetry-contrib "default"
+cargo_feature opentelemetry-contrib "adaptive_sampler"
 cargo_feature opentelemetry-contrib "api"
+cargo_feature opentelemetry-contrib "attribute_limit_processor"
+cargo_feature opentelemetry-contrib "baggage_span_processor"
 cargo_feature opentelemetry-contrib "base64_format"
 cargo_feature opentelemetry-contrib "binary_propagator"
+cargo_feature opentelemetry-contrib "compression"
+cargo_feature opentelemetry-contrib "compression_gzip,compression_zstd"
+cargo_feature opentelemetry-contrib "event_to_log_processor"
+cargo_feature opentelemetry-contrib "exporter_metrics"
+cargo_feature opentelemetry-contrib "log_sampling_processor"
+cargo_feature opentelemetry-contrib "metrics_temporality"
+cargo_feature opentelemetry-contrib "opencensus_propagator"
+cargo_feature opentelemetry-contrib "thread"
+cargo_feature opentelemetry-contrib "rayon"
+cargo_feature opentelemetry-contrib "runtime_config"
+cargo_feature opentelemetry-contrib "runtime_config_http"
 cargo_feature opentelemetry-contrib "jaeger_json_exporter"
+cargo_feature opentelemetry-contrib "zipkin_json_exporter"
 cargo_feature opentelemetry-contrib "rt-tokio"
 cargo_feature opentelemetry-contrib "rt-tokio-current-thread"
 
@@ -31,9 +54,32 @@ cargo_feature opentelemetry-stackdriver "default"
 cargo_feature opentelemetry-stackdriver "gcp-authorizer"
 cargo_feature opentelemetry-stackdriver "tls-native-roots"
 cargo_feature opentelemetry-stackdriver "tls-webpki-roots"
+cargo_feature opentelemetry-stackdriver "pubsub"
+cargo_feature opentelemetry-stackdriver "self-metrics"
+cargo_feature opentelemetry-stackdriver "logs"
+cargo_feature opentelemetry-stackdriver "test-util"
+
+cargo_feature opentelemetry-journald "default"
+
+cargo_feature opentelemetry-syslog "default"
+cargo_feature opentelemetry-syslog "tls"
 
 cargo_feature opentelemetry-user-events-logs "default"
+cargo_feature opentelemetry-user-events-logs "self-metrics"
+cargo_feature opentelemetry-user-events-logs "test-util"
 
 cargo_feature opentelemetry-user-events-metrics ""
+cargo_feature opentelemetry-user-events-metrics "self-metrics"
+
+cargo_feature opentelemetry-user-events-trace "default"
+cargo_feature opentelemetry-user-events-trace "self-metrics"
 
 cargo_feature opentelemetry-resource-detectors ""
+cargo_feature opentelemetry-resource-detectors "azure"
+cargo_feature opentelemetry-resource-detectors "host-cpu"
+cargo_feature opentelemetry-resource-detectors "cache"
+
+cargo_feature opentelemetry-instrumentation-sqlx ""
+
+cargo_feature opentelemetry-instrumentation-tonic ""
+cargo_feature opentelemetry-instrumentation-tonic "grpc-trace-bin"